@@ -1,26 +1,32 @@
 pub mod error;
 pub mod event;
 pub mod message;
+pub mod plugin;
 pub mod provider;
+pub mod proxy;
 pub mod registry;
 pub mod sampler;
 pub mod statistics;
 pub mod websocket;
 pub mod writer;
 
-pub use error::{DanmakuError, Result};
+pub use error::{DanmakuError, DanmuConnectionError, DanmuErrorCode, Result};
 pub use event::{DanmuControlEvent, DanmuItem};
 pub use message::{DanmuMessage, DanmuType};
+pub use plugin::{DanmuPluginMessage, PLUGIN_ABI_VERSION, ProviderRegistryFfi};
 pub use provider::{ConnectionConfig, DanmuConnection, DanmuProvider};
-pub use registry::ProviderRegistry;
+pub use proxy::{ProxyConfig, ProxyCredentials, ProxyType};
+pub use registry::{ProviderInfo, ProviderRegistry};
 pub use sampler::{
     DanmuSampler, DanmuSamplingConfig, FixedIntervalSampler, VelocitySampler, create_sampler,
 };
 pub use statistics::{
-    DanmuStatistics, RateDataPoint, StatisticsAggregator, TopTalker, WordFrequency,
+    BatchMessage, DanmuStatistics, GiftSummary, LanguageCount, MAX_GIFT_KINDS, MAX_LANGUAGES,
+    MAX_USERNAME_ALIASES, RateDataPoint, RollingWindowStats, StatisticsAggregator, SuperChatEntry,
+    TopGifter, TopTalker, WordFrequency,
 };
 pub use websocket::{DanmuProtocol, WebSocketDanmuProvider};
-pub use writer::{XmlDanmuWriter, escape_xml, message_type_to_int};
+pub use writer::{AttributeStyle, DanmuXmlFormat, XmlDanmuWriter, escape_xml, message_type_to_int};
 
 pub use crate::extractor::platforms::huya::danmu::HuyaDanmuProvider;
 pub use crate::extractor::platforms::twitch::danmu::TwitchDanmuProvider;
@@ -32,6 +32,10 @@ pub enum DanmakuError {
     #[error("Protobuf encode error: {0}")]
     ProtobufEncode(#[from] prost::EncodeError),
 
+    /// Connection failure classified by the provider
+    #[error(transparent)]
+    ConnectionFailed(#[from] DanmuConnectionError),
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -52,4 +56,154 @@ impl DanmakuError {
     pub fn other(msg: impl Into<String>) -> Self {
         Self::Other(msg.into())
     }
+
+    /// Create a classified connection error.
+    pub fn connection_failed(code: DanmuErrorCode, msg: impl Into<String>) -> Self {
+        Self::ConnectionFailed(DanmuConnectionError::new(code, msg))
+    }
+
+    /// Classify this error as a connection failure.
+    ///
+    /// Errors not classified by a provider count as network errors.
+    pub fn into_connection_error(self) -> DanmuConnectionError {
+        match self {
+            Self::ConnectionFailed(error) => error,
+            other => DanmuConnectionError::new(DanmuErrorCode::NetworkError, other.to_string()),
+        }
+    }
+}
+
+/// Why a danmu connection could not be established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DanmuErrorCode {
+    /// No provider supports the streamer URL.
+    ProviderNotFound,
+    /// The room does not exist or its ID could not be determined.
+    RoomNotFound,
+    /// The platform rejected the credentials.
+    AuthFailed,
+    /// The connection was not established in time.
+    Timeout,
+    /// The platform asked to slow down.
+    RateLimited,
+    /// Any other transport or platform failure.
+    NetworkError,
+}
+
+impl DanmuErrorCode {
+    /// Whether connecting again with the same parameters can succeed.
+    pub fn is_retryable(self) -> bool {
+        !matches!(
+            self,
+            Self::ProviderNotFound | Self::RoomNotFound | Self::AuthFailed
+        )
+    }
+
+    /// Code for a failed HTTP request with the given status.
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::AuthFailed,
+            404 | 410 => Self::RoomNotFound,
+            408 | 504 => Self::Timeout,
+            429 => Self::RateLimited,
+            _ => Self::NetworkError,
+        }
+    }
+}
+
+impl std::fmt::Display for DanmuErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ProviderNotFound => "provider_not_found",
+            Self::RoomNotFound => "room_not_found",
+            Self::AuthFailed => "auth_failed",
+            Self::Timeout => "timeout",
+            Self::RateLimited => "rate_limited",
+            Self::NetworkError => "network_error",
+        })
+    }
+}
+
+/// A danmu connection failure with a code callers can act on.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Connection error ({code}): {message}")]
+pub struct DanmuConnectionError {
+    pub code: DanmuErrorCode,
+    pub message: String,
+    /// Seconds the platform asked to wait before retrying, for `RateLimited`.
+    pub retry_after_secs: Option<u64>,
+}
+
+impl DanmuConnectionError {
+    /// Create an error without a retry delay.
+    pub fn new(code: DanmuErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retry_after_secs: None,
+        }
+    }
+
+    /// Set the delay the platform asked for before retrying.
+    pub fn with_retry_after(mut self, secs: Option<u64>) -> Self {
+        self.retry_after_secs = secs;
+        self
+    }
+
+    /// Classify an unsuccessful HTTP response, reading its `Retry-After` header.
+    pub fn from_response(response: &reqwest::Response, message: impl Into<String>) -> Self {
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        Self::new(
+            DanmuErrorCode::from_http_status(response.status().as_u16()),
+            message,
+        )
+        .with_retry_after(retry_after_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_status_maps_to_codes() {
+        assert_eq!(
+            DanmuErrorCode::from_http_status(403),
+            DanmuErrorCode::AuthFailed
+        );
+        assert_eq!(
+            DanmuErrorCode::from_http_status(404),
+            DanmuErrorCode::RoomNotFound
+        );
+        assert_eq!(
+            DanmuErrorCode::from_http_status(429),
+            DanmuErrorCode::RateLimited
+        );
+        assert_eq!(
+            DanmuErrorCode::from_http_status(502),
+            DanmuErrorCode::NetworkError
+        );
+        assert!(!DanmuErrorCode::RoomNotFound.is_retryable());
+        assert!(DanmuErrorCode::RateLimited.is_retryable());
+    }
+
+    #[test]
+    fn unclassified_errors_are_network_errors() {
+        let error = DanmakuError::connection("reset by peer").into_connection_error();
+        assert_eq!(error.code, DanmuErrorCode::NetworkError);
+        assert!(error.message.contains("reset by peer"));
+
+        let error = DanmakuError::connection_failed(DanmuErrorCode::AuthFailed, "bad cookie")
+            .into_connection_error();
+        assert_eq!(error.code, DanmuErrorCode::AuthFailed);
+        assert_eq!(
+            error.to_string(),
+            "Connection error (auth_failed): bad cookie"
+        );
+    }
 }
@@ -145,6 +145,12 @@ impl DanmuMessage {
         self
     }
 
+    /// Set the total value of a gift message in the platform's coin unit,
+    /// used for gift value statistics.
+    pub fn with_coin_value(self, coin_value: u64) -> Self {
+        self.with_metadata("coin_value", serde_json::json!(coin_value))
+    }
+
     /// Set the timestamp.
     pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
         self.timestamp = timestamp;
@@ -177,6 +183,9 @@ mod tests {
         let metadata = msg.metadata.as_ref().unwrap();
         assert_eq!(metadata.get("gift_name").unwrap(), "Rocket");
         assert_eq!(metadata.get("gift_count").unwrap(), 5);
+
+        let msg = msg.with_coin_value(500);
+        assert_eq!(msg.metadata.unwrap().get("coin_value").unwrap(), 500);
     }
 
     #[test]
@@ -0,0 +1,612 @@
+//! Danmu providers loaded from shared libraries.
+//!
+//! A plugin is a `.so`/`.dylib`/`.dll` exporting
+//!
+//! ```c
+//! uint32_t danmu_plugin_abi_version(void);
+//! void register_providers(ProviderRegistryFfi *registry);
+//! ```
+//!
+//! [`ProviderRegistry::load_plugin`](super::ProviderRegistry::load_plugin)
+//! first calls `danmu_plugin_abi_version` and rejects the plugin unless it
+//! returns [`PLUGIN_ABI_VERSION`], so a plugin built against another layout of
+//! the registration table never writes to it. It then passes a zeroed
+//! [`ProviderRegistryFfi`], which the plugin fills in with an opaque context
+//! pointer and its callbacks. The provider is rejected unless every callback
+//! is set.
+//!
+//! # Contract
+//!
+//! - Every callback receives the plugin's `context` first. Callbacks may be
+//!   called from several threads at once, and the context must stay valid for
+//!   as long as the library is loaded; the library is never unloaded while a
+//!   provider created from it is alive or a callback is running.
+//! - Calls for one connection are never made concurrently, and none follows
+//!   its `disconnect`.
+//! - Strings passed to the plugin are NUL-terminated UTF-8 and only valid for
+//!   the duration of the call.
+//! - `get_platform_name` returns a NUL-terminated name valid for as long as the
+//!   library is loaded. It is read once, right after registration.
+//! - `extract_room_id` writes the room ID of `url`, without a terminating NUL,
+//!   to `out` and returns its length; it returns a negative value when the URL
+//!   is not a room of the platform, or the room ID does not fit in `out_len`
+//!   bytes.
+//! - `connect` returns a non-zero handle for a connection to `room_id`, or `0`
+//!   when the connection failed. `cookies` is null without cookies.
+//! - `receive` waits for the next message of `connection` and returns `1` after
+//!   filling in `message`, `0` when no message arrived yet (it is called again
+//!   shortly), `-1` when the connection was closed and any other negative value
+//!   on failure. A closed connection is reported as a connection error, so the
+//!   collection reconnects. It should not block for more than about a second. The string
+//!   pointers of `message` must stay valid until the next call for the same
+//!   connection.
+//! - `disconnect` closes `connection`; the handle is not used afterwards.
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use parking_lot::Mutex;
+
+use crate::danmaku::error::{DanmakuError, DanmuErrorCode, Result};
+use crate::danmaku::event::DanmuItem;
+use crate::danmaku::message::DanmuMessage;
+use crate::danmaku::provider::{ConnectionConfig, DanmuConnection, DanmuProvider};
+
+/// ABI version a plugin's `danmu_plugin_abi_version` must return: `"SRC"`
+/// followed by the revision of this contract.
+pub const PLUGIN_ABI_VERSION: u32 = 0x5352_4302;
+
+/// Name of the ABI version function a plugin exports.
+pub const PLUGIN_ABI_VERSION_SYMBOL: &str = "danmu_plugin_abi_version";
+
+/// Name of the registration function a plugin exports.
+pub const PLUGIN_REGISTER_SYMBOL: &str = "register_providers";
+
+/// Longest room ID a plugin can return from `extract_room_id`.
+const MAX_ROOM_ID_LEN: usize = 256;
+
+/// Delay before asking a plugin again after it had no message.
+const IDLE_RECEIVE_DELAY: Duration = Duration::from_millis(50);
+
+/// Registration table a plugin fills in from `register_providers`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ProviderRegistryFfi {
+    /// Opaque plugin state passed back to every callback.
+    pub context: *mut c_void,
+    /// Returns the platform name.
+    pub get_platform_name: Option<unsafe extern "C" fn(context: *mut c_void) -> *const c_char>,
+    /// Writes the room ID of `url` to `out` and returns its length.
+    pub extract_room_id: Option<
+        unsafe extern "C" fn(
+            context: *mut c_void,
+            url: *const c_char,
+            out: *mut c_char,
+            out_len: usize,
+        ) -> isize,
+    >,
+    /// Connects to a room and returns a non-zero connection handle.
+    pub connect: Option<
+        unsafe extern "C" fn(
+            context: *mut c_void,
+            room_id: *const c_char,
+            cookies: *const c_char,
+        ) -> u64,
+    >,
+    /// Waits for the next message of a connection.
+    pub receive: Option<
+        unsafe extern "C" fn(
+            context: *mut c_void,
+            connection: u64,
+            message: *mut DanmuPluginMessage,
+        ) -> i32,
+    >,
+    /// Closes a connection.
+    pub disconnect: Option<unsafe extern "C" fn(context: *mut c_void, connection: u64)>,
+}
+
+impl Default for ProviderRegistryFfi {
+    fn default() -> Self {
+        Self {
+            context: std::ptr::null_mut(),
+            get_platform_name: None,
+            extract_room_id: None,
+            connect: None,
+            receive: None,
+            disconnect: None,
+        }
+    }
+}
+
+/// A chat message returned by a plugin's `receive`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DanmuPluginMessage {
+    /// Message ID; null to have one generated.
+    pub id: *const c_char,
+    pub user_id: *const c_char,
+    pub username: *const c_char,
+    pub content: *const c_char,
+    /// Unix timestamp in milliseconds; `0` uses the time it was received.
+    pub timestamp_ms: i64,
+}
+
+impl Default for DanmuPluginMessage {
+    fn default() -> Self {
+        Self {
+            id: std::ptr::null(),
+            user_id: std::ptr::null(),
+            username: std::ptr::null(),
+            content: std::ptr::null(),
+            timestamp_ms: 0,
+        }
+    }
+}
+
+type ExtractRoomIdFn =
+    unsafe extern "C" fn(*mut c_void, *const c_char, *mut c_char, usize) -> isize;
+type ConnectFn = unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char) -> u64;
+type ReceiveFn = unsafe extern "C" fn(*mut c_void, u64, *mut DanmuPluginMessage) -> i32;
+type DisconnectFn = unsafe extern "C" fn(*mut c_void, u64);
+
+/// The callbacks of a registered plugin.
+#[derive(Clone, Copy)]
+struct PluginVtable {
+    context: *mut c_void,
+    extract_room_id: ExtractRoomIdFn,
+    connect: ConnectFn,
+    receive: ReceiveFn,
+    disconnect: DisconnectFn,
+}
+
+// SAFETY: the plugin contract requires the context and callbacks to be usable
+// from any thread for as long as the library is loaded.
+unsafe impl Send for PluginVtable {}
+// SAFETY: see the `Send` impl; callbacks may be called concurrently.
+unsafe impl Sync for PluginVtable {}
+
+// The wrappers take `self` so closures capture the whole (`Send`) table rather
+// than its raw context pointer.
+impl PluginVtable {
+    /// # Safety
+    ///
+    /// `room_id` must be NUL-terminated and `cookies` null or NUL-terminated.
+    unsafe fn connect(self, room_id: *const c_char, cookies: *const c_char) -> u64 {
+        // SAFETY: guaranteed by the caller.
+        unsafe { (self.connect)(self.context, room_id, cookies) }
+    }
+
+    /// # Safety
+    ///
+    /// `connection` must be open and `message` valid for writes.
+    unsafe fn receive(self, connection: u64, message: *mut DanmuPluginMessage) -> i32 {
+        // SAFETY: guaranteed by the caller.
+        unsafe { (self.receive)(self.context, connection, message) }
+    }
+
+    /// # Safety
+    ///
+    /// `connection` must be open; it is not used afterwards.
+    unsafe fn disconnect(self, connection: u64) {
+        // SAFETY: guaranteed by the caller.
+        unsafe { (self.disconnect)(self.context, connection) }
+    }
+}
+
+/// An open plugin connection.
+struct PluginConnection {
+    handle: u64,
+    /// Whether `disconnect` was called. Locked for every call into the plugin
+    /// for this connection, so `disconnect` waits for a `receive` that is still
+    /// running on a blocking thread after its caller went away.
+    closed: Mutex<bool>,
+}
+
+/// Result of one `receive` call into a plugin.
+enum ReceiveStatus {
+    Message(DanmuMessage),
+    Idle,
+    Closed,
+    Failed(i32),
+}
+
+/// A danmu provider backed by a plugin library.
+pub(crate) struct PluginProvider {
+    platform: String,
+    vtable: PluginVtable,
+    /// Open connections by connection ID.
+    connections: Mutex<HashMap<String, Arc<PluginConnection>>>,
+    /// Keeps the library loaded while the provider is alive; every blocking
+    /// call into the plugin holds a clone until it returns.
+    library: Arc<libloading::Library>,
+}
+
+/// Load the plugin at `path` and build the provider it registers.
+pub(crate) fn load(path: &Path) -> Result<PluginProvider> {
+    let plugin_error =
+        |msg: String| DanmakuError::other(format!("Plugin {}: {}", path.display(), msg));
+
+    // SAFETY: loading a library runs its initializers; plugins are trusted
+    // code chosen by the operator.
+    let library = unsafe { libloading::Library::new(path) }
+        .map_err(|e| plugin_error(format!("failed to load: {e}")))?;
+    {
+        // SAFETY: the contract fixes the signature of the exported symbol.
+        let abi_version = unsafe {
+            library.get::<unsafe extern "C" fn() -> u32>(PLUGIN_ABI_VERSION_SYMBOL.as_bytes())
+        }
+        .map_err(|e| plugin_error(format!("missing `{PLUGIN_ABI_VERSION_SYMBOL}`: {e}")))?;
+        // SAFETY: the function takes no arguments and only returns a value.
+        let abi_version = unsafe { abi_version() };
+        if abi_version != PLUGIN_ABI_VERSION {
+            return Err(plugin_error(format!(
+                "unsupported ABI version {:#x}, expected {:#x}",
+                abi_version, PLUGIN_ABI_VERSION
+            )));
+        }
+    }
+    let mut table = ProviderRegistryFfi::default();
+    {
+        // SAFETY: the contract fixes the signature of the exported symbol.
+        let register = unsafe {
+            library.get::<unsafe extern "C" fn(*mut ProviderRegistryFfi)>(
+                PLUGIN_REGISTER_SYMBOL.as_bytes(),
+            )
+        }
+        .map_err(|e| plugin_error(format!("missing `{PLUGIN_REGISTER_SYMBOL}`: {e}")))?;
+        // SAFETY: `table` is a valid, exclusively borrowed registration table.
+        unsafe { register(&mut table) };
+    }
+
+    let missing = |name: &str| plugin_error(format!("callback `{name}` is not set"));
+    let get_platform_name = table
+        .get_platform_name
+        .ok_or_else(|| missing("get_platform_name"))?;
+    let vtable = PluginVtable {
+        context: table.context,
+        extract_room_id: table
+            .extract_room_id
+            .ok_or_else(|| missing("extract_room_id"))?,
+        connect: table.connect.ok_or_else(|| missing("connect"))?,
+        receive: table.receive.ok_or_else(|| missing("receive"))?,
+        disconnect: table.disconnect.ok_or_else(|| missing("disconnect"))?,
+    };
+
+    // SAFETY: the contract requires a NUL-terminated name valid while the
+    // library is loaded; it is copied right away.
+    let platform = unsafe {
+        let name = get_platform_name(vtable.context);
+        if name.is_null() {
+            return Err(plugin_error("platform name is null".to_string()));
+        }
+        CStr::from_ptr(name).to_string_lossy().into_owned()
+    };
+    if platform.trim().is_empty() {
+        return Err(plugin_error("platform name is empty".to_string()));
+    }
+
+    Ok(PluginProvider {
+        platform,
+        vtable,
+        connections: Mutex::new(HashMap::new()),
+        library: Arc::new(library),
+    })
+}
+
+/// Copy a plugin string, `None` for null pointers.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn plugin_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    // SAFETY: guaranteed by the caller.
+    Some(
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+fn c_string(value: &str) -> Result<CString> {
+    CString::new(value).map_err(|_| DanmakuError::other(format!("string contains NUL: {value:?}")))
+}
+
+/// Copy a message returned by a plugin's `receive`.
+///
+/// # Safety
+///
+/// The string pointers of `message` must be null or point to NUL-terminated
+/// strings.
+unsafe fn read_message(message: &DanmuPluginMessage) -> DanmuMessage {
+    // SAFETY: guaranteed by the caller.
+    let (id, user_id, username, content) = unsafe {
+        (
+            plugin_string(message.id),
+            plugin_string(message.user_id),
+            plugin_string(message.username),
+            plugin_string(message.content),
+        )
+    };
+    let mut danmu = DanmuMessage::chat(
+        id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        user_id.unwrap_or_default(),
+        username.unwrap_or_default(),
+        content.unwrap_or_default(),
+    );
+    if message.timestamp_ms != 0
+        && let Some(timestamp) = DateTime::from_timestamp_millis(message.timestamp_ms)
+    {
+        danmu = danmu.with_timestamp(timestamp);
+    }
+    danmu
+}
+
+impl PluginProvider {
+    fn connection(&self, connection: &DanmuConnection) -> Result<Arc<PluginConnection>> {
+        self.connections
+            .lock()
+            .get(&connection.id)
+            .cloned()
+            .ok_or_else(|| {
+                DanmakuError::connection(format!("Unknown plugin connection {}", connection.id))
+            })
+    }
+}
+
+#[async_trait]
+impl DanmuProvider for PluginProvider {
+    fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    async fn connect(&self, room_id: &str, config: ConnectionConfig) -> Result<DanmuConnection> {
+        let room = c_string(room_id)?;
+        let cookies = config.cookies.as_deref().map(c_string).transpose()?;
+        let vtable = self.vtable;
+        let library = self.library.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            let _library = library;
+            let cookies = cookies.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
+            // SAFETY: both strings outlive the call, and `cookies` may be null.
+            unsafe { vtable.connect(room.as_ptr(), cookies) }
+        })
+        .await
+        .map_err(|e| DanmakuError::connection(format!("Plugin connect task failed: {e}")))?;
+        if handle == 0 {
+            return Err(DanmakuError::connection_failed(
+                DanmuErrorCode::NetworkError,
+                format!(
+                    "{} plugin failed to connect to room {}",
+                    self.platform, room_id
+                ),
+            ));
+        }
+
+        let mut connection = DanmuConnection::new(
+            format!("{}-plugin-{}", self.platform, handle),
+            self.platform.clone(),
+            room_id,
+        );
+        connection.set_connected();
+        self.connections.lock().insert(
+            connection.id.clone(),
+            Arc::new(PluginConnection {
+                handle,
+                closed: Mutex::new(false),
+            }),
+        );
+        Ok(connection)
+    }
+
+    async fn disconnect(&self, connection: &mut DanmuConnection) -> Result<()> {
+        let plugin_connection = self.connections.lock().remove(&connection.id);
+        if let Some(plugin_connection) = plugin_connection {
+            let vtable = self.vtable;
+            let library = self.library.clone();
+            tokio::task::spawn_blocking(move || {
+                let _library = library;
+                let mut closed = plugin_connection.closed.lock();
+                if !*closed {
+                    *closed = true;
+                    // SAFETY: the handle came from `connect`, and `closed` is
+                    // set under the lock every call takes, so it is not used
+                    // afterwards.
+                    unsafe { vtable.disconnect(plugin_connection.handle) };
+                }
+            })
+            .await
+            .map_err(|e| DanmakuError::connection(format!("Plugin disconnect task failed: {e}")))?;
+        }
+        connection.set_disconnected();
+        Ok(())
+    }
+
+    async fn receive(&self, connection: &DanmuConnection) -> Result<Option<DanmuItem>> {
+        let plugin_connection = self.connection(connection)?;
+        loop {
+            let vtable = self.vtable;
+            let library = self.library.clone();
+            let plugin_connection = plugin_connection.clone();
+            let status = tokio::task::spawn_blocking(move || {
+                let _library = library;
+                let closed = plugin_connection.closed.lock();
+                if *closed {
+                    return ReceiveStatus::Closed;
+                }
+                let mut message = DanmuPluginMessage::default();
+                // SAFETY: the connection is open while `closed` is locked and
+                // unset, and `message` is valid for writes.
+                match unsafe { vtable.receive(plugin_connection.handle, &mut message) } {
+                    // SAFETY: the contract keeps the message strings valid
+                    // until the next call for the connection, which waits
+                    // for the lock held here.
+                    1 => ReceiveStatus::Message(unsafe { read_message(&message) }),
+                    0 => ReceiveStatus::Idle,
+                    -1 => ReceiveStatus::Closed,
+                    code => ReceiveStatus::Failed(code),
+                }
+            })
+            .await
+            .map_err(|e| DanmakuError::connection(format!("Plugin receive task failed: {e}")))?;
+            match status {
+                ReceiveStatus::Message(message) => return Ok(Some(DanmuItem::Message(message))),
+                ReceiveStatus::Idle => tokio::time::sleep(IDLE_RECEIVE_DELAY).await,
+                ReceiveStatus::Closed => {
+                    return Err(DanmakuError::connection(format!(
+                        "{} plugin connection was closed",
+                        self.platform
+                    )));
+                }
+                ReceiveStatus::Failed(code) => {
+                    return Err(DanmakuError::connection(format!(
+                        "{} plugin receive failed with code {}",
+                        self.platform, code
+                    )));
+                }
+            }
+        }
+    }
+
+    fn supports_url(&self, url: &str) -> bool {
+        self.extract_room_id(url).is_some()
+    }
+
+    fn extract_room_id(&self, url: &str) -> Option<String> {
+        let url = CString::new(url).ok()?;
+        let mut out = [0u8; MAX_ROOM_ID_LEN];
+        // SAFETY: `url` is NUL-terminated and `out` is valid for `out.len()`
+        // bytes of writes.
+        let len = unsafe {
+            (self.vtable.extract_room_id)(
+                self.vtable.context,
+                url.as_ptr(),
+                out.as_mut_ptr().cast(),
+                out.len(),
+            )
+        };
+        let len = usize::try_from(len).ok().filter(|len| *len <= out.len())?;
+        let room_id = String::from_utf8_lossy(&out[..len]).into_owned();
+        (!room_id.is_empty()).then_some(room_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmaku::ProviderRegistry;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// Build the fixture plugin in `tests/fixtures`, with `cfg` set when given.
+    fn build_fixture_plugin(name: &str, cfg: Option<&str>) -> PathBuf {
+        let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/danmu_plugin.rs");
+        let dir = std::env::temp_dir().join(format!("danmu-plugin-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join(format!(
+            "{}{}{}",
+            std::env::consts::DLL_PREFIX,
+            name,
+            std::env::consts::DLL_SUFFIX
+        ));
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let mut command = Command::new(rustc);
+        command
+            .args(["--edition", "2021", "--crate-type", "cdylib", "-o"])
+            .arg(&output)
+            .arg(&source);
+        if let Some(cfg) = cfg {
+            command.args(["--cfg", cfg, "--check-cfg", &format!("cfg({cfg})")]);
+        }
+        let status = command.status().expect("rustc runs");
+        assert!(status.success(), "fixture plugin builds");
+        output
+    }
+
+    #[tokio::test]
+    async fn test_load_plugin_registers_provider() {
+        let path = build_fixture_plugin("fake_danmu", None);
+        let mut registry = ProviderRegistry::new();
+        registry.load_plugin(&path).unwrap();
+
+        assert_eq!(registry.platforms(), ["fake"]);
+        let provider = registry.get_by_url("fake://room42").unwrap();
+        assert_eq!(provider.platform(), "fake");
+        assert_eq!(
+            provider.extract_room_id("fake://room42").as_deref(),
+            Some("room42")
+        );
+        assert!(registry.get_by_url("https://example.com/room42").is_none());
+
+        let mut connection = provider
+            .connect("room42", ConnectionConfig::default())
+            .await
+            .unwrap();
+        assert!(connection.is_connected);
+        let Some(DanmuItem::Message(message)) = provider.receive(&connection).await.unwrap() else {
+            panic!("plugin delivers a message");
+        };
+        assert_eq!(message.user_id, "u1");
+        assert_eq!(message.username, "plugin user");
+        assert_eq!(message.content, "hello from room42");
+        provider.disconnect(&mut connection).await.unwrap();
+        assert!(!connection.is_connected);
+    }
+
+    #[tokio::test]
+    async fn test_closed_plugin_connection_is_a_connection_error() {
+        let path = build_fixture_plugin("fake_danmu_closed", None);
+        let mut registry = ProviderRegistry::new();
+        registry.load_plugin(&path).unwrap();
+        let provider = registry.get_by_platform("fake").unwrap();
+
+        let mut connection = provider
+            .connect("room7", ConnectionConfig::default())
+            .await
+            .unwrap();
+        provider.receive(&connection).await.unwrap().unwrap();
+        // The fixture closes the connection after its only message.
+        let error = provider.receive(&connection).await.unwrap_err();
+        assert!(matches!(error, DanmakuError::Connection(_)));
+        assert!(error.to_string().contains("closed"));
+        provider.disconnect(&mut connection).await.unwrap();
+    }
+
+    #[test]
+    fn test_load_plugin_rejects_wrong_abi_version() {
+        let path = build_fixture_plugin("fake_danmu_wrong_abi", Some("wrong_abi"));
+        let mut registry = ProviderRegistry::new();
+
+        let error = registry.load_plugin(&path).unwrap_err();
+        assert!(error.to_string().contains("unsupported ABI version"));
+        assert!(registry.platforms().is_empty());
+    }
+
+    #[test]
+    fn test_load_plugin_rejects_registered_platform() {
+        let path = build_fixture_plugin("fake_danmu_twice", None);
+        let mut registry = ProviderRegistry::new();
+        registry.load_plugin(&path).unwrap();
+
+        let error = registry.load_plugin(&path).unwrap_err();
+        assert!(error.to_string().contains("already registered"));
+        assert_eq!(registry.platforms(), ["fake"]);
+    }
+
+    #[test]
+    fn test_load_plugin_missing_library() {
+        let mut registry = ProviderRegistry::new();
+        assert!(
+            registry
+                .load_plugin(Path::new("/nonexistent/libdanmu_plugin.so"))
+                .is_err()
+        );
+    }
+}
@@ -8,6 +8,7 @@ use std::collections::HashMap;
 
 use crate::danmaku::error::Result;
 use crate::danmaku::event::DanmuItem;
+use crate::danmaku::proxy::ProxyConfig;
 use crate::danmaku::websocket::WebSocketProviderConfig;
 
 /// Connection handle for an active danmu stream.
@@ -70,6 +71,11 @@ pub struct ConnectionConfig {
     pub websocket: Option<WebSocketProviderConfig>,
     /// Platform-specific extras (e.g., presenter_uid for huya, id_str for douyin)
     pub extras: Option<HashMap<String, String>>,
+    /// Proxy that WebSocket connections are tunnelled through
+    pub proxy: Option<ProxyConfig>,
+    /// Additional headers for the WebSocket upgrade request, replacing protocol headers
+    /// with the same name
+    pub extra_headers: Option<HashMap<String, String>>,
 }
 
 impl ConnectionConfig {
@@ -79,6 +85,8 @@ impl ConnectionConfig {
             cookies,
             websocket: None,
             extras: None,
+            proxy: None,
+            extra_headers: None,
         }
     }
 
@@ -93,6 +101,18 @@ impl ConnectionConfig {
         self.extras = Some(extras);
         self
     }
+
+    /// Set the proxy for WebSocket connections.
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Set extra WebSocket upgrade headers.
+    pub fn with_extra_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.extra_headers = Some(headers);
+        self
+    }
 }
 
 /// Trait for platform-specific danmu providers.
@@ -114,8 +134,31 @@ pub trait DanmuProvider: Send + Sync {
     /// Check if the provider supports the given URL.
     fn supports_url(&self, url: &str) -> bool;
 
+    /// Patterns of the URLs accepted by [`supports_url`](Self::supports_url),
+    /// for display; empty when the provider does not describe them.
+    fn url_patterns(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether connections need cookies of a logged-in account.
+    fn requires_cookies(&self) -> bool {
+        false
+    }
+
     /// Extract room ID from a streamer URL.
     fn extract_room_id(&self, url: &str) -> Option<String>;
+
+    /// Whether `room_id` looks like a room ID of this platform, for room IDs
+    /// supplied by the caller instead of extracted from a URL.
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        !room_id.trim().is_empty()
+    }
+}
+
+/// Whether `room_id` is a non-empty string of ASCII digits, the room ID format
+/// of platforms with numeric rooms.
+pub(crate) fn is_numeric_room_id(room_id: &str) -> bool {
+    !room_id.is_empty() && room_id.bytes().all(|b| b.is_ascii_digit())
 }
 
 #[cfg(test)]
@@ -138,4 +181,12 @@ mod tests {
         conn.increment_reconnect();
         assert_eq!(conn.reconnect_count, 1);
     }
+
+    #[test]
+    fn test_is_numeric_room_id() {
+        assert!(is_numeric_room_id("12345"));
+        assert!(!is_numeric_room_id(""));
+        assert!(!is_numeric_room_id("12a45"));
+        assert!(!is_numeric_room_id(" 12345"));
+    }
 }
@@ -0,0 +1,549 @@
+//! Proxy support for danmu WebSocket connections.
+//!
+//! Connections are tunnelled through a SOCKS5 proxy (RFC 1928, with RFC 1929
+//! username/password authentication) or an HTTP proxy using `CONNECT`.
+//! Target hostnames are resolved by the proxy, so deployments that cannot
+//! resolve streaming platforms locally still work.
+
+use std::net::IpAddr;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::danmaku::error::{DanmakuError, Result};
+
+/// Maximum size of an HTTP `CONNECT` response head.
+const MAX_CONNECT_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// Proxy protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyType {
+    Socks5,
+    Http,
+}
+
+/// Credentials for proxy authentication.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for ProxyCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyCredentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Proxy used for danmu connections.
+///
+/// The host is validated on construction and when deserializing, so an invalid
+/// proxy is rejected when the configuration is loaded rather than on connect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawProxyConfig")]
+pub struct ProxyConfig {
+    pub proxy_type: ProxyType,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub credentials: Option<ProxyCredentials>,
+}
+
+#[derive(Deserialize)]
+struct RawProxyConfig {
+    proxy_type: ProxyType,
+    host: String,
+    port: u16,
+    #[serde(default)]
+    credentials: Option<ProxyCredentials>,
+}
+
+impl TryFrom<RawProxyConfig> for ProxyConfig {
+    type Error = DanmakuError;
+
+    fn try_from(raw: RawProxyConfig) -> Result<Self> {
+        let config = Self::new(raw.proxy_type, raw.host, raw.port)?;
+        Ok(match raw.credentials {
+            Some(credentials) => config.with_credentials(credentials),
+            None => config,
+        })
+    }
+}
+
+impl ProxyConfig {
+    /// Create a proxy config, validating the host and port.
+    pub fn new(proxy_type: ProxyType, host: impl Into<String>, port: u16) -> Result<Self> {
+        let host = host.into();
+        validate_proxy_host(&host)?;
+        if port == 0 {
+            return Err(DanmakuError::other("Proxy port must not be 0"));
+        }
+        Ok(Self {
+            proxy_type,
+            host,
+            port,
+            credentials: None,
+        })
+    }
+
+    /// Set authentication credentials.
+    pub fn with_credentials(mut self, credentials: ProxyCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Open a TCP stream to `target_host:target_port` tunnelled through this proxy.
+    pub async fn connect(&self, target_host: &str, target_port: u16) -> Result<TcpStream> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| {
+                DanmakuError::connection(format!(
+                    "Failed to connect to proxy {}:{}: {}",
+                    self.host, self.port, e
+                ))
+            })?;
+        stream.set_nodelay(true)?;
+
+        match self.proxy_type {
+            ProxyType::Socks5 => {
+                socks5_handshake(
+                    &mut stream,
+                    target_host,
+                    target_port,
+                    self.credentials.as_ref(),
+                )
+                .await?
+            }
+            ProxyType::Http => {
+                http_connect(
+                    &mut stream,
+                    target_host,
+                    target_port,
+                    self.credentials.as_ref(),
+                )
+                .await?
+            }
+        }
+        Ok(stream)
+    }
+}
+
+/// Check that `host` is an IP address or a valid DNS hostname.
+fn validate_proxy_host(host: &str) -> Result<()> {
+    let invalid =
+        |reason: &str| DanmakuError::other(format!("Invalid proxy host {host:?}: {reason}"));
+
+    // Accept bracketed IPv6 literals as written in URLs.
+    if strip_brackets(host).parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    if host.is_empty() {
+        return Err(invalid("empty"));
+    }
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.len() > 253 {
+        return Err(invalid("longer than 253 characters"));
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid("labels must be 1-63 characters"));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(invalid("labels must not start or end with '-'"));
+        }
+        if !label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            return Err(invalid("only letters, digits, '-' and '.' are allowed"));
+        }
+    }
+    // A dotted all-numeric name is a malformed IPv4 address, not a hostname.
+    if name
+        .split('.')
+        .all(|label| label.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Err(invalid("not a valid IP address"));
+    }
+    Ok(())
+}
+
+fn strip_brackets(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+async fn socks5_handshake(
+    stream: &mut TcpStream,
+    target_host: &str,
+    target_port: u16,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<()> {
+    // Greeting: offer username/password authentication only when configured.
+    let greeting: &[u8] = if credentials.is_some() {
+        &[0x05, 0x02, 0x00, 0x02]
+    } else {
+        &[0x05, 0x01, 0x00]
+    };
+    stream.write_all(greeting).await?;
+
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != 0x05 {
+        return Err(DanmakuError::connection(
+            "SOCKS5 proxy replied with an unsupported version",
+        ));
+    }
+    match (choice[1], credentials) {
+        (0x00, _) => {}
+        (0x02, Some(credentials)) => {
+            let username = credentials.username.as_bytes();
+            let password = credentials.password.as_bytes();
+            let (Ok(username_len), Ok(password_len)) =
+                (u8::try_from(username.len()), u8::try_from(password.len()))
+            else {
+                return Err(DanmakuError::connection(
+                    "SOCKS5 username and password must be at most 255 bytes",
+                ));
+            };
+            let mut auth = Vec::with_capacity(3 + username.len() + password.len());
+            auth.push(0x01);
+            auth.push(username_len);
+            auth.extend_from_slice(username);
+            auth.push(password_len);
+            auth.extend_from_slice(password);
+            stream.write_all(&auth).await?;
+
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err(DanmakuError::connection(
+                    "SOCKS5 proxy rejected the credentials",
+                ));
+            }
+        }
+        _ => {
+            return Err(DanmakuError::connection(
+                "SOCKS5 proxy does not accept any offered authentication method",
+            ));
+        }
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match strip_brackets(target_host).parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let host = target_host.as_bytes();
+            let host_len = u8::try_from(host.len()).map_err(|_| {
+                DanmakuError::connection("SOCKS5 target hostname is longer than 255 bytes")
+            })?;
+            request.push(0x03);
+            request.push(host_len);
+            request.extend_from_slice(host);
+        }
+    }
+    request.extend_from_slice(&target_port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(DanmakuError::connection(format!(
+            "SOCKS5 proxy failed to connect to {}:{}: {}",
+            target_host,
+            target_port,
+            socks5_reply_message(reply[1])
+        )));
+    }
+    // Discard the bound address and port.
+    let address_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            usize::from(len[0])
+        }
+        other => {
+            return Err(DanmakuError::connection(format!(
+                "SOCKS5 proxy replied with unknown address type {other}"
+            )));
+        }
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+fn socks5_reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    target_host: &str,
+    target_port: u16,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<()> {
+    let authority = match strip_brackets(target_host).parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{ip}]:{target_port}"),
+        _ => format!("{target_host}:{target_port}"),
+    };
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(credentials) = credentials {
+        let token = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}:{}", credentials.username, credentials.password));
+        request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response head byte by byte so no tunnelled bytes are consumed.
+    let mut head = Vec::with_capacity(256);
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_CONNECT_RESPONSE_BYTES {
+            return Err(DanmakuError::connection(
+                "HTTP proxy CONNECT response is too large",
+            ));
+        }
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await?;
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok());
+    match status {
+        Some(code) if (200..300).contains(&code) => Ok(()),
+        _ => Err(DanmakuError::connection(format!(
+            "HTTP proxy refused CONNECT to {authority}: {status_line}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_validate_proxy_host() {
+        for host in [
+            "127.0.0.1",
+            "::1",
+            "[::1]",
+            "proxy",
+            "proxy.example.com",
+            "proxy-1.example.com.",
+        ] {
+            assert!(validate_proxy_host(host).is_ok(), "{host} should be valid");
+        }
+        for host in [
+            "",
+            "256.1.1.1",
+            "1.2.3",
+            "-proxy.example.com",
+            "proxy..example.com",
+            "proxy_host",
+            "http://proxy",
+            "proxy:1080",
+        ] {
+            assert!(
+                validate_proxy_host(host).is_err(),
+                "{host} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_validates_host() {
+        let config: ProxyConfig = serde_json::from_str(
+            r#"{"proxy_type":"socks5","host":"127.0.0.1","port":1080,
+                "credentials":{"username":"user","password":"pass"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.proxy_type, ProxyType::Socks5);
+        assert_eq!(config.credentials.as_ref().unwrap().username, "user");
+        assert!(!format!("{config:?}").contains("pass\""));
+
+        let err = serde_json::from_str::<ProxyConfig>(
+            r#"{"proxy_type":"http","host":"bad host","port":8080}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid proxy host"));
+
+        assert!(
+            serde_json::from_str::<ProxyConfig>(r#"{"proxy_type":"http","host":"proxy","port":0}"#)
+                .is_err()
+        );
+    }
+
+    /// Accept one SOCKS5 client with username/password auth and echo the tunnel.
+    async fn socks5_server(listener: TcpListener) -> (String, u16) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut greeting = [0u8; 4];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [0x05, 0x02, 0x00, 0x02]);
+        stream.write_all(&[0x05, 0x02]).await.unwrap();
+
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).await.unwrap();
+        let mut username = vec![0u8; header[1] as usize];
+        stream.read_exact(&mut username).await.unwrap();
+        let mut len = [0u8; 1];
+        stream.read_exact(&mut len).await.unwrap();
+        let mut password = vec![0u8; len[0] as usize];
+        stream.read_exact(&mut password).await.unwrap();
+        assert_eq!(username, b"user");
+        assert_eq!(password, b"secret");
+        stream.write_all(&[0x01, 0x00]).await.unwrap();
+
+        let mut request = [0u8; 5];
+        stream.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[..4], [0x05, 0x01, 0x00, 0x03]);
+        let mut host = vec![0u8; request[4] as usize];
+        stream.read_exact(&mut host).await.unwrap();
+        let mut port = [0u8; 2];
+        stream.read_exact(&mut port).await.unwrap();
+        stream
+            .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+
+        let mut ping = [0u8; 4];
+        stream.read_exact(&mut ping).await.unwrap();
+        stream.write_all(&ping).await.unwrap();
+
+        (String::from_utf8(host).unwrap(), u16::from_be_bytes(port))
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connect_with_credentials() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(socks5_server(listener));
+
+        let proxy = ProxyConfig::new(ProxyType::Socks5, "127.0.0.1", port)
+            .unwrap()
+            .with_credentials(ProxyCredentials {
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            });
+        let mut stream = proxy.connect("danmu.example.com", 443).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut echoed = [0u8; 4];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"ping");
+
+        let (host, target_port) = server.await.unwrap();
+        assert_eq!(host, "danmu.example.com");
+        assert_eq!(target_port, 443);
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connect_failure_reply() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            stream.write_all(&[0x05, 0x00]).await.unwrap();
+            let mut request = [0u8; 10];
+            stream.read_exact(&mut request).await.unwrap();
+            stream
+                .write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let proxy = ProxyConfig::new(ProxyType::Socks5, "127.0.0.1", port).unwrap();
+        let err = proxy.connect("10.0.0.1", 80).await.unwrap_err();
+        assert!(err.to_string().contains("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_http_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            // The tunnel payload directly follows the response head.
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\npong")
+                .await
+                .unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let proxy = ProxyConfig::new(ProxyType::Http, "127.0.0.1", port)
+            .unwrap()
+            .with_credentials(ProxyCredentials {
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            });
+        let mut stream = proxy.connect("danmu.example.com", 443).await.unwrap();
+        let mut payload = [0u8; 4];
+        stream.read_exact(&mut payload).await.unwrap();
+        assert_eq!(&payload, b"pong");
+
+        let head = server.await.unwrap();
+        assert!(head.starts_with("CONNECT danmu.example.com:443 HTTP/1.1\r\n"));
+        assert!(head.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 256];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let proxy = ProxyConfig::new(ProxyType::Http, "127.0.0.1", port).unwrap();
+        let err = proxy.connect("danmu.example.com", 443).await.unwrap_err();
+        assert!(err.to_string().contains("407"));
+    }
+}
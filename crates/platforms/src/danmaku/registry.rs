@@ -1,5 +1,7 @@
 //! Registry of available danmu providers.
 
+use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::plugin;
 use crate::danmaku::provider::DanmuProvider;
 use crate::extractor::platforms::bigo::create_bigo_danmu_provider;
 use crate::extractor::platforms::bilibili::danmu::create_bilibili_danmu_provider;
@@ -9,8 +11,30 @@ use crate::extractor::platforms::huya::create_huya_danmu_provider;
 use crate::extractor::platforms::soop::create_soop_danmu_provider;
 use crate::extractor::platforms::twitcasting::create_twitcasting_danmu_provider;
 use crate::extractor::platforms::twitch::create_twitch_danmu_provider;
+use serde::Serialize;
+use std::path::Path;
 use std::sync::Arc;
 
+/// Description of a registered provider, from [`ProviderRegistry::provider_infos`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderInfo {
+    pub platform: String,
+    /// Patterns of the streamer URLs the provider accepts.
+    pub url_patterns: Vec<String>,
+    /// Whether connections need cookies of a logged-in account.
+    pub requires_cookies: bool,
+}
+
+impl ProviderInfo {
+    fn of(provider: &dyn DanmuProvider) -> Self {
+        Self {
+            platform: provider.platform().to_string(),
+            url_patterns: provider.url_patterns(),
+            requires_cookies: provider.requires_cookies(),
+        }
+    }
+}
+
 /// Registry of available danmu providers.
 #[derive(Default)]
 pub struct ProviderRegistry {
@@ -44,6 +68,39 @@ impl ProviderRegistry {
         self.providers.push(provider);
     }
 
+    /// Register a provider unless one for the same platform is registered,
+    /// in which case it is replaced when `replace` is set and rejected otherwise.
+    ///
+    /// Platform names are compared case-insensitively, like
+    /// [`get_by_platform`](Self::get_by_platform) does.
+    pub fn try_register(&mut self, provider: Arc<dyn DanmuProvider>, replace: bool) -> Result<()> {
+        let existing = self
+            .providers
+            .iter()
+            .position(|p| p.platform().eq_ignore_ascii_case(provider.platform()));
+        match existing {
+            Some(index) if replace => self.providers[index] = provider,
+            Some(_) => {
+                return Err(DanmakuError::other(format!(
+                    "A danmu provider for platform {} is already registered",
+                    provider.platform()
+                )));
+            }
+            None => self.register(provider),
+        }
+        Ok(())
+    }
+
+    /// Load a plugin library and register the provider it exports.
+    ///
+    /// See [`plugin`](crate::danmaku::plugin) for the FFI contract. Plugins
+    /// built against a different [`PLUGIN_ABI_VERSION`](plugin::PLUGIN_ABI_VERSION)
+    /// are rejected, as are plugins for a platform that already has a provider.
+    pub fn load_plugin(&mut self, path: &Path) -> Result<()> {
+        let provider = plugin::load(path)?;
+        self.try_register(Arc::new(provider), false)
+    }
+
     /// Get a provider for the given platform.
     pub fn get_by_platform(&self, platform: &str) -> Option<Arc<dyn DanmuProvider>> {
         self.providers
@@ -61,6 +118,14 @@ impl ProviderRegistry {
     pub fn platforms(&self) -> Vec<&str> {
         self.providers.iter().map(|p| p.platform()).collect()
     }
+
+    /// Describe all registered providers, in registration order.
+    pub fn provider_infos(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .iter()
+            .map(|p| ProviderInfo::of(p.as_ref()))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -91,6 +156,42 @@ mod tests {
         assert_eq!(huya.unwrap().platform(), "huya");
     }
 
+    #[test]
+    fn test_provider_infos() {
+        let registry = ProviderRegistry::with_defaults();
+        let infos = registry.provider_infos();
+
+        assert_eq!(infos.len(), registry.platforms().len());
+        let huya = infos.iter().find(|info| info.platform == "huya").unwrap();
+        assert!(!huya.requires_cookies);
+        assert_eq!(huya.url_patterns.len(), 1);
+        let pattern = regex::Regex::new(&huya.url_patterns[0]).unwrap();
+        assert!(pattern.is_match("https://www.huya.com/12345"));
+    }
+
+    #[test]
+    fn test_try_register_rejects_duplicate_platforms() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Arc::new(create_huya_danmu_provider()));
+
+        let duplicate = Arc::new(create_huya_danmu_provider());
+        assert!(registry.try_register(duplicate.clone(), false).is_err());
+        assert_eq!(registry.platforms(), ["huya"]);
+
+        registry.try_register(duplicate.clone(), true).unwrap();
+        assert_eq!(registry.platforms(), ["huya"]);
+        let registered = registry.get_by_platform("huya").unwrap();
+        assert!(std::ptr::addr_eq(
+            Arc::as_ptr(&registered),
+            Arc::as_ptr(&duplicate)
+        ));
+
+        registry
+            .try_register(Arc::new(create_twitch_danmu_provider()), false)
+            .unwrap();
+        assert_eq!(registry.platforms(), ["huya", "twitch"]);
+    }
+
     #[test]
     fn test_get_by_url() {
         let registry = ProviderRegistry::with_defaults();
@@ -8,8 +8,11 @@ use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::collections::{HashMap, VecDeque};
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
+use super::error::Result;
+
 /// Statistics for a danmu collection session.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DanmuStatistics {
@@ -19,10 +22,56 @@ pub struct DanmuStatistics {
     pub chat_count: u64,
     /// Number of gift messages
     pub gift_count: u64,
+    /// Total value of gifts recorded with [`StatisticsAggregator::record_gift`],
+    /// in the platform's currency unit (e.g. coins)
+    #[serde(default)]
+    pub total_gift_value: u64,
     /// Top talkers (user_id -> message count)
     pub top_talkers: Vec<TopTalker>,
+    /// Usernames seen for users who changed their name, most seen first and
+    /// at most [`MAX_USERNAME_ALIASES`] per user
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub username_aliases: HashMap<String, Vec<String>>,
+    /// Top gift senders by total gift value
+    #[serde(default)]
+    pub top_gifters: Vec<TopGifter>,
+    /// Gifts by name, most valuable first and at most [`MAX_GIFT_KINDS`] names
+    #[serde(default)]
+    pub gift_breakdown: Vec<GiftSummary>,
+    /// Number of super chats recorded with [`StatisticsAggregator::record_super_chat`]
+    #[serde(default)]
+    pub total_super_chat_count: u64,
+    /// Total value of recorded super chats, in US cents
+    #[serde(default)]
+    pub total_super_chat_value_usd_cents: u64,
+    /// Top super chat senders by total value
+    #[serde(default)]
+    pub super_chat_leaderboard: Vec<SuperChatEntry>,
+    /// Number of messages dropped as spam
+    #[serde(default)]
+    pub spam_suppressed_count: u64,
+    /// Number of messages dropped by user and content filters
+    #[serde(default)]
+    pub filtered_count: u64,
+    /// Number of messages dropped because the collector fell behind
+    #[serde(default)]
+    pub queue_dropped_count: u64,
+    /// Number of chat messages containing at least one URL
+    #[serde(default)]
+    pub url_count: u64,
+    /// Messages matching each keyword alert rule, keyed by rule pattern
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keyword_matches: HashMap<String, u64>,
     /// Word frequency (word -> count)
     pub word_frequency: Vec<WordFrequency>,
+    /// Frequency of adjacent word n-grams, joined by spaces; empty unless the
+    /// aggregator was built with [`StatisticsAggregator::with_ngrams`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bigram_frequency: Vec<WordFrequency>,
+    /// Chat messages per detected language, most common first; empty unless
+    /// the aggregator was built with [`StatisticsAggregator::with_language_detection`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub language_distribution: Vec<LanguageCount>,
     /// Danmu rate timeseries (timestamp -> count)
     pub rate_timeseries: Vec<RateDataPoint>,
     /// Session start time
@@ -31,6 +80,99 @@ pub struct DanmuStatistics {
     pub end_time: Option<DateTime<Utc>>,
     /// Duration in seconds
     pub duration_secs: u64,
+    /// Whether these are the last statistics published while collecting,
+    /// returned because the collection did not finalize its own
+    #[serde(default)]
+    pub is_partial: bool,
+}
+
+impl DanmuStatistics {
+    /// Share of chat messages containing a URL, `0.0` without chat messages.
+    pub fn url_ratio(&self) -> f64 {
+        if self.chat_count == 0 {
+            return 0.0;
+        }
+        self.url_count as f64 / self.chat_count as f64
+    }
+
+    /// Render the top talkers, word frequency and rate timeseries as CSV,
+    /// keyed by report name (`top_talkers`, `word_frequency`, `rate_timeseries`).
+    ///
+    /// Each report starts with a header row.
+    pub fn to_csv_reports(&self) -> HashMap<String, String> {
+        self.csv_reports()
+            .into_iter()
+            .map(|(name, csv)| (name.to_string(), csv))
+            .collect()
+    }
+
+    /// Write each report of [`Self::to_csv_reports`] to `<name>.csv` in
+    /// `output_dir`, creating the directory if needed, and return the written paths.
+    pub fn write_csv_reports(&self, output_dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(output_dir)?;
+        let mut paths = Vec::new();
+        for (name, csv) in self.csv_reports() {
+            let path = output_dir.join(format!("{name}.csv"));
+            std::fs::write(&path, csv)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn csv_reports(&self) -> [(&'static str, String); 3] {
+        let mut top_talkers = String::from("user_id,username,message_count\n");
+        for talker in &self.top_talkers {
+            push_csv_row(
+                &mut top_talkers,
+                &[
+                    &talker.user_id,
+                    &talker.username,
+                    &talker.message_count.to_string(),
+                ],
+            );
+        }
+
+        let mut word_frequency = String::from("word,count\n");
+        for word in &self.word_frequency {
+            push_csv_row(&mut word_frequency, &[&word.word, &word.count.to_string()]);
+        }
+
+        let mut rate_timeseries = String::from("timestamp_utc,count\n");
+        for point in &self.rate_timeseries {
+            push_csv_row(
+                &mut rate_timeseries,
+                &[
+                    &point
+                        .timestamp
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    &point.count.to_string(),
+                ],
+            );
+        }
+
+        [
+            ("top_talkers", top_talkers),
+            ("word_frequency", word_frequency),
+            ("rate_timeseries", rate_timeseries),
+        ]
+    }
+}
+
+/// Append one CSV row, quoting fields that contain separators, quotes or line breaks.
+fn push_csv_row(out: &mut String, fields: &[&str]) {
+    for (idx, field) in fields.iter().enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
 }
 
 /// A top talker entry.
@@ -41,6 +183,33 @@ pub struct TopTalker {
     pub message_count: u64,
 }
 
+/// A top gift sender entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopGifter {
+    pub user_id: String,
+    pub username: String,
+    pub total_value: u64,
+    pub gift_count: u64,
+}
+
+/// Gifts of one kind received during a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftSummary {
+    pub gift_name: String,
+    /// Number of gifts, counting each unit of a combo
+    pub count: u64,
+    pub total_value: u64,
+}
+
+/// A super chat sender entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuperChatEntry {
+    pub user_id: String,
+    pub username: String,
+    pub total_value_usd_cents: u64,
+    pub count: u64,
+}
+
 /// A word frequency entry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordFrequency {
@@ -48,6 +217,16 @@ pub struct WordFrequency {
     pub count: u64,
 }
 
+/// Chat messages detected in one language.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageCount {
+    /// ISO 639-1 code, e.g. `"zh"` or `"en"`
+    pub language_code: String,
+    pub count: u64,
+    /// Share of the messages whose language was detected
+    pub fraction: f64,
+}
+
 /// A rate timeseries data point.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateDataPoint {
@@ -55,6 +234,41 @@ pub struct RateDataPoint {
     pub count: u64,
 }
 
+/// Statistics over the most recent `window_secs` of a session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RollingWindowStats {
+    /// Number of messages in the window
+    pub messages_in_window: u64,
+    /// Number of distinct users who sent a message in the window
+    pub unique_users_in_window: u64,
+    /// Most frequent words in the window
+    pub top_words_in_window: Vec<WordFrequency>,
+}
+
+/// A message passed to [`StatisticsAggregator::record_message_batch`].
+#[derive(Debug, Clone, Copy)]
+pub struct BatchMessage<'a> {
+    pub user_id: &'a str,
+    pub username: &'a str,
+    pub content: &'a str,
+    pub is_gift: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// How long per-bucket users and words are kept for rolling window queries.
+///
+/// Windows longer than this still count every message, but unique users and
+/// top words only cover the retained buckets.
+const ROLLING_WINDOW_RETENTION_SECS: u64 = 10 * 60;
+
+/// Users and words seen in one rate bucket.
+#[derive(Debug, Clone)]
+struct RecentBucket {
+    start: DateTime<Utc>,
+    users: HashSet<String>,
+    words: HashMap<String, u64>,
+}
+
 #[derive(Debug, Clone)]
 struct TalkerCounter {
     username: String,
@@ -76,13 +290,18 @@ impl TalkerHeavyHitters {
         }
     }
 
-    fn increment(&mut self, user_id: &str, username: &str) {
+    /// Count a message of `user_id`, returning the user evicted to make room.
+    fn increment(&mut self, user_id: &str, username: &str) -> Option<String> {
+        self.add(user_id, username, 1)
+    }
+
+    fn add(&mut self, user_id: &str, username: &str, count: u64) -> Option<String> {
         if let Some(counter) = self.counters.get_mut(user_id) {
-            counter.count = counter.count.saturating_add(1);
+            counter.count = counter.count.saturating_add(count);
             if counter.username != username {
                 counter.username = username.to_string();
             }
-            return;
+            return None;
         }
 
         if self.counters.len() < self.capacity {
@@ -90,11 +309,11 @@ impl TalkerHeavyHitters {
                 user_id.to_string(),
                 TalkerCounter {
                     username: username.to_string(),
-                    count: 1,
+                    count,
                     error: 0,
                 },
             );
-            return;
+            return None;
         }
 
         let min_key_and_count = self
@@ -103,17 +322,17 @@ impl TalkerHeavyHitters {
             .min_by_key(|(_, counter)| counter.count)
             .map(|(key, counter)| (key.clone(), counter.count));
 
-        if let Some((key, min_count)) = min_key_and_count {
-            self.counters.remove(&key);
-            self.counters.insert(
-                user_id.to_string(),
-                TalkerCounter {
-                    username: username.to_string(),
-                    count: min_count.saturating_add(1),
-                    error: min_count,
-                },
-            );
-        }
+        let (key, min_count) = min_key_and_count?;
+        self.counters.remove(&key);
+        self.counters.insert(
+            user_id.to_string(),
+            TalkerCounter {
+                username: username.to_string(),
+                count: min_count.saturating_add(count),
+                error: min_count,
+            },
+        );
+        Some(key)
     }
 
     fn top_n(&self, n: usize) -> Vec<TopTalker> {
@@ -163,6 +382,113 @@ impl TalkerHeavyHitters {
     }
 }
 
+#[derive(Debug, Clone)]
+struct GifterCounter {
+    username: String,
+    total_value: u64,
+    gift_count: u64,
+    error: u64,
+}
+
+/// Space-Saving over paying senders (gifts or super chats), weighted by value.
+#[derive(Debug, Clone)]
+struct GiftLeaderboard {
+    capacity: usize,
+    counters: HashMap<String, GifterCounter>,
+}
+
+impl GiftLeaderboard {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counters: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, user_id: &str, username: &str, value: u64, count: u64) {
+        if let Some(counter) = self.counters.get_mut(user_id) {
+            counter.total_value = counter.total_value.saturating_add(value);
+            counter.gift_count = counter.gift_count.saturating_add(count);
+            if counter.username != username {
+                counter.username = username.to_string();
+            }
+            return;
+        }
+
+        if self.counters.len() < self.capacity {
+            self.counters.insert(
+                user_id.to_string(),
+                GifterCounter {
+                    username: username.to_string(),
+                    total_value: value,
+                    gift_count: count,
+                    error: 0,
+                },
+            );
+            return;
+        }
+
+        let min_key_and_value = self
+            .counters
+            .iter()
+            .min_by_key(|(_, counter)| counter.total_value)
+            .map(|(key, counter)| (key.clone(), counter.total_value));
+
+        if let Some((key, min_value)) = min_key_and_value {
+            self.counters.remove(&key);
+            self.counters.insert(
+                user_id.to_string(),
+                GifterCounter {
+                    username: username.to_string(),
+                    total_value: min_value.saturating_add(value),
+                    gift_count: count,
+                    error: min_value,
+                },
+            );
+        }
+    }
+
+    fn ranked(&self, n: usize) -> Vec<(&String, &GifterCounter)> {
+        if n == 0 || self.counters.is_empty() {
+            return Vec::new();
+        }
+
+        let mut entries: Vec<_> = self.counters.iter().collect();
+        entries.sort_by(|(aid, a), (bid, b)| {
+            b.total_value
+                .cmp(&a.total_value)
+                .then_with(|| aid.cmp(bid))
+                .then_with(|| a.error.cmp(&b.error))
+        });
+        entries.truncate(n);
+        entries
+    }
+
+    fn top_n(&self, n: usize) -> Vec<TopGifter> {
+        self.ranked(n)
+            .into_iter()
+            .map(|(user_id, counter)| TopGifter {
+                user_id: user_id.clone(),
+                username: counter.username.clone(),
+                total_value: counter.total_value,
+                gift_count: counter.gift_count,
+            })
+            .collect()
+    }
+
+    fn top_super_chats(&self, n: usize) -> Vec<SuperChatEntry> {
+        self.ranked(n)
+            .into_iter()
+            .map(|(user_id, counter)| SuperChatEntry {
+                user_id: user_id.clone(),
+                username: counter.username.clone(),
+                total_value_usd_cents: counter.total_value,
+                count: counter.gift_count,
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct WordCounter {
     count: u64,
@@ -230,18 +556,28 @@ impl WordHeavyHitters {
     }
 
     fn increment(&mut self, word: &str) {
+        self.add(word, 1);
+    }
+
+    /// Reserve room for up to `additional` new words, never beyond `capacity`.
+    fn reserve(&mut self, additional: usize) {
+        let free = self.capacity.saturating_sub(self.counters.len());
+        self.counters.reserve(additional.min(free));
+    }
+
+    fn add(&mut self, word: &str, count: u64) {
         if let Some(sketch) = &mut self.sketch {
-            sketch.increment(word, 1);
+            sketch.increment(word, count);
         }
 
         if let Some(counter) = self.counters.get_mut(word) {
-            counter.count = counter.count.saturating_add(1);
+            counter.count = counter.count.saturating_add(count);
             return;
         }
 
         if self.counters.len() < self.capacity {
             self.counters
-                .insert(word.to_string(), WordCounter { count: 1, error: 0 });
+                .insert(word.to_string(), WordCounter { count, error: 0 });
             return;
         }
 
@@ -258,7 +594,7 @@ impl WordHeavyHitters {
                 .as_ref()
                 .map(|sketch| sketch.estimate(word))
                 .unwrap_or(0);
-            let count = min_count.saturating_add(1).max(cms_count);
+            let count = min_count.saturating_add(count).max(cms_count);
             self.counters.insert(
                 word.to_string(),
                 WordCounter {
@@ -335,6 +671,191 @@ impl WordHeavyHitters {
     }
 }
 
+/// Maximum number of gift names tracked in [`DanmuStatistics::gift_breakdown`].
+pub const MAX_GIFT_KINDS: usize = 100;
+
+/// Count and value per gift name, bounded to [`MAX_GIFT_KINDS`] names.
+///
+/// Platforms only offer a fixed catalogue of gifts, so names past the bound
+/// are not tracked rather than evicting known ones.
+#[derive(Debug, Clone, Default)]
+struct GiftBreakdown {
+    gifts: HashMap<String, (u64, u64)>,
+}
+
+impl GiftBreakdown {
+    fn add(&mut self, gift_name: &str, count: u64, value: u64) {
+        if let Some(entry) = self.gifts.get_mut(gift_name) {
+            entry.0 = entry.0.saturating_add(count);
+            entry.1 = entry.1.saturating_add(value);
+        } else if self.gifts.len() < MAX_GIFT_KINDS {
+            self.gifts.insert(gift_name.to_owned(), (count, value));
+        }
+    }
+
+    fn summaries(&self) -> Vec<GiftSummary> {
+        let mut summaries: Vec<_> = self
+            .gifts
+            .iter()
+            .map(|(gift_name, (count, total_value))| GiftSummary {
+                gift_name: gift_name.clone(),
+                count: *count,
+                total_value: *total_value,
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.total_value
+                .cmp(&a.total_value)
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.gift_name.cmp(&b.gift_name))
+        });
+        summaries
+    }
+}
+
+/// Maximum number of languages tracked in [`DanmuStatistics::language_distribution`].
+pub const MAX_LANGUAGES: usize = 20;
+
+/// Messages shorter than this many characters are too short to detect their language.
+const MIN_LANGUAGE_DETECTION_CHARS: usize = 10;
+
+/// ISO 639-1 code of a language detected by `whichlang`.
+fn iso_639_1(lang: whichlang::Lang) -> &'static str {
+    match lang.three_letter_code() {
+        "ara" => "ar",
+        "cmn" => "zh",
+        "deu" => "de",
+        "eng" => "en",
+        "fra" => "fr",
+        "hin" => "hi",
+        "ita" => "it",
+        "jpn" => "ja",
+        "kor" => "ko",
+        "nld" => "nl",
+        "por" => "pt",
+        "rus" => "ru",
+        "spa" => "es",
+        "swe" => "sv",
+        "tur" => "tr",
+        "vie" => "vi",
+        other => other,
+    }
+}
+
+/// Chat messages per detected language, bounded to [`MAX_LANGUAGES`] codes.
+///
+/// Like gift names, languages past the bound are not tracked rather than
+/// evicting known ones.
+#[derive(Debug, Clone, Default)]
+struct LanguageCounts {
+    counts: HashMap<String, u64>,
+}
+
+impl LanguageCounts {
+    fn add(&mut self, language_code: &str, count: u64) {
+        if !self.counts.contains_key(language_code) && self.counts.len() >= MAX_LANGUAGES {
+            return;
+        }
+        let total = self.counts.entry(language_code.to_string()).or_insert(0);
+        *total = total.saturating_add(count);
+    }
+
+    fn distribution(&self) -> Vec<LanguageCount> {
+        let detected: u64 = self.counts.values().sum();
+        let mut distribution: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(language_code, count)| LanguageCount {
+                language_code: language_code.clone(),
+                count: *count,
+                fraction: *count as f64 / detected as f64,
+            })
+            .collect();
+        distribution.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.language_code.cmp(&b.language_code))
+        });
+        distribution
+    }
+}
+
+/// Maximum number of usernames remembered per user.
+pub const MAX_USERNAME_ALIASES: usize = 5;
+
+/// Messages recorded per username of each tracked talker.
+#[derive(Debug, Clone, Default)]
+struct UsernameAliases {
+    counts: HashMap<String, HashMap<String, u64>>,
+}
+
+impl UsernameAliases {
+    fn add(&mut self, user_id: &str, username: &str, count: u64) {
+        let names = self.counts.entry(user_id.to_string()).or_default();
+        if let Some(total) = names.get_mut(username) {
+            *total = total.saturating_add(count);
+            return;
+        }
+        if names.len() >= MAX_USERNAME_ALIASES {
+            // Make room by forgetting the least seen name.
+            let least_seen = names
+                .iter()
+                .min_by(|(an, a), (bn, b)| a.cmp(b).then_with(|| bn.cmp(an)))
+                .map(|(name, _)| name.clone());
+            if let Some(name) = least_seen {
+                names.remove(&name);
+            }
+        }
+        names.insert(username.to_string(), count);
+    }
+
+    fn remove(&mut self, user_id: &str) {
+        self.counts.remove(user_id);
+    }
+
+    /// Names of `user_id`, most seen first; ties are ordered by name.
+    fn ranked(&self, user_id: &str) -> Vec<(&str, u64)> {
+        let mut names: Vec<_> = self
+            .counts
+            .get(user_id)
+            .into_iter()
+            .flatten()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        names.sort_by(|(an, a), (bn, b)| b.cmp(a).then_with(|| an.cmp(bn)));
+        names
+    }
+
+    fn canonical(&self, user_id: &str) -> Option<&str> {
+        self.ranked(user_id).first().map(|(name, _)| *name)
+    }
+
+    /// Users seen under more than one name, with their names most seen first.
+    fn aliases(&self) -> HashMap<String, Vec<String>> {
+        self.counts
+            .iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(user_id, _)| {
+                let names = self
+                    .ranked(user_id)
+                    .into_iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                (user_id.clone(), names)
+            })
+            .collect()
+    }
+
+    /// Replace each talker's username by the one seen most often.
+    fn canonicalize(&self, talkers: &mut [TopTalker]) {
+        for talker in talkers {
+            if let Some(name) = self.canonical(&talker.user_id) {
+                talker.username = name.to_string();
+            }
+        }
+    }
+}
+
 /// Aggregator for calculating danmu statistics.
 #[derive(Debug)]
 pub struct StatisticsAggregator {
@@ -344,14 +865,46 @@ pub struct StatisticsAggregator {
     chat_count: u64,
     /// Gift message count
     gift_count: u64,
+    /// Total value of recorded gifts
+    total_gift_value: u64,
+    /// Super chat count
+    super_chat_count: u64,
+    /// Total value of recorded super chats, in US cents
+    super_chat_value_usd_cents: u64,
+    /// Messages dropped as spam
+    spam_suppressed_count: u64,
+    /// Messages dropped by user and content filters
+    filtered_count: u64,
+    /// Messages dropped because the collector fell behind
+    queue_dropped_count: u64,
+    /// Chat messages containing a URL
+    url_count: u64,
+    /// Keyword alert matches by rule pattern
+    keyword_matches: HashMap<String, u64>,
     /// Heavy hitters for active talkers (Space-Saving).
     talker_hh: TalkerHeavyHitters,
+    /// Usernames of the talkers tracked by `talker_hh`.
+    usernames: UsernameAliases,
+    /// Heavy hitters for gift senders by value (Space-Saving).
+    gift_leaderboard: GiftLeaderboard,
+    /// Count and value per gift name.
+    gift_breakdown: GiftBreakdown,
+    /// Heavy hitters for super chat senders by value (Space-Saving).
+    super_chat_leaderboard: GiftLeaderboard,
     /// Heavy hitters for words (Space-Saving + optional Count-Min Sketch).
     word_hh: WordHeavyHitters,
+    /// Heavy hitters for word n-grams, bounded to `max_words` entries.
+    ngram_hh: Option<WordHeavyHitters>,
+    /// Words per n-gram; `0` disables n-gram tracking.
+    ngram_size: usize,
+    /// Chat messages per detected language; `None` disables language detection.
+    languages: Option<LanguageCounts>,
     /// Rate data points.
     rate_data: VecDeque<RateDataPoint>,
     /// Current rate bucket
     current_bucket: Option<(DateTime<Utc>, u64)>,
+    /// Users and words of the buckets within the rolling window retention.
+    recent_buckets: VecDeque<RecentBucket>,
     /// Bucket duration in seconds
     bucket_duration_secs: u64,
     /// Session start time
@@ -366,6 +919,40 @@ pub struct StatisticsAggregator {
     stop_words: &'static HashSet<&'static str>,
 }
 
+/// Top-level domains recognized in URLs written without a scheme.
+const URL_TLDS: &[&str] = &[
+    "com", "net", "org", "io", "tv", "gg", "me", "co", "cn", "jp", "kr", "ly", "xyz", "app", "dev",
+    "live", "info",
+];
+
+/// Whether a whitespace-separated token looks like a URL: it starts with a
+/// scheme or `www.`, or is a domain name ending in a common TLD, with an
+/// optional path.
+fn is_url(token: &str) -> bool {
+    let lower = token.to_ascii_lowercase();
+    if ["http://", "https://", "www."]
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+    {
+        return true;
+    }
+    let host = lower.split(['/', '?', '#']).next().unwrap_or_default();
+    let Some((name, tld)) = host.rsplit_once('.') else {
+        return false;
+    };
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && URL_TLDS.contains(&tld)
+}
+
+/// Approximate heap bytes of a hash map's table, excluding what its entries own.
+fn table_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    // One control byte per slot besides the entry itself.
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
 static STOP_WORDS: LazyLock<HashSet<&'static str>> = LazyLock::new(default_stop_words);
 
 impl StatisticsAggregator {
@@ -379,6 +966,19 @@ impl StatisticsAggregator {
         max_top_talkers: usize,
         max_words: usize,
         bucket_duration_secs: u64,
+    ) -> Self {
+        Self::with_config_full(max_top_talkers, max_words, bucket_duration_secs, None)
+    }
+
+    /// Create a new statistics aggregator with custom configuration, keeping
+    /// at most `max_rate_points` rate buckets in memory.
+    ///
+    /// `None` keeps six hours of buckets (at least 60).
+    pub fn with_config_full(
+        max_top_talkers: usize,
+        max_words: usize,
+        bucket_duration_secs: u64,
+        max_rate_points: Option<usize>,
     ) -> Self {
         let talker_capacity = max_top_talkers.max(1).saturating_mul(8);
         let word_capacity = max_words.max(1).saturating_mul(4);
@@ -386,18 +986,37 @@ impl StatisticsAggregator {
             .next_power_of_two()
             .max(256);
         let cms_depth = 4;
-        let max_rate_points = ((6 * 60 * 60) / bucket_duration_secs.max(1) as usize).max(60);
+        let max_rate_points = max_rate_points.map_or_else(
+            || ((6 * 60 * 60) / bucket_duration_secs.max(1) as usize).max(60),
+            |max| max.max(1),
+        );
         Self {
             total_count: 0,
             chat_count: 0,
             gift_count: 0,
+            total_gift_value: 0,
+            super_chat_count: 0,
+            super_chat_value_usd_cents: 0,
+            spam_suppressed_count: 0,
+            filtered_count: 0,
+            queue_dropped_count: 0,
+            url_count: 0,
+            keyword_matches: HashMap::new(),
             talker_hh: TalkerHeavyHitters::new(talker_capacity),
+            usernames: UsernameAliases::default(),
+            gift_leaderboard: GiftLeaderboard::new(talker_capacity),
+            gift_breakdown: GiftBreakdown::default(),
+            super_chat_leaderboard: GiftLeaderboard::new(talker_capacity),
             word_hh: WordHeavyHitters::new(
                 word_capacity,
                 Some(CountMinSketch::new(cms_width, cms_depth)),
             ),
+            ngram_hh: None,
+            ngram_size: 0,
+            languages: None,
             rate_data: VecDeque::new(),
             current_bucket: None,
+            recent_buckets: VecDeque::new(),
             bucket_duration_secs,
             start_time: None,
             max_top_talkers,
@@ -407,6 +1026,32 @@ impl StatisticsAggregator {
         }
     }
 
+    /// Also count n-grams of `n` adjacent words, e.g. bigrams for `n = 2`,
+    /// reported in `DanmuStatistics::bigram_frequency`.
+    ///
+    /// Stop words and very short words are skipped before n-grams are formed.
+    /// `n < 2` disables n-gram tracking.
+    pub fn with_ngrams(mut self, n: usize) -> Self {
+        if n < 2 {
+            self.ngram_hh = None;
+            self.ngram_size = 0;
+        } else {
+            self.ngram_hh = Some(WordHeavyHitters::new(self.max_words, None));
+            self.ngram_size = n;
+        }
+        self
+    }
+
+    /// Also detect the language of chat messages, reported in
+    /// `DanmuStatistics::language_distribution`.
+    ///
+    /// Detection costs CPU time on every chat message, so it is off by default.
+    /// Messages shorter than 10 characters are not detected.
+    pub fn with_language_detection(mut self, enabled: bool) -> Self {
+        self.languages = enabled.then(LanguageCounts::default);
+        self
+    }
+
     /// Record a message.
     pub fn record_message(
         &mut self,
@@ -421,6 +1066,42 @@ impl StatisticsAggregator {
             self.start_time = Some(timestamp);
         }
 
+        self.record_started(user_id, username, content, is_gift, timestamp);
+    }
+
+    /// Record `messages` in order.
+    ///
+    /// Equivalent to calling [`Self::record_message`] for each message, but
+    /// sets the start time once and grows the word table once for the batch.
+    pub fn record_message_batch(&mut self, messages: &[BatchMessage<'_>]) {
+        let Some(first) = messages.first() else {
+            return;
+        };
+        if self.start_time.is_none() {
+            self.start_time = Some(first.timestamp);
+        }
+
+        let words: usize = messages
+            .iter()
+            .filter(|m| !m.is_gift)
+            .map(|m| m.content.split_whitespace().count())
+            .sum();
+        self.word_hh.reserve(words);
+
+        for m in messages {
+            self.record_started(m.user_id, m.username, m.content, m.is_gift, m.timestamp);
+        }
+    }
+
+    /// Record a message once `start_time` is set.
+    fn record_started(
+        &mut self,
+        user_id: &str,
+        username: &str,
+        content: &str,
+        is_gift: bool,
+        timestamp: DateTime<Utc>,
+    ) {
         // Update counts
         self.total_count += 1;
         if is_gift {
@@ -429,21 +1110,205 @@ impl StatisticsAggregator {
             self.chat_count += 1;
         }
 
-        self.talker_hh.increment(user_id, username);
-
-        // Update word counts (only for chat messages)
-        if !is_gift && !content.is_empty() {
-            self.process_words(content);
+        if let Some(evicted) = self.talker_hh.increment(user_id, username) {
+            self.usernames.remove(&evicted);
         }
+        self.usernames.add(user_id, username, 1);
 
         // Update rate data
         self.update_rate_bucket(timestamp);
-    }
+        self.record_recent_user(user_id, timestamp);
+
+        // Update word counts (only for chat messages)
+        if !is_gift && !content.is_empty() {
+            self.process_words(content);
+            if let Some(languages) = &mut self.languages
+                && content.chars().count() >= MIN_LANGUAGE_DETECTION_CHARS
+            {
+                languages.add(iso_639_1(whichlang::detect_language(content)), 1);
+            }
+        }
+    }
+
+    /// Record `gift_count` gifts named `gift_name` from `user_id`, worth
+    /// `value` in total (e.g. coins).
+    ///
+    /// Counts like a gift passed to [`Self::record_message`], and additionally
+    /// adds the value to the session total, the sender's leaderboard entry and
+    /// the gift's breakdown entry.
+    pub fn record_gift(
+        &mut self,
+        user_id: &str,
+        username: &str,
+        gift_name: &str,
+        gift_count: u64,
+        value: u64,
+        timestamp: DateTime<Utc>,
+    ) {
+        self.record_message(user_id, username, gift_name, true, timestamp);
+        self.total_gift_value = self.total_gift_value.saturating_add(value);
+        self.gift_leaderboard
+            .add(user_id, username, value, gift_count);
+        self.gift_breakdown.add(gift_name, gift_count, value);
+    }
+
+    /// Record a super chat worth `price_usd_cents` from `user_id`.
+    ///
+    /// Counts like a chat passed to [`Self::record_message`], and additionally
+    /// adds the value to the super chat total and the sender's leaderboard entry.
+    pub fn record_super_chat(
+        &mut self,
+        user_id: &str,
+        username: &str,
+        content: &str,
+        price_usd_cents: u64,
+        timestamp: DateTime<Utc>,
+    ) {
+        self.record_message(user_id, username, content, false, timestamp);
+        self.super_chat_count = self.super_chat_count.saturating_add(1);
+        self.super_chat_value_usd_cents = self
+            .super_chat_value_usd_cents
+            .saturating_add(price_usd_cents);
+        self.super_chat_leaderboard
+            .add(user_id, username, price_usd_cents, 1);
+    }
+
+    /// Number of messages recorded so far.
+    pub fn total_count(&self) -> u64 {
+        self.total_count
+    }
+
+    /// Approximate heap memory held by the aggregator, in bytes.
+    ///
+    /// Counts the allocated slots of its tables and buffers and the strings
+    /// they own, so it grows with the fill up to the bounds set by the
+    /// configured capacities. Allocator overhead is not included.
+    pub fn memory_estimate_bytes(&self) -> usize {
+        let talkers = table_bytes(&self.talker_hh.counters)
+            + self
+                .talker_hh
+                .counters
+                .iter()
+                .map(|(user_id, counter)| user_id.capacity() + counter.username.capacity())
+                .sum::<usize>();
+        let usernames = table_bytes(&self.usernames.counts)
+            + self
+                .usernames
+                .counts
+                .iter()
+                .map(|(user_id, names)| {
+                    user_id.capacity()
+                        + table_bytes(names)
+                        + names.keys().map(String::capacity).sum::<usize>()
+                })
+                .sum::<usize>();
+        let leaderboards = [&self.gift_leaderboard, &self.super_chat_leaderboard]
+            .into_iter()
+            .map(|board| {
+                table_bytes(&board.counters)
+                    + board
+                        .counters
+                        .iter()
+                        .map(|(user_id, counter)| user_id.capacity() + counter.username.capacity())
+                        .sum::<usize>()
+            })
+            .sum::<usize>();
+        let words = std::iter::once(&self.word_hh)
+            .chain(&self.ngram_hh)
+            .map(|hitters| {
+                let sketch = hitters.sketch.as_ref().map_or(0, |sketch| {
+                    sketch.rows.capacity() * size_of::<Vec<u64>>()
+                        + sketch
+                            .rows
+                            .iter()
+                            .map(|row| row.capacity() * size_of::<u64>())
+                            .sum::<usize>()
+                });
+                table_bytes(&hitters.counters)
+                    + hitters.counters.keys().map(String::capacity).sum::<usize>()
+                    + sketch
+            })
+            .sum::<usize>();
+        let gifts = table_bytes(&self.gift_breakdown.gifts)
+            + self
+                .gift_breakdown
+                .gifts
+                .keys()
+                .map(String::capacity)
+                .sum::<usize>();
+        let keywords = table_bytes(&self.keyword_matches)
+            + self
+                .keyword_matches
+                .keys()
+                .map(String::capacity)
+                .sum::<usize>();
+        let languages = self.languages.as_ref().map_or(0, |languages| {
+            table_bytes(&languages.counts)
+                + languages.counts.keys().map(String::capacity).sum::<usize>()
+        });
+        let rates = self.rate_data.capacity() * size_of::<RateDataPoint>();
+        let recent = self.recent_buckets.capacity() * size_of::<RecentBucket>()
+            + self
+                .recent_buckets
+                .iter()
+                .map(|bucket| {
+                    bucket.users.capacity() * (size_of::<String>() + 1)
+                        + bucket.users.iter().map(String::capacity).sum::<usize>()
+                        + table_bytes(&bucket.words)
+                        + bucket.words.keys().map(String::capacity).sum::<usize>()
+                })
+                .sum::<usize>();
+
+        talkers + usernames + leaderboards + words + gifts + keywords + languages + rates + recent
+    }
+
+    /// The username `user_id` sent the most messages under, `None` for users
+    /// not among the tracked talkers.
+    pub fn canonical_username(&self, user_id: &str) -> Option<&str> {
+        self.usernames.canonical(user_id)
+    }
+
+    /// Record a message that was dropped as spam.
+    ///
+    /// Suppressed messages are not counted by [`Self::record_message`].
+    pub fn record_spam_suppressed(&mut self) {
+        self.spam_suppressed_count = self.spam_suppressed_count.saturating_add(1);
+    }
+
+    /// Record a message that was dropped by a user or content filter.
+    ///
+    /// Filtered messages are not counted by [`Self::record_message`].
+    pub fn record_filtered(&mut self) {
+        self.filtered_count = self.filtered_count.saturating_add(1);
+    }
+
+    /// Record `count` messages that were dropped because the collector fell behind.
+    ///
+    /// Dropped messages are not counted by [`Self::record_message`].
+    pub fn record_queue_dropped(&mut self, count: u64) {
+        self.queue_dropped_count = self.queue_dropped_count.saturating_add(count);
+    }
+
+    /// Record a message matching the keyword alert rule `pattern`.
+    pub fn record_keyword_match(&mut self, pattern: &str) {
+        let count = self.keyword_matches.entry(pattern.to_string()).or_insert(0);
+        *count = count.saturating_add(1);
+    }
 
     /// Process words from a message.
+    ///
+    /// URLs are counted in `url_count` and left out of the word frequencies.
     fn process_words(&mut self, content: &str) {
+        let mut words = Vec::new();
+        let mut has_url = false;
         for word in content
-            .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+            .split_whitespace()
+            .filter(|token| {
+                let is_url = is_url(token);
+                has_url |= is_url;
+                !is_url
+            })
+            .flat_map(|token| token.split(|c: char| c.is_ascii_punctuation()))
             .filter(|s| !s.is_empty())
         {
             let word_lower = word.to_lowercase();
@@ -454,6 +1319,51 @@ impl StatisticsAggregator {
             }
 
             self.word_hh.increment(&word_lower);
+            if self.ngram_hh.is_some() {
+                words.push(word_lower.clone());
+            }
+            if let Some(bucket) = self.recent_buckets.back_mut() {
+                *bucket.words.entry(word_lower).or_insert(0) += 1;
+            }
+        }
+
+        if let Some(ngram_hh) = &mut self.ngram_hh {
+            for ngram in words.windows(self.ngram_size) {
+                ngram_hh.increment(&ngram.join(" "));
+            }
+        }
+        if has_url {
+            self.url_count = self.url_count.saturating_add(1);
+        }
+    }
+
+    /// Record the sender in the recent bucket of `timestamp`.
+    fn record_recent_user(&mut self, user_id: &str, timestamp: DateTime<Utc>) {
+        let bucket_start = self.get_bucket_start(timestamp);
+        if self
+            .recent_buckets
+            .back()
+            .is_none_or(|bucket| bucket.start != bucket_start)
+        {
+            self.recent_buckets.push_back(RecentBucket {
+                start: bucket_start,
+                users: HashSet::new(),
+                words: HashMap::new(),
+            });
+            let retention_start =
+                bucket_start - chrono::Duration::seconds(ROLLING_WINDOW_RETENTION_SECS as i64);
+            while self
+                .recent_buckets
+                .front()
+                .is_some_and(|bucket| bucket.start < retention_start)
+            {
+                self.recent_buckets.pop_front();
+            }
+        }
+        if let Some(bucket) = self.recent_buckets.back_mut()
+            && !bucket.users.contains(user_id)
+        {
+            bucket.users.insert(user_id.to_string());
         }
     }
 
@@ -508,25 +1418,73 @@ impl StatisticsAggregator {
             .map(|start| (end_time - start).num_seconds().max(0) as u64)
             .unwrap_or(0);
 
-        let top_talkers = self.talker_hh.into_top_n(self.max_top_talkers);
+        let mut top_talkers = self.talker_hh.into_top_n(self.max_top_talkers);
+        self.usernames.canonicalize(&mut top_talkers);
+        let username_aliases = self.usernames.aliases();
+        let top_gifters = self.gift_leaderboard.top_n(self.max_top_talkers);
+        let gift_breakdown = self.gift_breakdown.summaries();
+        let super_chat_leaderboard = self
+            .super_chat_leaderboard
+            .top_super_chats(self.max_top_talkers);
         let word_frequency = self.word_hh.into_top_n(self.max_words);
+        let bigram_frequency = self
+            .ngram_hh
+            .map(|ngram_hh| ngram_hh.into_top_n(self.max_words))
+            .unwrap_or_default();
+        let language_distribution = self
+            .languages
+            .as_ref()
+            .map(LanguageCounts::distribution)
+            .unwrap_or_default();
         DanmuStatistics {
             total_count: self.total_count,
             chat_count: self.chat_count,
             gift_count: self.gift_count,
+            total_gift_value: self.total_gift_value,
             top_talkers,
+            username_aliases,
+            top_gifters,
+            gift_breakdown,
+            total_super_chat_count: self.super_chat_count,
+            total_super_chat_value_usd_cents: self.super_chat_value_usd_cents,
+            super_chat_leaderboard,
+            spam_suppressed_count: self.spam_suppressed_count,
+            filtered_count: self.filtered_count,
+            queue_dropped_count: self.queue_dropped_count,
+            url_count: self.url_count,
+            keyword_matches: self.keyword_matches,
             word_frequency,
+            bigram_frequency,
+            language_distribution,
             rate_timeseries: self.rate_data.into_iter().collect(),
             start_time: self.start_time,
             end_time: Some(end_time),
             duration_secs,
+            is_partial: false,
         }
     }
 
     /// Get current statistics without finalizing.
     pub fn current_stats(&self) -> DanmuStatistics {
-        let top_talkers = self.talker_hh.top_n(self.max_top_talkers);
+        let mut top_talkers = self.talker_hh.top_n(self.max_top_talkers);
+        self.usernames.canonicalize(&mut top_talkers);
+        let username_aliases = self.usernames.aliases();
+        let top_gifters = self.gift_leaderboard.top_n(self.max_top_talkers);
+        let gift_breakdown = self.gift_breakdown.summaries();
+        let super_chat_leaderboard = self
+            .super_chat_leaderboard
+            .top_super_chats(self.max_top_talkers);
         let word_frequency = self.word_hh.top_n(self.max_words);
+        let bigram_frequency = self
+            .ngram_hh
+            .as_ref()
+            .map(|ngram_hh| ngram_hh.top_n(self.max_words))
+            .unwrap_or_default();
+        let language_distribution = self
+            .languages
+            .as_ref()
+            .map(LanguageCounts::distribution)
+            .unwrap_or_default();
 
         let mut rate_data: Vec<_> = self.rate_data.iter().cloned().collect();
         if let Some((start, count)) = &self.current_bucket {
@@ -540,12 +1498,89 @@ impl StatisticsAggregator {
             total_count: self.total_count,
             chat_count: self.chat_count,
             gift_count: self.gift_count,
+            total_gift_value: self.total_gift_value,
             top_talkers,
+            username_aliases,
+            top_gifters,
+            gift_breakdown,
+            total_super_chat_count: self.super_chat_count,
+            total_super_chat_value_usd_cents: self.super_chat_value_usd_cents,
+            super_chat_leaderboard,
+            spam_suppressed_count: self.spam_suppressed_count,
+            filtered_count: self.filtered_count,
+            queue_dropped_count: self.queue_dropped_count,
+            url_count: self.url_count,
+            keyword_matches: self.keyword_matches.clone(),
             word_frequency,
+            bigram_frequency,
+            language_distribution,
             rate_timeseries: rate_data,
             start_time: self.start_time,
             end_time: None,
             duration_secs: 0,
+            is_partial: false,
+        }
+    }
+
+    /// Get statistics for the last `window_secs` seconds of the session.
+    ///
+    /// The window ends at the end of the bucket holding the most recent message.
+    /// Buckets partially covered by the window are counted whole, and only the
+    /// buckets inside the window are visited.
+    pub fn rolling_window_stats(&self, window_secs: u64) -> RollingWindowStats {
+        let Some((current_start, current_count)) = self.current_bucket else {
+            return RollingWindowStats::default();
+        };
+        if window_secs == 0 {
+            return RollingWindowStats::default();
+        }
+
+        let bucket_duration = chrono::Duration::seconds(self.bucket_duration_secs as i64);
+        let window_start =
+            current_start + bucket_duration - chrono::Duration::seconds(window_secs as i64);
+        let in_window = |start: DateTime<Utc>| start + bucket_duration > window_start;
+
+        let mut messages_in_window = if in_window(current_start) {
+            current_count
+        } else {
+            0
+        };
+        messages_in_window += self
+            .rate_data
+            .iter()
+            .rev()
+            .take_while(|point| in_window(point.timestamp))
+            .map(|point| point.count)
+            .sum::<u64>();
+
+        let mut users: HashSet<&str> = HashSet::new();
+        let mut words: HashMap<&str, u64> = HashMap::new();
+        for bucket in self
+            .recent_buckets
+            .iter()
+            .rev()
+            .take_while(|bucket| in_window(bucket.start))
+        {
+            users.extend(bucket.users.iter().map(String::as_str));
+            for (word, count) in &bucket.words {
+                *words.entry(word.as_str()).or_insert(0) += count;
+            }
+        }
+
+        let mut top_words: Vec<_> = words.into_iter().collect();
+        top_words.sort_by(|(aw, a), (bw, b)| b.cmp(a).then_with(|| aw.cmp(bw)));
+        top_words.truncate(self.max_words);
+
+        RollingWindowStats {
+            messages_in_window,
+            unique_users_in_window: users.len() as u64,
+            top_words_in_window: top_words
+                .into_iter()
+                .map(|(word, count)| WordFrequency {
+                    word: word.to_string(),
+                    count,
+                })
+                .collect(),
         }
     }
 
@@ -560,18 +1595,103 @@ impl StatisticsAggregator {
                 self.max_top_talkers,
                 self.max_words,
                 self.bucket_duration_secs,
-            ),
+            )
+            .with_ngrams(self.ngram_size)
+            .with_language_detection(self.languages.is_some()),
         );
         prev.finalize(end_time)
     }
 
+    /// Fold in statistics of an earlier run of the same session, e.g. ones
+    /// persisted before a restart.
+    ///
+    /// Counts, top talkers and gifters, gift breakdowns, word frequencies,
+    /// language counts (when detecting languages) and rate points are added to the ones recorded so far, and the start time becomes the earlier of both.
+    pub fn merge_previous(&mut self, previous: &DanmuStatistics) {
+        self.total_count = self.total_count.saturating_add(previous.total_count);
+        self.chat_count = self.chat_count.saturating_add(previous.chat_count);
+        self.gift_count = self.gift_count.saturating_add(previous.gift_count);
+        self.total_gift_value = self
+            .total_gift_value
+            .saturating_add(previous.total_gift_value);
+        self.spam_suppressed_count = self
+            .spam_suppressed_count
+            .saturating_add(previous.spam_suppressed_count);
+        self.filtered_count = self.filtered_count.saturating_add(previous.filtered_count);
+        self.queue_dropped_count = self
+            .queue_dropped_count
+            .saturating_add(previous.queue_dropped_count);
+        self.url_count = self.url_count.saturating_add(previous.url_count);
+        for (pattern, count) in &previous.keyword_matches {
+            let total = self.keyword_matches.entry(pattern.clone()).or_insert(0);
+            *total = total.saturating_add(*count);
+        }
+        for talker in &previous.top_talkers {
+            if let Some(evicted) =
+                self.talker_hh
+                    .add(&talker.user_id, &talker.username, talker.message_count)
+            {
+                self.usernames.remove(&evicted);
+            }
+            // Earlier names only keep their rank through the talker's
+            // canonical name; the others are remembered without messages.
+            self.usernames
+                .add(&talker.user_id, &talker.username, talker.message_count);
+            for alias in previous
+                .username_aliases
+                .get(&talker.user_id)
+                .into_iter()
+                .flatten()
+            {
+                self.usernames.add(&talker.user_id, alias, 0);
+            }
+        }
+        for gifter in &previous.top_gifters {
+            self.gift_leaderboard.add(
+                &gifter.user_id,
+                &gifter.username,
+                gifter.total_value,
+                gifter.gift_count,
+            );
+        }
+        for gift in &previous.gift_breakdown {
+            self.gift_breakdown
+                .add(&gift.gift_name, gift.count, gift.total_value);
+        }
+        for word in &previous.word_frequency {
+            self.word_hh.add(&word.word, word.count);
+        }
+        if let Some(languages) = &mut self.languages {
+            for language in &previous.language_distribution {
+                languages.add(&language.language_code, language.count);
+            }
+        }
+
+        let mut rate_data: Vec<_> = previous
+            .rate_timeseries
+            .iter()
+            .cloned()
+            .chain(self.rate_data.drain(..))
+            .collect();
+        rate_data.sort_by_key(|point| point.timestamp);
+        let excess = rate_data.len().saturating_sub(self.max_rate_points);
+        self.rate_data = rate_data.into_iter().skip(excess).collect();
+
+        self.start_time = match (self.start_time, previous.start_time) {
+            (Some(current), Some(previous)) => Some(current.min(previous)),
+            (current, previous) => current.or(previous),
+        };
+    }
+
     /// Reset all counters and tracked state.
     pub fn reset(&mut self) {
         *self = Self::with_config(
             self.max_top_talkers,
             self.max_words,
             self.bucket_duration_secs,
-        );
+        )
+        .with_ngrams(self.ngram_size)
+        .with_language_detection(self.languages.is_some());
     }
 }
 
@@ -609,6 +1729,76 @@ mod tests {
     use chrono::TimeZone;
     use std::time::Instant;
 
+    #[test]
+    fn test_csv_reports() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 60);
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        agg.record_message("u1", "Alice", "hello world", false, start);
+        agg.record_message("u2", "Bob, \"the\" builder", "hello", false, start);
+        agg.record_message(
+            "u1",
+            "Alice",
+            "world",
+            false,
+            start + chrono::Duration::seconds(90),
+        );
+        let stats = agg.current_stats();
+
+        let reports = stats.to_csv_reports();
+        assert_eq!(reports.len(), 3);
+
+        let talkers: Vec<&str> = reports["top_talkers"].lines().collect();
+        assert_eq!(talkers[0], "user_id,username,message_count");
+        assert_eq!(talkers.len(), 1 + stats.top_talkers.len());
+        assert!(talkers.contains(&"u2,\"Bob, \"\"the\"\" builder\",1"));
+
+        let words: Vec<&str> = reports["word_frequency"].lines().collect();
+        assert_eq!(words[0], "word,count");
+        assert_eq!(words.len(), 1 + stats.word_frequency.len());
+
+        let rates: Vec<&str> = reports["rate_timeseries"].lines().collect();
+        assert_eq!(rates[0], "timestamp_utc,count");
+        assert_eq!(rates.len(), 3);
+        assert_eq!(rates[1], "2024-01-01T12:00:00Z,2");
+    }
+
+    #[test]
+    fn test_write_csv_reports() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-srec-danmu-csv-{}", uuid::Uuid::new_v4()));
+        let stats = DanmuStatistics {
+            word_frequency: vec![WordFrequency {
+                word: "hello".to_string(),
+                count: 2,
+            }],
+            ..Default::default()
+        };
+
+        let paths = stats.write_csv_reports(&dir).unwrap();
+
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "top_talkers.csv",
+                "word_frequency.csv",
+                "rate_timeseries.csv"
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&paths[0]).unwrap(),
+            "user_id,username,message_count\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&paths[1]).unwrap(),
+            "word,count\nhello,2\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_record_message() {
         let mut agg = StatisticsAggregator::new();
@@ -646,6 +1836,259 @@ mod tests {
         assert_eq!(stats.top_talkers[1].message_count, 3);
     }
 
+    #[test]
+    fn test_canonical_username_is_most_frequent() {
+        let mut agg = StatisticsAggregator::new();
+        let now = Utc::now();
+
+        agg.record_message("u1", "Old Name", "hi", false, now);
+        agg.record_message("u1", "New Name", "hi", false, now);
+        agg.record_message("u1", "New Name", "hi", false, now);
+        agg.record_message("u1", "Latest", "hi", false, now);
+        agg.record_message("u2", "Bob", "hi", false, now);
+
+        assert_eq!(agg.canonical_username("u1"), Some("New Name"));
+        assert_eq!(agg.canonical_username("u2"), Some("Bob"));
+        assert_eq!(agg.canonical_username("u3"), None);
+
+        let stats = agg.current_stats();
+        let u1 = stats
+            .top_talkers
+            .iter()
+            .find(|t| t.user_id == "u1")
+            .unwrap();
+        assert_eq!(u1.username, "New Name");
+        assert_eq!(
+            stats.username_aliases["u1"],
+            ["New Name", "Latest", "Old Name"]
+        );
+        assert!(!stats.username_aliases.contains_key("u2"));
+    }
+
+    #[test]
+    fn test_username_aliases_are_capped() {
+        let mut agg = StatisticsAggregator::new();
+        let now = Utc::now();
+
+        for _ in 0..3 {
+            agg.record_message("u1", "Main", "hi", false, now);
+        }
+        for i in 0..10 {
+            agg.record_message("u1", &format!("Alt{i}"), "hi", false, now);
+        }
+
+        let stats = agg.finalize(now);
+        let aliases = &stats.username_aliases["u1"];
+        assert_eq!(aliases.len(), MAX_USERNAME_ALIASES);
+        assert_eq!(aliases[0], "Main");
+        assert!(aliases.contains(&"Alt9".to_string()));
+        assert_eq!(stats.top_talkers[0].username, "Main");
+    }
+
+    #[test]
+    fn test_gift_value_and_top_gifters() {
+        let mut agg = StatisticsAggregator::with_config(2, 10, 10);
+        let now = Utc::now();
+
+        agg.record_gift("user1", "Alice", "rocket", 1, 500, now);
+        agg.record_gift("user2", "Bob", "flower", 1, 1, now);
+        agg.record_gift("user2", "Bob", "flower", 1, 1, now);
+        agg.record_gift("user3", "Carol", "car", 1, 100, now);
+        agg.record_message("user4", "Dave", "gift", true, now);
+        agg.record_message("user5", "Eve", "hi", false, now);
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.total_count, 6);
+        assert_eq!(stats.gift_count, 5);
+        assert_eq!(stats.chat_count, 1);
+        assert_eq!(stats.total_gift_value, 602);
+
+        assert_eq!(stats.top_gifters.len(), 2);
+        assert_eq!(stats.top_gifters[0].user_id, "user1");
+        assert_eq!(stats.top_gifters[0].username, "Alice");
+        assert_eq!(stats.top_gifters[0].total_value, 500);
+        assert_eq!(stats.top_gifters[0].gift_count, 1);
+        assert_eq!(stats.top_gifters[1].user_id, "user3");
+
+        let finalized = agg.finalize(now);
+        assert_eq!(finalized.total_gift_value, 602);
+        assert_eq!(finalized.top_gifters[0].total_value, 500);
+    }
+
+    #[test]
+    fn test_gift_breakdown_by_name() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10);
+        let now = Utc::now();
+
+        agg.record_gift("user1", "Alice", "flower", 10, 10, now);
+        agg.record_gift("user2", "Bob", "rocket", 1, 500, now);
+        agg.record_gift("user1", "Alice", "flower", 5, 5, now);
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.gift_count, 3);
+        assert_eq!(stats.total_gift_value, 515);
+        assert_eq!(stats.top_gifters[1].user_id, "user1");
+        assert_eq!(stats.top_gifters[1].gift_count, 15);
+
+        let breakdown = &stats.gift_breakdown;
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[0].gift_name, "rocket");
+        assert_eq!(breakdown[0].total_value, 500);
+        assert_eq!(breakdown[1].gift_name, "flower");
+        assert_eq!(breakdown[1].count, 15);
+        assert_eq!(breakdown[1].total_value, 15);
+    }
+
+    #[test]
+    fn test_gift_breakdown_is_bounded() {
+        let mut agg = StatisticsAggregator::new();
+        let now = Utc::now();
+
+        for i in 0..MAX_GIFT_KINDS + 10 {
+            agg.record_gift("user1", "Alice", &format!("gift{i}"), 1, 1, now);
+        }
+        agg.record_gift("user1", "Alice", "gift0", 1, 100, now);
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.gift_breakdown.len(), MAX_GIFT_KINDS);
+        assert_eq!(stats.gift_breakdown[0].gift_name, "gift0");
+        assert_eq!(stats.gift_breakdown[0].total_value, 101);
+        assert_eq!(stats.total_gift_value, (MAX_GIFT_KINDS + 10) as u64 + 100);
+    }
+
+    #[test]
+    fn test_super_chat_leaderboard_ranked_by_value() {
+        let mut agg = StatisticsAggregator::with_config(2, 10, 10);
+        let now = Utc::now();
+
+        agg.record_super_chat("user1", "Alice", "first", 500, now);
+        agg.record_super_chat("user2", "Bob", "hi", 300, now);
+        agg.record_super_chat("user2", "Bob", "hi again", 400, now);
+        agg.record_super_chat("user3", "Carol", "hello", 100, now);
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.total_count, 4);
+        assert_eq!(stats.chat_count, 4);
+        assert_eq!(stats.total_super_chat_count, 4);
+        assert_eq!(stats.total_super_chat_value_usd_cents, 1300);
+
+        assert_eq!(stats.super_chat_leaderboard.len(), 2);
+        assert_eq!(stats.super_chat_leaderboard[0].user_id, "user2");
+        assert_eq!(stats.super_chat_leaderboard[0].username, "Bob");
+        assert_eq!(stats.super_chat_leaderboard[0].total_value_usd_cents, 700);
+        assert_eq!(stats.super_chat_leaderboard[0].count, 2);
+        assert_eq!(stats.super_chat_leaderboard[1].user_id, "user1");
+        assert!(stats.top_gifters.is_empty());
+
+        let finalized = agg.finalize(now);
+        assert_eq!(finalized.total_super_chat_value_usd_cents, 1300);
+        assert_eq!(finalized.super_chat_leaderboard[0].user_id, "user2");
+    }
+
+    #[test]
+    fn test_spam_suppressed_count() {
+        let mut agg = StatisticsAggregator::new();
+        agg.record_message("user1", "User", "hello", false, Utc::now());
+        agg.record_spam_suppressed();
+        agg.record_spam_suppressed();
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.total_count, 1);
+        assert_eq!(stats.spam_suppressed_count, 2);
+    }
+
+    #[test]
+    fn test_filtered_count() {
+        let mut agg = StatisticsAggregator::new();
+        agg.record_message("user1", "User", "hello", false, Utc::now());
+        agg.record_filtered();
+
+        let stats = agg.checkpoint(Utc::now());
+        assert_eq!(stats.total_count, 1);
+        assert_eq!(stats.filtered_count, 1);
+        assert_eq!(agg.current_stats().filtered_count, 0);
+    }
+
+    #[test]
+    fn test_queue_dropped_count() {
+        let mut agg = StatisticsAggregator::new();
+        agg.record_queue_dropped(3);
+        agg.record_queue_dropped(2);
+        let previous = agg.checkpoint(Utc::now());
+        assert_eq!(previous.total_count, 0);
+        assert_eq!(previous.queue_dropped_count, 5);
+
+        agg.record_queue_dropped(1);
+        agg.merge_previous(&previous);
+        assert_eq!(agg.current_stats().queue_dropped_count, 6);
+    }
+
+    #[test]
+    fn test_url_count() {
+        let mut agg = StatisticsAggregator::new();
+        let now = Utc::now();
+        agg.record_message(
+            "u1",
+            "User",
+            "watch https://example.com/clip now",
+            false,
+            now,
+        );
+        agg.record_message(
+            "u2",
+            "User",
+            "see www.example.org and bit.ly/abc",
+            false,
+            now,
+        );
+        agg.record_message("u3", "User", "hello.world is not a link", false, now);
+        agg.record_message("u4", "User", "plain message", false, now);
+        agg.record_message("u5", "User", "https://gift.example.com", true, now);
+
+        let stats = agg.current_stats();
+        assert_eq!(stats.url_count, 2);
+        assert_eq!(stats.chat_count, 4);
+        assert!((stats.url_ratio() - 0.5).abs() < f64::EPSILON);
+        assert!(
+            stats
+                .word_frequency
+                .iter()
+                .all(|w| !w.word.contains("example") && w.word != "ly")
+        );
+        assert!(stats.word_frequency.iter().any(|w| w.word == "watch"));
+    }
+
+    #[test]
+    fn test_url_ratio_without_chat() {
+        assert_eq!(DanmuStatistics::default().url_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_keyword_match_counts() {
+        let mut agg = StatisticsAggregator::new();
+        agg.record_keyword_match("抽奖");
+        agg.record_keyword_match("抽奖");
+        agg.record_keyword_match("giveaway");
+
+        let stats = agg.finalize(Utc::now());
+        assert_eq!(stats.keyword_matches["抽奖"], 2);
+        assert_eq!(stats.keyword_matches["giveaway"], 1);
+        assert_eq!(stats.total_count, 0);
+    }
+
+    #[test]
+    fn test_gift_leaderboard_evicts_lowest_value() {
+        let mut leaderboard = GiftLeaderboard::new(2);
+        leaderboard.add("a", "A", 10, 1);
+        leaderboard.add("b", "B", 3, 1);
+        leaderboard.add("c", "C", 5, 1);
+
+        let top = leaderboard.top_n(2);
+        assert_eq!(top[0].user_id, "a");
+        assert_eq!(top[1].user_id, "c");
+        assert_eq!(top[1].total_value, 8);
+    }
+
     #[test]
     fn test_word_frequency() {
         let mut agg = StatisticsAggregator::with_config(10, 10, 10);
@@ -662,6 +2105,166 @@ mod tests {
         assert_eq!(hello.unwrap().count, 3);
     }
 
+    #[test]
+    fn test_bigram_frequency() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10).with_ngrams(2);
+        let now = Utc::now();
+
+        agg.record_message("user1", "User", "hello world hello", false, now);
+        agg.record_message("user2", "User", "hello the world", false, now);
+
+        let stats = agg.current_stats();
+        let count = |bigram: &str| {
+            stats
+                .bigram_frequency
+                .iter()
+                .find(|w| w.word == bigram)
+                .map(|w| w.count)
+        };
+        // The stop word "the" is skipped, so "hello world" is counted twice.
+        assert_eq!(count("hello world"), Some(2));
+        assert_eq!(count("world hello"), Some(1));
+        assert_eq!(count("hello the"), None);
+        assert_eq!(stats.bigram_frequency.len(), 2);
+
+        let stats = agg.checkpoint(now);
+        assert_eq!(stats.bigram_frequency[0].word, "hello world");
+        agg.record_message("user1", "User", "hello world", false, now);
+        assert_eq!(agg.current_stats().bigram_frequency.len(), 1);
+    }
+
+    #[test]
+    fn test_language_distribution() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10).with_language_detection(true);
+        let now = Utc::now();
+
+        agg.record_message(
+            "user1",
+            "User",
+            "what a great stream, thank you for playing",
+            false,
+            now,
+        );
+        agg.record_message(
+            "user2",
+            "User",
+            "this is the best game I have seen today",
+            false,
+            now,
+        );
+        agg.record_message(
+            "user3",
+            "User",
+            "今天的直播真的非常好看，主播加油",
+            false,
+            now,
+        );
+        // Too short to detect, and gift names are never detected.
+        agg.record_message("user4", "User", "hello", false, now);
+        agg.record_message("user5", "User", "Super Rocket Launcher", true, now);
+
+        let stats = agg.current_stats();
+        let languages: Vec<_> = stats
+            .language_distribution
+            .iter()
+            .map(|language| (language.language_code.as_str(), language.count))
+            .collect();
+        assert_eq!(languages, [("en", 2), ("zh", 1)]);
+        let fractions: f64 = stats
+            .language_distribution
+            .iter()
+            .map(|language| language.fraction)
+            .sum();
+        assert!((fractions - 1.0).abs() < 1e-9);
+
+        let mut restarted =
+            StatisticsAggregator::with_config(10, 10, 10).with_language_detection(true);
+        restarted.merge_previous(&stats);
+        assert_eq!(restarted.current_stats().language_distribution[0].count, 2);
+
+        let stats = agg.checkpoint(now);
+        assert_eq!(stats.language_distribution.len(), 2);
+        agg.record_message(
+            "user3",
+            "User",
+            "今天的直播真的非常好看，主播加油",
+            false,
+            now,
+        );
+        assert_eq!(agg.current_stats().language_distribution.len(), 1);
+
+        let mut disabled = StatisticsAggregator::with_config(10, 10, 10);
+        disabled.record_message("user1", "User", "this is the best game today", false, now);
+        assert!(disabled.current_stats().language_distribution.is_empty());
+    }
+
+    #[test]
+    fn test_language_counts_are_bounded() {
+        let mut languages = LanguageCounts::default();
+        for i in 0..MAX_LANGUAGES + 5 {
+            languages.add(&format!("l{i}"), 1);
+        }
+        languages.add("l0", 2);
+
+        let distribution = languages.distribution();
+        assert_eq!(distribution.len(), MAX_LANGUAGES);
+        assert_eq!(distribution[0].language_code, "l0");
+        assert_eq!(distribution[0].count, 3);
+    }
+
+    #[test]
+    fn test_bigram_frequency_is_bounded() {
+        let mut agg = StatisticsAggregator::with_config(10, 3, 10).with_ngrams(2);
+        let now = Utc::now();
+
+        agg.record_message("user1", "User", "aa bb cc dd ee ff gg hh", false, now);
+
+        assert!(agg.ngram_hh.as_ref().unwrap().counters.len() <= 3);
+        assert_eq!(agg.current_stats().bigram_frequency.len(), 3);
+        assert!(
+            StatisticsAggregator::new()
+                .current_stats()
+                .bigram_frequency
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_merge_previous() {
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let mut before = StatisticsAggregator::with_config(10, 10, 10);
+        before.record_message("user1", "Alice", "hello world", false, base);
+        before.record_message("user1", "Alice", "hello", false, base);
+        before.record_gift("user1", "Alice", "flower", 2, 20, base);
+        let previous = before.finalize(base + chrono::Duration::seconds(60));
+
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10);
+        let later = base + chrono::Duration::seconds(120);
+        agg.record_message("user2", "Bob", "hello", false, later);
+        agg.record_gift("user1", "Alice", "flower", 1, 10, later);
+        agg.merge_previous(&previous);
+
+        let stats = agg.finalize(later + chrono::Duration::seconds(5));
+        assert_eq!(stats.total_count, 5);
+        assert_eq!(stats.chat_count, 3);
+        assert_eq!(stats.start_time, Some(base));
+        assert_eq!(stats.top_talkers[0].user_id, "user1");
+        assert_eq!(stats.top_talkers[0].message_count, 4);
+        assert_eq!(stats.total_gift_value, 30);
+        assert_eq!(stats.top_gifters[0].total_value, 30);
+        assert_eq!(stats.top_gifters[0].gift_count, 3);
+        assert_eq!(stats.gift_breakdown[0].count, 3);
+        assert_eq!(stats.gift_breakdown[0].total_value, 30);
+        let hello = stats.word_frequency.iter().find(|w| w.word == "hello");
+        assert_eq!(hello.map(|w| w.count), Some(3));
+        let rate: Vec<_> = stats
+            .rate_timeseries
+            .iter()
+            .map(|point| (point.timestamp, point.count))
+            .collect();
+        assert_eq!(rate, vec![(base, 3), (later, 2)]);
+    }
+
     #[test]
     fn test_rate_timeseries() {
         let mut agg = StatisticsAggregator::with_config(10, 10, 10);
@@ -694,6 +2297,131 @@ mod tests {
         assert_eq!(stats.rate_timeseries[1].count, 1); // Second bucket
     }
 
+    #[test]
+    fn test_max_rate_points_cap() {
+        let mut agg = StatisticsAggregator::with_config_full(10, 10, 10, Some(3));
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        for bucket in 0..6 {
+            agg.record_message(
+                "user1",
+                "User",
+                "msg",
+                false,
+                base + chrono::Duration::seconds(bucket * 10),
+            );
+        }
+        let stats = agg.finalize(base + chrono::Duration::seconds(60));
+
+        // The three most recent closed buckets, plus the open one.
+        assert_eq!(stats.rate_timeseries.len(), 4);
+        assert_eq!(
+            stats.rate_timeseries[0].timestamp,
+            base + chrono::Duration::seconds(20)
+        );
+        assert_eq!(
+            StatisticsAggregator::with_config_full(10, 10, 10, None).max_rate_points,
+            StatisticsAggregator::with_config(10, 10, 10).max_rate_points
+        );
+    }
+
+    #[test]
+    fn test_memory_estimate_grows_with_fill() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10);
+        let empty = agg.memory_estimate_bytes();
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        for i in 0..200 {
+            agg.record_message(
+                &format!("user{i}"),
+                "User",
+                &format!("word{i} another{i}"),
+                false,
+                base + chrono::Duration::seconds(i),
+            );
+        }
+        let filled = agg.memory_estimate_bytes();
+
+        // The count-min sketch is allocated up front.
+        assert!(empty > 0);
+        assert!(filled > empty);
+    }
+
+    #[test]
+    fn test_rolling_window_stats_boundaries() {
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10);
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let at = |secs: i64| base + chrono::Duration::seconds(secs);
+
+        // Buckets: [0, 10) x2, [50, 60) x1, [60, 70) x2, [110, 120) x1.
+        agg.record_message("user1", "User", "alpha beta", false, at(0));
+        agg.record_message("user2", "User", "alpha", false, at(9));
+        agg.record_message("user3", "User", "beta gamma", false, at(59));
+        agg.record_message("user1", "User", "gamma", false, at(60));
+        agg.record_message("user4", "User", "gift", true, at(65));
+        agg.record_message("user3", "User", "gamma delta", false, at(115));
+
+        // The window ends at 120: a 60s window starts at 60, excluding the [50, 60) bucket.
+        let stats = agg.rolling_window_stats(60);
+        assert_eq!(stats.messages_in_window, 3);
+        assert_eq!(stats.unique_users_in_window, 3);
+        assert_eq!(stats.top_words_in_window[0].word, "gamma");
+        assert_eq!(stats.top_words_in_window[0].count, 2);
+        assert_eq!(stats.top_words_in_window[1].word, "delta");
+        assert_eq!(stats.top_words_in_window.len(), 2);
+
+        // One second more reaches back into the [50, 60) bucket.
+        let stats = agg.rolling_window_stats(61);
+        assert_eq!(stats.messages_in_window, 4);
+        assert_eq!(stats.unique_users_in_window, 3);
+        assert_eq!(stats.top_words_in_window[0].count, 3);
+
+        // Only the current bucket.
+        let stats = agg.rolling_window_stats(10);
+        assert_eq!(stats.messages_in_window, 1);
+        assert_eq!(stats.unique_users_in_window, 1);
+
+        // The whole session.
+        let stats = agg.rolling_window_stats(120);
+        assert_eq!(stats.messages_in_window, 6);
+        assert_eq!(stats.unique_users_in_window, 4);
+        let alpha = stats
+            .top_words_in_window
+            .iter()
+            .find(|w| w.word == "alpha")
+            .unwrap();
+        assert_eq!(alpha.count, 2);
+
+        assert_eq!(agg.rolling_window_stats(0).messages_in_window, 0);
+    }
+
+    #[test]
+    fn test_rolling_window_stats_empty_and_retention() {
+        let agg = StatisticsAggregator::new();
+        let stats = agg.rolling_window_stats(60);
+        assert_eq!(stats.messages_in_window, 0);
+        assert_eq!(stats.unique_users_in_window, 0);
+        assert!(stats.top_words_in_window.is_empty());
+
+        let mut agg = StatisticsAggregator::with_config(10, 10, 10);
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        agg.record_message("old", "User", "stale", false, base);
+        agg.record_message(
+            "new",
+            "User",
+            "fresh",
+            false,
+            base + chrono::Duration::hours(1),
+        );
+
+        // Message counts cover the whole window; users and words only the retained buckets.
+        let stats = agg.rolling_window_stats(2 * 60 * 60);
+        assert_eq!(stats.messages_in_window, 2);
+        assert_eq!(stats.unique_users_in_window, 1);
+        assert_eq!(stats.top_words_in_window.len(), 1);
+        assert_eq!(agg.recent_buckets.len(), 1);
+    }
+
     #[test]
     fn test_finalize() {
         let mut agg = StatisticsAggregator::new();
@@ -724,6 +2452,50 @@ mod tests {
         assert_eq!(stats.gift_count, 2);
     }
 
+    #[test]
+    fn test_record_message_batch_matches_single_calls() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let owned: Vec<(String, String, bool, DateTime<Utc>)> = (0..200)
+            .map(|i| {
+                (
+                    format!("user{}", i % 7),
+                    format!("word{} word{} shared https://example.com", i % 13, i % 3),
+                    i % 5 == 0,
+                    start + chrono::Duration::seconds(i * 7),
+                )
+            })
+            .collect();
+        let batch: Vec<BatchMessage<'_>> = owned
+            .iter()
+            .map(|(user_id, content, is_gift, timestamp)| BatchMessage {
+                user_id,
+                username: user_id,
+                content,
+                is_gift: *is_gift,
+                timestamp: *timestamp,
+            })
+            .collect();
+
+        let mut single = StatisticsAggregator::new().with_ngrams(2);
+        for m in &batch {
+            single.record_message(m.user_id, m.username, m.content, m.is_gift, m.timestamp);
+        }
+        let mut batched = StatisticsAggregator::new().with_ngrams(2);
+        batched.record_message_batch(&batch[..120]);
+        batched.record_message_batch(&[]);
+        batched.record_message_batch(&batch[120..]);
+
+        let end = start + chrono::Duration::hours(1);
+        let single = single.finalize(end);
+        let batched = batched.finalize(end);
+        assert_eq!(batched.start_time, Some(start));
+        assert_eq!(batched.total_count, 200);
+        assert_eq!(
+            serde_json::to_value(&batched).unwrap(),
+            serde_json::to_value(&single).unwrap()
+        );
+    }
+
     #[test]
     fn test_heavy_hitter_high_cardinality_bounds() {
         let mut agg = StatisticsAggregator::with_config(10, 50, 10);
@@ -743,6 +2515,7 @@ mod tests {
 
         // Internal heavy-hitter structures must stay bounded.
         assert!(agg.talker_hh.counters.len() <= agg.talker_hh.capacity);
+        assert!(agg.usernames.counts.len() <= agg.talker_hh.capacity);
         assert!(agg.word_hh.counters.len() <= agg.word_hh.capacity);
         assert_eq!(agg.total_count as usize, total_messages);
 
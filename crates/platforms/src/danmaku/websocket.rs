@@ -11,20 +11,23 @@ use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore, mpsc};
 use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Response;
 use tokio_tungstenite::tungstenite::http::HeaderMap;
 use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::tungstenite::http::header;
 use tokio_tungstenite::{
-    Connector, MaybeTlsStream, WebSocketStream, connect_async_tls_with_config,
-    tungstenite::protocol::Message,
+    Connector, MaybeTlsStream, WebSocketStream, client_async_tls_with_config,
+    connect_async_tls_with_config, tungstenite::protocol::Message,
 };
 use tracing::{debug, error, info, trace, warn};
 use url::Url;
 
 use crate::danmaku::ConnectionConfig;
-use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::error::{DanmakuError, DanmuErrorCode, Result};
 use crate::danmaku::event::DanmuItem;
 use crate::danmaku::provider::{DanmuConnection, DanmuProvider};
+use crate::danmaku::proxy::ProxyConfig;
 use crate::extractor::utils::merge_cookie_headers;
 
 const MAX_ACTIVE_CONNECTIONS: usize = 1024;
@@ -172,6 +175,74 @@ fn connector_for_url(url: &str) -> Connector {
     rustls_connector()
 }
 
+type WsConnectResult = std::result::Result<
+    (WebSocketStream<MaybeTlsStream<TcpStream>>, Response),
+    tokio_tungstenite::tungstenite::Error,
+>;
+
+/// Opens a WebSocket connection for `request`, giving up after `timeout` when set.
+async fn connect_websocket<R>(
+    request: R,
+    url: &str,
+    proxy: Option<&ProxyConfig>,
+    timeout: Option<Duration>,
+) -> WsConnectResult
+where
+    R: IntoClientRequest + Unpin,
+{
+    let Some(timeout) = timeout else {
+        return connect_websocket_via(request, url, proxy).await;
+    };
+    tokio::time::timeout(timeout, connect_websocket_via(request, url, proxy))
+        .await
+        .unwrap_or_else(|_| {
+            Err(tokio_tungstenite::tungstenite::Error::Io(
+                std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("WebSocket connect timed out after {timeout:?}"),
+                ),
+            ))
+        })
+}
+
+/// Opens a WebSocket connection for `request`, tunnelled through `proxy` when set.
+async fn connect_websocket_via<R>(
+    request: R,
+    url: &str,
+    proxy: Option<&ProxyConfig>,
+) -> WsConnectResult
+where
+    R: IntoClientRequest + Unpin,
+{
+    let connector = connector_for_url(url);
+    let Some(proxy) = proxy else {
+        return connect_async_tls_with_config(request, None, true, Some(connector)).await;
+    };
+
+    let request = request.into_client_request()?;
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .ok_or(tokio_tungstenite::tungstenite::Error::Url(
+            tokio_tungstenite::tungstenite::error::UrlError::NoHostName,
+        ))?
+        .to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        });
+
+    debug!(%host, port, proxy_host = %proxy.host, proxy_port = proxy.port, "Connecting to WebSocket through proxy");
+    let stream = proxy
+        .connect(&host, port)
+        .await
+        .map_err(|e| tokio_tungstenite::tungstenite::Error::Io(std::io::Error::other(e)))?;
+    client_async_tls_with_config(request, stream, None, Some(connector)).await
+}
+
 /// Creates isolated protocol state for WebSocket danmaku connections.
 ///
 /// Factories are shared by the provider and must not reuse connection state. Each call to
@@ -187,9 +258,24 @@ pub trait DanmuProtocolFactory: Send + Sync + 'static {
     /// Returns whether `url` belongs to this platform.
     fn supports_url(&self, url: &str) -> bool;
 
+    /// Returns the patterns of the URLs accepted by [`supports_url`](Self::supports_url).
+    fn url_patterns(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Returns whether connections need cookies of a logged-in account.
+    fn requires_cookies(&self) -> bool {
+        false
+    }
+
     /// Extracts the platform room identifier from `url`.
     fn extract_room_id(&self, url: &str) -> Option<String>;
 
+    /// Returns whether `room_id` looks like a room identifier of this platform.
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        !room_id.trim().is_empty()
+    }
+
     /// Creates fresh state for one connection or reconnect attempt.
     fn create_protocol(&self) -> Self::Protocol;
 }
@@ -203,12 +289,17 @@ pub trait DanmuProtocolFactory: Send + Sync + 'static {
 pub struct DanmuProtocolOutput {
     items: Vec<DanmuItem>,
     outbound: Vec<Message>,
+    reconnect: bool,
 }
 
 impl DanmuProtocolOutput {
     /// Creates output containing decoded `items` and outbound WebSocket frames.
     pub fn new(items: Vec<DanmuItem>, outbound: Vec<Message>) -> Self {
-        Self { items, outbound }
+        Self {
+            items,
+            outbound,
+            reconnect: false,
+        }
     }
 
     /// Creates output containing only decoded danmaku items.
@@ -221,6 +312,20 @@ impl DanmuProtocolOutput {
         Self::new(Vec::new(), outbound)
     }
 
+    /// Asks the runner to drop this connection and reconnect immediately.
+    ///
+    /// Outbound frames and items are still delivered first. Use this for server
+    /// notices announcing an imminent disconnect, such as Twitch's `RECONNECT`.
+    pub fn with_reconnect(mut self) -> Self {
+        self.reconnect = true;
+        self
+    }
+
+    /// Returns whether the protocol asked for an immediate reconnect.
+    pub fn reconnect_requested(&self) -> bool {
+        self.reconnect
+    }
+
     /// Separates decoded items from outbound WebSocket frames.
     pub fn into_parts(self) -> (Vec<DanmuItem>, Vec<Message>) {
         (self.items, self.outbound)
@@ -394,6 +499,10 @@ pub struct WebSocketProviderConfig {
     pub max_reconnect_attempts: u32,
     pub base_reconnect_delay_ms: u64,
     pub max_reconnect_delay_ms: u64,
+    /// Timeout for establishing the WebSocket connection (no timeout when unset).
+    pub connect_timeout: Option<Duration>,
+    /// Overrides the protocol's heartbeat interval when set.
+    pub heartbeat_interval: Option<Duration>,
 }
 
 impl Default for WebSocketProviderConfig {
@@ -402,6 +511,23 @@ impl Default for WebSocketProviderConfig {
             max_reconnect_attempts: 10,
             base_reconnect_delay_ms: 1000,
             max_reconnect_delay_ms: 60000,
+            connect_timeout: None,
+            heartbeat_interval: None,
+        }
+    }
+}
+
+/// Inserts `extra` into `headers`, replacing headers with the same name.
+fn apply_extra_headers(headers: &mut HeaderMap, extra: &HashMap<String, String>) {
+    for (name, value) in extra {
+        match (
+            header::HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => warn!(header = %name, "Invalid extra WebSocket header; skipping"),
         }
     }
 }
@@ -439,6 +565,8 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
         let room_id_owned = room_id.to_string();
         let cookies = config.cookies;
         let extras = config.extras;
+        let proxy = config.proxy;
+        let extra_headers = config.extra_headers;
         let is_connected_clone = is_connected.clone();
         let reconnect_count_clone = reconnect_count.clone();
 
@@ -506,10 +634,18 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
                                 headers.remove(header::COOKIE);
                             }
 
+                            if let Some(extra) = extra_headers.as_ref() {
+                                apply_extra_headers(&mut headers, extra);
+                            }
+
                             let connect_result = if headers.is_empty() {
-                                let connector = connector_for_url(&url);
-                                connect_async_tls_with_config(&url, None, true, Some(connector))
-                                    .await
+                                connect_websocket(
+                                    &url,
+                                    &url,
+                                    proxy.as_ref(),
+                                    ws_config.connect_timeout,
+                                )
+                                .await
                             } else {
                                 use tokio_tungstenite::tungstenite::handshake::client::generate_key;
                                 use tokio_tungstenite::tungstenite::http::Request;
@@ -549,12 +685,11 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
                                         for (name, value) in headers.iter() {
                                             request.headers_mut().insert(name, value.clone());
                                         }
-                                        let connector = connector_for_url(&url);
-                                        connect_async_tls_with_config(
+                                        connect_websocket(
                                             request,
-                                            None,
-                                            true,
-                                            Some(connector),
+                                            &url,
+                                            proxy.as_ref(),
+                                            ws_config.connect_timeout,
                                         )
                                         .await
                                     }
@@ -626,7 +761,9 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
                 // Main loop: read/write/heartbeat
                 if let Some((mut stream, mut protocol)) = current_connection.take() {
                     let heartbeat_enabled = protocol.heartbeat_message().is_some();
-                    let heartbeat_interval = protocol.heartbeat_interval();
+                    let heartbeat_interval = ws_config
+                        .heartbeat_interval
+                        .unwrap_or_else(|| protocol.heartbeat_interval());
                     let mut heartbeat_timer = tokio::time::interval(heartbeat_interval);
                     heartbeat_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
                     loop {
@@ -648,6 +785,7 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
                                     Some(Ok(msg)) => {
                                         match protocol.decode_message(&msg, &room_id_owned).await {
                                             Ok(output) => {
+                                                let reconnect = output.reconnect_requested();
                                                 let (items, outbound) = output.into_parts();
                                                 let mut response_failed = false;
                                                 for response in outbound {
@@ -665,6 +803,13 @@ impl<F: DanmuProtocolFactory> WebSocketDanmuProvider<F> {
                                                         return;
                                                     }
                                                 }
+                                                if reconnect {
+                                                    debug!("Server requested reconnect for {}", room_id_owned);
+                                                    if let Err(error) = stream.close(None).await {
+                                                        debug!(%error, "Failed to close WebSocket before reconnect");
+                                                    }
+                                                    break; // Reconnect
+                                                }
                                             }
                                             Err(e) => {
                                                 warn!("Failed to decode message: {}", e);
@@ -726,10 +871,13 @@ impl<F: DanmuProtocolFactory> DanmuProvider for WebSocketDanmuProvider<F> {
             .clone()
             .try_acquire_owned()
             .map_err(|_| {
-                DanmakuError::connection(format!(
-                    "Too many active connections (max {})",
-                    MAX_ACTIVE_CONNECTIONS
-                ))
+                DanmakuError::connection_failed(
+                    DanmuErrorCode::RateLimited,
+                    format!(
+                        "Too many active connections (max {})",
+                        MAX_ACTIVE_CONNECTIONS
+                    ),
+                )
             })?;
 
         let connection_id = format!("{}-{}-{}", self.platform(), room_id, uuid::Uuid::new_v4());
@@ -810,15 +958,45 @@ impl<F: DanmuProtocolFactory> DanmuProvider for WebSocketDanmuProvider<F> {
         self.factory.supports_url(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        self.factory.url_patterns()
+    }
+
+    fn requires_cookies(&self) -> bool {
+        self.factory.requires_cookies()
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         self.factory.extract_room_id(url)
     }
+
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        self.factory.validate_room_id(room_id)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_apply_extra_headers_replaces_and_skips_invalid() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://a.example"),
+        );
+        let extra = HashMap::from([
+            ("Origin".to_string(), "https://b.example".to_string()),
+            ("bad header".to_string(), "x".to_string()),
+        ]);
+
+        apply_extra_headers(&mut headers, &extra);
+
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[header::ORIGIN], "https://b.example");
+    }
+
     #[test]
     fn test_merge_cookie_headers_merges_and_overrides() {
         let base = "a=1; b=2";
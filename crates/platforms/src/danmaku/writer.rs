@@ -23,12 +23,16 @@
 //! - `pool`: Danmu pool (0=normal, 1=subtitle, 2=special)
 //! - `uid_crc32`: CRC32 hash of the sender's user ID
 //! - `row_id`: Row ID for ordering (uses message count)
+//!
+//! The root element, namespace, XML declaration and metadata layout can be
+//! customized with [`DanmuXmlFormat`]; the default reproduces the format above.
 
 use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
 
 use crate::danmaku::error::Result;
 use crate::danmaku::message::{DanmuMessage, DanmuType};
@@ -42,6 +46,44 @@ const DEFAULT_COLOR: u32 = 16777215;
 /// Default danmu pool (normal pool).
 const DEFAULT_POOL: u8 = 0;
 
+/// How message metadata is laid out in the XML output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeStyle {
+    /// Metadata is written as attributes of the message element,
+    /// e.g. `<d p="..." user="...">text</d>`.
+    #[default]
+    Inline,
+    /// Metadata is written as child elements of the message element, with the
+    /// message text in a `<text>` child, e.g. `<d><p>...</p><user>...</user><text>text</text></d>`.
+    Elements,
+}
+
+/// Layout of the danmu XML segment files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DanmuXmlFormat {
+    /// Name of the root element wrapping all messages.
+    pub root_element: String,
+    /// Optional default namespace, written as an `xmlns` attribute on the root element.
+    pub namespace: Option<String>,
+    /// Whether to write the `<?xml ...?>` declaration at the top of the file.
+    pub include_header: bool,
+    /// How message metadata is written.
+    pub attribute_style: AttributeStyle,
+}
+
+impl Default for DanmuXmlFormat {
+    fn default() -> Self {
+        Self {
+            root_element: "i".to_string(),
+            namespace: None,
+            include_header: true,
+            attribute_style: AttributeStyle::Inline,
+        }
+    }
+}
+
 /// XML writer for danmu messages.
 ///
 /// This writer creates XML files in Bilibili-compatible format suitable for
@@ -60,13 +102,19 @@ const DEFAULT_POOL: u8 = 0;
 /// ```
 pub struct XmlDanmuWriter {
     path: PathBuf,
-    file: Option<File>,
+    file: Option<BufWriter<File>>,
     message_count: u64,
+    /// Bytes handed to the file so far, including buffered ones.
+    bytes_written: u64,
     /// The start time of the current segment.
     /// Timestamps are written as second offsets from this time.
     segment_start_time: DateTime<Utc>,
     /// Optional header comments (metadata).
     header_comments: Vec<String>,
+    /// Layout of the written XML.
+    format: DanmuXmlFormat,
+    /// Whether each batch of messages is flushed to the file once written.
+    auto_flush: bool,
 }
 
 impl XmlDanmuWriter {
@@ -90,14 +138,34 @@ impl XmlDanmuWriter {
         path: &Path,
         segment_start_time: DateTime<Utc>,
         header_comments: Vec<String>,
+    ) -> Result<Self> {
+        Self::with_format(
+            path,
+            segment_start_time,
+            header_comments,
+            DanmuXmlFormat::default(),
+        )
+        .await
+    }
+
+    /// Create a new XML writer with a specific segment start time, header comments
+    /// and output layout.
+    pub async fn with_format(
+        path: &Path,
+        segment_start_time: DateTime<Utc>,
+        header_comments: Vec<String>,
+        format: DanmuXmlFormat,
     ) -> Result<Self> {
         let file = File::create(path).await?;
         let mut writer = Self {
             path: path.to_path_buf(),
-            file: Some(file),
+            file: Some(BufWriter::new(file)),
             message_count: 0,
+            bytes_written: 0,
             segment_start_time,
             header_comments,
+            format,
+            auto_flush: true,
         };
 
         // Write XML header
@@ -116,6 +184,51 @@ impl XmlDanmuWriter {
         self.message_count
     }
 
+    /// Get the number of bytes written so far, including the header and
+    /// messages still in the write buffer.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Set whether each batch of messages is flushed to the file once written
+    /// (the default).
+    ///
+    /// Without it, messages reach the file when [`Self::flush`] is called or
+    /// the write buffer fills up.
+    pub fn set_auto_flush(&mut self, auto_flush: bool) {
+        self.auto_flush = auto_flush;
+    }
+
+    /// Flush buffered messages to the file, and with `sync` also to disk.
+    ///
+    /// Messages are buffered whole, so the file then ends with a complete
+    /// element and only lacks the closing root tag written by
+    /// [`Self::finalize`].
+    pub async fn flush(&mut self, sync: bool) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush().await?;
+            if sync {
+                file.get_ref().sync_data().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Continue appending to the file at `path`, after the file written so
+    /// far was moved there.
+    ///
+    /// Buffered messages must be flushed before the file is moved. Does
+    /// nothing once the writer is finalized.
+    pub async fn reopen_at(&mut self, path: &Path) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let file = OpenOptions::new().append(true).open(path).await?;
+        self.file = Some(BufWriter::new(file));
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
     /// Get the segment start time.
     pub fn segment_start_time(&self) -> DateTime<Utc> {
         self.segment_start_time
@@ -123,14 +236,25 @@ impl XmlDanmuWriter {
 
     async fn write_header(&mut self) -> Result<()> {
         if let Some(file) = &mut self.file {
-            file.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")
-                .await?;
+            let mut header = String::new();
+            if self.format.include_header {
+                header.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            }
             for comment in &self.header_comments {
-                let comment_xml = format!("<!-- {} -->\n", comment.replace("-->", "--"));
-                file.write_all(comment_xml.as_bytes()).await?;
+                header.push_str(&format!("<!-- {} -->\n", comment.replace("-->", "--")));
             }
             self.header_comments.clear();
-            file.write_all(b"<i>\n").await?;
+            match &self.format.namespace {
+                Some(namespace) => header.push_str(&format!(
+                    "<{} xmlns=\"{}\">\n",
+                    self.format.root_element,
+                    escape_xml(namespace)
+                )),
+                None => header.push_str(&format!("<{}>\n", self.format.root_element)),
+            }
+            file.write_all(header.as_bytes()).await?;
+            file.flush().await?;
+            self.bytes_written += header.len() as u64;
         }
         Ok(())
     }
@@ -150,65 +274,93 @@ impl XmlDanmuWriter {
     /// - `row_id`: Message sequence number
     /// - `user`: Username of the sender
     pub async fn write_message(&mut self, message: &DanmuMessage) -> Result<()> {
-        if let Some(file) = &mut self.file {
-            // Calculate offset from segment start in seconds (3 decimal places)
-            let offset_ms = (message.timestamp - self.segment_start_time)
-                .num_milliseconds()
-                .max(0);
-            let offset_secs = offset_ms as f64 / 1000.0;
-
-            // Calculate CRC32 of user ID
-            let uid_crc32 = crc32_hash(&message.user_id);
-
-            // Unix timestamp in milliseconds
-            let unix_timestamp_ms = message.timestamp.timestamp_millis();
+        self.write_messages(std::slice::from_ref(message)).await
+    }
 
+    /// Write a batch of danmu messages with a single write to the file.
+    ///
+    /// The messages are rendered as in [`Self::write_message`], concatenated
+    /// and written (and flushed, unless auto flush is off) together, so the
+    /// file sees one write per batch instead of one per message.
+    pub async fn write_messages(&mut self, messages: &[DanmuMessage]) -> Result<()> {
+        if self.file.is_none() || messages.is_empty() {
+            return Ok(());
+        }
+        let mut xml = String::new();
+        for (idx, message) in messages.iter().enumerate() {
             // Row ID is the message count + 1
-            let row_id = self.message_count + 1;
-
-            let xml = match message.message_type {
-                DanmuType::Gift => gift_to_xml(message, offset_secs, unix_timestamp_ms),
-                DanmuType::SuperChat => super_chat_to_xml(message, offset_secs, unix_timestamp_ms),
-                _ => {
-                    // Get danmu type for Bilibili format
-                    let danmu_type = message_type_to_bilibili_type(&message.message_type);
-                    let color = message_color_to_bilibili_color(message).unwrap_or(DEFAULT_COLOR);
-                    let content = message_content_for_xml(message);
-
-                    // Format: <d p="{time},{type},{size},{color},{timestamp},{pool},{uid_crc32},{row_id}" user="{username}">{content}</d>
-                    format!(
-                        "  <d p=\"{:.3},{},{},{},{},{},{},{}\" user=\"{}\">{}</d>\n",
-                        offset_secs,
-                        danmu_type,
-                        DEFAULT_FONT_SIZE,
-                        color,
-                        unix_timestamp_ms,
-                        DEFAULT_POOL,
-                        uid_crc32,
-                        row_id,
-                        escape_xml(&message.username),
-                        escape_xml(&content),
-                    )
-                }
-            };
+            let row_id = self.message_count + idx as u64 + 1;
+            xml.push_str(&self.render_message(message, row_id));
+        }
+        if let Some(file) = &mut self.file {
             file.write_all(xml.as_bytes()).await?;
-            self.message_count += 1;
-
-            // Flush periodically
-            if self.message_count.is_multiple_of(100) {
+            if self.auto_flush {
                 file.flush().await?;
             }
+            self.message_count += messages.len() as u64;
+            self.bytes_written += xml.len() as u64;
         }
         Ok(())
     }
 
+    fn render_message(&self, message: &DanmuMessage, row_id: u64) -> String {
+        // Calculate offset from segment start in seconds (3 decimal places)
+        let offset_ms = (message.timestamp - self.segment_start_time)
+            .num_milliseconds()
+            .max(0);
+        let offset_secs = offset_ms as f64 / 1000.0;
+
+        // Calculate CRC32 of user ID
+        let uid_crc32 = crc32_hash(&message.user_id);
+
+        // Unix timestamp in milliseconds
+        let unix_timestamp_ms = message.timestamp.timestamp_millis();
+
+        let element = match message.message_type {
+            DanmuType::Gift => gift_element(message, offset_secs, unix_timestamp_ms),
+            DanmuType::SuperChat => super_chat_element(message, offset_secs, unix_timestamp_ms),
+            _ => {
+                // Get danmu type for Bilibili format
+                let danmu_type = message_type_to_bilibili_type(&message.message_type);
+                let color = message_color_to_bilibili_color(message).unwrap_or(DEFAULT_COLOR);
+                let content = message_content_for_xml(message);
+
+                // Format: <d p="{time},{type},{size},{color},{timestamp},{pool},{uid_crc32},{row_id}" user="{username}">{content}</d>
+                XmlElement {
+                    name: "d",
+                    fields: vec![
+                        (
+                            "p",
+                            format!(
+                                "{:.3},{},{},{},{},{},{},{}",
+                                offset_secs,
+                                danmu_type,
+                                DEFAULT_FONT_SIZE,
+                                color,
+                                unix_timestamp_ms,
+                                DEFAULT_POOL,
+                                uid_crc32,
+                                row_id,
+                            ),
+                        ),
+                        ("user", message.username.clone()),
+                    ],
+                    text: Some(content),
+                }
+            }
+        };
+        element.render(self.format.attribute_style)
+    }
+
     /// Finalize the XML file by writing the closing tag.
     ///
     /// This should be called when all messages have been written.
     pub async fn finalize(&mut self) -> Result<()> {
         if let Some(file) = &mut self.file {
-            file.write_all(b"</i>\n").await?;
+            let footer = format!("</{}>\n", self.format.root_element);
+            file.write_all(footer.as_bytes()).await?;
             file.flush().await?;
+            self.bytes_written += footer.len() as u64;
         }
         self.file = None;
         Ok(())
@@ -315,7 +467,44 @@ fn message_content_for_xml(message: &DanmuMessage) -> String {
     }
 }
 
-fn gift_to_xml(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> String {
+/// A message element with its metadata fields, rendered according to an [`AttributeStyle`].
+struct XmlElement {
+    name: &'static str,
+    /// Metadata as `(name, unescaped value)` pairs.
+    fields: Vec<(&'static str, String)>,
+    /// Unescaped element text. `None` for elements without text content.
+    text: Option<String>,
+}
+
+impl XmlElement {
+    fn render(&self, style: AttributeStyle) -> String {
+        let mut out = format!("  <{}", self.name);
+        match style {
+            AttributeStyle::Inline => {
+                for (key, value) in &self.fields {
+                    out.push_str(&format!(" {}=\"{}\"", key, escape_xml(value)));
+                }
+                out.push('>');
+                if let Some(text) = &self.text {
+                    out.push_str(&escape_xml(text));
+                }
+            }
+            AttributeStyle::Elements => {
+                out.push('>');
+                for (key, value) in &self.fields {
+                    out.push_str(&format!("<{key}>{}</{key}>", escape_xml(value)));
+                }
+                if let Some(text) = &self.text {
+                    out.push_str(&format!("<text>{}</text>", escape_xml(text)));
+                }
+            }
+        }
+        out.push_str(&format!("</{}>\n", self.name));
+        out
+    }
+}
+
+fn gift_element(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> XmlElement {
     let mut gift_name = "";
     let mut gift_count: u64 = 0;
     let mut price: u64 = 0;
@@ -332,19 +521,22 @@ fn gift_to_xml(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> String {
         price = metadata.get("price").and_then(|v| v.as_u64()).unwrap_or(0);
     }
 
-    format!(
-        "  <gift ts=\"{:.3}\" giftname=\"{}\" giftcount=\"{}\" price=\"{}\" user=\"{}\" uid=\"{}\" timestamp=\"{}\"></gift>\n",
-        ts,
-        escape_xml(gift_name),
-        gift_count,
-        price,
-        escape_xml(&message.username),
-        escape_xml(&message.user_id),
-        timestamp_ms,
-    )
+    XmlElement {
+        name: "gift",
+        fields: vec![
+            ("ts", format!("{:.3}", ts)),
+            ("giftname", gift_name.to_string()),
+            ("giftcount", gift_count.to_string()),
+            ("price", price.to_string()),
+            ("user", message.username.clone()),
+            ("uid", message.user_id.clone()),
+            ("timestamp", timestamp_ms.to_string()),
+        ],
+        text: None,
+    }
 }
 
-fn super_chat_to_xml(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> String {
+fn super_chat_element(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> XmlElement {
     let mut price: u64 = 0;
     let mut keep_time: u64 = 0;
 
@@ -357,16 +549,18 @@ fn super_chat_to_xml(message: &DanmuMessage, ts: f64, timestamp_ms: i64) -> Stri
             .unwrap_or(0);
     }
 
-    format!(
-        "  <sc ts=\"{:.3}\" user=\"{}\" uid=\"{}\" price=\"{}\" time=\"{}\" timestamp=\"{}\">{}</sc>\n",
-        ts,
-        escape_xml(&message.username),
-        escape_xml(&message.user_id),
-        price,
-        keep_time,
-        timestamp_ms,
-        escape_xml(message.content.trim()),
-    )
+    XmlElement {
+        name: "sc",
+        fields: vec![
+            ("ts", format!("{:.3}", ts)),
+            ("user", message.username.clone()),
+            ("uid", message.user_id.clone()),
+            ("price", price.to_string()),
+            ("time", keep_time.to_string()),
+            ("timestamp", timestamp_ms.to_string()),
+        ],
+        text: Some(message.content.trim().to_string()),
+    }
 }
 
 /// Escape special XML characters in a string.
@@ -503,4 +697,230 @@ mod tests {
         assert!(xml.contains("ts=\"2.500\""));
         assert!(xml.contains(">Hello</sc>"));
     }
+
+    #[tokio::test]
+    async fn test_xml_writer_without_auto_flush_writes_on_flush() {
+        let tmp =
+            std::env::temp_dir().join(format!("rust-srec-xml-writer-{}.xml", uuid::Uuid::new_v4()));
+        let mut writer = XmlDanmuWriter::new(&tmp).await.expect("writer");
+        writer.set_auto_flush(false);
+
+        let message = DanmuMessage::chat("m1", "u1", "User", "buffered");
+        writer.write_message(&message).await.expect("write");
+        let before = tokio::fs::read_to_string(&tmp).await.expect("read xml");
+        writer.flush(true).await.expect("flush");
+        let after = tokio::fs::read_to_string(&tmp).await.expect("read xml");
+        writer.finalize().await.expect("finalize");
+        let _ = tokio::fs::remove_file(&tmp).await;
+
+        assert!(!before.contains("buffered"));
+        assert!(after.trim_end().ends_with(">buffered</d>"));
+        assert!(!after.contains("</i>"));
+    }
+
+    #[tokio::test]
+    async fn test_bytes_written_matches_file_size() {
+        let tmp =
+            std::env::temp_dir().join(format!("rust-srec-xml-writer-{}.xml", uuid::Uuid::new_v4()));
+        let mut writer = XmlDanmuWriter::with_start_time_and_comments(
+            &tmp,
+            Utc::now(),
+            vec!["Room ID: 1".to_string()],
+        )
+        .await
+        .expect("writer");
+        writer.set_auto_flush(false);
+        let header_bytes = writer.bytes_written();
+        assert!(header_bytes > 0);
+
+        let message = DanmuMessage::chat("m1", "u1", "用户", "弹幕 buffered");
+        writer.write_message(&message).await.expect("write");
+        // Buffered bytes are counted before they reach the file.
+        assert!(writer.bytes_written() > header_bytes);
+        writer.finalize().await.expect("finalize");
+
+        let size = tokio::fs::metadata(&tmp).await.expect("stat xml").len();
+        let _ = tokio::fs::remove_file(&tmp).await;
+        assert_eq!(writer.bytes_written(), size);
+    }
+
+    async fn write_segment(format: DanmuXmlFormat, messages: &[DanmuMessage]) -> String {
+        use chrono::TimeZone;
+
+        let tmp =
+            std::env::temp_dir().join(format!("rust-srec-xml-writer-{}.xml", uuid::Uuid::new_v4()));
+        let start = Utc.timestamp_opt(1_700_000_000, 0).single().unwrap();
+        let mut writer =
+            XmlDanmuWriter::with_format(&tmp, start, vec!["Room ID: 1".to_string()], format)
+                .await
+                .expect("writer");
+        for message in messages {
+            writer.write_message(message).await.expect("write");
+        }
+        writer.finalize().await.expect("finalize");
+
+        let xml = tokio::fs::read_to_string(&tmp).await.expect("read xml");
+        let _ = tokio::fs::remove_file(&tmp).await;
+        xml
+    }
+
+    /// One second of a 10k messages/s session.
+    fn one_second_at_10k_messages_per_sec() -> (DateTime<Utc>, Vec<DanmuMessage>) {
+        use chrono::TimeZone;
+
+        let start = Utc.timestamp_opt(1_700_000_000, 0).single().unwrap();
+        let messages = (0..10_000)
+            .map(|i| {
+                DanmuMessage::chat(format!("m{i}"), format!("u{}", i % 500), "User", "hello")
+                    .with_timestamp(start + chrono::Duration::microseconds(i * 100))
+            })
+            .collect();
+        (start, messages)
+    }
+
+    /// Write `messages` in batches of `batch_size`, returning the XML and the
+    /// time spent in the writes.
+    async fn write_in_batches(
+        start: DateTime<Utc>,
+        messages: &[DanmuMessage],
+        batch_size: usize,
+    ) -> (String, std::time::Duration) {
+        let tmp =
+            std::env::temp_dir().join(format!("rust-srec-xml-writer-{}.xml", uuid::Uuid::new_v4()));
+        let mut writer = XmlDanmuWriter::with_start_time(&tmp, start)
+            .await
+            .expect("writer");
+        let started = std::time::Instant::now();
+        for batch in messages.chunks(batch_size) {
+            writer.write_messages(batch).await.expect("write");
+        }
+        let elapsed = started.elapsed();
+        writer.finalize().await.expect("finalize");
+        let xml = tokio::fs::read_to_string(&tmp).await.expect("read xml");
+        let _ = tokio::fs::remove_file(&tmp).await;
+        (xml, elapsed)
+    }
+
+    #[tokio::test]
+    async fn test_batch_write_matches_single_writes() {
+        let (start, messages) = one_second_at_10k_messages_per_sec();
+
+        let (single, _) = write_in_batches(start, &messages, 1).await;
+        let (batched, _) = write_in_batches(start, &messages, 100).await;
+
+        assert_eq!(single, batched);
+        assert_eq!(batched.matches("<d ").count(), 10_000);
+        assert!(batched.contains(",10000\" user="));
+    }
+
+    #[tokio::test]
+    #[ignore = "timing depends on the machine; run explicitly"]
+    async fn test_batch_write_keeps_up_with_10k_messages_per_sec() {
+        let (start, messages) = one_second_at_10k_messages_per_sec();
+
+        let (_, elapsed) = write_in_batches(start, &messages, 100).await;
+
+        assert!(
+            elapsed < std::time::Duration::from_secs(1),
+            "took {elapsed:?}"
+        );
+    }
+
+    fn chat_at(offset_ms: i64) -> DanmuMessage {
+        use chrono::TimeZone;
+
+        let start = Utc.timestamp_opt(1_700_000_000, 0).single().unwrap();
+        DanmuMessage::chat("m1", "u1", "Alice", "hi")
+            .with_timestamp(start + chrono::Duration::milliseconds(offset_ms))
+    }
+
+    #[tokio::test]
+    async fn test_default_format_output_is_unchanged() {
+        let xml = write_segment(DanmuXmlFormat::default(), &[chat_at(1500)]).await;
+
+        let expected = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!-- Room ID: 1 -->\n\
+             <i>\n  \
+             <d p=\"1.500,1,25,16777215,1700000001500,0,{},1\" user=\"Alice\">hi</d>\n\
+             </i>\n",
+            crc32_hash("u1")
+        );
+        assert_eq!(xml, expected);
+    }
+
+    #[tokio::test]
+    async fn test_custom_namespace_and_element_style() {
+        use quick_xml::events::Event;
+        use quick_xml::name::{Namespace, ResolveResult};
+        use quick_xml::reader::NsReader;
+
+        let format = DanmuXmlFormat {
+            root_element: "danmaku".to_string(),
+            namespace: Some("urn:rust-srec:danmu".to_string()),
+            include_header: false,
+            attribute_style: AttributeStyle::Elements,
+        };
+        let gift = DanmuMessage::gift("g1", "u2", "Bob", "Rocket", 2)
+            .with_timestamp(chat_at(0).timestamp + chrono::Duration::milliseconds(2000));
+        let xml = write_segment(format, &[chat_at(1500), gift]).await;
+        assert!(!xml.starts_with("<?xml"));
+
+        // Collect (depth, local name, bound to namespace) for every element and the
+        // text of leaf elements, then compare against the expected document.
+        let mut reader = NsReader::from_str(&xml);
+        reader.config_mut().trim_text(true);
+        let mut depth = 0usize;
+        let mut elements = Vec::new();
+        let mut texts = Vec::new();
+        loop {
+            match reader.read_resolved_event().expect("valid xml") {
+                (ns, Event::Start(e)) => {
+                    let bound = ns == ResolveResult::Bound(Namespace(b"urn:rust-srec:danmu"));
+                    let name = String::from_utf8(e.local_name().as_ref().to_vec()).unwrap();
+                    elements.push((depth, name, bound));
+                    depth += 1;
+                }
+                (_, Event::End(_)) => depth -= 1,
+                (_, Event::Text(t)) => texts.push(t.decode().unwrap().into_owned()),
+                (_, Event::Eof) => break,
+                _ => {}
+            }
+        }
+
+        let expected_elements: Vec<(usize, String, bool)> = [
+            (0, "danmaku"),
+            (1, "d"),
+            (2, "p"),
+            (2, "user"),
+            (2, "text"),
+            (1, "gift"),
+            (2, "ts"),
+            (2, "giftname"),
+            (2, "giftcount"),
+            (2, "price"),
+            (2, "user"),
+            (2, "uid"),
+            (2, "timestamp"),
+        ]
+        .into_iter()
+        .map(|(depth, name)| (depth, name.to_string(), true))
+        .collect();
+        assert_eq!(elements, expected_elements);
+        assert_eq!(
+            texts,
+            vec![
+                format!("1.500,1,25,16777215,1700000001500,0,{},1", crc32_hash("u1")),
+                "Alice".to_string(),
+                "hi".to_string(),
+                "2.000".to_string(),
+                "Rocket".to_string(),
+                "2".to_string(),
+                "0".to_string(),
+                "Bob".to_string(),
+                "u2".to_string(),
+                "1700000002000".to_string(),
+            ]
+        );
+    }
 }
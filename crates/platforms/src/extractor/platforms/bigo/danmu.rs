@@ -18,7 +18,7 @@ use tokio_tungstenite::tungstenite::http::HeaderMap;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::debug;
 
-use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::error::{DanmakuError, DanmuConnectionError, Result};
 use crate::danmaku::websocket::ws_headers_origin_ua;
 use crate::danmaku::websocket::{
     DanmuProtocol, DanmuProtocolFactory, DanmuProtocolOutput, WebSocketDanmuProvider,
@@ -331,10 +331,11 @@ impl BigoDanmuProtocol {
             .map_err(|e| DanmakuError::connection(format!("getWebSocketLink failed: {e}")))?;
 
         if !response.status().is_success() {
-            return Err(DanmakuError::connection(format!(
-                "getWebSocketLink HTTP {}",
-                response.status()
-            )));
+            return Err(DanmuConnectionError::from_response(
+                &response,
+                format!("getWebSocketLink HTTP {}", response.status()),
+            )
+            .into());
         }
 
         let payload: WsLinkResponse = response
@@ -386,6 +387,10 @@ impl DanmuProtocolFactory for BigoDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         // siteId from URL — studio roomId must come from MediaInfo.extras.
         capture_group_1_owned(&URL_REGEX, url)
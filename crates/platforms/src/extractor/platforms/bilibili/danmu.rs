@@ -16,6 +16,7 @@ use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::debug;
 
 use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::provider::is_numeric_room_id;
 use crate::danmaku::websocket::ws_headers_origin_referer_ua;
 use crate::danmaku::websocket::{
     DanmuProtocol, DanmuProtocolFactory, DanmuProtocolOutput, WebSocketDanmuProvider,
@@ -517,6 +518,10 @@ impl BilibiliDanmuProtocol {
             .or_else(|| data.get("total_coin"))
             .and_then(|v| v.as_u64().or_else(|| v.as_f64().map(|f| f as u64)))
             .unwrap_or(0);
+        let coin_value = data
+            .get("total_coin")
+            .and_then(|v| v.as_u64().or_else(|| v.as_f64().map(|f| f as u64)))
+            .unwrap_or_else(|| price.saturating_mul(u64::from(num)));
 
         let timestamp_ms = data
             .get("timestamp")
@@ -536,7 +541,8 @@ impl BilibiliDanmuProtocol {
             gift_name,
             num,
         )
-        .with_metadata("price", serde_json::json!(price));
+        .with_metadata("price", serde_json::json!(price))
+        .with_coin_value(coin_value);
 
         if let Some(ts_ms) = timestamp_ms
             && let Some(dt) = Utc.timestamp_millis_opt(ts_ms).single()
@@ -607,10 +613,18 @@ impl DanmuProtocolFactory for BilibiliDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1_owned(&URL_REGEX, url)
     }
 
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        is_numeric_room_id(room_id)
+    }
+
     fn create_protocol(&self) -> Self::Protocol {
         Self {
             client: self.client.clone(),
@@ -877,6 +891,7 @@ mod tests {
                 assert_eq!(msg.content, "赠送 Rocket x5");
                 let meta = msg.metadata.expect("gift metadata");
                 assert_eq!(meta.get("price").unwrap(), 100);
+                assert_eq!(meta.get("coin_value").unwrap(), 500);
             }
             other => panic!("Unexpected item: {other:?}"),
         }
@@ -14,6 +14,7 @@ use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::debug;
 
 use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::provider::is_numeric_room_id;
 use crate::danmaku::websocket::ws_headers_origin_ua;
 use crate::danmaku::websocket::{
     DanmuProtocol, DanmuProtocolFactory, DanmuProtocolOutput, WebSocketDanmuProvider,
@@ -284,10 +285,18 @@ impl DanmuProtocolFactory for DouyinDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1_owned(&URL_REGEX, url)
     }
 
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        is_numeric_room_id(room_id)
+    }
+
     fn create_protocol(&self) -> Self::Protocol {
         self.clone()
     }
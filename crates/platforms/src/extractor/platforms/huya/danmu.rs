@@ -14,7 +14,8 @@ use tars_codec::{
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::debug;
 
-use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::error::{DanmakuError, DanmuErrorCode, Result};
+use crate::danmaku::provider::is_numeric_room_id;
 use crate::danmaku::websocket::{
     DanmuProtocol, DanmuProtocolFactory, DanmuProtocolOutput, WebSocketDanmuProvider,
 };
@@ -78,10 +79,18 @@ impl DanmuProtocolFactory for HuyaDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1_owned(&URL_REGEX, url)
     }
 
+    fn validate_room_id(&self, room_id: &str) -> bool {
+        is_numeric_room_id(room_id)
+    }
+
     fn create_protocol(&self) -> Self::Protocol {
         self.clone()
     }
@@ -97,9 +106,9 @@ impl DanmuProtocol for HuyaDanmuProtocol {
     }
 
     async fn handshake_messages(&mut self, room_id: &str) -> Result<Vec<Message>> {
-        let room_id_num: i64 = room_id
-            .parse()
-            .map_err(|_| DanmakuError::connection("Invalid room ID".to_string()))?;
+        let room_id_num: i64 = room_id.parse().map_err(|_| {
+            DanmakuError::connection_failed(DanmuErrorCode::RoomNotFound, "Invalid room ID")
+        })?;
 
         // 2512200523
         let ua = format!("webh5&{}&websocket", Utc::now().format("%y%m%d%H%M"));
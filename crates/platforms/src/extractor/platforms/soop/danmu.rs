@@ -261,6 +261,10 @@ impl DanmuProtocolFactory for SoopDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1_owned(&URL_REGEX, url)
     }
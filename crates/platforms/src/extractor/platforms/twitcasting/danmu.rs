@@ -17,7 +17,7 @@ use std::time::Duration;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::{debug, warn};
 
-use crate::danmaku::error::{DanmakuError, Result};
+use crate::danmaku::error::{DanmakuError, DanmuConnectionError, Result};
 use crate::danmaku::websocket::ws_headers_origin_referer;
 use crate::danmaku::websocket::{
     DanmuProtocol, DanmuProtocolFactory, DanmuProtocolOutput, WebSocketDanmuProvider,
@@ -191,10 +191,11 @@ impl TwitcastingDanmuProtocol {
             .map_err(|e| DanmakuError::connection(format!("Failed to get stream info: {}", e)))?;
 
         if !response.status().is_success() {
-            return Err(DanmakuError::connection(format!(
-                "Failed to get stream info: HTTP {}",
-                response.status()
-            )));
+            return Err(DanmuConnectionError::from_response(
+                &response,
+                format!("Failed to get stream info: HTTP {}", response.status()),
+            )
+            .into());
         }
 
         let data: StreamServerResponse = response.json().await.map_err(|e| {
@@ -234,10 +235,11 @@ impl TwitcastingDanmuProtocol {
             .map_err(|e| DanmakuError::connection(format!("Failed to get pubsub URL: {}", e)))?;
 
         if !response.status().is_success() {
-            return Err(DanmakuError::connection(format!(
-                "Failed to get pubsub URL: HTTP {}",
-                response.status()
-            )));
+            return Err(DanmuConnectionError::from_response(
+                &response,
+                format!("Failed to get pubsub URL: HTTP {}", response.status()),
+            )
+            .into());
         }
 
         let data: EventPubSubResponse = response.json().await.map_err(|e| {
@@ -360,6 +362,10 @@ impl DanmuProtocolFactory for TwitcastingDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1_owned(&URL_REGEX, url)
     }
@@ -2,6 +2,7 @@
 //!
 //! Implements danmu collection for the Twitch streaming platform using IRC over WebSocket.
 
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::debug;
@@ -13,7 +14,7 @@ use crate::danmaku::websocket::{
 use crate::danmaku::{DanmuItem, DanmuMessage, DanmuType};
 
 use super::URL_REGEX;
-use crate::extractor::utils::capture_group_1;
+use crate::extractor::utils::{capture_group_1, parse_cookie_header};
 
 /// Twitch WebSocket IRC server URL
 const TWITCH_WS_URL: &str = "wss://irc-ws.chat.twitch.tv:443";
@@ -22,11 +23,51 @@ const TWITCH_WS_URL: &str = "wss://irc-ws.chat.twitch.tv:443";
 /// We don't need to send heartbeat proactively, just respond to PING
 const HEARTBEAT_INTERVAL_SECS: u64 = 300;
 
+/// Gift name used for cheers; the gift count and value are the number of bits.
+const BITS_GIFT_NAME: &str = "bits";
+
+/// USERNOTICE `msg-id`s that represent a paid subscription.
+///
+/// `submysterygift` is left out on purpose: Twitch follows it with one
+/// `subgift` notice per recipient, which are counted individually.
+const SUBSCRIPTION_NOTICES: &[&str] = &["sub", "resub", "subgift", "anonsubgift"];
+
 /// Twitch Danmu Protocol Implementation using WebSocket IRC
+///
+/// Connects anonymously with a `justinfan` nick unless an OAuth token is
+/// configured. Gift values are expressed in bits (1 bit = 1 US cent), so
+/// subscriptions are valued at their list price in bits.
 #[derive(Clone, Default)]
 pub struct TwitchDanmuProtocol {
     /// Optional OAuth token for authenticated sessions
     oauth_token: Option<String>,
+    /// Login name owning `oauth_token`, sent as NICK
+    login: Option<String>,
+}
+
+/// A single IRC line split into its parts.
+struct IrcLine<'a> {
+    tags: HashMap<String, String>,
+    prefix: Option<&'a str>,
+    command: &'a str,
+    trailing: Option<&'a str>,
+}
+
+impl IrcLine<'_> {
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Login name from the `user!user@host` prefix, falling back to the `login` tag.
+    fn login(&self) -> Option<&str> {
+        self.prefix
+            .and_then(|p| p.split_once('!'))
+            .map(|(nick, _)| nick)
+            .or_else(|| self.tag("login"))
+    }
 }
 
 impl TwitchDanmuProtocol {
@@ -39,6 +80,15 @@ impl TwitchDanmuProtocol {
     pub fn with_oauth(token: impl Into<String>) -> Self {
         Self {
             oauth_token: Some(token.into()),
+            login: None,
+        }
+    }
+
+    /// Create a new TwitchDanmuProtocol authenticated as `login` with `token`.
+    pub fn with_credentials(login: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            oauth_token: Some(token.into()),
+            login: Some(login.into().to_lowercase()),
         }
     }
 
@@ -48,88 +98,189 @@ impl TwitchDanmuProtocol {
         format!("justinfan{}", random_num)
     }
 
-    /// Parse IRC message into DanmuMessage
-    fn parse_irc_message(line: &str) -> Option<DanmuMessage> {
-        if line.starts_with("PING") || !line.contains("PRIVMSG") {
-            return None;
+    /// Unescape an IRCv3 tag value (`\s` is a space, `\:` a semicolon).
+    fn unescape_tag_value(value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('s') => out.push(' '),
+                Some(':') => out.push(';'),
+                Some('r') => out.push('\r'),
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            }
         }
+        out
+    }
 
-        // Parse tags
-        let mut tags = std::collections::HashMap::new();
-        let mut remaining = line;
+    /// Split an IRC line into tags, prefix, command and trailing parameter.
+    fn parse_irc_line(line: &str) -> Option<IrcLine<'_>> {
+        let mut rest = line;
+        let mut tags = HashMap::new();
 
-        if line.starts_with('@')
-            && let Some(space_idx) = line.find(' ')
-        {
-            let tag_str = &line[1..space_idx];
+        if let Some(stripped) = rest.strip_prefix('@') {
+            let (tag_str, tail) = stripped.split_once(' ')?;
             for tag in tag_str.split(';') {
-                if let Some(eq_idx) = tag.find('=') {
-                    let key = &tag[..eq_idx];
-                    let value = &tag[eq_idx + 1..];
-                    tags.insert(key.to_string(), value.to_string());
+                if let Some((key, value)) = tag.split_once('=') {
+                    tags.insert(key.to_string(), Self::unescape_tag_value(value));
                 }
             }
-            remaining = &line[space_idx + 1..];
+            rest = tail;
         }
 
-        // Parse: :user!user@user.tmi.twitch.tv PRIVMSG #channel :message
-        let parts: Vec<&str> = remaining.splitn(4, ' ').collect();
-        if parts.len() < 4 {
-            return None;
+        let mut prefix = None;
+        if let Some(stripped) = rest.strip_prefix(':') {
+            let (p, tail) = stripped.split_once(' ')?;
+            prefix = Some(p);
+            rest = tail;
         }
 
-        let prefix = parts[0];
-        let content = if parts[3].starts_with(':') {
-            &parts[3][1..]
-        } else {
-            parts[3]
+        let (head, trailing) = match rest.split_once(" :") {
+            Some((head, trailing)) => (head, Some(trailing)),
+            None => (rest, None),
         };
+        let command = head.split_whitespace().next()?;
+
+        Some(IrcLine {
+            tags,
+            prefix,
+            command,
+            trailing,
+        })
+    }
 
-        // Extract username from prefix
-        let username = prefix
-            .strip_prefix(':')
-            .and_then(|s| s.split('!').next())
-            .unwrap_or("unknown");
-
-        let display_name = tags
-            .get("display-name")
-            .cloned()
-            .unwrap_or_else(|| username.to_string());
-
-        let user_id = tags
-            .get("user-id")
-            .cloned()
-            .unwrap_or_else(|| username.to_string());
-
-        let message_id = tags
-            .get("id")
-            .cloned()
+    /// Parse IRC message into DanmuMessage
+    fn parse_irc_message(line: &str) -> Option<DanmuMessage> {
+        let irc = Self::parse_irc_line(line)?;
+        match irc.command {
+            "PRIVMSG" => Self::parse_privmsg(&irc),
+            "USERNOTICE" => Self::parse_usernotice(&irc),
+            _ => None,
+        }
+    }
+
+    fn is_reconnect_notice(line: &str) -> bool {
+        Self::parse_irc_line(line).is_some_and(|irc| irc.command == "RECONNECT")
+    }
+
+    /// Parse: :user!user@user.tmi.twitch.tv PRIVMSG #channel :message
+    fn parse_privmsg(irc: &IrcLine<'_>) -> Option<DanmuMessage> {
+        let content = irc.trailing?;
+        let username = irc.login().unwrap_or("unknown");
+
+        let display_name = irc.tag("display-name").unwrap_or(username);
+        let user_id = irc.tag("user-id").unwrap_or(username);
+        let message_id = irc
+            .tag("id")
+            .map(str::to_string)
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
         let mut msg = DanmuMessage::chat(message_id, user_id, display_name, content.trim());
 
         // Add color if present
-        if let Some(color) = tags.get("color")
-            && !color.is_empty()
-        {
+        if let Some(color) = irc.tag("color") {
             msg = msg.with_color(color);
         }
 
         // Add badges as metadata
-        if let Some(badges) = tags.get("badges")
-            && !badges.is_empty()
-        {
+        if let Some(badges) = irc.tag("badges") {
             msg = msg.with_metadata("badges", serde_json::json!(badges));
         }
 
-        // Check for bits (cheering) - change message type to Gift
-        if let Some(bits) = tags.get("bits") {
+        // Check for bits (cheering) - change message type to Gift, valued in bits
+        if let Some(bits) = irc.tag("bits") {
+            let bits = bits.parse::<u64>().unwrap_or(0);
             msg.message_type = DanmuType::Gift;
-            msg = msg.with_metadata("bits", serde_json::json!(bits.parse::<u32>().unwrap_or(0)));
+            msg = msg
+                .with_metadata("bits", serde_json::json!(bits))
+                .with_metadata("gift_name", serde_json::json!(BITS_GIFT_NAME))
+                .with_metadata("gift_count", serde_json::json!(bits))
+                .with_coin_value(bits);
+        }
+
+        Some(msg)
+    }
+
+    /// Parse subscription USERNOTICEs into gift messages valued in bits.
+    ///
+    /// Other notices (raids, announcements, ...) are ignored.
+    fn parse_usernotice(irc: &IrcLine<'_>) -> Option<DanmuMessage> {
+        let msg_id = irc.tag("msg-id")?;
+        if !SUBSCRIPTION_NOTICES.contains(&msg_id) {
+            return None;
+        }
+
+        let plan = irc.tag("msg-param-sub-plan").unwrap_or("1000");
+        let username = irc.login().unwrap_or("unknown");
+        let display_name = irc.tag("display-name").unwrap_or(username);
+        let user_id = irc.tag("user-id").unwrap_or(username);
+        let message_id = irc
+            .tag("id")
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let mut msg = DanmuMessage::gift(
+            message_id,
+            user_id,
+            display_name,
+            Self::sub_plan_name(plan),
+            1,
+        )
+        .with_metadata("msg_id", serde_json::json!(msg_id))
+        .with_metadata("sub_plan", serde_json::json!(plan));
+
+        if let Some(value) = Self::sub_plan_value(plan) {
+            msg = msg.with_coin_value(value);
+        }
+
+        if let Some(recipient) = irc
+            .tag("msg-param-recipient-display-name")
+            .or_else(|| irc.tag("msg-param-recipient-user-name"))
+        {
+            msg = msg.with_metadata("recipient", serde_json::json!(recipient));
+        }
+
+        // Prefer the user's resub message, then Twitch's own system text.
+        if let Some(content) = irc
+            .trailing
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .or_else(|| irc.tag("system-msg"))
+        {
+            msg.content = content.to_string();
+        }
+
+        if let Some(color) = irc.tag("color") {
+            msg = msg.with_color(color);
         }
 
         Some(msg)
     }
+
+    fn sub_plan_name(plan: &str) -> &'static str {
+        match plan {
+            "Prime" => "Prime sub",
+            "2000" => "Tier 2 sub",
+            "3000" => "Tier 3 sub",
+            _ => "Tier 1 sub",
+        }
+    }
+
+    /// Subscription list price in bits (1 bit = 1 US cent).
+    fn sub_plan_value(plan: &str) -> Option<u64> {
+        match plan {
+            "Prime" | "1000" => Some(499),
+            "2000" => Some(999),
+            "3000" => Some(2499),
+            _ => None,
+        }
+    }
 }
 
 impl DanmuProtocolFactory for TwitchDanmuProtocol {
@@ -143,6 +294,10 @@ impl DanmuProtocolFactory for TwitchDanmuProtocol {
         URL_REGEX.is_match(url)
     }
 
+    fn url_patterns(&self) -> Vec<String> {
+        vec![URL_REGEX.as_str().to_string()]
+    }
+
     fn extract_room_id(&self, url: &str) -> Option<String> {
         capture_group_1(&URL_REGEX, url).map(str::to_lowercase)
     }
@@ -162,6 +317,39 @@ impl DanmuProtocol for TwitchDanmuProtocol {
         None
     }
 
+    fn send_cookie_header(&self) -> bool {
+        false
+    }
+
+    /// Picks up credentials from extras (`oauth_token`, `login`) or from the
+    /// `auth-token` and `login` cookies of a twitch.tv session.
+    fn configure_connection(
+        &mut self,
+        cookies: Option<&str>,
+        extras: Option<&HashMap<String, String>>,
+    ) {
+        let cookies = cookies.map(parse_cookie_header).unwrap_or_default();
+        let cookie = |name: &str| {
+            cookies
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        let extra = |key: &str| {
+            extras
+                .and_then(|e| e.get(key))
+                .filter(|v| !v.is_empty())
+                .cloned()
+        };
+
+        if let Some(token) = extra("oauth_token").or_else(|| cookie("auth-token")) {
+            self.oauth_token = Some(token);
+        }
+        if let Some(login) = extra("login").or_else(|| cookie("login")) {
+            self.login = Some(login.to_lowercase());
+        }
+    }
+
     async fn handshake_messages(&mut self, room_id: &str) -> Result<Vec<Message>> {
         let mut messages = Vec::new();
 
@@ -182,14 +370,11 @@ impl DanmuProtocol for TwitchDanmuProtocol {
         };
         messages.push(Message::Text(pass.into()));
 
-        // Send NICK
-        let nick = if self.oauth_token.is_some() {
-            // For authenticated, the nick should match the token owner
-            // But for simplicity, we still use anonymous nick pattern
-            Self::generate_anonymous_nick()
-        } else {
-            Self::generate_anonymous_nick()
-        };
+        // Send NICK - it must match the token owner when authenticated
+        let nick = self
+            .login
+            .clone()
+            .unwrap_or_else(Self::generate_anonymous_nick);
         messages.push(Message::Text(format!("NICK {}", nick).into()));
 
         // Join channel
@@ -240,6 +425,7 @@ impl TwitchDanmuProtocol {
     fn decode_text(text: &str) -> DanmuProtocolOutput {
         let mut items = Vec::new();
         let mut outbound = Vec::new();
+        let mut reconnect = false;
 
         // Handle each line (Twitch may send multiple messages in one frame)
         for line in text.lines() {
@@ -257,13 +443,25 @@ impl TwitchDanmuProtocol {
                 continue;
             }
 
-            // Parse chat messages
+            // Twitch is about to restart the server; reconnect right away
+            if Self::is_reconnect_notice(trimmed) {
+                debug!("Received RECONNECT from Twitch");
+                reconnect = true;
+                continue;
+            }
+
+            // Parse chat messages and subscription notices
             if let Some(danmu) = Self::parse_irc_message(trimmed) {
                 items.push(DanmuItem::Message(danmu));
             }
         }
 
-        DanmuProtocolOutput::new(items, outbound)
+        let output = DanmuProtocolOutput::new(items, outbound);
+        if reconnect {
+            output.with_reconnect()
+        } else {
+            output
+        }
     }
 }
 
@@ -328,6 +526,111 @@ mod tests {
         assert!(msg.metadata.is_some());
         let metadata = msg.metadata.unwrap();
         assert_eq!(metadata.get("bits").unwrap(), &serde_json::json!(100));
+        assert_eq!(
+            metadata.get("gift_name").unwrap(),
+            &serde_json::json!("bits")
+        );
+        assert_eq!(metadata.get("gift_count").unwrap(), &serde_json::json!(100));
+        assert_eq!(metadata.get("coin_value").unwrap(), &serde_json::json!(100));
+        assert_eq!(msg.content, "cheer100 Great stream!");
+    }
+
+    #[test]
+    fn test_parse_resub_usernotice() {
+        let line = r"@badge-info=subscriber/5;color=#00FF7F;display-name=Subber;id=sub1;login=subber;msg-id=resub;msg-param-cumulative-months=5;msg-param-sub-plan=1000;system-msg=Subber\ssubscribed\sat\sTier\s1.;user-id=111 :tmi.twitch.tv USERNOTICE #channel :Five months!";
+
+        let msg = TwitchDanmuProtocol::parse_irc_message(line).expect("resub notice");
+        assert_eq!(msg.message_type, DanmuType::Gift);
+        assert_eq!(msg.username, "Subber");
+        assert_eq!(msg.user_id, "111");
+        assert_eq!(msg.content, "Five months!");
+        let metadata = msg.metadata.unwrap();
+        assert_eq!(
+            metadata.get("gift_name").unwrap(),
+            &serde_json::json!("Tier 1 sub")
+        );
+        assert_eq!(metadata.get("gift_count").unwrap(), &serde_json::json!(1));
+        assert_eq!(metadata.get("coin_value").unwrap(), &serde_json::json!(499));
+    }
+
+    #[test]
+    fn test_parse_subgift_usernotice_uses_system_message() {
+        let line = r"@display-name=Gifter;id=gift1;login=gifter;msg-id=subgift;msg-param-recipient-display-name=Lucky;msg-param-sub-plan=3000;system-msg=Gifter\sgifted\sa\sTier\s3\ssub\sto\sLucky!;user-id=222 :tmi.twitch.tv USERNOTICE #channel";
+
+        let msg = TwitchDanmuProtocol::parse_irc_message(line).expect("subgift notice");
+        assert_eq!(msg.username, "Gifter");
+        assert_eq!(msg.content, "Gifter gifted a Tier 3 sub to Lucky!");
+        let metadata = msg.metadata.unwrap();
+        assert_eq!(
+            metadata.get("gift_name").unwrap(),
+            &serde_json::json!("Tier 3 sub")
+        );
+        assert_eq!(
+            metadata.get("coin_value").unwrap(),
+            &serde_json::json!(2499)
+        );
+        assert_eq!(
+            metadata.get("recipient").unwrap(),
+            &serde_json::json!("Lucky")
+        );
+    }
+
+    #[test]
+    fn test_mystery_gift_and_raid_notices_are_ignored() {
+        let mystery = "@display-name=Gifter;login=gifter;msg-id=submysterygift;msg-param-mass-gift-count=5;msg-param-sub-plan=1000;user-id=222 :tmi.twitch.tv USERNOTICE #channel";
+        let raid = "@display-name=Raider;login=raider;msg-id=raid;msg-param-viewerCount=10;user-id=333 :tmi.twitch.tv USERNOTICE #channel";
+
+        assert!(TwitchDanmuProtocol::parse_irc_message(mystery).is_none());
+        assert!(TwitchDanmuProtocol::parse_irc_message(raid).is_none());
+    }
+
+    #[tokio::test]
+    async fn reconnect_notice_requests_reconnect_after_pending_items() {
+        let mut protocol = TwitchDanmuProtocol::default();
+        let frame = "@display-name=A;id=1;user-id=1 :a!a@a.tmi.twitch.tv PRIVMSG #channel :hi\r\n:tmi.twitch.tv RECONNECT";
+        let output = protocol
+            .decode_message(&Message::Text(frame.into()), "channel")
+            .await
+            .expect("decode reconnect");
+
+        assert!(output.reconnect_requested());
+        let (items, outbound) = output.into_parts();
+        assert_eq!(items.len(), 1);
+        assert!(outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn handshake_is_anonymous_without_credentials() {
+        let mut protocol = TwitchDanmuProtocol::default();
+        protocol.configure_connection(Some("unrelated=1"), None);
+        let messages = protocol.handshake_messages("Channel").await.unwrap();
+
+        assert_eq!(messages[1], Message::Text("PASS oauth:".into()));
+        let Message::Text(nick) = &messages[2] else {
+            panic!("expected NICK text frame");
+        };
+        assert!(nick.starts_with("NICK justinfan"));
+        assert_eq!(messages[3], Message::Text("JOIN #channel".into()));
+    }
+
+    #[tokio::test]
+    async fn handshake_authenticates_with_cookie_or_extras_credentials() {
+        let mut protocol = TwitchDanmuProtocol::default();
+        protocol.configure_connection(Some("auth-token=abc123; login=MyUser"), None);
+        let messages = protocol.handshake_messages("channel").await.unwrap();
+        assert_eq!(messages[1], Message::Text("PASS oauth:abc123".into()));
+        assert_eq!(messages[2], Message::Text("NICK myuser".into()));
+
+        let extras = HashMap::from([
+            ("oauth_token".to_string(), "oauth:xyz".to_string()),
+            ("login".to_string(), "other".to_string()),
+        ]);
+        let mut protocol = TwitchDanmuProtocol::default();
+        protocol.configure_connection(Some("auth-token=abc123; login=MyUser"), Some(&extras));
+        let messages = protocol.handshake_messages("channel").await.unwrap();
+        assert_eq!(messages[1], Message::Text("PASS oauth:xyz".into()));
+        assert_eq!(messages[2], Message::Text("NICK other".into()));
+        assert!(!protocol.send_cookie_header());
     }
 
     #[test]
@@ -0,0 +1,124 @@
+//! Fake danmu plugin used by the `danmaku::plugin` tests.
+//!
+//! Built on the fly as a `cdylib` without dependencies. Rooms are addressed as
+//! `fake://<room>`; each connection delivers one message and is then closed.
+//! With `--cfg wrong_abi` the plugin reports an unsupported ABI version.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::sync::Mutex;
+
+const PLUGIN_ABI_VERSION: u32 = 0x5352_4302;
+
+#[repr(C)]
+pub struct ProviderRegistryFfi {
+    context: *mut c_void,
+    get_platform_name: Option<unsafe extern "C" fn(*mut c_void) -> *const c_char>,
+    extract_room_id:
+        Option<unsafe extern "C" fn(*mut c_void, *const c_char, *mut c_char, usize) -> isize>,
+    connect: Option<unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char) -> u64>,
+    receive: Option<unsafe extern "C" fn(*mut c_void, u64, *mut DanmuPluginMessage) -> i32>,
+    disconnect: Option<unsafe extern "C" fn(*mut c_void, u64)>,
+}
+
+#[repr(C)]
+pub struct DanmuPluginMessage {
+    id: *const c_char,
+    user_id: *const c_char,
+    username: *const c_char,
+    content: *const c_char,
+    timestamp_ms: i64,
+}
+
+struct Connection {
+    handle: u64,
+    /// Message content; `None` once delivered.
+    content: Option<Vec<u8>>,
+    /// Keeps the last delivered content alive until the next `receive`.
+    delivered: Vec<u8>,
+}
+
+static CONNECTIONS: Mutex<Vec<Connection>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn get_platform_name(_context: *mut c_void) -> *const c_char {
+    c"fake".as_ptr()
+}
+
+unsafe extern "C" fn extract_room_id(
+    _context: *mut c_void,
+    url: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> isize {
+    let url = unsafe { CStr::from_ptr(url) }.to_bytes();
+    let Some(room) = url.strip_prefix(b"fake://") else {
+        return -1;
+    };
+    if room.is_empty() || room.len() > out_len {
+        return -1;
+    }
+    unsafe { std::ptr::copy_nonoverlapping(room.as_ptr(), out.cast::<u8>(), room.len()) };
+    room.len() as isize
+}
+
+unsafe extern "C" fn connect(
+    _context: *mut c_void,
+    room_id: *const c_char,
+    _cookies: *const c_char,
+) -> u64 {
+    let room = unsafe { CStr::from_ptr(room_id) }.to_string_lossy();
+    let mut connections = CONNECTIONS.lock().unwrap();
+    let handle = connections.len() as u64 + 1;
+    connections.push(Connection {
+        handle,
+        content: Some(format!("hello from {room}\0").into_bytes()),
+        delivered: Vec::new(),
+    });
+    handle
+}
+
+unsafe extern "C" fn receive(
+    _context: *mut c_void,
+    connection: u64,
+    message: *mut DanmuPluginMessage,
+) -> i32 {
+    let mut connections = CONNECTIONS.lock().unwrap();
+    let Some(connection) = connections.iter_mut().find(|c| c.handle == connection) else {
+        return -2;
+    };
+    let Some(content) = connection.content.take() else {
+        return -1;
+    };
+    connection.delivered = content;
+    let message = unsafe { &mut *message };
+    message.user_id = c"u1".as_ptr();
+    message.username = c"plugin user".as_ptr();
+    message.content = connection.delivered.as_ptr().cast();
+    message.timestamp_ms = 1_700_000_000_000;
+    1
+}
+
+unsafe extern "C" fn disconnect(_context: *mut c_void, connection: u64) {
+    CONNECTIONS
+        .lock()
+        .unwrap()
+        .retain(|c| c.handle != connection);
+}
+
+#[no_mangle]
+pub extern "C" fn danmu_plugin_abi_version() -> u32 {
+    if cfg!(wrong_abi) {
+        0
+    } else {
+        PLUGIN_ABI_VERSION
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn register_providers(registry: *mut ProviderRegistryFfi) {
+    let registry = unsafe { &mut *registry };
+    registry.get_platform_name = Some(get_platform_name);
+    registry.extract_room_id = Some(extract_room_id);
+    registry.connect = Some(connect);
+    registry.receive = Some(receive);
+    registry.disconnect = Some(disconnect);
+}
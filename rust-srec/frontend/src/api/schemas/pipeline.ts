@@ -66,6 +66,7 @@ export const JobStatusSchema = z.enum([
   'COMPLETED',
   'FAILED',
   'CANCELLED',
+  'SKIPPED',
 ]);
 export type JobStatus = z.infer<typeof JobStatusSchema>;
 
@@ -177,6 +177,7 @@ export const PipelineStatsSchema = z.object({
   completed_count: z.number(),
   failed_count: z.number(),
   cancelled_count: z.number().optional(),
+  skipped_count: z.number().optional(),
   avg_processing_time_secs: z.number().nullable().optional(),
 });
 
@@ -4,10 +4,11 @@ import {
   CheckCircle2,
   XCircle,
   AlertCircle,
+  SkipForward,
 } from 'lucide-react';
 import type { I18n } from '@lingui/core';
 import { msg } from '@lingui/core/macro';
-import type { DagStatus, DagStepStatus } from '@/api/schemas';
+import type { DagStatus, DagStepStatus, JobStatus } from '@/api/schemas';
 
 export interface StatusConfigItem {
   icon: React.ElementType;
@@ -22,7 +23,7 @@ export interface StatusConfigItem {
 }
 
 export const STATUS_CONFIG: Record<
-  DagStatus | DagStepStatus,
+  DagStatus | DagStepStatus | JobStatus,
   StatusConfigItem
 > = {
   PENDING: {
@@ -76,6 +77,16 @@ export const STATUS_CONFIG: Record<
     surfaceBg: 'bg-gray-500/10',
     glow: 'shadow-transparent',
   },
+  SKIPPED: {
+    icon: SkipForward,
+    textColor: 'text-amber-500',
+    bgColor: 'bg-amber-500/10 text-amber-500 border-amber-500/20',
+    badgeVariant: 'secondary',
+    gradient: 'from-amber-500/20 to-amber-500/5',
+    borderColor: 'border-amber-500/20',
+    surfaceBg: 'bg-amber-500/10',
+    glow: 'shadow-transparent',
+  },
   BLOCKED: {
     icon: Clock,
     textColor: 'text-muted-foreground/40',
@@ -108,6 +119,8 @@ export function getStatusLabel(i18n: I18n, status: string): string {
       return i18n._(msg`Failed`);
     case 'CANCELLED':
       return i18n._(msg`Cancelled`);
+    case 'SKIPPED':
+      return i18n._(msg`Skipped`);
     default:
       return status;
   }
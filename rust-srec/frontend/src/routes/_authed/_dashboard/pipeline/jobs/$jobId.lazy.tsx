@@ -296,7 +296,7 @@ function JobDetailsPage() {
               transition={{ delay: 0.2 }}
               className="flex items-center gap-3"
             >
-              {['FAILED', 'CANCELLED'].includes(job.status) && (
+              {['FAILED', 'CANCELLED', 'SKIPPED'].includes(job.status) && (
                 <Button
                   className="bg-primary shadow-lg shadow-primary/20 hover:shadow-primary/40 transition-all font-medium"
                   onClick={() => retryMutation.mutate(job.id)}
@@ -322,7 +322,9 @@ function JobDetailsPage() {
                   <Trans>Cancel Execution</Trans>
                 </Button>
               )}
-              {['COMPLETED', 'FAILED', 'CANCELLED'].includes(job.status) && (
+              {['COMPLETED', 'FAILED', 'CANCELLED', 'SKIPPED'].includes(
+                job.status,
+              ) && (
                 <Button
                   variant="destructive"
                   className="shadow-lg shadow-destructive/20 hover:shadow-destructive/40 transition-all"
@@ -46,6 +46,7 @@ import {
   CheckCircle2,
   Clock,
   AlertCircle,
+  SkipForward,
   Timer,
   ListTodo,
   Plus,
@@ -108,6 +109,11 @@ function PipelineJobsPage() {
           label: i18n._(msg`Cancelled`),
           icon: AlertCircle,
         },
+        {
+          value: 'SKIPPED',
+          label: i18n._(msg`Skipped`),
+          icon: SkipForward,
+        },
       ] as const,
     [i18n],
   );
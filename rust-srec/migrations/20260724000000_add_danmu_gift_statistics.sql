@@ -0,0 +1,9 @@
+-- Gift value aggregation for danmu statistics.
+--
+-- JSON object written by `persist_statistics` in rust-srec/src/danmu/service.rs
+-- with the session's gift count, total gift value (in the platform's coin
+-- unit), top gifters and per-gift breakdown. NULL for sessions recorded before
+-- gift values were tracked.
+
+ALTER TABLE danmu_statistics
+    ADD COLUMN gift_statistics TEXT;
@@ -0,0 +1,69 @@
+-- no-transaction
+-- Add the SKIPPED job status for jobs whose dependency did not complete.
+--
+-- Runs outside the migrator's transaction so foreign keys can be switched off
+-- while the job table is rebuilt; otherwise dropping it would cascade into
+-- job_execution_logs / job_execution_progress and null out dag_step_execution.job_id.
+
+PRAGMA foreign_keys=OFF;
+
+BEGIN;
+
+CREATE TABLE job_new (
+    id TEXT PRIMARY KEY,
+    job_type TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('PENDING', 'PROCESSING', 'COMPLETED', 'FAILED', 'CANCELLED', 'SKIPPED')),
+    config TEXT NOT NULL,
+    state TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    input TEXT,
+    outputs TEXT,
+    priority INTEGER NOT NULL DEFAULT 0 CHECK (priority >= 0),
+    streamer_id TEXT,
+    session_id TEXT,
+    started_at INTEGER,
+    completed_at INTEGER,
+    error TEXT,
+    retry_count INTEGER NOT NULL DEFAULT 0 CHECK (retry_count >= 0),
+    pipeline_id TEXT,
+    execution_info TEXT,
+    duration_secs REAL,
+    queue_wait_secs REAL,
+    dag_step_execution_id TEXT REFERENCES dag_step_execution(id) ON DELETE SET NULL
+);
+
+INSERT INTO job_new (
+    id, job_type, status, config, state, created_at, updated_at, input, outputs, priority,
+    streamer_id, session_id, started_at, completed_at, error, retry_count, pipeline_id,
+    execution_info, duration_secs, queue_wait_secs, dag_step_execution_id
+)
+SELECT
+    id, job_type, status, config, state, created_at, updated_at, input, outputs, priority,
+    streamer_id, session_id, started_at, completed_at, error, retry_count, pipeline_id,
+    execution_info, duration_secs, queue_wait_secs, dag_step_execution_id
+FROM job;
+
+DROP TABLE job;
+ALTER TABLE job_new RENAME TO job;
+
+CREATE INDEX idx_job_status_created_at ON job(status, created_at DESC);
+CREATE INDEX idx_job_priority_created_at ON job(priority DESC, created_at DESC);
+CREATE INDEX idx_job_updated_at ON job(updated_at);
+CREATE INDEX idx_job_created_at ON job(created_at);
+CREATE INDEX idx_job_streamer_id ON job(streamer_id);
+CREATE INDEX idx_job_session_id ON job(session_id);
+CREATE INDEX idx_job_started_at ON job(started_at);
+CREATE INDEX idx_job_completed_at ON job(completed_at);
+CREATE INDEX idx_job_pipeline_id ON job(pipeline_id);
+CREATE INDEX idx_jobs_completed_at_status ON job(completed_at) WHERE status IN ('COMPLETED', 'FAILED', 'CANCELLED', 'SKIPPED');
+CREATE INDEX idx_job_dag_step ON job(dag_step_execution_id);
+CREATE INDEX idx_job_pending_priority_created_at ON job(priority DESC, created_at DESC) WHERE status = 'PENDING';
+CREATE INDEX idx_job_pending_type_priority_created_at ON job(job_type, priority DESC, created_at DESC) WHERE status = 'PENDING';
+CREATE INDEX idx_job_terminal_updated_at
+    ON job(updated_at)
+    WHERE status IN ('COMPLETED', 'FAILED', 'CANCELLED', 'SKIPPED');
+
+COMMIT;
+
+PRAGMA foreign_keys=ON;
@@ -130,6 +130,8 @@ impl From<Error> for ApiError {
                 ApiError::internal("IO error occurred")
             }
             Error::ApiError(msg) => ApiError::bad_request(msg),
+            Error::CollectionLimitReached { .. } => ApiError::service_unavailable(err.to_string()),
+            Error::ResourceExhausted(_) => ApiError::service_unavailable(err.to_string()),
             _ => {
                 tracing::error!("Unexpected error: {}", err);
                 ApiError::internal("An unexpected error occurred")
@@ -506,12 +506,14 @@ pub struct TemplateResponse {
 /// - `COMPLETED` - Job finished successfully
 /// - `FAILED` - Job encountered an error during processing
 /// - `CANCELLED` - Job was cancelled and remains terminal until retried
+/// - `SKIPPED` - Job never ran because a job it depends on did not complete
 ///
 /// # State Transitions
 ///
 /// ```text
 /// pending -> processing -> completed
 ///                      \-> failed
+/// pending -> skipped (a dependency did not complete)
 /// failed -> pending (via retry)
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -522,6 +524,7 @@ pub enum JobStatus {
     Completed,
     Failed,
     Cancelled,
+    Skipped,
 }
 
 /// Pipeline job response.
@@ -635,6 +638,9 @@ pub struct JobLogEntry {
     pub level: String,
     /// Log message.
     pub message: String,
+    /// Structured context attached by the processor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<serde_json::Value>,
 }
 
 /// Filter parameters for listing jobs.
@@ -690,6 +696,7 @@ pub struct JobFilterParams {
 /// - `completed_count` - Number of successfully completed jobs
 /// - `failed_count` - Number of failed jobs
 /// - `cancelled_count` - Number of cancelled jobs
+/// - `skipped_count` - Number of jobs skipped because a dependency failed
 /// - `avg_processing_time_secs` - Average processing time in seconds (null if no completed jobs)
 #[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
 pub struct PipelineStatsResponse {
@@ -698,6 +705,7 @@ pub struct PipelineStatsResponse {
     pub completed_count: u64,
     pub failed_count: u64,
     pub cancelled_count: u64,
+    pub skipped_count: u64,
     pub avg_processing_time_secs: Option<f64>,
 }
 
@@ -838,6 +838,7 @@ mod tests {
             completed_count: 100,
             failed_count: 5,
             cancelled_count: 1,
+            skipped_count: 0,
             avg_processing_time_secs: Some(45.5),
         };
 
@@ -921,6 +922,10 @@ mod tests {
             api_status_to_job_status(ApiJobStatus::Cancelled),
             JobStatus::Cancelled
         );
+        assert_eq!(
+            api_status_to_job_status(ApiJobStatus::Skipped),
+            JobStatus::Skipped
+        );
     }
 
     #[test]
@@ -945,6 +950,10 @@ mod tests {
             job_status_to_api_status(JobStatus::Cancelled),
             ApiJobStatus::Cancelled
         );
+        assert_eq!(
+            job_status_to_api_status(JobStatus::Skipped),
+            ApiJobStatus::Skipped
+        );
     }
 
     #[test]
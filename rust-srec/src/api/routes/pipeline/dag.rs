@@ -283,7 +283,7 @@ pub async fn retry_dag(
         };
 
         match job.status {
-            JobStatus::Failed | JobStatus::Cancelled => {
+            JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped => {
                 match pipeline_manager.retry_job(job_id).await {
                     Ok(job) => job_ids.push(job.id),
                     Err(e) => tracing::warn!("Failed to retry job {}: {}", job_id, e),
@@ -537,7 +537,7 @@ pub async fn retry_all_failed_dags(
                 continue;
             };
             match job.status {
-                JobStatus::Failed | JobStatus::Cancelled => {
+                JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped => {
                     if let Err(e) = pipeline_manager.retry_job(job_id).await {
                         tracing::warn!(job_id, error = %e, "Failed to retry DAG job");
                     }
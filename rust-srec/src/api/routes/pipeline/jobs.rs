@@ -191,6 +191,7 @@ pub async fn list_job_logs(
             timestamp: log.timestamp,
             level: format!("{:?}", log.level),
             message: log.message,
+            fields: (!log.fields.is_null()).then_some(log.fields),
         })
         .collect();
 
@@ -624,6 +625,7 @@ pub async fn get_stats(
         completed_count: stats.completed,
         failed_count: stats.failed,
         cancelled_count: stats.cancelled,
+        skipped_count: stats.skipped,
         avg_processing_time_secs: stats.avg_processing_time_secs,
     };
 
@@ -705,6 +707,7 @@ pub(super) fn api_status_to_job_status(status: ApiJobStatus) -> JobStatus {
         ApiJobStatus::Completed => JobStatus::Completed,
         ApiJobStatus::Failed => JobStatus::Failed,
         ApiJobStatus::Cancelled => JobStatus::Cancelled,
+        ApiJobStatus::Skipped => JobStatus::Skipped,
     }
 }
 
@@ -716,6 +719,7 @@ pub(super) fn job_status_to_api_status(status: JobStatus) -> ApiJobStatus {
         JobStatus::Completed => ApiJobStatus::Completed,
         JobStatus::Failed => ApiJobStatus::Failed,
         JobStatus::Cancelled => ApiJobStatus::Cancelled,
+        JobStatus::Skipped => ApiJobStatus::Skipped,
     }
 }
 
@@ -739,6 +743,7 @@ fn job_to_response(job: Job, streamer_name: Option<String>) -> JobResponse {
                 timestamp: log.timestamp,
                 level: format!("{:?}", log.level),
                 message: log.message,
+                fields: (!log.fields.is_null()).then_some(log.fields),
             })
             .collect(),
         log_lines_total: info.log_lines_total,
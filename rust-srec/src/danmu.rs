@@ -7,17 +7,36 @@
 
 // Re-export core types from platforms-parser
 pub use platforms_parser::danmaku::{
-    DanmuConnection, DanmuControlEvent, DanmuItem, DanmuMessage, DanmuProvider, DanmuSampler,
-    DanmuSamplingConfig, DanmuStatistics, DanmuType, FixedIntervalSampler, HuyaDanmuProvider,
-    ProviderRegistry, RateDataPoint, StatisticsAggregator, TopTalker, TwitchDanmuProvider,
-    VelocitySampler, WordFrequency, XmlDanmuWriter, create_sampler, escape_xml,
-    message_type_to_int,
+    AttributeStyle, DanmuConnection, DanmuControlEvent, DanmuItem, DanmuMessage, DanmuProvider,
+    DanmuSampler, DanmuSamplingConfig, DanmuStatistics, DanmuType, DanmuXmlFormat,
+    FixedIntervalSampler, HuyaDanmuProvider, ProviderInfo, ProviderRegistry, ProxyConfig,
+    ProxyCredentials, ProxyType, RateDataPoint, RollingWindowStats, StatisticsAggregator,
+    SuperChatEntry, TopGifter, TopTalker, TwitchDanmuProvider, VelocitySampler, WordFrequency,
+    XmlDanmuWriter, create_sampler, escape_xml, message_type_to_int,
 };
 
 // Local modules (application-specific)
+mod ass;
 pub mod events;
+mod filter;
+mod health;
+mod ingest;
+mod jsonl;
+mod keywords;
+mod live_feed;
 mod runner;
 pub mod service;
+mod spam;
+mod subscription;
 
-pub use events::DanmuEvent;
-pub use service::DanmuService;
+pub use ass::{AssDanmuWriter, DanmuAssConfig};
+pub use events::{DanmuEvent, FinalizedSegment, RoomIdSource};
+pub use filter::DanmuFilterConfig;
+pub use health::CollectionHealth;
+pub use ingest::{IngestQueueConfig, OverflowPolicy};
+pub use jsonl::JsonLinesDanmuWriter;
+pub use keywords::KeywordRule;
+pub use live_feed::{LiveFeedConfig, LiveFeedMode};
+pub use service::{ConnectionTestReport, DanmuOutputFormat, DanmuService, FlushPolicy};
+pub use spam::SpamDetectionConfig;
+pub use subscription::DanmuEventStream;
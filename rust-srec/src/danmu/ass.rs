@@ -0,0 +1,438 @@
+//! ASS subtitle writer for danmu segments.
+//!
+//! Renders chat and super chat messages as scrolling `Dialogue` events that
+//! players such as mpv can overlay on the recorded video. Each message moves
+//! from the right edge to the left edge of the screen in a fixed time and is
+//! placed on a horizontal lane where it cannot overlap the previous message
+//! on that lane; messages that fit on no lane are dropped.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::danmu::{DanmuMessage, DanmuType};
+use crate::error::{Error, Result};
+
+/// Name of the style used by every dialogue line.
+const STYLE_NAME: &str = "Danmu";
+
+/// Rendering settings for ASS danmu segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DanmuAssConfig {
+    /// Font family used for the messages.
+    pub font_name: String,
+    /// Font size in script pixels; also the height of a lane.
+    pub font_size: u32,
+    /// Seconds a message takes to cross the screen; lower is faster.
+    pub scroll_duration_secs: f64,
+    /// Number of lanes from the top of the screen that messages scroll on.
+    pub lane_count: u32,
+    /// Message opacity, from `0.0` (invisible) to `1.0` (opaque).
+    pub opacity: f64,
+    /// Script width (`PlayResX`); should match the video aspect ratio.
+    pub play_res_x: u32,
+    /// Script height (`PlayResY`).
+    pub play_res_y: u32,
+}
+
+impl Default for DanmuAssConfig {
+    fn default() -> Self {
+        Self {
+            font_name: "sans-serif".to_string(),
+            font_size: 36,
+            scroll_duration_secs: 8.0,
+            lane_count: 12,
+            opacity: 0.8,
+            play_res_x: 1920,
+            play_res_y: 1080,
+        }
+    }
+}
+
+/// Last message placed on a lane.
+#[derive(Debug, Clone, Copy)]
+struct LaneTail {
+    start_secs: f64,
+    width: f64,
+}
+
+/// Assigns scrolling messages to lanes so that no two messages overlap.
+#[derive(Debug)]
+struct LaneAllocator {
+    screen_width: f64,
+    duration_secs: f64,
+    lanes: Vec<Option<LaneTail>>,
+}
+
+impl LaneAllocator {
+    fn new(screen_width: f64, duration_secs: f64, lane_count: usize) -> Self {
+        Self {
+            screen_width,
+            duration_secs,
+            lanes: vec![None; lane_count],
+        }
+    }
+
+    /// Place a message of `width` pixels entering the screen at `start_secs`,
+    /// returning its lane, or `None` if every lane would collide.
+    fn allocate(&mut self, start_secs: f64, width: f64) -> Option<usize> {
+        let lane = self.lanes.iter().position(|tail| match tail {
+            Some(tail) => self.fits_after(tail, start_secs, width),
+            None => true,
+        })?;
+        self.lanes[lane] = Some(LaneTail { start_secs, width });
+        Some(lane)
+    }
+
+    /// Whether a message can follow `tail` on the same lane without touching it.
+    fn fits_after(&self, tail: &LaneTail, start_secs: f64, width: f64) -> bool {
+        let tail_speed = self.speed(tail.width);
+        let speed = self.speed(width);
+        // The previous message must have fully entered the screen...
+        if start_secs < tail.start_secs + tail.width / tail_speed {
+            return false;
+        }
+        // ...and, if this one is faster, must leave before it is caught up.
+        speed <= tail_speed
+            || start_secs + self.screen_width / speed >= tail.start_secs + self.duration_secs
+    }
+
+    fn speed(&self, width: f64) -> f64 {
+        (self.screen_width + width) / self.duration_secs
+    }
+}
+
+/// ASS writer for danmu messages.
+///
+/// Dialogue timestamps are offsets from the segment start time, so the file
+/// lines up with the video segment it was recorded alongside.
+pub struct AssDanmuWriter {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+    message_count: u64,
+    dropped_count: u64,
+    bytes_written: u64,
+    segment_start_time: DateTime<Utc>,
+    config: DanmuAssConfig,
+    lanes: LaneAllocator,
+}
+
+impl AssDanmuWriter {
+    /// Create the ASS file and write the script header, with `header_comments`
+    /// as comment lines in the `[Script Info]` section.
+    pub async fn create(
+        path: &Path,
+        segment_start_time: DateTime<Utc>,
+        header_comments: &[String],
+        config: DanmuAssConfig,
+    ) -> Result<Self> {
+        let file = File::create(path)
+            .await
+            .map_err(|e| Error::io_path("create", path, e))?;
+        let lanes = LaneAllocator::new(
+            f64::from(config.play_res_x),
+            config.scroll_duration_secs.max(0.1),
+            config.lane_count.max(1) as usize,
+        );
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            file: Some(BufWriter::new(file)),
+            message_count: 0,
+            dropped_count: 0,
+            bytes_written: 0,
+            segment_start_time,
+            config,
+            lanes,
+        };
+        let header = writer.render_header(header_comments);
+        writer.write_str(&header).await?;
+        Ok(writer)
+    }
+
+    /// Get the output path of this writer.
+    pub fn output_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of dialogue lines written so far.
+    pub fn message_count(&self) -> u64 {
+        self.message_count
+    }
+
+    /// Get the number of bytes written so far, including the script header.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Continue appending to the file at `path`, after the file written so
+    /// far was moved there.
+    ///
+    /// Does nothing once the writer is finalized.
+    pub async fn reopen_at(&mut self, path: &Path) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| Error::io_path("open", path, e))?;
+        self.file = Some(BufWriter::new(file));
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
+    /// Get the number of messages dropped because no lane was free.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped_count
+    }
+
+    /// Write a batch of messages, in timestamp order, as dialogue lines.
+    ///
+    /// Only chat and super chat messages are rendered; other message types
+    /// are skipped.
+    pub async fn write_messages(&mut self, messages: &[DanmuMessage]) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let mut out = String::new();
+        for message in messages {
+            if !matches!(message.message_type, DanmuType::Chat | DanmuType::SuperChat)
+                || message.content.trim().is_empty()
+            {
+                continue;
+            }
+            match self.render_dialogue(message) {
+                Some(line) => {
+                    out.push_str(&line);
+                    self.message_count += 1;
+                }
+                None => self.dropped_count += 1,
+            }
+        }
+        if !out.is_empty() {
+            self.write_str(&out).await?;
+        }
+        Ok(())
+    }
+
+    /// Flush and close the file.
+    pub async fn finalize(&mut self) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()
+                .await
+                .map_err(|e| Error::io_path("flush", &self.path, e))?;
+        }
+        Ok(())
+    }
+
+    async fn write_str(&mut self, text: &str) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            file.write_all(text.as_bytes())
+                .await
+                .map_err(|e| Error::io_path("write", &self.path, e))?;
+            file.flush()
+                .await
+                .map_err(|e| Error::io_path("flush", &self.path, e))?;
+            self.bytes_written += text.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn render_header(&self, header_comments: &[String]) -> String {
+        let config = &self.config;
+        let alpha = alpha_hex(config.opacity);
+        let mut out = String::from("[Script Info]\n");
+        for comment in header_comments {
+            let _ = writeln!(out, "; {}", comment.replace(['\r', '\n'], " "));
+        }
+        let _ = write!(
+            out,
+            "ScriptType: v4.00+\n\
+             PlayResX: {}\n\
+             PlayResY: {}\n\
+             WrapStyle: 2\n\
+             ScaledBorderAndShadow: yes\n\
+             \n\
+             [V4+ Styles]\n\
+             Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+             Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, \
+             Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+             Style: {STYLE_NAME},{},{},&H{alpha}FFFFFF,&H{alpha}FFFFFF,&H{alpha}000000,&H{alpha}000000,\
+             0,0,0,0,100,100,0,0,1,1,0,7,0,0,0,1\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+            config.play_res_x,
+            config.play_res_y,
+            config.font_name.replace(',', " "),
+            config.font_size,
+        );
+        out
+    }
+
+    /// Render a message as a dialogue line, or `None` if no lane is free.
+    fn render_dialogue(&mut self, message: &DanmuMessage) -> Option<String> {
+        let start_ms = (message.timestamp - self.segment_start_time)
+            .num_milliseconds()
+            .max(0);
+        let start_secs = start_ms as f64 / 1000.0;
+        let width = text_width(&message.content, self.config.font_size);
+        let lane = self.lanes.allocate(start_secs, width)?;
+
+        let duration_ms = (self.config.scroll_duration_secs.max(0.1) * 1000.0).round() as i64;
+        let y = lane as u64 * u64::from(self.config.font_size);
+        let color = message_color_tag(message).unwrap_or_default();
+        Some(format!(
+            "Dialogue: 0,{},{},{STYLE_NAME},{},0,0,0,,{{\\move({},{y},{},{y})}}{color}{}\n",
+            format_timestamp(start_ms),
+            format_timestamp(start_ms + duration_ms),
+            message.username.replace([',', '\r', '\n'], " "),
+            self.config.play_res_x,
+            -(width.ceil() as i64),
+            escape_text(&message.content),
+        ))
+    }
+}
+
+/// Estimated rendered width of `text`: full-width characters take the font
+/// size, ASCII characters half of it.
+fn text_width(text: &str, font_size: u32) -> f64 {
+    let font_size = f64::from(font_size);
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                font_size / 2.0
+            } else {
+                font_size
+            }
+        })
+        .sum()
+}
+
+/// Format milliseconds as an ASS timestamp (`H:MM:SS.cc`).
+fn format_timestamp(ms: i64) -> String {
+    let cs = ms / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6_000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+/// ASS alpha byte for `opacity` (`00` is opaque, `FF` transparent).
+fn alpha_hex(opacity: f64) -> String {
+    let alpha = ((1.0 - opacity.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    format!("{alpha:02X}")
+}
+
+/// Color override tag for a non-white `#RRGGBB` message color.
+fn message_color_tag(message: &DanmuMessage) -> Option<String> {
+    let raw = message.color.as_deref()?.trim();
+    let hex = raw.strip_prefix('#').unwrap_or(raw);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    if rgb == 0xFF_FF_FF {
+        return None;
+    }
+    let (r, g, b) = (rgb >> 16, (rgb >> 8) & 0xFF, rgb & 0xFF);
+    Some(format!("{{\\c&H{b:02X}{g:02X}{r:02X}&}}"))
+}
+
+/// Keep message text from being parsed as override tags or line breaks.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\u{200B}")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn chat_at(start: DateTime<Utc>, offset_ms: i64, content: &str) -> DanmuMessage {
+        let mut message = DanmuMessage::chat("id", "u1", "user", content);
+        message.timestamp = start + chrono::Duration::milliseconds(offset_ms);
+        message
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "0:00:00.00");
+        assert_eq!(format_timestamp(61_230), "0:01:01.23");
+        assert_eq!(format_timestamp(3_723_450), "1:02:03.45");
+    }
+
+    #[test]
+    fn test_lane_allocator_avoids_collisions() {
+        // 1000px screen, 10s to cross: a 100px message moves at 110px/s and
+        // has fully entered the screen after ~0.91s.
+        let mut lanes = LaneAllocator::new(1000.0, 10.0, 2);
+
+        assert_eq!(lanes.allocate(0.0, 100.0), Some(0));
+        assert_eq!(lanes.allocate(0.5, 100.0), Some(1));
+        assert_eq!(lanes.allocate(0.6, 100.0), None);
+        assert_eq!(lanes.allocate(1.0, 100.0), Some(0));
+        // A much longer (faster) message would catch up with either lane's
+        // tail before it leaves the screen...
+        assert_eq!(lanes.allocate(2.0, 900.0), None);
+        // ...until lane 0's message is gone by the time it reaches the left edge.
+        assert_eq!(lanes.allocate(6.0, 900.0), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_ass_writer_dialogue_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segment.ass");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let config = DanmuAssConfig {
+            font_size: 40,
+            scroll_duration_secs: 8.0,
+            lane_count: 2,
+            opacity: 0.5,
+            ..Default::default()
+        };
+
+        let mut writer = AssDanmuWriter::create(&path, start, &["Room ID: 1".to_string()], config)
+            .await
+            .unwrap();
+        let mut red = chat_at(start, 1_500, "red {text}");
+        red.color = Some("#FF0000".to_string());
+        writer
+            .write_messages(&[
+                chat_at(start, 1_000, "hello"),
+                red,
+                chat_at(start, 1_600, "no lane left"),
+                chat_at(start, 3_000, "你好"),
+            ])
+            .await
+            .unwrap();
+        writer.finalize().await.unwrap();
+
+        assert_eq!(writer.message_count(), 3);
+        assert_eq!(writer.dropped_count(), 1);
+        let ass = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(writer.bytes_written(), ass.len() as u64);
+        assert!(ass.starts_with("[Script Info]\n; Room ID: 1\n"));
+        assert!(ass.contains("PlayResX: 1920\n"));
+        assert!(ass.contains("Style: Danmu,sans-serif,40,&H80FFFFFF,"));
+        let dialogues: Vec<_> = ass.lines().filter(|l| l.starts_with("Dialogue:")).collect();
+        assert_eq!(
+            dialogues,
+            vec![
+                "Dialogue: 0,0:00:01.00,0:00:09.00,Danmu,user,0,0,0,,{\\move(1920,0,-100,0)}hello",
+                "Dialogue: 0,0:00:01.50,0:00:09.50,Danmu,user,0,0,0,,{\\move(1920,40,-200,40)}{\\c&H0000FF&}red \\{text\\}",
+                "Dialogue: 0,0:00:03.00,0:00:11.00,Danmu,user,0,0,0,,{\\move(1920,0,-80,0)}你好",
+            ]
+        );
+    }
+}
@@ -3,11 +3,21 @@
 //! This module defines the events emitted by the danmu service and the
 //! internal commands used to control collection sessions.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use chrono::{DateTime, Utc};
+use platforms_parser::danmaku::{ConnectionConfig, DanmakuError, DanmuProvider};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
 
-use crate::danmu::{DanmuControlEvent, DanmuStatistics};
+use super::filter::DanmuFilter;
+use crate::danmu::{
+    DanmuControlEvent, DanmuMessage, DanmuOutputFormat, DanmuStatistics, KeywordRule,
+};
+use crate::error::{Error, Result};
 
 /// Events emitted by the danmu service.
 ///
@@ -19,11 +29,17 @@ pub enum DanmuEvent {
     CollectionStarted {
         session_id: String,
         streamer_id: String,
+        /// Room the collection connected to
+        room_id: String,
+        /// Where `room_id` came from
+        room_id_source: RoomIdSource,
     },
     /// Collection stopped for a session
     CollectionStopped {
         session_id: String,
         statistics: DanmuStatistics,
+        /// Tags set with `DanmuService::set_session_tags`
+        tags: HashMap<String, String>,
     },
     /// Segment file started
     SegmentStarted {
@@ -40,8 +56,44 @@ pub enum DanmuEvent {
         streamer_id: String,
         segment_id: String,
         output_path: PathBuf,
+        /// Every file of the segment, `output_path` first; the XML and the
+        /// JSON Lines file for `DanmuOutputFormat::Both`
+        output_paths: Vec<PathBuf>,
         message_count: u64,
     },
+    /// Statistics of the messages recorded while a segment was active, sent
+    /// right after its `SegmentCompleted`
+    SegmentEnded {
+        session_id: String,
+        segment_id: String,
+        statistics: DanmuStatistics,
+        /// Files the segment was written to
+        parts: u32,
+        /// Path of the last file written, after any redirect
+        output_path: PathBuf,
+        /// Messages written to the segment, over all parts
+        message_count: u64,
+        /// Bytes written to the segment, over all parts
+        bytes_written: u64,
+    },
+    /// A rotation limit was reached and the segment continues in a new part
+    SegmentRotated {
+        session_id: String,
+        segment_id: String,
+        /// Number of the new part, starting at 2
+        part: u32,
+        /// Output path of the new part
+        path: PathBuf,
+    },
+    /// The runner finalized a segment after `auto_segment_duration_secs` and
+    /// started the next one
+    SegmentRolledOver {
+        session_id: String,
+        old_segment_id: String,
+        new_segment_id: String,
+        /// Output path of the new segment
+        path: PathBuf,
+    },
     /// Platform control event (best-effort signal derived from danmu stream).
     ///
     /// When a provider emits `DanmuControlEvent::StreamClosed`, the runner will shut down
@@ -53,18 +105,160 @@ pub enum DanmuEvent {
         platform: String,
         control: DanmuControlEvent,
     },
+    /// Statistics collected so far, emitted every `stats_snapshot_interval`
+    /// while new messages arrive
+    StatisticsSnapshot {
+        session_id: String,
+        statistics: DanmuStatistics,
+    },
+    /// Individual danmu message, emitted by replay and, when
+    /// `DanmuServiceConfig::live_feed` is set, by live collection
+    Message {
+        session_id: String,
+        streamer_id: String,
+        message: DanmuMessage,
+    },
+    /// Messages of the live feed emitted and dropped in the last second; sent
+    /// only for seconds in which messages arrived
+    LiveFeedSnapshot {
+        session_id: String,
+        sent_count: u64,
+        dropped_count: u64,
+    },
     /// Connection lost and reconnecting
     Reconnecting { session_id: String, attempt: u32 },
+    /// Connection restored; the active segment and statistics were kept
+    Reconnected { session_id: String, attempts: u32 },
     /// Reconnection failed
     ReconnectFailed { session_id: String, error: String },
+    /// No frame arrived from the provider for `stale_after`; sent once until
+    /// frames arrive again
+    ConnectionStale {
+        session_id: String,
+        /// Time since the last frame, or since connecting when none arrived
+        silent_secs: u64,
+    },
+    /// Collection was paused; messages are no longer written to segment files
+    CollectionPaused {
+        session_id: String,
+        streamer_id: String,
+    },
+    /// Collection was resumed after a pause
+    CollectionResumed {
+        session_id: String,
+        streamer_id: String,
+        /// How long the collection was paused
+        paused_secs: u64,
+        /// Messages received while paused that did not fit the pause buffer
+        dropped_count: u64,
+    },
+    /// A user's messages started being dropped as spam
+    SpamDetected {
+        session_id: String,
+        user_id: String,
+        /// Messages of the user suppressed so far in this session
+        suppressed_count: u64,
+    },
+    /// A chat message matched a keyword alert rule; sent at most once per
+    /// rule within `keyword_alert_cooldown`
+    KeywordMatched {
+        session_id: String,
+        rule: KeywordRule,
+        user_id: String,
+        username: String,
+        content: String,
+        timestamp: DateTime<Utc>,
+    },
     /// Error during collection
     Error { session_id: String, error: String },
 }
 
+impl DanmuEvent {
+    /// Session the event belongs to.
+    pub fn session_id(&self) -> &str {
+        match self {
+            Self::CollectionStarted { session_id, .. }
+            | Self::CollectionStopped { session_id, .. }
+            | Self::SegmentStarted { session_id, .. }
+            | Self::SegmentCompleted { session_id, .. }
+            | Self::SegmentEnded { session_id, .. }
+            | Self::SegmentRotated { session_id, .. }
+            | Self::SegmentRolledOver { session_id, .. }
+            | Self::Control { session_id, .. }
+            | Self::StatisticsSnapshot { session_id, .. }
+            | Self::Message { session_id, .. }
+            | Self::LiveFeedSnapshot { session_id, .. }
+            | Self::Reconnecting { session_id, .. }
+            | Self::Reconnected { session_id, .. }
+            | Self::ReconnectFailed { session_id, .. }
+            | Self::ConnectionStale { session_id, .. }
+            | Self::CollectionPaused { session_id, .. }
+            | Self::CollectionResumed { session_id, .. }
+            | Self::SpamDetected { session_id, .. }
+            | Self::KeywordMatched { session_id, .. }
+            | Self::Error { session_id, .. } => session_id,
+        }
+    }
+
+    /// Streamer the event belongs to, for events that carry it.
+    pub fn streamer_id(&self) -> Option<&str> {
+        match self {
+            Self::CollectionStarted { streamer_id, .. }
+            | Self::SegmentStarted { streamer_id, .. }
+            | Self::SegmentCompleted { streamer_id, .. }
+            | Self::Control { streamer_id, .. }
+            | Self::Message { streamer_id, .. }
+            | Self::CollectionPaused { streamer_id, .. }
+            | Self::CollectionResumed { streamer_id, .. } => Some(streamer_id),
+            _ => None,
+        }
+    }
+}
+
+/// A segment file closed by `CollectionHandle::end_segment`.
+///
+/// The closing element has been written and flushed (and the file synced to
+/// disk when `sync_segments_on_end` is set), so the file can be read right away.
+#[derive(Debug, Clone)]
+pub struct FinalizedSegment {
+    pub segment_id: String,
+    pub output_path: PathBuf,
+    /// Every file of the last part, `output_path` first; the XML and the
+    /// JSON Lines file for `DanmuOutputFormat::Both`
+    pub output_paths: Vec<PathBuf>,
+    /// Size of the finished file, the last part when the segment was rotated
+    pub size_bytes: u64,
+    /// Files the segment was written to; more than one when it was rotated
+    pub parts: u32,
+    /// Statistics of the messages recorded while the segment was active
+    pub statistics: DanmuStatistics,
+}
+
+/// Where the room ID of a collection came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomIdSource {
+    /// `StartCollectionOptions::room_id`, given by the caller
+    Override,
+    /// Platform extras, e.g. Huya's `presenter_uid`
+    Extras,
+    /// Extracted from the streamer URL
+    Url,
+}
+
+impl RoomIdSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Override => "override",
+            Self::Extras => "extras",
+            Self::Url => "url",
+        }
+    }
+}
+
 /// Commands sent to the collection task.
 ///
-/// These are internal commands used to control segment file writing
-/// and stop collection from the `CollectionHandle`.
+/// These are internal commands used to control segment file writing,
+/// pause it, switch connections and stop collection from the `CollectionHandle`.
 #[derive(Debug)]
 pub(crate) enum CollectionCommand {
     /// Start a new segment file
@@ -73,9 +267,133 @@ pub(crate) enum CollectionCommand {
         output_path: PathBuf,
         /// The start time of this segment (for danmu timestamp offset calculation).
         start_time: DateTime<Utc>,
+        /// File format of the segment; `None` uses the service's output format
+        format: Option<DanmuOutputFormat>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    /// End the current segment file, replying once it is finalized
+    EndSegment {
+        segment_id: String,
+        reply: oneshot::Sender<Result<FinalizedSegment>>,
+    },
+    /// Move the active segment's file and keep appending at the new path
+    RedirectSegment {
+        segment_id: String,
+        new_output_path: PathBuf,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    /// Reconnect to a different streaming URL, keeping the active segment and statistics
+    SwitchProvider {
+        target: Box<ProviderTarget>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    /// Stop writing messages to segment files, keeping the connection open
+    Pause,
+    /// Resume writing messages after a pause
+    Resume,
+    /// Replace the credentials used for the next (re)connect; `None` keeps
+    /// the current value
+    UpdateCredentials {
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
     },
-    /// End the current segment file
-    EndSegment { segment_id: String },
+    /// Replace the user and content filters; `None` keeps every message
+    UpdateFilters { filter: Option<DanmuFilter> },
+    /// Reply with a snapshot of the statistics collected so far
+    GetStats {
+        reply: oneshot::Sender<DanmuStatistics>,
+    },
+    /// Reply with the number of messages received so far
+    GetMessageCount { reply: oneshot::Sender<u64> },
     /// Stop collection entirely
     Stop,
 }
+
+impl CollectionCommand {
+    /// Fail the command without handling it, replying `reason` as a
+    /// connection error to commands that wait for a reply.
+    pub(crate) fn reject(self, reason: &str) {
+        let error = || Error::from(DanmakuError::connection(reason.to_string()));
+        match self {
+            Self::StartSegment { reply, .. }
+            | Self::SwitchProvider { reply, .. }
+            | Self::RedirectSegment { reply, .. } => {
+                let _ = reply.send(Err(error()));
+            }
+            Self::EndSegment { reply, .. } => {
+                let _ = reply.send(Err(error()));
+            }
+            // Dropping the reply sender fails the caller's receive; the other
+            // commands have no caller waiting.
+            Self::GetStats { .. }
+            | Self::GetMessageCount { .. }
+            | Self::Pause
+            | Self::Resume
+            | Self::UpdateCredentials { .. }
+            | Self::UpdateFilters { .. }
+            | Self::Stop => {}
+        }
+    }
+}
+
+/// Broadcast sender for [`DanmuEvent`]s that warns once the channel backs up.
+#[derive(Clone)]
+pub(crate) struct DanmuEventSender {
+    tx: broadcast::Sender<DanmuEvent>,
+    capacity: usize,
+    /// Whether the channel was more than 75% full after the last send.
+    backlogged: Arc<AtomicBool>,
+}
+
+impl DanmuEventSender {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self {
+            tx,
+            capacity,
+            backlogged: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Send an event, logging a warning when the channel first fills past 75%.
+    ///
+    /// Returns the number of subscribers, or `None` if there are none.
+    pub fn send(&self, event: DanmuEvent) -> Option<usize> {
+        let result = self.tx.send(event).ok();
+        let len = self.tx.len();
+        let backlogged = len * 4 > self.capacity * 3;
+        if self.backlogged.swap(backlogged, Ordering::Relaxed) != backlogged && backlogged {
+            warn!(
+                len,
+                capacity = self.capacity,
+                "Danmu event channel is more than 75% full; slow subscribers will miss events"
+            );
+        }
+        result
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DanmuEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Number of events not yet read by the slowest subscriber.
+    pub fn len(&self) -> usize {
+        self.tx.len()
+    }
+}
+
+/// Connection target for [`CollectionCommand::SwitchProvider`].
+pub(crate) struct ProviderTarget {
+    pub provider: Arc<dyn DanmuProvider>,
+    pub room_id: String,
+    pub conn_config: ConnectionConfig,
+}
+
+impl std::fmt::Debug for ProviderTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderTarget")
+            .field("platform", &self.provider.platform())
+            .field("room_id", &self.room_id)
+            .finish_non_exhaustive()
+    }
+}
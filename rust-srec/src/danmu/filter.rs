@@ -0,0 +1,160 @@
+//! User and content filtering for danmu collection.
+//!
+//! A [`DanmuFilter`] drops messages from blocked users and chat messages whose
+//! content is blocked or too short, before they are counted or written. Checks
+//! are a set lookup, substring scans and one pass of a [`RegexSet`], so they
+//! stay cheap on busy rooms.
+
+use std::collections::HashSet;
+
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+use crate::danmu::{DanmuMessage, DanmuType};
+use crate::error::{Error, Result};
+
+/// Messages to drop during collection.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DanmuFilterConfig {
+    /// Users whose messages are dropped, by platform user ID.
+    pub blocked_user_ids: Vec<String>,
+    /// Users whose name contains any of these texts, ignoring case, are dropped.
+    pub blocked_username_substrings: Vec<String>,
+    /// Chat messages matching any of these regular expressions are dropped.
+    pub blocked_content_patterns: Vec<String>,
+    /// Chat messages with fewer characters than this, ignoring surrounding
+    /// whitespace, are dropped.
+    pub min_message_length: usize,
+}
+
+impl DanmuFilterConfig {
+    /// Whether the config drops no messages.
+    pub fn is_empty(&self) -> bool {
+        self.blocked_user_ids.is_empty()
+            && self.blocked_username_substrings.is_empty()
+            && self.blocked_content_patterns.is_empty()
+            && self.min_message_length == 0
+    }
+}
+
+/// Compiled [`DanmuFilterConfig`].
+#[derive(Debug)]
+pub(crate) struct DanmuFilter {
+    blocked_user_ids: HashSet<String>,
+    blocked_username_substrings: Vec<String>,
+    blocked_content: Option<RegexSet>,
+    min_message_length: usize,
+}
+
+impl DanmuFilter {
+    /// Compile `config`, or `None` when it drops no messages.
+    pub fn new(config: &DanmuFilterConfig) -> Result<Option<Self>> {
+        if config.is_empty() {
+            return Ok(None);
+        }
+        let blocked_content = if config.blocked_content_patterns.is_empty() {
+            None
+        } else {
+            Some(
+                RegexSet::new(&config.blocked_content_patterns).map_err(|e| {
+                    Error::Validation(format!("Invalid danmu content filter: {}", e))
+                })?,
+            )
+        };
+        Ok(Some(Self {
+            blocked_user_ids: config.blocked_user_ids.iter().cloned().collect(),
+            blocked_username_substrings: config
+                .blocked_username_substrings
+                .iter()
+                .filter(|substring| !substring.is_empty())
+                .map(|substring| substring.to_lowercase())
+                .collect(),
+            blocked_content,
+            min_message_length: config.min_message_length,
+        }))
+    }
+
+    /// Whether `message` should be dropped.
+    ///
+    /// User filters apply to every message; content filters only to chat.
+    pub fn is_blocked(&self, message: &DanmuMessage) -> bool {
+        if self.blocked_user_ids.contains(&message.user_id) {
+            return true;
+        }
+        if !self.blocked_username_substrings.is_empty() {
+            let username = message.username.to_lowercase();
+            if self
+                .blocked_username_substrings
+                .iter()
+                .any(|substring| username.contains(substring.as_str()))
+            {
+                return true;
+            }
+        }
+        if message.message_type != DanmuType::Chat {
+            return false;
+        }
+        let content = message.content.trim();
+        content.chars().count() < self.min_message_length
+            || self
+                .blocked_content
+                .as_ref()
+                .is_some_and(|set| set.is_match(content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(user_id: &str, username: &str, content: &str) -> DanmuMessage {
+        DanmuMessage::chat("1", user_id, username, content)
+    }
+
+    #[test]
+    fn test_blocks_users_and_content() {
+        let filter = DanmuFilter::new(&DanmuFilterConfig {
+            blocked_user_ids: vec!["42".to_string()],
+            blocked_username_substrings: vec!["Bot".to_string()],
+            blocked_content_patterns: vec![r"^\d+$".to_string(), "抽奖".to_string()],
+            min_message_length: 2,
+        })
+        .unwrap()
+        .unwrap();
+
+        assert!(filter.is_blocked(&chat("42", "alice", "hello")));
+        assert!(filter.is_blocked(&chat("1", "lottery_bot_7", "hello")));
+        assert!(filter.is_blocked(&chat("1", "alice", "123456")));
+        assert!(filter.is_blocked(&chat("1", "alice", "来抽奖")));
+        assert!(filter.is_blocked(&chat("1", "alice", " a ")));
+        assert!(!filter.is_blocked(&chat("1", "alice", "hello")));
+
+        let mut gift = chat("1", "alice", "1");
+        gift.message_type = DanmuType::Gift;
+        assert!(!filter.is_blocked(&gift));
+        gift.user_id = "42".to_string();
+        assert!(filter.is_blocked(&gift));
+    }
+
+    #[test]
+    fn test_empty_config_compiles_to_none() {
+        assert!(
+            DanmuFilter::new(&DanmuFilterConfig::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_pattern() {
+        let config = DanmuFilterConfig {
+            blocked_content_patterns: vec!["(unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            DanmuFilter::new(&config),
+            Err(Error::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+//! Connection health of active danmu collections.
+//!
+//! Each runner keeps a [`CollectionHealth`] up to date as items arrive and the
+//! connection drops and recovers, so callers can tell a quiet room (frames
+//! still arriving, just no chat) from a connection that silently died.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Connection health of one collection, from `DanmuService::health`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionHealth {
+    pub session_id: String,
+    pub platform: String,
+    pub room_id: String,
+    /// Whether the runner holds a connection; `false` while reconnecting.
+    pub connected: bool,
+    /// When the current connection was established.
+    pub connected_at: DateTime<Utc>,
+    /// Last danmu message received, filtered messages included.
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// Last item of any kind received, control events included.
+    ///
+    /// Heartbeats are answered inside the provider's transport; a room that
+    /// only exchanges heartbeats shows no frames here.
+    pub last_frame_at: Option<DateTime<Utc>>,
+    /// Successful reconnects after the connection dropped.
+    pub reconnect_count: u32,
+    /// Receive errors and failed reconnect attempts since the last frame.
+    pub consecutive_errors: u32,
+    /// Whether no frame arrived for `DanmuServiceConfig::stale_after`.
+    pub stale: bool,
+    /// Messages dropped because the ingest queue was full.
+    pub queue_dropped: u64,
+}
+
+impl CollectionHealth {
+    pub(crate) fn new(session_id: &str, platform: &str, room_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            platform: platform.to_string(),
+            room_id: room_id.to_string(),
+            connected: false,
+            connected_at: Utc::now(),
+            last_message_at: None,
+            last_frame_at: None,
+            reconnect_count: 0,
+            consecutive_errors: 0,
+            stale: false,
+            queue_dropped: 0,
+        }
+    }
+}
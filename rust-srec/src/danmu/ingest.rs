@@ -0,0 +1,188 @@
+//! Bounded queue between the provider connection and message handling.
+//!
+//! Each collection reads every frame the provider has ready into an
+//! [`IngestQueue`] before handling the oldest one, so a burst of gifts does
+//! not back up into the provider's read loop while messages are written and
+//! counted. When the queue is full, its [`OverflowPolicy`] decides whether
+//! reading waits or messages are dropped; dropped messages are counted in
+//! [`DanmuStatistics::queue_dropped_count`](super::DanmuStatistics::queue_dropped_count)
+//! and [`CollectionHealth::queue_dropped`](super::CollectionHealth::queue_dropped).
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::danmu::DanmuItem;
+
+/// Settings of the queue between a collection's connection and its message handling.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestQueueConfig {
+    /// Messages held before the overflow policy applies, at least 1.
+    pub capacity: usize,
+    /// What happens to messages received while the queue is full.
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for IngestQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// What a full ingest queue does with newly received messages.
+///
+/// Control events are always queued, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message to make room.
+    DropOldest,
+    /// Drop the message just received.
+    DropNewest,
+    /// Stop reading from the connection until a message was handled, leaving
+    /// the provider to buffer what arrives meanwhile.
+    #[default]
+    Block,
+}
+
+/// Frames received from the provider and not handled yet.
+pub(crate) struct IngestQueue {
+    config: IngestQueueConfig,
+    items: VecDeque<DanmuItem>,
+    /// Queued messages, which the capacity applies to
+    messages: usize,
+}
+
+impl IngestQueue {
+    pub fn new(config: IngestQueueConfig) -> Self {
+        Self {
+            config: IngestQueueConfig {
+                capacity: config.capacity.max(1),
+                ..config
+            },
+            items: VecDeque::new(),
+            messages: 0,
+        }
+    }
+
+    /// Most frames to read from the connection at once.
+    pub fn capacity(&self) -> usize {
+        self.config.capacity
+    }
+
+    /// Whether a frame may be read now; only `Block` stops reading.
+    pub fn is_accepting(&self) -> bool {
+        self.config.overflow != OverflowPolicy::Block || self.messages < self.config.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Queue `item`, returning how many messages were dropped for it.
+    pub fn push(&mut self, item: DanmuItem) -> u64 {
+        let is_message = matches!(item, DanmuItem::Message(_));
+        let mut dropped = 0;
+        if is_message && self.messages >= self.config.capacity {
+            match self.config.overflow {
+                OverflowPolicy::DropNewest => return 1,
+                OverflowPolicy::DropOldest => {
+                    if let Some(oldest) = self
+                        .items
+                        .iter()
+                        .position(|item| matches!(item, DanmuItem::Message(_)))
+                    {
+                        self.items.remove(oldest);
+                        self.messages -= 1;
+                        dropped = 1;
+                    }
+                }
+                // Callers stop reading while full; never drop what was read.
+                OverflowPolicy::Block => {}
+            }
+        }
+        if is_message {
+            self.messages += 1;
+        }
+        self.items.push_back(item);
+        dropped
+    }
+
+    /// Take the oldest queued frame.
+    pub fn pop(&mut self) -> Option<DanmuItem> {
+        let item = self.items.pop_front()?;
+        if matches!(item, DanmuItem::Message(_)) {
+            self.messages -= 1;
+        }
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmu::{DanmuControlEvent, DanmuMessage};
+
+    fn message(content: &str) -> DanmuItem {
+        DanmuItem::Message(DanmuMessage::chat(content, "u1", "user", content))
+    }
+
+    fn contents(queue: &mut IngestQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|item| match item {
+                DanmuItem::Message(message) => message.content,
+                DanmuItem::Control(_) => "control".to_string(),
+            })
+            .collect()
+    }
+
+    fn queue(capacity: usize, overflow: OverflowPolicy) -> IngestQueue {
+        IngestQueue::new(IngestQueueConfig { capacity, overflow })
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_queued_messages() {
+        let mut queue = queue(2, OverflowPolicy::DropNewest);
+        let dropped: u64 = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|content| queue.push(message(content)))
+            .sum();
+        assert_eq!(dropped, 2);
+        assert!(queue.is_accepting());
+        assert_eq!(contents(&mut queue), ["a", "b"]);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_newest_messages_and_control_events() {
+        let mut queue = queue(2, OverflowPolicy::DropOldest);
+        queue.push(DanmuItem::Control(DanmuControlEvent::Other {
+            kind: "test".to_string(),
+            message: None,
+            metadata: None,
+        }));
+        let dropped: u64 = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|content| queue.push(message(content)))
+            .sum();
+        assert_eq!(dropped, 2);
+        assert_eq!(contents(&mut queue), ["control", "c", "d"]);
+    }
+
+    #[test]
+    fn block_stops_reading_while_full() {
+        let mut queue = queue(2, OverflowPolicy::Block);
+        assert_eq!(queue.push(message("a")), 0);
+        assert!(queue.is_accepting());
+        assert_eq!(queue.push(message("b")), 0);
+        assert!(!queue.is_accepting());
+
+        queue.pop();
+        assert!(queue.is_accepting());
+        assert_eq!(contents(&mut queue), ["b"]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! JSON Lines writer for danmu segments.
+//!
+//! Writes one JSON object per message and line, for analytics tools that
+//! want the raw messages rather than a player format: the timestamp, the
+//! offset from the segment start, the sender, the content, the message type
+//! and the platform metadata as `extras`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::danmu::{DanmuMessage, DanmuType};
+use crate::error::{Error, Result};
+
+/// File extension of JSON Lines segment files.
+pub const JSON_LINES_EXTENSION: &str = "jsonl";
+
+/// One line of a JSON Lines segment file.
+#[derive(Serialize)]
+struct JsonLinesRecord<'a> {
+    timestamp: DateTime<Utc>,
+    /// Milliseconds since the segment start
+    offset_ms: i64,
+    id: &'a str,
+    user_id: &'a str,
+    username: &'a str,
+    content: &'a str,
+    #[serde(rename = "type")]
+    message_type: DanmuType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extras: Option<&'a HashMap<String, serde_json::Value>>,
+}
+
+/// JSON Lines writer for danmu messages.
+pub struct JsonLinesDanmuWriter {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+    message_count: u64,
+    bytes_written: u64,
+    segment_start_time: DateTime<Utc>,
+}
+
+impl JsonLinesDanmuWriter {
+    /// Create the JSON Lines file.
+    pub async fn create(path: &Path, segment_start_time: DateTime<Utc>) -> Result<Self> {
+        let file = File::create(path)
+            .await
+            .map_err(|e| Error::io_path("create", path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Some(BufWriter::new(file)),
+            message_count: 0,
+            bytes_written: 0,
+            segment_start_time,
+        })
+    }
+
+    /// Get the output path of this writer.
+    pub fn output_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of messages written so far.
+    pub fn message_count(&self) -> u64 {
+        self.message_count
+    }
+
+    /// Get the number of bytes written so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Continue appending to the file at `path`, after the file written so
+    /// far was moved there.
+    ///
+    /// Does nothing once the writer is finalized.
+    pub async fn reopen_at(&mut self, path: &Path) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| Error::io_path("open", path, e))?;
+        self.file = Some(BufWriter::new(file));
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
+    /// Write a batch of messages, one line each, with a single flushed write.
+    pub async fn write_messages(&mut self, messages: &[DanmuMessage]) -> Result<()> {
+        if self.file.is_none() || messages.is_empty() {
+            return Ok(());
+        }
+        let mut out = Vec::new();
+        for message in messages {
+            let record = JsonLinesRecord {
+                timestamp: message.timestamp,
+                offset_ms: (message.timestamp - self.segment_start_time)
+                    .num_milliseconds()
+                    .max(0),
+                id: &message.id,
+                user_id: &message.user_id,
+                username: &message.username,
+                content: &message.content,
+                message_type: message.message_type,
+                color: message.color.as_deref(),
+                extras: message.metadata.as_ref(),
+            };
+            serde_json::to_writer(&mut out, &record)?;
+            out.push(b'\n');
+        }
+        if let Some(file) = &mut self.file {
+            file.write_all(&out)
+                .await
+                .map_err(|e| Error::io_path("write", &self.path, e))?;
+            file.flush()
+                .await
+                .map_err(|e| Error::io_path("flush", &self.path, e))?;
+            self.message_count += messages.len() as u64;
+            self.bytes_written += out.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Flush and close the file.
+    pub async fn finalize(&mut self) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()
+                .await
+                .map_err(|e| Error::io_path("flush", &self.path, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_jsonl_writer_writes_one_object_per_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segment.jsonl");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        let mut writer = JsonLinesDanmuWriter::create(&path, start).await.unwrap();
+        let chat = DanmuMessage::chat("m1", "u1", "用户", "hello\nworld")
+            .with_color("#FF0000")
+            .with_timestamp(start + chrono::Duration::milliseconds(1_500));
+        let gift = DanmuMessage::gift("m2", "u2", "Gifter", "rocket", 2)
+            .with_coin_value(1000)
+            .with_timestamp(start + chrono::Duration::seconds(3));
+        writer.write_messages(&[chat, gift]).await.unwrap();
+        writer.finalize().await.unwrap();
+
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(writer.message_count(), 2);
+        assert_eq!(writer.bytes_written(), text.len() as u64);
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["offset_ms"], 1_500);
+        assert_eq!(lines[0]["username"], "用户");
+        assert_eq!(lines[0]["content"], "hello\nworld");
+        assert_eq!(lines[0]["type"], "chat");
+        assert_eq!(lines[0]["color"], "#FF0000");
+        assert!(lines[0].get("extras").is_none());
+        assert_eq!(lines[1]["type"], "gift");
+        assert_eq!(lines[1]["extras"]["coin_value"], 1000);
+        assert_eq!(lines[1]["extras"]["gift_name"], "rocket");
+    }
+}
@@ -0,0 +1,184 @@
+//! Keyword alerts for danmu collection.
+//!
+//! A [`KeywordMatcher`] checks chat messages against configured
+//! [`KeywordRule`]s, counting every match and deciding which ones to alert on,
+//! with at most one alert per rule within the cooldown.
+
+use chrono::{DateTime, Duration, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// A word or pattern to alert on when it appears in chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeywordRule {
+    /// Text to look for, or a regular expression when `is_regex` is set.
+    pub pattern: String,
+    /// Whether `pattern` is a regular expression instead of plain text.
+    #[serde(default)]
+    pub is_regex: bool,
+    /// Whether letter case is ignored when matching.
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+impl KeywordRule {
+    fn compile(&self) -> Result<Regex> {
+        if self.pattern.is_empty() {
+            return Err(Error::Validation(
+                "Keyword alert pattern must not be empty".to_string(),
+            ));
+        }
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()
+            .map_err(|e| {
+                Error::Validation(format!(
+                    "Invalid keyword alert regex {:?}: {}",
+                    self.pattern, e
+                ))
+            })
+    }
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: KeywordRule,
+    regex: Regex,
+    last_alert: Option<DateTime<Utc>>,
+}
+
+/// Match of a message against one rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeywordMatch {
+    pub rule: KeywordRule,
+    /// Whether an alert should be sent, i.e. the rule is out of its cooldown.
+    pub alert: bool,
+}
+
+/// Per-session keyword matcher.
+#[derive(Debug)]
+pub(crate) struct KeywordMatcher {
+    rules: Vec<CompiledRule>,
+    cooldown: Duration,
+}
+
+impl KeywordMatcher {
+    /// Compile `rules`, failing on the first invalid pattern.
+    pub fn new(rules: &[KeywordRule], cooldown: std::time::Duration) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                Ok(CompiledRule {
+                    regex: rule.compile()?,
+                    rule: rule.clone(),
+                    last_alert: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rules,
+            cooldown: Duration::from_std(cooldown).unwrap_or(Duration::MAX),
+        })
+    }
+
+    /// Rules matching `content`, starting the cooldown of those alerted on.
+    pub fn check(&mut self, content: &str, timestamp: DateTime<Utc>) -> Vec<KeywordMatch> {
+        let cooldown = self.cooldown;
+        self.rules
+            .iter_mut()
+            .filter(|compiled| compiled.regex.is_match(content))
+            .map(|compiled| {
+                let alert = compiled
+                    .last_alert
+                    .is_none_or(|last| timestamp - last >= cooldown);
+                if alert {
+                    compiled.last_alert = Some(timestamp);
+                }
+                KeywordMatch {
+                    rule: compiled.rule.clone(),
+                    alert,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + Duration::seconds(secs)
+    }
+
+    fn rule(pattern: &str, is_regex: bool, case_insensitive: bool) -> KeywordRule {
+        KeywordRule {
+            pattern: pattern.to_string(),
+            is_regex,
+            case_insensitive,
+        }
+    }
+
+    #[test]
+    fn test_matches_text_and_regex_rules() {
+        let mut matcher = KeywordMatcher::new(
+            &[
+                rule("抽奖", false, false),
+                rule("giveaway", false, true),
+                rule(r"^\d{6}$", true, false),
+                rule("a.b", false, false),
+            ],
+            std::time::Duration::ZERO,
+        )
+        .unwrap();
+
+        let patterns = |matches: Vec<KeywordMatch>| -> Vec<String> {
+            matches.into_iter().map(|m| m.rule.pattern).collect()
+        };
+        assert_eq!(patterns(matcher.check("今晚抽奖吗", at(0))), vec!["抽奖"]);
+        assert_eq!(
+            patterns(matcher.check("GiveAway soon", at(0))),
+            vec!["giveaway"]
+        );
+        assert_eq!(patterns(matcher.check("123456", at(0))), vec![r"^\d{6}$"]);
+        // Plain text rules do not treat regex metacharacters specially.
+        assert!(matcher.check("axb", at(0)).is_empty());
+        assert_eq!(patterns(matcher.check("a.b", at(0))), vec!["a.b"]);
+    }
+
+    #[test]
+    fn test_alerts_once_per_cooldown() {
+        let mut matcher = KeywordMatcher::new(
+            &[rule("抽奖", false, false)],
+            std::time::Duration::from_secs(30),
+        )
+        .unwrap();
+
+        let alerts = |matcher: &mut KeywordMatcher, secs| {
+            matcher
+                .check("抽奖", at(secs))
+                .into_iter()
+                .map(|m| m.alert)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(alerts(&mut matcher, 0), vec![true]);
+        assert_eq!(alerts(&mut matcher, 10), vec![false]);
+        assert_eq!(alerts(&mut matcher, 29), vec![false]);
+        assert_eq!(alerts(&mut matcher, 30), vec![true]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        let invalid = KeywordMatcher::new(&[rule("(unclosed", true, false)], Default::default());
+        assert!(matches!(invalid, Err(Error::Validation(msg)) if msg.contains("(unclosed")));
+        assert!(KeywordMatcher::new(&[rule("", false, false)], Default::default()).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Rate-limited live message feed.
+//!
+//! When enabled, collections emit received messages as
+//! [`DanmuEvent::Message`](super::DanmuEvent::Message) events for live chat
+//! overlays. A [`LiveFeed`] keeps them under a per-second cap so bursts do not
+//! crowd lifecycle events out of the shared event channel; what it had to
+//! drop is reported once per second in a
+//! [`DanmuEvent::LiveFeedSnapshot`](super::DanmuEvent::LiveFeedSnapshot).
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::danmu::{DanmuMessage, DanmuType};
+
+/// Settings of the live message feed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveFeedConfig {
+    /// Messages emitted per second and collection, at least 1.
+    pub max_per_sec: u32,
+    /// Whether gifts and super chats are emitted besides chat messages.
+    #[serde(default = "default_true")]
+    pub include_gifts: bool,
+    /// Which messages are kept when more arrive than the cap allows.
+    #[serde(default)]
+    pub mode: LiveFeedMode,
+}
+
+impl Default for LiveFeedConfig {
+    fn default() -> Self {
+        Self {
+            max_per_sec: 20,
+            include_gifts: true,
+            mode: LiveFeedMode::default(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How the live feed picks messages within a second.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveFeedMode {
+    /// Emit messages right away, spread evenly over the messages received,
+    /// based on the rate of the previous second.
+    #[default]
+    Uniform,
+    /// Emit the newest `max_per_sec` messages of each second at its end.
+    NewestWins,
+}
+
+/// Messages emitted and dropped by the live feed during one second.
+#[derive(Debug, Default)]
+pub(crate) struct LiveFeedWindow {
+    /// Messages kept by `NewestWins`, to emit now
+    pub messages: Vec<DanmuMessage>,
+    pub sent_count: u64,
+    pub dropped_count: u64,
+}
+
+/// Samples messages for the live feed of one collection.
+pub(crate) struct LiveFeed {
+    config: LiveFeedConfig,
+    /// Messages offered in the current second
+    seen: u64,
+    sent: u64,
+    dropped: u64,
+    /// Messages `Uniform` expects this second, from the rate of the previous one
+    expected: u64,
+    /// `Uniform` adds `max_per_sec` per message and sends one each time it
+    /// reaches `expected`
+    credit: u64,
+    /// Newest messages of the current second, for `NewestWins`
+    newest: VecDeque<DanmuMessage>,
+}
+
+impl LiveFeed {
+    pub fn new(mut config: LiveFeedConfig) -> Self {
+        config.max_per_sec = config.max_per_sec.max(1);
+        Self {
+            seen: 0,
+            sent: 0,
+            dropped: 0,
+            expected: u64::from(config.max_per_sec),
+            credit: 0,
+            newest: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Offer a received message, returning it if it is to be emitted now.
+    pub fn offer(&mut self, message: &DanmuMessage) -> Option<DanmuMessage> {
+        if !self.config.include_gifts
+            && matches!(message.message_type, DanmuType::Gift | DanmuType::SuperChat)
+        {
+            return None;
+        }
+        self.seen += 1;
+        let max = u64::from(self.config.max_per_sec);
+
+        match self.config.mode {
+            LiveFeedMode::Uniform => {
+                if self.sent < max {
+                    self.credit += max;
+                    if self.credit >= self.expected {
+                        self.credit -= self.expected;
+                        self.sent += 1;
+                        return Some(message.clone());
+                    }
+                }
+                self.dropped += 1;
+                None
+            }
+            LiveFeedMode::NewestWins => {
+                if self.newest.len() as u64 >= max {
+                    self.newest.pop_front();
+                    self.dropped += 1;
+                }
+                self.newest.push_back(message.clone());
+                None
+            }
+        }
+    }
+
+    /// End the current second, returning what was emitted and dropped in it.
+    pub fn tick(&mut self) -> LiveFeedWindow {
+        let messages: Vec<_> = self.newest.drain(..).collect();
+        let window = LiveFeedWindow {
+            sent_count: self.sent + messages.len() as u64,
+            dropped_count: self.dropped,
+            messages,
+        };
+        let max = u64::from(self.config.max_per_sec);
+        self.expected = std::mem::take(&mut self.seen).max(max);
+        // The first message of the next second is always sent.
+        self.credit = self.expected - max;
+        self.sent = 0;
+        self.dropped = 0;
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: usize, message_type: DanmuType) -> DanmuMessage {
+        let mut message = DanmuMessage::chat(id.to_string(), "u1", "user", format!("m{id}"));
+        message.message_type = message_type;
+        message
+    }
+
+    fn offer_all(feed: &mut LiveFeed, count: usize) -> Vec<String> {
+        (0..count)
+            .filter_map(|id| feed.offer(&message(id, DanmuType::Chat)))
+            .map(|message| message.id)
+            .collect()
+    }
+
+    #[test]
+    fn uniform_spreads_the_cap_over_a_burst() {
+        let mut feed = LiveFeed::new(LiveFeedConfig {
+            max_per_sec: 2,
+            ..Default::default()
+        });
+
+        // Without a previous rate the first messages go out.
+        assert_eq!(offer_all(&mut feed, 10), ["0", "1"]);
+        let window = feed.tick();
+        assert_eq!((window.sent_count, window.dropped_count), (2, 8));
+
+        // Knowing the rate, the next burst is sampled evenly.
+        assert_eq!(offer_all(&mut feed, 10), ["0", "5"]);
+        assert_eq!(feed.tick().dropped_count, 8);
+    }
+
+    #[test]
+    fn newest_wins_keeps_the_latest_messages_of_each_second() {
+        let mut feed = LiveFeed::new(LiveFeedConfig {
+            max_per_sec: 3,
+            mode: LiveFeedMode::NewestWins,
+            ..Default::default()
+        });
+
+        assert!(offer_all(&mut feed, 10).is_empty());
+        let window = feed.tick();
+        let ids: Vec<_> = window.messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["7", "8", "9"]);
+        assert_eq!((window.sent_count, window.dropped_count), (3, 7));
+        let window = feed.tick();
+        assert!(window.messages.is_empty());
+        assert_eq!((window.sent_count, window.dropped_count), (0, 0));
+    }
+
+    #[test]
+    fn gifts_can_be_left_out() {
+        let mut feed = LiveFeed::new(LiveFeedConfig {
+            include_gifts: false,
+            ..Default::default()
+        });
+
+        assert!(feed.offer(&message(1, DanmuType::Gift)).is_none());
+        assert!(feed.offer(&message(2, DanmuType::SuperChat)).is_none());
+        assert!(feed.offer(&message(3, DanmuType::Chat)).is_some());
+        assert_eq!(feed.tick().dropped_count, 0);
+    }
+}
@@ -3,31 +3,61 @@
 //! This module provides a state machine for running danmu collection with:
 //! - Message buffering and sorting
 //! - Segment-based file writing
-//! - Automatic reconnection
+//! - Automatic reconnection with exponential backoff
 //! - Periodic buffer flushing
+//! - A bounded queue between the connection and message handling
+//! - Pausing segment writing without closing the connection
+//! - Time-based automatic segment rollover
+//! - Splitting segment files into parts by duration, message count or size
+//! - Periodic statistics snapshots
+//! - Connection health tracking with an optional stale-connection watchdog
 
 use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
 use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
 use platforms_parser::danmaku::{
-    ConnectionConfig, DanmuConnection, DanmuControlEvent, DanmuItem, DanmuProvider,
+    ConnectionConfig, DanmuConnection, DanmuConnectionError, DanmuControlEvent, DanmuErrorCode,
+    DanmuItem, DanmuProvider,
     message::{DanmuMessage, DanmuType},
 };
 
-use crate::danmu::{DanmuSampler, DanmuStatistics, StatisticsAggregator, XmlDanmuWriter};
+use crate::danmu::{
+    AssDanmuWriter, DanmuAssConfig, DanmuSampler, DanmuStatistics, DanmuXmlFormat,
+    JsonLinesDanmuWriter, StatisticsAggregator, XmlDanmuWriter,
+};
 use crate::error::{Error, Result};
 
-use super::events::{CollectionCommand, DanmuEvent};
+use super::events::{
+    CollectionCommand, DanmuEvent, DanmuEventSender, FinalizedSegment, ProviderTarget,
+};
+use super::filter::DanmuFilter;
+use super::health::CollectionHealth;
+use super::ingest::{IngestQueue, IngestQueueConfig};
+use super::jsonl::JSON_LINES_EXTENSION;
+use super::keywords::KeywordMatcher;
+use super::live_feed::LiveFeed;
+use super::service::{
+    DanmuOutputFormat, DanmuPauseConfig, DanmuReconnectConfig, DanmuRotationConfig, FlushPolicy,
+};
+use super::spam::{SpamFilter, SpamVerdict};
 
 /// Configuration constants for the collection runner.
 mod config {
-    /// Buffer flush interval in milliseconds.
-    pub const BUFFER_FLUSH_INTERVAL_MS: u64 = 500;
-    /// Maximum number of messages to buffer before forcing a flush.
+    /// Minimum number of messages to buffer before forcing a flush; larger
+    /// write batches raise it to the batch size.
     pub const MAX_BUFFER_SIZE: usize = 100;
+    /// Timeout for connecting to a new provider when switching URLs.
+    pub const SWITCH_CONNECT_TIMEOUT_SECS: u64 = 30;
+    /// Commands queued while the initial connection is being made; further
+    /// commands are rejected until it is ready.
+    pub const MAX_STARTUP_COMMANDS: usize = 32;
 }
 
 /// Result of command handling - indicates whether to continue or stop.
@@ -39,6 +69,248 @@ pub(crate) enum CommandResult {
     Stop,
 }
 
+/// Wait for the next tick of `interval`, or forever when it is disabled.
+async fn tick_if_enabled(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Flushes an XML segment file according to its [`FlushPolicy`].
+struct XmlFlusher {
+    policy: FlushPolicy,
+    fsync: bool,
+    /// Messages written since the last flush.
+    unflushed: u64,
+    last_flush: Instant,
+    /// Flushes made so far.
+    flushes: u64,
+}
+
+impl XmlFlusher {
+    fn new(policy: FlushPolicy, fsync: bool) -> Self {
+        Self {
+            policy,
+            fsync,
+            unflushed: 0,
+            last_flush: Instant::now(),
+            flushes: 0,
+        }
+    }
+
+    fn is_due(&self) -> bool {
+        self.unflushed > 0
+            && match self.policy {
+                FlushPolicy::EveryN(count) => self.unflushed >= count.max(1),
+                FlushPolicy::Interval(interval) => self.last_flush.elapsed() >= interval,
+                FlushPolicy::Never => false,
+            }
+    }
+
+    async fn flush(&mut self, writer: &mut XmlDanmuWriter) -> Result<()> {
+        writer.flush(self.fsync).await?;
+        self.flushes += 1;
+        debug!(
+            path = %writer.output_path().display(),
+            flushes = self.flushes,
+            messages = self.unflushed,
+            total_messages = writer.message_count(),
+            fsync = self.fsync,
+            "danmu: flushed segment file"
+        );
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    async fn flush_if_due(&mut self, writer: &mut XmlDanmuWriter) -> Result<()> {
+        if self.is_due() {
+            self.flush(writer).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Writer for the active segment file, in the segment's output format.
+enum SegmentWriter {
+    Xml(XmlDanmuWriter, XmlFlusher),
+    Ass(AssDanmuWriter),
+    JsonLines(JsonLinesDanmuWriter),
+    /// XML file with a JSON Lines file next to it.
+    Both(XmlDanmuWriter, XmlFlusher, Box<JsonLinesDanmuWriter>),
+    /// XML writer whose finalize first waits for the given delay.
+    #[cfg(test)]
+    Slow(XmlDanmuWriter, Duration),
+}
+
+impl SegmentWriter {
+    fn message_count(&self) -> u64 {
+        match self {
+            Self::Xml(writer, _) | Self::Both(writer, _, _) => writer.message_count(),
+            Self::Ass(writer) => writer.message_count(),
+            Self::JsonLines(writer) => writer.message_count(),
+            #[cfg(test)]
+            Self::Slow(writer, _) => writer.message_count(),
+        }
+    }
+
+    /// Bytes written to the file of [`Self::output_path`], which size-based
+    /// rotation applies to.
+    fn bytes_written(&self) -> u64 {
+        match self {
+            Self::Xml(writer, _) | Self::Both(writer, _, _) => writer.bytes_written(),
+            Self::Ass(writer) => writer.bytes_written(),
+            Self::JsonLines(writer) => writer.bytes_written(),
+            #[cfg(test)]
+            Self::Slow(writer, _) => writer.bytes_written(),
+        }
+    }
+
+    /// Bytes written to every file of [`Self::output_paths`].
+    fn total_bytes_written(&self) -> u64 {
+        match self {
+            Self::Both(_, _, jsonl) => self.bytes_written() + jsonl.bytes_written(),
+            _ => self.bytes_written(),
+        }
+    }
+
+    fn output_path(&self) -> &std::path::Path {
+        match self {
+            Self::Xml(writer, _) | Self::Both(writer, _, _) => writer.output_path(),
+            Self::Ass(writer) => writer.output_path(),
+            Self::JsonLines(writer) => writer.output_path(),
+            #[cfg(test)]
+            Self::Slow(writer, _) => writer.output_path(),
+        }
+    }
+
+    /// Every file written to, the one of [`Self::output_path`] first.
+    fn output_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.output_path().to_path_buf()];
+        if let Self::Both(_, _, jsonl) = self {
+            paths.push(jsonl.output_path().to_path_buf());
+        }
+        paths
+    }
+
+    async fn write_messages(&mut self, messages: &[DanmuMessage]) -> Result<()> {
+        match self {
+            Self::Xml(writer, flusher) => {
+                writer.write_messages(messages).await?;
+                flusher.unflushed += messages.len() as u64;
+                flusher.flush_if_due(writer).await
+            }
+            Self::Ass(writer) => writer.write_messages(messages).await,
+            Self::JsonLines(writer) => writer.write_messages(messages).await,
+            Self::Both(xml, flusher, jsonl) => {
+                xml.write_messages(messages).await?;
+                flusher.unflushed += messages.len() as u64;
+                flusher.flush_if_due(xml).await?;
+                jsonl.write_messages(messages).await
+            }
+            #[cfg(test)]
+            Self::Slow(writer, _) => Ok(writer.write_messages(messages).await?),
+        }
+    }
+
+    /// Flush the file if its flush policy asks for it by now.
+    async fn flush_if_due(&mut self) -> Result<()> {
+        match self {
+            Self::Xml(writer, flusher) | Self::Both(writer, flusher, _) => {
+                flusher.flush_if_due(writer).await
+            }
+            Self::Ass(_) | Self::JsonLines(_) => Ok(()),
+            #[cfg(test)]
+            Self::Slow(..) => Ok(()),
+        }
+    }
+
+    /// Flush everything written so far to the file.
+    async fn flush(&mut self) -> Result<()> {
+        match self {
+            Self::Xml(writer, flusher) | Self::Both(writer, flusher, _) => {
+                flusher.flush(writer).await
+            }
+            // Every ASS and JSON Lines write is flushed already.
+            Self::Ass(_) | Self::JsonLines(_) => Ok(()),
+            #[cfg(test)]
+            Self::Slow(..) => Ok(()),
+        }
+    }
+
+    async fn reopen_at(&mut self, path: &std::path::Path) -> Result<()> {
+        match self {
+            Self::Xml(writer, _) => Ok(writer.reopen_at(path).await?),
+            Self::Ass(writer) => writer.reopen_at(path).await,
+            Self::JsonLines(writer) => writer.reopen_at(path).await,
+            Self::Both(xml, _, jsonl) => {
+                xml.reopen_at(path).await?;
+                jsonl.reopen_at(&json_lines_path(path)).await
+            }
+            #[cfg(test)]
+            Self::Slow(writer, _) => Ok(writer.reopen_at(path).await?),
+        }
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        match self {
+            Self::Xml(writer, _) => Ok(writer.finalize().await?),
+            Self::Ass(writer) => writer.finalize().await,
+            Self::JsonLines(writer) => writer.finalize().await,
+            Self::Both(xml, _, jsonl) => {
+                xml.finalize().await?;
+                jsonl.finalize().await
+            }
+            #[cfg(test)]
+            Self::Slow(writer, delay) => {
+                tokio::time::sleep(*delay).await;
+                Ok(writer.finalize().await?)
+            }
+        }
+    }
+}
+
+/// Path of part `part` of the segment file at `base`: `<stem>_part<N>.<ext>`.
+fn part_path(base: &std::path::Path, part: u32) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match base.extension() {
+        Some(ext) => format!("{stem}_part{part}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_part{part}"),
+    };
+    base.with_file_name(file_name)
+}
+
+/// Path of the JSON Lines file written next to the XML file at `path`.
+fn json_lines_path(path: &std::path::Path) -> PathBuf {
+    path.with_extension(JSON_LINES_EXTENSION)
+}
+
+/// Write out a finalized segment file to disk.
+async fn sync_segment_file(path: &std::path::Path) -> Result<()> {
+    let file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await
+        .map_err(|e| Error::io_path("open", path, e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| Error::io_path("sync", path, e))
+}
+
+/// Failure of [`CollectionRunner::switch_provider`].
+enum SwitchError {
+    /// The switch failed, but the runner is still connected (to the previous target).
+    Rejected(Error),
+    /// Neither the new target nor the previous one could be connected.
+    Lost { switch: Error, fallback: Error },
+}
+
 /// State machine for running a danmu collection session.
 ///
 /// Encapsulates all state and logic for collecting danmu messages,
@@ -52,19 +324,99 @@ pub(crate) struct CollectionRunner {
     // Provider and connection
     provider: Arc<dyn DanmuProvider>,
     connection: DanmuConnection,
+    conn_config: ConnectionConfig,
+
+    // Current segment writer, its format, and whether finalized segments are
+    // synced to disk
+    current_writer: Option<(String, SegmentWriter)>,
+    segment_format: DanmuOutputFormat,
+    sync_segments_on_end: bool,
+    #[cfg(test)]
+    finalize_delay: Option<Duration>,
 
-    // Current segment writer
-    current_writer: Option<(String, XmlDanmuWriter)>,
+    // Rotation into parts: limits, path and start time of the segment's first
+    // part, current part number, when it started and messages in earlier parts
+    rotation: DanmuRotationConfig,
+    segment_base_path: PathBuf,
+    segment_start_time: DateTime<Utc>,
+    segment_part: u32,
+    part_started_at: Option<Instant>,
+    rotated_message_count: u64,
+    rotated_bytes_written: u64,
 
-    // Message buffer for sorting before writing
+    // Automatic rollover: segment length, when the current segment started,
+    // directory of the first explicit segment and the last sequence number used
+    auto_segment_duration: Option<Duration>,
+    segment_started_at: Option<Instant>,
+    auto_segment_dir: Option<PathBuf>,
+    auto_segment_seq: u64,
+
+    // Statistics snapshots: interval and whether messages arrived since the last one
+    stats_snapshot_interval: Option<Duration>,
+    stats_changed: bool,
+
+    // Rate-limited feed of received messages as events, if enabled
+    live_feed: Option<LiveFeed>,
+
+    // Connection health for `DanmuService::health`, and the watchdog: silence
+    // after which the connection is reported stale and when the last frame
+    // (or the connection) arrived
+    health: watch::Sender<CollectionHealth>,
+    stale_after: Option<Duration>,
+    last_frame: Instant,
+
+    // Latest statistics for `stop_collection` to fall back on, and how often
+    // they are published
+    latest_stats_tx: watch::Sender<DanmuStatistics>,
+    latest_stats_interval: Duration,
+
+    // Message buffer for sorting before writing, flushed in batches of
+    // `write_batch_size` messages per write
     message_buffer: Vec<DanmuMessage>,
+    write_batch_size: usize,
+    write_batch_timeout: Duration,
+
+    // Frames read from the connection and not handled yet
+    ingest: IngestQueue,
 
-    // Stats state
+    // Stats state; `segment_stats` only covers the active segment and is
+    // reset whenever it ends
     stats: StatisticsAggregator,
+    segment_stats: StatisticsAggregator,
     sampler: Box<dyn DanmuSampler>,
     sampling_enabled: bool,
 
-    event_tx: broadcast::Sender<DanmuEvent>,
+    // Drops messages from blocked users or with blocked content before they
+    // are counted or written
+    filter: Option<DanmuFilter>,
+
+    // Drops spam messages before they are counted or written
+    spam_filter: Option<SpamFilter>,
+
+    // Counts and alerts on chat messages matching keyword rules
+    keyword_matcher: Option<KeywordMatcher>,
+
+    // Pause state: when the current pause started, messages kept for resume,
+    // messages dropped during this pause and time spent in earlier pauses
+    pause: DanmuPauseConfig,
+    paused_at: Option<DateTime<Utc>>,
+    pause_buffer: Vec<DanmuMessage>,
+    pause_dropped: u64,
+    paused_total: chrono::Duration,
+
+    // Backoff for reconnecting after the connection drops, and commands
+    // received while connecting or reconnecting, handled once the connection is up
+    reconnect: DanmuReconnectConfig,
+    pending_commands: VecDeque<CollectionCommand>,
+
+    // Default format of the segment files and the settings for each format
+    output_format: DanmuOutputFormat,
+    xml_format: DanmuXmlFormat,
+    xml_flush: FlushPolicy,
+    xml_flush_fsync: bool,
+    ass_config: DanmuAssConfig,
+
+    event_tx: DanmuEventSender,
 }
 
 /// Parameters for creating a new collection runner.
@@ -74,28 +426,146 @@ pub(crate) struct RunnerParams {
     pub room_id: String,
     pub provider: Arc<dyn DanmuProvider>,
     pub conn_config: ConnectionConfig,
+    /// Budget for all initial connection attempts together
+    pub connect_timeout: Duration,
+    /// Initial connection attempts after the first one fails
+    pub connect_retries: u32,
+    pub connect_retry_delay: Duration,
     pub stats: StatisticsAggregator,
+    pub segment_stats: StatisticsAggregator,
     pub sampler: Box<dyn DanmuSampler>,
     pub sampling_enabled: bool,
-    pub event_tx: broadcast::Sender<DanmuEvent>,
+    pub filter: Option<DanmuFilter>,
+    pub spam_filter: Option<SpamFilter>,
+    pub keyword_matcher: Option<KeywordMatcher>,
+    pub pause: DanmuPauseConfig,
+    pub reconnect: DanmuReconnectConfig,
+    pub auto_segment_duration: Option<Duration>,
+    pub rotation: DanmuRotationConfig,
+    pub stats_snapshot_interval: Option<Duration>,
+    pub live_feed: Option<LiveFeed>,
+    pub health_tx: watch::Sender<CollectionHealth>,
+    pub stale_after: Option<Duration>,
+    pub latest_stats_tx: watch::Sender<DanmuStatistics>,
+    pub latest_stats_interval: Duration,
+    pub write_batch_size: usize,
+    pub write_batch_timeout: Duration,
+    pub ingest_queue: IngestQueueConfig,
+    pub output_format: DanmuOutputFormat,
+    pub xml_format: DanmuXmlFormat,
+    pub xml_flush: FlushPolicy,
+    pub xml_flush_fsync: bool,
+    pub ass_config: DanmuAssConfig,
+    pub sync_segments_on_end: bool,
+    /// Delay added to every segment finalize, for ordering tests
+    #[cfg(test)]
+    pub finalize_delay: Option<Duration>,
+    pub event_tx: DanmuEventSender,
 }
 
 impl CollectionRunner {
+    /// Create a new collection runner, queueing the commands received while
+    /// the initial connection is made.
+    ///
+    /// Up to [`config::MAX_STARTUP_COMMANDS`] commands are queued and handled
+    /// in the order they were sent, before any later command. If the
+    /// connection fails, every queued or still buffered command is rejected
+    /// and the channel is closed, so callers waiting for a reply get an error.
+    pub async fn start(
+        params: RunnerParams,
+        command_rx: &mut mpsc::Receiver<CollectionCommand>,
+    ) -> Result<Self> {
+        let mut queued = VecDeque::new();
+        let connect = Self::new(params);
+        tokio::pin!(connect);
+        let result = loop {
+            tokio::select! {
+                result = &mut connect => break result,
+                Some(cmd) = command_rx.recv() => {
+                    if queued.len() < config::MAX_STARTUP_COMMANDS {
+                        queued.push_back(cmd);
+                    } else {
+                        cmd.reject("Too many danmu commands queued while connecting");
+                    }
+                }
+            }
+        };
+
+        match result {
+            Ok(mut runner) => {
+                runner.pending_commands = queued;
+                Ok(runner)
+            }
+            Err(e) => {
+                let reason = format!("Danmu collection failed to connect: {e}");
+                command_rx.close();
+                while let Ok(cmd) = command_rx.try_recv() {
+                    queued.push_back(cmd);
+                }
+                for cmd in queued {
+                    cmd.reject(&reason);
+                }
+                Err(e)
+            }
+        }
+    }
+
     /// Create a new collection runner.
-    pub async fn new(params: RunnerParams) -> Result<Self> {
+    async fn new(params: RunnerParams) -> Result<Self> {
         let RunnerParams {
             session_id,
             streamer_id,
             room_id,
             provider,
             conn_config,
+            connect_timeout,
+            connect_retries,
+            connect_retry_delay,
             stats,
+            segment_stats,
             sampler,
             sampling_enabled,
+            filter,
+            spam_filter,
+            keyword_matcher,
+            pause,
+            reconnect,
+            auto_segment_duration,
+            rotation,
+            stats_snapshot_interval,
+            live_feed,
+            health_tx,
+            stale_after,
+            latest_stats_tx,
+            latest_stats_interval,
+            write_batch_size,
+            write_batch_timeout,
+            ingest_queue,
+            output_format,
+            xml_format,
+            xml_flush,
+            xml_flush_fsync,
+            ass_config,
+            sync_segments_on_end,
+            #[cfg(test)]
+            finalize_delay,
             event_tx,
         } = params;
         // Connect to danmu stream
-        let connection = provider.connect(&room_id, conn_config.clone()).await?;
+        let connection = Self::connect_with_retries(
+            &session_id,
+            &provider,
+            &room_id,
+            &conn_config,
+            connect_timeout,
+            connect_retries,
+            connect_retry_delay,
+        )
+        .await?;
+        health_tx.send_modify(|health| {
+            health.connected = true;
+            health.connected_at = Utc::now();
+        });
 
         Ok(Self {
             session_id,
@@ -103,11 +573,54 @@ impl CollectionRunner {
             room_id,
             provider,
             connection,
+            conn_config,
             current_writer: None,
-            message_buffer: Vec::with_capacity(config::MAX_BUFFER_SIZE),
+            segment_format: output_format,
+            sync_segments_on_end,
+            #[cfg(test)]
+            finalize_delay,
+            rotation,
+            segment_base_path: PathBuf::new(),
+            segment_start_time: Utc::now(),
+            segment_part: 1,
+            part_started_at: None,
+            rotated_message_count: 0,
+            rotated_bytes_written: 0,
+            auto_segment_duration,
+            segment_started_at: None,
+            auto_segment_dir: None,
+            auto_segment_seq: 0,
+            stats_snapshot_interval,
+            stats_changed: false,
+            live_feed,
+            health: health_tx,
+            stale_after,
+            last_frame: Instant::now(),
+            latest_stats_tx,
+            latest_stats_interval,
+            message_buffer: Vec::with_capacity(config::MAX_BUFFER_SIZE.max(write_batch_size)),
+            write_batch_size: write_batch_size.max(1),
+            write_batch_timeout,
+            ingest: IngestQueue::new(ingest_queue),
             stats,
+            segment_stats,
             sampler,
             sampling_enabled,
+            filter,
+            spam_filter,
+            keyword_matcher,
+            pause,
+            paused_at: None,
+            pause_buffer: Vec::new(),
+            pause_dropped: 0,
+            paused_total: chrono::Duration::zero(),
+            reconnect,
+            pending_commands: VecDeque::new(),
+            output_format,
+            xml_format,
+            xml_flush,
+            xml_flush_fsync,
+            ass_config,
             event_tx,
         })
     }
@@ -118,12 +631,39 @@ impl CollectionRunner {
         mut command_rx: mpsc::Receiver<CollectionCommand>,
         cancel_token: CancellationToken,
     ) -> Result<DanmuStatistics> {
-        let mut flush_interval = tokio::time::interval(tokio::time::Duration::from_millis(
-            config::BUFFER_FLUSH_INTERVAL_MS,
-        ));
+        let mut flush_interval = tokio::time::interval(self.write_batch_timeout);
         flush_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut snapshot_interval = self.stats_snapshot_interval.map(|period| {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            interval
+        });
+        let mut live_feed_interval = self.live_feed.as_ref().map(|_| {
+            let period = Duration::from_secs(1);
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            interval
+        });
+        let mut watchdog_interval = self.stale_after.map(|stale_after| {
+            let period = stale_after.min(Duration::from_secs(1));
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            interval
+        });
+        let mut latest_stats_interval = tokio::time::interval(self.latest_stats_interval);
+        latest_stats_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
         loop {
+            if let Some(cmd) = self.pending_commands.pop_front() {
+                match self.handle_command(Some(cmd)).await? {
+                    CommandResult::Continue => continue,
+                    CommandResult::Stop => break,
+                }
+            }
+
             tokio::select! {
                 biased;
 
@@ -141,22 +681,171 @@ impl CollectionRunner {
                     break;
                 }
 
-                // Periodic buffer flush
+                // Periodic buffer flush and segment rollover
                 _ = flush_interval.tick() => {
                     self.flush_buffer_if_needed().await?;
+                    if let Some((_, writer)) = &mut self.current_writer {
+                        writer.flush_if_due().await?;
+                    }
+                    self.roll_over_if_due().await?;
+                }
+
+                // Periodic statistics snapshot
+                _ = tick_if_enabled(&mut snapshot_interval) => {
+                    self.emit_statistics_snapshot();
+                }
+
+                // End of a live feed second
+                _ = tick_if_enabled(&mut live_feed_interval) => {
+                    self.end_live_feed_window();
+                }
+
+                // Stale connection watchdog
+                _ = tick_if_enabled(&mut watchdog_interval) => {
+                    self.check_stale();
+                }
+
+                // Statistics to fall back on if stopping times out
+                _ = latest_stats_interval.tick() => {
+                    self.publish_latest_statistics();
                 }
 
                 // Receive danmu messages
-                result = self.provider.receive(&self.connection) => {
-                    match self.handle_receive_result(result).await? {
+                result = self.provider.receive(&self.connection), if self.ingest.is_accepting() => {
+                    match self.ingest_frames(result, &mut command_rx, &cancel_token).await? {
                         CommandResult::Continue => {}
                         CommandResult::Stop => break,
                     }
                 }
+
+                // Handle queued messages while the connection has none ready
+                _ = std::future::ready(()), if !self.ingest.is_empty() => {
+                    if let Some(item) = self.ingest.pop() {
+                        match self.handle_item(item).await? {
+                            CommandResult::Continue => {}
+                            CommandResult::Stop => break,
+                        }
+                    }
+                }
             }
         }
 
-        Ok(self.stats.current_stats())
+        let now = Utc::now();
+        let paused = self.paused_duration(now);
+        let mut statistics = self.stats.finalize(now);
+        if self.pause.exclude_paused_duration {
+            statistics.duration_secs = statistics
+                .duration_secs
+                .saturating_sub(paused.num_seconds().max(0) as u64);
+        }
+        Ok(statistics)
+    }
+
+    /// Emit a statistics snapshot if messages were counted since the last one.
+    fn emit_statistics_snapshot(&mut self) {
+        if !std::mem::take(&mut self.stats_changed) {
+            return;
+        }
+        let _ = self.event_tx.send(DanmuEvent::StatisticsSnapshot {
+            session_id: self.session_id.clone(),
+            statistics: self.live_statistics(),
+        });
+    }
+
+    /// Emit the messages `NewestWins` kept for the last second of the live
+    /// feed and report what was sent and dropped in it.
+    fn end_live_feed_window(&mut self) {
+        let Some(feed) = &mut self.live_feed else {
+            return;
+        };
+        let window = feed.tick();
+        for message in window.messages {
+            self.emit_live_message(message);
+        }
+        if window.sent_count > 0 || window.dropped_count > 0 {
+            let _ = self.event_tx.send(DanmuEvent::LiveFeedSnapshot {
+                session_id: self.session_id.clone(),
+                sent_count: window.sent_count,
+                dropped_count: window.dropped_count,
+            });
+        }
+    }
+
+    fn emit_live_message(&self, message: DanmuMessage) {
+        let _ = self.event_tx.send(DanmuEvent::Message {
+            session_id: self.session_id.clone(),
+            streamer_id: self.streamer_id.clone(),
+            message,
+        });
+    }
+
+    /// Report the connection as stale once no frame arrived for `stale_after`.
+    fn check_stale(&mut self) {
+        let Some(stale_after) = self.stale_after else {
+            return;
+        };
+        let silent = self.last_frame.elapsed();
+        if silent < stale_after || self.health.borrow().stale {
+            return;
+        }
+        self.health.send_modify(|health| health.stale = true);
+        warn!(
+            session_id = %self.session_id,
+            silent_secs = silent.as_secs(),
+            "danmu: connection is stale"
+        );
+        let _ = self.event_tx.send(DanmuEvent::ConnectionStale {
+            session_id: self.session_id.clone(),
+            silent_secs: silent.as_secs(),
+        });
+    }
+
+    /// Record a successful (re)connection in the health state.
+    fn mark_connected(&mut self, reconnected: bool) {
+        self.last_frame = Instant::now();
+        let platform = self.provider.platform().to_string();
+        let room_id = self.room_id.clone();
+        self.health.send_modify(|health| {
+            health.platform = platform;
+            health.room_id = room_id;
+            health.connected = true;
+            health.connected_at = Utc::now();
+            health.stale = false;
+            if reconnected {
+                health.reconnect_count += 1;
+            }
+        });
+    }
+
+    /// Publish the statistics so far for `stop_collection`, if messages
+    /// arrived since they were last published.
+    fn publish_latest_statistics(&self) {
+        if self.latest_stats_tx.borrow().total_count == self.stats.total_count() {
+            return;
+        }
+        self.latest_stats_tx.send_replace(self.live_statistics());
+    }
+
+    /// Snapshot of the statistics so far, with the duration measured up to now.
+    fn live_statistics(&self) -> DanmuStatistics {
+        let now = Utc::now();
+        let mut statistics = self.stats.current_stats();
+        if let Some(start) = statistics.start_time {
+            let mut elapsed = now - start;
+            if self.pause.exclude_paused_duration {
+                elapsed -= self.paused_duration(now);
+            }
+            statistics.duration_secs = elapsed.num_seconds().max(0) as u64;
+        }
+        statistics
+    }
+
+    /// Total time spent paused, including a pause still in progress at `now`.
+    fn paused_duration(&self, now: DateTime<Utc>) -> chrono::Duration {
+        match self.paused_at {
+            Some(since) => self.paused_total + (now - since),
+            None => self.paused_total,
+        }
     }
 
     /// Handle a command from the channel.
@@ -166,15 +855,98 @@ impl CollectionRunner {
                 segment_id,
                 output_path,
                 start_time,
+                format,
+                reply,
             }) => {
-                self.start_segment(segment_id, output_path, start_time)
-                    .await?;
+                if self.auto_segment_dir.is_none() {
+                    self.auto_segment_dir = output_path.parent().map(PathBuf::from);
+                }
+                let format = format.unwrap_or(self.output_format);
+                if let Err(e) = self
+                    .start_segment(segment_id, output_path, start_time, format)
+                    .await
+                {
+                    let _ = reply.send(Err(Error::Other(format!(
+                        "Failed to start danmu segment: {e}"
+                    ))));
+                    return Err(e);
+                }
+                let _ = reply.send(Ok(()));
                 Ok(CommandResult::Continue)
             }
-            Some(CollectionCommand::EndSegment { segment_id }) => {
-                self.end_segment(&segment_id).await?;
+            Some(CollectionCommand::EndSegment { segment_id, reply }) => {
+                let is_active = self
+                    .current_writer
+                    .as_ref()
+                    .is_some_and(|(current_id, _)| *current_id == segment_id);
+                if !is_active {
+                    let _ = reply.send(Err(Error::not_found("Danmu segment", segment_id)));
+                    return Ok(CommandResult::Continue);
+                }
+                let finalized = self.end_segment(&segment_id).await?;
+                let _ = reply.send(Ok(finalized));
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::RedirectSegment {
+                segment_id,
+                new_output_path,
+                reply,
+            }) => {
+                let is_active = self
+                    .current_writer
+                    .as_ref()
+                    .is_some_and(|(current_id, _)| *current_id == segment_id);
+                if !is_active {
+                    let _ = reply.send(Err(Error::not_found("Danmu segment", segment_id)));
+                    return Ok(CommandResult::Continue);
+                }
+                self.flush_buffer().await?;
+                let _ = reply.send(self.redirect_segment(new_output_path).await);
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::Pause) => {
+                self.pause().await?;
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::Resume) => {
+                self.resume().await?;
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::UpdateFilters { filter }) => {
+                self.filter = filter;
                 Ok(CommandResult::Continue)
             }
+            Some(CollectionCommand::UpdateCredentials { cookies, extras }) => {
+                self.update_credentials(cookies, extras);
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::GetStats { reply }) => {
+                let _ = reply.send(self.live_statistics());
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::GetMessageCount { reply }) => {
+                let _ = reply.send(self.stats.total_count());
+                Ok(CommandResult::Continue)
+            }
+            Some(CollectionCommand::SwitchProvider { target, reply }) => {
+                let outcome = self.switch_provider(*target).await;
+                match outcome {
+                    Ok(()) => {
+                        let _ = reply.send(Ok(()));
+                        Ok(CommandResult::Continue)
+                    }
+                    Err(SwitchError::Rejected(e)) => {
+                        let _ = reply.send(Err(e));
+                        Ok(CommandResult::Continue)
+                    }
+                    Err(SwitchError::Lost { switch, fallback }) => {
+                        let _ = reply.send(Err(switch));
+                        self.flush_buffer().await?;
+                        self.finalize_current_segment().await?;
+                        Err(fallback)
+                    }
+                }
+            }
             Some(CollectionCommand::Stop) | None => {
                 self.shutdown().await?;
                 Ok(CommandResult::Stop)
@@ -182,27 +954,60 @@ impl CollectionRunner {
         }
     }
 
-    /// Start a new segment, flushing and finalizing the old one if present.
+    /// Start a new segment in `format`, flushing and finalizing the old one if present.
     async fn start_segment(
         &mut self,
         segment_id: String,
         output_path: PathBuf,
         start_time: DateTime<Utc>,
+        format: DanmuOutputFormat,
     ) -> Result<()> {
         // Flush buffer to old segment before switching
         self.flush_buffer().await?;
 
         // Finalize previous segment if any
         self.finalize_current_segment().await?;
+        self.segment_format = format;
 
-        // Clear buffer for new segment
+        // Clear buffers for new segment
         self.message_buffer.clear();
+        self.pause_buffer.clear();
+
+        let writer = self
+            .open_writer(&segment_id, &output_path, start_time, 1)
+            .await?;
+        let _ = self.event_tx.send(DanmuEvent::SegmentStarted {
+            session_id: self.session_id.clone(),
+            streamer_id: self.streamer_id.clone(),
+            segment_id: segment_id.clone(),
+            output_path: output_path.clone(),
+            start_time,
+        });
+        self.current_writer = Some((segment_id, writer));
+        self.segment_started_at = Some(Instant::now());
+        self.segment_base_path = output_path;
+        self.segment_start_time = start_time;
+        self.segment_part = 1;
+        self.part_started_at = self.segment_started_at;
+        self.rotated_message_count = 0;
+        self.rotated_bytes_written = 0;
 
+        Ok(())
+    }
+
+    /// Create the file for part `part` of a segment.
+    async fn open_writer(
+        &self,
+        segment_id: &str,
+        output_path: &std::path::Path,
+        start_time: DateTime<Utc>,
+        part: u32,
+    ) -> Result<SegmentWriter> {
         // Create output directory if needed
-        crate::utils::fs::ensure_parent_dir(&output_path).await?;
+        crate::utils::fs::ensure_parent_dir(output_path).await?;
 
-        // Start new segment with the provided start time and metadata comments
-        let comments = vec![
+        // Start the file with the segment's start time and metadata comments
+        let mut comments = vec![
             format!("Rust-Srec version: {}", env!("CARGO_PKG_VERSION")),
             format!("Platform: {}", self.provider.platform()),
             format!("Room ID: {}", self.room_id),
@@ -210,58 +1015,634 @@ impl CollectionRunner {
             format!("Segment ID: {}", segment_id),
             format!("Start Time: {}", start_time),
         ];
-        let writer =
-            XmlDanmuWriter::with_start_time_and_comments(&output_path, start_time, comments)
+        if part > 1 {
+            comments.push(format!("Part: {}", part));
+        }
+        let writer = match self.segment_format {
+            DanmuOutputFormat::Xml => {
+                let (writer, flusher) = self.open_xml(output_path, start_time, comments).await?;
+                SegmentWriter::Xml(writer, flusher)
+            }
+            DanmuOutputFormat::Ass => SegmentWriter::Ass(
+                AssDanmuWriter::create(output_path, start_time, &comments, self.ass_config.clone())
+                    .await?,
+            ),
+            DanmuOutputFormat::JsonLines => SegmentWriter::JsonLines(
+                JsonLinesDanmuWriter::create(output_path, start_time).await?,
+            ),
+            DanmuOutputFormat::Both => {
+                let jsonl =
+                    JsonLinesDanmuWriter::create(&json_lines_path(output_path), start_time).await?;
+                let (writer, flusher) = self.open_xml(output_path, start_time, comments).await?;
+                SegmentWriter::Both(writer, flusher, Box::new(jsonl))
+            }
+        };
+        #[cfg(test)]
+        let writer = match (writer, self.finalize_delay) {
+            (SegmentWriter::Xml(writer, _), Some(delay)) => SegmentWriter::Slow(writer, delay),
+            (writer, _) => writer,
+        };
+        Ok(writer)
+    }
+
+    /// Create an XML segment file with its flusher.
+    async fn open_xml(
+        &self,
+        output_path: &std::path::Path,
+        start_time: DateTime<Utc>,
+        comments: Vec<String>,
+    ) -> Result<(XmlDanmuWriter, XmlFlusher)> {
+        let mut writer =
+            XmlDanmuWriter::with_format(output_path, start_time, comments, self.xml_format.clone())
                 .await?;
-        let _ = self.event_tx.send(DanmuEvent::SegmentStarted {
+        writer.set_auto_flush(self.xml_flush == FlushPolicy::Never);
+        Ok((
+            writer,
+            XmlFlusher::new(self.xml_flush, self.xml_flush_fsync),
+        ))
+    }
+
+    /// Continue the active segment in a new part once the current part
+    /// reached a limit of `rotation`.
+    ///
+    /// Checked before each write rather than on a timer, so a segment never
+    /// ends with an empty part.
+    async fn rotate_if_due(&mut self) -> Result<()> {
+        if !self.rotation.is_enabled() {
+            return Ok(());
+        }
+        let Some((_, writer)) = &self.current_writer else {
+            return Ok(());
+        };
+        let messages_due = self
+            .rotation
+            .max_messages
+            .is_some_and(|max| writer.message_count() >= max);
+        let duration_due = self
+            .rotation
+            .max_duration
+            .zip(self.part_started_at)
+            .is_some_and(|(max, started_at)| started_at.elapsed() >= max);
+        let bytes_due = self
+            .rotation
+            .max_bytes
+            .is_some_and(|max| writer.bytes_written() >= max);
+        if messages_due || duration_due || bytes_due {
+            self.rotate_part().await?;
+        }
+        Ok(())
+    }
+
+    /// Finalize the current part of the active segment and open the next one.
+    async fn rotate_part(&mut self) -> Result<()> {
+        let Some((segment_id, mut writer)) = self.current_writer.take() else {
+            return Ok(());
+        };
+        self.rotated_message_count += writer.message_count();
+        writer.finalize().await?;
+        self.rotated_bytes_written += writer.total_bytes_written();
+        if self.sync_segments_on_end {
+            for path in writer.output_paths() {
+                sync_segment_file(&path).await?;
+            }
+        }
+
+        let part = self.segment_part + 1;
+        let path = part_path(&self.segment_base_path, part);
+        let writer = self
+            .open_writer(&segment_id, &path, self.segment_start_time, part)
+            .await?;
+        self.current_writer = Some((segment_id.clone(), writer));
+        self.segment_part = part;
+        self.part_started_at = Some(Instant::now());
+        info!(
+            session_id = %self.session_id,
+            segment_id = %segment_id,
+            part,
+            "danmu: rotated segment file"
+        );
+        let _ = self.event_tx.send(DanmuEvent::SegmentRotated {
             session_id: self.session_id.clone(),
-            streamer_id: self.streamer_id.clone(),
-            segment_id: segment_id.clone(),
-            output_path,
-            start_time,
+            segment_id,
+            part,
+            path,
         });
-        self.current_writer = Some((segment_id, writer));
+        Ok(())
+    }
 
+    /// Start the next automatic segment once the current one has run for
+    /// `auto_segment_duration`.
+    async fn roll_over_if_due(&mut self) -> Result<()> {
+        let (Some(duration), Some(started_at)) =
+            (self.auto_segment_duration, self.segment_started_at)
+        else {
+            return Ok(());
+        };
+        let Some((old_segment_id, _)) = &self.current_writer else {
+            return Ok(());
+        };
+        if started_at.elapsed() < duration {
+            return Ok(());
+        }
+
+        let old_segment_id = old_segment_id.clone();
+        self.auto_segment_seq += 1;
+        let new_segment_id = format!("{}-{}", self.session_id, self.auto_segment_seq);
+        let path = self
+            .auto_segment_dir
+            .clone()
+            .unwrap_or_default()
+            .join(format!(
+                "{new_segment_id}.{}",
+                self.segment_format.extension()
+            ));
+        self.start_segment(
+            new_segment_id.clone(),
+            path.clone(),
+            Utc::now(),
+            self.segment_format,
+        )
+        .await?;
+        info!(
+            session_id = %self.session_id,
+            old_segment_id = %old_segment_id,
+            new_segment_id = %new_segment_id,
+            "danmu: rolled over segment"
+        );
+        let _ = self.event_tx.send(DanmuEvent::SegmentRolledOver {
+            session_id: self.session_id.clone(),
+            old_segment_id,
+            new_segment_id,
+            path,
+        });
         Ok(())
     }
 
-    /// End a specific segment by ID.
-    async fn end_segment(&mut self, target_segment_id: &str) -> Result<()> {
-        if let Some((current_id, _)) = &self.current_writer
-            && current_id == target_segment_id
-        {
-            // Flush buffer before finalizing
+    /// End the active segment, returning its file and statistics once the
+    /// file is complete.
+    async fn end_segment(&mut self, segment_id: &str) -> Result<FinalizedSegment> {
+        // Flush buffer before finalizing
+        self.flush_buffer().await?;
+        self.finalize_current_segment()
+            .await?
+            .ok_or_else(|| Error::not_found("Danmu segment", segment_id))
+    }
+
+    /// Stop writing messages, writing out those received before the pause.
+    async fn pause(&mut self) -> Result<()> {
+        if self.paused_at.is_some() {
+            return Ok(());
+        }
+        self.flush_buffer().await?;
+        self.paused_at = Some(Utc::now());
+        info!(session_id = %self.session_id, "danmu: collection paused");
+        let _ = self.event_tx.send(DanmuEvent::CollectionPaused {
+            session_id: self.session_id.clone(),
+            streamer_id: self.streamer_id.clone(),
+        });
+        Ok(())
+    }
+
+    /// Resume writing messages, starting with those buffered while paused.
+    async fn resume(&mut self) -> Result<()> {
+        let Some(since) = self.paused_at.take() else {
+            return Ok(());
+        };
+        let paused = Utc::now() - since;
+        self.paused_total += paused;
+        let dropped_count = std::mem::take(&mut self.pause_dropped);
+
+        if self.current_writer.is_some() {
+            self.message_buffer.append(&mut self.pause_buffer);
             self.flush_buffer().await?;
-            self.finalize_current_segment().await?;
+        } else {
+            self.pause_buffer.clear();
         }
+
+        info!(
+            session_id = %self.session_id,
+            paused_secs = paused.num_seconds(),
+            dropped_count,
+            "danmu: collection resumed"
+        );
+        let _ = self.event_tx.send(DanmuEvent::CollectionResumed {
+            session_id: self.session_id.clone(),
+            streamer_id: self.streamer_id.clone(),
+            paused_secs: paused.num_seconds().max(0) as u64,
+            dropped_count,
+        });
         Ok(())
     }
 
+    /// Replace the WebSocket connection while keeping the segment writer and statistics.
+    ///
+    /// Messages are not received while this runs, so nothing reaches the XML
+    /// writer until the new connection is in place. When the new target cannot
+    /// be connected, the previous provider and room are reconnected instead.
+    async fn switch_provider(
+        &mut self,
+        target: ProviderTarget,
+    ) -> std::result::Result<(), SwitchError> {
+        let ProviderTarget {
+            provider,
+            room_id,
+            conn_config,
+        } = target;
+
+        // Write out everything received on the old connection first.
+        self.flush_buffer().await.map_err(SwitchError::Rejected)?;
+
+        if let Err(e) = self.provider.disconnect(&mut self.connection).await {
+            warn!(
+                session_id = %self.session_id,
+                error = %e,
+                "danmu: failed to close previous connection during provider switch"
+            );
+        }
+
+        match Self::connect_with_timeout(&provider, &room_id, conn_config.clone()).await {
+            Ok(connection) => {
+                info!(
+                    session_id = %self.session_id,
+                    from_platform = self.provider.platform(),
+                    from_room = %self.room_id,
+                    to_platform = provider.platform(),
+                    to_room = %room_id,
+                    "danmu: switched provider connection"
+                );
+                self.provider = provider;
+                self.room_id = room_id;
+                self.conn_config = conn_config;
+                self.connection = connection;
+                self.mark_connected(false);
+                Ok(())
+            }
+            Err(switch) => {
+                let _ = self.event_tx.send(DanmuEvent::Error {
+                    session_id: self.session_id.clone(),
+                    error: switch.to_string(),
+                });
+                match Self::connect_with_timeout(
+                    &self.provider,
+                    &self.room_id,
+                    self.conn_config.clone(),
+                )
+                .await
+                {
+                    Ok(connection) => {
+                        self.connection = connection;
+                        self.mark_connected(false);
+                        Err(SwitchError::Rejected(switch))
+                    }
+                    Err(fallback) => Err(SwitchError::Lost { switch, fallback }),
+                }
+            }
+        }
+    }
+
+    /// Reconnect to the current provider and room with exponential backoff.
+    ///
+    /// The segment writer and statistics are kept, so messages resume in the
+    /// same XML file. Commands are queued until the connection is back, except
+    /// credential updates, which are applied and end the current backoff
+    /// early; cancellation stops the backoff immediately.
+    async fn reconnect(
+        &mut self,
+        error: Error,
+        command_rx: &mut mpsc::Receiver<CollectionCommand>,
+        cancel_token: &CancellationToken,
+    ) -> Result<CommandResult> {
+        self.flush_buffer().await?;
+        self.health.send_modify(|health| health.connected = false);
+        if let Err(e) = self.provider.disconnect(&mut self.connection).await {
+            warn!(
+                session_id = %self.session_id,
+                error = %e,
+                "danmu: failed to close dropped connection"
+            );
+        }
+
+        let mut last_error = error;
+        for attempt in 1..=self.reconnect.max_attempts {
+            let delay = self.reconnect.delay_for_attempt(attempt);
+            warn!(
+                session_id = %self.session_id,
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %last_error,
+                "danmu: connection lost, reconnecting"
+            );
+            let _ = self.event_tx.send(DanmuEvent::Reconnecting {
+                session_id: self.session_id.clone(),
+                attempt,
+            });
+
+            let backoff = tokio::time::sleep(delay);
+            tokio::pin!(backoff);
+            let mut cancelled = false;
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        cancelled = true;
+                        break;
+                    }
+                    _ = &mut backoff => break,
+                    Some(cmd) = command_rx.recv() => match cmd {
+                        CollectionCommand::UpdateCredentials { cookies, extras } => {
+                            self.update_credentials(cookies, extras);
+                            break;
+                        }
+                        cmd => self.pending_commands.push_back(cmd),
+                    },
+                }
+            }
+            let connected = if cancelled {
+                None
+            } else {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => None,
+                    result = Self::connect_with_timeout(
+                        &self.provider,
+                        &self.room_id,
+                        self.conn_config.clone(),
+                    ) => Some(result),
+                }
+            };
+            match connected {
+                None => {
+                    self.flush_buffer().await?;
+                    self.finalize_current_segment().await?;
+                    return Ok(CommandResult::Stop);
+                }
+                Some(Ok(connection)) => {
+                    self.connection = connection;
+                    self.mark_connected(true);
+                    info!(
+                        session_id = %self.session_id,
+                        attempts = attempt,
+                        "danmu: reconnected"
+                    );
+                    let _ = self.event_tx.send(DanmuEvent::Reconnected {
+                        session_id: self.session_id.clone(),
+                        attempts: attempt,
+                    });
+                    return Ok(CommandResult::Continue);
+                }
+                Some(Err(e)) => {
+                    self.health
+                        .send_modify(|health| health.consecutive_errors += 1);
+                    last_error = e;
+                }
+            }
+        }
+
+        let _ = self.event_tx.send(DanmuEvent::ReconnectFailed {
+            session_id: self.session_id.clone(),
+            error: last_error.to_string(),
+        });
+        self.flush_buffer().await?;
+        self.finalize_current_segment().await?;
+        Err(last_error)
+    }
+
+    /// Replace the cookies and/or extras used for the next (re)connect.
+    fn update_credentials(
+        &mut self,
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
+    ) {
+        // Only log what changed, never the cookie contents.
+        info!(
+            session_id = %self.session_id,
+            cookies_updated = cookies.is_some(),
+            extras_updated = extras.is_some(),
+            "danmu: credentials updated"
+        );
+        if cookies.is_some() {
+            self.conn_config.cookies = cookies;
+        }
+        if extras.is_some() {
+            self.conn_config.extras = extras;
+        }
+    }
+
+    /// Make the initial connection, retrying failed attempts after `retry_delay`
+    /// until `retries` retries or `timeout` overall are used up.
+    ///
+    /// Failures whose code is not retryable end the attempts right away, and a
+    /// rate-limited attempt waits at least the delay the platform asked for.
+    async fn connect_with_retries(
+        session_id: &str,
+        provider: &Arc<dyn DanmuProvider>,
+        room_id: &str,
+        conn_config: &ConnectionConfig,
+        timeout: Duration,
+        retries: u32,
+        retry_delay: Duration,
+    ) -> Result<DanmuConnection> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            let error = match tokio::time::timeout_at(
+                deadline,
+                provider.connect(room_id, conn_config.clone()),
+            )
+            .await
+            {
+                Ok(Ok(connection)) => return Ok(connection),
+                Ok(Err(e)) => e.into_connection_error(),
+                Err(_) => {
+                    return Err(Error::from(DanmuConnectionError::new(
+                        DanmuErrorCode::Timeout,
+                        format!(
+                            "Danmu connection timed out after {:?} (session_id={}, attempts={})",
+                            timeout, session_id, attempt
+                        ),
+                    )));
+                }
+            };
+            if attempt > retries || !error.code.is_retryable() {
+                return Err(Error::from(DanmuConnectionError {
+                    message: format!(
+                        "Danmu connection failed after {} attempt(s): {}",
+                        attempt, error.message
+                    ),
+                    ..error
+                }));
+            }
+            debug!(
+                session_id,
+                attempt,
+                error = %error,
+                "Danmu connection attempt failed; retrying"
+            );
+            let delay = error.retry_after_secs.map_or(retry_delay, |secs| {
+                retry_delay.max(Duration::from_secs(secs))
+            });
+            tokio::time::sleep_until(deadline.min(tokio::time::Instant::now() + delay)).await;
+        }
+    }
+
+    async fn connect_with_timeout(
+        provider: &Arc<dyn DanmuProvider>,
+        room_id: &str,
+        conn_config: ConnectionConfig,
+    ) -> Result<DanmuConnection> {
+        let timeout = tokio::time::Duration::from_secs(config::SWITCH_CONNECT_TIMEOUT_SECS);
+        match tokio::time::timeout(timeout, provider.connect(room_id, conn_config)).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::from(DanmuConnectionError::new(
+                DanmuErrorCode::Timeout,
+                format!(
+                    "Danmu connection timed out after {:?} (room_id={})",
+                    timeout, room_id
+                ),
+            ))),
+        }
+    }
+
     /// Shutdown the runner, flushing and finalizing any active segment.
     async fn shutdown(&mut self) -> Result<()> {
+        // Messages already read still belong to the segment; control events
+        // are moot once stopping.
+        while let Some(item) = self.ingest.pop() {
+            if let DanmuItem::Message(message) = item {
+                self.handle_message(message).await?;
+            }
+        }
         self.flush_buffer().await?;
         self.finalize_current_segment().await?;
         self.provider.disconnect(&mut self.connection).await?;
         Ok(())
     }
 
-    /// Finalize the current segment if one is active.
-    async fn finalize_current_segment(&mut self) -> Result<()> {
-        if let Some((segment_id, mut writer)) = self.current_writer.take() {
-            let count = writer.message_count();
-            let path = writer.output_path().to_path_buf();
-            writer.finalize().await?;
-            let _ = self.event_tx.send(DanmuEvent::SegmentCompleted {
-                session_id: self.session_id.clone(),
-                streamer_id: self.streamer_id.clone(),
-                segment_id,
-                output_path: path,
-                message_count: count,
-            });
+    /// Move the active segment's file to `new_path` and keep appending there.
+    ///
+    /// A JSON Lines file written next to an XML file moves along with it.
+    /// Later parts are created in the new directory, and a redirect of the
+    /// first part also renames them after the new file. On failure the files
+    /// stay at their old paths and the writer keeps using them.
+    async fn redirect_segment(&mut self, new_path: PathBuf) -> Result<()> {
+        let Some((segment_id, writer)) = &mut self.current_writer else {
+            return Ok(());
+        };
+        let old_path = writer.output_path().to_path_buf();
+        if old_path == new_path {
+            return Ok(());
+        }
+        let old_paths = writer.output_paths();
+        let new_paths: Vec<PathBuf> = std::iter::once(new_path.clone())
+            .chain(old_paths.iter().skip(1).map(|_| json_lines_path(&new_path)))
+            .collect();
+        for path in &new_paths {
+            let exists = tokio::fs::try_exists(path)
+                .await
+                .map_err(|e| Error::io_path("stat", path, e))?;
+            if exists {
+                return Err(Error::validation(format!(
+                    "Danmu segment destination {} already exists",
+                    path.display()
+                )));
+            }
+        }
+
+        crate::utils::fs::ensure_parent_dir(&new_path).await?;
+        writer.flush().await?;
+        let mut moved = 0;
+        let mut result = Ok(());
+        for (from, to) in old_paths.iter().zip(&new_paths) {
+            result = crate::utils::fs::move_file(from, to).await;
+            if result.is_err() {
+                break;
+            }
+            moved += 1;
+        }
+        if result.is_ok() {
+            result = writer.reopen_at(&new_path).await;
+        }
+        if let Err(e) = result {
+            for (from, to) in old_paths.iter().zip(&new_paths).take(moved) {
+                if let Err(restore) = crate::utils::fs::move_file(to, from).await {
+                    warn!(
+                        session_id = %self.session_id,
+                        segment_id = %segment_id,
+                        path = %to.display(),
+                        error = %restore,
+                        "danmu: failed to move segment file back after a failed redirect"
+                    );
+                }
+            }
+            return Err(e);
+        }
+
+        info!(
+            session_id = %self.session_id,
+            segment_id = %segment_id,
+            from = %old_path.display(),
+            to = %new_path.display(),
+            "danmu: redirected segment file"
+        );
+        let new_dir = new_path.parent().map(PathBuf::from);
+        self.segment_base_path = match (self.segment_part, &new_dir) {
+            (1, _) | (_, None) => new_path,
+            (_, Some(dir)) => dir.join(self.segment_base_path.file_name().unwrap_or_default()),
+        };
+        if new_dir.is_some() {
+            self.auto_segment_dir = new_dir;
         }
         Ok(())
     }
 
+    /// Finalize the current segment if one is active, returning its file and
+    /// statistics.
+    async fn finalize_current_segment(&mut self) -> Result<Option<FinalizedSegment>> {
+        let Some((segment_id, mut writer)) = self.current_writer.take() else {
+            return Ok(None);
+        };
+        let count = std::mem::take(&mut self.rotated_message_count) + writer.message_count();
+        let path = writer.output_path().to_path_buf();
+        let output_paths = writer.output_paths();
+        writer.finalize().await?;
+        let bytes_written =
+            std::mem::take(&mut self.rotated_bytes_written) + writer.total_bytes_written();
+        if self.sync_segments_on_end {
+            for path in &output_paths {
+                sync_segment_file(path).await?;
+            }
+        }
+        let size_bytes = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| Error::io_path("stat", &path, e))?
+            .len();
+        let statistics = self.segment_stats.checkpoint(Utc::now());
+        let _ = self.event_tx.send(DanmuEvent::SegmentCompleted {
+            session_id: self.session_id.clone(),
+            streamer_id: self.streamer_id.clone(),
+            segment_id: segment_id.clone(),
+            output_path: path.clone(),
+            output_paths: output_paths.clone(),
+            message_count: count,
+        });
+        let _ = self.event_tx.send(DanmuEvent::SegmentEnded {
+            session_id: self.session_id.clone(),
+            segment_id: segment_id.clone(),
+            statistics: statistics.clone(),
+            parts: self.segment_part,
+            output_path: path.clone(),
+            message_count: count,
+            bytes_written,
+        });
+        Ok(Some(FinalizedSegment {
+            segment_id,
+            output_path: path,
+            output_paths,
+            size_bytes,
+            parts: self.segment_part,
+            statistics,
+        }))
+    }
+
     /// Flush the message buffer if there are messages and a writer is active.
     async fn flush_buffer_if_needed(&mut self) -> Result<()> {
         if self.current_writer.is_some() && !self.message_buffer.is_empty() {
@@ -276,23 +1657,95 @@ impl CollectionRunner {
             return Ok(());
         }
 
-        if let Some((_, ref mut writer)) = self.current_writer {
+        if self.current_writer.is_some() {
             // Sort messages by timestamp
             self.message_buffer.sort_by_key(|m| m.timestamp);
 
-            // Write all messages
-            for message in self.message_buffer.drain(..) {
-                writer.write_message(&message).await?;
+            // Write all messages, one write per batch, starting a new part
+            // whenever the current one reaches a rotation limit
+            let mut messages = std::mem::take(&mut self.message_buffer);
+            let mut written = 0;
+            while written < messages.len() {
+                self.rotate_if_due().await?;
+                let Some((_, ref mut writer)) = self.current_writer else {
+                    break;
+                };
+                let part_room = self.rotation.max_messages.map_or(usize::MAX, |max| {
+                    usize::try_from(max.saturating_sub(writer.message_count()))
+                        .unwrap_or(usize::MAX)
+                        .max(1)
+                });
+                let end = messages
+                    .len()
+                    .min(written + self.write_batch_size.min(part_room));
+                writer.write_messages(&messages[written..end]).await?;
+                written = end;
             }
+            messages.clear();
+            self.message_buffer = messages;
         }
 
         Ok(())
     }
 
+    /// Queue a received frame along with every other frame the provider has
+    /// ready, then handle the oldest queued one.
+    ///
+    /// At most the queue's capacity is read at once, so queued frames keep
+    /// being handled while the connection stays busy.
+    async fn ingest_frames(
+        &mut self,
+        result: platforms_parser::danmaku::error::Result<Option<DanmuItem>>,
+        command_rx: &mut mpsc::Receiver<CollectionCommand>,
+        cancel_token: &CancellationToken,
+    ) -> Result<CommandResult> {
+        let mut next = Some(result);
+        let mut read = 0;
+        while let Some(result) = next.take() {
+            match result {
+                Ok(Some(item)) => {
+                    read += 1;
+                    let dropped = self.ingest.push(item);
+                    if dropped > 0 {
+                        self.record_queue_dropped(dropped);
+                    }
+                }
+                // Nothing more ready; handle what was queued.
+                Ok(None) if read > 0 || !self.ingest.is_empty() => break,
+                result => {
+                    return self
+                        .handle_receive_result(result, command_rx, cancel_token)
+                        .await;
+                }
+            }
+            if read < self.ingest.capacity() && self.ingest.is_accepting() {
+                next = self.provider.receive(&self.connection).now_or_never();
+            }
+        }
+
+        match self.ingest.pop() {
+            Some(item) => self.handle_item(item).await,
+            None => Ok(CommandResult::Continue),
+        }
+    }
+
+    /// Count messages the ingest queue dropped.
+    fn record_queue_dropped(&mut self, count: u64) {
+        self.stats_changed = true;
+        self.stats.record_queue_dropped(count);
+        if self.current_writer.is_some() {
+            self.segment_stats.record_queue_dropped(count);
+        }
+        self.health
+            .send_modify(|health| health.queue_dropped += count);
+    }
+
     /// Handle the result of receiving a message from the provider.
     async fn handle_receive_result(
         &mut self,
         result: platforms_parser::danmaku::error::Result<Option<DanmuItem>>,
+        command_rx: &mut mpsc::Receiver<CollectionCommand>,
+        cancel_token: &CancellationToken,
     ) -> Result<CommandResult> {
         match result {
             Ok(Some(item)) => return self.handle_item(item).await,
@@ -301,12 +1754,27 @@ impl CollectionRunner {
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             }
             Err(e) => {
-                // Log the error - reconnection is handled by the transport layer
+                self.health
+                    .send_modify(|health| health.consecutive_errors += 1);
                 let _ = self.event_tx.send(DanmuEvent::Error {
                     session_id: self.session_id.clone(),
                     error: e.to_string(),
                 });
-                // Propagate the error to stop collection if transport layer can't recover
+                // The transport layer retries on its own; once it gives up,
+                // reconnect here so the active segment keeps being written.
+                let transient = match &e {
+                    platforms_parser::danmaku::DanmakuError::Connection(_)
+                    | platforms_parser::danmaku::DanmakuError::Io(_) => true,
+                    platforms_parser::danmaku::DanmakuError::ConnectionFailed(error) => {
+                        error.code.is_retryable()
+                    }
+                    _ => false,
+                };
+                if transient && self.reconnect.max_attempts > 0 {
+                    return self
+                        .reconnect(Error::DanmakuError(e), command_rx, cancel_token)
+                        .await;
+                }
                 return Err(Error::DanmakuError(e));
             }
         }
@@ -314,6 +1782,17 @@ impl CollectionRunner {
     }
 
     async fn handle_item(&mut self, item: DanmuItem) -> Result<CommandResult> {
+        self.last_frame = Instant::now();
+        let now = Utc::now();
+        let is_message = matches!(item, DanmuItem::Message(_));
+        self.health.send_modify(|health| {
+            health.last_frame_at = Some(now);
+            if is_message {
+                health.last_message_at = Some(now);
+            }
+            health.consecutive_errors = 0;
+            health.stale = false;
+        });
         match item {
             DanmuItem::Message(message) => self.handle_message(message).await,
             DanmuItem::Control(control) => self.handle_control(control).await,
@@ -347,19 +1826,62 @@ impl CollectionRunner {
 
     /// Handle a received danmu message.
     async fn handle_message(&mut self, message: DanmuMessage) -> Result<CommandResult> {
-        // Update session-level statistics.
+        // Drop filtered messages and spam before they reach statistics or the
+        // segment file.
+        if let Some(filter) = &self.filter
+            && filter.is_blocked(&message)
+        {
+            self.stats.record_filtered();
+            if self.current_writer.is_some() {
+                self.segment_stats.record_filtered();
+            }
+            return Ok(CommandResult::Continue);
+        }
+
         let is_gift = matches!(message.message_type, DanmuType::Gift | DanmuType::SuperChat);
-        self.stats.record_message(
-            &message.user_id,
-            &message.username,
-            &message.content,
-            is_gift,
-            message.timestamp,
-        );
+        if !is_gift
+            && let Some(filter) = &mut self.spam_filter
+            && let SpamVerdict::Suppress {
+                first,
+                suppressed_count,
+            } = filter.check(&message.user_id, &message.content, message.timestamp)
+        {
+            self.stats.record_spam_suppressed();
+            if self.current_writer.is_some() {
+                self.segment_stats.record_spam_suppressed();
+            }
+            if first {
+                let _ = self.event_tx.send(DanmuEvent::SpamDetected {
+                    session_id: self.session_id.clone(),
+                    user_id: message.user_id,
+                    suppressed_count,
+                });
+            }
+            return Ok(CommandResult::Continue);
+        }
 
-        if self.sampling_enabled {
-            // Update sampler (best-effort; used only when sampling is enabled)
-            self.sampler.record_message(message.timestamp);
+        if message.message_type == DanmuType::Chat {
+            self.check_keywords(&message);
+        }
+
+        if let Some(feed) = &mut self.live_feed
+            && let Some(live) = feed.offer(&message)
+        {
+            self.emit_live_message(live);
+        }
+
+        let paused = self.paused_at.is_some();
+        if !(paused && self.pause.pause_statistics) {
+            self.record_statistics(&message, is_gift);
+        }
+
+        if paused {
+            if self.pause_buffer.len() < self.pause.buffer_capacity {
+                self.pause_buffer.push(message);
+            } else {
+                self.pause_dropped += 1;
+            }
+            return Ok(CommandResult::Continue);
         }
 
         // Buffer the message (will be written on flush)
@@ -367,11 +1889,94 @@ impl CollectionRunner {
             self.message_buffer.push(message);
 
             // Flush if buffer is full
-            if self.message_buffer.len() >= config::MAX_BUFFER_SIZE {
+            if self.message_buffer.len() >= config::MAX_BUFFER_SIZE.max(self.write_batch_size) {
                 self.flush_buffer().await?;
             }
         }
 
         Ok(CommandResult::Continue)
     }
+
+    /// Count keyword rule matches of a chat message and alert on those out of cooldown.
+    fn check_keywords(&mut self, message: &DanmuMessage) {
+        let Some(matcher) = &mut self.keyword_matcher else {
+            return;
+        };
+        for matched in matcher.check(&message.content, message.timestamp) {
+            self.stats.record_keyword_match(&matched.rule.pattern);
+            if self.current_writer.is_some() {
+                self.segment_stats
+                    .record_keyword_match(&matched.rule.pattern);
+            }
+            if matched.alert {
+                let _ = self.event_tx.send(DanmuEvent::KeywordMatched {
+                    session_id: self.session_id.clone(),
+                    rule: matched.rule,
+                    user_id: message.user_id.clone(),
+                    username: message.username.clone(),
+                    content: message.content.clone(),
+                    timestamp: message.timestamp,
+                });
+            }
+        }
+    }
+
+    /// Update session-level statistics, the active segment's statistics and
+    /// the sampler with an accepted message.
+    fn record_statistics(&mut self, message: &DanmuMessage, is_gift: bool) {
+        self.stats_changed = true;
+        record_into(&mut self.stats, message, is_gift);
+        if self.current_writer.is_some() {
+            record_into(&mut self.segment_stats, message, is_gift);
+        }
+
+        if self.sampling_enabled {
+            // Update sampler (best-effort; used only when sampling is enabled)
+            self.sampler.record_message(message.timestamp);
+        }
+    }
+}
+
+/// Record a message in `stats`: a super chat with its price, or a gift with
+/// its value when it carries a coin value (or, for gifts without one, a price).
+fn record_into(stats: &mut StatisticsAggregator, message: &DanmuMessage, is_gift: bool) {
+    let metadata = message.metadata.as_ref();
+    let field = |key: &str| metadata.and_then(|m| m.get(key));
+    if message.message_type == DanmuType::SuperChat {
+        let price = field("price").and_then(|v| v.as_u64()).unwrap_or(0);
+        stats.record_super_chat(
+            &message.user_id,
+            &message.username,
+            &message.content,
+            price,
+            message.timestamp,
+        );
+        return;
+    }
+    match field("coin_value")
+        .or_else(|| field("price"))
+        .and_then(|v| v.as_u64())
+    {
+        Some(value) if is_gift => {
+            let gift_name = field("gift_name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&message.content);
+            let gift_count = field("gift_count").and_then(|v| v.as_u64()).unwrap_or(1);
+            stats.record_gift(
+                &message.user_id,
+                &message.username,
+                gift_name,
+                gift_count,
+                value,
+                message.timestamp,
+            );
+        }
+        _ => stats.record_message(
+            &message.user_id,
+            &message.username,
+            &message.content,
+            is_gift,
+            message.timestamp,
+        ),
+    }
 }
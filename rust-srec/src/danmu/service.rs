@@ -10,26 +10,44 @@
 //! When segment closes → finalize that XML file, but keep collecting danmu
 //! When session ends → stop collection entirely
 
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, broadcast, mpsc, oneshot, watch};
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 use crate::danmu::{
-    DanmuSampler, DanmuSamplingConfig as SamplerConfig, DanmuStatistics, ProviderRegistry,
-    create_sampler,
+    DanmuAssConfig, DanmuSampler, DanmuSamplingConfig as SamplerConfig, DanmuStatistics,
+    DanmuXmlFormat, ProviderRegistry, ProxyConfig, RateDataPoint, create_sampler,
 };
 use crate::database::models::DanmuRateEntry;
 use crate::database::repositories::SessionRepository;
 use crate::domain::DanmuSamplingConfig;
 use crate::error::{Error, Result};
-use platforms_parser::danmaku::ConnectionConfig;
+use parking_lot::RwLock;
+use platforms_parser::danmaku::{
+    ConnectionConfig, DanmuConnectionError, DanmuErrorCode, DanmuProvider, GiftSummary,
+    ProviderInfo, TopGifter,
+};
 
-use super::events::{CollectionCommand, DanmuEvent};
+use super::events::{
+    CollectionCommand, DanmuEvent, DanmuEventSender, FinalizedSegment, ProviderTarget, RoomIdSource,
+};
+use super::filter::{DanmuFilter, DanmuFilterConfig};
+use super::health::CollectionHealth;
+use super::ingest::IngestQueueConfig;
+use super::keywords::{KeywordMatcher, KeywordRule};
+use super::live_feed::{LiveFeed, LiveFeedConfig};
 use super::runner::{CollectionRunner, RunnerParams};
+use super::spam::{SpamDetectionConfig, SpamFilter};
+use super::subscription::DanmuEventStream;
 
 /// Configuration for the danmu service.
 #[derive(Debug, Clone)]
@@ -47,6 +65,364 @@ pub struct DanmuServiceConfig {
     pub default_sampling: DanmuSamplingConfig,
     /// Buffer size for statistics (number of recent messages to keep)
     pub stats_buffer_size: usize,
+    /// Detect the language of chat messages for
+    /// [`DanmuStatistics::language_distribution`], at a CPU cost per message.
+    pub language_detection_enabled: bool,
+    /// File format of the segment files.
+    pub output_format: DanmuOutputFormat,
+    /// Layout of the XML segment files.
+    pub xml_format: DanmuXmlFormat,
+    /// When XML segment files are flushed while they are written.
+    pub xml_flush: FlushPolicy,
+    /// Sync XML segment files to disk on every flush made by `xml_flush`.
+    pub xml_flush_fsync: bool,
+    /// Rendering of the ASS segment files.
+    pub ass: DanmuAssConfig,
+    /// Proxy for provider WebSocket connections, for hosts that cannot reach
+    /// streaming platforms directly.
+    pub proxy: Option<ProxyConfig>,
+    /// Connection settings overridden per platform, keyed by provider platform name
+    /// (e.g. `"bilibili"`, `"huya"`).
+    pub platform_overrides: HashMap<String, PlatformOverride>,
+    /// Messages dropped before they are recorded; overridable per collection
+    /// and updatable with [`CollectionHandle::update_filters`].
+    pub filters: DanmuFilterConfig,
+    /// Drop spam messages before they are recorded; `None` keeps every message.
+    pub spam_detection: Option<SpamDetectionConfig>,
+    /// Rules checked against every chat message, emitting
+    /// [`DanmuEvent::KeywordMatched`]; overridable per collection.
+    pub keyword_alerts: Vec<KeywordRule>,
+    /// Shortest time between two alerts for the same keyword rule.
+    pub keyword_alert_cooldown: Duration,
+    /// Behavior of collections paused with [`CollectionHandle::pause`].
+    pub pause: DanmuPauseConfig,
+    /// Reconnect policy for collections whose connection drops.
+    pub reconnect: DanmuReconnectConfig,
+    /// Start a new segment file after this many seconds of the current one.
+    ///
+    /// Automatic segments are named `<session_id>-<sequence_number>` and written
+    /// next to the first explicitly started segment. `None` leaves segment
+    /// transitions to [`CollectionHandle::start_segment`].
+    pub auto_segment_duration_secs: Option<u64>,
+    /// Split segment files into parts by duration, message count or size.
+    pub rotation: DanmuRotationConfig,
+    /// Emit [`DanmuEvent::StatisticsSnapshot`] for each collection at this
+    /// interval, skipping intervals without new messages. `None` disables snapshots.
+    pub stats_snapshot_interval: Option<Duration>,
+    /// Emit received messages as [`DanmuEvent::Message`] events, at most
+    /// `max_per_sec` per collection. `None` disables the feed.
+    pub live_feed: Option<LiveFeedConfig>,
+    /// Emit [`DanmuEvent::ConnectionStale`] when no frame arrived from the
+    /// provider for this long. `None` disables the watchdog.
+    pub stale_after: Option<Duration>,
+    /// Messages written to the segment file with a single write.
+    ///
+    /// Buffered messages are sorted by timestamp and written in batches of
+    /// this size; raise it for sessions receiving thousands of messages per second.
+    pub write_batch_size: usize,
+    /// Longest time a received message waits in the buffer before it is written.
+    pub write_batch_timeout_ms: u64,
+    /// Queue between each collection's connection and its message handling,
+    /// and what happens to messages when it is full.
+    pub ingest_queue: IngestQueueConfig,
+    /// How long `start_collection` waits for the initial connection,
+    /// including retries.
+    pub connect_timeout: Duration,
+    /// Attempts to make the initial connection after the first one fails.
+    pub connect_retries: u32,
+    /// Delay between two initial connection attempts.
+    pub connect_retry_delay: Duration,
+    /// How long [`DanmuService::test_connection`] waits for the first frame
+    /// after connecting.
+    pub connection_test_wait: Duration,
+    /// How long `stop_collection` waits for the runner to finalize its segment.
+    pub stop_timeout: Duration,
+    /// Deadline for [`DanmuService::shutdown`] to stop all collections, which
+    /// it does concurrently; runners still running afterwards are aborted.
+    pub shutdown_timeout: Duration,
+    /// How often each collection publishes the statistics `stop_collection`
+    /// returns, marked as partial, when the runner does not stop in time.
+    pub partial_statistics_interval: Duration,
+    /// Persist each collection's statistics at this interval while it runs,
+    /// so they survive a crash; `None` persists them only when it stops.
+    /// Needs a session repository.
+    pub persist_interval: Option<Duration>,
+    /// How long [`CollectionHandle::end_segment`] waits for the segment file
+    /// to be finalized, and [`CollectionHandle::start_segment`] for it to be
+    /// created.
+    pub end_segment_timeout: Duration,
+    /// Sync each segment file to disk when it is finalized, so it survives a
+    /// crash once `end_segment` returns.
+    pub sync_segments_on_end: bool,
+    /// Delay added to every segment finalize, for ordering tests.
+    #[cfg(test)]
+    pub(crate) segment_finalize_delay: Option<Duration>,
+    /// Events kept for subscribers that have not read them yet (at least 16).
+    ///
+    /// The channel is shared by every session; subscribers falling further
+    /// behind miss the oldest events.
+    pub event_broadcast_capacity: usize,
+    /// Events buffered for each `subscribe_session`/`subscribe_streamer`
+    /// subscriber; further events are dropped until it catches up.
+    pub subscription_capacity: usize,
+    /// Collections allowed to run at once; `None` allows any number.
+    pub max_concurrent_collections: Option<usize>,
+    /// What `start_collection` does when `max_concurrent_collections` is reached.
+    pub collection_limit_policy: CollectionLimitPolicy,
+}
+
+/// Behavior of `start_collection` when the collection limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollectionLimitPolicy {
+    /// Fail with [`Error::CollectionLimitReached`] right away.
+    #[default]
+    Reject,
+    /// Wait up to `timeout` for a running collection to stop, then fail with
+    /// [`Error::CollectionLimitReached`].
+    Wait { timeout: Duration },
+}
+
+/// Running collections and the configured limit, from [`DanmuService::capacity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectionCapacity {
+    pub used: usize,
+    pub limit: Option<usize>,
+}
+
+/// How the collections stopped by [`DanmuService::shutdown`] ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DanmuShutdownSummary {
+    /// Collections that finalized and returned their statistics.
+    pub stopped: usize,
+    /// Collections still running at `shutdown_timeout`, aborted with their
+    /// partial statistics persisted.
+    pub timed_out: usize,
+    /// Collections that ended with an error or could not be stopped.
+    pub failed: usize,
+}
+
+/// How one collection stopped, from [`DanmuService::stop_within`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopOutcome {
+    Stopped,
+    TimedOut,
+    Failed,
+}
+
+/// An active collection, from [`DanmuService::active_sessions_detailed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSessionInfo {
+    pub session_id: String,
+    pub streamer_id: String,
+    pub streamer_url: String,
+    pub started_at: DateTime<Utc>,
+    /// Messages received so far; `None` when the runner did not answer in
+    /// time, e.g. while it is still connecting.
+    pub message_count: Option<u64>,
+}
+
+/// Result of [`DanmuService::test_connection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionTestReport {
+    pub platform: String,
+    pub room_id: String,
+    pub room_id_source: RoomIdSource,
+    /// Time the provider took to connect.
+    pub handshake_latency: Duration,
+    /// Whether a message or control event arrived within
+    /// [`DanmuServiceConfig::connection_test_wait`].
+    pub message_received: bool,
+}
+
+/// File format of danmu segment files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DanmuOutputFormat {
+    /// Bilibili-compatible XML, laid out by [`DanmuServiceConfig::xml_format`].
+    #[default]
+    Xml,
+    /// ASS subtitles with scrolling messages, rendered per [`DanmuServiceConfig::ass`].
+    Ass,
+    /// One JSON object per message and line, with the raw platform metadata.
+    JsonLines,
+    /// XML plus a JSON Lines file with the same name and a `.jsonl` extension.
+    Both,
+}
+
+impl DanmuOutputFormat {
+    /// File extension of segment files in this format; the XML file's for
+    /// [`Self::Both`].
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Xml | Self::Both => "xml",
+            Self::Ass => "ass",
+            Self::JsonLines => super::jsonl::JSON_LINES_EXTENSION,
+        }
+    }
+}
+
+/// When XML segment files are flushed while they are written.
+///
+/// Flushes end after a complete message element and the closing root tag is
+/// only written when the segment is finalized, so a file cut short by a
+/// crash holds every flushed message and only lacks that tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Flush once this many messages were written since the last flush.
+    EveryN(u64),
+    /// Flush written messages once this long has passed since the last flush.
+    Interval(Duration),
+    /// Hand each write batch to the OS as it is written, without syncing it.
+    #[default]
+    Never,
+}
+
+/// Exponential backoff for reconnecting a dropped danmu connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanmuReconnectConfig {
+    /// Delay before the first reconnect attempt; doubled for each further attempt.
+    pub base_delay: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_delay: Duration,
+    /// Attempts before the collection is stopped; `0` disables reconnecting.
+    pub max_attempts: u32,
+}
+
+impl Default for DanmuReconnectConfig {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl DanmuReconnectConfig {
+    /// Delay before reconnect attempt `attempt` (1-based).
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Limits after which a segment file is split into parts.
+///
+/// When a limit is reached the runner finalizes the current file and writes
+/// the next messages of the same segment to `<base>_partN.<ext>` (N = 2, 3, …).
+/// Every limit is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DanmuRotationConfig {
+    /// Longest time a part is written to.
+    pub max_duration: Option<Duration>,
+    /// Most messages written to a part.
+    pub max_messages: Option<u64>,
+    /// Size after which a part is closed; parts may exceed it by one write batch.
+    /// With [`DanmuOutputFormat::Both`] it applies to the XML file.
+    pub max_bytes: Option<u64>,
+}
+
+impl DanmuRotationConfig {
+    /// Whether any limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_duration.is_some() || self.max_messages.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// Behavior of a paused danmu collection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DanmuPauseConfig {
+    /// Messages kept while paused and written to the active segment on resume;
+    /// further messages are dropped. `0` drops every message received while paused.
+    pub buffer_capacity: usize,
+    /// Whether statistics also stop counting messages while paused.
+    pub pause_statistics: bool,
+    /// Whether time spent paused is left out of `DanmuStatistics::duration_secs`.
+    pub exclude_paused_duration: bool,
+}
+
+/// Per-call settings for [`DanmuService::start_collection_with`].
+#[derive(Debug, Clone, Default)]
+pub struct StartCollectionOptions {
+    /// Sampling configuration; `None` uses the service default.
+    pub sampling_config: Option<DanmuSamplingConfig>,
+    /// Cookies for the platform connection.
+    pub cookies: Option<String>,
+    /// Platform extras used to resolve the room, e.g. Huya's `presenter_uid`.
+    pub extras: Option<HashMap<String, String>>,
+    /// Room ID to connect to, bypassing extraction from `extras` and the URL.
+    ///
+    /// Still checked with [`DanmuProvider::validate_room_id`].
+    pub room_id: Option<String>,
+    /// Overrides [`DanmuServiceConfig::connect_timeout`] for this collection.
+    pub connect_timeout: Option<Duration>,
+    /// Overrides [`DanmuServiceConfig::keyword_alerts`] for this collection.
+    pub keyword_alerts: Option<Vec<KeywordRule>>,
+    /// Overrides [`DanmuServiceConfig::filters`] for this collection.
+    pub filters: Option<DanmuFilterConfig>,
+    /// Statistics of an earlier run of this session to continue from.
+    pub previous_statistics: Option<DanmuStatistics>,
+}
+
+/// Parameters of an active collection, saved before a restart and passed to
+/// [`DanmuService::restore_session`] afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistableSession {
+    pub session_id: String,
+    pub streamer_id: String,
+    pub streamer_url: String,
+    /// Sampling configuration the session was started with; `None` uses the
+    /// service default.
+    pub sampling_config: Option<DanmuSamplingConfig>,
+    pub started_at: DateTime<Utc>,
+    /// Tags set with [`DanmuService::set_session_tags`].
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+/// Connection settings that override the provider defaults for one platform.
+///
+/// Unset fields keep the provider's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformOverride {
+    /// Timeout for establishing the WebSocket connection.
+    pub connect_timeout_secs: Option<u64>,
+    /// Heartbeat interval, replacing the protocol's own interval.
+    pub heartbeat_interval_secs: Option<u64>,
+    /// Maximum reconnect attempts before the connection is given up.
+    pub max_reconnect_attempts: Option<u32>,
+    /// Extra headers for the WebSocket upgrade request.
+    pub extra_headers: HashMap<String, String>,
+}
+
+impl PlatformOverride {
+    /// Merge this override into `config`.
+    pub fn apply(&self, mut config: ConnectionConfig) -> ConnectionConfig {
+        if self.connect_timeout_secs.is_some()
+            || self.heartbeat_interval_secs.is_some()
+            || self.max_reconnect_attempts.is_some()
+        {
+            let mut websocket = config.websocket.unwrap_or_default();
+            if let Some(secs) = self.connect_timeout_secs {
+                websocket.connect_timeout = Some(Duration::from_secs(secs));
+            }
+            if let Some(secs) = self.heartbeat_interval_secs {
+                websocket.heartbeat_interval = Some(Duration::from_secs(secs));
+            }
+            if let Some(attempts) = self.max_reconnect_attempts {
+                websocket.max_reconnect_attempts = attempts;
+            }
+            config.websocket = Some(websocket);
+        }
+
+        if !self.extra_headers.is_empty() {
+            config
+                .extra_headers
+                .get_or_insert_with(HashMap::new)
+                .extend(self.extra_headers.clone());
+        }
+
+        config
+    }
 }
 
 impl Default for DanmuServiceConfig {
@@ -56,6 +432,44 @@ impl Default for DanmuServiceConfig {
             sampling_enabled: false,
             default_sampling: DanmuSamplingConfig::default(),
             stats_buffer_size: 100,
+            language_detection_enabled: false,
+            output_format: DanmuOutputFormat::default(),
+            xml_format: DanmuXmlFormat::default(),
+            xml_flush: FlushPolicy::default(),
+            xml_flush_fsync: false,
+            ass: DanmuAssConfig::default(),
+            proxy: None,
+            platform_overrides: HashMap::new(),
+            filters: DanmuFilterConfig::default(),
+            spam_detection: None,
+            keyword_alerts: Vec::new(),
+            keyword_alert_cooldown: Duration::from_secs(60),
+            pause: DanmuPauseConfig::default(),
+            reconnect: DanmuReconnectConfig::default(),
+            auto_segment_duration_secs: None,
+            rotation: DanmuRotationConfig::default(),
+            stats_snapshot_interval: None,
+            live_feed: None,
+            stale_after: None,
+            write_batch_size: 1,
+            write_batch_timeout_ms: 50,
+            ingest_queue: IngestQueueConfig::default(),
+            connect_timeout: Duration::from_secs(30),
+            connect_retries: 2,
+            connect_retry_delay: Duration::from_secs(1),
+            connection_test_wait: Duration::from_secs(10),
+            stop_timeout: Duration::from_secs(10),
+            shutdown_timeout: Duration::from_secs(15),
+            partial_statistics_interval: Duration::from_secs(5),
+            persist_interval: None,
+            end_segment_timeout: Duration::from_secs(30),
+            sync_segments_on_end: false,
+            #[cfg(test)]
+            segment_finalize_delay: None,
+            event_broadcast_capacity: 1024,
+            subscription_capacity: 256,
+            max_concurrent_collections: None,
+            collection_limit_policy: CollectionLimitPolicy::default(),
         }
     }
 }
@@ -77,48 +491,173 @@ fn to_sampler_config(config: &DanmuSamplingConfig) -> SamplerConfig {
 }
 
 /// Handle for controlling a danmu collection session.
+///
+/// Commands are handled in the order they are sent. A handle can be obtained
+/// with [`DanmuService::get_handle`] while the collection is still
+/// connecting: its commands are queued and handled once the connection is
+/// ready, and fail with an error if it cannot be made.
 #[derive(Clone)]
 pub struct CollectionHandle {
     session_id: String,
     command_tx: mpsc::Sender<CollectionCommand>,
+    end_segment_timeout: Duration,
 }
 
 impl CollectionHandle {
     /// Start writing to a new segment file.
     ///
-    /// The `start_time` is used to calculate danmu timestamp offsets.
+    /// The `start_time` is used to calculate danmu timestamp offsets. Returns
+    /// once the file is created, which waits for the connection while the
+    /// collection is connecting or reconnecting, up to `end_segment_timeout`;
+    /// after a timeout the segment still starts once the connection is back.
     pub async fn start_segment(
         &self,
         segment_id: &str,
         output_path: PathBuf,
         start_time: chrono::DateTime<chrono::Utc>,
     ) -> Result<()> {
-        self.command_tx
-            .send(CollectionCommand::StartSegment {
-                segment_id: segment_id.to_string(),
-                output_path,
-                start_time,
-            })
+        self.start_segment_with_format(segment_id, output_path, start_time, None)
             .await
-            .map_err(|_| {
+    }
+
+    /// Start writing to a new segment file in `format`, or in the service's
+    /// `output_format` (XML by default) when `None`.
+    ///
+    /// With [`DanmuOutputFormat::Both`], `output_path` is the XML file and the
+    /// JSON Lines file is written next to it. Otherwise behaves like
+    /// [`Self::start_segment`].
+    pub async fn start_segment_with_format(
+        &self,
+        segment_id: &str,
+        output_path: PathBuf,
+        start_time: chrono::DateTime<chrono::Utc>,
+        format: Option<DanmuOutputFormat>,
+    ) -> Result<()> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.send(CollectionCommand::StartSegment {
+            segment_id: segment_id.to_string(),
+            output_path,
+            start_time,
+            format,
+            reply,
+        })
+        .await?;
+        match tokio::time::timeout(self.end_segment_timeout, reply_rx).await {
+            Ok(reply) => reply.map_err(|_| {
                 Error::from(platforms_parser::danmaku::DanmakuError::connection(
                     "Collection task not running",
                 ))
-            })
+            })?,
+            Err(_) => Err(Error::Other(format!(
+                "Danmu segment {} was not started within {:?} (session_id={})",
+                segment_id, self.end_segment_timeout, self.session_id
+            ))),
+        }
     }
 
-    /// End the current segment file (finalize XML).
-    pub async fn end_segment(&self, segment_id: &str) -> Result<()> {
-        self.command_tx
-            .send(CollectionCommand::EndSegment {
-                segment_id: segment_id.to_string(),
-            })
-            .await
-            .map_err(|_| {
+    /// End the current segment file and return its path, size and the
+    /// statistics of the messages recorded while it was active.
+    ///
+    /// Returns once the file is complete: buffered messages and the closing
+    /// element are written and flushed, and the file is synced to disk when
+    /// `sync_segments_on_end` is set. Fails if `segment_id` is not the active
+    /// segment, or if finalizing takes longer than `end_segment_timeout`.
+    pub async fn end_segment(&self, segment_id: &str) -> Result<FinalizedSegment> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.send(CollectionCommand::EndSegment {
+            segment_id: segment_id.to_string(),
+            reply,
+        })
+        .await?;
+        match tokio::time::timeout(self.end_segment_timeout, reply_rx).await {
+            Ok(reply) => reply.map_err(|_| {
                 Error::from(platforms_parser::danmaku::DanmakuError::connection(
                     "Collection task not running",
                 ))
-            })
+            })?,
+            Err(_) => Err(Error::Other(format!(
+                "Danmu segment {} was not finalized within {:?} (session_id={})",
+                segment_id, self.end_segment_timeout, self.session_id
+            ))),
+        }
+    }
+
+    /// Move the file the active segment is written to, e.g. to another disk,
+    /// and keep appending to it at `new_output_path`.
+    ///
+    /// Nothing is finalized: the file is renamed, or copied and removed when
+    /// the new path is on another filesystem. Later parts and automatic
+    /// segments are created next to the new file. Fails if `segment_id` is not
+    /// the active segment or the destination already exists; on failure the
+    /// file stays where it was and collection continues there.
+    pub async fn redirect_segment(&self, segment_id: &str, new_output_path: PathBuf) -> Result<()> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.send(CollectionCommand::RedirectSegment {
+            segment_id: segment_id.to_string(),
+            new_output_path,
+            reply,
+        })
+        .await?;
+        reply_rx.await.map_err(|_| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                "Collection task not running",
+            ))
+        })?
+    }
+
+    /// Pause writing danmu to segment files while keeping the connection open.
+    ///
+    /// Segments can still be started and ended while paused. Pausing an already
+    /// paused collection has no effect.
+    pub async fn pause(&self) -> Result<()> {
+        self.send(CollectionCommand::Pause).await
+    }
+
+    /// Resume writing danmu after [`CollectionHandle::pause`].
+    pub async fn resume(&self) -> Result<()> {
+        self.send(CollectionCommand::Resume).await
+    }
+
+    /// Replace the cookies and/or platform extras used the next time the
+    /// collection connects; `None` keeps the current value.
+    ///
+    /// A reconnect waiting out its backoff retries right away with the new
+    /// credentials. The active connection and segment are kept.
+    pub async fn update_credentials(
+        &self,
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
+    ) -> Result<()> {
+        self.send(CollectionCommand::UpdateCredentials { cookies, extras })
+            .await
+    }
+
+    /// Replace the user and content filters of this collection.
+    ///
+    /// Takes effect for the next message received; messages already dropped
+    /// stay counted in `DanmuStatistics::filtered_count`.
+    pub async fn update_filters(&self, filters: &DanmuFilterConfig) -> Result<()> {
+        let filter = DanmuFilter::new(filters)?;
+        self.send(CollectionCommand::UpdateFilters { filter }).await
+    }
+
+    /// Snapshot of the statistics collected so far, without stopping collection.
+    pub async fn current_statistics(&self) -> Result<DanmuStatistics> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.send(CollectionCommand::GetStats { reply }).await?;
+        reply_rx.await.map_err(|_| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                "Collection task not running",
+            ))
+        })
+    }
+
+    async fn send(&self, command: CollectionCommand) -> Result<()> {
+        self.command_tx.send(command).await.map_err(|_| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                "Collection task not running",
+            ))
+        })
     }
 
     /// Get the session ID.
@@ -131,6 +670,12 @@ impl CollectionHandle {
 struct CollectionState {
     /// Streamer ID
     streamer_id: String,
+    /// URL the collection was started for.
+    streamer_url: String,
+    /// Sampling configuration the collection was started with.
+    sampling_config: Option<DanmuSamplingConfig>,
+    /// When the session's collection first started; kept across restores.
+    started_at: DateTime<Utc>,
     /// Cancellation token for this collection
     cancel_token: CancellationToken,
     /// Command sender
@@ -138,6 +683,24 @@ struct CollectionState {
     /// Signals when the runner has fully stopped (including final XML flush/finalize),
     /// carrying final statistics when available.
     done_rx: Option<oneshot::Receiver<std::result::Result<DanmuStatistics, String>>>,
+    /// Statistics last published by the runner, used when it does not stop in time.
+    latest_stats: watch::Receiver<DanmuStatistics>,
+    /// Stops the periodic statistics persists of the collection task.
+    persist_stop: CancellationToken,
+    /// Signals when the periodic persists have stopped.
+    persist_done: Option<oneshot::Receiver<()>>,
+    /// Connection health kept up to date by the runner.
+    health: watch::Receiver<CollectionHealth>,
+    /// Aborts the collection task, for runners that outlast the shutdown deadline.
+    abort_handle: Option<tokio::task::AbortHandle>,
+    /// Cookies the session was started with, reused when switching providers.
+    cookies: Option<String>,
+    /// Platform extras the session was started with, reused when switching providers.
+    extras: Option<HashMap<String, String>>,
+    /// Tags set with `set_session_tags`, persisted with the statistics.
+    tags: watch::Sender<HashMap<String, String>>,
+    /// Slot under `max_concurrent_collections`, released when the state is removed.
+    _slot: Option<OwnedSemaphorePermit>,
 }
 
 #[derive(Debug, Default)]
@@ -163,14 +726,16 @@ impl DanmuSampler for NoopSampler {
 pub struct DanmuService {
     /// Configuration
     config: DanmuServiceConfig,
-    /// Provider registry
-    providers: Arc<ProviderRegistry>,
+    /// Provider registry, extended by [`DanmuService::register_provider`]
+    providers: Arc<RwLock<ProviderRegistry>>,
     /// Active collections (session_id -> state)
     collections: Arc<DashMap<String, CollectionState>>,
     /// Reverse index for fast lookups (streamer_id -> session_id).
     sessions_by_streamer: Arc<DashMap<String, String>>,
     /// Event sender
-    event_tx: broadcast::Sender<DanmuEvent>,
+    event_tx: DanmuEventSender,
+    /// One permit per collection allowed by `max_concurrent_collections`
+    collection_slots: Option<Arc<Semaphore>>,
     /// Global cancellation token
     cancel_token: CancellationToken,
     /// Session repository for persistence
@@ -181,17 +746,21 @@ impl DanmuService {
     const DEFAULT_MAX_TOP_TALKERS: usize = 32;
     const DEFAULT_MAX_WORDS: usize = 50;
     const DEFAULT_RATE_BUCKET_SECS: u64 = 10;
+    /// Smallest accepted [`DanmuServiceConfig::event_broadcast_capacity`].
+    const MIN_EVENT_BROADCAST_CAPACITY: usize = 16;
 
     /// Create a new danmu service.
     pub fn new(config: DanmuServiceConfig) -> Self {
-        let (event_tx, _) = broadcast::channel(256);
+        let event_tx = Self::event_sender(&config);
+        let collection_slots = Self::collection_slots(&config);
 
         Self {
             config,
-            providers: Arc::new(ProviderRegistry::with_defaults()),
+            providers: Arc::new(RwLock::new(ProviderRegistry::with_defaults())),
             collections: Arc::new(DashMap::new()),
             sessions_by_streamer: Arc::new(DashMap::new()),
             event_tx,
+            collection_slots,
             cancel_token: CancellationToken::new(),
             session_repo: None,
         }
@@ -199,19 +768,84 @@ impl DanmuService {
 
     /// Create a new danmu service with custom providers.
     pub fn with_providers(config: DanmuServiceConfig, providers: ProviderRegistry) -> Self {
-        let (event_tx, _) = broadcast::channel(256);
+        let event_tx = Self::event_sender(&config);
+        let collection_slots = Self::collection_slots(&config);
 
         Self {
             config,
-            providers: Arc::new(providers),
+            providers: Arc::new(RwLock::new(providers)),
             collections: Arc::new(DashMap::new()),
             sessions_by_streamer: Arc::new(DashMap::new()),
             event_tx,
+            collection_slots,
             cancel_token: CancellationToken::new(),
             session_repo: None,
         }
     }
 
+    fn event_sender(config: &DanmuServiceConfig) -> DanmuEventSender {
+        DanmuEventSender::new(
+            config
+                .event_broadcast_capacity
+                .max(Self::MIN_EVENT_BROADCAST_CAPACITY),
+        )
+    }
+
+    fn collection_slots(config: &DanmuServiceConfig) -> Option<Arc<Semaphore>> {
+        config
+            .max_concurrent_collections
+            .map(|limit| Arc::new(Semaphore::new(limit)))
+    }
+
+    /// Take a collection slot, waiting for one per the limit policy.
+    async fn acquire_collection_slot(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        let (Some(slots), Some(limit)) = (
+            &self.collection_slots,
+            self.config.max_concurrent_collections,
+        ) else {
+            return Ok(None);
+        };
+        let permit = match self.config.collection_limit_policy {
+            CollectionLimitPolicy::Reject => slots.clone().try_acquire_owned().ok(),
+            CollectionLimitPolicy::Wait { timeout } => {
+                tokio::time::timeout(timeout, slots.clone().acquire_owned())
+                    .await
+                    .ok()
+                    .and_then(|permit| permit.ok())
+            }
+        };
+        permit
+            .map(Some)
+            .ok_or(Error::CollectionLimitReached { limit })
+    }
+
+    /// Running collections and the configured limit.
+    pub fn capacity(&self) -> CollectionCapacity {
+        CollectionCapacity {
+            used: self.collections.len(),
+            limit: self.config.max_concurrent_collections,
+        }
+    }
+
+    /// Describe the registered providers, e.g. to show which platforms support danmu.
+    pub fn list_providers(&self) -> Vec<ProviderInfo> {
+        self.providers.read().provider_infos()
+    }
+
+    /// Register a provider at runtime, used by collections started afterwards.
+    ///
+    /// Fails if a provider for the same platform is registered, unless
+    /// `replace` is set; running collections keep the provider they started with.
+    pub fn register_provider(&self, provider: Arc<dyn DanmuProvider>, replace: bool) -> Result<()> {
+        let platform = provider.platform().to_string();
+        self.providers.write().try_register(provider, replace)?;
+        info!(
+            platform = platform.as_str(),
+            replace, "danmu: registered provider"
+        );
+        Ok(())
+    }
+
     /// Set the session repository for persistence.
     pub fn with_session_repository(
         mut self,
@@ -228,11 +862,59 @@ impl DanmuService {
         self.session_repo.as_ref()
     }
 
+    /// File format of the segment files written by this service.
+    pub fn output_format(&self) -> DanmuOutputFormat {
+        self.config.output_format
+    }
+
     /// Subscribe to danmu events.
     pub fn subscribe(&self) -> broadcast::Receiver<DanmuEvent> {
         self.event_tx.subscribe()
     }
 
+    /// Subscribe to the events of one session.
+    ///
+    /// See [`DanmuEventStream`] for what happens when the subscriber falls behind.
+    pub fn subscribe_session(&self, session_id: &str) -> DanmuEventStream {
+        let session_id = session_id.to_string();
+        DanmuEventStream::spawn(
+            self.event_tx.subscribe(),
+            self.config.subscription_capacity,
+            move |event| event.session_id() == session_id,
+        )
+    }
+
+    /// Subscribe to the events of a streamer's sessions, including sessions
+    /// started after subscribing.
+    ///
+    /// See [`DanmuEventStream`] for what happens when the subscriber falls behind.
+    pub fn subscribe_streamer(&self, streamer_id: &str) -> DanmuEventStream {
+        let streamer_id = streamer_id.to_string();
+        let sessions_by_streamer = self.sessions_by_streamer.clone();
+        // The last session seen for the streamer, so events sent after the
+        // session left the index (e.g. `CollectionStopped`) still match.
+        let mut current: Option<String> = None;
+        DanmuEventStream::spawn(
+            self.event_tx.subscribe(),
+            self.config.subscription_capacity,
+            move |event| {
+                if event.streamer_id() == Some(streamer_id.as_str()) {
+                    current = Some(event.session_id().to_string());
+                    return true;
+                }
+                if let Some(session_id) = sessions_by_streamer.get(&streamer_id) {
+                    current = Some(session_id.value().clone());
+                }
+                current.as_deref() == Some(event.session_id())
+            },
+        )
+    }
+
+    /// Approximate number of events the slowest subscriber has not read yet.
+    pub fn event_lag(&self) -> usize {
+        self.event_tx.len()
+    }
+
     /// Start danmu collection for a session.
     /// Returns a handle that can be used to control segment file writing.
     pub async fn start_collection(
@@ -244,8 +926,54 @@ impl DanmuService {
         cookies: Option<String>,
         extras: Option<std::collections::HashMap<String, String>>,
     ) -> Result<CollectionHandle> {
-        const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+        self.start_collection_with(
+            session_id,
+            streamer_id,
+            streamer_url,
+            StartCollectionOptions {
+                sampling_config,
+                cookies,
+                extras,
+                room_id: None,
+                connect_timeout: None,
+                keyword_alerts: None,
+                filters: None,
+                previous_statistics: None,
+            },
+        )
+        .await
+    }
 
+    /// Start danmu collection with per-call options, e.g. a longer connect
+    /// timeout for platforms with slow handshakes.
+    pub async fn start_collection_with(
+        &self,
+        session_id: &str,
+        streamer_id: &str,
+        streamer_url: &str,
+        options: StartCollectionOptions,
+    ) -> Result<CollectionHandle> {
+        let StartCollectionOptions {
+            sampling_config,
+            cookies,
+            extras,
+            room_id,
+            connect_timeout,
+            keyword_alerts,
+            filters,
+            previous_statistics,
+        } = options;
+        let connect_timeout = connect_timeout.unwrap_or(self.config.connect_timeout);
+        let keyword_alerts = keyword_alerts.unwrap_or_else(|| self.config.keyword_alerts.clone());
+        let keyword_matcher = if keyword_alerts.is_empty() {
+            None
+        } else {
+            Some(KeywordMatcher::new(
+                &keyword_alerts,
+                self.config.keyword_alert_cooldown,
+            )?)
+        };
+        let filter = DanmuFilter::new(filters.as_ref().unwrap_or(&self.config.filters))?;
         // Check if already collecting
         if self.collections.contains_key(session_id) {
             return Err(Error::from(
@@ -295,91 +1023,47 @@ impl DanmuService {
             }
         }
 
-        // Find provider for URL
-        let provider = self.providers.get_by_url(streamer_url).ok_or_else(|| {
-            Error::from(platforms_parser::danmaku::DanmakuError::connection(
-                format!("No danmu provider for URL: {}", streamer_url),
-            ))
-        })?;
-
-        // Extract room ID - use platform-specific extras when available
-        // - Huya: uses "presenter_uid" from extras
-        // - Douyin: uses "id_str" from extras
-        // - Douyu: uses "rid" from extras
-        // - Bigo: uses studio "room_id" from extras (not siteId from the URL)
-        // - Others: fallback to URL-based extraction
-        let platform = provider.platform();
-        let room_id = match platform {
-            "huya" => {
-                // Huya uses presenter_uid for danmu connection
-                extras
-                    .as_ref()
-                    .and_then(|e| e.get("presenter_uid"))
-                    .cloned()
-                    .or_else(|| provider.extract_room_id(streamer_url))
-            }
-            "douyin" => {
-                // Douyin uses id_str (room_id) for danmu connection
-                extras
-                    .as_ref()
-                    .and_then(|e| e.get("id_str"))
-                    .cloned()
-                    .or_else(|| provider.extract_room_id(streamer_url))
-            }
-            "douyu" => {
-                // Douyu uses rid for danmu connection
-                extras
-                    .as_ref()
-                    .and_then(|e| e.get("rid"))
-                    .cloned()
-                    .or_else(|| provider.extract_room_id(streamer_url))
-            }
-            "soop" => {
-                // SOOP chat path uses bj id; chat host/FTK arrive via MediaInfo extras.
-                extras
-                    .as_ref()
-                    .and_then(|e| e.get("bjid").or_else(|| e.get("channel_id")))
-                    .cloned()
-                    .or_else(|| provider.extract_room_id(streamer_url))
-            }
-            "bigo" => {
-                // Bigo WS enter needs studio roomId (not siteId from the URL)
-                extras
-                    .as_ref()
-                    .and_then(|e| e.get("room_id"))
-                    .cloned()
-                    .or_else(|| provider.extract_room_id(streamer_url))
-            }
-            _ => provider.extract_room_id(streamer_url),
-        }
-        .ok_or_else(|| {
-            Error::from(platforms_parser::danmaku::DanmakuError::connection(
-                format!("Could not extract room ID from URL: {}", streamer_url),
-            ))
-        })?;
-
-        // Build connection config
-        let mut connection_config = ConnectionConfig::with_cookies(cookies.clone());
-        if let Some(e) = extras {
-            // Remove common fields that are used for room ID extraction but might be useful as extras too
-            // We keep them in extras for now as it's cleaner
-            connection_config = connection_config.with_extras(e);
-        }
+        let (provider, room_id, room_id_source, connection_config) =
+            self.resolve_connection(streamer_url, room_id, cookies.clone(), extras.clone())?;
+        info!(
+            session_id,
+            streamer_id,
+            platform = provider.platform(),
+            room_id = room_id.as_str(),
+            room_id_source = room_id_source.as_str(),
+            "danmu: resolved room"
+        );
+        // Taken after any previous collector of the streamer has stopped, so
+        // replacing a session does not need a second slot.
+        let slot = self.acquire_collection_slot().await?;
 
         // Create command channel
-        let (command_tx, command_rx) = mpsc::channel(32);
+        let (command_tx, mut command_rx) = mpsc::channel(32);
 
         // Build bounded per-session statistics/sampler state.
         let max_top_talkers =
             Self::DEFAULT_MAX_TOP_TALKERS.min(self.config.stats_buffer_size.max(10));
         let max_words = Self::DEFAULT_MAX_WORDS.min(self.config.stats_buffer_size.max(25));
-        let stats = platforms_parser::danmaku::StatisticsAggregator::with_config(
+        let language_detection = self.config.language_detection_enabled;
+        let mut stats = platforms_parser::danmaku::StatisticsAggregator::with_config(
             max_top_talkers,
             max_words,
             Self::DEFAULT_RATE_BUCKET_SECS,
-        );
+        )
+        .with_language_detection(language_detection);
+        if let Some(previous) = &previous_statistics {
+            stats.merge_previous(previous);
+        }
+        let segment_stats = platforms_parser::danmaku::StatisticsAggregator::with_config(
+            max_top_talkers,
+            max_words,
+            Self::DEFAULT_RATE_BUCKET_SECS,
+        )
+        .with_language_detection(language_detection);
         let sampler: Box<dyn DanmuSampler> = if self.config.sampling_enabled {
-            let sampling = sampling_config.unwrap_or_else(|| self.config.default_sampling.clone());
+            let sampling = sampling_config
+                .clone()
+                .unwrap_or_else(|| self.config.default_sampling.clone());
             let sampler_config = to_sampler_config(&sampling);
             create_sampler(&sampler_config)
         } else {
@@ -389,12 +1073,34 @@ impl DanmuService {
 
         let (ready_tx, ready_rx) = oneshot::channel::<Result<()>>();
         let (done_tx, done_rx) = oneshot::channel::<std::result::Result<DanmuStatistics, String>>();
+        let (latest_stats_tx, latest_stats) =
+            watch::channel(previous_statistics.clone().unwrap_or_default());
+        let (health_tx, health) = watch::channel(CollectionHealth::new(
+            session_id,
+            provider.platform(),
+            &room_id,
+        ));
+        let (tags, tags_rx) = watch::channel(HashMap::new());
+        let persist_stop = CancellationToken::new();
+        let (persist_done_tx, persist_done_rx) = oneshot::channel();
 
         let state = CollectionState {
             streamer_id: streamer_id.to_string(),
+            streamer_url: streamer_url.to_string(),
+            sampling_config,
+            started_at: Utc::now(),
             cancel_token: cancel_token.clone(),
             command_tx: command_tx.clone(),
             done_rx: Some(done_rx),
+            latest_stats: latest_stats.clone(),
+            persist_stop: persist_stop.clone(),
+            persist_done: Some(persist_done_rx),
+            health,
+            abort_handle: None,
+            cookies,
+            extras,
+            tags,
+            _slot: slot,
         };
 
         self.collections.insert(session_id.to_string(), state);
@@ -411,31 +1117,85 @@ impl DanmuService {
         let session_repo = self.session_repo.clone();
         let provider = Arc::clone(&provider);
         let sampling_enabled = self.config.sampling_enabled;
+        let output_format = self.config.output_format;
+        let xml_format = self.config.xml_format.clone();
+        let xml_flush = self.config.xml_flush;
+        let xml_flush_fsync = self.config.xml_flush_fsync;
+        let ass_config = self.config.ass.clone();
+        let spam_filter = self.config.spam_detection.clone().map(SpamFilter::new);
+        let pause = self.config.pause.clone();
+        let reconnect = self.config.reconnect.clone();
+        let auto_segment_duration = self
+            .config
+            .auto_segment_duration_secs
+            .map(Duration::from_secs);
+        let rotation = self.config.rotation.clone();
+        let stats_snapshot_interval = self.config.stats_snapshot_interval;
+        let live_feed = self.config.live_feed.clone().map(LiveFeed::new);
+        let stale_after = self.config.stale_after;
+        let latest_stats_interval = self.config.partial_statistics_interval;
+        let persist_interval = self.config.persist_interval;
+        let write_batch_size = self.config.write_batch_size;
+        let write_batch_timeout = Duration::from_millis(self.config.write_batch_timeout_ms.max(1));
+        let ingest_queue = self.config.ingest_queue.clone();
         let conn_config = connection_config;
+        let connect_retries = self.config.connect_retries;
+        let connect_retry_delay = self.config.connect_retry_delay;
+        let sync_segments_on_end = self.config.sync_segments_on_end;
+        #[cfg(test)]
+        let finalize_delay = self.config.segment_finalize_delay;
         let cancel_token_task = cancel_token.clone();
 
-        tokio::spawn(async move {
-            let runner = match tokio::time::timeout(
-                CONNECT_TIMEOUT,
-                CollectionRunner::new(RunnerParams {
+        let task = tokio::spawn(async move {
+            let runner = match CollectionRunner::start(
+                RunnerParams {
                     session_id: session_id_clone.clone(),
                     streamer_id: streamer_id_clone.clone(),
                     room_id: room_id_clone,
                     provider: Arc::clone(&provider),
                     conn_config,
+                    connect_timeout,
+                    connect_retries,
+                    connect_retry_delay,
                     stats,
+                    segment_stats,
                     sampler,
                     sampling_enabled,
+                    filter,
+                    spam_filter,
+                    keyword_matcher,
+                    pause,
+                    reconnect,
+                    auto_segment_duration,
+                    rotation,
+                    stats_snapshot_interval,
+                    live_feed,
+                    health_tx,
+                    stale_after,
+                    latest_stats_tx,
+                    latest_stats_interval,
+                    write_batch_size,
+                    write_batch_timeout,
+                    ingest_queue,
+                    output_format,
+                    xml_format,
+                    xml_flush,
+                    xml_flush_fsync,
+                    ass_config,
+                    sync_segments_on_end,
+                    #[cfg(test)]
+                    finalize_delay,
                     event_tx: event_tx.clone(),
-                }),
+                },
+                &mut command_rx,
             )
             .await
             {
-                Ok(Ok(runner)) => {
+                Ok(runner) => {
                     let _ = ready_tx.send(Ok(()));
                     runner
                 }
-                Ok(Err(e)) => {
+                Err(e) => {
                     let error_message = e.to_string();
                     let _ = event_tx.send(DanmuEvent::Error {
                         session_id: session_id_clone.clone(),
@@ -453,32 +1213,29 @@ impl DanmuService {
                     let _ = done_tx.send(Err(error_message));
                     return;
                 }
-                Err(_) => {
-                    let message = format!(
-                        "Danmu connection timed out after {:?} (session_id={})",
-                        CONNECT_TIMEOUT, session_id_clone
-                    );
-                    let _ = ready_tx.send(Err(Error::from(
-                        platforms_parser::danmaku::DanmakuError::connection(message.clone()),
-                    )));
-                    let _ = event_tx.send(DanmuEvent::Error {
-                        session_id: session_id_clone.clone(),
-                        error: message.clone(),
-                    });
-                    if let Some((_, state)) = collections.remove(&session_id_clone) {
-                        let should_remove = sessions_by_streamer
-                            .get(&state.streamer_id)
-                            .is_some_and(|sid| sid.value() == &session_id_clone);
-                        if should_remove {
-                            sessions_by_streamer.remove(&state.streamer_id);
-                        }
-                    }
-                    let _ = done_tx.send(Err(message));
-                    return;
-                }
             };
 
-            let result = runner.run(command_rx, cancel_token_task).await;
+            // Periodic persists end before the final one below or in
+            // `stop_collection`, so they never overwrite it.
+            let (result, ()) = tokio::join!(
+                async {
+                    let result = runner.run(command_rx, cancel_token_task).await;
+                    persist_stop.cancel();
+                    result
+                },
+                async {
+                    persist_periodically(
+                        session_repo.clone(),
+                        &session_id_clone,
+                        latest_stats,
+                        tags_rx,
+                        persist_interval,
+                        persist_stop.clone(),
+                    )
+                    .await;
+                    let _ = persist_done_tx.send(());
+                },
+            );
             if let Err(e) = &result {
                 let _ = event_tx.send(DanmuEvent::Error {
                     session_id: session_id_clone.clone(),
@@ -499,11 +1256,18 @@ impl DanmuService {
                     sessions_by_streamer.remove(&state.streamer_id);
                 }
                 if let Ok(statistics) = &result {
-                    persist_statistics(session_repo.as_deref(), &session_id_clone, statistics)
-                        .await;
+                    let tags = state.tags.borrow().clone();
+                    persist_statistics(
+                        session_repo.as_deref(),
+                        &session_id_clone,
+                        statistics,
+                        &tags,
+                    )
+                    .await;
                     let _ = event_tx.send(DanmuEvent::CollectionStopped {
                         session_id: session_id_clone.clone(),
                         statistics: statistics.clone(),
+                        tags,
                     });
                 }
             }
@@ -511,6 +1275,9 @@ impl DanmuService {
             let done_value = result.map_err(|e| e.to_string());
             let _ = done_tx.send(done_value);
         });
+        if let Some(mut state) = self.collections.get_mut(session_id) {
+            state.abort_handle = Some(task.abort_handle());
+        }
 
         tokio::select! {
             ready = ready_rx => {
@@ -519,6 +1286,8 @@ impl DanmuService {
                         let _ = self.event_tx.send(DanmuEvent::CollectionStarted {
                             session_id: session_id.to_string(),
                             streamer_id: streamer_id.to_string(),
+                            room_id,
+                            room_id_source,
                         });
                     }
                     Ok(Err(e)) => {
@@ -568,49 +1337,234 @@ impl DanmuService {
         Ok(CollectionHandle {
             session_id: session_id.to_string(),
             command_tx,
+            end_segment_timeout: self.config.end_segment_timeout,
         })
     }
 
-    /// Stop danmu collection for a session.
-    pub async fn stop_collection(&self, session_id: &str) -> Result<DanmuStatistics> {
-        // Get and remove state
-        let (_, state) = self.collections.remove(session_id).ok_or_else(|| {
-            Error::from(platforms_parser::danmaku::DanmakuError::connection(
-                format!("No active collection for session {}", session_id),
-            ))
-        })?;
+    /// Check that the provider of `streamer_url` accepts a connection with
+    /// `cookies` and `extras`, without starting a collection.
+    ///
+    /// The room is resolved like [`start_collection`](Self::start_collection)
+    /// does. After a single connect attempt bounded by `connect_timeout`, the
+    /// connection is kept until the first frame arrives or
+    /// `connection_test_wait` elapses, then closed.
+    pub async fn test_connection(
+        &self,
+        streamer_url: &str,
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
+    ) -> Result<ConnectionTestReport> {
+        let (provider, room_id, room_id_source, connection_config) =
+            self.resolve_connection(streamer_url, None, cookies, extras)?;
+        let timeout = self.config.connect_timeout;
+        let started = std::time::Instant::now();
+        let mut connection = match tokio::time::timeout(
+            timeout,
+            provider.connect(&room_id, connection_config),
+        )
+        .await
+        {
+            Ok(result) => result.map_err(|e| Error::from(e.into_connection_error()))?,
+            Err(_) => {
+                return Err(Error::from(DanmuConnectionError::new(
+                    DanmuErrorCode::Timeout,
+                    format!(
+                        "Danmu connection timed out after {:?} (room_id={})",
+                        timeout, room_id
+                    ),
+                )));
+            }
+        };
+        let handshake_latency = started.elapsed();
 
-        let should_remove = self
-            .sessions_by_streamer
-            .get(&state.streamer_id)
-            .is_some_and(|sid| sid.value() == session_id);
-        if should_remove {
-            self.sessions_by_streamer.remove(&state.streamer_id);
+        let deadline = tokio::time::Instant::now() + self.config.connection_test_wait;
+        let received = loop {
+            match tokio::time::timeout_at(deadline, provider.receive(&connection)).await {
+                Ok(Ok(Some(_))) => break Ok(true),
+                // No message available, wait a bit. The timeout never fires
+                // for a provider that returns right away, so check it here.
+                Ok(Ok(None)) => {
+                    let now = tokio::time::Instant::now();
+                    if now >= deadline {
+                        break Ok(false);
+                    }
+                    tokio::time::sleep_until(deadline.min(now + Duration::from_millis(100))).await;
+                }
+                Ok(Err(e)) => break Err(Error::from(e.into_connection_error())),
+                Err(_) => break Ok(false),
+            }
+        };
+        if let Err(e) = provider.disconnect(&mut connection).await {
+            warn!(
+                platform = provider.platform(),
+                room_id = room_id.as_str(),
+                error = %e,
+                "danmu: failed to close test connection"
+            );
         }
 
-        // Send stop command
-        if state
-            .command_tx
-            .send(CollectionCommand::Stop)
-            .await
-            .is_err()
-        {
-            tracing::debug!(session_id, "Danmu collection task already stopped");
-        }
+        Ok(ConnectionTestReport {
+            platform: provider.platform().to_string(),
+            room_id,
+            room_id_source,
+            handshake_latency,
+            message_received: received?,
+        })
+    }
 
-        // Cancel the collection task
-        state.cancel_token.cancel();
+    /// Resolve the provider, room ID and connection settings for a streaming URL.
+    ///
+    /// `room_id_override` is used instead of the extras and the URL when set,
+    /// after the provider accepts it.
+    fn resolve_connection(
+        &self,
+        streamer_url: &str,
+        room_id_override: Option<String>,
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
+    ) -> Result<(
+        Arc<dyn DanmuProvider>,
+        String,
+        RoomIdSource,
+        ConnectionConfig,
+    )> {
+        // Find provider for URL
+        let provider = self
+            .providers
+            .read()
+            .get_by_url(streamer_url)
+            .ok_or_else(|| {
+                Error::from(DanmuConnectionError::new(
+                    DanmuErrorCode::ProviderNotFound,
+                    format!("No danmu provider for URL: {}", streamer_url),
+                ))
+            })?;
 
-        if let Some(done_rx) = state.done_rx {
-            const STOP_TIMEOUT: Duration = Duration::from_secs(10);
-            match tokio::time::timeout(STOP_TIMEOUT, done_rx).await {
-                Ok(Ok(Ok(statistics))) => {
-                    persist_statistics(self.session_repo.as_deref(), session_id, &statistics).await;
-                    let _ = self.event_tx.send(DanmuEvent::CollectionStopped {
-                        session_id: session_id.to_string(),
+        let platform = provider.platform();
+        let (room_id, room_id_source) = if let Some(room_id) = room_id_override {
+            if !provider.validate_room_id(&room_id) {
+                return Err(Error::from(DanmuConnectionError::new(
+                    DanmuErrorCode::RoomNotFound,
+                    format!("Invalid {} room ID: {:?}", platform, room_id),
+                )));
+            }
+            (room_id, RoomIdSource::Override)
+        } else {
+            // Extract room ID - use platform-specific extras when available
+            // - Huya: uses "presenter_uid" from extras
+            // - Douyin: uses "id_str" from extras
+            // - Douyu: uses "rid" from extras
+            // - SOOP: uses "bjid" (or "channel_id") from extras
+            // - Bigo: uses studio "room_id" from extras (not siteId from the URL)
+            // - Others: fallback to URL-based extraction
+            let from_extras = extras.as_ref().and_then(|e| match platform {
+                "huya" => e.get("presenter_uid"),
+                "douyin" => e.get("id_str"),
+                "douyu" => e.get("rid"),
+                // SOOP chat path uses bj id; chat host/FTK arrive via MediaInfo extras.
+                "soop" => e.get("bjid").or_else(|| e.get("channel_id")),
+                // Bigo WS enter needs studio roomId (not siteId from the URL)
+                "bigo" => e.get("room_id"),
+                _ => None,
+            });
+            match from_extras {
+                Some(room_id) => (room_id.clone(), RoomIdSource::Extras),
+                None => {
+                    let room_id = provider.extract_room_id(streamer_url).ok_or_else(|| {
+                        Error::from(DanmuConnectionError::new(
+                            DanmuErrorCode::RoomNotFound,
+                            format!("Could not extract room ID from URL: {}", streamer_url),
+                        ))
+                    })?;
+                    (room_id, RoomIdSource::Url)
+                }
+            }
+        };
+
+        // Build connection config
+        let mut connection_config = ConnectionConfig::with_cookies(cookies);
+        if let Some(e) = extras {
+            // Remove common fields that are used for room ID extraction but might be useful as extras too
+            // We keep them in extras for now as it's cleaner
+            connection_config = connection_config.with_extras(e);
+        }
+        if let Some(proxy) = &self.config.proxy {
+            connection_config = connection_config.with_proxy(proxy.clone());
+        }
+        if let Some(overrides) = self.config.platform_overrides.get(platform) {
+            connection_config = overrides.apply(connection_config);
+        }
+
+        Ok((provider, room_id, room_id_source, connection_config))
+    }
+
+    /// Stop danmu collection for a session.
+    ///
+    /// Returns the final statistics once the runner has finalized its segment.
+    /// If it does not within `stop_timeout`, or ends without statistics, the
+    /// statistics it last published are returned and persisted instead, with
+    /// [`DanmuStatistics::is_partial`] set.
+    pub async fn stop_collection(&self, session_id: &str) -> Result<DanmuStatistics> {
+        let (statistics, _) = self
+            .stop_within(session_id, self.config.stop_timeout, false)
+            .await?;
+        Ok(statistics)
+    }
+
+    /// Stop the collection of `session_id`, waiting up to `stop_timeout` for
+    /// its final statistics and aborting the runner afterwards if `abort` is set.
+    async fn stop_within(
+        &self,
+        session_id: &str,
+        stop_timeout: Duration,
+        abort: bool,
+    ) -> Result<(DanmuStatistics, StopOutcome)> {
+        // Get and remove state
+        let (_, state) = self.collections.remove(session_id).ok_or_else(|| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                format!("No active collection for session {}", session_id),
+            ))
+        })?;
+
+        let should_remove = self
+            .sessions_by_streamer
+            .get(&state.streamer_id)
+            .is_some_and(|sid| sid.value() == session_id);
+        if should_remove {
+            self.sessions_by_streamer.remove(&state.streamer_id);
+        }
+
+        // Send stop command
+        if state
+            .command_tx
+            .send(CollectionCommand::Stop)
+            .await
+            .is_err()
+        {
+            tracing::debug!(session_id, "Danmu collection task already stopped");
+        }
+
+        // Cancel the collection task
+        state.cancel_token.cancel();
+
+        let tags = state.tags.borrow().clone();
+        let mut outcome = StopOutcome::Failed;
+        if let Some(done_rx) = state.done_rx {
+            match tokio::time::timeout(stop_timeout, done_rx).await {
+                Ok(Ok(Ok(statistics))) => {
+                    persist_statistics(
+                        self.session_repo.as_deref(),
+                        session_id,
+                        &statistics,
+                        &tags,
+                    )
+                    .await;
+                    let _ = self.event_tx.send(DanmuEvent::CollectionStopped {
+                        session_id: session_id.to_string(),
                         statistics: statistics.clone(),
+                        tags,
                     });
-                    return Ok(statistics);
+                    return Ok((statistics, StopOutcome::Stopped));
                 }
                 Ok(Ok(Err(error))) => {
                     warn!(
@@ -621,14 +1575,110 @@ impl DanmuService {
                 Ok(Err(_)) => {}
                 Err(_) => {
                     warn!(
-                        "Danmu collection stop timed out after {:?} (session_id={})",
-                        STOP_TIMEOUT, session_id
+                        "Danmu collection stop timed out after {:?} (session_id={}); \
+                         the segment may not be finalized, returning partial statistics",
+                        stop_timeout, session_id
                     );
+                    outcome = StopOutcome::TimedOut;
+                    if abort && let Some(abort_handle) = &state.abort_handle {
+                        abort_handle.abort();
+                    }
                 }
             }
         }
 
-        Ok(DanmuStatistics::default())
+        // The runner may still be running, so stop its periodic persists
+        // before they can overwrite the partial statistics.
+        state.persist_stop.cancel();
+        if let Some(persist_done) = state.persist_done {
+            let _ = persist_done.await;
+        }
+        let mut statistics = state.latest_stats.borrow().clone();
+        statistics.is_partial = true;
+        if statistics.total_count > 0 {
+            persist_statistics(self.session_repo.as_deref(), session_id, &statistics, &tags).await;
+        }
+        Ok((statistics, outcome))
+    }
+
+    /// Move a running collection to a new streaming URL (e.g. CDN failover).
+    ///
+    /// Message delivery to the XML writer is paused while the old WebSocket is
+    /// closed and the new one is opened; the active segment, any
+    /// [`CollectionHandle`] for the session and the accumulated statistics are
+    /// kept. If the new URL cannot be connected, the collector falls back to
+    /// its previous connection and the error is returned.
+    pub async fn switch_provider(&self, session_id: &str, new_url: &str) -> Result<()> {
+        // Clone out of the DashMap guard before any .await (see `start_collection`).
+        let (command_tx, cookies, extras) = self
+            .collections
+            .get(session_id)
+            .map(|state| {
+                (
+                    state.command_tx.clone(),
+                    state.cookies.clone(),
+                    state.extras.clone(),
+                )
+            })
+            .ok_or_else(|| {
+                Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                    format!("No active collection for session {}", session_id),
+                ))
+            })?;
+
+        let (provider, room_id, _, conn_config) =
+            self.resolve_connection(new_url, None, cookies, extras)?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let not_running = || {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                "Collection task not running",
+            ))
+        };
+        command_tx
+            .send(CollectionCommand::SwitchProvider {
+                target: Box::new(ProviderTarget {
+                    provider,
+                    room_id,
+                    conn_config,
+                }),
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| not_running())?;
+        reply_rx.await.map_err(|_| not_running())?
+    }
+
+    /// Replace the credentials of an active collection, see
+    /// [`CollectionHandle::update_credentials`].
+    ///
+    /// The new values are also used by later [`Self::switch_provider`] calls.
+    pub async fn update_credentials(
+        &self,
+        session_id: &str,
+        cookies: Option<String>,
+        extras: Option<HashMap<String, String>>,
+    ) -> Result<()> {
+        let handle = {
+            // Release the DashMap guard before the .await (see `start_collection`).
+            let mut state = self.collections.get_mut(session_id).ok_or_else(|| {
+                Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                    format!("No active collection for session {}", session_id),
+                ))
+            })?;
+            if cookies.is_some() {
+                state.cookies = cookies.clone();
+            }
+            if extras.is_some() {
+                state.extras = extras.clone();
+            }
+            CollectionHandle {
+                session_id: session_id.to_string(),
+                command_tx: state.command_tx.clone(),
+                end_segment_timeout: self.config.end_segment_timeout,
+            }
+        };
+        handle.update_credentials(cookies, extras).await
     }
 
     /// Get a handle for an existing collection.
@@ -638,19 +1688,220 @@ impl DanmuService {
             .map(|state| CollectionHandle {
                 session_id: session_id.to_string(),
                 command_tx: state.command_tx.clone(),
+                end_segment_timeout: self.config.end_segment_timeout,
             })
     }
 
+    /// Get a snapshot of the statistics of an active collection.
+    pub async fn get_statistics(&self, session_id: &str) -> Result<DanmuStatistics> {
+        let handle = self.get_handle(session_id).ok_or_else(|| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                format!("No active collection for session {}", session_id),
+            ))
+        })?;
+        handle.current_statistics().await
+    }
+
+    /// Stream of the number of messages a session received in the last
+    /// `window_secs` seconds, yielded once per second.
+    ///
+    /// Counts are sampled from the collection's statistics, so until the
+    /// stream has run for a full window each value covers the time since it
+    /// started. Ends when the session stops, right away if it is not active;
+    /// dropping it stops the sampling task.
+    pub fn rate_stream(
+        &self,
+        session_id: &str,
+        window_secs: u64,
+    ) -> impl Stream<Item = u64> + Send + use<> {
+        let (tx, rx) = mpsc::channel(1);
+        let Some(handle) = self.get_handle(session_id) else {
+            return ReceiverStream::new(rx);
+        };
+        let window = usize::try_from(window_secs.max(1)).unwrap_or(usize::MAX);
+
+        tokio::spawn(async move {
+            // Total count at each of the last `window` ticks, plus the current one.
+            let mut totals = VecDeque::new();
+            let mut ticker = tokio::time::interval(Duration::from_secs(1));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    _ = ticker.tick() => {}
+                }
+                let Ok(statistics) = handle.current_statistics().await else {
+                    break;
+                };
+                totals.push_back(statistics.total_count);
+                if totals.len() > window.saturating_add(1) {
+                    totals.pop_front();
+                }
+                let oldest = totals.front().copied().unwrap_or_default();
+                if tx
+                    .send(statistics.total_count.saturating_sub(oldest))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
+    /// Replace the tags of an active collection, e.g. the stream title and
+    /// category, for correlating its statistics with external metadata.
+    ///
+    /// Tags are persisted with the session's statistics and included in
+    /// [`DanmuEvent::CollectionStopped`].
+    pub fn set_session_tags(&self, session_id: &str, tags: HashMap<String, String>) -> Result<()> {
+        let state = self.collections.get(session_id).ok_or_else(|| {
+            Error::from(platforms_parser::danmaku::DanmakuError::connection(
+                format!("No active collection for session {}", session_id),
+            ))
+        })?;
+        state.tags.send_replace(tags);
+        Ok(())
+    }
+
+    /// Tags of an active collection, set with [`Self::set_session_tags`].
+    pub fn get_session_tags(&self, session_id: &str) -> Option<HashMap<String, String>> {
+        self.collections
+            .get(session_id)
+            .map(|state| state.tags.borrow().clone())
+    }
+
+    /// Connection health of an active collection.
+    pub fn health(&self, session_id: &str) -> Option<CollectionHealth> {
+        self.collections
+            .get(session_id)
+            .map(|state| state.health.borrow().clone())
+    }
+
+    /// Connection health of all active collections, ordered by session ID.
+    pub fn health_all(&self) -> Vec<CollectionHealth> {
+        let mut health: Vec<_> = self
+            .collections
+            .iter()
+            .map(|entry| entry.health.borrow().clone())
+            .collect();
+        health.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        health
+    }
+
     /// Check if collection is active for a session.
     pub fn is_collecting(&self, session_id: &str) -> bool {
         self.collections.contains_key(session_id)
     }
 
+    /// Serialize the parameters of all active collections as a JSON array of
+    /// [`PersistableSession`]s, e.g. before a graceful restart.
+    pub fn serialize_sessions(&self) -> Result<String> {
+        let sessions: Vec<PersistableSession> = self
+            .collections
+            .iter()
+            .map(|entry| PersistableSession {
+                session_id: entry.key().clone(),
+                streamer_id: entry.streamer_id.clone(),
+                streamer_url: entry.streamer_url.clone(),
+                sampling_config: entry.sampling_config.clone(),
+                started_at: entry.started_at,
+                tags: entry.tags.borrow().clone(),
+            })
+            .collect();
+        serde_json::to_string(&sessions)
+            .map_err(|e| Error::Other(format!("Failed to serialize danmu sessions: {}", e)))
+    }
+
+    /// Restart collection for a session saved by [`Self::serialize_sessions`].
+    ///
+    /// Statistics persisted for the session are loaded from the session
+    /// repository and merged into the new collection's statistics.
+    pub async fn restore_session(&self, session: PersistableSession) -> Result<CollectionHandle> {
+        let previous_statistics = match &self.session_repo {
+            Some(repo) => load_statistics(repo.as_ref(), &session.session_id).await?,
+            None => None,
+        };
+        let handle = self
+            .start_collection_with(
+                &session.session_id,
+                &session.streamer_id,
+                &session.streamer_url,
+                StartCollectionOptions {
+                    sampling_config: session.sampling_config,
+                    previous_statistics,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        if let Some(mut state) = self.collections.get_mut(&session.session_id) {
+            state.started_at = session.started_at;
+            state.tags.send_replace(session.tags);
+        }
+        Ok(handle)
+    }
+
     /// Get all active session IDs.
     pub fn active_sessions(&self) -> Vec<String> {
         self.collections.iter().map(|r| r.key().clone()).collect()
     }
 
+    /// IDs of the active sessions whose streamer URL starts with `url_prefix`,
+    /// e.g. `https://www.douyu.com/` for all Douyu sessions.
+    pub fn sessions_for_url_prefix(&self, url_prefix: &str) -> Vec<String> {
+        self.collections
+            .iter()
+            .filter(|entry| entry.streamer_url.starts_with(url_prefix))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Details of all active sessions, ordered by session ID.
+    ///
+    /// Message counts are queried from the runners concurrently, waiting at
+    /// most one second for each.
+    pub async fn active_sessions_detailed(&self) -> Vec<ActiveSessionInfo> {
+        const MESSAGE_COUNT_TIMEOUT: Duration = Duration::from_secs(1);
+
+        // Collect first so no DashMap guard is held across the queries.
+        let sessions: Vec<_> = self
+            .collections
+            .iter()
+            .map(|entry| {
+                let info = ActiveSessionInfo {
+                    session_id: entry.key().clone(),
+                    streamer_id: entry.streamer_id.clone(),
+                    streamer_url: entry.streamer_url.clone(),
+                    started_at: entry.started_at,
+                    message_count: None,
+                };
+                (info, entry.command_tx.clone())
+            })
+            .collect();
+
+        let mut sessions =
+            futures::future::join_all(sessions.into_iter().map(|(mut info, command_tx)| async {
+                let query = async move {
+                    let (reply, reply_rx) = oneshot::channel();
+                    command_tx
+                        .send(CollectionCommand::GetMessageCount { reply })
+                        .await
+                        .ok()?;
+                    reply_rx.await.ok()
+                };
+                info.message_count = tokio::time::timeout(MESSAGE_COUNT_TIMEOUT, query)
+                    .await
+                    .ok()
+                    .flatten();
+                info
+            }))
+            .await;
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        sessions
+    }
+
     /// Get the session ID for a streamer if one exists.
     ///
     /// Iterates over active collections to find a session matching the given streamer ID.
@@ -662,17 +1913,37 @@ impl DanmuService {
     }
 
     /// Shutdown the service.
-    pub async fn shutdown(&self) {
+    ///
+    /// Stops all collections concurrently within `shutdown_timeout`. Runners
+    /// still running at the deadline are aborted and their partial statistics
+    /// persisted.
+    pub async fn shutdown(&self) -> DanmuShutdownSummary {
         // Cancel all collections
         self.cancel_token.cancel();
 
         // Stop all active collections
+        let shutdown_timeout = self.config.shutdown_timeout;
         let session_ids: Vec<_> = self.collections.iter().map(|r| r.key().clone()).collect();
-        for session_id in session_ids {
-            if let Err(error) = self.stop_collection(&session_id).await {
-                warn!(session_id, %error, "Failed to stop danmu collection during shutdown");
+        let outcomes = futures::future::join_all(session_ids.iter().map(|session_id| async move {
+            match self.stop_within(session_id, shutdown_timeout, true).await {
+                Ok((_, outcome)) => outcome,
+                Err(error) => {
+                    warn!(session_id, %error, "Failed to stop danmu collection during shutdown");
+                    StopOutcome::Failed
+                }
+            }
+        }))
+        .await;
+
+        let mut summary = DanmuShutdownSummary::default();
+        for outcome in outcomes {
+            match outcome {
+                StopOutcome::Stopped => summary.stopped += 1,
+                StopOutcome::TimedOut => summary.timed_out += 1,
+                StopOutcome::Failed => summary.failed += 1,
             }
         }
+        summary
     }
 }
 
@@ -684,10 +1955,25 @@ fn saturating_u64_to_i64(value: u64) -> i64 {
     }
 }
 
+/// Gift statistics stored as one JSON object by [`persist_statistics`],
+/// along with the session's tags.
+#[derive(Default, Serialize, Deserialize)]
+struct PersistedGiftStatistics {
+    gift_count: u64,
+    total_gift_value: u64,
+    #[serde(default)]
+    top_gifters: Vec<TopGifter>,
+    #[serde(default)]
+    gift_breakdown: Vec<GiftSummary>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tags: HashMap<String, String>,
+}
+
 async fn persist_statistics(
     session_repo: Option<&dyn SessionRepository>,
     session_id: &str,
     statistics: &DanmuStatistics,
+    tags: &HashMap<String, String>,
 ) {
     #[derive(serde::Serialize)]
     struct TopTalkerView<'a> {
@@ -749,6 +2035,21 @@ async fn persist_statistics(
         }
     };
 
+    let gift_statistics = PersistedGiftStatistics {
+        gift_count: statistics.gift_count,
+        total_gift_value: statistics.total_gift_value,
+        top_gifters: statistics.top_gifters.clone(),
+        gift_breakdown: statistics.gift_breakdown.clone(),
+        tags: tags.clone(),
+    };
+    let gift_statistics = match serde_json::to_string(&gift_statistics) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!(session_id, %error, "Failed to serialize gift statistics");
+            None
+        }
+    };
+
     if let Err(error) = repo
         .upsert_danmu_statistics(
             session_id,
@@ -756,6 +2057,7 @@ async fn persist_statistics(
             danmu_rate_timeseries.as_deref(),
             top_talkers.as_deref(),
             word_frequency.as_deref(),
+            gift_statistics.as_deref(),
         )
         .await
     {
@@ -763,15 +2065,161 @@ async fn persist_statistics(
     }
 }
 
+/// Persist the statistics last published by a runner every `interval` until
+/// `stop` is cancelled, skipping intervals without new statistics.
+///
+/// Returns once any write in progress has completed, so a persist made
+/// afterwards is not overwritten.
+async fn persist_periodically(
+    session_repo: Option<Arc<dyn SessionRepository>>,
+    session_id: &str,
+    mut latest_stats: watch::Receiver<DanmuStatistics>,
+    tags: watch::Receiver<HashMap<String, String>>,
+    interval: Option<Duration>,
+    stop: CancellationToken,
+) {
+    let (Some(repo), Some(interval)) = (session_repo, interval) else {
+        return;
+    };
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = stop.cancelled() => break,
+            _ = ticker.tick() => {}
+        }
+        match latest_stats.has_changed() {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => break,
+        }
+        let statistics = latest_stats.borrow_and_update().clone();
+        if statistics.total_count > 0 {
+            let tags = tags.borrow().clone();
+            persist_statistics(Some(repo.as_ref()), session_id, &statistics, &tags).await;
+        }
+    }
+}
+
+/// Statistics persisted for `session_id` by [`persist_statistics`], if any.
+async fn load_statistics(
+    session_repo: &dyn SessionRepository,
+    session_id: &str,
+) -> Result<Option<DanmuStatistics>> {
+    fn parse<T: serde::de::DeserializeOwned>(
+        session_id: &str,
+        field: &str,
+        value: Option<&str>,
+    ) -> Vec<T> {
+        let Some(value) = value else {
+            return Vec::new();
+        };
+        serde_json::from_str(value).unwrap_or_else(|error| {
+            warn!(session_id, field, %error, "Failed to parse persisted danmu statistics");
+            Vec::new()
+        })
+    }
+
+    let Some(model) = session_repo.get_danmu_statistics(session_id).await? else {
+        return Ok(None);
+    };
+    let rate_timeseries = parse::<DanmuRateEntry>(
+        session_id,
+        "danmu_rate_timeseries",
+        model.danmu_rate_timeseries.as_deref(),
+    )
+    .into_iter()
+    .filter_map(|entry| {
+        Some(RateDataPoint {
+            timestamp: DateTime::from_timestamp_millis(entry.ts)?,
+            count: u64::try_from(entry.count).unwrap_or(0),
+        })
+    })
+    .collect::<Vec<_>>();
+    let gifts = model
+        .gift_statistics
+        .as_deref()
+        .and_then(|value| {
+            serde_json::from_str::<PersistedGiftStatistics>(value)
+                .inspect_err(|error| {
+                    warn!(session_id, %error, "Failed to parse persisted gift statistics");
+                })
+                .ok()
+        })
+        .unwrap_or_default();
+    Ok(Some(DanmuStatistics {
+        total_count: u64::try_from(model.total_danmus).unwrap_or(0),
+        gift_count: gifts.gift_count,
+        total_gift_value: gifts.total_gift_value,
+        top_gifters: gifts.top_gifters,
+        gift_breakdown: gifts.gift_breakdown,
+        top_talkers: parse(session_id, "top_talkers", model.top_talkers.as_deref()),
+        word_frequency: parse(
+            session_id,
+            "word_frequency",
+            model.word_frequency.as_deref(),
+        ),
+        start_time: rate_timeseries.first().map(|point| point.timestamp),
+        rate_timeseries,
+        ..Default::default()
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::danmu::OverflowPolicy;
+    use platforms_parser::danmaku::websocket::WebSocketProviderConfig;
+
+    #[test]
+    fn platform_override_merges_websocket_settings() {
+        let config = ConnectionConfig::with_cookies(None).with_websocket(WebSocketProviderConfig {
+            max_reconnect_attempts: 3,
+            ..Default::default()
+        });
+        let overrides = PlatformOverride {
+            connect_timeout_secs: Some(5),
+            heartbeat_interval_secs: Some(20),
+            ..Default::default()
+        };
+
+        let websocket = overrides.apply(config).websocket.unwrap();
+        assert_eq!(websocket.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(websocket.heartbeat_interval, Some(Duration::from_secs(20)));
+        assert_eq!(websocket.max_reconnect_attempts, 3);
+    }
+
+    #[test]
+    fn platform_override_without_settings_keeps_config() {
+        let config = PlatformOverride::default().apply(ConnectionConfig::with_cookies(None));
+        assert!(config.websocket.is_none());
+        assert!(config.extra_headers.is_none());
+    }
+
+    #[test]
+    fn platform_override_extends_extra_headers() {
+        let config = ConnectionConfig::with_cookies(None).with_extra_headers(HashMap::from([
+            ("Origin".to_string(), "https://a.example".to_string()),
+            ("X-Keep".to_string(), "1".to_string()),
+        ]));
+        let overrides = PlatformOverride {
+            max_reconnect_attempts: Some(0),
+            extra_headers: HashMap::from([("Origin".to_string(), "https://b.example".to_string())]),
+            ..Default::default()
+        };
+
+        let config = overrides.apply(config);
+        let headers = config.extra_headers.unwrap();
+        assert_eq!(headers["Origin"], "https://b.example");
+        assert_eq!(headers["X-Keep"], "1");
+        assert_eq!(config.websocket.unwrap().max_reconnect_attempts, 0);
+    }
 
     /// Seed the service's maps as if a prior collector had spawned for this
     /// `(streamer_id, session_id)`, without actually running a connection
     /// task. Returns a oneshot tx the test can use to drive the runner's
-    /// "done" signal — `stop_collection` awaits the rx with a 10s timeout,
-    /// so resolving the tx makes the abort path return promptly.
+    /// "done" signal — `stop_collection` awaits the rx for the configured
+    /// `stop_timeout`, so resolving the tx makes the abort path return promptly.
     fn seed_active_collection(
         service: &DanmuService,
         streamer_id: &str,
@@ -782,9 +2230,21 @@ mod tests {
         let cancel_token = service.cancel_token.child_token();
         let state = CollectionState {
             streamer_id: streamer_id.to_string(),
+            streamer_url: format!("mock://{streamer_id}"),
+            sampling_config: None,
+            started_at: Utc::now(),
             cancel_token,
             command_tx,
             done_rx: Some(done_rx),
+            latest_stats: watch::channel(DanmuStatistics::default()).1,
+            persist_stop: CancellationToken::new(),
+            persist_done: None,
+            health: watch::channel(CollectionHealth::new(session_id, "mock", streamer_id)).1,
+            abort_handle: None,
+            cookies: None,
+            extras: None,
+            tags: watch::channel(HashMap::new()).0,
+            _slot: None,
         };
         service.collections.insert(session_id.to_string(), state);
         service
@@ -906,4 +2366,2455 @@ mod tests {
         // line 250 short-circuits before the abort logic could touch it.
         assert!(service.is_collecting(session_id));
     }
+
+    /// Provider for `mock://<room>` URLs. Once `live` is set, each connection
+    /// delivers one chat message; connecting to room `down` fails, as does any
+    /// connect while `refuse` is set, and connecting to room `hang` never
+    /// completes. The next `fail_connects` connects fail. `drop_next` fails the next receive.
+    /// `cookies` records the cookies of every connect attempt. Each connect
+    /// first waits `connect_delay_ms`. Receives return a chat message right
+    /// away while `burst` is above zero, decrementing it, and before that the
+    /// messages queued in `scripted`, in order.
+    #[derive(Default)]
+    struct SwitchMockProvider {
+        live: std::sync::atomic::AtomicBool,
+        connect_delay_ms: std::sync::atomic::AtomicU64,
+        refuse: std::sync::atomic::AtomicBool,
+        fail_connects: std::sync::atomic::AtomicU32,
+        drop_next: std::sync::atomic::AtomicBool,
+        burst: std::sync::atomic::AtomicU64,
+        connects: parking_lot::Mutex<Vec<String>>,
+        cookies: parking_lot::Mutex<Vec<Option<String>>>,
+        delivered: parking_lot::Mutex<std::collections::HashSet<String>>,
+        scripted: parking_lot::Mutex<std::collections::VecDeque<crate::danmu::DanmuMessage>>,
+    }
+
+    #[async_trait::async_trait]
+    impl DanmuProvider for SwitchMockProvider {
+        fn platform(&self) -> &str {
+            "mock"
+        }
+
+        async fn connect(
+            &self,
+            room_id: &str,
+            config: ConnectionConfig,
+        ) -> platforms_parser::danmaku::error::Result<platforms_parser::danmaku::DanmuConnection>
+        {
+            self.cookies.lock().push(config.cookies);
+            let delay = self
+                .connect_delay_ms
+                .load(std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            if room_id == "hang" {
+                std::future::pending::<()>().await;
+            }
+            if room_id == "private" {
+                return Err(platforms_parser::danmaku::DanmakuError::connection_failed(
+                    DanmuErrorCode::AuthFailed,
+                    "login required",
+                ));
+            }
+            let failing = self
+                .fail_connects
+                .fetch_update(
+                    std::sync::atomic::Ordering::SeqCst,
+                    std::sync::atomic::Ordering::SeqCst,
+                    |n| n.checked_sub(1),
+                )
+                .is_ok();
+            if failing || room_id == "down" || self.refuse.load(std::sync::atomic::Ordering::SeqCst)
+            {
+                return Err(platforms_parser::danmaku::DanmakuError::connection(
+                    "room is down",
+                ));
+            }
+            let mut connects = self.connects.lock();
+            connects.push(room_id.to_string());
+            let id = format!("{room_id}-{}", connects.len());
+            Ok(platforms_parser::danmaku::DanmuConnection::new(
+                id, "mock", room_id,
+            ))
+        }
+
+        async fn disconnect(
+            &self,
+            _connection: &mut platforms_parser::danmaku::DanmuConnection,
+        ) -> platforms_parser::danmaku::error::Result<()> {
+            Ok(())
+        }
+
+        async fn receive(
+            &self,
+            connection: &platforms_parser::danmaku::DanmuConnection,
+        ) -> platforms_parser::danmaku::error::Result<Option<platforms_parser::danmaku::DanmuItem>>
+        {
+            if self
+                .drop_next
+                .swap(false, std::sync::atomic::Ordering::SeqCst)
+            {
+                return Err(platforms_parser::danmaku::DanmakuError::connection(
+                    "connection dropped",
+                ));
+            }
+            if let Some(message) = self.scripted.lock().pop_front() {
+                return Ok(Some(platforms_parser::danmaku::DanmuItem::Message(message)));
+            }
+            if let Ok(left) = self.burst.fetch_update(
+                std::sync::atomic::Ordering::SeqCst,
+                std::sync::atomic::Ordering::SeqCst,
+                |n| n.checked_sub(1),
+            ) {
+                return Ok(Some(platforms_parser::danmaku::DanmuItem::Message(
+                    crate::danmu::DanmuMessage::chat(
+                        format!("burst-{left}"),
+                        "u1",
+                        "user",
+                        "gift storm",
+                    ),
+                )));
+            }
+            if !self.live.load(std::sync::atomic::Ordering::SeqCst)
+                || !self.delivered.lock().insert(connection.id.clone())
+            {
+                return Ok(None);
+            }
+            Ok(Some(platforms_parser::danmaku::DanmuItem::Message(
+                crate::danmu::DanmuMessage::chat(&connection.id, "u1", "user", "hello"),
+            )))
+        }
+
+        fn supports_url(&self, url: &str) -> bool {
+            url.starts_with("mock://")
+        }
+
+        fn extract_room_id(&self, url: &str) -> Option<String> {
+            url.strip_prefix("mock://").map(str::to_string)
+        }
+
+        fn validate_room_id(&self, room_id: &str) -> bool {
+            !room_id.is_empty()
+                && room_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+    }
+
+    fn mock_service() -> (DanmuService, Arc<SwitchMockProvider>) {
+        mock_service_with(DanmuServiceConfig {
+            sampling_enabled: false,
+            ..Default::default()
+        })
+    }
+
+    fn mock_service_with(config: DanmuServiceConfig) -> (DanmuService, Arc<SwitchMockProvider>) {
+        let provider = Arc::new(SwitchMockProvider::default());
+        let mut registry = ProviderRegistry::new();
+        registry.register(provider.clone());
+        (DanmuService::with_providers(config, registry), provider)
+    }
+
+    async fn wait_for_event(
+        events: &mut broadcast::Receiver<DanmuEvent>,
+        matches: impl Fn(&DanmuEvent) -> bool,
+    ) -> DanmuEvent {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.expect("danmu event");
+                if matches(&event) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("danmu event arrived")
+    }
+
+    async fn wait_for_delivered(provider: &SwitchMockProvider, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while provider.delivered.lock().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("mock provider delivered messages");
+    }
+
+    #[tokio::test]
+    async fn keyword_alerts_are_rate_limited_and_counted() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            keyword_alerts: vec![KeywordRule {
+                pattern: "HELLO".to_string(),
+                is_regex: false,
+                case_insensitive: true,
+            }],
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let matched = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::KeywordMatched { .. })
+        })
+        .await;
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+        let stats = service.stop_collection("s1").await.unwrap();
+
+        assert!(matches!(
+            matched,
+            DanmuEvent::KeywordMatched { rule, content, .. }
+                if rule.pattern == "HELLO" && content == "hello"
+        ));
+        assert_eq!(stats.keyword_matches["HELLO"], 2);
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event, DanmuEvent::KeywordMatched { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn filters_drop_messages_and_can_be_updated() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            filters: DanmuFilterConfig {
+                blocked_user_ids: vec!["u1".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        let stats = handle.current_statistics().await.unwrap();
+        assert_eq!((stats.total_count, stats.filtered_count), (0, 1));
+
+        handle
+            .update_filters(&DanmuFilterConfig::default())
+            .await
+            .unwrap();
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+        let stats = service.stop_collection("s1").await.unwrap();
+        assert_eq!((stats.total_count, stats.filtered_count), (1, 1));
+
+        let stopped = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionStopped { .. })
+        })
+        .await;
+        assert!(matches!(
+            stopped,
+            DanmuEvent::CollectionStopped { statistics, .. } if statistics.filtered_count == 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_filters_rejects_invalid_pattern() {
+        let (service, _provider) = mock_service();
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+
+        let error = handle
+            .update_filters(&DanmuFilterConfig {
+                blocked_content_patterns: vec!["[unclosed".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn collection_limit_rejects_extra_collections() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            max_concurrent_collections: Some(2),
+            ..Default::default()
+        });
+
+        let starts = (0..5).map(|i| {
+            let service = &service;
+            async move {
+                service
+                    .start_collection(
+                        &format!("s{i}"),
+                        &format!("streamer-{i}"),
+                        "mock://room-a",
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+            }
+        });
+        let results = futures::future::join_all(starts).await;
+
+        let rejected = results
+            .iter()
+            .filter(|result| matches!(result, Err(Error::CollectionLimitReached { limit: 2 })))
+            .count();
+        assert_eq!(rejected, 3);
+        assert_eq!(
+            service.capacity(),
+            CollectionCapacity {
+                used: 2,
+                limit: Some(2)
+            }
+        );
+
+        let running = service.active_sessions();
+        service.stop_collection(&running[0]).await.unwrap();
+        assert_eq!(service.capacity().used, 1);
+        service
+            .start_collection("s9", "streamer-9", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn collection_limit_waits_for_a_free_slot() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            max_concurrent_collections: Some(1),
+            collection_limit_policy: CollectionLimitPolicy::Wait {
+                timeout: Duration::from_secs(5),
+            },
+            ..Default::default()
+        });
+        let service = Arc::new(service);
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+
+        let waiting = tokio::spawn({
+            let service = service.clone();
+            async move {
+                service
+                    .start_collection("s2", "streamer-2", "mock://room-a", None, None, None)
+                    .await
+                    .map(|_| ())
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        service.stop_collection("s1").await.unwrap();
+        waiting.await.unwrap().unwrap();
+        assert!(service.is_collecting("s2"));
+    }
+
+    #[tokio::test]
+    async fn collection_limit_wait_times_out() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            max_concurrent_collections: Some(1),
+            collection_limit_policy: CollectionLimitPolicy::Wait {
+                timeout: Duration::from_millis(20),
+            },
+            ..Default::default()
+        });
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+
+        let Err(error) = service
+            .start_collection("s2", "streamer-2", "mock://room-a", None, None, None)
+            .await
+        else {
+            panic!("no slot should free up");
+        };
+
+        assert!(matches!(error, Error::CollectionLimitReached { limit: 1 }));
+    }
+
+    async fn session_repo_with_session(session_id: &str) -> Arc<dyn SessionRepository> {
+        use crate::database::models::{LiveSessionDbModel, StreamerDbModel};
+        use crate::database::repositories::{
+            SqlxSessionRepository, SqlxStreamerRepository, StreamerRepository as _,
+        };
+        use crate::database::{init_pool_with_size, run_migrations};
+
+        let pool = init_pool_with_size("sqlite::memory:", 1).await.unwrap();
+        run_migrations(&pool).await.unwrap();
+        let mut streamer = StreamerDbModel::new("Streamer One", "mock://room-a", "platform-twitch");
+        streamer.id = "streamer-1".to_string();
+        SqlxStreamerRepository::new(pool.clone(), pool.clone())
+            .create_streamer(&streamer)
+            .await
+            .unwrap();
+        let repo = SqlxSessionRepository::new(pool.clone(), pool.clone());
+        let mut session = LiveSessionDbModel::new("streamer-1");
+        session.id = session_id.to_string();
+        repo.create_session(&session).await.unwrap();
+        Arc::new(repo)
+    }
+
+    #[tokio::test]
+    async fn persisted_statistics_keep_gift_values() {
+        let repo = session_repo_with_session("s1").await;
+        let now = chrono::Utc::now();
+        let mut aggregator = platforms_parser::danmaku::StatisticsAggregator::new();
+        aggregator.record_gift("u1", "Alice", "rocket", 2, 1000, now);
+        aggregator.record_gift("u2", "Bob", "flower", 10, 10, now);
+        aggregator.record_message("u3", "Carol", "hi", false, now);
+
+        persist_statistics(
+            Some(repo.as_ref()),
+            "s1",
+            &aggregator.current_stats(),
+            &HashMap::new(),
+        )
+        .await;
+        let persisted = load_statistics(repo.as_ref(), "s1").await.unwrap().unwrap();
+
+        assert_eq!(persisted.total_count, 3);
+        assert_eq!(persisted.gift_count, 2);
+        assert_eq!(persisted.total_gift_value, 1010);
+        assert_eq!(persisted.top_gifters[0].user_id, "u1");
+        assert_eq!(persisted.top_gifters[0].gift_count, 2);
+        assert_eq!(persisted.gift_breakdown.len(), 2);
+        assert_eq!(persisted.gift_breakdown[1].gift_name, "flower");
+        assert_eq!(persisted.gift_breakdown[1].count, 10);
+    }
+
+    #[tokio::test]
+    async fn statistics_are_persisted_periodically_while_collecting() {
+        let repo = session_repo_with_session("s1").await;
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            partial_statistics_interval: Duration::from_millis(20),
+            persist_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let service = service.with_session_repository(repo.clone());
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        let checkpoint = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(statistics) = load_statistics(repo.as_ref(), "s1").await.unwrap() {
+                    return statistics;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("statistics persisted before the collection stopped");
+        assert!(service.is_collecting("s1"));
+        assert_eq!(checkpoint.total_count, 1);
+
+        let stopped = service.stop_collection("s1").await.unwrap();
+        let persisted = load_statistics(repo.as_ref(), "s1").await.unwrap().unwrap();
+        assert!(!stopped.is_partial);
+        assert_eq!(persisted.total_count, stopped.total_count);
+    }
+
+    #[tokio::test]
+    async fn session_tags_are_reported_and_persisted_on_stop() {
+        let repo = session_repo_with_session("s1").await;
+        let (service, provider) = mock_service();
+        let service = service.with_session_repository(repo.clone());
+        let mut events = service.subscribe();
+        assert!(
+            service
+                .set_session_tags("s1", HashMap::from([("k".to_string(), "v".to_string())]))
+                .is_err()
+        );
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(service.get_session_tags("s1"), Some(HashMap::new()));
+        let tags = HashMap::from([
+            ("stream_title".to_string(), "Gaming session".to_string()),
+            ("category".to_string(), "valorant".to_string()),
+        ]);
+        service.set_session_tags("s1", tags.clone()).unwrap();
+        assert_eq!(service.get_session_tags("s1").as_ref(), Some(&tags));
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        service.stop_collection("s1").await.unwrap();
+        let stopped = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionStopped { .. })
+        })
+        .await;
+        assert!(matches!(stopped, DanmuEvent::CollectionStopped { tags: t, .. } if t == tags));
+        assert!(service.get_session_tags("s1").is_none());
+
+        let model = repo.get_danmu_statistics("s1").await.unwrap().unwrap();
+        let persisted: PersistedGiftStatistics =
+            serde_json::from_str(model.gift_statistics.as_deref().unwrap()).unwrap();
+        assert_eq!(persisted.tags, tags);
+    }
+
+    #[tokio::test]
+    async fn sessions_are_serialized_and_restored_with_statistics() {
+        let (service, provider) = mock_service();
+        let service = service.with_session_repository(session_repo_with_session("s1").await);
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        let sessions: Vec<PersistableSession> =
+            serde_json::from_str(&service.serialize_sessions().unwrap()).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s1");
+        assert_eq!(sessions[0].streamer_url, "mock://room-a");
+        assert_eq!(sessions[0].sampling_config, None);
+        let before = service.stop_collection("s1").await.unwrap();
+        assert_eq!(before.total_count, 1);
+
+        let session = sessions.into_iter().next().unwrap();
+        let started_at = session.started_at;
+        service.restore_session(session).await.unwrap();
+        wait_for_delivered(&provider, 2).await;
+
+        let restored: Vec<PersistableSession> =
+            serde_json::from_str(&service.serialize_sessions().unwrap()).unwrap();
+        assert_eq!(restored[0].started_at, started_at);
+        let after = service.stop_collection("s1").await.unwrap();
+        assert_eq!(after.total_count, 2);
+        assert_eq!(after.top_talkers[0].message_count, 2);
+    }
+
+    #[tokio::test]
+    async fn start_collection_rejects_invalid_keyword_regex() {
+        let (service, _provider) = mock_service();
+
+        let Err(error) = service
+            .start_collection_with(
+                "s1",
+                "streamer-1",
+                "mock://room-a",
+                StartCollectionOptions {
+                    keyword_alerts: Some(vec![KeywordRule {
+                        pattern: "[unclosed".to_string(),
+                        is_regex: true,
+                        case_insensitive: false,
+                    }]),
+                    ..Default::default()
+                },
+            )
+            .await
+        else {
+            panic!("an invalid keyword regex should be rejected");
+        };
+
+        assert!(matches!(error, Error::Validation(_)));
+        assert!(!service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn end_segment_returns_per_segment_statistics() {
+        let (service, provider) = mock_service();
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", dir.path().join("1.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        let first = handle.end_segment("seg-1").await.unwrap();
+        let ended = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::SegmentEnded { .. })
+        })
+        .await;
+
+        handle
+            .start_segment("seg-2", dir.path().join("2.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+        let second = handle.end_segment("seg-2").await.unwrap();
+        let session = service.stop_collection("s1").await.unwrap();
+
+        assert_eq!(first.statistics.total_count, 1);
+        assert_eq!(first.statistics.top_talkers[0].user_id, "u1");
+        assert!(matches!(
+            ended,
+            DanmuEvent::SegmentEnded { segment_id, statistics, .. }
+                if segment_id == "seg-1" && statistics.total_count == 1
+        ));
+        assert_eq!(second.statistics.total_count, 1);
+        assert_eq!(session.total_count, 2);
+    }
+
+    #[tokio::test]
+    async fn segment_lifecycle_events_precede_command_acks() {
+        let (service, provider) = mock_service();
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let drain = |events: &mut broadcast::Receiver<DanmuEvent>| {
+            std::iter::from_fn(|| events.try_recv().ok()).collect::<Vec<_>>()
+        };
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        let start_time = chrono::Utc::now();
+        handle
+            .start_segment("seg-1", dir.path().join("1.xml"), start_time)
+            .await
+            .unwrap();
+        let started = drain(&mut events);
+        assert!(started.iter().any(|e| matches!(
+            e,
+            DanmuEvent::SegmentStarted { segment_id, output_path, start_time: t, .. }
+                if segment_id == "seg-1" && *output_path == dir.path().join("1.xml") && *t == start_time
+        )));
+
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        let finalized = handle.end_segment("seg-1").await.unwrap();
+        let ended: Vec<_> = drain(&mut events)
+            .into_iter()
+            .filter(|e| {
+                matches!(
+                    e,
+                    DanmuEvent::SegmentCompleted { .. } | DanmuEvent::SegmentEnded { .. }
+                )
+            })
+            .collect();
+        assert!(matches!(
+            &ended[..],
+            [
+                DanmuEvent::SegmentCompleted { segment_id: completed, .. },
+                DanmuEvent::SegmentEnded { segment_id, message_count: 1, bytes_written, .. },
+            ] if completed == "seg-1" && segment_id == "seg-1" && *bytes_written == finalized.size_bytes
+        ));
+
+        handle
+            .start_segment("seg-2", dir.path().join("2.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        service.stop_collection("s1").await.unwrap();
+        let mut stopping = Vec::new();
+        loop {
+            let event = wait_for_event(&mut events, |_| true).await;
+            let stopped = matches!(event, DanmuEvent::CollectionStopped { .. });
+            stopping.push(event);
+            if stopped {
+                break;
+            }
+        }
+        // The open segment is finalized before the collection reports stopping.
+        let segment_ended = stopping.iter().position(|e| {
+            matches!(
+                e,
+                DanmuEvent::SegmentEnded { segment_id, message_count: 0, bytes_written, .. }
+                    if segment_id == "seg-2" && *bytes_written > 0
+            )
+        });
+        assert!(segment_ended.is_some_and(|i| i < stopping.len() - 1));
+    }
+
+    /// Start a collection in the background and return a handle taken while
+    /// it is still connecting.
+    async fn handle_while_connecting(
+        service: &Arc<DanmuService>,
+        url: &'static str,
+    ) -> (
+        CollectionHandle,
+        tokio::task::JoinHandle<Result<CollectionHandle>>,
+    ) {
+        let starting = tokio::spawn({
+            let service = service.clone();
+            async move {
+                service
+                    .start_collection("s1", "streamer-1", url, None, None, None)
+                    .await
+            }
+        });
+        let handle = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(handle) = service.get_handle("s1") {
+                    return handle;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("collection registered");
+        (handle, starting)
+    }
+
+    #[tokio::test]
+    async fn commands_sent_while_connecting_are_replayed_in_order() {
+        let (service, provider) = mock_service();
+        let service = Arc::new(service);
+        provider
+            .connect_delay_ms
+            .store(300, std::sync::atomic::Ordering::SeqCst);
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("1.xml");
+        let second = dir.path().join("2.xml");
+
+        let (handle, starting) = handle_while_connecting(&service, "mock://room-a").await;
+        let (started, ended, restarted) = tokio::join!(
+            handle.start_segment("seg-1", first.clone(), chrono::Utc::now()),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                handle.end_segment("seg-1").await
+            },
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                handle
+                    .start_segment("seg-2", second.clone(), chrono::Utc::now())
+                    .await
+            },
+        );
+
+        starting.await.unwrap().unwrap();
+        started.unwrap();
+        assert_eq!(ended.unwrap().output_path, first);
+        restarted.unwrap();
+        let xml = tokio::fs::read_to_string(&first).await.unwrap();
+        assert!(xml.trim_end().ends_with("</i>"));
+        assert!(second.exists());
+        assert!(service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn commands_sent_while_connecting_fail_when_the_connection_fails() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connect_retries: 0,
+            ..Default::default()
+        });
+        let service = Arc::new(service);
+        provider
+            .connect_delay_ms
+            .store(300, std::sync::atomic::Ordering::SeqCst);
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("1.xml");
+
+        let (handle, starting) = handle_while_connecting(&service, "mock://down").await;
+        let (started, redirected) = tokio::join!(
+            handle.start_segment("seg-1", output.clone(), chrono::Utc::now()),
+            handle.redirect_segment("seg-1", dir.path().join("2.xml")),
+        );
+
+        assert!(starting.await.unwrap().is_err());
+        assert!(started.is_err());
+        assert!(redirected.is_err());
+        assert!(!output.exists());
+        assert!(!service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn rate_stream_counts_recent_messages_until_the_session_stops() {
+        use futures::StreamExt;
+
+        let (service, provider) = mock_service();
+        let mut missing = std::pin::pin!(service.rate_stream("missing", 5));
+        assert_eq!(missing.next().await, None);
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        let mut rates = std::pin::pin!(service.rate_stream("s1", 5));
+        assert_eq!(rates.next().await, Some(0));
+
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while rates.next().await != Some(1) {}
+        })
+        .await
+        .expect("rate includes the delivered message");
+
+        service.stop_collection("s1").await.unwrap();
+        let ended = tokio::time::timeout(Duration::from_secs(5), async {
+            while rates.next().await.is_some() {}
+        })
+        .await;
+        assert!(ended.is_ok(), "rate stream ends with the session");
+    }
+
+    #[tokio::test]
+    async fn redirect_segment_moves_the_file_and_keeps_appending() {
+        let (service, provider) = mock_service();
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("disk-a").join("1.xml");
+        let moved = dir.path().join("disk-b").join("moved.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", original.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        handle
+            .redirect_segment("seg-1", moved.clone())
+            .await
+            .unwrap();
+        assert!(!original.exists());
+        assert!(moved.exists());
+
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+        let finalized = handle.end_segment("seg-1").await.unwrap();
+        let ended = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::SegmentEnded { .. })
+        })
+        .await;
+
+        assert_eq!(finalized.output_path, moved);
+        assert!(matches!(
+            ended,
+            DanmuEvent::SegmentEnded { output_path, .. } if output_path == moved
+        ));
+        let xml = tokio::fs::read_to_string(&moved).await.unwrap();
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.trim_end().ends_with("</i>"));
+        assert_eq!(xml.matches(">hello<").count(), 2);
+        assert!(!original.exists());
+    }
+
+    #[tokio::test]
+    async fn failed_redirect_keeps_the_original_file() {
+        let (service, provider) = mock_service();
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("1.xml");
+        let taken = dir.path().join("taken.xml");
+        tokio::fs::write(&taken, "keep me").await.unwrap();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", original.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        let unknown = handle
+            .redirect_segment("seg-2", dir.path().join("other.xml"))
+            .await;
+        let clobber = handle.redirect_segment("seg-1", taken.clone()).await;
+        let finalized = handle.end_segment("seg-1").await.unwrap();
+
+        assert!(matches!(unknown, Err(Error::NotFound { id, .. }) if id == "seg-2"));
+        assert!(matches!(clobber, Err(Error::Validation(_))));
+        assert!(!dir.path().join("other.xml").exists());
+        assert_eq!(tokio::fs::read_to_string(&taken).await.unwrap(), "keep me");
+        assert_eq!(finalized.output_path, original);
+        let xml = tokio::fs::read_to_string(&original).await.unwrap();
+        assert_eq!(xml.matches(">hello<").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn xml_flush_policy_controls_when_messages_reach_the_file() {
+        for (policy, flushed_early) in [
+            (FlushPolicy::EveryN(1), true),
+            (FlushPolicy::Interval(Duration::from_secs(3600)), false),
+        ] {
+            let (service, provider) = mock_service_with(DanmuServiceConfig {
+                xml_flush: policy,
+                xml_flush_fsync: true,
+                write_batch_timeout_ms: 10,
+                ..Default::default()
+            });
+            let dir = tempfile::tempdir().unwrap();
+            let output = dir.path().join("1.xml");
+
+            let handle = service
+                .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+                .await
+                .unwrap();
+            handle
+                .start_segment("seg-1", output.clone(), chrono::Utc::now())
+                .await
+                .unwrap();
+            provider
+                .live
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+            wait_for_delivered(&provider, 1).await;
+            // Let the message leave the runner's buffer.
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let partial = tokio::fs::read_to_string(&output).await.unwrap();
+            handle.end_segment("seg-1").await.unwrap();
+            let complete = tokio::fs::read_to_string(&output).await.unwrap();
+
+            assert_eq!(partial.contains(">hello<"), flushed_early, "{policy:?}");
+            assert!(!partial.contains("</i>"));
+            assert!(complete.contains(">hello<"));
+            assert!(complete.trim_end().ends_with("</i>"));
+        }
+    }
+
+    #[tokio::test]
+    async fn end_segment_returns_after_file_is_finalized() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            sync_segments_on_end: true,
+            segment_finalize_delay: Some(Duration::from_millis(300)),
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        let started = std::time::Instant::now();
+        let finalized = handle.end_segment("seg-1").await.unwrap();
+
+        // The ack waits for the slow finalize rather than the queued command.
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(finalized.segment_id, "seg-1");
+        assert_eq!(finalized.output_path, output);
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert!(xml.trim_end().ends_with("</i>"));
+        assert_eq!(xml.matches(">hello<").count(), 1);
+        assert_eq!(finalized.size_bytes, xml.len() as u64);
+        assert_eq!(finalized.statistics.total_count, 1);
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn end_segment_times_out_on_slow_finalize() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            end_segment_timeout: Duration::from_millis(500),
+            segment_finalize_delay: Some(Duration::from_secs(5)),
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", dir.path().join("1.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        let result = handle.end_segment("seg-1").await;
+
+        assert!(matches!(result, Err(Error::Other(message)) if message.contains("seg-1")));
+    }
+
+    #[tokio::test]
+    async fn rotation_splits_segment_into_parts() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            rotation: DanmuRotationConfig {
+                max_messages: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        for (delivered, room) in [(2, "mock://room-b"), (3, "mock://room-c")] {
+            service.switch_provider("s1", room).await.unwrap();
+            wait_for_delivered(&provider, delivered).await;
+        }
+        let finalized = handle.end_segment("seg-1").await.unwrap();
+
+        let rotated = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::SegmentRotated { part: 3, .. })
+        })
+        .await;
+        assert!(matches!(
+            rotated,
+            DanmuEvent::SegmentRotated { segment_id, path, .. }
+                if segment_id == "seg-1" && path == dir.path().join("segment_part3.xml")
+        ));
+        assert_eq!(finalized.parts, 3);
+        assert_eq!(finalized.output_path, dir.path().join("segment_part3.xml"));
+        assert_eq!(finalized.statistics.total_count, 3);
+        for name in ["segment.xml", "segment_part2.xml", "segment_part3.xml"] {
+            let xml = tokio::fs::read_to_string(dir.path().join(name))
+                .await
+                .unwrap();
+            assert_eq!(xml.matches(">hello<").count(), 1, "{name}");
+            assert!(xml.trim_end().ends_with("</i>"), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn end_segment_rejects_unknown_segment() {
+        let (service, _provider) = mock_service();
+        let dir = tempfile::tempdir().unwrap();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        let never_started = handle.end_segment("seg-1").await;
+        handle
+            .start_segment("seg-2", dir.path().join("2.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        let other = handle.end_segment("seg-1").await;
+
+        assert!(matches!(never_started, Err(Error::NotFound { id, .. }) if id == "seg-1"));
+        assert!(matches!(other, Err(Error::NotFound { .. })));
+        // The collection keeps running and the active segment can still be ended.
+        assert!(handle.end_segment("seg-2").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn switch_provider_keeps_segment_and_statistics() {
+        let (service, provider) = mock_service();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+
+        // The handle obtained before the switch still drives the same collector.
+        handle.end_segment("seg-1").await.unwrap();
+        let stats = service.stop_collection("s1").await.unwrap();
+
+        assert_eq!(*provider.connects.lock(), vec!["room-a", "room-b"]);
+        assert_eq!(stats.total_count, 2);
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert_eq!(xml.matches(">hello<").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn ass_output_format_writes_dialogue_lines() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            output_format: DanmuOutputFormat::Ass,
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.ass");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        handle.end_segment("seg-1").await.unwrap();
+        service.stop_collection("s1").await.unwrap();
+
+        let ass = tokio::fs::read_to_string(&output).await.unwrap();
+        assert!(ass.contains("; Segment ID: seg-1\n"));
+        assert_eq!(
+            ass.lines()
+                .filter(|l| l.starts_with("Dialogue:") && l.ends_with("}hello"))
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn segments_can_request_json_lines_alongside_xml() {
+        let (service, provider) = mock_service();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment_with_format(
+                "seg-1",
+                output.clone(),
+                chrono::Utc::now(),
+                Some(DanmuOutputFormat::Both),
+            )
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        let both = handle.end_segment("seg-1").await.unwrap();
+        handle
+            .start_segment("seg-2", dir.path().join("plain.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        let plain = handle.end_segment("seg-2").await.unwrap();
+        service.stop_collection("s1").await.unwrap();
+
+        let jsonl_path = dir.path().join("segment.jsonl");
+        assert_eq!(both.output_paths, vec![output.clone(), jsonl_path.clone()]);
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert!(xml.contains(">hello</d>"));
+        let jsonl = tokio::fs::read_to_string(&jsonl_path).await.unwrap();
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["content"], "hello");
+        assert_eq!(lines[0]["user_id"], "u1");
+        assert_eq!(plain.output_paths, vec![dir.path().join("plain.xml")]);
+        assert!(!dir.path().join("plain.jsonl").exists());
+    }
+
+    #[tokio::test]
+    async fn switch_provider_falls_back_when_new_url_fails() {
+        let (service, provider) = mock_service();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+
+        let result = service.switch_provider("s1", "mock://down").await;
+
+        assert!(result.is_err());
+        assert!(service.is_collecting("s1"));
+        assert_eq!(*provider.connects.lock(), vec!["room-a", "room-a"]);
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pause_drops_messages_and_still_finalizes_segment() {
+        let (service, provider) = mock_service();
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        handle.pause().await.unwrap();
+        wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionPaused { .. })
+        })
+        .await;
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        handle.end_segment("seg-1").await.unwrap();
+        let completed = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::SegmentCompleted { .. })
+        })
+        .await;
+        assert!(matches!(
+            completed,
+            DanmuEvent::SegmentCompleted {
+                message_count: 0,
+                ..
+            }
+        ));
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert!(xml.trim_end().ends_with("</i>"));
+        assert!(!xml.contains(">hello<"));
+
+        handle.resume().await.unwrap();
+        let resumed = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionResumed { .. })
+        })
+        .await;
+        assert!(matches!(
+            resumed,
+            DanmuEvent::CollectionResumed {
+                dropped_count: 1,
+                ..
+            }
+        ));
+
+        // Statistics keep counting while paused by default.
+        let stats = service.stop_collection("s1").await.unwrap();
+        assert_eq!(stats.total_count, 1);
+    }
+
+    #[test]
+    fn event_broadcast_capacity_drops_events_for_slow_consumer() {
+        let service = DanmuService::new(DanmuServiceConfig {
+            event_broadcast_capacity: 32,
+            ..Default::default()
+        });
+        let mut slow = service.subscribe();
+
+        for i in 0..50 {
+            let _ = service.event_tx.send(DanmuEvent::Error {
+                session_id: format!("s{i}"),
+                error: "test".to_string(),
+            });
+        }
+
+        assert_eq!(service.event_lag(), 32);
+        assert!(matches!(
+            slow.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(18))
+        ));
+        assert!(matches!(
+            slow.try_recv(),
+            Ok(DanmuEvent::Error { session_id, .. }) if session_id == "s18"
+        ));
+        assert_eq!(service.event_lag(), 31);
+    }
+
+    #[tokio::test]
+    async fn subscribe_session_yields_only_events_of_that_session() {
+        let service = DanmuService::new(DanmuServiceConfig::default());
+        let mut stream = service.subscribe_session("s1");
+
+        for session_id in ["s2", "s1", "s3", "s1"] {
+            service.event_tx.send(DanmuEvent::Error {
+                session_id: session_id.to_string(),
+                error: "test".to_string(),
+            });
+        }
+
+        for _ in 0..2 {
+            let event = tokio::time::timeout(Duration::from_secs(1), stream.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(event.session_id(), "s1");
+        }
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), stream.recv())
+                .await
+                .is_err()
+        );
+        assert_eq!(stream.dropped_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn subscribe_streamer_follows_the_streamer_sessions() {
+        let service = DanmuService::new(DanmuServiceConfig::default());
+        let _done_tx = seed_active_collection(&service, "streamer-1", "s1");
+        let mut stream = service.subscribe_streamer("streamer-1");
+
+        service.event_tx.send(DanmuEvent::Error {
+            session_id: "s1".to_string(),
+            error: "test".to_string(),
+        });
+        service.event_tx.send(DanmuEvent::Error {
+            session_id: "other".to_string(),
+            error: "test".to_string(),
+        });
+        let mut sessions = Vec::new();
+        let event = tokio::time::timeout(Duration::from_secs(1), stream.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        sessions.push(event.session_id().to_string());
+
+        // The streamer's next session is matched by the events naming the streamer.
+        service.sessions_by_streamer.remove("streamer-1");
+        service.event_tx.send(DanmuEvent::CollectionStarted {
+            session_id: "s2".to_string(),
+            streamer_id: "streamer-1".to_string(),
+            room_id: "room-a".to_string(),
+            room_id_source: RoomIdSource::Url,
+        });
+        service.event_tx.send(DanmuEvent::Reconnecting {
+            session_id: "s2".to_string(),
+            attempt: 1,
+        });
+
+        for _ in 0..2 {
+            let event = tokio::time::timeout(Duration::from_secs(1), stream.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            sessions.push(event.session_id().to_string());
+        }
+        assert_eq!(sessions, ["s1", "s2", "s2"]);
+    }
+
+    #[test]
+    fn event_broadcast_capacity_has_a_minimum() {
+        let service = DanmuService::new(DanmuServiceConfig {
+            event_broadcast_capacity: 1,
+            ..Default::default()
+        });
+        let mut slow = service.subscribe();
+
+        for _ in 0..16 {
+            let _ = service.event_tx.send(DanmuEvent::Error {
+                session_id: "s1".to_string(),
+                error: "test".to_string(),
+            });
+        }
+
+        assert_eq!(service.event_lag(), 16);
+        assert!(slow.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn connect_timeout_error_reports_configured_value() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            connect_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "mock://hang", None, None, None)
+            .await
+        else {
+            panic!("connect to a hanging room should time out");
+        };
+
+        assert!(error.to_string().contains("timed out after 50ms"));
+        assert!(!service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn registered_providers_are_listed_and_used_by_new_collections() {
+        let (service, provider) = mock_service();
+        assert_eq!(
+            service.list_providers(),
+            [ProviderInfo {
+                platform: "mock".to_string(),
+                url_patterns: Vec::new(),
+                requires_cookies: false,
+            }]
+        );
+
+        let replacement = Arc::new(SwitchMockProvider::default());
+        assert!(
+            service
+                .register_provider(replacement.clone(), false)
+                .is_err()
+        );
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        service
+            .register_provider(replacement.clone(), true)
+            .unwrap();
+        service
+            .start_collection("s2", "streamer-2", "mock://room-b", None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(*provider.connects.lock(), ["room-a"]);
+        assert_eq!(*replacement.connects.lock(), ["room-b"]);
+        assert_eq!(service.list_providers().len(), 1);
+        service.stop_collection("s1").await.unwrap();
+        service.stop_collection("s2").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_reports_handshake_without_collecting() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connection_test_wait: Duration::from_millis(200),
+            ..Default::default()
+        });
+        provider
+            .connect_delay_ms
+            .store(20, std::sync::atomic::Ordering::SeqCst);
+
+        let report = service
+            .test_connection("mock://room-a", Some("SESSDATA=x".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(report.platform, "mock");
+        assert_eq!(report.room_id, "room-a");
+        assert_eq!(report.room_id_source, RoomIdSource::Url);
+        assert!(report.handshake_latency >= Duration::from_millis(20));
+        assert!(!report.message_received);
+
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let report = service
+            .test_connection("mock://room-a", None, None)
+            .await
+            .unwrap();
+        assert!(report.message_received);
+
+        assert_eq!(*provider.connects.lock(), ["room-a", "room-a"]);
+        assert_eq!(provider.cookies.lock()[0].as_deref(), Some("SESSDATA=x"));
+        assert!(service.active_sessions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connection_returns_connect_errors() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connect_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+
+        let error = service
+            .test_connection("mock://private", None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            connection_error_code(&error),
+            Some(DanmuErrorCode::AuthFailed)
+        );
+        let error = service
+            .test_connection("mock://hang", None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(connection_error_code(&error), Some(DanmuErrorCode::Timeout));
+        assert!(
+            service
+                .test_connection("unknown://room", None, None)
+                .await
+                .is_err()
+        );
+        assert!(provider.connects.lock().is_empty());
+        assert!(service.active_sessions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn start_collection_retries_failed_connects() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connect_retries: 2,
+            connect_retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        provider
+            .fail_connects
+            .store(2, std::sync::atomic::Ordering::SeqCst);
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+
+        assert!(service.is_collecting("s1"));
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event, DanmuEvent::Error { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn start_collection_fails_after_last_connect_retry() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connect_retries: 1,
+            connect_retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        provider
+            .fail_connects
+            .store(5, std::sync::atomic::Ordering::SeqCst);
+
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+        else {
+            panic!("every connect attempt fails");
+        };
+
+        assert!(error.to_string().contains("after 2 attempt(s)"));
+        assert_eq!(
+            provider
+                .fail_connects
+                .load(std::sync::atomic::Ordering::SeqCst),
+            3
+        );
+        assert!(!service.is_collecting("s1"));
+        assert_eq!(service.capacity().used, 0);
+        let errors = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|event| matches!(event, DanmuEvent::Error { .. }))
+            .count();
+        assert_eq!(errors, 1);
+    }
+
+    fn connection_error_code(error: &Error) -> Option<DanmuErrorCode> {
+        match error {
+            Error::DanmuConnection(error) => Some(error.code),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn start_collection_reports_connection_error_codes() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            connect_timeout: Duration::from_millis(50),
+            connect_retries: 3,
+            connect_retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "unknown://room", None, None, None)
+            .await
+        else {
+            panic!("no provider handles the URL");
+        };
+        assert_eq!(
+            connection_error_code(&error),
+            Some(DanmuErrorCode::ProviderNotFound)
+        );
+
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "mock://hang", None, None, None)
+            .await
+        else {
+            panic!("connect to a hanging room should time out");
+        };
+        assert_eq!(connection_error_code(&error), Some(DanmuErrorCode::Timeout));
+
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "mock://down", None, None, None)
+            .await
+        else {
+            panic!("connect to a down room fails");
+        };
+        assert_eq!(
+            connection_error_code(&error),
+            Some(DanmuErrorCode::NetworkError)
+        );
+        assert!(error.to_string().contains("after 4 attempt(s)"));
+
+        // Credentials are not retried with the same parameters.
+        let attempts_before = provider.cookies.lock().len();
+        let Err(error) = service
+            .start_collection("s1", "streamer-1", "mock://private", None, None, None)
+            .await
+        else {
+            panic!("connect to a private room fails");
+        };
+        assert_eq!(
+            connection_error_code(&error),
+            Some(DanmuErrorCode::AuthFailed)
+        );
+        assert!(error.to_string().contains("after 1 attempt(s)"));
+        assert_eq!(provider.cookies.lock().len(), attempts_before + 1);
+    }
+
+    #[tokio::test]
+    async fn start_collection_with_overrides_connect_timeout() {
+        let (service, _provider) = mock_service();
+
+        let Err(error) = service
+            .start_collection_with(
+                "s1",
+                "streamer-1",
+                "mock://hang",
+                StartCollectionOptions {
+                    connect_timeout: Some(Duration::from_millis(20)),
+                    ..Default::default()
+                },
+            )
+            .await
+        else {
+            panic!("connect to a hanging room should time out");
+        };
+
+        assert!(error.to_string().contains("timed out after 20ms"));
+    }
+
+    #[tokio::test]
+    async fn room_id_override_bypasses_url_extraction() {
+        let (service, provider) = mock_service();
+        let mut events = service.subscribe();
+
+        service
+            .start_collection_with(
+                "s1",
+                "streamer-1",
+                "mock://room-a",
+                StartCollectionOptions {
+                    room_id: Some("room-b".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let started = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionStarted { .. })
+        })
+        .await;
+        let DanmuEvent::CollectionStarted {
+            room_id,
+            room_id_source,
+            ..
+        } = started
+        else {
+            unreachable!();
+        };
+        assert_eq!(room_id, "room-b");
+        assert_eq!(room_id_source, RoomIdSource::Override);
+        assert_eq!(*provider.connects.lock(), ["room-b"]);
+
+        service
+            .start_collection("s2", "streamer-2", "mock://room-c", None, None, None)
+            .await
+            .unwrap();
+        let started = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionStarted { .. })
+        })
+        .await;
+        assert!(matches!(
+            started,
+            DanmuEvent::CollectionStarted {
+                room_id_source: RoomIdSource::Url,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn room_id_override_is_validated_by_provider() {
+        let (service, provider) = mock_service();
+
+        let Err(error) = service
+            .start_collection_with(
+                "s1",
+                "streamer-1",
+                "mock://room-a",
+                StartCollectionOptions {
+                    room_id: Some("room b".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+        else {
+            panic!("invalid room ID override should be rejected");
+        };
+
+        assert_eq!(
+            connection_error_code(&error),
+            Some(DanmuErrorCode::RoomNotFound)
+        );
+        assert!(provider.cookies.lock().is_empty());
+        assert!(service.active_sessions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_collection_gives_up_after_stop_timeout() {
+        let service = DanmuService::new(DanmuServiceConfig {
+            stop_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        // Keep the tx alive so the runner never reports completion.
+        let _done_tx = seed_active_collection(&service, "streamer-1", "s1");
+
+        let stats = tokio::time::timeout(Duration::from_secs(2), service.stop_collection("s1"))
+            .await
+            .expect("stop honours the configured timeout")
+            .unwrap();
+
+        assert_eq!(stats.total_count, 0);
+        assert!(stats.is_partial);
+        assert!(!service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_collections_concurrently_within_deadline() {
+        let service = DanmuService::new(DanmuServiceConfig {
+            stop_timeout: Duration::from_secs(30),
+            shutdown_timeout: Duration::from_millis(200),
+            ..Default::default()
+        });
+        let mut done_txs: Vec<_> = (1..=4)
+            .map(|i| seed_active_collection(&service, &format!("streamer-{i}"), &format!("s{i}")))
+            .collect();
+        // s4 hangs: its runner never reports completion.
+        let _hanging = done_txs.pop();
+        let failing = done_txs.pop().unwrap();
+        for done_tx in done_txs {
+            done_tx.send(Ok(DanmuStatistics::default())).unwrap();
+        }
+        failing.send(Err("finalize failed".to_string())).unwrap();
+
+        let started = std::time::Instant::now();
+        let summary = service.shutdown().await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            summary,
+            DanmuShutdownSummary {
+                stopped: 2,
+                timed_out: 1,
+                failed: 1,
+            }
+        );
+        assert!(service.active_sessions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_timeout_returns_and_persists_partial_statistics() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            stop_timeout: Duration::from_millis(100),
+            partial_statistics_interval: Duration::from_millis(20),
+            segment_finalize_delay: Some(Duration::from_secs(5)),
+            ..Default::default()
+        });
+        let repo = session_repo_with_session("s1").await;
+        let service = service.with_session_repository(repo.clone());
+        let dir = tempfile::tempdir().unwrap();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", dir.path().join("1.xml"), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Finalizing the segment outlasts the stop timeout.
+        let stats = service.stop_collection("s1").await.unwrap();
+
+        assert!(stats.is_partial);
+        assert_eq!(stats.total_count, 1);
+        let persisted = load_statistics(repo.as_ref(), "s1").await.unwrap().unwrap();
+        assert_eq!(persisted.total_count, 1);
+    }
+
+    #[tokio::test]
+    async fn stop_timeout_stops_periodic_persists() {
+        let repo = session_repo_with_session("s1").await;
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            stop_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let service = service.with_session_repository(repo.clone());
+        let _done_tx = seed_active_collection(&service, "streamer-1", "s1");
+
+        // Stands in for a runner that still publishes statistics after the
+        // stop timed out.
+        let statistics = |total_count| DanmuStatistics {
+            total_count,
+            ..Default::default()
+        };
+        let (latest_tx, latest_stats) = watch::channel(statistics(1));
+        let persist_stop = CancellationToken::new();
+        let (persist_done_tx, persist_done) = oneshot::channel();
+        {
+            let mut state = service.collections.get_mut("s1").unwrap();
+            state.latest_stats = latest_stats.clone();
+            state.persist_stop = persist_stop.clone();
+            state.persist_done = Some(persist_done);
+        }
+        tokio::spawn({
+            let repo = repo.clone();
+            async move {
+                persist_periodically(
+                    Some(repo),
+                    "s1",
+                    latest_stats,
+                    watch::channel(HashMap::new()).1,
+                    Some(Duration::from_millis(20)),
+                    persist_stop,
+                )
+                .await;
+                let _ = persist_done_tx.send(());
+            }
+        });
+
+        let stats = service.stop_collection("s1").await.unwrap();
+        assert!(stats.is_partial);
+        latest_tx.send_replace(statistics(5));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let persisted = load_statistics(repo.as_ref(), "s1").await.unwrap().unwrap();
+        assert_eq!(persisted.total_count, 1);
+    }
+
+    #[tokio::test]
+    async fn pause_buffers_messages_until_resume() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            pause: DanmuPauseConfig {
+                buffer_capacity: 10,
+                pause_statistics: true,
+                exclude_paused_duration: true,
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        handle.pause().await.unwrap();
+        wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::CollectionPaused { .. })
+        })
+        .await;
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        handle.resume().await.unwrap();
+        handle.end_segment("seg-1").await.unwrap();
+        let stats = service.stop_collection("s1").await.unwrap();
+
+        assert_eq!(stats.total_count, 0);
+        assert_eq!(stats.duration_secs, 0);
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert_eq!(xml.matches(">hello<").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_statistics_returns_live_snapshot() {
+        let (service, provider) = mock_service();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        service
+            .switch_provider("s1", "mock://room-b")
+            .await
+            .unwrap();
+        wait_for_delivered(&provider, 2).await;
+
+        let stats = service.get_statistics("s1").await.unwrap();
+        assert_eq!(stats.total_count, 2);
+        assert_eq!(stats.chat_count, 2);
+        assert_eq!(stats.top_talkers.len(), 1);
+        assert_eq!(stats.top_talkers[0].message_count, 2);
+        assert!(stats.end_time.is_none());
+        assert!(service.is_collecting("s1"));
+
+        let handle = service.get_handle("s1").unwrap();
+        service.stop_collection("s1").await.unwrap();
+        assert!(handle.current_statistics().await.is_err());
+        assert!(service.get_statistics("s1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn super_chats_are_counted_in_statistics() {
+        let (service, provider) = mock_service();
+        provider.scripted.lock().extend([
+            crate::danmu::DanmuMessage::super_chat("sc1", "u1", "Alice", "first", 500),
+            crate::danmu::DanmuMessage::super_chat("sc2", "u1", "Alice", "second", 300),
+            crate::danmu::DanmuMessage::gift("g1", "u2", "Bob", "rocket", 1).with_coin_value(1000),
+        ]);
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !provider.scripted.lock().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("mock provider delivered the scripted messages");
+
+        let stats = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let stats = service.get_statistics("s1").await.unwrap();
+                if stats.total_count == 3 {
+                    return stats;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("statistics count the scripted messages");
+        assert_eq!(stats.total_super_chat_count, 2);
+        assert_eq!(stats.total_super_chat_value_usd_cents, 800);
+        assert_eq!(stats.super_chat_leaderboard.len(), 1);
+        assert_eq!(stats.super_chat_leaderboard[0].user_id, "u1");
+        assert_eq!(stats.super_chat_leaderboard[0].count, 2);
+        assert_eq!(stats.chat_count, 2);
+        assert_eq!(stats.gift_count, 1);
+        assert_eq!(stats.total_gift_value, 1000);
+
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn auto_segment_rolls_over_after_duration() {
+        let (service, _provider) = mock_service_with(DanmuServiceConfig {
+            auto_segment_duration_secs: Some(1),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("seg-1.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", first.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+
+        let rolled = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::SegmentRolledOver { .. })
+        })
+        .await;
+        let DanmuEvent::SegmentRolledOver {
+            session_id,
+            old_segment_id,
+            new_segment_id,
+            path,
+        } = rolled
+        else {
+            unreachable!()
+        };
+        assert_eq!(session_id, "s1");
+        assert_eq!(old_segment_id, "seg-1");
+        assert_eq!(new_segment_id, "s1-1");
+        assert_eq!(path, dir.path().join("s1-1.xml"));
+
+        let xml = tokio::fs::read_to_string(&first).await.unwrap();
+        assert!(xml.trim_end().ends_with("</i>"));
+
+        service.stop_collection("s1").await.unwrap();
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn live_feed_emits_received_messages() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            live_feed: Some(LiveFeedConfig::default()),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let DanmuEvent::Message {
+            session_id,
+            streamer_id,
+            message,
+        } = wait_for_event(&mut events, |e| matches!(e, DanmuEvent::Message { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!(
+            (session_id.as_str(), streamer_id.as_str()),
+            ("s1", "streamer-1")
+        );
+        assert_eq!(message.content, "hello");
+
+        let DanmuEvent::LiveFeedSnapshot {
+            sent_count,
+            dropped_count,
+            ..
+        } = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::LiveFeedSnapshot { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!((sent_count, dropped_count), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn statistics_snapshots_skip_idle_intervals() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            stats_snapshot_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let snapshot = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::StatisticsSnapshot { .. })
+        })
+        .await;
+        let DanmuEvent::StatisticsSnapshot {
+            session_id,
+            statistics,
+        } = snapshot
+        else {
+            unreachable!()
+        };
+        assert_eq!(session_id, "s1");
+        assert_eq!(statistics.total_count, 1);
+
+        // No further messages arrive, so no further snapshots are sent.
+        let idle = tokio::time::timeout(Duration::from_millis(300), async {
+            loop {
+                if let DanmuEvent::StatisticsSnapshot { .. } = events.recv().await.unwrap() {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(idle.is_err());
+
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_max() {
+        let config = DanmuReconnectConfig {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            max_attempts: 10,
+        };
+        let delays: Vec<u64> = (1..=5)
+            .map(|attempt| config.delay_for_attempt(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        assert_eq!(config.delay_for_attempt(100), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn reconnect_keeps_segment_and_statistics() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            reconnect: DanmuReconnectConfig {
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("segment.xml");
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        handle
+            .start_segment("seg-1", output.clone(), chrono::Utc::now())
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+
+        provider
+            .drop_next
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let reconnected =
+            wait_for_event(&mut events, |e| matches!(e, DanmuEvent::Reconnected { .. })).await;
+        assert!(matches!(
+            reconnected,
+            DanmuEvent::Reconnected { attempts: 1, .. }
+        ));
+        wait_for_delivered(&provider, 2).await;
+
+        handle.end_segment("seg-1").await.unwrap();
+        let stats = service.stop_collection("s1").await.unwrap();
+
+        assert_eq!(*provider.connects.lock(), vec!["room-a", "room-a"]);
+        assert_eq!(stats.total_count, 2);
+        let xml = tokio::fs::read_to_string(&output).await.unwrap();
+        assert_eq!(xml.matches(">hello<").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn health_tracks_messages_and_reconnects() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            reconnect: DanmuReconnectConfig {
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        let health = service.health("s1").unwrap();
+        assert!(health.connected);
+        assert_eq!(
+            (health.platform.as_str(), health.room_id.as_str()),
+            ("mock", "room-a")
+        );
+        assert!(health.last_message_at.is_none());
+
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        // Commands are handled after the received item, so this waits for it.
+        handle.current_statistics().await.unwrap();
+        let health = service.health("s1").unwrap();
+        assert!(health.last_message_at.is_some());
+        assert_eq!(health.last_frame_at, health.last_message_at);
+
+        provider
+            .drop_next
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_event(&mut events, |e| matches!(e, DanmuEvent::Reconnected { .. })).await;
+        wait_for_delivered(&provider, 2).await;
+        handle.current_statistics().await.unwrap();
+        let health = service.health_all();
+        assert_eq!(health.len(), 1);
+        assert!(health[0].connected);
+        assert_eq!(health[0].reconnect_count, 1);
+        // Reset by the message received on the new connection.
+        assert_eq!(health[0].consecutive_errors, 0);
+
+        service.stop_collection("s1").await.unwrap();
+        assert!(service.health("s1").is_none());
+    }
+
+    #[tokio::test]
+    async fn full_ingest_queue_drops_messages_by_policy() {
+        for overflow in [
+            OverflowPolicy::Block,
+            OverflowPolicy::DropNewest,
+            OverflowPolicy::DropOldest,
+        ] {
+            let (service, provider) = mock_service_with(DanmuServiceConfig {
+                ingest_queue: IngestQueueConfig {
+                    capacity: 2,
+                    overflow,
+                },
+                ..Default::default()
+            });
+            let handle = service
+                .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+                .await
+                .unwrap();
+
+            provider
+                .burst
+                .store(50, std::sync::atomic::Ordering::SeqCst);
+            let stats = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let stats = handle.current_statistics().await.unwrap();
+                    if stats.total_count + stats.queue_dropped_count == 50 {
+                        return stats;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("burst handled");
+
+            if overflow == OverflowPolicy::Block {
+                assert_eq!(stats.queue_dropped_count, 0);
+            } else {
+                assert!(stats.queue_dropped_count > 0, "{overflow:?}");
+            }
+            assert_eq!(
+                service.health("s1").unwrap().queue_dropped,
+                stats.queue_dropped_count
+            );
+            let stopped = service.stop_collection("s1").await.unwrap();
+            assert_eq!(stopped.queue_dropped_count, stats.queue_dropped_count);
+        }
+    }
+
+    #[tokio::test]
+    async fn watchdog_reports_stale_connection_until_frames_arrive() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            stale_after: Some(Duration::from_millis(200)),
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        let handle = service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        let stale = wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::ConnectionStale { .. })
+        })
+        .await;
+        assert_eq!(stale.session_id(), "s1");
+        assert!(service.health("s1").unwrap().stale);
+
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 1).await;
+        handle.current_statistics().await.unwrap();
+        let health = service.health("s1").unwrap();
+        assert!(!health.stale);
+        assert_eq!(health.consecutive_errors, 0);
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stop_collection_cancels_reconnect_backoff() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            reconnect: DanmuReconnectConfig {
+                base_delay: Duration::from_secs(30),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .refuse
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        provider
+            .drop_next
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::Reconnecting { attempt: 1, .. })
+        })
+        .await;
+
+        let started = std::time::Instant::now();
+        service.stop_collection("s1").await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!service.is_collecting("s1"));
+    }
+
+    #[tokio::test]
+    async fn update_credentials_retries_reconnect_immediately() {
+        let (service, provider) = mock_service_with(DanmuServiceConfig {
+            reconnect: DanmuReconnectConfig {
+                base_delay: Duration::from_secs(30),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut events = service.subscribe();
+
+        service
+            .start_collection(
+                "s1",
+                "streamer-1",
+                "mock://room-a",
+                None,
+                Some("old".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+        provider
+            .refuse
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        provider
+            .drop_next
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_event(&mut events, |e| {
+            matches!(e, DanmuEvent::Reconnecting { attempt: 1, .. })
+        })
+        .await;
+
+        provider
+            .refuse
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        let started = std::time::Instant::now();
+        service
+            .update_credentials("s1", Some("new".to_string()), None)
+            .await
+            .unwrap();
+        wait_for_event(&mut events, |e| matches!(e, DanmuEvent::Reconnected { .. })).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            provider.cookies.lock().last().cloned().flatten().as_deref(),
+            Some("new")
+        );
+        service.stop_collection("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_credentials_requires_active_session() {
+        let (service, _provider) = mock_service();
+
+        let result = service
+            .update_credentials("missing", Some("new".to_string()), None)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn sessions_for_url_prefix_matches_streamer_urls() {
+        let (service, _provider) = mock_service();
+        for (session_id, url) in [
+            ("s1", "mock://douyu-1"),
+            ("s2", "mock://douyu-2"),
+            ("s3", "mock://huya-1"),
+        ] {
+            service
+                .start_collection(session_id, session_id, url, None, None, None)
+                .await
+                .unwrap();
+        }
+
+        let mut douyu = service.sessions_for_url_prefix("mock://douyu");
+        douyu.sort();
+
+        assert_eq!(douyu, vec!["s1", "s2"]);
+        assert!(
+            service
+                .sessions_for_url_prefix("mock://bilibili")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn active_sessions_detailed_reports_message_counts() {
+        let (service, provider) = mock_service();
+        service
+            .start_collection("s1", "streamer-1", "mock://room-a", None, None, None)
+            .await
+            .unwrap();
+        service
+            .start_collection("s2", "streamer-2", "mock://room-b", None, None, None)
+            .await
+            .unwrap();
+        provider
+            .live
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_delivered(&provider, 2).await;
+
+        let sessions = service.active_sessions_detailed().await;
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session_id, "s1");
+        assert_eq!(sessions[0].streamer_id, "streamer-1");
+        assert_eq!(sessions[0].streamer_url, "mock://room-a");
+        assert_eq!(sessions[1].session_id, "s2");
+        assert!(sessions.iter().all(|s| s.message_count == Some(1)));
+    }
+
+    #[tokio::test]
+    async fn switch_provider_requires_active_session() {
+        let (service, _provider) = mock_service();
+
+        let result = service.switch_provider("missing", "mock://room-b").await;
+
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,319 @@
+//! Spam detection for danmu collection.
+//!
+//! A [`SpamFilter`] keeps a sliding window of recently accepted messages per
+//! user and decides whether a new message is flooding (too many messages per
+//! second) or repeating (too many identical or near-identical messages per
+//! minute). Near-duplicates are found by Jaccard similarity on character
+//! bigrams, which are cached for recent message contents.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Thresholds for suppressing spam messages during collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamDetectionConfig {
+    /// Identical or similar messages one user may send within a minute before
+    /// further ones are suppressed.
+    pub max_identical_per_user_per_minute: u32,
+    /// Messages one user may send within a second before further ones are
+    /// suppressed.
+    pub max_messages_per_user_per_second: f64,
+    /// Jaccard similarity on character bigrams (0.0-1.0) at which two messages
+    /// count as identical; `1.0` only matches equal bigram sets.
+    pub content_similarity_threshold: f64,
+}
+
+impl Default for SpamDetectionConfig {
+    fn default() -> Self {
+        Self {
+            max_identical_per_user_per_minute: 3,
+            max_messages_per_user_per_second: 2.0,
+            content_similarity_threshold: 0.8,
+        }
+    }
+}
+
+/// Message contents whose bigrams are cached for similarity checks.
+const BIGRAM_CACHE_CAPACITY: usize = 1024;
+
+/// Users tracked before idle ones are dropped.
+const MAX_TRACKED_USERS: usize = 10_000;
+
+/// Accepted messages kept per user.
+const MAX_MESSAGES_PER_USER: usize = 64;
+
+type Bigrams = HashSet<(char, char)>;
+
+/// Character bigrams of `content`, ignoring case and whitespace.
+///
+/// Single-character contents yield one bigram pairing the character with itself,
+/// so they still compare equal to themselves.
+fn bigrams(content: &str) -> Bigrams {
+    let chars: Vec<char> = content
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    match chars.as_slice() {
+        [] => Bigrams::new(),
+        [c] => Bigrams::from([(*c, *c)]),
+        _ => chars.windows(2).map(|pair| (pair[0], pair[1])).collect(),
+    }
+}
+
+/// Jaccard similarity of two bigram sets; two empty sets are identical.
+fn jaccard(a: &Bigrams, b: &Bigrams) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    let union = a.len() + b.len() - intersection;
+    intersection as f64 / union as f64
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Least recently used cache of bigram sets, keyed by content hash.
+#[derive(Debug, Default)]
+struct BigramCache {
+    entries: HashMap<u64, (Bigrams, u64)>,
+    tick: u64,
+}
+
+impl BigramCache {
+    /// Bigrams of `content`, computing and caching them when missing.
+    fn get_or_insert(&mut self, hash: u64, content: &str) -> &Bigrams {
+        self.tick += 1;
+        let tick = self.tick;
+        if !self.entries.contains_key(&hash) && self.entries.len() >= BIGRAM_CACHE_CAPACITY {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        let entry = self
+            .entries
+            .entry(hash)
+            .or_insert_with(|| (bigrams(content), tick));
+        entry.1 = tick;
+        &entry.0
+    }
+
+    fn get(&self, hash: u64) -> Option<&Bigrams> {
+        self.entries.get(&hash).map(|(bigrams, _)| bigrams)
+    }
+}
+
+/// Recently accepted messages of one user.
+#[derive(Debug, Default)]
+struct UserWindow {
+    messages: VecDeque<(DateTime<Utc>, u64)>,
+    suppressed: u64,
+    suppressing: bool,
+}
+
+impl UserWindow {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::minutes(1);
+        while self
+            .messages
+            .front()
+            .is_some_and(|(timestamp, _)| *timestamp <= cutoff)
+        {
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// Outcome of [`SpamFilter::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SpamVerdict {
+    /// The message is not spam.
+    Accept,
+    /// The message is spam; `first` is set for the first suppressed message
+    /// since the user's last accepted one.
+    Suppress { first: bool, suppressed_count: u64 },
+}
+
+/// Per-session spam detector.
+#[derive(Debug)]
+pub(crate) struct SpamFilter {
+    config: SpamDetectionConfig,
+    users: HashMap<String, UserWindow>,
+    cache: BigramCache,
+}
+
+impl SpamFilter {
+    pub fn new(config: SpamDetectionConfig) -> Self {
+        Self {
+            config,
+            users: HashMap::new(),
+            cache: BigramCache::default(),
+        }
+    }
+
+    /// Classify a message, recording it when accepted.
+    pub fn check(&mut self, user_id: &str, content: &str, timestamp: DateTime<Utc>) -> SpamVerdict {
+        if self.users.len() >= MAX_TRACKED_USERS && !self.users.contains_key(user_id) {
+            self.drop_idle_users(timestamp);
+        }
+
+        let hash = content_hash(content);
+        let candidate = self.cache.get_or_insert(hash, content).clone();
+        let window = self.users.entry(user_id.to_string()).or_default();
+        window.prune(timestamp);
+
+        let second_ago = timestamp - Duration::seconds(1);
+        let in_last_second = window
+            .messages
+            .iter()
+            .rev()
+            .take_while(|(sent, _)| *sent > second_ago)
+            .count();
+        let flooding = in_last_second as f64 >= self.config.max_messages_per_user_per_second;
+
+        let similar = window
+            .messages
+            .iter()
+            .filter(|(_, previous)| {
+                *previous == hash
+                    || self.cache.get(*previous).is_some_and(|previous| {
+                        jaccard(previous, &candidate) >= self.config.content_similarity_threshold
+                    })
+            })
+            .count();
+        let repeating = similar >= self.config.max_identical_per_user_per_minute as usize;
+
+        if flooding || repeating {
+            window.suppressed += 1;
+            let first = !window.suppressing;
+            window.suppressing = true;
+            return SpamVerdict::Suppress {
+                first,
+                suppressed_count: window.suppressed,
+            };
+        }
+
+        window.suppressing = false;
+        window.messages.push_back((timestamp, hash));
+        if window.messages.len() > MAX_MESSAGES_PER_USER {
+            window.messages.pop_front();
+        }
+        SpamVerdict::Accept
+    }
+
+    /// Forget users without a message in the last minute.
+    fn drop_idle_users(&mut self, now: DateTime<Utc>) {
+        self.users.retain(|_, window| {
+            window.prune(now);
+            !window.messages.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: f64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+            + Duration::milliseconds((secs * 1000.0) as i64)
+    }
+
+    fn filter(max_identical: u32, max_per_second: f64, threshold: f64) -> SpamFilter {
+        SpamFilter::new(SpamDetectionConfig {
+            max_identical_per_user_per_minute: max_identical,
+            max_messages_per_user_per_second: max_per_second,
+            content_similarity_threshold: threshold,
+        })
+    }
+
+    #[test]
+    fn test_jaccard_on_bigrams() {
+        assert_eq!(jaccard(&bigrams("hello"), &bigrams("HELLO")), 1.0);
+        assert_eq!(jaccard(&bigrams("ab"), &bigrams("cd")), 0.0);
+        // {he, el, ll, lo} vs {he, el, ll, lp}: 3 shared of 5.
+        assert!((jaccard(&bigrams("hello"), &bigrams("hellp")) - 0.6).abs() < 1e-9);
+        assert_eq!(jaccard(&bigrams("6"), &bigrams("6")), 1.0);
+    }
+
+    #[test]
+    fn test_suppresses_repeated_messages_within_a_minute() {
+        let mut filter = filter(2, 10.0, 0.8);
+
+        assert_eq!(
+            filter.check("u1", "buy followers", at(0.0)),
+            SpamVerdict::Accept
+        );
+        assert_eq!(
+            filter.check("u1", "buy followers!", at(5.0)),
+            SpamVerdict::Accept
+        );
+        assert_eq!(
+            filter.check("u1", "buy  followers", at(10.0)),
+            SpamVerdict::Suppress {
+                first: true,
+                suppressed_count: 1
+            }
+        );
+        assert_eq!(
+            filter.check("u1", "buy followers", at(15.0)),
+            SpamVerdict::Suppress {
+                first: false,
+                suppressed_count: 2
+            }
+        );
+
+        // Other users and unrelated content are unaffected.
+        assert_eq!(
+            filter.check("u2", "buy followers", at(15.0)),
+            SpamVerdict::Accept
+        );
+        assert_eq!(
+            filter.check("u1", "nice play", at(16.0)),
+            SpamVerdict::Accept
+        );
+
+        // The window slides: a minute after the first messages they no longer count.
+        assert_eq!(
+            filter.check("u1", "buy followers", at(60.5)),
+            SpamVerdict::Accept
+        );
+    }
+
+    #[test]
+    fn test_suppresses_floods_per_second() {
+        let mut filter = filter(100, 2.0, 1.0);
+
+        assert_eq!(filter.check("u1", "a1", at(0.0)), SpamVerdict::Accept);
+        assert_eq!(filter.check("u1", "b2", at(0.2)), SpamVerdict::Accept);
+        assert!(matches!(
+            filter.check("u1", "c3", at(0.4)),
+            SpamVerdict::Suppress { first: true, .. }
+        ));
+        assert_eq!(filter.check("u1", "d4", at(1.1)), SpamVerdict::Accept);
+    }
+
+    #[test]
+    fn test_bigram_cache_is_bounded() {
+        let mut cache = BigramCache::default();
+        for i in 0..(BIGRAM_CACHE_CAPACITY as u64 + 10) {
+            cache.get_or_insert(i, &i.to_string());
+        }
+        assert_eq!(cache.entries.len(), BIGRAM_CACHE_CAPACITY);
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(BIGRAM_CACHE_CAPACITY as u64 + 9).is_some());
+    }
+}
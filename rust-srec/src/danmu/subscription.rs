@@ -0,0 +1,158 @@
+//! Filtered subscriptions to danmu events.
+//!
+//! A [`DanmuEventStream`] is fed by a task that reads the service's shared
+//! broadcast channel and forwards only the events accepted by a filter into a
+//! bounded channel owned by the subscriber, so consumers interested in one
+//! session do not have to receive and discard the events of every other one.
+//!
+//! Events are never waited for: when the subscriber's channel is full the
+//! event being forwarded is dropped, and when the forwarding task falls
+//! behind the broadcast channel the oldest events are lost. Both are counted
+//! in [`DanmuEventStream::dropped_count`], so consumers can detect gaps.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use tokio::sync::{broadcast, mpsc};
+use tracing::debug;
+
+use super::events::DanmuEvent;
+
+/// Stream of the danmu events of a filtered subscription.
+///
+/// Ends when the service shuts down; dropping it stops the forwarding task.
+pub struct DanmuEventStream {
+    rx: mpsc::Receiver<DanmuEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl DanmuEventStream {
+    /// Forward the events of `source` accepted by `filter` into a channel
+    /// holding up to `capacity` events.
+    pub(crate) fn spawn(
+        mut source: broadcast::Receiver<DanmuEvent>,
+        capacity: usize,
+        mut filter: impl FnMut(&DanmuEvent) -> bool + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let task_dropped = dropped.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = tx.closed() => break,
+                    result = source.recv() => match result {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            debug!(missed, "Filtered danmu subscription lagged behind");
+                            task_dropped.fetch_add(missed, Ordering::Relaxed);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                if !filter(&event) {
+                    continue;
+                }
+                match tx.try_send(event) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        task_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => break,
+                }
+            }
+        });
+
+        Self { rx, dropped }
+    }
+
+    /// Receive the next event, or `None` once the service shut down.
+    pub async fn recv(&mut self) -> Option<DanmuEvent> {
+        self.rx.recv().await
+    }
+
+    /// Events lost so far because this subscriber or its forwarding task fell
+    /// behind.
+    ///
+    /// Events lost from the shared broadcast channel were never filtered, so
+    /// the count can include events of other sessions.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Stream for DanmuEventStream {
+    type Item = DanmuEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    fn error_event(session_id: &str) -> DanmuEvent {
+        DanmuEvent::Error {
+            session_id: session_id.to_string(),
+            error: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_only_matching_events() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut stream = DanmuEventStream::spawn(rx, 8, |event| event.session_id() == "s1");
+
+        tx.send(error_event("s2")).unwrap();
+        tx.send(error_event("s1")).unwrap();
+        drop(tx);
+
+        let events: Vec<_> = (&mut stream).collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].session_id(), "s1");
+        assert_eq!(stream.dropped_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_events_dropped_for_a_full_subscriber() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut stream = DanmuEventStream::spawn(rx, 2, |_| true);
+
+        for _ in 0..5 {
+            tx.send(error_event("s1")).unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while stream.dropped_count() < 3 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        drop(tx);
+
+        assert_eq!((&mut stream).collect::<Vec<_>>().await.len(), 2);
+        assert_eq!(stream.dropped_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn counts_events_missed_from_the_broadcast_channel() {
+        let (tx, rx) = broadcast::channel(2);
+        for _ in 0..5 {
+            tx.send(error_event("s1")).unwrap();
+        }
+        let mut stream = DanmuEventStream::spawn(rx, 8, |_| true);
+        drop(tx);
+
+        assert_eq!((&mut stream).collect::<Vec<_>>().await.len(), 2);
+        assert_eq!(stream.dropped_count(), 3);
+    }
+}
@@ -200,7 +200,7 @@ impl MaintenanceRepository {
                     let result = sqlx::query(
                         "DELETE FROM job WHERE id IN (\
                         SELECT j.id FROM job j \
-                        WHERE j.status IN (?, ?, ?) AND j.updated_at < ? \
+                        WHERE j.status IN (?, ?, ?, ?) AND j.updated_at < ? \
                           AND NOT EXISTS (\
                             SELECT 1 FROM dag_step_execution s \
                             JOIN dag_execution d ON d.id = s.dag_id \
@@ -213,6 +213,7 @@ impl MaintenanceRepository {
                     .bind(JobStatus::Completed.as_str())
                     .bind(JobStatus::Failed.as_str())
                     .bind(JobStatus::Cancelled.as_str())
+                    .bind(JobStatus::Skipped.as_str())
                     .bind(cutoff_ms)
                     .bind(DagExecutionStatus::Completed.as_str())
                     .bind(DagExecutionStatus::Failed.as_str())
@@ -255,7 +256,7 @@ impl MaintenanceRepository {
                             SELECT 1 FROM dag_step_execution s \
                             JOIN job j ON j.id = s.job_id \
                             WHERE s.dag_id = d.id \
-                              AND (j.status NOT IN (?, ?, ?) OR j.updated_at >= ?)\
+                              AND (j.status NOT IN (?, ?, ?, ?) OR j.updated_at >= ?)\
                           ) \
                         ORDER BY d.updated_at ASC LIMIT ?\
                         )",
@@ -267,6 +268,7 @@ impl MaintenanceRepository {
                     .bind(JobStatus::Completed.as_str())
                     .bind(JobStatus::Failed.as_str())
                     .bind(JobStatus::Cancelled.as_str())
+                    .bind(JobStatus::Skipped.as_str())
                     .bind(cutoff_ms)
                     .bind(self.batch_size)
                     .execute(&self.write_pool)
@@ -126,12 +126,19 @@ pub struct JobCounts {
     /// Number of failed jobs.
     pub failed: u64,
     pub cancelled: u64,
+    /// Number of jobs skipped because a dependency failed.
+    pub skipped: u64,
 }
 
 impl JobCounts {
     /// Get total count of all jobs.
     pub fn total(&self) -> u64 {
-        self.pending + self.processing + self.completed + self.failed + self.cancelled
+        self.pending
+            + self.processing
+            + self.completed
+            + self.failed
+            + self.cancelled
+            + self.skipped
     }
 }
 
@@ -387,6 +394,8 @@ pub enum JobStatus {
     /// Job failed after exhausting retries.
     Failed,
     Cancelled,
+    /// Job never ran because a job it depends on did not complete.
+    Skipped,
 }
 
 impl JobStatus {
@@ -397,6 +406,7 @@ impl JobStatus {
             Self::Completed => "COMPLETED",
             Self::Failed => "FAILED",
             Self::Cancelled => "CANCELLED",
+            Self::Skipped => "SKIPPED",
         }
     }
 
@@ -407,13 +417,17 @@ impl JobStatus {
             "COMPLETED" => Some(Self::Completed),
             "FAILED" => Some(Self::Failed),
             "CANCELLED" => Some(Self::Cancelled),
+            "SKIPPED" => Some(Self::Skipped),
             _ => None,
         }
     }
 
     /// Check if this is a terminal status.
     pub fn is_terminal(&self) -> bool {
-        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+        matches!(
+            self,
+            Self::Completed | Self::Failed | Self::Cancelled | Self::Skipped
+        )
     }
 }
 
@@ -1114,6 +1128,7 @@ mod tests {
         assert!(!JobStatus::Pending.is_terminal());
         assert!(!JobStatus::Processing.is_terminal());
         assert!(JobStatus::Cancelled.is_terminal());
+        assert!(JobStatus::Skipped.is_terminal());
     }
 
     #[test]
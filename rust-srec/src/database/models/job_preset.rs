@@ -16,11 +16,14 @@ pub const VALID_PROCESSORS: &[&str] = &[
     "execute",
     "audio_extract",
     "compression",
+    "archive_info",
+    "archive_verify",
     "copy_move",
     "delete",
     "metadata",
     "danmaku_factory",
     "ass_burnin",
+    "fan_out",
 ];
 
 /// Valid preset categories.
@@ -346,6 +346,9 @@ pub struct DanmuStatisticsDbModel {
     pub top_talkers: Option<String>,
     /// JSON array of word-frequency entries
     pub word_frequency: Option<String>,
+    /// JSON object with gift count, total value, top gifters, gift breakdown
+    /// and the session's danmu tags
+    pub gift_statistics: Option<String>,
 }
 
 impl DanmuStatisticsDbModel {
@@ -357,6 +360,7 @@ impl DanmuStatisticsDbModel {
             danmu_rate_timeseries: Some("[]".to_string()),
             top_talkers: Some("[]".to_string()),
             word_frequency: Some("[]".to_string()),
+            gift_statistics: None,
         }
     }
 }
@@ -25,6 +25,9 @@ pub trait JobRepository: Send + Sync {
     /// Mark a job as CANCELLED and set completed_at.
     /// Returns the number of rows updated (0 means the job was already in a terminal state).
     async fn mark_job_cancelled(&self, id: &str) -> Result<u64>;
+    /// Mark a job as SKIPPED and set error/completed_at.
+    /// Returns the number of rows updated (0 means the job was already in a terminal state).
+    async fn mark_job_skipped(&self, id: &str, reason: &str) -> Result<u64>;
     /// Reset a job for retry (PENDING, clear started/completed/error, increment retry_count).
     async fn reset_job_for_retry(&self, id: &str) -> Result<()>;
     /// Count pending jobs, optionally filtered by job types.
@@ -43,10 +46,11 @@ pub trait JobRepository: Send + Sync {
     /// Returns the claimed job, if any.
     ///
     /// This is intended for the hot dequeue path to avoid a list+update race and
-    /// to reduce DB round-trips.
+    /// to reduce DB round-trips. Jobs listed in `exclude_ids` are not claimed.
     async fn claim_next_pending_job(
         &self,
         job_types: Option<&[String]>,
+        exclude_ids: &[String],
     ) -> Result<Option<JobDbModel>>;
     /// Fetch only the `execution_info` field for a job.
     async fn get_job_execution_info(&self, id: &str) -> Result<Option<String>>;
@@ -261,17 +265,38 @@ impl JobRepository for SqlxJobRepository {
         .await
     }
 
+    async fn mark_job_skipped(&self, id: &str, reason: &str) -> Result<u64> {
+        retry_on_sqlite_busy("mark_job_skipped", || async {
+            let now = crate::database::time::now_ms();
+            let res = sqlx::query(
+                "UPDATE job SET status = ?, completed_at = ?, updated_at = ?, error = ? WHERE id = ? AND status IN (?, ?)",
+            )
+            .bind(JobStatus::Skipped.as_str())
+            .bind(now)
+            .bind(now)
+            .bind(reason)
+            .bind(id)
+            .bind(JobStatus::Pending.as_str())
+            .bind(JobStatus::Processing.as_str())
+            .execute(&self.write_pool)
+            .await?;
+            Ok(res.rows_affected())
+        })
+        .await
+    }
+
     async fn reset_job_for_retry(&self, id: &str) -> Result<()> {
         retry_on_sqlite_busy("reset_job_for_retry", || async {
             let now = crate::database::time::now_ms();
             let res = sqlx::query(
-                "UPDATE job SET status = ?, started_at = NULL, completed_at = NULL, error = NULL, retry_count = retry_count + 1, updated_at = ? WHERE id = ? AND status IN (?, ?)",
+                "UPDATE job SET status = ?, started_at = NULL, completed_at = NULL, error = NULL, retry_count = retry_count + 1, updated_at = ? WHERE id = ? AND status IN (?, ?, ?)",
             )
             .bind(JobStatus::Pending.as_str())
             .bind(now)
             .bind(id)
             .bind(JobStatus::Failed.as_str())
             .bind(JobStatus::Cancelled.as_str())
+            .bind(JobStatus::Skipped.as_str())
             .execute(&self.write_pool)
             .await?;
 
@@ -369,6 +394,7 @@ impl JobRepository for SqlxJobRepository {
     async fn claim_next_pending_job(
         &self,
         job_types: Option<&[String]>,
+        exclude_ids: &[String],
     ) -> Result<Option<JobDbModel>> {
         retry_on_sqlite_busy("claim_next_pending_job", || async {
             let now = crate::database::time::now_ms();
@@ -377,43 +403,32 @@ impl JobRepository for SqlxJobRepository {
             // then claim it with a conditional UPDATE. This reduces lock contention under load.
             //
             // We keep ordering consistent with list_jobs_filtered: priority DESC, created_at DESC.
+            let job_types = job_types.filter(|types| !types.is_empty());
+            let mut sql = String::from("SELECT id FROM job WHERE status = ?");
+            if let Some(types) = job_types {
+                let placeholders = types.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+                sql.push_str(&format!(" AND job_type IN ({})", placeholders));
+            }
+            if !exclude_ids.is_empty() {
+                let placeholders = exclude_ids
+                    .iter()
+                    .map(|_| "?")
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                sql.push_str(&format!(" AND id NOT IN ({})", placeholders));
+            }
+            sql.push_str(" ORDER BY priority DESC, created_at DESC LIMIT 1");
+
             for _ in 0..3 {
-                let next_id: Option<String> = match job_types {
-                    Some(types) if !types.is_empty() => {
-                        let placeholders = types.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-                        let sql = format!(
-                            r#"
-                            SELECT id
-                            FROM job
-                            WHERE status = ? AND job_type IN ({})
-                            ORDER BY priority DESC, created_at DESC
-                            LIMIT 1
-                            "#,
-                            placeholders
-                        );
-
-                        let mut query = sqlx::query_scalar::<_, String>(sqlx::AssertSqlSafe(sql))
-                            .bind(JobStatus::Pending.as_str());
-                        for jt in types {
-                            query = query.bind(jt);
-                        }
-                        query.fetch_optional(&self.pool).await?
-                    }
-                    _ => {
-                        sqlx::query_scalar::<_, String>(
-                            r#"
-                            SELECT id
-                            FROM job
-                            WHERE status = ?
-                            ORDER BY priority DESC, created_at DESC
-                            LIMIT 1
-                            "#,
-                        )
-                        .bind(JobStatus::Pending.as_str())
-                        .fetch_optional(&self.pool)
-                        .await?
-                    }
-                };
+                let mut query = sqlx::query_scalar::<_, String>(sqlx::AssertSqlSafe(sql.clone()))
+                    .bind(JobStatus::Pending.as_str());
+                for jt in job_types.unwrap_or_default() {
+                    query = query.bind(jt);
+                }
+                for id in exclude_ids {
+                    query = query.bind(id);
+                }
+                let next_id: Option<String> = query.fetch_optional(&self.pool).await?;
 
                 let Some(next_id) = next_id else {
                     return Ok(None);
@@ -980,14 +995,15 @@ impl JobRepository for SqlxJobRepository {
 
     async fn get_job_counts_by_status(&self) -> Result<JobCounts> {
         // Use a single query with CASE statements for efficiency
-        let row: (i64, i64, i64, i64, i64) = sqlx::query_as(
+        let row: (i64, i64, i64, i64, i64, i64) = sqlx::query_as(
             r#"
             SELECT
                 COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as pending,
                 COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as processing,
                 COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as completed,
                 COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as failed,
-                COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as cancelled
+                COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as cancelled,
+                COALESCE(SUM(CASE WHEN status = ? THEN 1 ELSE 0 END), 0) as skipped
             FROM job
             "#,
         )
@@ -996,6 +1012,7 @@ impl JobRepository for SqlxJobRepository {
         .bind(JobStatus::Completed.as_str())
         .bind(JobStatus::Failed.as_str())
         .bind(JobStatus::Cancelled.as_str())
+        .bind(JobStatus::Skipped.as_str())
         .fetch_one(&self.pool)
         .await?;
 
@@ -1005,6 +1022,7 @@ impl JobRepository for SqlxJobRepository {
             completed: row.2 as u64,
             failed: row.3 as u64,
             cancelled: row.4 as u64,
+            skipped: row.5 as u64,
         })
     }
 
@@ -1134,7 +1152,7 @@ mod stress_tests {
             let repo = repo.clone();
             let claimed_ids = claimed_ids.clone();
             join_set.spawn(async move {
-                while let Some(mut job) = repo.claim_next_pending_job(None).await.unwrap() {
+                while let Some(mut job) = repo.claim_next_pending_job(None, &[]).await.unwrap() {
                     assert!(
                         claimed_ids.insert(job.id.clone()),
                         "double-claim {}",
@@ -89,6 +89,7 @@ pub trait SessionRepository: Send + Sync {
         danmu_rate_timeseries: Option<&str>,
         top_talkers: Option<&str>,
         word_frequency: Option<&str>,
+        gift_statistics: Option<&str>,
     ) -> Result<()>;
 }
 
@@ -494,8 +495,8 @@ impl SessionRepository for SqlxSessionRepository {
         retry_on_sqlite_busy("create_danmu_statistics", || async {
             sqlx::query(
                 r#"
-                INSERT INTO danmu_statistics (id, session_id, total_danmus, danmu_rate_timeseries, top_talkers, word_frequency)
-                VALUES (?, ?, ?, ?, ?, ?)
+                INSERT INTO danmu_statistics (id, session_id, total_danmus, danmu_rate_timeseries, top_talkers, word_frequency, gift_statistics)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
                 "#,
             )
             .bind(&stats.id)
@@ -504,6 +505,7 @@ impl SessionRepository for SqlxSessionRepository {
             .bind(&stats.danmu_rate_timeseries)
             .bind(&stats.top_talkers)
             .bind(&stats.word_frequency)
+            .bind(&stats.gift_statistics)
             .execute(&self.write_pool)
             .await?;
             Ok(())
@@ -519,7 +521,8 @@ impl SessionRepository for SqlxSessionRepository {
                     total_danmus = ?,
                     danmu_rate_timeseries = ?,
                     top_talkers = ?,
-                    word_frequency = ?
+                    word_frequency = ?,
+                    gift_statistics = ?
                 WHERE id = ?
                 "#,
             )
@@ -527,6 +530,7 @@ impl SessionRepository for SqlxSessionRepository {
             .bind(&stats.danmu_rate_timeseries)
             .bind(&stats.top_talkers)
             .bind(&stats.word_frequency)
+            .bind(&stats.gift_statistics)
             .bind(&stats.id)
             .execute(&self.write_pool)
             .await?;
@@ -542,17 +546,19 @@ impl SessionRepository for SqlxSessionRepository {
         danmu_rate_timeseries: Option<&str>,
         top_talkers: Option<&str>,
         word_frequency: Option<&str>,
+        gift_statistics: Option<&str>,
     ) -> Result<()> {
         retry_on_sqlite_busy("upsert_danmu_statistics", || async {
             sqlx::query(
                 r#"
-                INSERT INTO danmu_statistics (id, session_id, total_danmus, danmu_rate_timeseries, top_talkers, word_frequency)
-                VALUES (?, ?, ?, ?, ?, ?)
+                INSERT INTO danmu_statistics (id, session_id, total_danmus, danmu_rate_timeseries, top_talkers, word_frequency, gift_statistics)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
                 ON CONFLICT(session_id) DO UPDATE SET
                     total_danmus = excluded.total_danmus,
                     danmu_rate_timeseries = excluded.danmu_rate_timeseries,
                     top_talkers = excluded.top_talkers,
-                    word_frequency = excluded.word_frequency
+                    word_frequency = excluded.word_frequency,
+                    gift_statistics = excluded.gift_statistics
                 "#,
             )
             .bind(uuid::Uuid::new_v4().to_string())
@@ -561,6 +567,7 @@ impl SessionRepository for SqlxSessionRepository {
             .bind(danmu_rate_timeseries)
             .bind(top_talkers)
             .bind(word_frequency)
+            .bind(gift_statistics)
             .execute(&self.write_pool)
             .await?;
             Ok(())
@@ -47,12 +47,23 @@ pub enum Error {
     #[error("Pipeline error: {0}")]
     PipelineError(String),
 
+    #[error("Danmu collection limit of {limit} reached")]
+    CollectionLimitReached { limit: usize },
+
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
+
     #[error("API error: {0}")]
     ApiError(String),
 
     #[error("Danmaku crate error: {0}")]
     DanmakuError(#[from] platforms_parser::danmaku::DanmakuError),
 
+    /// A danmu connection could not be established; the code tells whether
+    /// retrying can help.
+    #[error("Danmu connection failed: {0}")]
+    DanmuConnection(#[from] platforms_parser::danmaku::DanmuConnectionError),
+
     #[error("{0}")]
     Other(String),
 
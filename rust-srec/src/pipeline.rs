@@ -11,6 +11,8 @@
 
 mod coordination;
 mod dag_scheduler;
+mod dedup;
+mod job_dependencies;
 mod job_queue;
 mod manager;
 mod processors;
@@ -24,19 +26,27 @@ pub use coordination::{
     SourceType,
 };
 pub use dag_scheduler::{DagCreationResult, DagScheduler};
+pub use dedup::{DedupStore, InMemoryDedupStore};
+pub use job_dependencies::{
+    DEPENDENCY_FAILED, DependencyState, JobCompletion, JobCompletionRegistry,
+};
 pub use job_queue::{
-    Job, JobExecutionInfo, JobLogEntry, JobQueue, JobQueueConfig, JobResult, JobStats, LogLevel,
-    QueueDepthStatus,
+    BackpressureStrategy, Job, JobExecutionInfo, JobLogEntry, JobQueue, JobQueueConfig, JobResult,
+    JobStats, LogLevel, QueueDepthStatus,
 };
 pub(crate) use manager::PipelineRuntimeDependencies;
 pub use manager::{
     PipelineCreationResult, PipelineEvent, PipelineManager, PipelineManagerConfig, PipelineStats,
 };
 pub use processors::{
-    AssBurnInConfig, AssBurnInProcessor, AssMatchStrategy, CopyMoveConfig, CopyMoveOperation,
-    CopyMoveProcessor, DanmakuFactoryConfig, DanmakuFactoryProcessor, ExecuteCommandProcessor,
-    Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType, RcloneProcessor,
-    RemuxProcessor, ThumbnailProcessor,
+    AssBurnInConfig, AssBurnInProcessor, AssMatchStrategy, CompressionEntryMetadata,
+    CompressionResultMetadata, CopyMoveConfig, CopyMoveOperation, CopyMoveProcessor,
+    DanmakuFactoryConfig, DanmakuFactoryProcessor, DanmuReplayConfig, DanmuReplayProcessor,
+    DryRunReport, ExecuteCommandProcessor, FanOutConfig, FanOutProcessor, FileCompressionStat,
+    IoPriority, MergeStrategy, Processor, ProcessorCapabilities, ProcessorContext, ProcessorInput,
+    ProcessorJobConfig, ProcessorLogEntry, ProcessorOutput, ProcessorType, RcloneProcessor,
+    RemuxProcessor, ResourceLimits, StreamingOutputEvent, ThumbnailProcessor, VirtualEntry,
+    VirtualEntrySource, VirtualReader,
 };
 pub use progress::{JobProgressSnapshot, ProgressKind, ProgressReporter};
 pub use throttle::{DownloadLimitAdjuster, ThrottleConfig, ThrottleController, ThrottleEvent};
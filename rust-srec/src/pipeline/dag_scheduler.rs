@@ -600,6 +600,8 @@ impl DagScheduler {
             duration_secs: None,
             queue_wait_secs: None,
             dag_step_execution_id: Some(step_execution_id.to_string()),
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
         job_db.state = job_state_json(&job);
 
@@ -967,6 +969,10 @@ mod tests {
             unimplemented!("not needed for these tests")
         }
 
+        async fn mark_job_skipped(&self, _id: &str, _reason: &str) -> Result<u64> {
+            unimplemented!("not needed for these tests")
+        }
+
         async fn reset_job_for_retry(&self, _id: &str) -> Result<()> {
             unimplemented!("not needed for these tests")
         }
@@ -992,6 +998,7 @@ mod tests {
         async fn claim_next_pending_job(
             &self,
             _job_types: Option<&[String]>,
+            _exclude_ids: &[String],
         ) -> Result<Option<crate::database::models::JobDbModel>> {
             unimplemented!("not needed for these tests")
         }
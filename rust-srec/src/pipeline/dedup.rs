@@ -0,0 +1,70 @@
+//! Job deduplication by caller-supplied key.
+//!
+//! A job enqueued with [`Job::dedup_key`](super::Job::dedup_key) is checked
+//! against a [`DedupStore`] right before its processor runs; a key that was
+//! already seen makes the worker pass the job's inputs through untouched.
+//! A run that fails, times out or is cancelled forgets its key again, so
+//! only completed jobs keep later ones from running.
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+
+/// Record of dedup keys already processed.
+#[async_trait]
+pub trait DedupStore: Send + Sync {
+    /// Record `key` as seen.
+    ///
+    /// Returns `true` if the key was new, `false` if it had already been
+    /// recorded (same contract as [`HashSet::insert`]).
+    async fn check_and_insert(&self, key: &str) -> bool;
+
+    /// Forget `key`, so the next job with it runs again.
+    async fn remove(&self, key: &str);
+}
+
+/// Process-local [`DedupStore`]; keys are forgotten on restart.
+#[derive(Debug, Default)]
+pub struct InMemoryDedupStore {
+    seen: Mutex<HashSet<String>>,
+}
+
+impl InMemoryDedupStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DedupStore for InMemoryDedupStore {
+    async fn check_and_insert(&self, key: &str) -> bool {
+        self.seen.lock().insert(key.to_owned())
+    }
+
+    async fn remove(&self, key: &str) {
+        self.seen.lock().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store_reports_repeated_key() {
+        let store = InMemoryDedupStore::new();
+
+        assert!(store.check_and_insert("a").await);
+        assert!(!store.check_and_insert("a").await);
+        assert!(store.check_and_insert("b").await);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_accepts_removed_key_again() {
+        let store = InMemoryDedupStore::new();
+
+        assert!(store.check_and_insert("a").await);
+        store.remove("a").await;
+        assert!(store.check_and_insert("a").await);
+    }
+}
@@ -0,0 +1,157 @@
+//! Ordering between jobs via [`Job::depends_on`](super::Job::depends_on).
+//!
+//! A job listing dependencies is only dispatched once every dependency has
+//! completed successfully. The [`JobQueue`](super::JobQueue) records terminal
+//! jobs in a [`JobCompletionRegistry`] and consults it before dequeuing; the
+//! registry is a bounded cache, so dependencies it no longer tracks are
+//! resolved from the stored job status instead.
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Skip reason recorded for a job whose dependency did not succeed.
+pub const DEPENDENCY_FAILED: &str = "dependency_failed";
+
+/// Number of terminal jobs remembered before the oldest are evicted.
+const MAX_TRACKED_COMPLETIONS: usize = 4096;
+
+/// How a job ended, as far as its dependents are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobCompletion {
+    /// The job completed successfully.
+    Succeeded,
+    /// The job failed or was cancelled.
+    Failed,
+    /// The job never ran because one of its own dependencies did not succeed.
+    Skipped,
+}
+
+/// Dispatch state of a job with respect to its dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyState {
+    /// All dependencies succeeded (or there are none).
+    Ready,
+    /// At least one dependency has not finished yet.
+    Waiting,
+    /// The named dependency failed or was skipped.
+    Failed { job_id: String },
+}
+
+/// Terminal outcomes of recently finished jobs, keyed by job ID.
+#[derive(Debug, Clone)]
+pub struct JobCompletionRegistry {
+    completions: Arc<DashMap<String, JobCompletion>>,
+    /// Recording order, used to evict the oldest entries past `capacity`.
+    order: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl Default for JobCompletionRegistry {
+    fn default() -> Self {
+        Self::with_capacity(MAX_TRACKED_COMPLETIONS)
+    }
+}
+
+impl JobCompletionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry remembering at most `capacity` jobs.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            completions: Arc::new(DashMap::new()),
+            order: Arc::new(Mutex::new(VecDeque::new())),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Record the terminal outcome of `job_id`, evicting the oldest entries
+    /// once the registry is full.
+    pub fn record(&self, job_id: impl Into<String>, completion: JobCompletion) {
+        let job_id = job_id.into();
+        let mut order = self.order.lock();
+        self.completions.insert(job_id.clone(), completion);
+        order.push_back(job_id);
+        while order.len() > self.capacity {
+            if let Some(evicted) = order.pop_front() {
+                self.completions.remove(&evicted);
+            }
+        }
+    }
+
+    /// Drop the recorded outcome of `job_id`, e.g. when it is retried.
+    pub fn forget(&self, job_id: &str) {
+        self.completions.remove(job_id);
+    }
+
+    /// Terminal outcome of `job_id`, if it finished recently.
+    pub fn get(&self, job_id: &str) -> Option<JobCompletion> {
+        self.completions.get(job_id).map(|entry| *entry)
+    }
+
+    /// Dependency state from recorded outcomes only; unknown jobs count as
+    /// still running.
+    pub fn check(&self, depends_on: &[String]) -> DependencyState {
+        let mut waiting = false;
+        for job_id in depends_on {
+            match self.get(job_id) {
+                Some(JobCompletion::Succeeded) => {}
+                Some(JobCompletion::Failed | JobCompletion::Skipped) => {
+                    return DependencyState::Failed {
+                        job_id: job_id.clone(),
+                    };
+                }
+                None => waiting = true,
+            }
+        }
+        if waiting {
+            DependencyState::Waiting
+        } else {
+            DependencyState::Ready
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_waiting_ready_and_failed() {
+        let registry = JobCompletionRegistry::new();
+        let deps = vec!["a".to_string(), "b".to_string()];
+
+        assert_eq!(registry.check(&[]), DependencyState::Ready);
+        assert_eq!(registry.check(&deps), DependencyState::Waiting);
+
+        registry.record("a", JobCompletion::Succeeded);
+        assert_eq!(registry.check(&deps), DependencyState::Waiting);
+
+        registry.record("b", JobCompletion::Succeeded);
+        assert_eq!(registry.check(&deps), DependencyState::Ready);
+
+        registry.record("b", JobCompletion::Failed);
+        assert_eq!(
+            registry.check(&deps),
+            DependencyState::Failed {
+                job_id: "b".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_record_evicts_oldest_beyond_capacity() {
+        let registry = JobCompletionRegistry::with_capacity(2);
+
+        registry.record("a", JobCompletion::Succeeded);
+        registry.record("b", JobCompletion::Failed);
+        registry.record("c", JobCompletion::Skipped);
+
+        assert_eq!(registry.get("a"), None);
+        assert_eq!(registry.get("b"), Some(JobCompletion::Failed));
+        assert_eq!(registry.get("c"), Some(JobCompletion::Skipped));
+    }
+}
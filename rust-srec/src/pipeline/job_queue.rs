@@ -1,7 +1,7 @@
 //! Database-backed job queue implementation.
 
 use chrono::{DateTime, Utc};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -9,10 +9,14 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::sync::Notify;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+use super::job_dependencies::{
+    DEPENDENCY_FAILED, DependencyState, JobCompletion, JobCompletionRegistry,
+};
 use super::progress::{JobProgressSnapshot, JobProgressUpdate, ProgressReporter};
 use crate::database::models::JobExecutionProgressDbModel;
 use crate::database::models::job::LogEntry as DbLogEntry;
@@ -110,6 +114,17 @@ pub struct JobQueueConfig {
     pub critical_threshold: usize,
     /// Poll interval in milliseconds.
     pub poll_interval_ms: u64,
+    /// Pending or processing jobs accepted by [`JobQueue::enqueue`] before the
+    /// backpressure strategy applies (at least 1).
+    #[serde(default = "default_max_pending_jobs")]
+    pub max_pending_jobs: usize,
+    /// What [`JobQueue::enqueue`] does while `max_pending_jobs` jobs are queued.
+    #[serde(default)]
+    pub backpressure_strategy: BackpressureStrategy,
+}
+
+fn default_max_pending_jobs() -> usize {
+    1000
 }
 
 impl Default for JobQueueConfig {
@@ -118,10 +133,25 @@ impl Default for JobQueueConfig {
             warning_threshold: 100,
             critical_threshold: 500,
             poll_interval_ms: 100,
+            max_pending_jobs: default_max_pending_jobs(),
+            backpressure_strategy: BackpressureStrategy::default(),
         }
     }
 }
 
+/// What enqueueing a job does while the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressureStrategy {
+    /// Fail with [`Error::ResourceExhausted`] right away.
+    #[default]
+    Reject,
+    /// Wait until a queued job finishes, is cancelled or deleted.
+    Block,
+    /// Wait like `Block`, failing with [`Error::ResourceExhausted`] after the timeout.
+    BlockWithTimeout(Duration),
+}
+
 /// Status of queue depth.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QueueDepthStatus {
@@ -159,6 +189,9 @@ pub struct JobLogEntry {
     pub level: LogLevel,
     /// Log message.
     pub message: String,
+    /// Structured context attached with [`JobLogEntry::with_field`]; null when empty.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub fields: serde_json::Value,
 }
 
 impl JobLogEntry {
@@ -168,7 +201,23 @@ impl JobLogEntry {
             timestamp: Utc::now(),
             level,
             message: message.into(),
+            fields: serde_json::Value::Null,
+        }
+    }
+
+    /// Attach a structured field, replacing any previous value for `key`.
+    pub fn with_field(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        if !self.fields.is_object() {
+            self.fields = serde_json::Value::Object(serde_json::Map::new());
+        }
+        if let serde_json::Value::Object(fields) = &mut self.fields {
+            fields.insert(key.into(), value.into());
         }
+        self
     }
 
     /// Create an info log entry.
@@ -364,6 +413,11 @@ pub struct Job {
     pub queue_wait_secs: Option<f64>,
     /// DAG step execution ID (if this job is part of a DAG pipeline).
     pub dag_step_execution_id: Option<String>,
+    /// Key identifying duplicate jobs; a job whose key was already seen by
+    /// the worker pool's dedup store is skipped.
+    pub dedup_key: Option<String>,
+    /// IDs of jobs that must complete successfully before this job is dequeued.
+    pub depends_on: Vec<String>,
 }
 
 impl Job {
@@ -399,6 +453,8 @@ impl Job {
             duration_secs: None,
             queue_wait_secs: None,
             dag_step_execution_id: None,
+            dedup_key: None,
+            depends_on: Vec::new(),
         }
     }
 
@@ -435,6 +491,8 @@ impl Job {
             duration_secs: None,
             queue_wait_secs: None,
             dag_step_execution_id: None,
+            dedup_key: None,
+            depends_on: Vec::new(),
         }
     }
 
@@ -486,6 +544,18 @@ impl Job {
         self
     }
 
+    /// Set the dedup key.
+    pub fn with_dedup_key(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = Some(key.into());
+        self
+    }
+
+    /// Set the IDs of jobs that must complete successfully first.
+    pub fn with_depends_on(mut self, job_ids: Vec<String>) -> Self {
+        self.depends_on = job_ids;
+        self
+    }
+
     /// Check if this job is part of a DAG pipeline.
     pub fn is_dag_job(&self) -> bool {
         self.dag_step_execution_id.is_some()
@@ -513,6 +583,8 @@ pub struct JobQueue {
     depth: AtomicUsize,
     /// Notify when new jobs are added.
     notify: Arc<Notify>,
+    /// Notify when pending jobs leave the queue, waking blocked enqueues.
+    slot_freed: Notify,
     /// Job repository for database persistence.
     job_repository: Option<Arc<dyn JobRepository>>,
     /// Session repository for persisting media outputs (e.g., thumbnails).
@@ -529,6 +601,11 @@ pub struct JobQueue {
     progress_tx: tokio::sync::mpsc::Sender<JobProgressUpdate>,
     /// Cursor used to dedupe/append logs into `job_execution_logs`.
     persisted_log_cursor: DashMap<String, PersistedLogCursor>,
+    /// Terminal results of jobs, consulted for [`Job::depends_on`].
+    completion_registry: JobCompletionRegistry,
+    /// Pending jobs whose dependencies have not finished; not dequeued until
+    /// another job reaches a terminal state.
+    waiting_jobs: DashSet<String>,
 }
 
 impl JobQueue {
@@ -553,6 +630,7 @@ impl JobQueue {
             config,
             depth: AtomicUsize::new(0),
             notify: Arc::new(Notify::new()),
+            slot_freed: Notify::new(),
             job_repository: None,
             session_repo: std::sync::OnceLock::new(),
             streamer_repo: std::sync::OnceLock::new(),
@@ -561,6 +639,8 @@ impl JobQueue {
             progress_cache,
             progress_tx,
             persisted_log_cursor: DashMap::new(),
+            completion_registry: JobCompletionRegistry::new(),
+            waiting_jobs: DashSet::new(),
         }
     }
 
@@ -580,6 +660,7 @@ impl JobQueue {
             config,
             depth: AtomicUsize::new(0),
             notify: Arc::new(Notify::new()),
+            slot_freed: Notify::new(),
             job_repository: Some(repository),
             session_repo: std::sync::OnceLock::new(),
             streamer_repo: std::sync::OnceLock::new(),
@@ -588,9 +669,16 @@ impl JobQueue {
             progress_cache,
             progress_tx,
             persisted_log_cursor: DashMap::new(),
+            completion_registry: JobCompletionRegistry::new(),
+            waiting_jobs: DashSet::new(),
         }
     }
 
+    /// Registry of terminal job results used to resolve [`Job::depends_on`].
+    pub fn completion_registry(&self) -> JobCompletionRegistry {
+        self.completion_registry.clone()
+    }
+
     /// Set the session repository for persisting media outputs (e.g., thumbnails).
     /// This can only be called once.
     pub(crate) fn set_session_repo(&self, repo: Arc<dyn SessionRepository>) {
@@ -867,21 +955,28 @@ impl JobQueue {
     }
 
     /// Enqueue a new job.
+    ///
+    /// While [`JobQueueConfig::max_pending_jobs`] jobs are pending or
+    /// processing, the job is rejected or waits for a free slot according to
+    /// [`JobQueueConfig::backpressure_strategy`].
     pub async fn enqueue(&self, job: Job) -> Result<String> {
         let job_id = job.id.clone();
         let job_type = job.job_type.clone();
 
+        self.reserve_slot().await?;
+
         // Persist to database if repository is available
         if let Some(repo) = &self.job_repository {
             let db_model = job_to_db_model(&job);
-            repo.create_job(&db_model).await?;
+            if let Err(e) = repo.create_job(&db_model).await {
+                self.decrement_depth(1);
+                return Err(e);
+            }
         }
 
         // Add to in-memory cache
         self.jobs_cache.insert(job_id.clone(), job);
 
-        self.depth.fetch_add(1, Ordering::SeqCst);
-
         info!("Enqueued job {} of type {}", job_id, job_type);
 
         // Notify waiting workers
@@ -893,6 +988,9 @@ impl JobQueue {
     /// Enqueue an existing job (already persisted to database).
     /// This adds the job to the in-memory cache and notifies workers.
     /// Used by DagScheduler when creating jobs for DAG steps.
+    ///
+    /// Counts as pending but is never held back by `max_pending_jobs`, so a
+    /// started pipeline is not left with steps that were never enqueued.
     pub async fn enqueue_existing(&self, job: Job) -> Result<String> {
         let job_id = job.id.clone();
         let job_type = job.job_type.clone();
@@ -917,8 +1015,22 @@ impl JobQueue {
 
         // Try to get from database if repository is available
         if let Some(repo) = &self.job_repository {
-            if let Some(db_job) = repo.claim_next_pending_job(job_types).await? {
+            loop {
+                let waiting: Vec<String> = self.waiting_jobs.iter().map(|id| id.clone()).collect();
+                let Some(db_job) = repo.claim_next_pending_job(job_types, &waiting).await? else {
+                    break;
+                };
                 let mut job = db_model_to_job(&db_job);
+                if !self.dependencies_ready(&job.id, &job.depends_on).await? {
+                    if self.waiting_jobs.contains(&job.id) {
+                        let mut released = db_job;
+                        released.status = JobStatus::Pending.as_str().to_string();
+                        released.started_at = None;
+                        repo.update_job_if_status(&released, JobStatus::Processing)
+                            .await?;
+                    }
+                    continue;
+                }
                 job.status = JobStatus::Processing;
                 if job.started_at.is_none() {
                     job.started_at = Some(Utc::now());
@@ -939,52 +1051,137 @@ impl JobQueue {
             }
         } else {
             // Fallback to in-memory cache
-            let mut selected: Option<(i32, chrono::DateTime<Utc>, String)> = None;
-            for entry in self.jobs_cache.iter() {
-                let job = entry.value();
-                if job.status != JobStatus::Pending {
-                    continue;
+            loop {
+                let mut selected: Option<(i32, chrono::DateTime<Utc>, String)> = None;
+                for entry in self.jobs_cache.iter() {
+                    let job = entry.value();
+                    if job.status != JobStatus::Pending || self.waiting_jobs.contains(&job.id) {
+                        continue;
+                    }
+                    if let Some(types) = job_types
+                        && !types.iter().any(|t| t == &job.job_type)
+                    {
+                        continue;
+                    }
+
+                    // Match DB ordering: priority DESC, created_at DESC, id ASC for stability.
+                    let candidate = (job.priority, job.created_at, job.id.clone());
+                    match &selected {
+                        None => selected = Some(candidate),
+                        Some((best_prio, best_created, best_id)) => {
+                            if candidate.0 > *best_prio
+                                || (candidate.0 == *best_prio && candidate.1 > *best_created)
+                                || (candidate.0 == *best_prio
+                                    && candidate.1 == *best_created
+                                    && candidate.2 < *best_id)
+                            {
+                                selected = Some(candidate);
+                            }
+                        }
+                    }
                 }
-                if let Some(types) = job_types
-                    && !types.iter().any(|t| t == &job.job_type)
-                {
+
+                let Some((_, _, job_id)) = selected else {
+                    break;
+                };
+                let depends_on = self
+                    .jobs_cache
+                    .get(&job_id)
+                    .map(|job| job.depends_on.clone())
+                    .unwrap_or_default();
+                if !self.dependencies_ready(&job_id, &depends_on).await? {
                     continue;
                 }
 
-                // Match DB ordering: priority DESC, created_at DESC, id ASC for stability.
-                let candidate = (job.priority, job.created_at, job.id.clone());
-                match &selected {
-                    None => selected = Some(candidate),
-                    Some((best_prio, best_created, best_id)) => {
-                        if candidate.0 > *best_prio
-                            || (candidate.0 == *best_prio && candidate.1 > *best_created)
-                            || (candidate.0 == *best_prio
-                                && candidate.1 == *best_created
-                                && candidate.2 < *best_id)
-                        {
-                            selected = Some(candidate);
-                        }
+                if let Some(mut job_ref) = self.jobs_cache.get_mut(&job_id) {
+                    if job_ref.status != JobStatus::Pending {
+                        return Ok(None);
                     }
+                    job_ref.status = JobStatus::Processing;
+                    job_ref.started_at = Some(Utc::now());
+                    let job = job_ref.clone();
+                    drop(job_ref);
+
+                    self.cancellation_tokens.entry(job.id.clone()).or_default();
+                    return Ok(Some(job));
                 }
+                break;
             }
+        }
 
-            if let Some((_, _, job_id)) = selected
-                && let Some(mut job_ref) = self.jobs_cache.get_mut(&job_id)
-            {
-                if job_ref.status != JobStatus::Pending {
-                    return Ok(None);
-                }
-                job_ref.status = JobStatus::Processing;
-                job_ref.started_at = Some(Utc::now());
-                let job = job_ref.clone();
-                drop(job_ref);
+        Ok(None)
+    }
 
-                self.cancellation_tokens.entry(job.id.clone()).or_default();
-                return Ok(Some(job));
+    /// Check a job's dependencies before dispatching it.
+    ///
+    /// Returns `false` when the job must not run now: it is either parked in
+    /// the waiting set until another job finishes, or skipped with
+    /// [`DEPENDENCY_FAILED`] because a dependency did not succeed.
+    async fn dependencies_ready(&self, job_id: &str, depends_on: &[String]) -> Result<bool> {
+        if depends_on.is_empty() {
+            return Ok(true);
+        }
+        match self.dependency_state(depends_on).await? {
+            DependencyState::Ready => Ok(true),
+            DependencyState::Waiting => {
+                tracing::debug!(job_id, "Job is waiting for its dependencies");
+                self.waiting_jobs.insert(job_id.to_string());
+                Ok(false)
+            }
+            DependencyState::Failed { job_id: failed } => {
+                let reason = format!(
+                    "{}: job {} did not complete successfully",
+                    DEPENDENCY_FAILED, failed
+                );
+                self.skip(job_id, &reason).await?;
+                Ok(false)
             }
         }
+    }
 
-        Ok(None)
+    /// Resolve the state of `depends_on`, falling back to the stored job status
+    /// for dependencies that finished before this queue started.
+    async fn dependency_state(&self, depends_on: &[String]) -> Result<DependencyState> {
+        let mut waiting = false;
+        for job_id in depends_on {
+            let state = match self.completion_registry.check(std::slice::from_ref(job_id)) {
+                DependencyState::Waiting => match self.get_job(job_id).await? {
+                    Some(job) => match job.status {
+                        JobStatus::Completed => DependencyState::Ready,
+                        JobStatus::Pending | JobStatus::Processing => DependencyState::Waiting,
+                        JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped => {
+                            DependencyState::Failed {
+                                job_id: job_id.clone(),
+                            }
+                        }
+                    },
+                    // A dependency that does not exist can never complete.
+                    None => DependencyState::Failed {
+                        job_id: job_id.clone(),
+                    },
+                },
+                state => state,
+            };
+            match state {
+                DependencyState::Ready => {}
+                DependencyState::Waiting => waiting = true,
+                failed @ DependencyState::Failed { .. } => return Ok(failed),
+            }
+        }
+        Ok(if waiting {
+            DependencyState::Waiting
+        } else {
+            DependencyState::Ready
+        })
+    }
+
+    /// Let waiting jobs be reconsidered after a job reached a terminal state.
+    fn release_waiting_jobs(&self) {
+        if self.waiting_jobs.is_empty() {
+            return;
+        }
+        self.waiting_jobs.clear();
+        self.notify.notify_one();
     }
 
     /// Count pending jobs, optionally filtered by job types.
@@ -1141,11 +1338,51 @@ impl JobQueue {
 
         if transitioned {
             self.decrement_depth(1);
+            self.completion_registry
+                .record(job_id, JobCompletion::Succeeded);
+            self.release_waiting_jobs();
             info!("Job {} completed in {:.2}s", job_id, result.duration_secs);
         }
 
         Ok(())
     }
+
+    /// Mark a job as skipped without running it.
+    ///
+    /// Unlike [`Self::fail`], this records no failure log: the job itself never
+    /// ran, and its dependents are skipped in turn.
+    async fn skip(&self, job_id: &str, reason: &str) -> Result<()> {
+        let mut transitioned = false;
+
+        if let Some(repo) = &self.job_repository {
+            if repo.mark_job_skipped(job_id, reason).await? == 0 {
+                self.finalize_cancelled_job(job_id);
+                return Ok(());
+            }
+            transitioned = true;
+        }
+
+        if let Some(mut job) = self.jobs_cache.get_mut(job_id)
+            && matches!(job.status, JobStatus::Pending | JobStatus::Processing)
+        {
+            transitioned = true;
+            job.status = JobStatus::Skipped;
+            job.completed_at = Some(Utc::now());
+            job.error = Some(reason.to_string());
+        }
+
+        self.finalize_cancelled_job(job_id);
+
+        if transitioned {
+            self.decrement_depth(1);
+            self.completion_registry
+                .record(job_id, JobCompletion::Skipped);
+            self.release_waiting_jobs();
+            info!("Job {} skipped: {}", job_id, reason);
+        }
+        Ok(())
+    }
+
     /// Mark a job as failed.
     pub async fn fail(&self, job_id: &str, error: &str) -> Result<()> {
         self.fail_internal(job_id, error, None, None, None, false)
@@ -1226,6 +1463,7 @@ impl JobQueue {
                             timestamp,
                             level,
                             message: row.message.unwrap_or(row.entry),
+                            fields: serde_json::Value::Null,
                         };
                     }
 
@@ -1245,6 +1483,7 @@ impl JobQueue {
                             timestamp,
                             level,
                             message: entry.message,
+                            fields: serde_json::Value::Null,
                         };
                     }
 
@@ -1252,6 +1491,7 @@ impl JobQueue {
                         timestamp,
                         level: LogLevel::Info,
                         message: row.entry,
+                        fields: serde_json::Value::Null,
                     }
                 })
                 .collect();
@@ -1355,11 +1595,12 @@ impl JobQueue {
         Ok(jobs)
     }
 
-    /// Retry a failed or cancelled job.
+    /// Retry a failed, cancelled or skipped job.
     /// Returns error if job is not in a retryable terminal status.
     pub async fn retry_job(&self, id: &str) -> Result<Job> {
         if let Some(repo) = &self.job_repository {
             repo.reset_job_for_retry(id).await?;
+            self.completion_registry.forget(id);
 
             let _ = self.cancellation_tokens.remove(id);
             let _ = self.persisted_log_cursor.remove(id);
@@ -1379,7 +1620,10 @@ impl JobQueue {
             .get_mut(id)
             .ok_or_else(|| Error::not_found("Job", id))?;
 
-        if cached_job.status != JobStatus::Failed && cached_job.status != JobStatus::Cancelled {
+        if !matches!(
+            cached_job.status,
+            JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped
+        ) {
             return Err(Error::InvalidStateTransition {
                 from: cached_job.status.as_str().to_string(),
                 to: "PENDING".to_string(),
@@ -1393,6 +1637,7 @@ impl JobQueue {
         cached_job.retry_count += 1;
         let updated_job = cached_job.clone();
         drop(cached_job);
+        self.completion_registry.forget(id);
 
         self.depth.fetch_add(1, Ordering::SeqCst);
         self.notify.notify_one();
@@ -1402,7 +1647,7 @@ impl JobQueue {
     }
 
     /// Cancel a job.
-    /// Returns the cancelled job, or error for Completed/Failed jobs.
+    /// Returns the cancelled job, or error for Completed/Failed/Skipped jobs.
     pub async fn cancel_job(&self, id: &str) -> Result<Job> {
         // Get the job
         let job = self
@@ -1411,7 +1656,10 @@ impl JobQueue {
             .ok_or_else(|| Error::not_found("Job", id))?;
 
         // Validate job is not in terminal status
-        if job.status == JobStatus::Completed || job.status == JobStatus::Failed {
+        if matches!(
+            job.status,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Skipped
+        ) {
             return Err(Error::InvalidStateTransition {
                 from: job.status.as_str().to_string(),
                 to: "CANCELLED".to_string(),
@@ -1467,6 +1715,8 @@ impl JobQueue {
 
         if updated > 0 {
             self.decrement_depth(1);
+            self.completion_registry.record(id, JobCompletion::Failed);
+            self.release_waiting_jobs();
         }
 
         info!("Job {} cancelled", id);
@@ -1696,6 +1946,7 @@ impl JobQueue {
                 completed: counts.completed,
                 failed: counts.failed,
                 cancelled: counts.cancelled,
+                skipped: counts.skipped,
                 avg_processing_time_secs: avg_processing_time,
             });
         }
@@ -1710,6 +1961,7 @@ impl JobQueue {
                 JobStatus::Completed => stats.completed += 1,
                 JobStatus::Failed => stats.failed += 1,
                 JobStatus::Cancelled => stats.cancelled += 1,
+                JobStatus::Skipped => stats.skipped += 1,
             }
         }
 
@@ -1920,6 +2172,17 @@ impl JobQueue {
         self.depth.load(Ordering::SeqCst)
     }
 
+    /// Number of pending and processing jobs, which [`capacity`](Self::capacity)
+    /// applies to.
+    pub fn pending_count(&self) -> usize {
+        self.depth()
+    }
+
+    /// Most pending jobs [`enqueue`](Self::enqueue) accepts without backpressure.
+    pub fn capacity(&self) -> usize {
+        self.config.max_pending_jobs.max(1)
+    }
+
     fn decrement_depth(&self, by: usize) {
         if by == 0 {
             return;
@@ -1929,6 +2192,58 @@ impl JobQueue {
             .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
                 Some(current.saturating_sub(by))
             });
+        self.slot_freed.notify_waiters();
+    }
+
+    /// Count one more pending job if the queue has room for it.
+    fn try_reserve_slot(&self) -> bool {
+        let capacity = self.capacity();
+        self.depth
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (current < capacity).then_some(current + 1)
+            })
+            .is_ok()
+    }
+
+    /// Count one more pending job, applying the backpressure strategy while
+    /// the queue is full.
+    async fn reserve_slot(&self) -> Result<()> {
+        let wait_for_slot = async {
+            loop {
+                // Registered before checking, so a slot freed in between still wakes us.
+                let freed = self.slot_freed.notified();
+                tokio::pin!(freed);
+                freed.as_mut().enable();
+                if self.try_reserve_slot() {
+                    return;
+                }
+                freed.await;
+            }
+        };
+        let full = || {
+            Error::ResourceExhausted(format!(
+                "Job queue is full ({} pending jobs)",
+                self.capacity()
+            ))
+        };
+        match self.config.backpressure_strategy {
+            BackpressureStrategy::Reject => {
+                if self.try_reserve_slot() {
+                    Ok(())
+                } else {
+                    Err(full())
+                }
+            }
+            BackpressureStrategy::Block => {
+                wait_for_slot.await;
+                Ok(())
+            }
+            BackpressureStrategy::BlockWithTimeout(timeout) => {
+                tokio::time::timeout(timeout, wait_for_slot)
+                    .await
+                    .map_err(|_| full())
+            }
+        }
     }
 
     /// Get the queue depth status.
@@ -2050,6 +2365,9 @@ impl JobQueue {
 
         if transitioned {
             self.decrement_depth(1);
+            self.completion_registry
+                .record(job_id, JobCompletion::Failed);
+            self.release_waiting_jobs();
         }
         Ok(())
     }
@@ -2165,6 +2483,8 @@ pub struct JobStats {
     /// Number of failed jobs.
     pub failed: u64,
     pub cancelled: u64,
+    /// Number of jobs skipped because a dependency failed.
+    pub skipped: u64,
     /// Average processing time in seconds.
     pub avg_processing_time_secs: Option<f64>,
 }
@@ -2188,14 +2508,14 @@ impl JobStateMeta {
     }
 }
 
-/// Serialize a job's placeholder metadata into the `state` column JSON.
+/// Serialize a job's placeholder metadata and dedup key into the `state` column JSON.
 ///
 /// Single source of the state shape, together with its inverse
 /// [`parse_job_state`]: used by [`job_to_db_model`] at persist time, by
 /// [`JobQueue::resolve_job_metadata`] when writing back-filled values, and
 /// by `DagScheduler::create_step_job`.
 pub(crate) fn job_state_json(job: &Job) -> String {
-    if job.streamer_name.is_some()
+    let mut state = if job.streamer_name.is_some()
         || job.session_title.is_some()
         || job.platform.is_some()
         || job.session_start.is_some()
@@ -2206,10 +2526,34 @@ pub(crate) fn job_state_json(job: &Job) -> String {
             "platform": job.platform.clone(),
             "session_start_ms": job.session_start.as_ref().map(|dt| dt.timestamp_millis()),
         })
-        .to_string()
     } else {
-        "{}".to_string()
+        serde_json::json!({})
+    };
+    if let Some(key) = &job.dedup_key {
+        state["dedup_key"] = serde_json::Value::String(key.clone());
+    }
+    if !job.depends_on.is_empty() {
+        state["depends_on"] = serde_json::json!(job.depends_on);
     }
+    state.to_string()
+}
+
+/// Read the dependency list persisted by [`job_state_json`], if any.
+fn parse_job_depends_on(state: &str) -> Vec<String> {
+    serde_json::from_str::<serde_json::Value>(state)
+        .ok()
+        .and_then(|value| value.get("depends_on").cloned())
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Read the dedup key persisted by [`job_state_json`], if any.
+fn parse_job_dedup_key(state: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(state)
+        .ok()?
+        .get("dedup_key")?
+        .as_str()
+        .map(ToString::to_string)
 }
 
 /// Parse the placeholder metadata out of a job `state` JSON string.
@@ -2376,6 +2720,8 @@ fn db_model_to_job(db_job: &JobDbModel) -> Job {
         duration_secs: db_job.duration_secs,
         queue_wait_secs: db_job.queue_wait_secs,
         dag_step_execution_id: db_job.dag_step_execution_id.clone(),
+        dedup_key: parse_job_dedup_key(&db_job.state),
+        depends_on: parse_job_depends_on(&db_job.state),
         streamer_name,
         session_title,
         platform,
@@ -2400,6 +2746,30 @@ mod tests {
         assert_eq!(config.critical_threshold, 500);
     }
 
+    #[test]
+    fn test_log_entry_with_field_roundtrip() {
+        let entry = JobLogEntry::warn("disk low")
+            .with_field("input", "/rec/a.flv")
+            .with_field("free_bytes", 42u64);
+        assert_eq!(entry.fields["input"], "/rec/a.flv");
+        assert_eq!(entry.fields["free_bytes"], 42);
+
+        let restored: JobLogEntry =
+            serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+        assert_eq!(restored.fields, entry.fields);
+
+        // Entries without fields keep the old wire format.
+        let plain = serde_json::to_value(JobLogEntry::info("ok")).unwrap();
+        assert!(plain.get("fields").is_none());
+        let legacy: JobLogEntry = serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-01-01T00:00:00Z",
+            "level": "info",
+            "message": "ok",
+        }))
+        .unwrap();
+        assert!(legacy.fields.is_null());
+    }
+
     #[test]
     fn test_job_creation() {
         let job = Job::new(
@@ -2438,6 +2808,165 @@ mod tests {
         assert_eq!(restored.session_title.as_deref(), Some("SessionTitle"));
     }
 
+    #[test]
+    fn test_job_db_state_roundtrip_preserves_dedup_key() {
+        let job = Job::new("copy_move", vec![], vec![], "streamer-1", "session-1")
+            .with_dedup_key("upload:/input.flv");
+
+        let restored = db_model_to_job(&job_to_db_model(&job));
+        assert_eq!(restored.dedup_key.as_deref(), Some("upload:/input.flv"));
+
+        let plain = Job::new("copy_move", vec![], vec![], "streamer-1", "session-1");
+        assert_eq!(job_to_db_model(&plain).state, "{}");
+    }
+
+    #[test]
+    fn test_job_db_state_roundtrip_preserves_depends_on() {
+        let job = Job::new("copy_move", vec![], vec![], "streamer-1", "session-1")
+            .with_depends_on(vec!["job-a".to_string(), "job-b".to_string()]);
+
+        let restored = db_model_to_job(&job_to_db_model(&job));
+        assert_eq!(restored.depends_on, vec!["job-a", "job-b"]);
+    }
+
+    fn chain_job(depends_on: Option<&Job>) -> Job {
+        let job = Job::new("remux", vec![], vec![], "streamer-1", "session-1");
+        match depends_on {
+            Some(parent) => job.with_depends_on(vec![parent.id.clone()]),
+            None => job,
+        }
+    }
+
+    fn empty_result() -> JobResult {
+        JobResult {
+            outputs: vec![],
+            duration_secs: 0.0,
+            metadata: None,
+            logs: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_holds_jobs_until_dependencies_complete() {
+        let queue = JobQueue::new();
+        let a = chain_job(None);
+        let b = chain_job(Some(&a));
+        let c = chain_job(Some(&b));
+        // Enqueue dependents first so they would win the created_at ordering.
+        queue.enqueue(a.clone()).await.unwrap();
+        queue.enqueue(b.clone()).await.unwrap();
+        queue.enqueue(c.clone()).await.unwrap();
+
+        for (expected, dependent) in [(&a, &b), (&b, &c)] {
+            let job = queue.dequeue(None).await.unwrap().unwrap();
+            assert_eq!(job.id, expected.id);
+            assert!(queue.dequeue(None).await.unwrap().is_none());
+            let waiting = queue.get_job(&dependent.id).await.unwrap().unwrap();
+            assert_eq!(waiting.status, JobStatus::Pending);
+
+            queue.complete(&job.id, empty_result()).await.unwrap();
+        }
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(job.id, c.id);
+    }
+
+    #[tokio::test]
+    async fn test_db_dequeue_skips_waiting_jobs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("job_queue_deps.db");
+        let db_url = format!("sqlite:{}?mode=rwc", db_path.to_string_lossy());
+        let pool = crate::database::init_pool(&db_url).await.unwrap();
+        crate::database::run_migrations(&pool).await.unwrap();
+        let job_repo: Arc<dyn JobRepository> = Arc::new(
+            crate::database::repositories::job::SqlxJobRepository::new(pool.clone(), pool.clone()),
+        );
+        let queue = JobQueue::with_repository(JobQueueConfig::default(), job_repo.clone());
+
+        let a = chain_job(None);
+        let mut b = chain_job(Some(&a));
+        // The dependent is newer, so the claim query would pick it first.
+        b.created_at = a.created_at + chrono::Duration::seconds(1);
+        queue.enqueue(a.clone()).await.unwrap();
+        queue.enqueue(b.clone()).await.unwrap();
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(job.id, a.id);
+        let waiting = job_repo.get_job(&b.id).await.unwrap();
+        assert_eq!(waiting.get_status(), Some(JobStatus::Pending));
+        assert!(queue.dequeue(None).await.unwrap().is_none());
+
+        queue.complete(&a.id, empty_result()).await.unwrap();
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(job.id, b.id);
+    }
+
+    #[tokio::test]
+    async fn test_failed_dependency_skips_dependents() {
+        let queue = JobQueue::new();
+        let a = chain_job(None);
+        let b = chain_job(Some(&a));
+        let c = chain_job(Some(&b));
+        queue.enqueue(a.clone()).await.unwrap();
+        queue.enqueue(b.clone()).await.unwrap();
+        queue.enqueue(c.clone()).await.unwrap();
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(job.id, a.id);
+        queue.fail(&a.id, "boom").await.unwrap();
+
+        assert!(queue.dequeue(None).await.unwrap().is_none());
+        for dependent in [&b, &c] {
+            let skipped = queue.get_job(&dependent.id).await.unwrap().unwrap();
+            assert_eq!(skipped.status, JobStatus::Skipped);
+            assert!(skipped.error.unwrap().starts_with(DEPENDENCY_FAILED));
+            assert_eq!(
+                queue.completion_registry().get(&dependent.id),
+                Some(JobCompletion::Skipped)
+            );
+        }
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_db_failed_dependency_persists_skipped_dependents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("job_queue_skipped.db");
+        let db_url = format!("sqlite:{}?mode=rwc", db_path.to_string_lossy());
+        let pool = crate::database::init_pool(&db_url).await.unwrap();
+        crate::database::run_migrations(&pool).await.unwrap();
+        let job_repo: Arc<dyn JobRepository> = Arc::new(
+            crate::database::repositories::job::SqlxJobRepository::new(pool.clone(), pool.clone()),
+        );
+        let queue = JobQueue::with_repository(JobQueueConfig::default(), job_repo.clone());
+
+        let a = chain_job(None);
+        let b = chain_job(Some(&a));
+        let c = chain_job(Some(&b));
+        queue.enqueue(a.clone()).await.unwrap();
+        queue.enqueue(b.clone()).await.unwrap();
+        queue.enqueue(c.clone()).await.unwrap();
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(job.id, a.id);
+        queue.fail(&a.id, "boom").await.unwrap();
+
+        assert!(queue.dequeue(None).await.unwrap().is_none());
+        for dependent in [&b, &c] {
+            let skipped = job_repo.get_job(&dependent.id).await.unwrap();
+            assert_eq!(skipped.get_status(), Some(JobStatus::Skipped));
+            assert!(skipped.error.unwrap().starts_with(DEPENDENCY_FAILED));
+        }
+        let counts = job_repo.get_job_counts_by_status().await.unwrap();
+        assert_eq!(counts.failed, 1);
+        assert_eq!(counts.skipped, 2);
+        assert_eq!(queue.depth(), 0);
+
+        queue.retry_job(&b.id).await.unwrap();
+        let retried = job_repo.get_job(&b.id).await.unwrap();
+        assert_eq!(retried.get_status(), Some(JobStatus::Pending));
+    }
+
     /// resolve_job_metadata must write back-filled values to the job row's
     /// state column so a later dequeue does not depend on the live_sessions
     /// row still existing.
@@ -2541,6 +3070,7 @@ mod tests {
             warning_threshold: 10,
             critical_threshold: 20,
             poll_interval_ms: 100,
+            ..Default::default()
         };
         let queue = JobQueue::with_config(config);
 
@@ -2564,6 +3094,82 @@ mod tests {
         assert_eq!(queue.depth(), 1);
     }
 
+    fn test_job() -> Job {
+        Job::new(
+            "test",
+            vec!["input".to_string()],
+            vec!["output".to_string()],
+            "streamer",
+            "session",
+        )
+    }
+
+    fn bounded_queue(strategy: BackpressureStrategy) -> Arc<JobQueue> {
+        Arc::new(JobQueue::with_config(JobQueueConfig {
+            max_pending_jobs: 2,
+            backpressure_strategy: strategy,
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_rejects_jobs_past_max_pending() {
+        let queue = bounded_queue(BackpressureStrategy::Reject);
+        assert_eq!(queue.capacity(), 2);
+
+        queue.enqueue(test_job()).await.unwrap();
+        queue.enqueue(test_job()).await.unwrap();
+        let err = queue.enqueue(test_job()).await.unwrap_err();
+        assert!(matches!(err, Error::ResourceExhausted(_)));
+        assert_eq!(queue.pending_count(), 2);
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        assert_eq!(queue.pending_count(), 2);
+        queue.complete(&job.id, empty_result()).await.unwrap();
+        assert_eq!(queue.pending_count(), 1);
+        queue.enqueue(test_job()).await.unwrap();
+        assert_eq!(queue.pending_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_blocks_until_a_slot_is_freed() {
+        let queue = bounded_queue(BackpressureStrategy::Block);
+        queue.enqueue(test_job()).await.unwrap();
+        queue.enqueue(test_job()).await.unwrap();
+
+        let blocked = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.enqueue(test_job()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!blocked.is_finished());
+
+        let job = queue.dequeue(None).await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!blocked.is_finished());
+
+        queue.complete(&job.id, empty_result()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), blocked)
+            .await
+            .expect("enqueue unblocked")
+            .unwrap()
+            .unwrap();
+        assert_eq!(queue.pending_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_block_with_timeout_rejects_after_timeout() {
+        let queue = bounded_queue(BackpressureStrategy::BlockWithTimeout(
+            Duration::from_millis(20),
+        ));
+        queue.enqueue(test_job()).await.unwrap();
+        queue.enqueue(test_job()).await.unwrap();
+
+        let err = queue.enqueue(test_job()).await.unwrap_err();
+        assert!(matches!(err, Error::ResourceExhausted(_)));
+        assert_eq!(queue.pending_count(), 2);
+    }
+
     #[tokio::test]
     async fn test_retry_job_resets_failed_to_pending() {
         let queue = JobQueue::new();
@@ -2,6 +2,7 @@
 
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU8, Ordering};
@@ -18,9 +19,10 @@ use super::dag_scheduler::{
 };
 use super::job_queue::{Job, JobLogEntry, JobQueue, JobQueueConfig, QueueDepthStatus};
 use super::processors::{
-    AssBurnInProcessor, AudioExtractProcessor, CompressionProcessor, CopyMoveProcessor,
-    DanmakuFactoryProcessor, DeleteProcessor, ExecuteCommandProcessor, MetadataProcessor,
-    Processor, RcloneProcessor, RemuxProcessor, TdlUploadProcessor, ThumbnailProcessor,
+    ArchiveInfoProcessor, ArchiveVerifyProcessor, AssBurnInProcessor, AudioExtractProcessor,
+    CompressionProcessor, CopyMoveProcessor, DanmakuFactoryProcessor, DeleteProcessor,
+    ExecuteCommandProcessor, FanOutProcessor, MetadataProcessor, Processor, RcloneProcessor,
+    RemuxProcessor, TdlUploadProcessor, ThumbnailProcessor,
 };
 use super::progress::JobProgressSnapshot;
 use super::throttle::{DownloadLimitAdjuster, ThrottleConfig, ThrottleController, ThrottleEvent};
@@ -134,6 +136,9 @@ pub struct PipelineManagerConfig {
     /// This is enforced inside the processor (in addition to worker pool timeouts).
     #[serde(default = "default_execute_timeout_secs")]
     pub execute_timeout_secs: u64,
+    /// Named compression configs that jobs select with `profile`.
+    #[serde(default)]
+    pub compression_profiles: HashMap<String, serde_json::Value>,
 }
 
 fn default_execute_timeout_secs() -> u64 {
@@ -155,6 +160,7 @@ impl Default for PipelineManagerConfig {
 
             throttle: ThrottleConfig::default(),
             execute_timeout_secs: default_execute_timeout_secs(),
+            compression_profiles: HashMap::new(),
         }
     }
 }
@@ -319,7 +325,7 @@ where
         let execute_timeout_secs = config.execute_timeout_secs;
 
         // Create default processors
-        let processors: Vec<Arc<dyn Processor>> = vec![
+        let mut processors: Vec<Arc<dyn Processor>> = vec![
             Arc::new(RemuxProcessor::new()),
             Arc::new(DanmakuFactoryProcessor::new()),
             Arc::new(AssBurnInProcessor::new()),
@@ -329,10 +335,15 @@ where
             Arc::new(ThumbnailProcessor::new()),
             Arc::new(CopyMoveProcessor::new()),
             Arc::new(AudioExtractProcessor::new()),
-            Arc::new(CompressionProcessor::new()),
+            Arc::new(
+                CompressionProcessor::new().with_profiles(config.compression_profiles.clone()),
+            ),
+            Arc::new(ArchiveInfoProcessor::new()),
+            Arc::new(ArchiveVerifyProcessor::new()),
             Arc::new(MetadataProcessor::new()),
             Arc::new(DeleteProcessor::new()),
         ];
+        processors.push(Arc::new(FanOutProcessor::new(processors.clone())));
 
         // Create throttle controller if enabled
         let throttle_controller = if config.throttle.enabled {
@@ -382,7 +393,7 @@ where
         let execute_timeout_secs = config.execute_timeout_secs;
 
         // Create default processors
-        let processors: Vec<Arc<dyn Processor>> = vec![
+        let mut processors: Vec<Arc<dyn Processor>> = vec![
             Arc::new(RemuxProcessor::new()),
             Arc::new(DanmakuFactoryProcessor::new()),
             Arc::new(AssBurnInProcessor::new()),
@@ -392,10 +403,15 @@ where
             Arc::new(ThumbnailProcessor::new()),
             Arc::new(CopyMoveProcessor::new()),
             Arc::new(AudioExtractProcessor::new()),
-            Arc::new(CompressionProcessor::new()),
+            Arc::new(
+                CompressionProcessor::new().with_profiles(config.compression_profiles.clone()),
+            ),
+            Arc::new(ArchiveInfoProcessor::new()),
+            Arc::new(ArchiveVerifyProcessor::new()),
             Arc::new(MetadataProcessor::new()),
             Arc::new(DeleteProcessor::new()),
         ];
+        processors.push(Arc::new(FanOutProcessor::new(processors.clone())));
 
         // Create throttle controller if enabled
         let throttle_controller = if config.throttle.enabled {
@@ -555,6 +571,8 @@ pub struct PipelineStats {
     /// Number of failed jobs.
     pub failed: u64,
     pub cancelled: u64,
+    /// Number of jobs skipped because a dependency failed.
+    pub skipped: u64,
     /// Average processing time in seconds for completed jobs.
     pub avg_processing_time_secs: Option<f64>,
     /// Current queue depth.
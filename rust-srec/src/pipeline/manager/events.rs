@@ -246,6 +246,7 @@ where
             DanmuEvent::CollectionStarted {
                 session_id,
                 streamer_id,
+                ..
             } => {
                 let mut commands = Vec::new();
                 if let Some(config_service) = &self.config_service
@@ -348,6 +349,7 @@ where
                 segment_id,
                 output_path,
                 message_count,
+                ..
             } => {
                 let segment_path = output_path.to_string_lossy().to_string();
 
@@ -125,7 +125,10 @@ where
             .await?
             .ok_or_else(|| Error::not_found("Job", id))?;
 
-        if job_snapshot.status != JobStatus::Failed && job_snapshot.status != JobStatus::Cancelled {
+        if !matches!(
+            job_snapshot.status,
+            JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped
+        ) {
             return Err(Error::InvalidStateTransition {
                 from: job_snapshot.status.as_str().to_string(),
                 to: "PENDING".to_string(),
@@ -363,6 +366,7 @@ where
             completed: job_stats.completed,
             failed: job_stats.failed,
             cancelled: job_stats.cancelled,
+            skipped: job_stats.skipped,
             avg_processing_time_secs: job_stats.avg_processing_time_secs,
             queue_depth: self.queue_depth(),
             queue_status: self.queue_status(),
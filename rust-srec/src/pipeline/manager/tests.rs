@@ -288,6 +288,7 @@ impl SessionRepository for TestSessionRepository {
         _danmu_rate_timeseries: Option<&str>,
         _top_talkers: Option<&str>,
         _word_frequency: Option<&str>,
+        _gift_statistics: Option<&str>,
     ) -> Result<()> {
         unimplemented!("not needed for these tests")
     }
@@ -379,6 +380,10 @@ impl crate::database::repositories::JobRepository for TestJobRepository {
         unimplemented!("not needed for these tests")
     }
 
+    async fn mark_job_skipped(&self, _id: &str, _reason: &str) -> Result<u64> {
+        unimplemented!("not needed for these tests")
+    }
+
     async fn reset_job_for_retry(&self, id: &str) -> Result<()> {
         let now = chrono::Utc::now().timestamp_millis();
         let mut jobs = self.jobs.lock().expect("lock poisoned");
@@ -387,7 +392,7 @@ impl crate::database::repositories::JobRepository for TestJobRepository {
             .ok_or_else(|| crate::Error::not_found("Job", id))?;
 
         match JobStatus::parse(&job.status) {
-            Some(JobStatus::Failed | JobStatus::Cancelled) => {
+            Some(JobStatus::Failed | JobStatus::Cancelled | JobStatus::Skipped) => {
                 job.status = JobStatus::Pending.as_str().to_string();
                 job.started_at = None;
                 job.completed_at = None;
@@ -424,6 +429,7 @@ impl crate::database::repositories::JobRepository for TestJobRepository {
     async fn claim_next_pending_job(
         &self,
         _job_types: Option<&[String]>,
+        _exclude_ids: &[String],
     ) -> Result<Option<JobDbModel>> {
         unimplemented!("not needed for these tests")
     }
@@ -1,12 +1,16 @@
 //! Pipeline processors for post-processing tasks.
 
+mod archive_info;
+mod archive_verify;
 mod ass_burnin;
 mod audio_extract;
 mod compression;
 mod copy_move;
 mod danmaku_factory;
+mod danmu_replay;
 mod delete;
 mod execute;
+mod fan_out;
 mod metadata;
 mod rclone;
 mod remux;
@@ -17,18 +21,27 @@ mod thumbnail;
 mod traits;
 pub mod utils;
 
+pub use archive_info::ArchiveInfoProcessor;
+pub use archive_verify::ArchiveVerifyProcessor;
 pub use ass_burnin::{AssBurnInConfig, AssBurnInProcessor, AssMatchStrategy};
 pub use audio_extract::AudioExtractProcessor;
-pub use compression::CompressionProcessor;
+pub use compression::{
+    CompressionEntryMetadata, CompressionProcessor, CompressionResultMetadata, FileCompressionStat,
+    VirtualEntry, VirtualEntrySource, VirtualReader,
+};
 pub use copy_move::{CopyMoveConfig, CopyMoveOperation, CopyMoveProcessor};
 pub use danmaku_factory::{DanmakuFactoryConfig, DanmakuFactoryProcessor};
+pub use danmu_replay::{DanmuReplayConfig, DanmuReplayProcessor};
 pub use delete::DeleteProcessor;
 pub use execute::ExecuteCommandProcessor;
+pub use fan_out::{FanOutConfig, FanOutProcessor, MergeStrategy, ProcessorJobConfig};
 pub use metadata::MetadataProcessor;
 pub use rclone::RcloneProcessor;
 pub use remux::RemuxProcessor;
 pub use tdl::TdlUploadProcessor;
 pub use thumbnail::ThumbnailProcessor;
 pub use traits::{
-    JobLogSink, Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType,
+    DryRunReport, IoPriority, JobLogSink, Processor, ProcessorCapabilities, ProcessorContext,
+    ProcessorInput, ProcessorLogEntry, ProcessorOutput, ProcessorType, ResourceLimits,
+    StreamingOutputEvent,
 };
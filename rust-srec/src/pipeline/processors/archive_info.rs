@@ -0,0 +1,568 @@
+//! Archive info processor for inspecting existing archives.
+//!
+//! This processor opens ZIP or tar.gz inputs and reports their contents
+//! (entry names, sizes and modification times) without extracting anything.
+//! The archive format is detected from the file signature rather than the
+//! extension, so misnamed archives are still listed and non-archives are
+//! rejected with a descriptive error.
+
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use zip::ZipArchive;
+
+use super::compression::{ARCHIVE_SIGNATURE_LEN, ArchiveFormat, detect_archive_format};
+use super::traits::{Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType};
+use super::utils::{create_log_entry, parse_config_or_default};
+use crate::Result;
+
+/// Default maximum number of entries listed per archive.
+fn default_max_entries() -> usize {
+    1000
+}
+
+/// Configuration for archive inspection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveInfoConfig {
+    /// Maximum number of entries to include in the listing for each archive.
+    /// Entries beyond this limit are still counted but not listed, and the
+    /// archive is reported with `truncated: true`.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+impl Default for ArchiveInfoConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+/// A single entry listed from an archive.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct ArchiveEntryInfo {
+    name: String,
+    size: u64,
+    /// Compressed size of the entry. Only ZIP archives record this per entry.
+    compressed_size: Option<u64>,
+    /// Modification time as Unix seconds, if the archive recorded one.
+    mtime: Option<i64>,
+}
+
+/// Summary of an inspected archive.
+#[derive(Debug, Clone, Serialize)]
+struct ArchiveListing {
+    path: String,
+    format: String,
+    entry_count: u64,
+    total_size_bytes: u64,
+    truncated: bool,
+    entries: Vec<ArchiveEntryInfo>,
+}
+
+impl ArchiveListing {
+    fn new(path: &str, format: &ArchiveFormat) -> Self {
+        Self {
+            path: path.to_string(),
+            format: format!("{:?}", format),
+            entry_count: 0,
+            total_size_bytes: 0,
+            truncated: false,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record an entry, keeping at most `max_entries` in the listing.
+    fn push(&mut self, entry: ArchiveEntryInfo, max_entries: usize) {
+        self.entry_count = self.entry_count.saturating_add(1);
+        self.total_size_bytes = self.total_size_bytes.saturating_add(entry.size);
+        if self.entries.len() < max_entries {
+            self.entries.push(entry);
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// Convert a ZIP (MS-DOS) timestamp to Unix seconds.
+///
+/// ZIP timestamps carry no timezone, so they are interpreted as UTC.
+fn zip_datetime_to_unix(dt: zip::DateTime) -> Option<i64> {
+    chrono::NaiveDate::from_ymd_opt(dt.year() as i32, dt.month() as u32, dt.day() as u32)
+        .and_then(|date| date.and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32))
+        .map(|datetime| datetime.and_utc().timestamp())
+}
+
+/// Guess the archive format from the file extension.
+fn format_from_extension(path: &str) -> Option<ArchiveFormat> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(ArchiveFormat::Zip.extension()) {
+        Some(ArchiveFormat::Zip)
+    } else if lower.ends_with(ArchiveFormat::TarGz.extension()) || lower.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if lower.ends_with(ArchiveFormat::TarBz2.extension()) || lower.ends_with(".tbz2") {
+        Some(ArchiveFormat::TarBz2)
+    } else if lower.ends_with(ArchiveFormat::TarXz.extension()) || lower.ends_with(".txz") {
+        Some(ArchiveFormat::TarXz)
+    } else if lower.ends_with(ArchiveFormat::TarZst.extension()) {
+        Some(ArchiveFormat::TarZst)
+    } else if lower.ends_with(ArchiveFormat::SevenZip.extension()) {
+        Some(ArchiveFormat::SevenZip)
+    } else {
+        None
+    }
+}
+
+fn cancelled_error() -> crate::Error {
+    crate::Error::PipelineError("Archive inspection cancelled".to_string())
+}
+
+/// Processor for listing the contents of existing archives.
+///
+/// - Supports ZIP and tar.gz inputs, detected by file signature
+/// - ZIP listings are read from the central directory
+/// - tar.gz listings are streamed, so only the listed entries are kept in memory
+/// - Inputs are passed through unchanged as outputs
+pub struct ArchiveInfoProcessor;
+
+impl ArchiveInfoProcessor {
+    /// Create a new archive info processor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Open an input and detect its archive format from the file signature.
+    pub(super) fn open_archive(input_path: &str) -> Result<(File, ArchiveFormat)> {
+        let mut file = File::open(input_path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                crate::Error::PipelineError(format!("Input file does not exist: {}", input_path))
+            } else {
+                crate::Error::PipelineError(format!(
+                    "Failed to open input file {}: {}",
+                    input_path, e
+                ))
+            }
+        })?;
+
+        let mut header = Vec::with_capacity(ARCHIVE_SIGNATURE_LEN);
+        file.by_ref()
+            .take(ARCHIVE_SIGNATURE_LEN as u64)
+            .read_to_end(&mut header)
+            .map_err(|e| crate::Error::io_path("read", Path::new(input_path), e))?;
+
+        let format = detect_archive_format(&header).ok_or_else(|| {
+            crate::Error::PipelineError(format!(
+                "Input is not a ZIP or tar.gz archive (unrecognized file signature): {}",
+                input_path
+            ))
+        })?;
+
+        file.seek(SeekFrom::Start(0))
+            .map_err(|e| crate::Error::io_path("seek", Path::new(input_path), e))?;
+
+        Ok((file, format))
+    }
+
+    fn list_zip(
+        file: File,
+        input_path: &str,
+        max_entries: usize,
+        cancel: &CancellationToken,
+    ) -> Result<ArchiveListing> {
+        let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to read ZIP archive {}: {}", input_path, e))
+        })?;
+
+        let mut listing = ArchiveListing::new(input_path, &ArchiveFormat::Zip);
+        for idx in 0..archive.len() {
+            if cancel.is_cancelled() {
+                return Err(cancelled_error());
+            }
+
+            let entry = archive.by_index_raw(idx).map_err(|e| {
+                crate::Error::PipelineError(format!(
+                    "Failed to read ZIP entry {} in {}: {}",
+                    idx, input_path, e
+                ))
+            })?;
+
+            listing.push(
+                ArchiveEntryInfo {
+                    name: entry.name().to_string(),
+                    size: entry.size(),
+                    compressed_size: Some(entry.compressed_size()),
+                    mtime: entry.last_modified().and_then(zip_datetime_to_unix),
+                },
+                max_entries,
+            );
+        }
+
+        Ok(listing)
+    }
+
+    fn list_tar_gz(
+        file: File,
+        input_path: &str,
+        max_entries: usize,
+        cancel: &CancellationToken,
+    ) -> Result<ArchiveListing> {
+        let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+        let entries = archive.entries().map_err(|e| {
+            crate::Error::PipelineError(format!(
+                "Failed to read tar.gz archive {}: {}",
+                input_path, e
+            ))
+        })?;
+
+        let mut listing = ArchiveListing::new(input_path, &ArchiveFormat::TarGz);
+        for entry in entries {
+            if cancel.is_cancelled() {
+                return Err(cancelled_error());
+            }
+
+            let entry = entry.map_err(|e| {
+                crate::Error::PipelineError(format!(
+                    "Failed to read tar.gz entry in {} (is it a gzipped tar archive?): {}",
+                    input_path, e
+                ))
+            })?;
+
+            let header = entry.header();
+            listing.push(
+                ArchiveEntryInfo {
+                    name: entry.path().map_or_else(
+                        |_| String::from_utf8_lossy(&entry.path_bytes()).to_string(),
+                        |p| p.to_string_lossy().to_string(),
+                    ),
+                    size: header.size().unwrap_or_else(|_| entry.size()),
+                    compressed_size: None,
+                    mtime: header.mtime().ok().map(|t| t as i64),
+                },
+                max_entries,
+            );
+        }
+
+        Ok(listing)
+    }
+
+    /// Inspect a single archive, blocking the current thread.
+    fn inspect(
+        input_path: &str,
+        max_entries: usize,
+        cancel: &CancellationToken,
+    ) -> Result<ArchiveListing> {
+        let (file, format) = Self::open_archive(input_path)?;
+        match format {
+            ArchiveFormat::Zip => Self::list_zip(file, input_path, max_entries, cancel),
+            ArchiveFormat::TarGz => Self::list_tar_gz(file, input_path, max_entries, cancel),
+            format => Err(crate::Error::PipelineError(format!(
+                "Listing {:?} archives is not supported: {}",
+                format, input_path
+            ))),
+        }
+    }
+}
+
+impl Default for ArchiveInfoProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Processor for ArchiveInfoProcessor {
+    fn processor_type(&self) -> ProcessorType {
+        ProcessorType::Io
+    }
+
+    fn job_types(&self) -> Vec<&'static str> {
+        vec!["archive_info", "archive-info"]
+    }
+
+    fn name(&self) -> &'static str {
+        "ArchiveInfoProcessor"
+    }
+
+    /// Indicates this processor supports multiple inputs (batch processing).
+    fn supports_batch_input(&self) -> bool {
+        true
+    }
+
+    async fn process(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<ProcessorOutput> {
+        let start = std::time::Instant::now();
+        let mut logs = Vec::new();
+
+        let config: ArchiveInfoConfig = parse_config_or_default(
+            input.config.as_deref(),
+            ctx,
+            "archive_info",
+            Some(&mut logs),
+        );
+
+        if input.inputs.is_empty() {
+            let msg = "No input archives specified for inspection".to_string();
+            error!("{}", msg);
+            logs.push(create_log_entry(
+                crate::pipeline::job_queue::LogLevel::Error,
+                msg.clone(),
+            ));
+            return Err(crate::Error::PipelineError(msg));
+        }
+
+        let inputs = input.inputs.clone();
+        let max_entries = config.max_entries;
+        let cancel = ctx.cancellation_token.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            inputs
+                .iter()
+                .map(|path| ArchiveInfoProcessor::inspect(path, max_entries, &cancel))
+                .collect::<Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|e| crate::Error::Other(format!("Archive inspection worker panicked: {}", e)))?;
+
+        let listings = match result {
+            Ok(listings) => listings,
+            Err(e) => {
+                let msg = format!("Archive inspection failed: {}", e);
+                error!("{}", msg);
+                logs.push(create_log_entry(
+                    crate::pipeline::job_queue::LogLevel::Error,
+                    msg,
+                ));
+                return Err(e);
+            }
+        };
+
+        for listing in &listings {
+            if let Some(expected) = format_from_extension(&listing.path)
+                && format!("{:?}", expected) != listing.format
+            {
+                let msg = format!(
+                    "Archive extension does not match its contents ({:?} expected, {} detected): {}",
+                    expected, listing.format, listing.path
+                );
+                warn!("{}", msg);
+                logs.push(create_log_entry(
+                    crate::pipeline::job_queue::LogLevel::Warn,
+                    msg,
+                ));
+            }
+
+            let msg = format!(
+                "Inspected {} archive with {} entries{}: {}",
+                listing.format,
+                listing.entry_count,
+                if listing.truncated {
+                    format!(" (listing truncated to {})", listing.entries.len())
+                } else {
+                    String::new()
+                },
+                listing.path
+            );
+            info!("{}", msg);
+            logs.push(create_log_entry(
+                crate::pipeline::job_queue::LogLevel::Info,
+                msg,
+            ));
+        }
+
+        let input_size: u64 = input
+            .inputs
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum();
+
+        Ok(ProcessorOutput {
+            outputs: input.inputs.clone(),
+            duration_secs: start.elapsed().as_secs_f64(),
+            metadata: Some(
+                serde_json::json!({
+                    "archive_count": listings.len(),
+                    "max_entries": max_entries,
+                    "archives": listings,
+                })
+                .to_string(),
+            ),
+            items_produced: vec![],
+            input_size_bytes: Some(input_size),
+            output_size_bytes: None,
+            failed_inputs: vec![],
+            succeeded_inputs: input.inputs.clone(),
+            skipped_inputs: vec![],
+            logs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder =
+            flate2::write::GzEncoder::new(File::create(path).unwrap(), Default::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_700_000_000);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    async fn run(path: &Path, config: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        let processor = ArchiveInfoProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![path.to_string_lossy().to_string()],
+            config: config.map(|c| c.to_string()),
+            ..Default::default()
+        };
+        let output = processor.process(&input, &ctx).await?;
+        assert_eq!(output.outputs, input.inputs);
+        Ok(serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_archive_info_processor_job_types() {
+        let processor = ArchiveInfoProcessor::new();
+        assert_eq!(processor.processor_type(), ProcessorType::Io);
+        assert!(processor.can_process("archive_info"));
+        assert!(processor.can_process("archive-info"));
+        assert!(!processor.can_process("archive"));
+        assert!(processor.supports_batch_input());
+    }
+
+    #[test]
+    fn test_archive_info_config_default() {
+        let config = ArchiveInfoConfig::default();
+        assert_eq!(config.max_entries, 1000);
+    }
+
+    #[test]
+    fn test_zip_datetime_to_unix() {
+        let dt = zip::DateTime::from_date_and_time(2024, 1, 2, 3, 4, 6).unwrap();
+        assert_eq!(zip_datetime_to_unix(dt), Some(1_704_164_646));
+    }
+
+    #[tokio::test]
+    async fn test_list_zip_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.zip");
+        write_zip(&path, &[("a.flv", b"aaaa"), ("b.xml", b"bbbbbbbb")]);
+
+        let metadata = run(&path, None).await.unwrap();
+        let archive = &metadata["archives"][0];
+        assert_eq!(metadata["archive_count"], 1);
+        assert_eq!(archive["format"], "Zip");
+        assert_eq!(archive["entry_count"], 2);
+        assert_eq!(archive["total_size_bytes"], 12);
+        assert_eq!(archive["truncated"], false);
+        assert_eq!(archive["entries"][0]["name"], "a.flv");
+        assert_eq!(archive["entries"][0]["size"], 4);
+        assert!(archive["entries"][0]["compressed_size"].as_u64().is_some());
+        assert!(archive["entries"][0]["mtime"].as_i64().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_tar_gz_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.tar.gz");
+        write_tar_gz(&path, &[("a.flv", b"aaaa"), ("b.xml", b"bbbbbbbb")]);
+
+        let metadata = run(&path, None).await.unwrap();
+        let archive = &metadata["archives"][0];
+        assert_eq!(archive["format"], "TarGz");
+        assert_eq!(archive["entry_count"], 2);
+        assert_eq!(archive["entries"][1]["name"], "b.xml");
+        assert_eq!(archive["entries"][1]["size"], 8);
+        assert!(archive["entries"][1]["compressed_size"].is_null());
+        assert_eq!(archive["entries"][1]["mtime"], 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_truncated() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("many.tar.gz");
+        write_tar_gz(&path, &[("1", b"x"), ("2", b"x"), ("3", b"x")]);
+
+        let metadata = run(&path, Some(serde_json::json!({"max_entries": 2})))
+            .await
+            .unwrap();
+        let archive = &metadata["archives"][0];
+        assert_eq!(archive["entry_count"], 3);
+        assert_eq!(archive["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(archive["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_misnamed_archive_is_detected_by_signature() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("actually_zip.tar.gz");
+        write_zip(&path, &[("a.flv", b"aaaa")]);
+
+        let metadata = run(&path, None).await.unwrap();
+        assert_eq!(metadata["archives"][0]["format"], "Zip");
+    }
+
+    #[tokio::test]
+    async fn test_non_archive_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.zip");
+        std::fs::write(&path, "just some text").unwrap();
+
+        let err = run(&path, None).await.unwrap_err();
+        assert!(err.to_string().contains("not a ZIP or tar.gz archive"));
+    }
+
+    #[tokio::test]
+    async fn test_unlistable_format_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.tar.xz");
+        std::fs::write(&path, [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00, 0x04]).unwrap();
+
+        let err = run(&path, None).await.unwrap_err();
+        assert!(err.to_string().contains("TarXz archives is not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_gzip_without_tar_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("plain.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b"not a tar stream").unwrap();
+        encoder.finish().unwrap();
+
+        let err = run(&path, None).await.unwrap_err();
+        assert!(err.to_string().contains("tar.gz"));
+    }
+}
@@ -0,0 +1,799 @@
+//! Archive verify processor for periodic integrity audits.
+//!
+//! This processor reads every entry of ZIP or tar.gz inputs without extracting
+//! anything: ZIP entries are checked against their stored CRC-32, and tar.gz
+//! archives are decompressed to the end of the gzip stream so both the tar
+//! headers and the gzip trailer are validated. Archives can additionally be
+//! compared against a `sha256sum`-style sidecar file and an embedded manifest.
+
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use zip::ZipArchive;
+
+use super::archive_info::ArchiveInfoProcessor;
+use super::compression::ArchiveFormat;
+use super::traits::{Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType};
+use super::utils::{create_log_entry, parse_config_or_default};
+use crate::Result;
+
+/// Size of the buffer entries are streamed through.
+const READ_BUFFER_LEN: usize = 64 * 1024;
+
+/// Largest embedded manifest that is read into memory.
+const MANIFEST_MAX_LEN: u64 = 1024 * 1024;
+
+fn default_sidecar_extension() -> Option<String> {
+    Some("sha256".to_string())
+}
+
+fn default_manifest_entry() -> Option<String> {
+    Some("SHA256SUMS".to_string())
+}
+
+/// Configuration for archive verification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveVerifyConfig {
+    /// Extension of a checksum sidecar next to each archive, e.g. `sha256` for
+    /// `session.zip.sha256`. When the sidecar exists, the SHA-256 of the whole
+    /// archive must match its first hash. `null` disables the check.
+    #[serde(default = "default_sidecar_extension")]
+    pub sidecar_extension: Option<String>,
+
+    /// Name of an entry inside the archive listing SHA-256 sums of the other
+    /// entries in `sha256sum` format. When the entry exists, every listed entry
+    /// must be present with a matching hash. `null` disables the check.
+    #[serde(default = "default_manifest_entry")]
+    pub manifest_entry: Option<String>,
+}
+
+impl Default for ArchiveVerifyConfig {
+    fn default() -> Self {
+        Self {
+            sidecar_extension: default_sidecar_extension(),
+            manifest_entry: default_manifest_entry(),
+        }
+    }
+}
+
+/// Result of verifying a single archive.
+#[derive(Debug, Clone, Serialize)]
+struct ArchiveVerification {
+    path: String,
+    format: Option<String>,
+    passed: bool,
+    error: Option<String>,
+    /// Entries read in full before verification finished or failed.
+    entry_count: u64,
+    total_size_bytes: u64,
+    /// Whether a checksum sidecar was found and compared.
+    sidecar_checked: bool,
+    /// Number of entries compared against the embedded manifest, if present.
+    manifest_entries: Option<usize>,
+}
+
+impl ArchiveVerification {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            format: None,
+            passed: false,
+            error: None,
+            entry_count: 0,
+            total_size_bytes: 0,
+            sidecar_checked: false,
+            manifest_entries: None,
+        }
+    }
+}
+
+/// SHA-256 sums of entries, and the manifest content once it was read.
+#[derive(Default)]
+struct EntryHashes {
+    hashes: HashMap<String, String>,
+    manifest: Option<String>,
+}
+
+fn cancelled_error() -> crate::Error {
+    crate::Error::PipelineError("Archive verification cancelled".to_string())
+}
+
+/// Parse `sha256sum` output lines (`<hex>  <name>` or `<hex> *<name>`).
+fn parse_sha256sums(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hash, name) = line.trim_end().split_once(char::is_whitespace)?;
+            let name = name.trim_start();
+            let name = name.strip_prefix('*').unwrap_or(name);
+            (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+                .then(|| (hash.to_ascii_lowercase(), name.to_string()))
+        })
+        .collect()
+}
+
+/// Read `reader` to the end in bounded chunks, returning the bytes read and
+/// their SHA-256 as lowercase hex.
+fn hash_stream(
+    reader: &mut impl Read,
+    cancel: &CancellationToken,
+) -> std::io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; READ_BUFFER_LEN];
+    let mut total = 0u64;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "archive verification cancelled",
+            ));
+        }
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        total = total.saturating_add(n as u64);
+    }
+    Ok((total, hex::encode(hasher.finalize())))
+}
+
+/// Processor for checking the integrity of existing archives.
+///
+/// - Supports ZIP and tar.gz inputs, detected by file signature
+/// - Entries are streamed through a fixed-size buffer
+/// - Cancellation is checked between entries and while reading them
+/// - Each archive passes or fails on its own; inputs are passed through as outputs
+pub struct ArchiveVerifyProcessor;
+
+impl ArchiveVerifyProcessor {
+    /// Create a new archive verify processor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Record an entry read in full, keeping its hash for the manifest check.
+    fn record_entry(
+        verification: &mut ArchiveVerification,
+        hashes: &mut EntryHashes,
+        name: String,
+        size: u64,
+        hash: String,
+    ) {
+        verification.entry_count = verification.entry_count.saturating_add(1);
+        verification.total_size_bytes = verification.total_size_bytes.saturating_add(size);
+        hashes.hashes.insert(name, hash);
+    }
+
+    fn verify_zip(
+        file: File,
+        verification: &mut ArchiveVerification,
+        manifest_entry: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<EntryHashes> {
+        let input_path = verification.path.clone();
+        let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to read ZIP archive {}: {}", input_path, e))
+        })?;
+
+        let mut hashes = EntryHashes::default();
+        for idx in 0..archive.len() {
+            if cancel.is_cancelled() {
+                return Err(cancelled_error());
+            }
+
+            let mut entry = archive.by_index(idx).map_err(|e| {
+                crate::Error::PipelineError(format!(
+                    "Failed to read ZIP entry {} in {}: {}",
+                    idx, input_path, e
+                ))
+            })?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry.name().to_string();
+
+            if manifest_entry == Some(name.as_str()) {
+                hashes.manifest = Some(Self::read_manifest(&mut entry, &input_path)?);
+                continue;
+            }
+
+            // zip checks the stored CRC-32 once the entry is read to the end.
+            let (size, hash) = hash_stream(&mut entry, cancel).map_err(|e| {
+                if cancel.is_cancelled() {
+                    cancelled_error()
+                } else {
+                    crate::Error::PipelineError(format!(
+                        "ZIP entry {} in {} is corrupt: {}",
+                        name, input_path, e
+                    ))
+                }
+            })?;
+            Self::record_entry(verification, &mut hashes, name, size, hash);
+        }
+
+        Ok(hashes)
+    }
+
+    fn verify_tar_gz(
+        file: File,
+        verification: &mut ArchiveVerification,
+        manifest_entry: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<EntryHashes> {
+        let input_path = verification.path.clone();
+        let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+        let mut hashes = EntryHashes::default();
+        {
+            let entries = archive.entries().map_err(|e| {
+                crate::Error::PipelineError(format!(
+                    "Failed to read tar.gz archive {}: {}",
+                    input_path, e
+                ))
+            })?;
+
+            for entry in entries {
+                if cancel.is_cancelled() {
+                    return Err(cancelled_error());
+                }
+
+                let mut entry = entry.map_err(|e| {
+                    crate::Error::PipelineError(format!(
+                        "Failed to read tar.gz entry in {}: {}",
+                        input_path, e
+                    ))
+                })?;
+                let name = entry.path().map_or_else(
+                    |_| String::from_utf8_lossy(&entry.path_bytes()).to_string(),
+                    |p| p.to_string_lossy().to_string(),
+                );
+
+                if manifest_entry == Some(name.as_str()) {
+                    hashes.manifest = Some(Self::read_manifest(&mut entry, &input_path)?);
+                    continue;
+                }
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+
+                let (size, hash) = hash_stream(&mut entry, cancel).map_err(|e| {
+                    if cancel.is_cancelled() {
+                        cancelled_error()
+                    } else {
+                        crate::Error::PipelineError(format!(
+                            "tar.gz entry {} in {} is corrupt: {}",
+                            name, input_path, e
+                        ))
+                    }
+                })?;
+                Self::record_entry(verification, &mut hashes, name, size, hash);
+            }
+        }
+
+        // The tar reader stops at the end-of-archive marker; drain the rest so
+        // the gzip decoder validates its CRC-32 and length trailer.
+        hash_stream(&mut archive.into_inner(), cancel).map_err(|e| {
+            if cancel.is_cancelled() {
+                cancelled_error()
+            } else {
+                crate::Error::PipelineError(format!(
+                    "gzip stream of {} is corrupt: {}",
+                    input_path, e
+                ))
+            }
+        })?;
+
+        Ok(hashes)
+    }
+
+    fn read_manifest(reader: &mut impl Read, input_path: &str) -> Result<String> {
+        let mut content = String::new();
+        reader
+            .take(MANIFEST_MAX_LEN + 1)
+            .read_to_string(&mut content)
+            .map_err(|e| {
+                crate::Error::PipelineError(format!(
+                    "Failed to read manifest in {}: {}",
+                    input_path, e
+                ))
+            })?;
+        if content.len() as u64 > MANIFEST_MAX_LEN {
+            return Err(crate::Error::PipelineError(format!(
+                "Manifest in {} exceeds {} bytes",
+                input_path, MANIFEST_MAX_LEN
+            )));
+        }
+        Ok(content)
+    }
+
+    /// Compare entry hashes with the embedded manifest, if one was read.
+    fn check_manifest(verification: &mut ArchiveVerification, hashes: &EntryHashes) -> Result<()> {
+        let Some(manifest) = &hashes.manifest else {
+            return Ok(());
+        };
+        let expected = parse_sha256sums(manifest);
+        for (hash, name) in &expected {
+            match hashes.hashes.get(name) {
+                Some(actual) if actual == hash => {}
+                Some(_) => {
+                    return Err(crate::Error::PipelineError(format!(
+                        "Entry {} in {} does not match its manifest checksum",
+                        name, verification.path
+                    )));
+                }
+                None => {
+                    return Err(crate::Error::PipelineError(format!(
+                        "Entry {} listed in the manifest is missing from {}",
+                        name, verification.path
+                    )));
+                }
+            }
+        }
+        verification.manifest_entries = Some(expected.len());
+        Ok(())
+    }
+
+    /// Compare the whole archive with `<archive>.<extension>`, if it exists.
+    fn check_sidecar(
+        verification: &mut ArchiveVerification,
+        extension: &str,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let sidecar = format!("{}.{}", verification.path, extension);
+        let content = match std::fs::read_to_string(&sidecar) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(crate::Error::io_path("read", Path::new(&sidecar), e)),
+        };
+        let Some((expected, _)) = parse_sha256sums(&content).into_iter().next() else {
+            return Err(crate::Error::PipelineError(format!(
+                "Checksum sidecar {} holds no SHA-256 hash",
+                sidecar
+            )));
+        };
+
+        let path = Path::new(&verification.path);
+        let mut file = File::open(path).map_err(|e| crate::Error::io_path("open", path, e))?;
+        let (_, actual) = hash_stream(&mut file, cancel).map_err(|e| {
+            if cancel.is_cancelled() {
+                cancelled_error()
+            } else {
+                crate::Error::io_path("read", path, e)
+            }
+        })?;
+        if actual != expected {
+            return Err(crate::Error::PipelineError(format!(
+                "{} does not match the checksum in {}",
+                verification.path, sidecar
+            )));
+        }
+        verification.sidecar_checked = true;
+        Ok(())
+    }
+
+    /// Verify a single archive, blocking the current thread.
+    fn verify(
+        verification: &mut ArchiveVerification,
+        config: &ArchiveVerifyConfig,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let (file, format) = ArchiveInfoProcessor::open_archive(&verification.path)?;
+        verification.format = Some(format!("{:?}", format));
+        let manifest_entry = config.manifest_entry.as_deref();
+        let hashes = match format {
+            ArchiveFormat::Zip => Self::verify_zip(file, verification, manifest_entry, cancel),
+            ArchiveFormat::TarGz => Self::verify_tar_gz(file, verification, manifest_entry, cancel),
+            format => Err(crate::Error::PipelineError(format!(
+                "Verifying {:?} archives is not supported: {}",
+                format, verification.path
+            ))),
+        }?;
+        Self::check_manifest(verification, &hashes)?;
+        if let Some(extension) = &config.sidecar_extension {
+            Self::check_sidecar(verification, extension, cancel)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ArchiveVerifyProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Processor for ArchiveVerifyProcessor {
+    fn processor_type(&self) -> ProcessorType {
+        ProcessorType::Cpu
+    }
+
+    fn job_types(&self) -> Vec<&'static str> {
+        vec!["archive_verify", "archive-verify"]
+    }
+
+    fn name(&self) -> &'static str {
+        "ArchiveVerifyProcessor"
+    }
+
+    /// Indicates this processor supports multiple inputs (batch processing).
+    fn supports_batch_input(&self) -> bool {
+        true
+    }
+
+    async fn process(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<ProcessorOutput> {
+        let start = std::time::Instant::now();
+        let mut logs = Vec::new();
+
+        let config: ArchiveVerifyConfig = parse_config_or_default(
+            input.config.as_deref(),
+            ctx,
+            "archive_verify",
+            Some(&mut logs),
+        );
+
+        if input.inputs.is_empty() {
+            let msg = "No input archives specified for verification".to_string();
+            error!("{}", msg);
+            logs.push(create_log_entry(
+                crate::pipeline::job_queue::LogLevel::Error,
+                msg.clone(),
+            ));
+            return Err(crate::Error::PipelineError(msg));
+        }
+
+        if ctx.is_dry_run() {
+            return Ok(ProcessorOutput {
+                outputs: input.inputs.clone(),
+                logs,
+                ..Default::default()
+            });
+        }
+
+        let inputs = input.inputs.clone();
+        let cancel = ctx.cancellation_token.clone();
+
+        let verifications = tokio::task::spawn_blocking(move || {
+            let mut verifications = Vec::with_capacity(inputs.len());
+            for path in &inputs {
+                if cancel.is_cancelled() {
+                    return Err(cancelled_error());
+                }
+                let mut verification = ArchiveVerification::new(path);
+                match ArchiveVerifyProcessor::verify(&mut verification, &config, &cancel) {
+                    Ok(()) => verification.passed = true,
+                    Err(e) if cancel.is_cancelled() => return Err(e),
+                    Err(e) => verification.error = Some(e.to_string()),
+                }
+                verifications.push(verification);
+            }
+            Ok(verifications)
+        })
+        .await
+        .map_err(|e| crate::Error::Other(format!("Archive verification worker panicked: {}", e)))?;
+
+        let verifications = match verifications {
+            Ok(verifications) => verifications,
+            Err(e) => {
+                let msg = format!("Archive verification failed: {}", e);
+                error!("{}", msg);
+                logs.push(create_log_entry(
+                    crate::pipeline::job_queue::LogLevel::Error,
+                    msg,
+                ));
+                return Err(e);
+            }
+        };
+
+        let mut succeeded_inputs = Vec::new();
+        let mut failed_inputs = Vec::new();
+        for verification in &verifications {
+            match &verification.error {
+                None => {
+                    let msg = format!(
+                        "Verified {} entries: {}",
+                        verification.entry_count, verification.path
+                    );
+                    info!("{}", msg);
+                    logs.push(create_log_entry(
+                        crate::pipeline::job_queue::LogLevel::Info,
+                        msg,
+                    ));
+                    succeeded_inputs.push(verification.path.clone());
+                }
+                Some(reason) => {
+                    let msg = format!("Archive failed verification: {}", reason);
+                    warn!("{}", msg);
+                    logs.push(create_log_entry(
+                        crate::pipeline::job_queue::LogLevel::Warn,
+                        msg,
+                    ));
+                    failed_inputs.push((verification.path.clone(), reason.clone()));
+                }
+            }
+        }
+
+        if succeeded_inputs.is_empty() {
+            return Err(crate::Error::PipelineError(format!(
+                "All {} archives failed verification",
+                failed_inputs.len()
+            )));
+        }
+
+        let input_size: u64 = input
+            .inputs
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum();
+
+        Ok(ProcessorOutput {
+            outputs: input.inputs.clone(),
+            duration_secs: start.elapsed().as_secs_f64(),
+            metadata: Some(
+                serde_json::json!({
+                    "archive_count": verifications.len(),
+                    "passed": succeeded_inputs.len(),
+                    "failed": failed_inputs.len(),
+                    "archives": verifications,
+                })
+                .to_string(),
+            ),
+            items_produced: vec![],
+            input_size_bytes: Some(input_size),
+            output_size_bytes: None,
+            failed_inputs,
+            succeeded_inputs,
+            skipped_inputs: vec![],
+            logs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder =
+            flate2::write::GzEncoder::new(File::create(path).unwrap(), Default::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    async fn run(paths: &[&Path], ctx: &ProcessorContext) -> Result<ProcessorOutput> {
+        let input = ProcessorInput {
+            inputs: paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            ..Default::default()
+        };
+        ArchiveVerifyProcessor::new().process(&input, ctx).await
+    }
+
+    fn metadata(output: &ProcessorOutput) -> serde_json::Value {
+        serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_archive_verify_processor_job_types() {
+        let processor = ArchiveVerifyProcessor::new();
+        assert_eq!(processor.processor_type(), ProcessorType::Cpu);
+        assert!(processor.can_process("archive_verify"));
+        assert!(processor.can_process("archive-verify"));
+        assert!(!processor.can_process("archive_info"));
+        assert!(processor.supports_batch_input());
+    }
+
+    #[test]
+    fn test_parse_sha256sums() {
+        let hash = "a".repeat(64);
+        let content = format!("{hash}  a.flv\n{hash} *b.xml\nnot a checksum line\n");
+        assert_eq!(
+            parse_sha256sums(&content),
+            vec![
+                (hash.clone(), "a.flv".to_string()),
+                (hash, "b.xml".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_intact_and_corrupt_archives() {
+        let temp_dir = TempDir::new().unwrap();
+        let good_zip = temp_dir.path().join("good.zip");
+        let good_tar = temp_dir.path().join("good.tar.gz");
+        let bad_zip = temp_dir.path().join("bad.zip");
+        write_zip(&good_zip, &[("a.flv", b"aaaa"), ("b.xml", b"bbbbbbbb")]);
+        write_tar_gz(&good_tar, &[("a.flv", b"aaaa")]);
+
+        // Stored entry so the payload bytes appear verbatim and can be flipped.
+        let mut zip = zip::ZipWriter::new(File::create(&bad_zip).unwrap());
+        let stored =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("a.flv", stored).unwrap();
+        zip.write_all(b"payload-payload").unwrap();
+        zip.finish().unwrap();
+        let mut bytes = std::fs::read(&bad_zip).unwrap();
+        let offset = bytes
+            .windows(7)
+            .position(|window| window == b"payload")
+            .unwrap();
+        bytes[offset] ^= 0xff;
+        std::fs::write(&bad_zip, bytes).unwrap();
+
+        let output = run(
+            &[&good_zip, &good_tar, &bad_zip],
+            &ProcessorContext::noop("test"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(output.succeeded_inputs.len(), 2);
+        assert_eq!(output.failed_inputs.len(), 1);
+        assert_eq!(output.failed_inputs[0].0, bad_zip.to_string_lossy());
+        let metadata = metadata(&output);
+        assert_eq!(metadata["passed"], 2);
+        assert_eq!(metadata["archives"][0]["entry_count"], 2);
+        assert_eq!(metadata["archives"][0]["total_size_bytes"], 12);
+        assert_eq!(metadata["archives"][1]["format"], "TarGz");
+        assert_eq!(metadata["archives"][2]["passed"], false);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_gzip_trailer_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good.zip");
+        let path = temp_dir.path().join("truncated.tar.gz");
+        write_zip(&good, &[("a.flv", b"aaaa")]);
+        write_tar_gz(&path, &[("a.flv", b"aaaa")]);
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let len = file.metadata().unwrap().len();
+        file.set_len(len - 4).unwrap();
+
+        let output = run(&[&good, &path], &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.failed_inputs.len(), 1);
+        assert!(output.failed_inputs[0].1.contains("corrupt"));
+    }
+
+    #[tokio::test]
+    async fn test_all_archives_failing_fails_the_job() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.zip");
+        std::fs::write(&path, "just some text").unwrap();
+
+        let err = run(&[&path], &ProcessorContext::noop("test"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("failed verification"));
+    }
+
+    #[tokio::test]
+    async fn test_compares_checksum_sidecar() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good.zip");
+        let stale = temp_dir.path().join("stale.zip");
+        write_zip(&good, &[("a.flv", b"aaaa")]);
+        write_zip(&stale, &[("a.flv", b"aaaa")]);
+        let good_hash = sha256_hex(&std::fs::read(&good).unwrap());
+        std::fs::write(
+            temp_dir.path().join("good.zip.sha256"),
+            format!("{good_hash}  good.zip\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("stale.zip.sha256"),
+            format!("{}  stale.zip\n", "0".repeat(64)),
+        )
+        .unwrap();
+
+        let output = run(&[&good, &stale], &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.succeeded_inputs, vec![good.to_string_lossy()]);
+        assert!(
+            output.failed_inputs[0]
+                .1
+                .contains("does not match the checksum")
+        );
+        assert_eq!(metadata(&output)["archives"][0]["sidecar_checked"], true);
+    }
+
+    #[tokio::test]
+    async fn test_compares_embedded_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good.tar.gz");
+        let bad = temp_dir.path().join("bad.zip");
+        let manifest = format!(
+            "{}  a.flv\n{}  b.xml\n",
+            sha256_hex(b"aaaa"),
+            sha256_hex(b"bbbb")
+        );
+        write_tar_gz(
+            &good,
+            &[
+                ("a.flv", b"aaaa"),
+                ("b.xml", b"bbbb"),
+                ("SHA256SUMS", manifest.as_bytes()),
+            ],
+        );
+        write_zip(
+            &bad,
+            &[
+                ("SHA256SUMS", manifest.as_bytes()),
+                ("a.flv", b"aaaa"),
+                ("b.xml", b"cccc"),
+            ],
+        );
+
+        let output = run(&[&good, &bad], &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.succeeded_inputs, vec![good.to_string_lossy()]);
+        assert!(output.failed_inputs[0].1.contains("b.xml"));
+        let metadata = metadata(&output);
+        assert_eq!(metadata["archives"][0]["manifest_entries"], 2);
+        assert_eq!(metadata["archives"][0]["entry_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_fails_the_job() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("good.zip");
+        write_zip(&path, &[("a.flv", b"aaaa")]);
+        let ctx = ProcessorContext::noop("test");
+        ctx.cancellation_token.cancel();
+
+        let err = run(&[&path], &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+    }
+
+    #[test]
+    fn test_hash_stream_honors_cancellation() {
+        let mut reader = std::io::Cursor::new(vec![0u8; 16]);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        assert!(hash_stream(&mut reader, &cancel).is_err());
+    }
+}
@@ -7,20 +7,39 @@
 use async_trait::async_trait;
 use flate2::Compression;
 use flate2::write::GzEncoder;
+use globset::{Glob, GlobMatcher};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sevenz_rust::lzma::LZMA2Options;
+use sevenz_rust::{
+    SeqReader, SevenZArchiveEntry, SevenZMethodConfiguration, SevenZWriter, SourceReader,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 use tar::Builder as TarBuilder;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
-use zip::ZipWriter;
-use zip::write::SimpleFileOptions;
-
-use super::traits::{Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType};
-use super::utils::{create_log_entry, parse_config_or_default, tmp_output_path};
+use zip::write::FullFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use super::traits::{
+    DryRunReport, Processor, ProcessorCapabilities, ProcessorContext, ProcessorEventStream,
+    ProcessorInput, ProcessorLogEntry, ProcessorOutput, ProcessorType, StreamingOutputEvent,
+    TimeAnchor, streaming_output,
+};
+use super::utils::{parse_config_or_default, tmp_output_path};
 use crate::Result;
 use crate::pipeline::progress::{JobProgressSnapshot, ProgressKind, ProgressReporter};
+use crate::utils::filename::expand_placeholders_at;
+use rsyncable::RsyncableGzEncoder;
+
+mod rsyncable;
 
 /// Default compression level (6 is a good balance between speed and compression).
 fn default_compression_level() -> u8 {
@@ -28,38 +47,144 @@ fn default_compression_level() -> u8 {
 }
 
 /// Archive format options.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+///
+/// Each format is recognized by [`detect_archive_format`] from its leading
+/// magic bytes. Only `Zip`, `TarGz` and `SevenZip` archives can be written.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ArchiveFormat {
-    /// ZIP archive format.
+    /// ZIP archive format; magic `PK\x03\x04`, or `PK\x05\x06` when empty.
     #[default]
     Zip,
-    /// Gzipped tar archive format.
+    /// Gzipped tar archive format; magic `\x1f\x8b`.
     TarGz,
+    /// Bzip2-compressed tar archive; magic `BZh` (`\x42\x5a\x68`).
+    TarBz2,
+    /// Xz-compressed tar archive; magic `\xfd7zXZ\x00` (`\xfd\x37\x7a\x58\x5a\x00`).
+    TarXz,
+    /// Zstandard-compressed tar archive; frame magic `\x28\xb5\x2f\xfd`.
+    TarZst,
+    /// 7z archive; magic `7z\xbc\xaf\x27\x1c` (`\x37\x7a\xbc\xaf\x27\x1c`).
+    #[serde(alias = "7z")]
+    SevenZip,
 }
 
 impl ArchiveFormat {
     /// Get the default file extension for this format.
-    fn extension(&self) -> &'static str {
+    pub(super) fn extension(&self) -> &'static str {
         match self {
             Self::Zip => "zip",
             Self::TarGz => "tar.gz",
+            Self::TarBz2 => "tar.bz2",
+            Self::TarXz => "tar.xz",
+            Self::TarZst => "tar.zst",
+            Self::SevenZip => "7z",
+        }
+    }
+
+    /// Whether the compression processor can create archives of this format.
+    pub(super) fn is_writable(&self) -> bool {
+        matches!(self, Self::Zip | Self::TarGz | Self::SevenZip)
+    }
+}
+
+/// Error returned when parsing an unknown [`ArchiveFormat`] name.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown archive format: {0}")]
+pub struct ArchiveFormatParseError(pub String);
+
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = ArchiveFormatParseError;
+
+    /// Parse a format name case-insensitively, accepting the serde names
+    /// (`targz`), file extensions (`tar.gz`) and short extensions (`tgz`).
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "zip" => Ok(Self::Zip),
+            "targz" | "tar.gz" | "tgz" => Ok(Self::TarGz),
+            "tarbz2" | "tar.bz2" | "tbz2" | "tbz" => Ok(Self::TarBz2),
+            "tarxz" | "tar.xz" | "txz" => Ok(Self::TarXz),
+            "tarzst" | "tar.zst" | "tzst" => Ok(Self::TarZst),
+            "sevenzip" | "7z" => Ok(Self::SevenZip),
+            _ => Err(ArchiveFormatParseError(s.to_string())),
         }
     }
 }
 
+impl TryFrom<&str> for ArchiveFormat {
+    type Error = ArchiveFormatParseError;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+fn unwritable_format_error(format: &ArchiveFormat) -> crate::Error {
+    crate::Error::PipelineError(format!(
+        "Creating {:?} archives is not supported; use zip, targz or 7z",
+        format
+    ))
+}
+
+/// Header bytes needed to tell every [`ArchiveFormat`] apart.
+pub(super) const ARCHIVE_SIGNATURE_LEN: usize = 6;
+
+/// Detect the archive format from the leading bytes of a file.
+///
+/// Returns `None` when the header carries none of the signatures listed on
+/// [`ArchiveFormat`]. The gzip, bzip2, xz and zstd signatures only say how the
+/// stream is compressed; callers that need to be sure it wraps a tar archive
+/// must still parse the tar headers.
+pub(super) fn detect_archive_format(header: &[u8]) -> Option<ArchiveFormat> {
+    // Local file header, or the end-of-central-directory record of an empty archive.
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        return Some(ArchiveFormat::Zip);
+    }
+    if header.starts_with(&[0x1f, 0x8b]) {
+        return Some(ArchiveFormat::TarGz);
+    }
+    if header.starts_with(b"BZh") {
+        return Some(ArchiveFormat::TarBz2);
+    }
+    if header.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) {
+        return Some(ArchiveFormat::TarXz);
+    }
+    if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Some(ArchiveFormat::TarZst);
+    }
+    if header.starts_with(&[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c]) {
+        return Some(ArchiveFormat::SevenZip);
+    }
+    None
+}
+
 /// Configuration for compression operations.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CompressionConfig {
-    /// Archive format (zip or tar.gz).
+    /// Archive format (zip, tar.gz or 7z).
     #[serde(default)]
     pub format: ArchiveFormat,
 
     /// Compression level (0-9, where 9 is maximum compression).
     /// 0 = no compression, 1 = fastest, 9 = best compression.
+    /// 7z archives use the LZMA2 preset of the same number; their writer has
+    /// no uncompressed method, so 0 is the fastest preset instead.
     #[serde(default = "default_compression_level")]
     pub compression_level: u8,
 
+    /// Named profile from the pipeline's `compression_profiles` to start from.
+    ///
+    /// Top-level fields of the profile replace the defaults, and fields set in
+    /// the job config replace those of the profile.
+    #[serde(default)]
+    pub profile: Option<String>,
+
     /// Output archive path. If not specified, uses the first output from ProcessorInput
     /// or generates one based on the first input filename.
     pub output_path: Option<String>,
@@ -72,1067 +197,6880 @@ pub struct CompressionConfig {
     /// If false, all files are placed at the root of the archive.
     #[serde(default)]
     pub preserve_paths: bool,
+
+    /// Whether to add the inputs to an existing ZIP archive at the output path
+    /// instead of replacing it.
+    ///
+    /// Inputs whose entry name already exists in the archive replace that entry
+    /// when `overwrite` is enabled, and are skipped otherwise.
+    #[serde(default)]
+    pub append: bool,
+
+    /// Whether an existing output archive counts as success when `overwrite`
+    /// is disabled: the archive is left untouched, listed as the output, and
+    /// every input is reported as skipped with reason `output_already_exists`.
+    #[serde(default)]
+    pub skip_if_exists: bool,
+
+    /// Whether to write every ZIP entry with ZIP64 extended size fields.
+    ///
+    /// ZIP64 is enabled automatically, without this flag, for entries large
+    /// enough to exceed the classic 4 GiB limit, and for all entries when the
+    /// archive would hold more than 65535 of them. Such archives need a reader
+    /// with ZIP64 support. This forces it for all entries, e.g. for inputs that
+    /// may still grow.
+    #[serde(default)]
+    pub force_zip64: bool,
+
+    /// Whether to record each input's modification time in its ZIP entry.
+    ///
+    /// ZIP stores local time with 2-second resolution. Inputs without a
+    /// readable modification time get the current time. When disabled, entries
+    /// carry the ZIP epoch (1980-01-01). tar.gz archives always record it.
+    #[serde(default = "default_true")]
+    pub store_timestamps: bool,
+
+    /// Whether to make tar.gz output friendly to rsync delta transfers.
+    ///
+    /// The gzip stream is reset at content-defined boundaries (like
+    /// `gzip --rsyncable`), so a local change in the input only alters the
+    /// compressed bytes around it, at the cost of a slightly larger archive.
+    /// Ignored for ZIP archives.
+    #[serde(default)]
+    pub rsyncable: bool,
+
+    /// Whether tar.gz and 7z archives compress all entries as one stream.
+    ///
+    /// Solid archives let the compressor reuse context across files, which
+    /// gives the best ratio for many small, similar inputs. Disabling it
+    /// writes each tar.gz entry as its own gzip member, concatenated in one
+    /// file, and each 7z entry as its own block: the archive grows slightly,
+    /// but an entry can be extracted without decompressing everything before
+    /// it. Ignored for ZIP archives, whose entries are always compressed
+    /// independently.
+    #[serde(default = "default_true")]
+    pub solid: bool,
+
+    /// Check that inputs are no longer being written before archiving them.
+    #[serde(default)]
+    pub stability_check: Option<StabilityCheck>,
+
+    /// Which resource usage statistics to record for the job.
+    #[serde(default)]
+    pub collect_resource_stats: ResourceStatsConfig,
+
+    /// Minimum time between progress snapshots, in milliseconds.
+    #[serde(default = "default_progress_interval_ms")]
+    pub progress_interval_ms: u64,
+
+    /// Also report progress before the interval elapsed once the percentage
+    /// moved by at least this much since the last snapshot.
+    #[serde(default)]
+    pub min_progress_delta_percent: Option<f32>,
+
+    /// Entries archived from memory instead of input files, written after the inputs.
+    ///
+    /// Names are normalized like preserved input paths and must not collide with
+    /// another entry of the job; existing-archive collisions follow the input rules.
+    #[serde(default)]
+    pub virtual_entries: Vec<VirtualEntry>,
+
+    /// ZIP archive comment, e.g. to record provenance shown by `unzip -z`.
+    ///
+    /// Supports the same placeholders as output paths; time placeholders use the
+    /// session start. Ignored for tar.gz archives.
+    #[serde(default)]
+    pub archive_comment: Option<String>,
+
+    /// Comments for individual ZIP entries, keyed by entry name in the archive.
+    /// Ignored for tar.gz archives.
+    #[serde(default)]
+    pub entry_comments: HashMap<String, String>,
+
+    /// Compression method overrides for ZIP entries, e.g. to store already
+    /// compressed media uncompressed.
+    ///
+    /// Patterns are globs matched against the entry name in the archive; the
+    /// first matching rule wins and unmatched entries follow `compression_level`.
+    /// Ignored for tar.gz archives.
+    #[serde(default)]
+    pub per_file_method: Option<Vec<FileMethodRule>>,
+
+    /// Retry transient read errors on input files, e.g. EIO from a network mount.
+    ///
+    /// The file is reopened and reading resumes where it failed. An input that
+    /// cannot be resumed fails the job.
+    #[serde(default)]
+    pub read_retry: Option<RetryPolicy>,
+
+    /// Whether to leave zero-byte input files, e.g. stubs of crash-recovered
+    /// sessions, out of the archive and report them as skipped.
+    #[serde(default = "default_true")]
+    pub skip_empty_files: bool,
+
+    /// Minimum estimated size reduction (0.0-1.0) for an entry to be compressed;
+    /// `0.05` stores entries that would shrink by less than 5%.
+    ///
+    /// The reduction is estimated by compressing the first `probe_bytes` of each
+    /// entry. ZIP decides per entry; tar.gz decides once for the whole archive.
+    /// Entries matched by `per_file_method` keep their method.
+    #[serde(default)]
+    pub min_compression_ratio: Option<f64>,
+
+    /// Bytes of each entry compressed to estimate its compression ratio.
+    #[serde(default = "default_probe_bytes")]
+    pub probe_bytes: u64,
+
+    /// Directory to build the archive in before moving it to the output path,
+    /// e.g. a local SSD when the output lives on a slow network mount.
+    ///
+    /// The archive is renamed into place, or copied when the directory is on
+    /// another filesystem. Defaults to the output directory.
+    #[serde(default)]
+    pub tmp_dir: Option<String>,
+
+    /// Read buffer for each input file, in bytes (at least 4 KiB).
+    ///
+    /// Larger buffers mean fewer system calls for big inputs.
+    #[serde(default = "default_io_buffer_bytes")]
+    pub read_buffer_bytes: Option<usize>,
+
+    /// Write buffer for the archive file, in bytes (at least 4 KiB).
+    ///
+    /// Raising it can significantly improve throughput when the output path
+    /// or `tmp_dir` is on a network-backed filesystem.
+    #[serde(default = "default_io_buffer_bytes")]
+    pub write_buffer_bytes: Option<usize>,
+
+    /// How inputs that are symbolic links are archived.
+    #[serde(default)]
+    pub symlink_handling: SymlinkHandling,
+
+    /// Largest archive the job may write, in bytes.
+    ///
+    /// The archive file is checked after each file is added; once it is over
+    /// the limit the job fails and the partial archive is removed. Bytes still
+    /// in the write buffer are not counted, so an archive can overshoot the
+    /// limit by up to `write_buffer_bytes` before the job fails.
+    #[serde(default)]
+    pub max_archive_size_bytes: Option<u64>,
 }
 
-fn default_true() -> bool {
-    true
+impl CompressionConfig {
+    fn read_buffer_size(&self) -> usize {
+        self.read_buffer_bytes.unwrap_or(DEFAULT_IO_BUFFER_BYTES)
+    }
+
+    fn write_buffer_size(&self) -> usize {
+        self.write_buffer_bytes.unwrap_or(DEFAULT_IO_BUFFER_BYTES)
+    }
 }
 
-const PROGRESS_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// How the compression processor treats inputs that are symbolic links.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkHandling {
+    /// Archive the file the link points to.
+    #[default]
+    Follow,
+    /// Store the link itself as a symlink entry. Only tar.gz archives can hold
+    /// links; ZIP archives fall back to `Follow`.
+    Preserve,
+    /// Leave links out of the archive and report them as skipped.
+    Skip,
+}
 
-struct CancelProgressReader<R> {
-    inner: R,
-    cancel: CancellationToken,
-    progress: ProgressReporter,
-    bytes_total: u64,
-    bytes_done: u64,
-    last_report_at: std::time::Instant,
-    file_index: usize,
-    file_count: usize,
-    current_file: String,
+/// Warning for `SymlinkHandling::Preserve` with a format that cannot store links.
+const SYMLINK_PRESERVE_ZIP_WARNING: &str =
+    "symlink_handling preserve only applies to tar.gz archives; symlinks are followed";
+
+/// Warning for a non-solid ZIP archive.
+const SOLID_ZIP_WARNING: &str = "solid only applies to tar.gz and 7z archives and is ignored";
+
+/// Warning for `rsyncable` with a format other than tar.gz.
+const RSYNCABLE_WARNING: &str = "rsyncable only applies to tar.gz archives and is ignored";
+
+/// Warning for `per_file_method` with a format other than ZIP.
+const PER_FILE_METHOD_WARNING: &str = "per_file_method only applies to ZIP archives and is ignored";
+
+/// Error for a job without inputs or virtual entries.
+const NO_INPUTS_ERROR: &str = "No input files specified for compression";
+
+/// Error for appending to an archive that is not a ZIP archive.
+fn append_unsupported_message(output_path: impl std::fmt::Display) -> String {
+    format!(
+        "Appending is only supported for ZIP archives: {}",
+        output_path
+    )
 }
 
-struct CompressionProgressContext {
-    cancel: CancellationToken,
-    progress: ProgressReporter,
-    bytes_total: u64,
-    bytes_done: u64,
-    file_index: usize,
-    file_count: usize,
-    current_file: String,
+/// Error for an existing output archive when `overwrite` is disabled.
+fn output_exists_message(output_path: impl std::fmt::Display) -> String {
+    format!(
+        "Output archive already exists and overwrite is disabled: {}",
+        output_path
+    )
 }
 
-impl<R> CancelProgressReader<R> {
-    fn new(inner: R, context: CompressionProgressContext) -> Self {
-        let CompressionProgressContext {
-            cancel,
-            progress,
-            bytes_total,
-            bytes_done,
-            file_index,
-            file_count,
-            current_file,
-        } = context;
+/// Error for an input path that does not exist.
+fn missing_input_message(input_path: impl std::fmt::Display) -> String {
+    format!("Input file does not exist: {}", input_path)
+}
+
+/// Default read and write buffer size for archive I/O.
+const DEFAULT_IO_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Smallest accepted read or write buffer size.
+const MIN_IO_BUFFER_BYTES: usize = 4 * 1024;
+
+fn default_io_buffer_bytes() -> Option<usize> {
+    Some(DEFAULT_IO_BUFFER_BYTES)
+}
+
+fn default_probe_bytes() -> u64 {
+    64 * 1024
+}
+
+fn default_read_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_read_retry_backoff_ms() -> u64 {
+    1000
+}
+
+/// How often a failing input read is retried.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts for a failing read, including the first, before the input fails.
+    #[serde(default = "default_read_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before reopening the file, in milliseconds.
+    #[serde(default = "default_read_retry_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
         Self {
-            inner,
-            cancel,
-            progress,
-            bytes_total,
-            bytes_done,
-            last_report_at: std::time::Instant::now(),
-            file_index,
-            file_count,
-            current_file,
+            max_attempts: default_read_retry_max_attempts(),
+            backoff_ms: default_read_retry_backoff_ms(),
         }
     }
+}
 
-    fn maybe_report(&mut self) {
-        if self.last_report_at.elapsed() < PROGRESS_REPORT_INTERVAL {
-            return;
-        }
-        self.last_report_at = std::time::Instant::now();
-
-        let percent = if self.bytes_total == 0 {
-            None
-        } else {
-            Some(((self.bytes_done as f64 / self.bytes_total as f64) * 100.0) as f32)
-        };
+/// Compression method selected for ZIP entries matching a glob pattern.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct FileMethodRule {
+    /// Glob pattern matched against the entry name, e.g. `*.mp4`.
+    pub pattern: String,
+    /// Method used for matching entries.
+    pub method: PerFileMethod,
+}
 
-        let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
-        snapshot.percent = percent;
-        snapshot.bytes_done = Some(self.bytes_done);
-        snapshot.bytes_total = Some(self.bytes_total);
-        snapshot.raw = serde_json::json!({
-            "file_index": self.file_index,
-            "file_count": self.file_count,
-            "file": self.current_file,
-        });
-        self.progress.report(snapshot);
-    }
+/// ZIP compression method for individual entries.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PerFileMethod {
+    /// No compression.
+    Stored,
+    /// Deflate at the configured compression level.
+    Deflated,
+    /// Bzip2; not available in builds without zip's bzip2 support.
+    Bzip2,
+    /// Zstandard; not available in builds without zip's zstd support.
+    Zstd,
 }
 
-impl<R: Read> Read for CancelProgressReader<R> {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        if self.cancel.is_cancelled() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Interrupted,
-                "compression cancelled",
-            ));
-        }
-        let n = self.inner.read(buf)?;
-        if n > 0 {
-            self.bytes_done = self.bytes_done.saturating_add(n as u64);
-            self.maybe_report();
+impl PerFileMethod {
+    /// ZIP writer options for this method.
+    fn zip_options(self, compression_level: u8) -> Result<FullFileOptions<'static>> {
+        match self {
+            Self::Stored => {
+                Ok(FullFileOptions::default().compression_method(zip::CompressionMethod::Stored))
+            }
+            Self::Deflated => Ok(FullFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level((compression_level > 0).then_some(compression_level as i64))),
+            Self::Bzip2 | Self::Zstd => Err(crate::Error::PipelineError(format!(
+                "Compression method {:?} is not supported by this build",
+                self
+            ))),
         }
-        Ok(n)
     }
 }
 
-struct CancelOnDrop {
-    token: CancellationToken,
-    armed: bool,
+/// Decides from a compressed sample whether entries are worth compressing.
+#[derive(Debug, Clone, Copy)]
+struct CompressionProbe {
+    min_ratio: f64,
+    probe_bytes: u64,
+    level: u32,
 }
 
-impl CancelOnDrop {
-    fn new(token: CancellationToken) -> Self {
-        Self { token, armed: true }
+impl CompressionProbe {
+    /// Probe configured by `config`, if entries would be compressed at all.
+    fn from_config(config: &CompressionConfig) -> Option<Self> {
+        let min_ratio = config.min_compression_ratio?;
+        (config.compression_level > 0 && config.probe_bytes > 0).then_some(Self {
+            min_ratio,
+            probe_bytes: config.probe_bytes,
+            level: u32::from(config.compression_level),
+        })
     }
 
-    fn disarm(&mut self) {
-        self.armed = false;
+    /// Deflate the first `probe_bytes` of `reader`, returning the sample and
+    /// compressed sizes.
+    fn sample(&self, reader: impl Read) -> std::io::Result<(u64, u64)> {
+        let mut sample = Vec::new();
+        reader.take(self.probe_bytes).read_to_end(&mut sample)?;
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), Compression::new(self.level));
+        encoder.write_all(&sample)?;
+        let compressed = encoder.finish()?;
+        Ok((sample.len() as u64, compressed.len() as u64))
     }
-}
 
-impl Drop for CancelOnDrop {
-    fn drop(&mut self) {
-        if self.armed {
-            self.token.cancel();
-        }
+    /// Whether samples totalling `raw` bytes that compressed to `compressed`
+    /// bytes fall short of the minimum reduction.
+    fn below_threshold(&self, raw: u64, compressed: u64) -> bool {
+        raw > 0 && 1.0 - (compressed as f64 / raw as f64) < self.min_ratio
     }
-}
 
-fn archive_entry_name(input_path: &str, preserve_paths: bool) -> Result<String> {
-    let path = Path::new(input_path);
-    if !preserve_paths {
-        let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+    /// Sample `entry`; `None` when its data can only be read once.
+    fn sample_entry(&self, entry: &EntryPlan) -> Result<Option<(u64, u64)>> {
+        if matches!(entry.virtual_source, Some(VirtualEntrySource::Reader(_))) {
+            return Ok(None);
+        }
+        let reader = entry.open(None, DEFAULT_IO_BUFFER_BYTES)?;
+        self.sample(reader).map(Some).map_err(|e| {
             crate::Error::PipelineError(format!(
-                "Invalid input filename (missing file_name): {}",
-                input_path
+                "Failed to sample {} for compression: {}",
+                entry.input_path, e
             ))
-        })?;
-        return Ok(name.to_string());
+        })
     }
 
-    let mut parts: Vec<String> = Vec::new();
-    for comp in path.components() {
-        match comp {
-            std::path::Component::Prefix(_) | std::path::Component::RootDir => {}
-            std::path::Component::CurDir => {}
-            std::path::Component::ParentDir => {}
-            std::path::Component::Normal(s) => {
-                parts.push(s.to_string_lossy().to_string());
-            }
-        }
+    /// Whether `entry` should be stored instead of compressed.
+    fn is_incompressible(&self, entry: &EntryPlan) -> Result<bool> {
+        Ok(self
+            .sample_entry(entry)?
+            .is_some_and(|(raw, compressed)| self.below_threshold(raw, compressed)))
+    }
+}
+
+/// Compiled `per_file_method` rules with the ZIP options of each.
+struct FileMethodRules {
+    rules: Vec<(GlobMatcher, FullFileOptions<'static>)>,
+}
+
+impl FileMethodRules {
+    fn compile(rules: Option<&[FileMethodRule]>, compression_level: u8) -> Result<Self> {
+        let rules = rules
+            .unwrap_or_default()
+            .iter()
+            .map(|rule| {
+                let matcher = Glob::new(&rule.pattern)
+                    .map_err(|e| {
+                        crate::Error::PipelineError(format!(
+                            "Invalid per_file_method pattern {}: {}",
+                            rule.pattern, e
+                        ))
+                    })?
+                    .compile_matcher();
+                Ok((matcher, rule.method.zip_options(compression_level)?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
     }
 
-    if parts.is_empty() {
-        return Err(crate::Error::PipelineError(format!(
-            "Invalid input path for archive entry: {}",
-            input_path
-        )));
+    /// Options of the first rule matching `archive_name`.
+    fn options_for(&self, archive_name: &str) -> Option<&FullFileOptions<'static>> {
+        self.rules
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(archive_name))
+            .map(|(_, options)| options)
     }
-    Ok(parts.join("/"))
 }
 
-impl Default for CompressionConfig {
-    fn default() -> Self {
+/// Longest comment the ZIP format can store, in bytes.
+const ZIP_COMMENT_MAX_LEN: usize = u16::MAX as usize;
+
+/// Truncate `comment` to the ZIP comment limit at a character boundary.
+///
+/// Returns whether the comment was truncated.
+fn truncate_zip_comment(comment: &mut String) -> bool {
+    if comment.len() <= ZIP_COMMENT_MAX_LEN {
+        return false;
+    }
+    let mut end = ZIP_COMMENT_MAX_LEN;
+    while !comment.is_char_boundary(end) {
+        end -= 1;
+    }
+    comment.truncate(end);
+    true
+}
+
+/// A reader consumed once while the archive is written.
+#[derive(Clone)]
+pub struct VirtualReader {
+    size: u64,
+    reader: Arc<Mutex<Option<Box<dyn Read + Send>>>>,
+}
+
+impl VirtualReader {
+    /// Wrap a reader that yields exactly `size` bytes.
+    pub fn new(size: u64, reader: impl Read + Send + 'static) -> Self {
         Self {
-            format: ArchiveFormat::Zip,
-            compression_level: default_compression_level(),
-            output_path: None,
-            overwrite: true,
-            preserve_paths: false,
+            size,
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
         }
     }
-}
 
-/// Processor for creating compressed archives.
-///
-/// Supports creating ZIP and tar.gz archives from one or more input files.
-/// - ZIP archives use the `zip` crate
-/// - tar.gz archives use `flate2` and `tar` crates
-/// - Multiple input files are bundled into a single archive
-pub struct CompressionProcessor;
+    /// Number of bytes the reader yields.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
 
-impl CompressionProcessor {
-    /// Create a new compression processor.
-    pub fn new() -> Self {
-        Self
+    fn take(&self) -> Option<Box<dyn Read + Send>> {
+        self.reader.lock().ok()?.take()
     }
+}
 
-    /// Determine the output archive path based on config and input.
-    fn determine_output_path(
-        &self,
-        inputs: &[String],
-        config: &CompressionConfig,
-        processor_input: &ProcessorInput,
-    ) -> String {
-        // Priority: config.output_path > processor_input.outputs > generated from first input
-        if let Some(ref output) = config.output_path {
-            return output.clone();
+impl std::fmt::Debug for VirtualReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualReader")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Content of a [`VirtualEntry`].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VirtualEntrySource {
+    /// Bytes held in memory.
+    Bytes(Arc<[u8]>),
+    /// A reader; only available through
+    /// [`CompressionProcessor::process_with_virtual_entries`].
+    #[serde(skip)]
+    Reader(VirtualReader),
+}
+
+impl VirtualEntrySource {
+    fn size(&self) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes.len() as u64,
+            Self::Reader(reader) => reader.size(),
         }
+    }
+}
 
-        if let Some(output) = processor_input.outputs.first() {
-            return output.clone();
+/// An archive entry that is not backed by an input file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VirtualEntry {
+    /// Name of the entry inside the archive.
+    pub name: String,
+    #[serde(flatten)]
+    pub source: VirtualEntrySource,
+}
+
+impl VirtualEntry {
+    /// Create an entry holding `bytes`.
+    pub fn from_bytes(name: impl Into<String>, bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            name: name.into(),
+            source: VirtualEntrySource::Bytes(bytes.into()),
         }
+    }
 
-        // Generate output path from first input path
-        if let Some(first_input) = inputs.first() {
-            let input = Path::new(first_input);
-            let stem = input
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("archive");
-            let parent = input.parent().unwrap_or(Path::new("."));
-
-            return parent
-                .join(format!("{}.{}", stem, config.format.extension()))
-                .to_string_lossy()
-                .to_string();
+    /// Create an entry read from `reader`, which must yield exactly `size` bytes.
+    pub fn from_reader(
+        name: impl Into<String>,
+        size: u64,
+        reader: impl Read + Send + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            source: VirtualEntrySource::Reader(VirtualReader::new(size, reader)),
         }
-
-        // Fallback
-        format!("archive.{}", config.format.extension())
     }
+}
 
-    /// Create a ZIP archive from the input files.
-    fn create_zip_archive(
-        &self,
-        inputs: &[String],
-        output_path: &Path,
-        config: &CompressionConfig,
-        progress: ProgressReporter,
-        cancel: CancellationToken,
-    ) -> Result<(u64, u64)> {
-        let file = File::create(output_path).map_err(|e| {
-            crate::Error::PipelineError(format!("Failed to create ZIP archive: {}", e))
-        })?;
-
-        let file = BufWriter::new(file);
-        let mut zip = ZipWriter::new(file);
+fn default_progress_interval_ms() -> u64 {
+    250
+}
 
-        // Map compression level (0-9) to zip compression method
-        let options = if config.compression_level == 0 {
-            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored)
-        } else {
-            // Deflate compression with level
-            SimpleFileOptions::default()
-                .compression_method(zip::CompressionMethod::Deflated)
-                .compression_level(Some(config.compression_level as i64))
-        };
+/// Resource usage statistics recorded by the compression processor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceStatsConfig {
+    /// Record the CPU time spent by the archiving worker.
+    #[serde(default = "default_true")]
+    pub cpu_time: bool,
+    /// Sample the process resident set size while archiving (Linux only).
+    #[serde(default)]
+    pub peak_rss: bool,
+}
 
-        let mut total_input_size: u64 = 0;
-        for input_path in inputs {
-            let metadata = std::fs::metadata(input_path).map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    crate::Error::PipelineError(format!(
-                        "Input file does not exist: {}",
-                        input_path
-                    ))
-                } else {
-                    crate::Error::PipelineError(format!(
-                        "Failed to get input metadata {}: {}",
-                        input_path, e
-                    ))
-                }
-            })?;
-            total_input_size = total_input_size.saturating_add(metadata.len());
+impl Default for ResourceStatsConfig {
+    fn default() -> Self {
+        Self {
+            cpu_time: true,
+            peak_rss: false,
         }
+    }
+}
 
-        let mut bytes_done: u64 = 0;
+fn default_true() -> bool {
+    true
+}
 
-        for (idx, input_path) in inputs.iter().enumerate() {
-            if cancel.is_cancelled() {
-                return Err(crate::Error::PipelineError(
-                    "Compression cancelled".to_string(),
-                ));
-            }
+fn default_stability_interval_ms() -> u64 {
+    1000
+}
 
-            let archive_name = archive_entry_name(input_path, config.preserve_paths)?;
-            debug!("Adding to ZIP: {} as {}", input_path, archive_name);
+fn default_stability_wait_timeout_secs() -> u64 {
+    60
+}
 
-            let file = File::open(input_path).map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    crate::Error::PipelineError(format!(
-                        "Input file does not exist: {}",
-                        input_path
-                    ))
-                } else {
-                    crate::Error::PipelineError(format!(
-                        "Failed to open input file {}: {}",
-                        input_path, e
-                    ))
-                }
-            })?;
+/// What to do with inputs that are still being written.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnstableInputPolicy {
+    /// Re-check until the inputs are stable, failing after `wait_timeout_secs`.
+    #[default]
+    Wait,
+    /// Leave unstable inputs out of the archive and report them as skipped.
+    Skip,
+    /// Fail the job.
+    Fail,
+}
 
-            let mut reader = CancelProgressReader::new(
-                BufReader::new(file),
-                CompressionProgressContext {
-                    cancel: cancel.clone(),
-                    progress: progress.clone(),
-                    bytes_total: total_input_size,
-                    bytes_done,
-                    file_index: idx.saturating_add(1),
-                    file_count: inputs.len(),
-                    current_file: input_path.clone(),
-                },
-            );
+/// Detects inputs that are still growing, e.g. a segment that is still flushing.
+///
+/// Each input is stat'ed twice, `interval_ms` apart; it is unstable when its size
+/// or modification time changed in between, or when it was modified less than
+/// `min_mtime_age_secs` ago.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StabilityCheck {
+    /// Delay between the two samples, in milliseconds.
+    #[serde(default = "default_stability_interval_ms")]
+    pub interval_ms: u64,
+
+    /// Minimum time since the last modification, in seconds.
+    #[serde(default)]
+    pub min_mtime_age_secs: Option<u64>,
 
-            // Write to archive
-            zip.start_file(&archive_name, options).map_err(|e| {
-                crate::Error::PipelineError(format!("Failed to start ZIP entry: {}", e))
-            })?;
+    /// What to do with unstable inputs.
+    #[serde(default)]
+    pub policy: UnstableInputPolicy,
 
-            std::io::copy(&mut reader, &mut zip).map_err(|e| {
-                crate::Error::PipelineError(format!("Failed to write ZIP entry: {}", e))
-            })?;
-            bytes_done = reader.bytes_done;
-        }
+    /// Maximum time to wait for inputs to become stable with the `wait` policy.
+    #[serde(default = "default_stability_wait_timeout_secs")]
+    pub wait_timeout_secs: u64,
+}
 
-        zip.finish().map_err(|e| {
-            crate::Error::PipelineError(format!("Failed to finalize ZIP archive: {}", e))
-        })?;
+impl Default for StabilityCheck {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_stability_interval_ms(),
+            min_mtime_age_secs: None,
+            policy: UnstableInputPolicy::default(),
+            wait_timeout_secs: default_stability_wait_timeout_secs(),
+        }
+    }
+}
 
-        // Get output file size
-        let output_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+/// Result of the input stability check.
+#[derive(Debug, Default)]
+struct StabilityOutcome {
+    /// Inputs detected as unstable at least once.
+    unstable_inputs: Vec<String>,
+    /// Unstable inputs left out of the archive, with the reason.
+    skipped_inputs: Vec<(String, String)>,
+}
 
-        Ok((total_input_size, output_size))
+/// Sleep for `duration`, returning early with an error when cancelled.
+fn sleep_unless_cancelled(duration: std::time::Duration, cancel: &CancellationToken) -> Result<()> {
+    const SLICE: std::time::Duration = std::time::Duration::from_millis(50);
+    let deadline = std::time::Instant::now() + duration;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(crate::Error::PipelineError(
+                "Compression cancelled".to_string(),
+            ));
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        std::thread::sleep(remaining.min(SLICE));
     }
+}
 
-    /// Create a tar.gz archive from the input files.
-    fn create_tar_gz_archive(
-        &self,
-        inputs: &[String],
-        output_path: &Path,
-        config: &CompressionConfig,
-        progress: ProgressReporter,
-        cancel: CancellationToken,
-    ) -> Result<(u64, u64)> {
-        let file = File::create(output_path).map_err(|e| {
-            crate::Error::PipelineError(format!("Failed to create tar.gz archive: {}", e))
-        })?;
-
-        // Map compression level (0-9) to flate2 Compression
-        let compression = match config.compression_level {
-            0 => Compression::none(),
-            1 => Compression::fast(),
-            9 => Compression::best(),
-            level => Compression::new(level as u32),
-        };
+/// Return the inputs among `inputs` that are still being written.
+fn find_unstable_inputs(
+    inputs: &[String],
+    check: &StabilityCheck,
+    cancel: &CancellationToken,
+) -> Result<Vec<String>> {
+    let first = inputs
+        .iter()
+        .map(|path| stat_input(path))
+        .collect::<Result<Vec<_>>>()?;
+    sleep_unless_cancelled(std::time::Duration::from_millis(check.interval_ms), cancel)?;
+
+    let min_age = check.min_mtime_age_secs.map(std::time::Duration::from_secs);
+    let now = std::time::SystemTime::now();
+    let mut unstable = Vec::new();
+    for (path, before) in inputs.iter().zip(first) {
+        let after = stat_input(path)?;
+        let changed = after.size != before.size || after.modified != before.modified;
+        // A modification time in the future counts as recent.
+        let too_recent = min_age.is_some_and(|min_age| {
+            after.modified.is_some_and(|modified| {
+                !now.duration_since(modified).is_ok_and(|age| age >= min_age)
+            })
+        });
+        if changed || too_recent {
+            unstable.push(path.clone());
+        }
+    }
+    Ok(unstable)
+}
 
-        let encoder = GzEncoder::new(file, compression);
-        let mut tar = TarBuilder::new(encoder);
+/// Apply the stability check to `inputs` according to its policy.
+fn check_input_stability(
+    inputs: &[String],
+    check: &StabilityCheck,
+    cancel: &CancellationToken,
+) -> Result<StabilityOutcome> {
+    let started = std::time::Instant::now();
+    let wait_timeout = std::time::Duration::from_secs(check.wait_timeout_secs);
+    let mut outcome = StabilityOutcome::default();
+    let mut pending = inputs.to_vec();
+
+    loop {
+        let unstable = find_unstable_inputs(&pending, check, cancel)?;
+        for path in &unstable {
+            if !outcome.unstable_inputs.contains(path) {
+                outcome.unstable_inputs.push(path.clone());
+            }
+        }
+        if unstable.is_empty() {
+            return Ok(outcome);
+        }
 
-        let mut total_input_size: u64 = 0;
-        for input_path in inputs {
-            let metadata = std::fs::metadata(input_path).map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    crate::Error::PipelineError(format!(
-                        "Input file does not exist: {}",
-                        input_path
-                    ))
-                } else {
-                    crate::Error::PipelineError(format!(
-                        "Failed to get input metadata {}: {}",
-                        input_path, e
-                    ))
+        match check.policy {
+            UnstableInputPolicy::Skip => {
+                for path in unstable {
+                    debug!("Skipping {}: input is still being written", path);
+                    outcome
+                        .skipped_inputs
+                        .push((path, "input is still being written".to_string()));
                 }
-            })?;
-            total_input_size = total_input_size.saturating_add(metadata.len());
+                return Ok(outcome);
+            }
+            UnstableInputPolicy::Fail => {
+                return Err(crate::Error::PipelineError(format!(
+                    "Inputs are still being written: {}",
+                    unstable.join(", ")
+                )));
+            }
+            UnstableInputPolicy::Wait => {
+                if started.elapsed() >= wait_timeout {
+                    return Err(crate::Error::PipelineError(format!(
+                        "Inputs did not stop changing within {}s: {}",
+                        check.wait_timeout_secs,
+                        unstable.join(", ")
+                    )));
+                }
+                debug!("Waiting for {} inputs to stop changing", unstable.len());
+                pending = unstable;
+            }
         }
+    }
+}
 
-        let mut bytes_done: u64 = 0;
+/// Entries at or above this size are written with ZIP64 extended size fields.
+///
+/// The classic ZIP limit is `u32::MAX` bytes; the threshold leaves headroom for
+/// entries whose compressed size ends up slightly larger than the input.
+const ZIP64_SIZE_THRESHOLD: u64 = u32::MAX as u64 - 64 * 1024 * 1024;
 
-        for (idx, input_path) in inputs.iter().enumerate() {
-            if cancel.is_cancelled() {
-                return Err(crate::Error::PipelineError(
-                    "Compression cancelled".to_string(),
-                ));
-            }
+/// Largest number of entries the classic (non-ZIP64) central directory can hold.
+const ZIP_CLASSIC_MAX_ENTRIES: usize = u16::MAX as usize;
 
-            let archive_name = archive_entry_name(input_path, config.preserve_paths)?;
-            debug!("Adding to tar.gz: {} as {}", input_path, archive_name);
+/// Whether a ZIP entry of `size` bytes must be written with ZIP64 size fields.
+fn needs_zip64(size: u64, force_zip64: bool) -> bool {
+    force_zip64 || size >= ZIP64_SIZE_THRESHOLD
+}
 
-            let mut file = File::open(input_path).map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    crate::Error::PipelineError(format!(
-                        "Input file does not exist: {}",
-                        input_path
-                    ))
-                } else {
-                    crate::Error::PipelineError(format!(
-                        "Failed to open input file {}: {}",
-                        input_path, e
-                    ))
-                }
-            })?;
+/// Whether every entry of an archive holding `entry_count` entries is written
+/// with ZIP64 fields.
+fn zip64_for_all_entries(entry_count: usize, force_zip64: bool) -> bool {
+    force_zip64 || entry_count > ZIP_CLASSIC_MAX_ENTRIES
+}
 
-            let metadata = file.metadata().map_err(|e| {
-                crate::Error::PipelineError(format!("Failed to get file metadata: {}", e))
-            })?;
+/// How often the resident set size is sampled while archiving.
+const RSS_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Clock ticks per second used by `/proc/<pid>/stat` CPU time fields.
+#[cfg(target_os = "linux")]
+const PROC_CLOCK_TICKS_PER_SEC: u64 = 100;
+
+/// CPU time consumed so far by the calling thread.
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Option<std::time::Duration> {
+    // `schedstat` reports nanoseconds on the CPU; it is absent on kernels
+    // built without scheduler statistics, so fall back to the tick counters.
+    if let Ok(schedstat) = std::fs::read_to_string("/proc/thread-self/schedstat")
+        && let Some(nanos) = schedstat
+            .split_whitespace()
+            .next()
+            .and_then(|field| field.parse().ok())
+    {
+        return Some(std::time::Duration::from_nanos(nanos));
+    }
 
-            let mut header = tar::Header::new_gnu();
-            header.set_size(metadata.len());
-            header.set_mode(0o644);
-            if let Ok(modified) = metadata.modified()
-                && let Ok(duration) = modified.duration_since(std::time::SystemTime::UNIX_EPOCH)
-            {
-                header.set_mtime(duration.as_secs());
-            }
+    let stat = std::fs::read_to_string("/proc/thread-self/stat").ok()?;
+    // Fields after the parenthesized command name start at field 3 (state);
+    // utime and stime are fields 14 and 15.
+    let mut fields = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(std::time::Duration::from_millis(
+        (utime + stime) * 1000 / PROC_CLOCK_TICKS_PER_SEC,
+    ))
+}
 
-            header.set_cksum();
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Option<std::time::Duration> {
+    None
+}
 
-            let reader = CancelProgressReader::new(
-                BufReader::new(&mut file),
-                CompressionProgressContext {
-                    cancel: cancel.clone(),
-                    progress: progress.clone(),
-                    bytes_total: total_input_size,
-                    bytes_done,
-                    file_index: idx.saturating_add(1),
-                    file_count: inputs.len(),
-                    current_file: input_path.clone(),
-                },
-            );
+/// Current resident set size of the process in bytes.
+#[cfg(target_os = "linux")]
+fn current_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
 
-            tar.append_data(&mut header, Path::new(&archive_name), reader)
-                .map_err(|e| {
-                    crate::Error::PipelineError(format!("Failed to add file to tar archive: {}", e))
-                })?;
+#[cfg(not(target_os = "linux"))]
+fn current_rss_bytes() -> Option<u64> {
+    None
+}
 
-            bytes_done = bytes_done.saturating_add(metadata.len());
-        }
+/// Samples the resident set size on a background thread until finished.
+struct RssSampler {
+    stop: std::sync::mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<u64>,
+}
 
-        // Finish the tar archive and get the gzip encoder back
-        let encoder = tar.into_inner().map_err(|e| {
-            crate::Error::PipelineError(format!("Failed to finalize tar archive: {}", e))
-        })?;
+impl RssSampler {
+    /// Start sampling, or `None` when the platform does not expose the RSS.
+    fn start() -> Option<Self> {
+        let initial = current_rss_bytes()?;
+        let (stop, stop_rx) = std::sync::mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            let mut peak = initial;
+            // Stops on an explicit signal or when the sender is dropped.
+            while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+                stop_rx.recv_timeout(RSS_SAMPLE_INTERVAL)
+            {
+                peak = peak.max(current_rss_bytes().unwrap_or(0));
+            }
+            peak.max(current_rss_bytes().unwrap_or(0))
+        });
+        Some(Self { stop, handle })
+    }
 
-        // Finish gzip compression
-        encoder.finish().map_err(|e| {
-            crate::Error::PipelineError(format!("Failed to finalize gzip compression: {}", e))
-        })?;
+    fn finish(self) -> Option<u64> {
+        let _ = self.stop.send(());
+        self.handle.join().ok()
+    }
+}
 
-        // Get output file size
-        let output_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+/// Resource usage of a compression job.
+#[derive(Debug, Clone, Copy, Default)]
+struct ResourceStats {
+    cpu_time_secs: Option<f64>,
+    peak_rss_bytes: Option<u64>,
+}
 
-        Ok((total_input_size, output_size))
-    }
+/// Measures the resources used by the archiving worker thread.
+struct ResourceTracker {
+    started_at: std::time::Instant,
+    cpu_time_at_start: Option<Option<std::time::Duration>>,
+    rss_sampler: Option<RssSampler>,
+}
 
-    /// Calculate compression ratio as a percentage.
-    fn calculate_compression_ratio(input_size: u64, output_size: u64) -> f64 {
-        if input_size == 0 {
-            return 0.0;
+impl ResourceTracker {
+    /// Start measuring on the current thread.
+    fn start(config: &ResourceStatsConfig) -> Self {
+        Self {
+            started_at: std::time::Instant::now(),
+            cpu_time_at_start: config.cpu_time.then(thread_cpu_time),
+            rss_sampler: if config.peak_rss {
+                RssSampler::start()
+            } else {
+                None
+            },
         }
-        (1.0 - (output_size as f64 / input_size as f64)) * 100.0
     }
 
-    fn clamp_compression_level(level: u8) -> Result<u8> {
-        if level <= 9 {
-            Ok(level)
-        } else {
-            Err(crate::Error::PipelineError(format!(
-                "Invalid compression_level {} (expected 0..=9)",
-                level
-            )))
+    /// Stop measuring. Must run on the thread that called [`Self::start`].
+    ///
+    /// CPU time falls back to the elapsed wall time where no thread CPU clock
+    /// is available.
+    fn finish(self) -> ResourceStats {
+        let cpu_time = self
+            .cpu_time_at_start
+            .map(|at_start| match (at_start, thread_cpu_time()) {
+                (Some(at_start), Some(now)) => now.saturating_sub(at_start),
+                _ => self.started_at.elapsed(),
+            });
+        ResourceStats {
+            cpu_time_secs: cpu_time.map(|duration| duration.as_secs_f64()),
+            peak_rss_bytes: self.rss_sampler.and_then(RssSampler::finish),
         }
     }
 }
 
-impl Default for CompressionProcessor {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Per-entry details recorded in [`CompressionResultMetadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionEntryMetadata {
+    /// Input file the entry was written from.
+    pub input_path: String,
+    /// Name of the entry inside the archive.
+    pub archive_name: String,
+    /// Uncompressed size in bytes.
+    pub size_bytes: u64,
+    /// CRC-32 of the uncompressed entry data.
+    pub crc32: u32,
+    /// Whether the entry was written from a [`VirtualEntry`], whose name is
+    /// recorded as `input_path`.
+    #[serde(default)]
+    pub is_virtual: bool,
 }
 
-#[async_trait]
-impl Processor for CompressionProcessor {
-    fn processor_type(&self) -> ProcessorType {
-        ProcessorType::Cpu
-    }
+/// Compression statistics of one entry, recorded in [`CompressionResultMetadata`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCompressionStat {
+    /// Name of the entry inside the archive.
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub input_size_bytes: u64,
+    /// Compressed size in bytes. For tar.gz and solid 7z blocks this is the
+    /// part of the shared stream emitted while the entry was written, so it
+    /// is approximate.
+    pub output_size_bytes: u64,
+    pub compression_ratio_percent: f64,
+    /// Time spent reading and compressing the entry.
+    pub duration_ms: u64,
+    /// Uncompressed bytes read per second while the entry was written, in
+    /// MiB/s; a low value points at a slow input, e.g. on a network mount.
+    #[serde(default)]
+    pub per_file_speed_mbs: f64,
+}
 
-    fn job_types(&self) -> Vec<&'static str> {
-        vec!["compress", "archive"]
+impl FileCompressionStat {
+    fn new(
+        name: &str,
+        input_size_bytes: u64,
+        output_size_bytes: u64,
+        elapsed: std::time::Duration,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            input_size_bytes,
+            output_size_bytes,
+            compression_ratio_percent: CompressionProcessor::calculate_compression_ratio(
+                input_size_bytes,
+                output_size_bytes,
+            ),
+            duration_ms: elapsed.as_millis() as u64,
+            per_file_speed_mbs: speed_mbs(input_size_bytes, elapsed),
+        }
     }
+}
 
-    fn name(&self) -> &'static str {
-        "CompressionProcessor"
+/// Rate of `bytes` over `elapsed` in MiB/s, `0.0` when no time elapsed.
+fn speed_mbs(bytes: u64, elapsed: std::time::Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        bytes as f64 / secs / (1024.0 * 1024.0)
+    } else {
+        0.0
     }
+}
 
-    /// Indicates this processor supports multiple inputs (batch processing).
-    fn supports_batch_input(&self) -> bool {
-        true
+/// Metadata recorded in `ProcessorOutput::metadata` by the compression processor.
+///
+/// The processor serializes this struct to JSON; consumers should read it back
+/// with [`CompressionResultMetadata::from_output`] instead of parsing keys by hand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressionResultMetadata {
+    /// Format of the written archive.
+    pub format: ArchiveFormat,
+    /// Effective compression level (0-9).
+    pub compression_level: u8,
+    /// All input files of the job, including skipped ones.
+    pub input_files: Vec<String>,
+    /// Number of inputs, not counting empty files left out by `skip_empty_files`.
+    pub input_count: usize,
+    /// Entries written by this job, in archive order.
+    pub entries: Vec<CompressionEntryMetadata>,
+    pub total_input_size_bytes: u64,
+    pub output_size_bytes: u64,
+    pub compression_ratio_percent: f64,
+    /// Whether the inputs were added to an existing archive.
+    pub appended_to_existing: bool,
+    /// Existing entries replaced by an input with the same name.
+    pub replaced_entries: Vec<String>,
+    pub skipped_count: usize,
+    pub failed_count: usize,
+    /// Number of entries written with ZIP64 extended size fields.
+    pub zip64_entries: usize,
+    /// Whether the tar.gz stream was written in rsyncable mode.
+    pub rsyncable: bool,
+    /// Inputs detected as still being written by the stability check.
+    #[serde(default)]
+    pub unstable_inputs: Vec<String>,
+    /// CPU time spent by the archiving worker, when collected.
+    #[serde(default)]
+    pub cpu_time_secs: Option<f64>,
+    /// Peak resident set size of the process while archiving, when sampled.
+    #[serde(default)]
+    pub peak_rss_bytes: Option<u64>,
+    /// Number of input reads retried after a transient error.
+    #[serde(default)]
+    pub read_retries: usize,
+    /// Entries stored uncompressed because they were below `min_compression_ratio`.
+    #[serde(default)]
+    pub skipped_compression_entries: usize,
+    /// Compression statistics of each written entry, in archive order.
+    #[serde(default)]
+    pub per_file_stats: Vec<FileCompressionStat>,
+    /// Input bytes archived per second over the whole job, in MiB/s.
+    #[serde(default)]
+    pub throughput_mbs: f64,
+}
+
+impl CompressionResultMetadata {
+    /// Read the metadata recorded by the compression processor in `output`.
+    pub fn from_output(output: &ProcessorOutput) -> Result<Self> {
+        let raw = output.metadata.as_deref().ok_or_else(|| {
+            crate::Error::PipelineError("Processor output has no compression metadata".to_string())
+        })?;
+        serde_json::from_str(raw).map_err(|e| {
+            crate::Error::PipelineError(format!("Invalid compression metadata: {}", e))
+        })
     }
 
-    async fn process(
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to serialize compression metadata: {}", e))
+        })
+    }
+}
+
+/// When progress snapshots are emitted.
+#[derive(Debug, Clone, Copy)]
+struct ProgressThrottle {
+    interval: std::time::Duration,
+    /// Ignored unless positive.
+    min_delta_percent: Option<f32>,
+}
+
+impl ProgressThrottle {
+    fn from_config(config: &CompressionConfig) -> Self {
+        Self {
+            interval: std::time::Duration::from_millis(config.progress_interval_ms),
+            min_delta_percent: config
+                .min_progress_delta_percent
+                .filter(|delta| *delta > 0.0),
+        }
+    }
+
+    /// Whether a snapshot is due, given the time and percentage of the last one.
+    fn should_report(
         &self,
-        input: &ProcessorInput,
-        ctx: &ProcessorContext,
-    ) -> Result<ProcessorOutput> {
-        let start = std::time::Instant::now();
+        last_report_at: std::time::Instant,
+        last_percent: Option<f32>,
+        percent: Option<f32>,
+    ) -> bool {
+        if last_report_at.elapsed() >= self.interval {
+            return true;
+        }
+        match (self.min_delta_percent, last_percent, percent) {
+            (Some(delta), Some(last), Some(percent)) => percent - last >= delta,
+            _ => false,
+        }
+    }
+}
 
-        // Initialize logs
-        let mut logs = Vec::new();
+fn progress_percent(bytes_done: u64, bytes_total: u64) -> Option<f32> {
+    if bytes_total == 0 {
+        None
+    } else {
+        Some(((bytes_done as f64 / bytes_total as f64) * 100.0) as f32)
+    }
+}
 
-        let mut config: CompressionConfig =
-            parse_config_or_default(input.config.as_deref(), ctx, "compression", Some(&mut logs));
+struct CancelProgressReader<R> {
+    inner: R,
+    cancel: CancellationToken,
+    progress: ProgressReporter,
+    throttle: ProgressThrottle,
+    bytes_total: u64,
+    bytes_done: u64,
+    last_report_at: std::time::Instant,
+    last_percent: Option<f32>,
+    file_index: usize,
+    file_count: usize,
+    current_file: String,
+    /// Size of the current entry, for the nested per-file progress.
+    file_bytes_total: u64,
+    file_bytes_done: u64,
+    /// When reading the current entry started, for its read speed.
+    start_instant: std::time::Instant,
+    crc: flate2::Crc,
+}
 
-        config.compression_level = Self::clamp_compression_level(config.compression_level)?;
+struct CompressionProgressContext {
+    cancel: CancellationToken,
+    progress: ProgressReporter,
+    bytes_total: u64,
+    bytes_done: u64,
+    file_index: usize,
+    file_count: usize,
+    current_file: String,
+    file_bytes_total: u64,
+}
 
-        // Validate inputs
-        if input.inputs.is_empty() {
-            let msg = "No input files specified for compression".to_string();
-            error!("{}", msg);
-            logs.push(create_log_entry(
-                crate::pipeline::job_queue::LogLevel::Error,
-                msg.clone(),
+impl<R> CancelProgressReader<R> {
+    fn new(inner: R, context: CompressionProgressContext, throttle: ProgressThrottle) -> Self {
+        let CompressionProgressContext {
+            cancel,
+            progress,
+            bytes_total,
+            bytes_done,
+            file_index,
+            file_count,
+            current_file,
+            file_bytes_total,
+        } = context;
+        Self {
+            inner,
+            cancel,
+            progress,
+            throttle,
+            bytes_total,
+            bytes_done,
+            last_report_at: std::time::Instant::now(),
+            last_percent: progress_percent(bytes_done, bytes_total),
+            file_index,
+            file_count,
+            current_file,
+            file_bytes_total,
+            file_bytes_done: 0,
+            start_instant: std::time::Instant::now(),
+            crc: flate2::Crc::new(),
+        }
+    }
+
+    fn maybe_report(&mut self) {
+        let percent = progress_percent(self.bytes_done, self.bytes_total);
+        if !self
+            .throttle
+            .should_report(self.last_report_at, self.last_percent, percent)
+        {
+            return;
+        }
+        self.last_report_at = std::time::Instant::now();
+        self.last_percent = percent;
+
+        let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
+        snapshot.percent = percent;
+        snapshot.bytes_done = Some(self.bytes_done);
+        snapshot.bytes_total = Some(self.bytes_total);
+        snapshot.raw = serde_json::json!({
+            "phase": "archiving",
+            "file_index": self.file_index,
+            "file_count": self.file_count,
+            "file": self.current_file,
+            "file_bytes_done": self.file_bytes_done,
+            "file_bytes_total": self.file_bytes_total,
+            "file_percent": progress_percent(self.file_bytes_done, self.file_bytes_total),
+            "file_speed_mbs": speed_mbs(self.file_bytes_done, self.start_instant.elapsed()),
+        });
+        self.progress.report(snapshot);
+    }
+}
+
+impl<R: Read> Read for CancelProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "compression cancelled",
             ));
-            return Err(crate::Error::PipelineError(msg));
         }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.crc.update(&buf[..n]);
+            self.bytes_done = self.bytes_done.saturating_add(n as u64);
+            self.file_bytes_done = self.file_bytes_done.saturating_add(n as u64);
+            self.maybe_report();
+        }
+        Ok(n)
+    }
+}
+
+/// `EIO`, which std reports without a stable [`std::io::ErrorKind`].
+const EIO: i32 = 5;
+
+/// Whether a read error may go away when the file is reopened.
+fn is_transient_read_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::TimedOut
+            | ErrorKind::WouldBlock
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::StaleNetworkFileHandle
+    ) || error.raw_os_error() == Some(EIO)
+}
+
+/// Retry settings shared by the input readers of one archive.
+#[derive(Clone)]
+struct ReadRetry {
+    policy: RetryPolicy,
+    cancel: CancellationToken,
+    /// One message per retry, in order.
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl ReadRetry {
+    fn new(policy: RetryPolicy, cancel: CancellationToken) -> Self {
+        Self {
+            policy,
+            cancel,
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn record(&self, message: String) {
+        warn!("{}", message);
+        if let Ok(mut events) = self.events.lock() {
+            events.push(message);
+        }
+    }
+
+    /// Messages of the retries recorded so far.
+    fn events(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Input file reader that reopens the file after a transient read error and
+/// resumes at the offset reached so far.
+struct RetryingFileReader<'a> {
+    path: PathBuf,
+    reader: Option<Box<dyn Read + 'a>>,
+    /// Bytes of the file returned so far.
+    offset: u64,
+    retry: ReadRetry,
+    /// Read buffer of reopened files.
+    buffer_size: usize,
+}
+
+impl<'a> RetryingFileReader<'a> {
+    fn new(path: impl Into<PathBuf>, reader: Box<dyn Read + 'a>, retry: ReadRetry) -> Self {
+        Self {
+            path: path.into(),
+            reader: Some(reader),
+            offset: 0,
+            retry,
+            buffer_size: DEFAULT_IO_BUFFER_BYTES,
+        }
+    }
+
+    fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    fn reader(&mut self) -> std::io::Result<&mut Box<dyn Read + 'a>> {
+        let reader = match self.reader.take() {
+            Some(reader) => reader,
+            None => {
+                let mut file = File::open(&self.path)?;
+                if file.metadata()?.len() < self.offset {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "input shrank below {} bytes and cannot be resumed",
+                            self.offset
+                        ),
+                    ));
+                }
+                file.seek(std::io::SeekFrom::Start(self.offset))?;
+                Box::new(BufReader::with_capacity(self.buffer_size, file))
+            }
+        };
+        Ok(self.reader.insert(reader))
+    }
+}
+
+impl Read for RetryingFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut attempt: u32 = 1;
+        loop {
+            match self.reader().and_then(|reader| reader.read(buf)) {
+                Ok(n) => {
+                    self.offset = self.offset.saturating_add(n as u64);
+                    return Ok(n);
+                }
+                Err(e)
+                    if attempt < self.retry.policy.max_attempts && is_transient_read_error(&e) =>
+                {
+                    self.reader = None;
+                    self.retry.record(format!(
+                        "Read of {} failed at byte {} (attempt {}/{}), retrying: {}",
+                        self.path.display(),
+                        self.offset,
+                        attempt,
+                        self.retry.policy.max_attempts,
+                        e
+                    ));
+                    sleep_unless_cancelled(
+                        std::time::Duration::from_millis(self.retry.policy.backoff_ms),
+                        &self.retry.cancel,
+                    )
+                    .map_err(std::io::Error::other)?;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Upper bound on the threads used to stat inputs before archiving.
+const SCAN_MAX_THREADS: usize = 8;
+
+/// Inputs stat'ed by one scan thread between cancellation checks.
+const SCAN_CHUNK_SIZE: usize = 256;
+
+/// Input metadata collected once by [`scan_inputs`] and reused while archiving.
+#[derive(Debug, Clone, Copy)]
+struct ScannedInput {
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+}
+
+#[derive(Debug)]
+struct InputScan {
+    /// Per-input metadata, in the same order as the scanned paths.
+    inputs: Vec<ScannedInput>,
+    total_size: u64,
+}
+
+fn stat_input(input_path: &str) -> Result<ScannedInput> {
+    let metadata = std::fs::metadata(input_path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            crate::Error::PipelineError(missing_input_message(input_path))
+        } else {
+            crate::Error::PipelineError(format!(
+                "Failed to get input metadata {}: {}",
+                input_path, e
+            ))
+        }
+    })?;
+    Ok(ScannedInput {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    })
+}
+
+fn report_scan_progress(progress: &ProgressReporter, files_scanned: usize, file_count: usize) {
+    let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
+    snapshot.percent =
+        (file_count > 0).then(|| ((files_scanned as f64 / file_count as f64) * 100.0) as f32);
+    snapshot.raw = serde_json::json!({
+        "phase": "scanning",
+        "files_scanned": files_scanned,
+        "file_count": file_count,
+    });
+    progress.report(snapshot);
+}
+
+/// Report that the archive is written and renamed into place, with the
+/// resources the job used. This is the last snapshot of a successful job.
+///
+/// Throttling usually drops the last "archiving" snapshots, so consumers would
+/// otherwise never see the bar reach 100%.
+fn report_finalized_progress(
+    progress: &ProgressReporter,
+    bytes_total: u64,
+    file_count: usize,
+    resource_stats: ResourceStats,
+) {
+    let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
+    snapshot.percent = Some(100.0);
+    snapshot.bytes_done = Some(bytes_total);
+    snapshot.bytes_total = Some(bytes_total);
+    snapshot.raw = serde_json::json!({
+        "phase": "finalized",
+        "file_count": file_count,
+        "cpu_time_secs": resource_stats.cpu_time_secs,
+        "peak_rss_bytes": resource_stats.peak_rss_bytes,
+    });
+    progress.report(snapshot);
+}
+
+/// Report that archiving ended without producing an archive (`phase` is
+/// `"failed"` or `"cancelled"`).
+fn report_terminal_progress(progress: &ProgressReporter, phase: &str, error: &crate::Error) {
+    let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
+    snapshot.raw = serde_json::json!({
+        "phase": phase,
+        "error": error.to_string(),
+    });
+    progress.report(snapshot);
+}
+
+/// Report bytes of the finished archive copied from the temp directory to the
+/// output filesystem.
+fn report_copy_progress(progress: &ProgressReporter, bytes_done: u64, bytes_total: u64) {
+    let mut snapshot = JobProgressSnapshot::new(ProgressKind::Compression);
+    snapshot.percent = progress_percent(bytes_done, bytes_total);
+    snapshot.bytes_done = Some(bytes_done);
+    snapshot.bytes_total = Some(bytes_total);
+    snapshot.raw = serde_json::json!({ "phase": "copying" });
+    progress.report(snapshot);
+}
+
+/// Error message of a job whose archive outgrew `max_archive_size_bytes`.
+const ARCHIVE_SIZE_LIMIT_EXCEEDED: &str = "Archive size limit exceeded";
+
+/// Fails an archive whose file grows past `max_archive_size_bytes`.
+#[derive(Clone, Default)]
+struct ArchiveSizeLimit {
+    max_bytes: Option<u64>,
+    /// Files added and archive size when the limit was exceeded.
+    exceeded: Arc<Mutex<Option<(usize, u64)>>>,
+}
+
+impl ArchiveSizeLimit {
+    fn new(max_bytes: Option<u64>) -> Self {
+        Self {
+            max_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Fail if the archive at `path` is over the limit after `files_added` files.
+    fn check(&self, path: &Path, files_added: usize) -> Result<()> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Ok(());
+        };
+        let size = std::fs::metadata(path)
+            .map_err(|e| crate::Error::io_path("stat", path, e))?
+            .len();
+        if size <= max_bytes {
+            return Ok(());
+        }
+        if let Ok(mut exceeded) = self.exceeded.lock() {
+            *exceeded = Some((files_added, size));
+        }
+        Err(crate::Error::PipelineError(
+            ARCHIVE_SIZE_LIMIT_EXCEEDED.to_string(),
+        ))
+    }
+
+    /// Files added and archive size when the limit was exceeded, if it was.
+    fn exceeded(&self) -> Option<(usize, u64)> {
+        *self.exceeded.lock().ok()?
+    }
+}
+
+/// Per-job state shared with the archive writers.
+struct ArchiveTask {
+    progress: ProgressReporter,
+    cancel: CancellationToken,
+    size_limit: ArchiveSizeLimit,
+    /// Most threads used to scan the inputs (`ResourceLimits::max_cpu_threads`).
+    max_threads: Option<u32>,
+}
+
+/// Removes a partially written output file unless committed.
+struct TmpFileGuard {
+    path: Option<PathBuf>,
+}
+
+impl TmpFileGuard {
+    fn new(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    fn commit(mut self) {
+        self.path.take();
+    }
+}
+
+impl Drop for TmpFileGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take()
+            && let Err(error) = std::fs::remove_file(&path)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(
+                %error,
+                path = %path.display(),
+                "failed to remove partial compression output"
+            );
+        }
+    }
+}
+
+/// Directory holding `path`, `.` for a bare file name.
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Upper bound of the bytes written while building the archive: the inputs,
+/// the virtual entries and, when appending, the existing archive.
+fn estimated_archive_space(
+    inputs: &[String],
+    virtual_entries: &[VirtualEntry],
+    existing_archive: Option<&Path>,
+) -> u64 {
+    let file_size = |path: &Path| std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    inputs
+        .iter()
+        .map(|input| file_size(Path::new(input)))
+        .chain(virtual_entries.iter().map(|entry| entry.source.size()))
+        .chain(existing_archive.map(file_size))
+        .fold(0, u64::saturating_add)
+}
+
+/// Free space on the filesystem holding `dir`, if it can be determined.
+fn available_space_for_path(dir: &Path) -> Option<u64> {
+    let dir = std::fs::canonicalize(dir).ok()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Fail before archiving when `dir` cannot hold `required` bytes; unknown free
+/// space passes.
+fn check_free_space(dir: &Path, required: u64, available: Option<u64>) -> Result<()> {
+    match available {
+        Some(available) if available < required => Err(crate::Error::PipelineError(format!(
+            "Insufficient space in {} to build the archive: need {} bytes, have {} bytes",
+            dir.display(),
+            required,
+            available
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Fail before archiving when the inputs exceed the job's memory limit.
+fn check_memory_limit(input_size: u64, max_memory_bytes: Option<u64>) -> Result<()> {
+    match max_memory_bytes {
+        Some(limit) if input_size > limit => Err(crate::Error::ResourceExhausted(format!(
+            "Archive inputs total {} bytes, over the {} byte memory limit",
+            input_size, limit
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Copy `from` to `to` in chunks, reporting "copying" progress and honoring
+/// cancellation, and fsync the copy.
+fn copy_with_progress(
+    from: &Path,
+    to: &Path,
+    progress: &ProgressReporter,
+    throttle: ProgressThrottle,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut reader = File::open(from).map_err(|e| crate::Error::io_path("open", from, e))?;
+    let bytes_total = reader
+        .metadata()
+        .map_err(|e| crate::Error::io_path("metadata", from, e))?
+        .len();
+    let mut writer = File::create(to).map_err(|e| crate::Error::io_path("create", to, e))?;
+
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut bytes_done = 0u64;
+    let mut last_report_at = std::time::Instant::now();
+    let mut last_percent = progress_percent(0, bytes_total);
+    report_copy_progress(progress, bytes_done, bytes_total);
+    loop {
+        if cancel.is_cancelled() {
+            return Err(crate::Error::PipelineError(
+                "Compression cancelled".to_string(),
+            ));
+        }
+        let n = reader
+            .read(&mut buf)
+            .map_err(|e| crate::Error::io_path("read", from, e))?;
+        if n == 0 {
+            break;
+        }
+        writer
+            .write_all(&buf[..n])
+            .map_err(|e| crate::Error::io_path("write", to, e))?;
+        bytes_done = bytes_done.saturating_add(n as u64);
+
+        let percent = progress_percent(bytes_done, bytes_total);
+        if throttle.should_report(last_report_at, last_percent, percent) {
+            last_report_at = std::time::Instant::now();
+            last_percent = percent;
+            report_copy_progress(progress, bytes_done, bytes_total);
+        }
+    }
+    writer
+        .sync_all()
+        .map_err(|e| crate::Error::io_path("sync_all", to, e))
+}
+
+/// Retry a failed rename after removing an existing output, which rename
+/// cannot replace on every platform.
+fn replace_output(
+    from: &Path,
+    to: &Path,
+    replace_existing: bool,
+    rename_err: std::io::Error,
+) -> Result<()> {
+    if !(replace_existing && to.exists()) {
+        return Err(crate::Error::io_path("rename", to, rename_err));
+    }
+    std::fs::remove_file(to).map_err(|e| crate::Error::io_path("remove_file", to, e))?;
+    std::fs::rename(from, to).map_err(|e| crate::Error::io_path("rename", to, e))
+}
+
+/// Move the finished archive at `tmp_path` to `output_path`.
+///
+/// When the temp file is on another filesystem it is copied to a staging file
+/// next to the output, fsynced and renamed into place, then removed.
+fn move_into_place(
+    tmp_path: &Path,
+    output_path: &Path,
+    replace_existing: bool,
+    progress: &ProgressReporter,
+    throttle: ProgressThrottle,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let Err(rename_err) = std::fs::rename(tmp_path, output_path) else {
+        return Ok(());
+    };
+    if rename_err.kind() != std::io::ErrorKind::CrossesDevices {
+        return replace_output(tmp_path, output_path, replace_existing, rename_err);
+    }
+
+    debug!(
+        from = %tmp_path.display(),
+        to = %output_path.display(),
+        "temp directory is on another filesystem; copying archive"
+    );
+    let staged = tmp_output_path(output_path);
+    let staged_guard = TmpFileGuard::new(staged.clone());
+    copy_with_progress(tmp_path, &staged, progress, throttle, cancel)?;
+    if let Err(rename_err) = std::fs::rename(&staged, output_path) {
+        replace_output(&staged, output_path, replace_existing, rename_err)?;
+    }
+    staged_guard.commit();
+    drop(TmpFileGuard::new(tmp_path.to_path_buf()));
+    Ok(())
+}
+
+/// Stat all inputs before archiving to learn the total size.
+///
+/// Inputs are stat'ed in chunks on a few scoped threads, at most `max_threads`
+/// when set, since the scan can take longer than the compression itself for
+/// many small files. Cancellation is checked between chunks and a "scanning"
+/// progress phase is reported.
+fn scan_inputs(
+    input_paths: &[&str],
+    progress: &ProgressReporter,
+    report_interval: std::time::Duration,
+    max_threads: Option<u32>,
+    cancel: &CancellationToken,
+) -> Result<InputScan> {
+    let file_count = input_paths.len();
+    let threads = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .min(SCAN_MAX_THREADS)
+        .min(max_threads.map_or(usize::MAX, |max| max as usize))
+        .min(file_count.div_ceil(SCAN_CHUNK_SIZE))
+        .max(1);
+
+    let mut inputs = Vec::with_capacity(file_count);
+    let mut last_report_at: Option<std::time::Instant> = None;
+
+    for round in input_paths.chunks(threads * SCAN_CHUNK_SIZE) {
+        if cancel.is_cancelled() {
+            return Err(crate::Error::PipelineError(
+                "Compression cancelled".to_string(),
+            ));
+        }
+
+        let results: Vec<Result<Vec<ScannedInput>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = round
+                .chunks(SCAN_CHUNK_SIZE)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|path| stat_input(path))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(crate::Error::Other(
+                            "Input scan thread panicked".to_string(),
+                        ))
+                    })
+                })
+                .collect()
+        });
+        for chunk in results {
+            inputs.extend(chunk?);
+        }
+
+        if last_report_at.is_none_or(|at| at.elapsed() >= report_interval)
+            || inputs.len() == file_count
+        {
+            last_report_at = Some(std::time::Instant::now());
+            report_scan_progress(progress, inputs.len(), file_count);
+        }
+    }
+
+    let total_size = inputs
+        .iter()
+        .fold(0u64, |total, input| total.saturating_add(input.size));
+    Ok(InputScan { inputs, total_size })
+}
+
+/// Result of writing an archive.
+struct ArchiveOutcome {
+    total_input_size: u64,
+    output_size: u64,
+    /// Inputs that were not added, with the reason they were skipped.
+    skipped_inputs: Vec<(String, String)>,
+    /// How many of `skipped_inputs` were left out for being empty.
+    empty_input_count: usize,
+    /// Existing entries that were replaced by an input with the same name.
+    replaced_entries: Vec<String>,
+    /// Number of entries written with ZIP64 extended size fields.
+    zip64_entry_count: usize,
+    /// Entries written from the inputs.
+    entries: Vec<CompressionEntryMetadata>,
+    /// Inputs detected as still being written.
+    unstable_inputs: Vec<String>,
+    /// Virtual entries left out because the name already exists in the archive.
+    skipped_virtual_entries: Vec<String>,
+    /// Entries stored uncompressed because the compression probe found them
+    /// incompressible.
+    skipped_compression_entries: usize,
+    /// One message per retried input read.
+    read_retries: Vec<String>,
+    /// Compression statistics of each entry in `entries`.
+    per_file_stats: Vec<FileCompressionStat>,
+}
+
+/// An input or virtual entry scheduled to be written into an archive.
+struct EntryPlan {
+    /// Input file path, or the entry name for virtual entries.
+    input_path: String,
+    archive_name: String,
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+    virtual_source: Option<VirtualEntrySource>,
+    /// Target of an input stored as a symlink entry.
+    link_target: Option<PathBuf>,
+}
+
+impl EntryPlan {
+    /// Open the entry's data, reading input files through a `read_buffer` byte
+    /// buffer; input files retry transient read errors when `read_retry` is set.
+    fn open(
+        &self,
+        read_retry: Option<&ReadRetry>,
+        read_buffer: usize,
+    ) -> Result<Box<dyn Read + '_>> {
+        match &self.virtual_source {
+            None => {
+                let file = File::open(&self.input_path).map_err(|e| {
+                    if e.kind() == std::io::ErrorKind::NotFound {
+                        crate::Error::PipelineError(missing_input_message(&self.input_path))
+                    } else {
+                        crate::Error::PipelineError(format!(
+                            "Failed to open input file {}: {}",
+                            self.input_path, e
+                        ))
+                    }
+                })?;
+                let reader: Box<dyn Read> = Box::new(BufReader::with_capacity(read_buffer, file));
+                Ok(match read_retry {
+                    Some(retry) => Box::new(
+                        RetryingFileReader::new(&self.input_path, reader, retry.clone())
+                            .with_buffer_size(read_buffer),
+                    ),
+                    None => reader,
+                })
+            }
+            Some(VirtualEntrySource::Bytes(bytes)) => Ok(Box::new(&bytes[..])),
+            Some(VirtualEntrySource::Reader(reader)) => {
+                let reader = reader.take().ok_or_else(|| {
+                    crate::Error::PipelineError(format!(
+                        "Virtual entry reader was already consumed: {}",
+                        self.input_path
+                    ))
+                })?;
+                Ok(Box::new(reader))
+            }
+        }
+    }
+}
+
+/// Remove zero-byte input files from scanned `entries`.
+///
+/// Returns the removed inputs as skipped inputs; fails when no entry is left.
+fn take_empty_inputs(entries: &mut Vec<EntryPlan>) -> Result<Vec<(String, String)>> {
+    let mut empty = Vec::new();
+    entries.retain(|entry| {
+        if entry.size == 0 && entry.virtual_source.is_none() && entry.link_target.is_none() {
+            empty.push((entry.input_path.clone(), "empty file".to_string()));
+            return false;
+        }
+        true
+    });
+    if entries.is_empty() && !empty.is_empty() {
+        return Err(crate::Error::PipelineError(
+            "All inputs are empty files".to_string(),
+        ));
+    }
+    Ok(empty)
+}
+
+/// Apply `handling` to the input entries of `entries` that are symbolic links.
+///
+/// `Skip` removes them and returns them as skipped inputs; `Preserve` records
+/// their target so they are stored as symlink entries.
+fn apply_symlink_handling(
+    entries: &mut Vec<EntryPlan>,
+    handling: SymlinkHandling,
+) -> Result<Vec<(String, String)>> {
+    if handling == SymlinkHandling::Follow {
+        return Ok(Vec::new());
+    }
+
+    let mut skipped = Vec::new();
+    let mut kept = Vec::with_capacity(entries.len());
+    for mut entry in entries.drain(..) {
+        let is_symlink = entry.virtual_source.is_none()
+            && std::fs::symlink_metadata(&entry.input_path)
+                .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if !is_symlink {
+            kept.push(entry);
+            continue;
+        }
+        if handling == SymlinkHandling::Skip {
+            debug!("Skipping symlink {}", entry.input_path);
+            skipped.push((entry.input_path, "symlink".to_string()));
+            continue;
+        }
+        let path = Path::new(&entry.input_path);
+        entry.link_target = Some(
+            std::fs::read_link(path).map_err(|e| crate::Error::io_path("read_link", path, e))?,
+        );
+        kept.push(entry);
+    }
+    *entries = kept;
+    if entries.is_empty() && !skipped.is_empty() {
+        return Err(crate::Error::PipelineError(
+            "All inputs are skipped symlinks".to_string(),
+        ));
+    }
+    Ok(skipped)
+}
+
+/// Plan the entries for `inputs` followed by `virtual_entries`.
+///
+/// Fails when a virtual entry has the same name as another entry.
+fn plan_entries(
+    inputs: &[String],
+    virtual_entries: &[VirtualEntry],
+    preserve_paths: bool,
+) -> Result<Vec<EntryPlan>> {
+    let mut plans = Vec::with_capacity(inputs.len() + virtual_entries.len());
+    for input_path in inputs {
+        plans.push(EntryPlan {
+            input_path: input_path.clone(),
+            archive_name: archive_entry_name(input_path, preserve_paths)?,
+            size: 0,
+            modified: None,
+            virtual_source: None,
+            link_target: None,
+        });
+    }
+
+    let mut names: HashSet<String> = plans.iter().map(|p| p.archive_name.clone()).collect();
+    let now = std::time::SystemTime::now();
+    for entry in virtual_entries {
+        let archive_name = archive_entry_name(&entry.name, true)?;
+        if !names.insert(archive_name.clone()) {
+            return Err(crate::Error::PipelineError(format!(
+                "Virtual entry {} collides with another archive entry",
+                archive_name
+            )));
+        }
+        plans.push(EntryPlan {
+            input_path: entry.name.clone(),
+            archive_name,
+            size: entry.source.size(),
+            modified: Some(now),
+            virtual_source: Some(entry.source.clone()),
+            link_target: None,
+        });
+    }
+    Ok(plans)
+}
+
+/// Stat the file-backed entries of `plans`, filling in their size and mtime.
+///
+/// Returns the total size of all entries, virtual ones included.
+fn scan_entry_plans(
+    plans: &mut [EntryPlan],
+    progress: &ProgressReporter,
+    report_interval: std::time::Duration,
+    max_threads: Option<u32>,
+    cancel: &CancellationToken,
+) -> Result<u64> {
+    let input_paths: Vec<&str> = plans
+        .iter()
+        .filter(|plan| plan.virtual_source.is_none() && plan.link_target.is_none())
+        .map(|plan| plan.input_path.as_str())
+        .collect();
+    let scan = scan_inputs(&input_paths, progress, report_interval, max_threads, cancel)?;
+
+    let mut total_size = scan.total_size;
+    let mut scanned = scan.inputs.into_iter();
+    for plan in plans.iter_mut() {
+        if plan.virtual_source.is_some() {
+            total_size = total_size.saturating_add(plan.size);
+        } else if plan.link_target.is_some() {
+            plan.modified = std::fs::symlink_metadata(&plan.input_path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+        } else if let Some(input) = scanned.next() {
+            plan.size = input.size;
+            plan.modified = input.modified;
+        }
+    }
+    Ok(total_size)
+}
+
+/// ZIP timestamp for an entry's modification time, falling back to the
+/// current time when it is unknown.
+fn zip_modified_time(entry: &EntryPlan) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+
+    let modified = entry.modified.unwrap_or_else(|| {
+        debug!(
+            "Modification time of {} unavailable; storing the current time",
+            entry.input_path
+        );
+        std::time::SystemTime::now()
+    });
+    let local = chrono::DateTime::<chrono::Local>::from(modified);
+    u16::try_from(local.year())
+        .ok()
+        .and_then(|year| {
+            zip::DateTime::from_date_and_time(
+                year,
+                local.month() as u8,
+                local.day() as u8,
+                local.hour() as u8,
+                local.minute() as u8,
+                local.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_else(|| {
+            debug!(
+                "Modification time of {} is outside the ZIP range; storing the ZIP epoch",
+                entry.input_path
+            );
+            zip::DateTime::default()
+        })
+}
+
+struct ZipEntriesContext {
+    options: FullFileOptions<'static>,
+    method_rules: FileMethodRules,
+    archive_comment: Option<String>,
+    entry_comments: HashMap<String, String>,
+    read_retry: Option<ReadRetry>,
+    compression_probe: Option<CompressionProbe>,
+    force_zip64: bool,
+    store_timestamps: bool,
+    read_buffer: usize,
+    total_input_size: u64,
+    progress: ProgressReporter,
+    throttle: ProgressThrottle,
+    cancel: CancellationToken,
+    size_limit: ArchiveSizeLimit,
+    archive_path: PathBuf,
+}
+
+/// File writer under the gzip stream of a tar.gz archive.
+type GzipSink = CountingWriter<BufWriter<File>>;
+
+/// Gzip stream backing a tar.gz archive.
+///
+/// The stream is one gzip member, or a sequence of concatenated members when
+/// [`GzipOutput::start_member`] is used to reset compression between entries.
+struct GzipOutput {
+    /// Encoder of the current member; `None` only while switching members.
+    encoder: Option<GzipEncoder>,
+    compression: Compression,
+}
+
+enum GzipEncoder {
+    Standard(GzEncoder<GzipSink>),
+    Rsyncable(RsyncableGzEncoder<GzipSink>),
+}
+
+impl GzipEncoder {
+    fn new(sink: GzipSink, compression: Compression, rsyncable: bool) -> std::io::Result<Self> {
+        if rsyncable {
+            RsyncableGzEncoder::new(sink, compression).map(Self::Rsyncable)
+        } else {
+            Ok(Self::Standard(GzEncoder::new(sink, compression)))
+        }
+    }
+
+    /// Write the gzip trailer and return the file writer.
+    fn finish(self) -> std::io::Result<GzipSink> {
+        match self {
+            Self::Standard(encoder) => encoder.finish(),
+            Self::Rsyncable(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl GzipOutput {
+    fn new(sink: GzipSink, compression: Compression, rsyncable: bool) -> std::io::Result<Self> {
+        Ok(Self {
+            encoder: Some(GzipEncoder::new(sink, compression, rsyncable)?),
+            compression,
+        })
+    }
+
+    fn encoder(&mut self) -> std::io::Result<&mut GzipEncoder> {
+        self.encoder
+            .as_mut()
+            .ok_or_else(|| std::io::Error::other("gzip stream failed while switching members"))
+    }
+
+    /// Finish the current gzip member and start a new one with the same settings.
+    fn start_member(&mut self) -> std::io::Result<()> {
+        let encoder = self
+            .encoder
+            .take()
+            .ok_or_else(|| std::io::Error::other("gzip stream failed while switching members"))?;
+        let rsyncable = matches!(encoder, GzipEncoder::Rsyncable(_));
+        let sink = encoder.finish()?;
+        self.encoder = Some(GzipEncoder::new(sink, self.compression, rsyncable)?);
+        Ok(())
+    }
+
+    /// Write the gzip trailer and flush everything to the file.
+    fn finish(mut self) -> std::io::Result<()> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.finish()?.flush(),
+            None => Err(std::io::Error::other(
+                "gzip stream failed while switching members",
+            )),
+        }
+    }
+}
+
+impl Write for GzipOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.encoder()? {
+            GzipEncoder::Standard(encoder) => encoder.write(buf),
+            GzipEncoder::Rsyncable(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.encoder()? {
+            GzipEncoder::Standard(encoder) => encoder.flush(),
+            GzipEncoder::Rsyncable(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writer that counts the bytes passed through it into a shared counter, so the
+/// count stays readable while an encoder owns the writer.
+struct CountingWriter<W> {
+    inner: W,
+    written: Arc<AtomicU64>,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W, written: Arc<AtomicU64>) -> Self {
+        Self { inner, written }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for CountingWriter<W> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Largest input compressed as one solid 7z block. Runs of entries are split
+/// before reaching it, and entries at least this large get a block of their own.
+const SEVEN_ZIP_MAX_SOLID_BLOCK_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Group the indices of `entries` into 7z blocks, in archive order.
+fn seven_zip_blocks(entries: &[EntryPlan], solid: bool) -> Vec<Vec<usize>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+    let mut current_size = 0u64;
+    for (idx, entry) in entries.iter().enumerate() {
+        if !solid || entry.size >= SEVEN_ZIP_MAX_SOLID_BLOCK_BYTES {
+            blocks.push(vec![idx]);
+            continue;
+        }
+        if !current.is_empty()
+            && current_size.saturating_add(entry.size) >= SEVEN_ZIP_MAX_SOLID_BLOCK_BYTES
+        {
+            blocks.push(std::mem::take(&mut current));
+            current_size = 0;
+        }
+        current_size = current_size.saturating_add(entry.size);
+        current.push(idx);
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks
+}
+
+/// LZMA2 at the preset matching `compression_level`.
+fn seven_zip_method(compression_level: u8) -> SevenZMethodConfiguration {
+    LZMA2Options::with_preset(u32::from(compression_level.min(9))).into()
+}
+
+/// The 7z entry header for `entry`.
+fn seven_zip_entry(entry: &EntryPlan) -> SevenZArchiveEntry {
+    let mut archive_entry = SevenZArchiveEntry::new();
+    archive_entry.name = entry.archive_name.clone();
+    archive_entry.has_stream = true;
+    if let Some(modified) = entry.modified
+        && let Ok(modified) = sevenz_rust::nt_time::FileTime::try_from(modified)
+    {
+        archive_entry.last_modified_date = modified;
+        archive_entry.has_last_modified_date = true;
+    }
+    archive_entry
+}
+
+/// What the reader of one entry in a solid 7z block saw, set once the entry
+/// was read to its end.
+#[derive(Debug, Default, Clone, Copy)]
+struct SolidEntryTally {
+    crc32: u32,
+    /// Archive bytes written while the entry was read.
+    compressed_bytes: u64,
+    /// Archive bytes written in total when the entry was done.
+    compressed_end: u64,
+    elapsed: std::time::Duration,
+}
+
+/// Reader of one entry in a solid 7z block.
+///
+/// The writer takes the readers of a whole block at once, so the entry is
+/// only opened on the first read, and closed again at its end.
+struct SolidEntryReader<'a> {
+    entry: &'a EntryPlan,
+    read_retry: Option<&'a ReadRetry>,
+    read_buffer: usize,
+    /// Progress context for the reader; `None` once the entry was opened.
+    context: Option<CompressionProgressContext>,
+    throttle: ProgressThrottle,
+    reader: Option<CancelProgressReader<std::io::Take<Box<dyn Read + 'a>>>>,
+    compressed_bytes: Arc<AtomicU64>,
+    compressed_before: u64,
+    started: std::time::Instant,
+    tally: Rc<Cell<SolidEntryTally>>,
+}
+
+impl Read for SolidEntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.reader.is_none() {
+            let Some(context) = self.context.take() else {
+                return Ok(0);
+            };
+            let inner = self
+                .entry
+                .open(self.read_retry, self.read_buffer)
+                .map_err(|e| std::io::Error::other(e.to_string()))?
+                .take(self.entry.size);
+            self.started = std::time::Instant::now();
+            self.compressed_before = self.compressed_bytes.load(Ordering::Relaxed);
+            self.reader = Some(CancelProgressReader::new(inner, context, self.throttle));
+        }
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(0);
+        };
+        let n = reader.read(buf)?;
+        if n == 0 {
+            let compressed_end = self.compressed_bytes.load(Ordering::Relaxed);
+            self.tally.set(SolidEntryTally {
+                crc32: reader.crc.sum(),
+                compressed_bytes: compressed_end.saturating_sub(self.compressed_before),
+                compressed_end,
+                elapsed: self.started.elapsed(),
+            });
+            self.reader = None;
+        }
+        Ok(n)
+    }
+}
+
+struct CancelOnDrop {
+    token: CancellationToken,
+    armed: bool,
+}
+
+impl CancelOnDrop {
+    fn new(token: CancellationToken) -> Self {
+        Self { token, armed: true }
+    }
+
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.armed {
+            self.token.cancel();
+        }
+    }
+}
+
+/// Strip a Windows drive, UNC share or verbatim/device prefix from `path`.
+///
+/// Handles `C:`, `\\server\share`, `\\?\C:`, `\\?\UNC\server\share` and
+/// `\\.\`; separators after the leading `\\` may be either slash.
+fn strip_windows_prefix(path: &str) -> &str {
+    fn split_first(path: &str) -> (&str, &str) {
+        match path.find(['/', '\\']) {
+            Some(idx) => (&path[..idx], &path[idx + 1..]),
+            None => (path, ""),
+        }
+    }
+
+    let mut rest = path;
+    let mut unc = false;
+    if let Some(verbatim) = path
+        .strip_prefix(r"\\?\")
+        .or_else(|| path.strip_prefix(r"\\.\"))
+    {
+        let (first, tail) = split_first(verbatim);
+        if first.eq_ignore_ascii_case("UNC") {
+            rest = tail;
+            unc = true;
+        } else {
+            rest = verbatim;
+        }
+    } else if let Some(share) = path.strip_prefix(r"\\") {
+        rest = share;
+        unc = true;
+    }
+
+    if unc {
+        // Skip the server and share names.
+        let (_, tail) = split_first(rest);
+        return split_first(tail).1;
+    }
+
+    let bytes = rest.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return &rest[2..];
+    }
+    rest
+}
+
+/// Name of the archive entry for `input_path`.
+///
+/// Both `/` and `\` separate components on every platform, so Windows paths
+/// from config are handled the same everywhere. Prefixes are removed with
+/// [`strip_windows_prefix`], `.` is dropped and `..` removes the previous
+/// component without ever leaving the archive root. The name always uses
+/// forward slashes, as both ZIP and tar expect; without `preserve_paths` only
+/// the last component is kept. Paths that leave no component are rejected.
+fn archive_entry_name(input_path: &str, preserve_paths: bool) -> Result<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in strip_windows_prefix(input_path).split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    if !preserve_paths {
+        let name = parts.last().ok_or_else(|| {
+            crate::Error::PipelineError(format!(
+                "Invalid input filename (missing file_name): {}",
+                input_path
+            ))
+        })?;
+        return Ok(name.to_string());
+    }
+
+    if parts.is_empty() {
+        return Err(crate::Error::PipelineError(format!(
+            "Invalid input path for archive entry: {}",
+            input_path
+        )));
+    }
+    Ok(parts.join("/"))
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            format: ArchiveFormat::Zip,
+            compression_level: default_compression_level(),
+            profile: None,
+            output_path: None,
+            overwrite: true,
+            preserve_paths: false,
+            append: false,
+            skip_if_exists: false,
+            force_zip64: false,
+            store_timestamps: true,
+            rsyncable: false,
+            solid: true,
+            stability_check: None,
+            collect_resource_stats: ResourceStatsConfig::default(),
+            progress_interval_ms: default_progress_interval_ms(),
+            min_progress_delta_percent: None,
+            virtual_entries: Vec::new(),
+            archive_comment: None,
+            entry_comments: HashMap::new(),
+            per_file_method: None,
+            read_retry: None,
+            skip_empty_files: true,
+            min_compression_ratio: None,
+            probe_bytes: default_probe_bytes(),
+            tmp_dir: None,
+            read_buffer_bytes: default_io_buffer_bytes(),
+            write_buffer_bytes: default_io_buffer_bytes(),
+            symlink_handling: SymlinkHandling::default(),
+            max_archive_size_bytes: None,
+        }
+    }
+}
+
+/// Processor for creating compressed archives.
+///
+/// Supports creating ZIP and tar.gz archives from one or more input files.
+/// - ZIP archives use the `zip` crate
+/// - tar.gz archives use `flate2` and `tar` crates
+/// - Multiple input files are bundled into a single archive
+pub struct CompressionProcessor {
+    /// Named configs selectable with [`CompressionConfig::profile`].
+    profiles: Arc<HashMap<String, serde_json::Value>>,
+}
+
+impl CompressionProcessor {
+    /// Create a new compression processor.
+    pub fn new() -> Self {
+        Self {
+            profiles: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Set the named configs jobs can select with [`CompressionConfig::profile`].
+    pub fn with_profiles(mut self, profiles: HashMap<String, serde_json::Value>) -> Self {
+        self.profiles = Arc::new(profiles);
+        self
+    }
+
+    /// Parse the job config, layered over its profile if it names one.
+    ///
+    /// Precedence is defaults < profile < job config, per top-level field.
+    fn resolve_config(
+        &self,
+        raw: Option<&str>,
+        ctx: &ProcessorContext,
+        logs: &mut Vec<ProcessorLogEntry>,
+    ) -> Result<CompressionConfig> {
+        let job = raw.and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok());
+        let Some(serde_json::Value::Object(job)) = job else {
+            return Ok(parse_config_or_default(raw, ctx, "compression", Some(logs)));
+        };
+        let Some(name) = job.get("profile").and_then(|profile| profile.as_str()) else {
+            return Ok(parse_config_or_default(raw, ctx, "compression", Some(logs)));
+        };
+
+        let Some(profile) = self.profiles.get(name) else {
+            let mut available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            available.sort_unstable();
+            return Err(crate::Error::PipelineError(format!(
+                "Unknown compression profile '{}'; available profiles: {}",
+                name,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )));
+        };
+        let serde_json::Value::Object(profile) = profile else {
+            return Err(crate::Error::PipelineError(format!(
+                "Compression profile '{}' must be a JSON object",
+                name
+            )));
+        };
+
+        let mut merged = profile.clone();
+        merged.extend(job);
+        let merged = serde_json::Value::Object(merged).to_string();
+        Ok(parse_config_or_default(
+            Some(&merged),
+            ctx,
+            "compression",
+            Some(logs),
+        ))
+    }
+
+    /// Determine the output archive path based on config and input.
+    fn determine_output_path(
+        &self,
+        inputs: &[String],
+        config: &CompressionConfig,
+        processor_input: &ProcessorInput,
+    ) -> String {
+        // Priority: config.output_path > processor_input.outputs > generated from first input
+        if let Some(ref output) = config.output_path {
+            return output.clone();
+        }
+
+        if let Some(output) = processor_input.outputs.first() {
+            return output.clone();
+        }
+
+        // Generate output path from first input path
+        if let Some(first_input) = inputs.first() {
+            let input = Path::new(first_input);
+            let stem = input
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("archive");
+            let parent = input.parent().unwrap_or(Path::new("."));
+
+            return parent
+                .join(format!("{}.{}", stem, config.format.extension()))
+                .to_string_lossy()
+                .to_string();
+        }
+
+        // Fallback
+        format!("archive.{}", config.format.extension())
+    }
+
+    /// Create a ZIP archive from the input files.
+    ///
+    /// When `existing_archive` is set, the entries of that archive are kept and
+    /// the inputs are added to it. Inputs whose entry name is already present are
+    /// skipped, or replace the existing entry when `overwrite` is enabled.
+    fn create_zip_archive(
+        &self,
+        inputs: &[String],
+        output_path: &Path,
+        config: &CompressionConfig,
+        existing_archive: Option<&Path>,
+        task: ArchiveTask,
+    ) -> Result<ArchiveOutcome> {
+        let ArchiveTask {
+            progress,
+            cancel,
+            size_limit,
+            max_threads,
+        } = task;
+        // Map compression level (0-9) to zip compression method
+        let options = if config.compression_level == 0 {
+            FullFileOptions::default().compression_method(zip::CompressionMethod::Stored)
+        } else {
+            // Deflate compression with level
+            FullFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(Some(config.compression_level as i64))
+        };
+
+        let method_rules =
+            FileMethodRules::compile(config.per_file_method.as_deref(), config.compression_level)?;
+        let read_retry = config
+            .read_retry
+            .map(|policy| ReadRetry::new(policy, cancel.clone()));
+
+        let mut entries = plan_entries(inputs, &config.virtual_entries, config.preserve_paths)?;
+
+        // ZIP archives cannot hold links, so `Preserve` follows them.
+        let symlink_handling = match config.symlink_handling {
+            SymlinkHandling::Preserve => SymlinkHandling::Follow,
+            handling => handling,
+        };
+        let mut skipped_inputs = apply_symlink_handling(&mut entries, symlink_handling)?;
+        let mut skipped_virtual_entries = Vec::new();
+        let mut replaced_entries = Vec::new();
+        let mut existing_entry_count = 0;
+        if let Some(existing) = existing_archive {
+            let archive = Self::open_zip_for_read(existing)?;
+            existing_entry_count = archive.len();
+            let existing_names: HashSet<&str> = archive.file_names().collect();
+            entries.retain(|entry| {
+                if !existing_names.contains(entry.archive_name.as_str()) {
+                    return true;
+                }
+                if config.overwrite {
+                    replaced_entries.push(entry.archive_name.clone());
+                    true
+                } else {
+                    debug!(
+                        "Skipping {}: entry {} already exists in archive",
+                        entry.input_path, entry.archive_name
+                    );
+                    if entry.virtual_source.is_some() {
+                        skipped_virtual_entries.push(entry.archive_name.clone());
+                    } else {
+                        skipped_inputs.push((
+                            entry.input_path.clone(),
+                            format!("entry {} already exists in archive", entry.archive_name),
+                        ));
+                    }
+                    false
+                }
+            });
+        }
+
+        let throttle = ProgressThrottle::from_config(config);
+        let total_input_size = scan_entry_plans(
+            &mut entries,
+            &progress,
+            throttle.interval,
+            max_threads,
+            &cancel,
+        )?;
+        let empty_inputs = if config.skip_empty_files {
+            take_empty_inputs(&mut entries)?
+        } else {
+            Vec::new()
+        };
+        let empty_input_count = empty_inputs.len();
+        skipped_inputs.extend(empty_inputs);
+
+        let archive_entry_count = existing_entry_count - replaced_entries.len() + entries.len();
+        let force_zip64 = zip64_for_all_entries(archive_entry_count, config.force_zip64);
+        if force_zip64 && !config.force_zip64 {
+            info!(
+                "Archive holds {} entries; writing all entries with ZIP64 extensions",
+                archive_entry_count
+            );
+        }
+        let zip64_entry_count = entries
+            .iter()
+            .filter(|entry| needs_zip64(entry.size, force_zip64))
+            .count();
+
+        let writer_context = ZipEntriesContext {
+            options,
+            method_rules,
+            archive_comment: config.archive_comment.clone(),
+            entry_comments: config.entry_comments.clone(),
+            read_retry: read_retry.clone(),
+            compression_probe: CompressionProbe::from_config(config),
+            force_zip64,
+            store_timestamps: config.store_timestamps,
+            read_buffer: config.read_buffer_size(),
+            total_input_size,
+            progress,
+            throttle,
+            cancel: cancel.clone(),
+            size_limit,
+            archive_path: output_path.to_path_buf(),
+        };
+
+        let (written_entries, durations, skipped_compression_entries) = match existing_archive {
+            // Nothing to replace: append new entries after the existing ones.
+            Some(existing) if replaced_entries.is_empty() => {
+                std::fs::copy(existing, output_path)
+                    .map_err(|e| crate::Error::io_path("copy", existing, e))?;
+                let file = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(output_path)
+                    .map_err(|e| crate::Error::io_path("open", output_path, e))?;
+                let zip = ZipWriter::new_append(file).map_err(|e| {
+                    crate::Error::PipelineError(format!(
+                        "Failed to open ZIP archive for append: {}",
+                        e
+                    ))
+                })?;
+                self.write_zip_entries(zip, &entries, writer_context)?
+            }
+            // ZIP entries cannot be removed in place, so rebuild the archive by
+            // copying the retained entries without recompressing them.
+            Some(existing) => {
+                let mut archive = Self::open_zip_for_read(existing)?;
+                let mut zip = ZipWriter::new(BufWriter::with_capacity(
+                    config.write_buffer_size(),
+                    Self::create_zip_file(output_path)?,
+                ));
+                zip.set_raw_comment(archive.comment().into()).map_err(|e| {
+                    crate::Error::PipelineError(format!("Failed to copy ZIP comment: {}", e))
+                })?;
+                for idx in 0..archive.len() {
+                    if cancel.is_cancelled() {
+                        return Err(crate::Error::PipelineError(
+                            "Compression cancelled".to_string(),
+                        ));
+                    }
+                    let entry = archive.by_index_raw(idx).map_err(|e| {
+                        crate::Error::PipelineError(format!("Failed to read ZIP entry: {}", e))
+                    })?;
+                    if replaced_entries.iter().any(|name| name == entry.name()) {
+                        continue;
+                    }
+                    zip.raw_copy_file(entry).map_err(|e| {
+                        crate::Error::PipelineError(format!("Failed to copy ZIP entry: {}", e))
+                    })?;
+                }
+                self.write_zip_entries(zip, &entries, writer_context)?
+            }
+            None => {
+                let zip = ZipWriter::new(BufWriter::with_capacity(
+                    config.write_buffer_size(),
+                    Self::create_zip_file(output_path)?,
+                ));
+                self.write_zip_entries(zip, &entries, writer_context)?
+            }
+        };
+
+        // Get output file size
+        let output_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+
+        // Compressed sizes are only known once the entries are finalized, so
+        // read them back from the central directory.
+        let mut archive = Self::open_zip_for_read(output_path)?;
+        let mut per_file_stats = Vec::with_capacity(written_entries.len());
+        for (entry, elapsed) in written_entries.iter().zip(durations) {
+            let compressed_size = archive
+                .by_name(&entry.archive_name)
+                .map(|file| file.compressed_size())
+                .map_err(|e| {
+                    crate::Error::PipelineError(format!(
+                        "Failed to read ZIP entry {}: {}",
+                        entry.archive_name, e
+                    ))
+                })?;
+            per_file_stats.push(FileCompressionStat::new(
+                &entry.archive_name,
+                entry.size_bytes,
+                compressed_size,
+                elapsed,
+            ));
+        }
+
+        Ok(ArchiveOutcome {
+            total_input_size,
+            output_size,
+            skipped_inputs,
+            empty_input_count,
+            replaced_entries,
+            zip64_entry_count,
+            entries: written_entries,
+            unstable_inputs: Vec::new(),
+            skipped_virtual_entries,
+            skipped_compression_entries,
+            read_retries: read_retry.map(|retry| retry.events()).unwrap_or_default(),
+            per_file_stats,
+        })
+    }
+
+    fn create_zip_file(output_path: &Path) -> Result<File> {
+        File::create(output_path).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to create ZIP archive: {}", e))
+        })
+    }
+
+    fn open_zip_for_read(path: &Path) -> Result<ZipArchive<BufReader<File>>> {
+        let file = File::open(path).map_err(|e| crate::Error::io_path("open", path, e))?;
+        ZipArchive::new(BufReader::new(file)).map_err(|e| {
+            crate::Error::PipelineError(format!(
+                "Failed to read existing ZIP archive {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the planned entries into a ZIP writer and finalize it.
+    ///
+    /// Returns the written entries, how long each took to write, and how many
+    /// of them were stored because the compression probe found them
+    /// incompressible.
+    fn write_zip_entries<W: Write + Seek>(
+        &self,
+        mut zip: ZipWriter<W>,
+        entries: &[EntryPlan],
+        context: ZipEntriesContext,
+    ) -> Result<(
+        Vec<CompressionEntryMetadata>,
+        Vec<std::time::Duration>,
+        usize,
+    )> {
+        let ZipEntriesContext {
+            options,
+            method_rules,
+            archive_comment,
+            entry_comments,
+            read_retry,
+            compression_probe,
+            force_zip64,
+            store_timestamps,
+            read_buffer,
+            total_input_size,
+            progress,
+            throttle,
+            cancel,
+            size_limit,
+            archive_path,
+        } = context;
+
+        if let Some(comment) = archive_comment {
+            zip.set_comment(comment).map_err(|e| {
+                crate::Error::PipelineError(format!("Failed to set ZIP comment: {}", e))
+            })?;
+        }
+
+        let stored_options =
+            FullFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let mut bytes_done: u64 = 0;
+        let mut written = Vec::with_capacity(entries.len());
+        let mut durations = Vec::with_capacity(entries.len());
+        let mut stored_by_probe = 0;
+
+        for (idx, entry) in entries.iter().enumerate() {
+            let input_path = &entry.input_path;
+            let archive_name = &entry.archive_name;
+            if cancel.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Compression cancelled".to_string(),
+                ));
+            }
+
+            debug!("Adding to ZIP: {} as {}", input_path, archive_name);
+
+            let mut reader = CancelProgressReader::new(
+                entry.open(read_retry.as_ref(), read_buffer)?,
+                CompressionProgressContext {
+                    cancel: cancel.clone(),
+                    progress: progress.clone(),
+                    bytes_total: total_input_size,
+                    bytes_done,
+                    file_index: idx.saturating_add(1),
+                    file_count: entries.len(),
+                    current_file: input_path.clone(),
+                    file_bytes_total: entry.size,
+                },
+                throttle,
+            );
+
+            let rule_options = method_rules.options_for(archive_name);
+            let entry_options = match (rule_options, &compression_probe) {
+                (None, Some(probe)) if probe.is_incompressible(entry)? => {
+                    debug!(
+                        "Storing {} uncompressed: below min_compression_ratio",
+                        archive_name
+                    );
+                    stored_by_probe += 1;
+                    &stored_options
+                }
+                _ => rule_options.unwrap_or(&options),
+            };
+
+            // Entries that may exceed 4 GiB must be written with ZIP64 headers;
+            // the writer cannot upgrade an entry once its header is written.
+            let mut options = entry_options
+                .clone()
+                .large_file(needs_zip64(entry.size, force_zip64));
+            if let Some(comment) = entry_comments.get(archive_name) {
+                options = options.with_file_comment(comment.as_str());
+            }
+            if store_timestamps {
+                options = options.last_modified_time(zip_modified_time(entry));
+            }
+
+            // Write to archive
+            zip.start_file(archive_name, options).map_err(|e| {
+                crate::Error::PipelineError(format!("Failed to start ZIP entry: {}", e))
+            })?;
+
+            let entry_start = std::time::Instant::now();
+            let size_bytes = match std::io::copy(&mut reader, &mut zip) {
+                Ok(size_bytes) => size_bytes,
+                Err(e) => {
+                    // Reads are resumed in place, so an error here means the
+                    // entry cannot be completed; drop it rather than leave a
+                    // truncated entry in the writer.
+                    if let Err(abort_err) = zip.abort_file() {
+                        debug!("Failed to abort ZIP entry {}: {}", archive_name, abort_err);
+                    }
+                    return Err(crate::Error::PipelineError(format!(
+                        "Failed to write ZIP entry {} from {}: {}",
+                        archive_name, input_path, e
+                    )));
+                }
+            };
+            durations.push(entry_start.elapsed());
+            bytes_done = reader.bytes_done;
+            written.push(CompressionEntryMetadata {
+                input_path: input_path.clone(),
+                archive_name: archive_name.clone(),
+                size_bytes,
+                crc32: reader.crc.sum(),
+                is_virtual: entry.virtual_source.is_some(),
+            });
+            size_limit.check(&archive_path, written.len())?;
+        }
+
+        zip.finish().map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to finalize ZIP archive: {}", e))
+        })?;
+
+        Ok((written, durations, stored_by_probe))
+    }
+
+    /// Create a tar.gz archive from the input files.
+    fn create_tar_gz_archive(
+        &self,
+        inputs: &[String],
+        output_path: &Path,
+        config: &CompressionConfig,
+        task: ArchiveTask,
+    ) -> Result<ArchiveOutcome> {
+        let ArchiveTask {
+            progress,
+            cancel,
+            size_limit,
+            max_threads,
+        } = task;
+        let mut entries = plan_entries(inputs, &config.virtual_entries, config.preserve_paths)?;
+        let mut skipped_inputs = apply_symlink_handling(&mut entries, config.symlink_handling)?;
+        let throttle = ProgressThrottle::from_config(config);
+        let total_input_size = scan_entry_plans(
+            &mut entries,
+            &progress,
+            throttle.interval,
+            max_threads,
+            &cancel,
+        )?;
+        let empty_inputs = if config.skip_empty_files {
+            take_empty_inputs(&mut entries)?
+        } else {
+            Vec::new()
+        };
+        let empty_input_count = empty_inputs.len();
+        skipped_inputs.extend(empty_inputs);
+        let read_retry = config
+            .read_retry
+            .map(|policy| ReadRetry::new(policy, cancel.clone()));
+
+        let file = File::create(output_path).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to create tar.gz archive: {}", e))
+        })?;
+
+        // The gzip stream has a single codec, so the probe decides for the
+        // whole archive from the samples of all entries.
+        let mut store_all = false;
+        if let Some(probe) = CompressionProbe::from_config(config) {
+            let (mut raw, mut compressed) = (0u64, 0u64);
+            for entry in &entries {
+                if let Some((entry_raw, entry_compressed)) = probe.sample_entry(entry)? {
+                    raw = raw.saturating_add(entry_raw);
+                    compressed = compressed.saturating_add(entry_compressed);
+                }
+            }
+            store_all = probe.below_threshold(raw, compressed);
+        }
+
+        // Map compression level (0-9) to flate2 Compression
+        let compression = match config.compression_level {
+            _ if store_all => Compression::none(),
+            0 => Compression::none(),
+            1 => Compression::fast(),
+            9 => Compression::best(),
+            level => Compression::new(level as u32),
+        };
+
+        let compressed_bytes = Arc::new(AtomicU64::new(0));
+        let writer = CountingWriter::new(
+            BufWriter::with_capacity(config.write_buffer_size(), file),
+            compressed_bytes.clone(),
+        );
+        let encoder = GzipOutput::new(writer, compression, config.rsyncable).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to write gzip header: {}", e))
+        })?;
+        let mut tar = TarBuilder::new(encoder);
+
+        let mut bytes_done: u64 = 0;
+        let mut written = Vec::with_capacity(entries.len());
+        let mut per_file_stats = Vec::with_capacity(entries.len());
+
+        for (idx, entry) in entries.iter().enumerate() {
+            if cancel.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Compression cancelled".to_string(),
+                ));
+            }
+
+            let input_path = &entry.input_path;
+            let archive_name = &entry.archive_name;
+            debug!("Adding to tar.gz: {} as {}", input_path, archive_name);
+            let entry_start = std::time::Instant::now();
+            let compressed_before = compressed_bytes.load(Ordering::Relaxed);
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(entry.size);
+            header.set_mode(0o644);
+            if let Some(modified) = entry.modified
+                && let Ok(duration) = modified.duration_since(std::time::SystemTime::UNIX_EPOCH)
+            {
+                header.set_mtime(duration.as_secs());
+            }
+
+            if let Some(target) = &entry.link_target {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                tar.append_link(&mut header, Path::new(archive_name), target)
+                    .map_err(|e| {
+                        crate::Error::PipelineError(format!(
+                            "Failed to add symlink to tar archive: {}",
+                            e
+                        ))
+                    })?;
+                if !config.solid && idx + 1 < entries.len() {
+                    Self::start_tar_gz_member(&mut tar)?;
+                }
+                per_file_stats.push(FileCompressionStat::new(
+                    archive_name,
+                    0,
+                    0,
+                    entry_start.elapsed(),
+                ));
+                written.push(CompressionEntryMetadata {
+                    input_path: input_path.clone(),
+                    archive_name: archive_name.clone(),
+                    size_bytes: 0,
+                    crc32: 0,
+                    is_virtual: false,
+                });
+                continue;
+            }
+
+            header.set_cksum();
+
+            // Read at most the scanned size so a file that grew since the scan
+            // cannot overrun its tar header.
+            let mut reader = CancelProgressReader::new(
+                entry
+                    .open(read_retry.as_ref(), config.read_buffer_size())?
+                    .take(entry.size),
+                CompressionProgressContext {
+                    cancel: cancel.clone(),
+                    progress: progress.clone(),
+                    bytes_total: total_input_size,
+                    bytes_done,
+                    file_index: idx.saturating_add(1),
+                    file_count: entries.len(),
+                    current_file: input_path.clone(),
+                    file_bytes_total: entry.size,
+                },
+                throttle,
+            );
+
+            tar.append_data(&mut header, Path::new(archive_name), &mut reader)
+                .map_err(|e| {
+                    crate::Error::PipelineError(format!("Failed to add file to tar archive: {}", e))
+                })?;
+            // The finished member belongs to this entry's compressed size.
+            if !config.solid && idx + 1 < entries.len() {
+                Self::start_tar_gz_member(&mut tar)?;
+            }
+
+            per_file_stats.push(FileCompressionStat::new(
+                archive_name,
+                entry.size,
+                compressed_bytes
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(compressed_before),
+                entry_start.elapsed(),
+            ));
+            bytes_done = bytes_done.saturating_add(entry.size);
+            written.push(CompressionEntryMetadata {
+                input_path: input_path.clone(),
+                archive_name: archive_name.clone(),
+                size_bytes: entry.size,
+                crc32: reader.crc.sum(),
+                is_virtual: entry.virtual_source.is_some(),
+            });
+            size_limit.check(output_path, written.len())?;
+        }
+
+        // Finish the tar archive and get the gzip encoder back
+        let compressed_before_finish = compressed_bytes.load(Ordering::Relaxed);
+        let encoder = tar.into_inner().map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to finalize tar archive: {}", e))
+        })?;
+
+        // Finish gzip compression
+        encoder.finish().map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to finalize gzip compression: {}", e))
+        })?;
+
+        // The encoder buffers input across entries; what it flushes at the end
+        // belongs to the last entry.
+        if let Some(last) = per_file_stats.last_mut() {
+            last.output_size_bytes = last.output_size_bytes.saturating_add(
+                compressed_bytes
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(compressed_before_finish),
+            );
+            last.compression_ratio_percent =
+                Self::calculate_compression_ratio(last.input_size_bytes, last.output_size_bytes);
+        }
+
+        // Get output file size
+        let output_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+
+        Ok(ArchiveOutcome {
+            total_input_size,
+            output_size,
+            empty_input_count,
+            skipped_inputs,
+            replaced_entries: Vec::new(),
+            zip64_entry_count: 0,
+            entries: written,
+            unstable_inputs: Vec::new(),
+            skipped_virtual_entries: Vec::new(),
+            skipped_compression_entries: if store_all { entries.len() } else { 0 },
+            read_retries: read_retry.map(|retry| retry.events()).unwrap_or_default(),
+            per_file_stats,
+        })
+    }
+
+    /// End the gzip member holding the entries written so far and start a new
+    /// one, so the next entry is compressed independently of them.
+    fn start_tar_gz_member(tar: &mut TarBuilder<GzipOutput>) -> Result<()> {
+        tar.get_mut()
+            .start_member()
+            .map_err(|e| crate::Error::PipelineError(format!("Failed to start gzip member: {}", e)))
+    }
+
+    /// Create a 7z archive with LZMA2-compressed entries.
+    ///
+    /// Solid archives compress runs of entries as one block, up to
+    /// [`SEVEN_ZIP_MAX_SOLID_BLOCK_BYTES`] of input each; otherwise every
+    /// entry gets its own block.
+    fn create_7z_archive(
+        &self,
+        inputs: &[String],
+        output_path: &Path,
+        config: &CompressionConfig,
+        task: ArchiveTask,
+    ) -> Result<ArchiveOutcome> {
+        let ArchiveTask {
+            progress,
+            cancel,
+            size_limit,
+            max_threads,
+        } = task;
+        let mut entries = plan_entries(inputs, &config.virtual_entries, config.preserve_paths)?;
+        // The 7z writer cannot store links, so `Preserve` follows them.
+        let symlink_handling = match config.symlink_handling {
+            SymlinkHandling::Preserve => SymlinkHandling::Follow,
+            handling => handling,
+        };
+        let mut skipped_inputs = apply_symlink_handling(&mut entries, symlink_handling)?;
+        let throttle = ProgressThrottle::from_config(config);
+        let total_input_size = scan_entry_plans(
+            &mut entries,
+            &progress,
+            throttle.interval,
+            max_threads,
+            &cancel,
+        )?;
+        let empty_inputs = if config.skip_empty_files {
+            take_empty_inputs(&mut entries)?
+        } else {
+            Vec::new()
+        };
+        let empty_input_count = empty_inputs.len();
+        skipped_inputs.extend(empty_inputs);
+        let read_retry = config
+            .read_retry
+            .map(|policy| ReadRetry::new(policy, cancel.clone()));
+
+        let file = File::create(output_path).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to create 7z archive: {}", e))
+        })?;
+        let compressed_bytes = Arc::new(AtomicU64::new(0));
+        let writer = CountingWriter::new(
+            BufWriter::with_capacity(config.write_buffer_size(), file),
+            compressed_bytes.clone(),
+        );
+        let mut archive = SevenZWriter::new(writer).map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to create 7z archive: {}", e))
+        })?;
+        archive.set_content_methods(vec![seven_zip_method(config.compression_level)]);
+
+        let mut bytes_done: u64 = 0;
+        let mut written = Vec::with_capacity(entries.len());
+        let mut per_file_stats = Vec::with_capacity(entries.len());
+        let progress_context = |idx: usize, bytes_done: u64| CompressionProgressContext {
+            cancel: cancel.clone(),
+            progress: progress.clone(),
+            bytes_total: total_input_size,
+            bytes_done,
+            file_index: idx.saturating_add(1),
+            file_count: entries.len(),
+            current_file: entries[idx].input_path.clone(),
+            file_bytes_total: entries[idx].size,
+        };
+
+        for block in seven_zip_blocks(&entries, config.solid) {
+            if cancel.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Compression cancelled".to_string(),
+                ));
+            }
+
+            if let [idx] = block[..] {
+                let entry = &entries[idx];
+                debug!(
+                    "Adding to 7z: {} as {}",
+                    entry.input_path, entry.archive_name
+                );
+                let entry_start = std::time::Instant::now();
+                // Read at most the scanned size, like the other formats.
+                let mut reader = CancelProgressReader::new(
+                    entry
+                        .open(read_retry.as_ref(), config.read_buffer_size())?
+                        .take(entry.size),
+                    progress_context(idx, bytes_done),
+                    throttle,
+                );
+                let compressed_size = archive
+                    .push_archive_entry(seven_zip_entry(entry), Some(&mut reader))
+                    .map_err(|e| {
+                        crate::Error::PipelineError(format!(
+                            "Failed to add file to 7z archive: {}",
+                            e
+                        ))
+                    })?
+                    .compressed_size;
+                per_file_stats.push(FileCompressionStat::new(
+                    &entry.archive_name,
+                    entry.size,
+                    compressed_size,
+                    entry_start.elapsed(),
+                ));
+                bytes_done = bytes_done.saturating_add(entry.size);
+                written.push(CompressionEntryMetadata {
+                    input_path: entry.input_path.clone(),
+                    archive_name: entry.archive_name.clone(),
+                    size_bytes: entry.size,
+                    crc32: reader.crc.sum(),
+                    is_virtual: entry.virtual_source.is_some(),
+                });
+                size_limit.check(output_path, written.len())?;
+                continue;
+            }
+
+            // The writer takes the readers of the whole block at once; each
+            // one opens its entry on first read and reports back through a tally.
+            let mut readers = Vec::with_capacity(block.len());
+            let mut tallies = Vec::with_capacity(block.len());
+            for &idx in &block {
+                let entry = &entries[idx];
+                debug!(
+                    "Adding to 7z: {} as {}",
+                    entry.input_path, entry.archive_name
+                );
+                let tally = Rc::new(Cell::new(SolidEntryTally::default()));
+                readers.push(SourceReader::new(SolidEntryReader {
+                    entry,
+                    read_retry: read_retry.as_ref(),
+                    read_buffer: config.read_buffer_size(),
+                    context: Some(progress_context(idx, bytes_done)),
+                    throttle,
+                    reader: None,
+                    compressed_bytes: compressed_bytes.clone(),
+                    compressed_before: 0,
+                    started: std::time::Instant::now(),
+                    tally: tally.clone(),
+                }));
+                tallies.push(tally);
+                bytes_done = bytes_done.saturating_add(entry.size);
+            }
+            let archive_entries = block.iter().map(|&idx| seven_zip_entry(&entries[idx]));
+            archive
+                .push_archive_entries(archive_entries.collect(), SeqReader::new(readers))
+                .map_err(|e| {
+                    crate::Error::PipelineError(format!("Failed to add files to 7z archive: {}", e))
+                })?;
+
+            for (&idx, tally) in block.iter().zip(&tallies) {
+                let entry = &entries[idx];
+                let tally = tally.get();
+                per_file_stats.push(FileCompressionStat::new(
+                    &entry.archive_name,
+                    entry.size,
+                    tally.compressed_bytes,
+                    tally.elapsed,
+                ));
+                written.push(CompressionEntryMetadata {
+                    input_path: entry.input_path.clone(),
+                    archive_name: entry.archive_name.clone(),
+                    size_bytes: entry.size,
+                    crc32: tally.crc32,
+                    is_virtual: entry.virtual_source.is_some(),
+                });
+            }
+            // The encoder buffers input across the block; what it flushes
+            // after the last entry was read belongs to that entry.
+            let block_end = tallies.last().map(|tally| tally.get().compressed_end);
+            if let (Some(last), Some(block_end)) = (per_file_stats.last_mut(), block_end) {
+                last.output_size_bytes = last.output_size_bytes.saturating_add(
+                    compressed_bytes
+                        .load(Ordering::Relaxed)
+                        .saturating_sub(block_end),
+                );
+                last.compression_ratio_percent = Self::calculate_compression_ratio(
+                    last.input_size_bytes,
+                    last.output_size_bytes,
+                );
+            }
+            size_limit.check(output_path, written.len())?;
+        }
+
+        archive.finish().map_err(|e| {
+            crate::Error::PipelineError(format!("Failed to finalize 7z archive: {}", e))
+        })?;
+
+        let output_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+
+        Ok(ArchiveOutcome {
+            total_input_size,
+            output_size,
+            empty_input_count,
+            skipped_inputs,
+            replaced_entries: Vec::new(),
+            zip64_entry_count: 0,
+            entries: written,
+            unstable_inputs: Vec::new(),
+            skipped_virtual_entries: Vec::new(),
+            skipped_compression_entries: 0,
+            read_retries: read_retry.map(|retry| retry.events()).unwrap_or_default(),
+            per_file_stats,
+        })
+    }
+
+    /// Calculate compression ratio as a percentage.
+    fn calculate_compression_ratio(input_size: u64, output_size: u64) -> f64 {
+        if input_size == 0 {
+            return 0.0;
+        }
+        (1.0 - (output_size as f64 / input_size as f64)) * 100.0
+    }
+
+    /// Archive `input` together with `virtual_entries`, after those of the job config.
+    ///
+    /// This is the way to archive [`VirtualEntry::from_reader`] entries, which cannot
+    /// be expressed in the job config.
+    pub async fn process_with_virtual_entries(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+        virtual_entries: Vec<VirtualEntry>,
+    ) -> Result<ProcessorOutput> {
+        self.compress(input, ctx, virtual_entries, None).await
+    }
+
+    /// Archive the job's inputs, sending [`StreamingOutputEvent::ItemProduced`]
+    /// to `events` as soon as the archive is in place.
+    async fn compress(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+        virtual_entries: Vec<VirtualEntry>,
+        events: Option<futures::channel::mpsc::UnboundedSender<StreamingOutputEvent>>,
+    ) -> Result<ProcessorOutput> {
+        let start = std::time::Instant::now();
+
+        // Initialize logs
+        let mut logs = Vec::new();
+
+        let mut config = self.resolve_config(input.config.as_deref(), ctx, &mut logs)?;
+
+        config.compression_level = Self::clamp_compression_level(config.compression_level)?;
+        if !config.format.is_writable() {
+            return Err(unwritable_format_error(&config.format));
+        }
+        Self::validate_min_compression_ratio(config.min_compression_ratio)?;
+        Self::validate_buffer_sizes(&config)?;
+        config.virtual_entries.extend(virtual_entries);
+
+        // Validate inputs
+        if input.inputs.is_empty() && config.virtual_entries.is_empty() {
+            let msg = NO_INPUTS_ERROR.to_string();
+            error!("{}", msg);
+            logs.push(ProcessorLogEntry::error(msg.clone()));
+            return Err(crate::Error::PipelineError(msg));
+        }
+
+        // Determine output path
+        let output_path_str = self.determine_output_path(&input.inputs, &config, input);
+        let output_path = PathBuf::from(&output_path_str);
+
+        // Check if output exists and handle overwrite
+        let output_exists = tokio::fs::try_exists(&output_path)
+            .await
+            .map_err(|e| crate::Error::io_path("try_exists", &output_path, e))?;
+        let appending = config.append && output_exists;
+        if appending && config.format != ArchiveFormat::Zip {
+            let msg = append_unsupported_message(output_path.display());
+            error!("{}", msg);
+            logs.push(
+                ProcessorLogEntry::error(msg.clone())
+                    .with_field("output", output_path_str.as_str()),
+            );
+            return Err(crate::Error::PipelineError(msg));
+        }
+        if output_exists && !config.overwrite && !appending && config.skip_if_exists {
+            let msg = format!(
+                "Output archive already exists, skipping compression: {}",
+                output_path.display()
+            );
+            info!("{}", msg);
+            logs.push(ProcessorLogEntry::info(msg).with_field("output", output_path_str.as_str()));
+            return Ok(ProcessorOutput {
+                outputs: vec![output_path_str],
+                duration_secs: start.elapsed().as_secs_f64(),
+                skipped_inputs: input
+                    .inputs
+                    .iter()
+                    .map(|path| (path.clone(), "output_already_exists".to_string()))
+                    .collect(),
+                logs,
+                ..Default::default()
+            });
+        }
+        if output_exists && !config.overwrite && !appending {
+            let msg = output_exists_message(output_path.display());
+            error!("{}", msg);
+            logs.push(
+                ProcessorLogEntry::error(msg.clone())
+                    .with_field("output", output_path_str.as_str()),
+            );
+            return Err(crate::Error::PipelineError(msg));
+        }
+
+        if config.rsyncable && config.format != ArchiveFormat::TarGz {
+            let msg = RSYNCABLE_WARNING.to_string();
+            warn!("{}", msg);
+            logs.push(ProcessorLogEntry::warn(msg).with_field("output", output_path_str.as_str()));
+        }
+        if !config.solid && config.format == ArchiveFormat::Zip {
+            let msg = SOLID_ZIP_WARNING.to_string();
+            warn!("{}", msg);
+            logs.push(ProcessorLogEntry::warn(msg).with_field("output", output_path_str.as_str()));
+        }
+        if config.symlink_handling == SymlinkHandling::Preserve
+            && config.format != ArchiveFormat::TarGz
+        {
+            let msg = SYMLINK_PRESERVE_ZIP_WARNING.to_string();
+            warn!("{}", msg);
+            logs.push(ProcessorLogEntry::warn(msg).with_field("output", output_path_str.as_str()));
+        }
+
+        if config.format == ArchiveFormat::Zip {
+            if let Some(template) = config.archive_comment.take() {
+                let mut comment = expand_placeholders_at(
+                    &template,
+                    &input.streamer_id,
+                    &input.session_id,
+                    input.streamer_name.as_deref(),
+                    input.session_title.as_deref(),
+                    input.platform.as_deref(),
+                    Some(
+                        TimeAnchor::SessionStart
+                            .reference_time(input)
+                            .timestamp_millis(),
+                    ),
+                );
+                if truncate_zip_comment(&mut comment) {
+                    let msg = format!(
+                        "Archive comment exceeds {} bytes and was truncated",
+                        ZIP_COMMENT_MAX_LEN
+                    );
+                    warn!("{}", msg);
+                    logs.push(
+                        ProcessorLogEntry::warn(msg).with_field("output", output_path_str.as_str()),
+                    );
+                }
+                config.archive_comment = Some(comment);
+            }
+            for (name, comment) in &mut config.entry_comments {
+                if truncate_zip_comment(comment) {
+                    let msg = format!(
+                        "Comment for entry {} exceeds {} bytes and was truncated",
+                        name, ZIP_COMMENT_MAX_LEN
+                    );
+                    warn!("{}", msg);
+                    logs.push(ProcessorLogEntry::warn(msg).with_field("entry", name.as_str()));
+                }
+            }
+        } else if config.archive_comment.is_some() || !config.entry_comments.is_empty() {
+            let msg =
+                "Archive and entry comments only apply to ZIP archives and are ignored".to_string();
+            debug!("{}", msg);
+            logs.push(ProcessorLogEntry::debug(msg).with_field("output", output_path_str.as_str()));
+        }
+
+        if config.per_file_method.is_some() && config.format != ArchiveFormat::Zip {
+            let msg = PER_FILE_METHOD_WARNING.to_string();
+            warn!("{}", msg);
+            logs.push(ProcessorLogEntry::warn(msg).with_field("output", output_path_str.as_str()));
+        }
+
+        let start_msg = if appending {
+            format!(
+                "Appending {} files to existing {:?} archive -> {}",
+                input.inputs.len(),
+                config.format,
+                output_path_str
+            )
+        } else {
+            format!(
+                "Creating {:?} archive with {} files -> {}",
+                config.format,
+                input.inputs.len(),
+                output_path_str
+            )
+        };
+        info!("{}", start_msg);
+        logs.push(
+            ProcessorLogEntry::info(start_msg)
+                .with_field("output", output_path_str.as_str())
+                .with_field("input_count", input.inputs.len()),
+        );
+
+        if ctx.is_dry_run() {
+            return Ok(ProcessorOutput {
+                outputs: vec![output_path_str],
+                logs,
+                ..Default::default()
+            });
+        }
+
+        if let Some(parent) = output_path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| crate::Error::io_path("create_dir_all", parent, e))?;
+        }
+
+        let tmp_path = match &config.tmp_dir {
+            Some(dir) => {
+                let dir = Path::new(dir);
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|e| crate::Error::io_path("create_dir_all", dir, e))?;
+                let file_name = output_path
+                    .file_name()
+                    .unwrap_or_else(|| std::ffi::OsStr::new("archive"));
+                tmp_output_path(&dir.join(file_name))
+            }
+            None => tmp_output_path(&output_path),
+        };
+
+        let inputs = input.inputs.clone();
+        let config_for_blocking = config.clone();
+        let cancel = ctx.cancellation_token.child_token();
+        let mut cancel_on_drop = CancelOnDrop::new(cancel.clone());
+        let cancel_for_result = cancel.clone();
+        let progress = ctx.progress.clone();
+        let size_limit = ArchiveSizeLimit::new(config.max_archive_size_bytes);
+        let size_limit_for_result = size_limit.clone();
+        let max_memory_bytes = ctx.resource_limits.max_memory_bytes;
+        let max_threads = ctx.resource_limits.max_cpu_threads;
+        let produced_path = output_path_str.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let guard = TmpFileGuard::new(tmp_path.clone());
+            let tracker = ResourceTracker::start(&config_for_blocking.collect_resource_stats);
+
+            if cancel.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Compression cancelled".to_string(),
+                ));
+            }
+
+            let stability = match &config_for_blocking.stability_check {
+                Some(check) => check_input_stability(&inputs, check, &cancel)?,
+                None => StabilityOutcome::default(),
+            };
+            let inputs: Vec<String> = inputs
+                .into_iter()
+                .filter(|path| {
+                    !stability
+                        .skipped_inputs
+                        .iter()
+                        .any(|(skipped, _)| skipped == path)
+                })
+                .collect();
+            if inputs.is_empty() && config_for_blocking.virtual_entries.is_empty() {
+                return Err(crate::Error::PipelineError(
+                    "All inputs are still being written".to_string(),
+                ));
+            }
+
+            check_memory_limit(
+                estimated_archive_space(&inputs, &config_for_blocking.virtual_entries, None),
+                max_memory_bytes,
+            )?;
+            let required = estimated_archive_space(
+                &inputs,
+                &config_for_blocking.virtual_entries,
+                appending.then_some(output_path.as_path()),
+            );
+            let tmp_dir = parent_dir(&tmp_path);
+            check_free_space(tmp_dir, required, available_space_for_path(tmp_dir))?;
+            let output_dir = parent_dir(&output_path);
+            if config_for_blocking.tmp_dir.is_some() {
+                check_free_space(output_dir, required, available_space_for_path(output_dir))?;
+            }
+
+            let processor = CompressionProcessor::new();
+            let final_progress = progress.clone();
+            let task = ArchiveTask {
+                progress,
+                cancel: cancel.clone(),
+                size_limit,
+                max_threads,
+            };
+            let mut outcome = match config_for_blocking.format {
+                ArchiveFormat::Zip => processor.create_zip_archive(
+                    &inputs,
+                    &tmp_path,
+                    &config_for_blocking,
+                    appending.then_some(output_path.as_path()),
+                    task,
+                ),
+                ArchiveFormat::TarGz => {
+                    processor.create_tar_gz_archive(&inputs, &tmp_path, &config_for_blocking, task)
+                }
+                ArchiveFormat::SevenZip => {
+                    processor.create_7z_archive(&inputs, &tmp_path, &config_for_blocking, task)
+                }
+                ref format => Err(unwritable_format_error(format)),
+            }?;
+            outcome.unstable_inputs = stability.unstable_inputs;
+            outcome.skipped_inputs.extend(stability.skipped_inputs);
+
+            if cancel.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Compression cancelled".to_string(),
+                ));
+            }
+
+            move_into_place(
+                &tmp_path,
+                &output_path,
+                config_for_blocking.overwrite || appending,
+                &final_progress,
+                ProgressThrottle::from_config(&config_for_blocking),
+                &cancel,
+            )?;
+            guard.commit();
+            if let Some(events) = &events {
+                let _ = events.unbounded_send(StreamingOutputEvent::ItemProduced(produced_path));
+            }
+
+            let resource_stats = tracker.finish();
+            report_finalized_progress(
+                &final_progress,
+                outcome.total_input_size,
+                inputs.len(),
+                resource_stats,
+            );
+
+            Ok::<_, crate::Error>((outcome, resource_stats))
+        })
+        .await
+        .map_err(|e| crate::Error::Other(format!("Compression worker panicked: {}", e)))
+        .and_then(|result| result);
+
+        cancel_on_drop.disarm();
+
+        let (outcome, resource_stats) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                let phase = if cancel_for_result.is_cancelled() {
+                    "cancelled"
+                } else {
+                    "failed"
+                };
+                report_terminal_progress(&ctx.progress, phase, &e);
+                let msg = format!("Compression failed: {}", e);
+                error!("{}", msg);
+                let mut entry =
+                    ProcessorLogEntry::error(msg).with_field("output", output_path_str.as_str());
+                // The job fails without an output, so the abort point goes to the job log.
+                if let Some((files, size)) = size_limit_for_result.exceeded() {
+                    entry = entry
+                        .with_field("aborted_at_file", files)
+                        .with_field("archive_size_at_abort", size);
+                    ctx.log(entry.clone());
+                }
+                logs.push(entry);
+                return Err(e);
+            }
+        };
+
+        let ArchiveOutcome {
+            total_input_size,
+            output_size,
+            skipped_inputs,
+            empty_input_count,
+            replaced_entries,
+            zip64_entry_count,
+            entries,
+            unstable_inputs,
+            skipped_virtual_entries,
+            skipped_compression_entries,
+            read_retries,
+            per_file_stats,
+        } = outcome;
+
+        let succeeded_inputs: Vec<String> = input
+            .inputs
+            .iter()
+            .filter(|path| !skipped_inputs.iter().any(|(skipped, _)| skipped == *path))
+            .cloned()
+            .collect();
+
+        // Add detailed logs for inputs
+        for input in &succeeded_inputs {
+            logs.push(
+                ProcessorLogEntry::debug(format!("Added file to archive: {}", input))
+                    .with_field("input", input.as_str()),
+            );
+        }
+        for (input, reason) in &skipped_inputs {
+            logs.push(
+                ProcessorLogEntry::info(format!("Skipped {}: {}", input, reason))
+                    .with_field("input", input.as_str())
+                    .with_field("reason", reason.as_str()),
+            );
+        }
+        for name in &skipped_virtual_entries {
+            logs.push(
+                ProcessorLogEntry::info(format!(
+                    "Skipped virtual entry {}: already exists in archive",
+                    name
+                ))
+                .with_field("entry", name.as_str()),
+            );
+        }
+        for path in &unstable_inputs {
+            logs.push(
+                ProcessorLogEntry::warn(format!("Input was still being written: {}", path))
+                    .with_field("input", path.as_str()),
+            );
+        }
+        for entry in &replaced_entries {
+            logs.push(
+                ProcessorLogEntry::info(format!("Replaced existing archive entry: {}", entry))
+                    .with_field("entry", entry.as_str()),
+            );
+        }
+        if skipped_compression_entries > 0 {
+            logs.push(ProcessorLogEntry::info(format!(
+                    "Stored {} entries uncompressed: estimated reduction below min_compression_ratio",
+                    skipped_compression_entries
+                )).with_field("entry_count", skipped_compression_entries));
+        }
+        for message in &read_retries {
+            logs.push(ProcessorLogEntry::warn(message.clone()));
+        }
+
+        let compression_ratio = Self::calculate_compression_ratio(total_input_size, output_size);
+        let elapsed = start.elapsed();
+        let duration = elapsed.as_secs_f64();
+        let throughput_mbs = speed_mbs(total_input_size, elapsed);
+
+        let complete_msg = format!(
+            "Compression completed in {:.2}s: {} files -> {} (ratio: {:.1}%)",
+            duration,
+            input.inputs.len(),
+            output_path_str,
+            compression_ratio
+        );
+        info!("{}", complete_msg);
+        logs.push(
+            ProcessorLogEntry::info(complete_msg)
+                .with_field("output", output_path_str.as_str())
+                .with_field("duration_secs", duration)
+                .with_field("compression_ratio_percent", compression_ratio),
+        );
+
+        let metadata = CompressionResultMetadata {
+            rsyncable: config.rsyncable && config.format == ArchiveFormat::TarGz,
+            format: config.format,
+            compression_level: config.compression_level,
+            input_files: input.inputs.clone(),
+            input_count: input.inputs.len().saturating_sub(empty_input_count),
+            entries,
+            total_input_size_bytes: total_input_size,
+            output_size_bytes: output_size,
+            compression_ratio_percent: compression_ratio,
+            appended_to_existing: appending,
+            replaced_entries,
+            skipped_count: skipped_inputs.len(),
+            failed_count: 0,
+            zip64_entries: zip64_entry_count,
+            unstable_inputs,
+            cpu_time_secs: resource_stats.cpu_time_secs,
+            peak_rss_bytes: resource_stats.peak_rss_bytes,
+            skipped_compression_entries,
+            read_retries: read_retries.len(),
+            per_file_stats,
+            throughput_mbs,
+        };
+
+        Ok(ProcessorOutput {
+            outputs: vec![output_path_str.clone()],
+            duration_secs: duration,
+            metadata: Some(metadata.to_json()?),
+            items_produced: vec![output_path_str],
+            input_size_bytes: Some(total_input_size),
+            output_size_bytes: Some(output_size),
+            failed_inputs: vec![],
+            succeeded_inputs,
+            skipped_inputs,
+            logs,
+        })
+    }
+
+    fn validate_min_compression_ratio(ratio: Option<f64>) -> Result<()> {
+        match ratio {
+            Some(ratio) if !(0.0..=1.0).contains(&ratio) => {
+                Err(crate::Error::PipelineError(format!(
+                    "Invalid min_compression_ratio {} (expected 0.0..=1.0)",
+                    ratio
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_buffer_sizes(config: &CompressionConfig) -> Result<()> {
+        for (name, size) in [
+            ("read_buffer_bytes", config.read_buffer_bytes),
+            ("write_buffer_bytes", config.write_buffer_bytes),
+        ] {
+            if let Some(size) = size
+                && size < MIN_IO_BUFFER_BYTES
+            {
+                return Err(crate::Error::PipelineError(format!(
+                    "Invalid {} {} (expected at least {})",
+                    name, size, MIN_IO_BUFFER_BYTES
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn clamp_compression_level(level: u8) -> Result<u8> {
+        if level <= 9 {
+            Ok(level)
+        } else {
+            Err(crate::Error::PipelineError(format!(
+                "Invalid compression_level {} (expected 0..=9)",
+                level
+            )))
+        }
+    }
+}
+
+impl Default for CompressionProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Processor for CompressionProcessor {
+    fn processor_type(&self) -> ProcessorType {
+        ProcessorType::Cpu
+    }
+
+    fn job_types(&self) -> Vec<&'static str> {
+        vec!["compress", "archive"]
+    }
+
+    fn name(&self) -> &'static str {
+        "CompressionProcessor"
+    }
+
+    /// Indicates this processor supports multiple inputs (batch processing).
+    fn supports_batch_input(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> ProcessorCapabilities {
+        static CONFIG_SCHEMA: LazyLock<String> = LazyLock::new(|| {
+            serde_json::to_string(&schemars::schema_for!(CompressionConfig)).unwrap_or_default()
+        });
+
+        ProcessorCapabilities {
+            max_inputs: None,
+            supported_input_extensions: vec![],
+            supported_output_extensions: vec![
+                ArchiveFormat::Zip.extension(),
+                ArchiveFormat::TarGz.extension(),
+                ArchiveFormat::SevenZip.extension(),
+            ],
+            config_schema: Some(CONFIG_SCHEMA.as_str()),
+        }
+    }
+
+    async fn process(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<ProcessorOutput> {
+        self.process_with_virtual_entries(input, ctx, Vec::new())
+            .await
+    }
+
+    fn process_streaming<'a>(
+        &'a self,
+        input: &'a ProcessorInput,
+        ctx: &'a ProcessorContext,
+    ) -> ProcessorEventStream<'a> {
+        streaming_output(move |events| self.compress(input, ctx, Vec::new(), Some(events)))
+    }
+
+    async fn dry_run(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<DryRunReport> {
+        let mut report = DryRunReport::default();
+
+        let mut logs = Vec::new();
+        let config = self.resolve_config(input.config.as_deref(), ctx, &mut logs);
+        report
+            .config_warnings
+            .extend(logs.into_iter().map(|entry| entry.message));
+        let config = match config {
+            Ok(config) => config,
+            Err(e) => {
+                report.config_errors.push(e.to_string());
+                return Ok(report);
+            }
+        };
+
+        if let Err(e) = Self::clamp_compression_level(config.compression_level) {
+            report.config_errors.push(e.to_string());
+        }
+        if !config.format.is_writable() {
+            report
+                .config_errors
+                .push(unwritable_format_error(&config.format).to_string());
+        }
+        if let Err(e) = Self::validate_min_compression_ratio(config.min_compression_ratio) {
+            report.config_errors.push(e.to_string());
+        }
+        if let Err(e) = Self::validate_buffer_sizes(&config) {
+            report.config_errors.push(e.to_string());
+        }
+        if config.symlink_handling == SymlinkHandling::Preserve
+            && config.format != ArchiveFormat::TarGz
+        {
+            report
+                .config_warnings
+                .push(SYMLINK_PRESERVE_ZIP_WARNING.to_string());
+        }
+        if config.rsyncable && config.format != ArchiveFormat::TarGz {
+            report.config_warnings.push(RSYNCABLE_WARNING.to_string());
+        }
+        if !config.solid && config.format == ArchiveFormat::Zip {
+            report.config_warnings.push(SOLID_ZIP_WARNING.to_string());
+        }
+        if config.format == ArchiveFormat::Zip {
+            if let Err(e) = FileMethodRules::compile(
+                config.per_file_method.as_deref(),
+                config.compression_level,
+            ) {
+                report.config_errors.push(e.to_string());
+            }
+        } else if config.per_file_method.is_some() {
+            report
+                .config_warnings
+                .push(PER_FILE_METHOD_WARNING.to_string());
+        }
+
+        if input.inputs.is_empty() && config.virtual_entries.is_empty() {
+            report.config_errors.push(NO_INPUTS_ERROR.to_string());
+        }
+        if let Err(e) = plan_entries(
+            &input.inputs,
+            &config.virtual_entries,
+            config.preserve_paths,
+        ) {
+            report.config_errors.push(e.to_string());
+        }
+        for input_path in &input.inputs {
+            match tokio::fs::metadata(input_path).await {
+                Ok(metadata) if metadata.is_file() => {}
+                Ok(_) => report
+                    .config_errors
+                    .push(format!("Input is not a file: {}", input_path)),
+                Err(_) => report.config_errors.push(missing_input_message(input_path)),
+            }
+        }
+
+        let output_path_str = self.determine_output_path(&input.inputs, &config, input);
+        let output_exists = tokio::fs::try_exists(&output_path_str)
+            .await
+            .unwrap_or(false);
+        let appending = config.append && output_exists;
+        if appending && config.format != ArchiveFormat::Zip {
+            report
+                .config_errors
+                .push(append_unsupported_message(&output_path_str));
+        }
+        if output_exists && !config.overwrite && !appending && !config.skip_if_exists {
+            report
+                .config_errors
+                .push(output_exists_message(&output_path_str));
+        }
+        report.estimated_output_paths.push(output_path_str);
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DEFAULT_TEST_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+    #[test]
+    fn test_compression_processor_type() {
+        let processor = CompressionProcessor::new();
+        assert_eq!(processor.processor_type(), ProcessorType::Cpu);
+    }
+
+    #[test]
+    fn test_compression_processor_job_types() {
+        let processor = CompressionProcessor::new();
+        assert!(processor.can_process("compress"));
+        assert!(processor.can_process("archive"));
+        assert!(!processor.can_process("remux"));
+    }
+
+    #[test]
+    fn test_compression_processor_name() {
+        let processor = CompressionProcessor::new();
+        assert_eq!(processor.name(), "CompressionProcessor");
+    }
+
+    #[test]
+    fn test_compression_processor_supports_batch() {
+        let processor = CompressionProcessor::new();
+        assert!(processor.supports_batch_input());
+    }
+
+    #[test]
+    fn test_archive_format_extensions() {
+        assert_eq!(ArchiveFormat::Zip.extension(), "zip");
+        assert_eq!(ArchiveFormat::TarGz.extension(), "tar.gz");
+    }
+
+    #[test]
+    fn test_archive_format_display_and_parse() {
+        assert_eq!(ArchiveFormat::Zip.to_string(), "zip");
+        assert_eq!(ArchiveFormat::TarGz.to_string(), "tar.gz");
+        for name in ["targz", "tar.gz", "TGZ", "Tar.Gz"] {
+            assert_eq!(name.parse::<ArchiveFormat>(), Ok(ArchiveFormat::TarGz));
+        }
+        assert_eq!(ArchiveFormat::try_from("ZIP"), Ok(ArchiveFormat::Zip));
+        assert_eq!(
+            "rar".parse::<ArchiveFormat>(),
+            Err(ArchiveFormatParseError("rar".to_string()))
+        );
+        for format in [ArchiveFormat::TarBz2, ArchiveFormat::SevenZip] {
+            assert_eq!(format.to_string().parse::<ArchiveFormat>(), Ok(format));
+        }
+    }
+
+    #[test]
+    fn test_detect_archive_format() {
+        assert_eq!(
+            detect_archive_format(b"PK\x03\x04rest"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect_archive_format(b"PK\x05\x06"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect_archive_format(&[0x1f, 0x8b, 0x08, 0x00]),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            detect_archive_format(b"BZh91AY&SY"),
+            Some(ArchiveFormat::TarBz2)
+        );
+        assert_eq!(
+            detect_archive_format(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00, 0x04]),
+            Some(ArchiveFormat::TarXz)
+        );
+        assert_eq!(
+            detect_archive_format(&[0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x00]),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(
+            detect_archive_format(&[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c, 0x00, 0x04]),
+            Some(ArchiveFormat::SevenZip)
+        );
+        // Truncated signatures are not enough.
+        assert_eq!(detect_archive_format(&[0xfd, 0x37, 0x7a, 0x58]), None);
+        assert_eq!(detect_archive_format(b"7z"), None);
+        assert_eq!(detect_archive_format(b"plain text"), None);
+        assert_eq!(detect_archive_format(b""), None);
+    }
+
+    #[tokio::test]
+    async fn test_unwritable_format_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, "data").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "tarxz"}).to_string()),
+            ..Default::default()
+        };
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+
+        let err = processor.process(&input, &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+        assert!(!processor.dry_run(&input, &ctx).await.unwrap().is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit_rejects_large_inputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, [0u8; 1000]).unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"output_path": output_path.to_string_lossy()}).to_string(),
+            ),
+            ..Default::default()
+        };
+        let processor = CompressionProcessor::new();
+        let limits = |max_memory_bytes| super::super::traits::ResourceLimits {
+            max_memory_bytes: Some(max_memory_bytes),
+            ..Default::default()
+        };
+
+        let ctx = ProcessorContext::noop_with_limits("test", limits(999));
+        let err = processor.process(&input, &ctx).await.unwrap_err();
+        assert!(matches!(err, crate::Error::ResourceExhausted(_)));
+        assert!(!output_path.exists());
+
+        let ctx = ProcessorContext::noop_with_limits("test", limits(1000));
+        processor.process(&input, &ctx).await.unwrap();
+        assert!(output_path.exists());
+    }
+
+    #[test]
+    fn test_compression_config_default() {
+        let config = CompressionConfig::default();
+        assert_eq!(config.format, ArchiveFormat::Zip);
+        assert_eq!(config.compression_level, 6);
+        assert!(config.output_path.is_none());
+        assert!(config.overwrite);
+        assert!(!config.preserve_paths);
+        assert!(!config.append);
+        assert!(!config.force_zip64);
+        assert!(!config.rsyncable);
+        assert!(config.solid);
+        assert!(config.collect_resource_stats.cpu_time);
+        assert_eq!(config.progress_interval_ms, 250);
+        assert!(config.min_progress_delta_percent.is_none());
+        assert!(!config.collect_resource_stats.peak_rss);
+        assert_eq!(config.read_buffer_bytes, Some(65536));
+        assert_eq!(config.write_buffer_bytes, Some(65536));
+    }
+
+    #[test]
+    fn test_compression_capabilities() {
+        let capabilities = CompressionProcessor::new().capabilities();
+        assert_eq!(capabilities.max_inputs, None);
+        assert_eq!(
+            capabilities.supported_output_extensions,
+            vec!["zip", "tar.gz", "7z"]
+        );
+
+        let schema: serde_json::Value =
+            serde_json::from_str(capabilities.config_schema.unwrap()).unwrap();
+        let properties = &schema["properties"];
+        assert_eq!(properties["compression_level"]["default"], 6);
+        assert!(properties["stability_check"].is_object());
+        assert!(properties["progress_interval_ms"].is_object());
+    }
+
+    #[test]
+    fn test_zip64_enabled_for_large_entries_and_entry_counts() {
+        let synthetic = |size: u64| EntryPlan {
+            input_path: "/virtual/large.flv".to_string(),
+            archive_name: "large.flv".to_string(),
+            size,
+            modified: None,
+            virtual_source: None,
+            link_target: None,
+        };
+        let entries = [synthetic(u32::MAX as u64 + 1), synthetic(1024)];
+        let force = zip64_for_all_entries(entries.len(), false);
+
+        let flags: Vec<bool> = entries
+            .iter()
+            .map(|entry| needs_zip64(entry.size, force))
+            .collect();
+        assert_eq!(flags, vec![true, false]);
+
+        assert!(!zip64_for_all_entries(ZIP_CLASSIC_MAX_ENTRIES, false));
+        assert!(zip64_for_all_entries(ZIP_CLASSIC_MAX_ENTRIES + 1, false));
+        assert!(zip64_for_all_entries(1, true));
+    }
+
+    #[test]
+    fn test_needs_zip64() {
+        assert!(!needs_zip64(0, false));
+        assert!(!needs_zip64(1024 * 1024 * 1024, false));
+        assert!(needs_zip64(u32::MAX as u64, false));
+        assert!(needs_zip64(5 * 1024 * 1024 * 1024, false));
+        assert!(needs_zip64(0, true));
+    }
+
+    fn profiled_processor() -> CompressionProcessor {
+        CompressionProcessor::new().with_profiles(HashMap::from([
+            (
+                "archive".to_string(),
+                serde_json::json!({
+                    "format": "targz",
+                    "compression_level": 9,
+                    "preserve_paths": true,
+                }),
+            ),
+            (
+                "fast".to_string(),
+                serde_json::json!({ "compression_level": 1 }),
+            ),
+        ]))
+    }
+
+    fn resolve(processor: &CompressionProcessor, raw: Option<&str>) -> Result<CompressionConfig> {
+        processor.resolve_config(raw, &ProcessorContext::noop("test"), &mut Vec::new())
+    }
+
+    #[test]
+    fn test_profile_precedence() {
+        let processor = profiled_processor();
+
+        // Defaults apply without a profile.
+        let config = resolve(&processor, Some(r#"{"compression_level": 3}"#)).unwrap();
+        assert_eq!(config.format, ArchiveFormat::Zip);
+        assert_eq!(config.compression_level, 3);
+
+        // The profile replaces defaults.
+        let config = resolve(&processor, Some(r#"{"profile": "archive"}"#)).unwrap();
+        assert_eq!(config.format, ArchiveFormat::TarGz);
+        assert_eq!(config.compression_level, 9);
+        assert!(config.preserve_paths);
+        assert!(config.overwrite);
+
+        // The job config replaces the profile, field by field.
+        let config = resolve(
+            &processor,
+            Some(r#"{"profile": "archive", "compression_level": 2}"#),
+        )
+        .unwrap();
+        assert_eq!(config.format, ArchiveFormat::TarGz);
+        assert_eq!(config.compression_level, 2);
+        assert_eq!(config.profile.as_deref(), Some("archive"));
+    }
+
+    #[test]
+    fn test_unknown_profile_lists_available_profiles() {
+        let err = resolve(&profiled_processor(), Some(r#"{"profile": "slow"}"#)).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("'slow'"));
+        assert!(message.contains("archive, fast"));
+
+        let err =
+            resolve(&CompressionProcessor::new(), Some(r#"{"profile": "slow"}"#)).unwrap_err();
+        assert!(err.to_string().contains("available profiles: none"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_profile_fails_job_and_dry_run() {
+        let input = ProcessorInput {
+            inputs: vec!["/tmp/input.flv".to_string()],
+            config: Some(r#"{"profile": "missing"}"#.to_string()),
+            ..Default::default()
+        };
+        let processor = profiled_processor();
+        let ctx = ProcessorContext::noop("test");
+
+        assert!(processor.process(&input, &ctx).await.is_err());
+        let report = processor.dry_run(&input, &ctx).await.unwrap();
+        assert!(report.config_errors[0].contains("Unknown compression profile"));
+    }
+
+    #[test]
+    fn test_progress_throttle_defaults_to_interval_only() {
+        let throttle = ProgressThrottle::from_config(&CompressionConfig::default());
+        assert_eq!(throttle.interval, DEFAULT_TEST_INTERVAL);
+        assert!(throttle.min_delta_percent.is_none());
+
+        let now = std::time::Instant::now();
+        assert!(!throttle.should_report(now, Some(0.0), Some(90.0)));
+        let earlier = now - DEFAULT_TEST_INTERVAL;
+        assert!(throttle.should_report(earlier, Some(0.0), Some(0.0)));
+    }
+
+    #[test]
+    fn test_progress_throttle_min_delta() {
+        let config = CompressionConfig {
+            progress_interval_ms: 60_000,
+            min_progress_delta_percent: Some(5.0),
+            ..Default::default()
+        };
+        let throttle = ProgressThrottle::from_config(&config);
+        let now = std::time::Instant::now();
+        assert!(!throttle.should_report(now, Some(10.0), Some(14.9)));
+        assert!(throttle.should_report(now, Some(10.0), Some(15.0)));
+        assert!(!throttle.should_report(now, None, None));
+
+        let disabled = ProgressThrottle::from_config(&CompressionConfig {
+            min_progress_delta_percent: Some(0.0),
+            ..config
+        });
+        assert!(disabled.min_delta_percent.is_none());
+    }
+
+    #[test]
+    fn test_progress_reader_reports_per_file_progress() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let throttle = ProgressThrottle::from_config(&CompressionConfig {
+            progress_interval_ms: 0,
+            ..Default::default()
+        });
+        // Third of three files: 1000 bytes were archived before this 400-byte entry.
+        let mut reader = CancelProgressReader::new(
+            std::io::Cursor::new(vec![0u8; 400]),
+            CompressionProgressContext {
+                cancel: CancellationToken::new(),
+                progress: ProgressReporter::new("test", tx),
+                bytes_total: 1400,
+                bytes_done: 1000,
+                file_index: 3,
+                file_count: 3,
+                current_file: "big.flv".to_string(),
+                file_bytes_total: 400,
+            },
+            throttle,
+        );
+
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+
+        let snapshots = drain_snapshots(&mut rx);
+        let first = &snapshots[0];
+        assert_eq!(first.bytes_done, Some(1100));
+        assert_eq!(first.bytes_total, Some(1400));
+        assert_eq!(first.raw["file_index"], 3);
+        assert_eq!(first.raw["file_bytes_done"], 100);
+        assert_eq!(first.raw["file_bytes_total"], 400);
+        assert_eq!(first.raw["file_percent"], 25.0);
+
+        let last = snapshots.last().unwrap();
+        assert_eq!(last.bytes_done, Some(1400));
+        assert_eq!(last.raw["file_bytes_done"], 400);
+        assert_eq!(last.raw["file_percent"], 100.0);
+    }
+
+    fn write_scan_inputs(dir: &Path, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|idx| {
+                let path = dir.join(format!("file_{}.txt", idx));
+                std::fs::write(&path, vec![b'x'; idx % 7]).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_inputs_preserves_order_and_reports_scanning_phase() {
+        let temp_dir = TempDir::new().unwrap();
+        let inputs = write_scan_inputs(temp_dir.path(), 1000);
+        let input_paths: Vec<&str> = inputs.iter().map(String::as_str).collect();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let progress = ProgressReporter::new("test", tx);
+        let scan = scan_inputs(
+            &input_paths,
+            &progress,
+            DEFAULT_TEST_INTERVAL,
+            None,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+        assert_eq!(scan.inputs.len(), 1000);
+        for (idx, scanned) in scan.inputs.iter().enumerate() {
+            assert_eq!(scanned.size, (idx % 7) as u64);
+        }
+        assert_eq!(
+            scan.total_size,
+            (0..1000u64).map(|idx| idx % 7).sum::<u64>()
+        );
+
+        let mut last = None;
+        while let Ok(update) = rx.try_recv() {
+            last = Some(update.snapshot);
+        }
+        let last = last.expect("scan should report progress");
+        assert_eq!(last.raw["phase"], "scanning");
+        assert_eq!(last.raw["files_scanned"], 1000);
+        assert_eq!(last.percent, Some(100.0));
+    }
+
+    #[test]
+    fn test_scan_inputs_with_one_thread_matches_parallel_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let inputs = write_scan_inputs(temp_dir.path(), 300);
+        let input_paths: Vec<&str> = inputs.iter().map(String::as_str).collect();
+        let scan = |max_threads| {
+            scan_inputs(
+                &input_paths,
+                &ProgressReporter::noop(),
+                DEFAULT_TEST_INTERVAL,
+                max_threads,
+                &CancellationToken::new(),
+            )
+            .unwrap()
+        };
+
+        let parallel = scan(None);
+        let single = scan(Some(1));
+
+        assert_eq!(single.total_size, parallel.total_size);
+        assert_eq!(
+            single
+                .inputs
+                .iter()
+                .map(|input| input.size)
+                .collect::<Vec<_>>(),
+            parallel
+                .inputs
+                .iter()
+                .map(|input| input.size)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_scan_inputs_honors_cancellation() {
+        let temp_dir = TempDir::new().unwrap();
+        let inputs = write_scan_inputs(temp_dir.path(), 10);
+        let input_paths: Vec<&str> = inputs.iter().map(String::as_str).collect();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = scan_inputs(
+            &input_paths,
+            &ProgressReporter::noop(),
+            DEFAULT_TEST_INTERVAL,
+            None,
+            &cancel,
+        );
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+    }
+
+    #[test]
+    fn test_scan_inputs_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut inputs = write_scan_inputs(temp_dir.path(), 3);
+        inputs.push(
+            temp_dir
+                .path()
+                .join("missing.txt")
+                .to_string_lossy()
+                .to_string(),
+        );
+        let input_paths: Vec<&str> = inputs.iter().map(String::as_str).collect();
+
+        let result = scan_inputs(
+            &input_paths,
+            &ProgressReporter::noop(),
+            DEFAULT_TEST_INTERVAL,
+            None,
+            &CancellationToken::new(),
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Input file does not exist")
+        );
+    }
+
+    #[test]
+    fn test_compression_config_parse() {
+        let json = r#"{
+            "format": "targz",
+            "compression_level": 9,
+            "output_path": "/output/archive.tar.gz",
+            "overwrite": false,
+            "preserve_paths": true
+        }"#;
+
+        let config: CompressionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.format, ArchiveFormat::TarGz);
+        assert_eq!(config.compression_level, 9);
+        assert_eq!(
+            config.output_path,
+            Some("/output/archive.tar.gz".to_string())
+        );
+        assert!(!config.overwrite);
+        assert!(config.preserve_paths);
+    }
+
+    #[test]
+    fn test_compression_config_parse_zip() {
+        let json = r#"{"format": "zip"}"#;
+        let config: CompressionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.format, ArchiveFormat::Zip);
+    }
+
+    #[test]
+    fn test_calculate_compression_ratio() {
+        // 50% compression
+        assert!((CompressionProcessor::calculate_compression_ratio(1000, 500) - 50.0).abs() < 0.01);
+        // No compression
+        assert!((CompressionProcessor::calculate_compression_ratio(1000, 1000) - 0.0).abs() < 0.01);
+        // 90% compression
+        assert!((CompressionProcessor::calculate_compression_ratio(1000, 100) - 90.0).abs() < 0.01);
+        // Zero input size
+        assert!((CompressionProcessor::calculate_compression_ratio(0, 0) - 0.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_get_archive_filename_no_preserve() {
+        let filename = archive_entry_name("/path/to/file.txt", false).unwrap();
+        assert_eq!(filename, "file.txt");
+    }
+
+    #[test]
+    fn test_get_archive_filename_preserve() {
+        let filename = archive_entry_name("/path/to/file.txt", true).unwrap();
+        assert_eq!(filename, "path/to/file.txt");
+    }
+
+    #[test]
+    fn test_archive_entry_name_windows_paths() {
+        let cases = [
+            (r"C:\rec\streamer\file.flv", "rec/streamer/file.flv"),
+            (r"C:/rec/streamer\file.flv", "rec/streamer/file.flv"),
+            (r"c:file.flv", "file.flv"),
+            (r"\\server\share\rec\file.flv", "rec/file.flv"),
+            (r"\\server/share/rec\file.flv", "rec/file.flv"),
+            (r"\\?\C:\rec\file.flv", "rec/file.flv"),
+            (r"\\?\UNC\server\share\rec\file.flv", "rec/file.flv"),
+            (r"\\.\D:\rec\file.flv", "rec/file.flv"),
+            (r"rec\..\other\.\file.flv", "other/file.flv"),
+            (r"..\..\file.flv", "file.flv"),
+            ("rec//streamer/./file.flv", "rec/streamer/file.flv"),
+        ];
+        for (input, expected) in cases {
+            let name = archive_entry_name(input, true).unwrap();
+            assert_eq!(name, expected, "{input}");
+            assert!(!name.contains('\\'), "{input}");
+            assert_eq!(
+                archive_entry_name(input, false).unwrap(),
+                "file.flv",
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_archive_entry_name_rejects_empty_names() {
+        for input in [
+            "",
+            r"C:\",
+            r"\\server\share\",
+            r"\\?\C:\",
+            "..",
+            "/",
+            r"rec\..",
+        ] {
+            assert!(archive_entry_name(input, true).is_err(), "{input}");
+            assert!(archive_entry_name(input, false).is_err(), "{input}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_zip_archive_single_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        // Create input file
+        std::fs::write(&input_path, "test content for compression").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "zip"}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        // Verify archive was created
+        assert!(output_path.exists());
+        assert_eq!(
+            output.outputs,
+            vec![output_path.to_string_lossy().to_string()]
+        );
+        assert!(output.input_size_bytes.is_some());
+        assert!(output.output_size_bytes.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_process_streaming_reports_archive_before_completion() {
+        use futures::StreamExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "test content for compression").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "zip"}).to_string()),
+            ..Default::default()
+        };
+
+        let mut events = processor.process_streaming(&input, &ctx);
+        match events.next().await {
+            Some(Ok(StreamingOutputEvent::ItemProduced(path))) => {
+                assert_eq!(path, output_path.to_string_lossy());
+                assert!(output_path.exists());
+            }
+            other => panic!("expected ItemProduced, got {other:?}"),
+        }
+        match events.next().await {
+            Some(Ok(StreamingOutputEvent::Completed(output))) => {
+                assert_eq!(
+                    output.outputs,
+                    vec![output_path.to_string_lossy().to_string()]
+                );
+            }
+            other => panic!("expected Completed, got {other:?}"),
+        }
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_tar_gz_archive_single_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+
+        // Create input file
+        std::fs::write(&input_path, "test content for compression").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "targz"}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        // Verify archive was created
+        assert!(output_path.exists());
+        assert_eq!(
+            output.outputs,
+            vec![output_path.to_string_lossy().to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_zip_archive_multiple_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let input1 = temp_dir.path().join("file1.txt");
+        let input2 = temp_dir.path().join("file2.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        // Create input files
+        std::fs::write(&input1, "content of file 1").unwrap();
+        std::fs::write(&input2, "content of file 2").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![
+                input1.to_string_lossy().to_string(),
+                input2.to_string_lossy().to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "zip"}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        // Verify archive was created
+        assert!(output_path.exists());
+
+        // Verify metadata contains file count
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.input_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_archive_size_limit_aborts_and_removes_partial_archive() {
+        for (format, extension) in [("zip", "zip"), ("targz", "tar.gz"), ("7z", "7z")] {
+            let temp_dir = TempDir::new().unwrap();
+            // Incompressible content, so each file adds its full size to the archive.
+            let mut state = 0x2545_f491_u32;
+            let inputs: Vec<String> = (0..3)
+                .map(|i| {
+                    let content: Vec<u8> = (0..64 * 1024)
+                        .map(|_| {
+                            state ^= state << 13;
+                            state ^= state >> 17;
+                            state ^= state << 5;
+                            state as u8
+                        })
+                        .collect();
+                    let path = temp_dir.path().join(format!("file{i}.bin"));
+                    std::fs::write(&path, content).unwrap();
+                    path.to_string_lossy().to_string()
+                })
+                .collect();
+            let output_path = temp_dir.path().join(format!("output.{extension}"));
+
+            let processor = CompressionProcessor::new();
+            let ctx = ProcessorContext::noop("test");
+            let input = ProcessorInput {
+                inputs,
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(
+                    serde_json::json!({
+                        "format": format,
+                        "write_buffer_bytes": 4096,
+                        "max_archive_size_bytes": 32 * 1024,
+                    })
+                    .to_string(),
+                ),
+                streamer_id: "test".to_string(),
+                session_id: "test".to_string(),
+                ..Default::default()
+            };
+
+            let err = processor.process(&input, &ctx).await.unwrap_err();
+            assert!(
+                err.to_string().contains(ARCHIVE_SIZE_LIMIT_EXCEEDED),
+                "{format}: {err}"
+            );
+            assert!(!output_path.exists(), "{format}");
+            assert!(!tmp_output_path(&output_path).exists(), "{format}");
+        }
+    }
+
+    #[test]
+    fn test_archive_size_limit_records_abort_point() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.zip");
+        std::fs::write(&path, [0u8; 100]).unwrap();
+
+        assert!(ArchiveSizeLimit::default().check(&path, 1).is_ok());
+        let limit = ArchiveSizeLimit::new(Some(100));
+        assert!(limit.check(&path, 1).is_ok());
+        assert_eq!(limit.exceeded(), None);
+
+        let limit = ArchiveSizeLimit::new(Some(99));
+        assert!(limit.clone().check(&path, 2).is_err());
+        assert_eq!(limit.exceeded(), Some((2, 100)));
+    }
+
+    #[tokio::test]
+    async fn test_create_tar_gz_archive_multiple_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let input1 = temp_dir.path().join("file1.txt");
+        let input2 = temp_dir.path().join("file2.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+
+        // Create input files
+        std::fs::write(&input1, "content of file 1").unwrap();
+        std::fs::write(&input2, "content of file 2").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![
+                input1.to_string_lossy().to_string(),
+                input2.to_string_lossy().to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "targz"}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        // Verify archive was created
+        assert!(output_path.exists());
+
+        // Verify metadata contains file count
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.input_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_per_file_stats_show_incompressible_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let text_path = temp_dir.path().join("chat.txt");
+        let noise_path = temp_dir.path().join("noise.bin");
+        std::fs::write(&text_path, "hello chat\n".repeat(4000)).unwrap();
+        let mut state = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..32 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        std::fs::write(&noise_path, &noise).unwrap();
+
+        for format in ["zip", "targz"] {
+            let output_path = temp_dir.path().join(format!("output.{}", format));
+            let input = ProcessorInput {
+                inputs: vec![
+                    text_path.to_string_lossy().to_string(),
+                    noise_path.to_string_lossy().to_string(),
+                ],
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(serde_json::json!({"format": format}).to_string()),
+                ..Default::default()
+            };
+            let output = CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
+
+            let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+            let stats = &metadata.per_file_stats;
+            assert_eq!(stats.len(), 2, "{}", format);
+            assert_eq!(stats[0].name, "chat.txt");
+            assert_eq!(stats[0].input_size_bytes, 44_000);
+            assert!(stats[0].compression_ratio_percent > 90.0, "{}", format);
+            assert_eq!(stats[1].name, "noise.bin");
+            assert_eq!(stats[1].input_size_bytes, noise.len() as u64);
+            assert!(stats[1].compression_ratio_percent < 5.0, "{}", format);
+            let compressed: u64 = stats.iter().map(|stat| stat.output_size_bytes).sum();
+            assert!(compressed > 0 && compressed <= metadata.output_size_bytes);
+            assert!(stats.iter().all(|stat| stat.per_file_speed_mbs > 0.0));
+            assert!(metadata.throughput_mbs > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_speed_mbs() {
+        let second = std::time::Duration::from_secs(1);
+        assert_eq!(speed_mbs(3 * 1024 * 1024, second), 3.0);
+        assert_eq!(speed_mbs(1024 * 1024, second / 4), 4.0);
+        assert_eq!(speed_mbs(1024, std::time::Duration::ZERO), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_solid_and_per_entry_tar_gz_archives() {
+        let temp_dir = TempDir::new().unwrap();
+        let contents = ["first file\n".repeat(500), "second file\n".repeat(500)];
+        let inputs: Vec<String> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = temp_dir.path().join(format!("file{}.txt", i + 1));
+                std::fs::write(&path, content).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        for (solid, rsyncable, expected_members) in
+            [(true, false, 1), (false, false, 2), (false, true, 2)]
+        {
+            let output_path = temp_dir
+                .path()
+                .join(format!("output-{}-{}.tar.gz", solid, rsyncable));
+            let input = ProcessorInput {
+                inputs: inputs.clone(),
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(
+                    serde_json::json!({
+                        "format": "targz",
+                        "solid": solid,
+                        "rsyncable": rsyncable,
+                    })
+                    .to_string(),
+                ),
+                ..Default::default()
+            };
+            CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
+
+            // Count the gzip members by decoding them one after another.
+            let data = std::fs::read(&output_path).unwrap();
+            let mut remaining = data.as_slice();
+            let mut members = 0;
+            while !remaining.is_empty() {
+                let mut decoder = flate2::bufread::GzDecoder::new(&mut remaining);
+                std::io::copy(&mut decoder, &mut std::io::sink()).unwrap();
+                members += 1;
+            }
+            assert_eq!(members, expected_members, "solid = {}", solid);
+
+            let decoder = flate2::read::MultiGzDecoder::new(File::open(&output_path).unwrap());
+            let mut archive = tar::Archive::new(decoder);
+            let mut extracted = Vec::new();
+            for entry in archive.entries().unwrap() {
+                let mut entry = entry.unwrap();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                extracted.push(content);
+            }
+            assert_eq!(extracted, contents, "solid = {}", solid);
+        }
+    }
+
+    /// Blocks of the 7z archive at `path` and its entries with their contents.
+    fn seven_zip_contents(path: &Path) -> (usize, Vec<(String, String)>) {
+        let mut reader =
+            sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty()).unwrap();
+        let blocks = reader.archive().folders.len();
+        let mut entries = Vec::new();
+        reader
+            .for_each_entries(|entry, data| {
+                let mut content = String::new();
+                data.read_to_string(&mut content).unwrap();
+                entries.push((entry.name().to_string(), content));
+                Ok(true)
+            })
+            .unwrap();
+        (blocks, entries)
+    }
+
+    #[tokio::test]
+    async fn test_create_7z_archive_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        let contents = ["first file\n".repeat(500), "second file\n".repeat(500)];
+        let inputs: Vec<String> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = nested.join(format!("file{}.txt", i + 1));
+                std::fs::write(&path, content).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        for (solid, preserve_paths, expected_blocks) in
+            [(true, false, 1), (false, false, 2), (true, true, 1)]
+        {
+            let output_path = temp_dir
+                .path()
+                .join(format!("output-{}-{}.7z", solid, preserve_paths));
+            let input = ProcessorInput {
+                inputs: inputs.clone(),
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(
+                    serde_json::json!({
+                        "format": "7z",
+                        "solid": solid,
+                        "preserve_paths": preserve_paths,
+                    })
+                    .to_string(),
+                ),
+                ..Default::default()
+            };
+            let output = CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
+
+            let header = std::fs::read(&output_path).unwrap();
+            assert_eq!(
+                detect_archive_format(&header),
+                Some(ArchiveFormat::SevenZip)
+            );
+            let (blocks, entries) = seven_zip_contents(&output_path);
+            assert_eq!(blocks, expected_blocks, "solid = {}", solid);
+            let expected: Vec<(String, String)> = inputs
+                .iter()
+                .zip(&contents)
+                .map(|(path, content)| {
+                    (
+                        archive_entry_name(path, preserve_paths).unwrap(),
+                        content.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(entries, expected, "solid = {}", solid);
+
+            let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+            assert_eq!(metadata.format, ArchiveFormat::SevenZip);
+            assert!(metadata.output_size_bytes < metadata.total_input_size_bytes);
+            for (entry, content) in metadata.entries.iter().zip(&contents) {
+                let mut crc = flate2::Crc::new();
+                crc.update(content.as_bytes());
+                assert_eq!(entry.crc32, crc.sum());
+                assert_eq!(entry.size_bytes, content.len() as u64);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_7z_archive_at_level_zero() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.7z");
+        std::fs::write(&input_path, "level zero content").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"format": "sevenzip", "compression_level": 0}).to_string(),
+            ),
+            ..Default::default()
+        };
+        CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        let (_, entries) = seven_zip_contents(&output_path);
+        assert_eq!(
+            entries,
+            [("input.txt".to_string(), "level zero content".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_rsyncable_tar_gz_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        let content = "rsyncable content line\n".repeat(5000);
+        std::fs::write(&input_path, &content).unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "targz", "rsyncable": true}).to_string()),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.rsyncable);
+
+        // The archive must be readable with the standard single-member decoder.
+        let decoder = flate2::read::GzDecoder::new(File::open(&output_path).unwrap());
+        let mut archive = tar::Archive::new(decoder);
+        let mut entries = archive.entries().unwrap();
+        let mut entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().to_string_lossy(), "input.txt");
+        let mut extracted = String::new();
+        entry.read_to_string(&mut extracted).unwrap();
+        assert_eq!(extracted, content);
+        assert!(entries.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compression_ratio_in_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        // Create a larger input file for better compression
+        let content = "a".repeat(10000);
+        std::fs::write(&input_path, &content).unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "zip", "compression_level": 9}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        // Verify compression ratio is recorded
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.compression_ratio_percent > 0.0);
+        assert!(metadata.total_input_size_bytes > 0);
+        assert!(metadata.output_size_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_resource_stats_in_metadata_and_final_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "a".repeat(10000)).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let mut ctx = ProcessorContext::noop("test");
+        ctx.progress = ProgressReporter::new("test", tx);
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"collect_resource_stats": {"peak_rss": true}}).to_string(),
+            ),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = CompressionProcessor::new()
+            .process(&input, &ctx)
+            .await
+            .unwrap();
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.cpu_time_secs.is_some_and(|secs| secs >= 0.0));
+        if cfg!(target_os = "linux") {
+            assert!(metadata.peak_rss_bytes.is_some_and(|bytes| bytes > 0));
+        }
+
+        let mut last = None;
+        while let Ok(update) = rx.try_recv() {
+            last = Some(update.snapshot);
+        }
+        let last = last.expect("compression should report a final snapshot");
+        assert_eq!(last.raw["phase"], "finalized");
+        assert_eq!(last.percent, Some(100.0));
+        assert_eq!(last.bytes_done, Some(10000));
+        assert_eq!(last.raw["peak_rss_bytes"].as_u64(), metadata.peak_rss_bytes);
+    }
+
+    fn drain_snapshots(
+        rx: &mut tokio::sync::mpsc::Receiver<crate::pipeline::progress::JobProgressUpdate>,
+    ) -> Vec<JobProgressSnapshot> {
+        let mut snapshots = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            snapshots.push(update.snapshot);
+        }
+        snapshots
+    }
+
+    #[tokio::test]
+    async fn test_tmp_dir_builds_archive_outside_output_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let build_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("out").join("output.zip");
+        std::fs::write(&input_path, "a".repeat(10000)).unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({ "tmp_dir": build_dir.path().join("staging") }).to_string(),
+            ),
+            ..Default::default()
+        };
+
+        CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        let archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        let staging = std::fs::read_dir(build_dir.path().join("staging")).unwrap();
+        assert_eq!(staging.count(), 0);
+        let output_dir = std::fs::read_dir(temp_dir.path().join("out")).unwrap();
+        assert_eq!(output_dir.count(), 1);
+    }
+
+    #[test]
+    fn test_copy_with_progress_reports_copying_phase() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("archive.zip.tmp");
+        let to = temp_dir.path().join("archive.zip");
+        std::fs::write(&from, vec![7u8; 3 * 1024 * 1024]).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let progress = ProgressReporter::new("test", tx);
+        let throttle = ProgressThrottle::from_config(&CompressionConfig {
+            progress_interval_ms: 0,
+            ..Default::default()
+        });
+
+        copy_with_progress(&from, &to, &progress, throttle, &CancellationToken::new()).unwrap();
+
+        assert_eq!(std::fs::read(&to).unwrap(), std::fs::read(&from).unwrap());
+        let snapshots = drain_snapshots(&mut rx);
+        assert!(snapshots.iter().all(|s| s.raw["phase"] == "copying"));
+        let last = snapshots.last().unwrap();
+        assert_eq!(last.bytes_done, Some(3 * 1024 * 1024));
+        assert_eq!(last.percent, Some(100.0));
+    }
+
+    #[test]
+    fn test_copy_with_progress_honors_cancellation() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("archive.zip.tmp");
+        let to = temp_dir.path().join("archive.zip");
+        std::fs::write(&from, b"archive").unwrap();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = copy_with_progress(
+            &from,
+            &to,
+            &ProcessorContext::noop("test").progress,
+            ProgressThrottle::from_config(&CompressionConfig::default()),
+            &cancel,
+        );
+
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+    }
+
+    #[test]
+    fn test_check_free_space() {
+        let dir = Path::new("/data/tmp");
+
+        assert!(check_free_space(dir, 100, Some(200)).is_ok());
+        assert!(check_free_space(dir, 100, None).is_ok());
+        let err = check_free_space(dir, 100, Some(10)).unwrap_err();
+        assert!(err.to_string().contains("/data/tmp"));
+    }
+
+    #[test]
+    fn test_estimated_archive_space_counts_inputs_and_existing_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let existing = temp_dir.path().join("existing.zip");
+        std::fs::write(&input_path, [0u8; 100]).unwrap();
+        std::fs::write(&existing, [0u8; 50]).unwrap();
+        let inputs = vec![
+            input_path.to_string_lossy().to_string(),
+            temp_dir
+                .path()
+                .join("missing")
+                .to_string_lossy()
+                .to_string(),
+        ];
+        let virtual_entries = vec![VirtualEntry::from_bytes("notes.txt", b"12345".to_vec())];
+
+        assert_eq!(estimated_archive_space(&inputs, &[], None), 100);
+        assert_eq!(
+            estimated_archive_space(&inputs, &virtual_entries, Some(&existing)),
+            155
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finalized_snapshot_reports_full_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&input_path, "a".repeat(10000)).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let mut ctx = ProcessorContext::noop("test");
+        ctx.progress = ProgressReporter::new("test", tx);
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "targz"}).to_string()),
+            ..Default::default()
+        };
+
+        CompressionProcessor::new()
+            .process(&input, &ctx)
+            .await
+            .unwrap();
+
+        let snapshots = drain_snapshots(&mut rx);
+        let finalized = snapshots
+            .last()
+            .expect("compression should report a final snapshot");
+        assert_eq!(finalized.raw["phase"], "finalized");
+        assert_eq!(finalized.percent, Some(100.0));
+        assert_eq!(finalized.bytes_done, Some(10000));
+        assert_eq!(finalized.bytes_done, finalized.bytes_total);
+        assert_eq!(
+            snapshots
+                .iter()
+                .filter(|snapshot| snapshot.raw["phase"] == "finalized")
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_compression_reports_failed_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("empty.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "").unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let mut ctx = ProcessorContext::noop("test");
+        ctx.progress = ProgressReporter::new("test", tx);
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+
+        let result = CompressionProcessor::new().process(&input, &ctx).await;
+
+        assert!(result.is_err());
+        let snapshots = drain_snapshots(&mut rx);
+        let last = snapshots.last().expect("a terminal snapshot");
+        assert_eq!(last.raw["phase"], "failed");
+        assert!(
+            snapshots
+                .iter()
+                .all(|snapshot| snapshot.raw["phase"] != "finalized")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_compression_reports_cancelled_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "hello").unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let mut ctx = ProcessorContext::noop("test");
+        ctx.progress = ProgressReporter::new("test", tx);
+        ctx.cancellation_token.cancel();
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+
+        let result = CompressionProcessor::new().process(&input, &ctx).await;
+
+        assert!(result.is_err());
+        let snapshots = drain_snapshots(&mut rx);
+        assert_eq!(snapshots.last().unwrap().raw["phase"], "cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_resource_stats_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "hello").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"collect_resource_stats": {"cpu_time": false}}).to_string(),
+            ),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.cpu_time_secs.is_none());
+        assert!(metadata.peak_rss_bytes.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_resolves_output_without_side_effects() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, "hello").unwrap();
+        let output_path = temp_dir.path().join("nested").join("output.zip");
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"rsyncable": true}).to_string()),
+            ..Default::default()
+        };
+        let report = CompressionProcessor::new()
+            .dry_run(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert!(report.is_valid());
+        assert_eq!(
+            report.estimated_output_paths,
+            vec![output_path.to_string_lossy().to_string()]
+        );
+        assert_eq!(report.config_warnings.len(), 1);
+        assert!(!output_path.parent().unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_config_and_input_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&output_path, "existing").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![
+                temp_dir
+                    .path()
+                    .join("missing.txt")
+                    .to_string_lossy()
+                    .to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({
+                    "format": "targz",
+                    "compression_level": 12,
+                    "overwrite": false,
+                })
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        let report = CompressionProcessor::new()
+            .dry_run(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert_eq!(report.config_errors.len(), 3, "{:?}", report.config_errors);
+        assert!(report.config_errors[0].contains("compression_level"));
+        assert!(report.config_errors[1].contains("does not exist"));
+        assert!(report.config_errors[2].contains("already exists"));
+        assert_eq!(std::fs::read(&output_path).unwrap(), b"existing");
+    }
+
+    #[tokio::test]
+    async fn test_process_skips_archiving_in_dry_run_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, "hello").unwrap();
+        let output_path = temp_dir.path().join("output.zip");
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let ctx = ProcessorContext::noop("test").dry_run_context();
+        let output = CompressionProcessor::new()
+            .process(&input, &ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output.outputs,
+            vec![output_path.to_string_lossy().to_string()]
+        );
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn test_compression_result_metadata_round_trip() {
+        let metadata = CompressionResultMetadata {
+            format: ArchiveFormat::TarGz,
+            compression_level: 6,
+            input_files: vec!["/tmp/a.txt".to_string(), "/tmp/b.txt".to_string()],
+            input_count: 2,
+            entries: vec![CompressionEntryMetadata {
+                input_path: "/tmp/a.txt".to_string(),
+                archive_name: "a.txt".to_string(),
+                size_bytes: 5,
+                crc32: 0x3610_a686,
+                is_virtual: false,
+            }],
+            total_input_size_bytes: 5,
+            output_size_bytes: 120,
+            compression_ratio_percent: 2400.0,
+            appended_to_existing: false,
+            replaced_entries: vec![],
+            skipped_count: 1,
+            failed_count: 0,
+            zip64_entries: 0,
+            rsyncable: true,
+            unstable_inputs: vec!["/tmp/b.txt".to_string()],
+            cpu_time_secs: Some(0.25),
+            peak_rss_bytes: Some(64 * 1024 * 1024),
+            read_retries: 2,
+            skipped_compression_entries: 1,
+            per_file_stats: vec![FileCompressionStat::new(
+                "a.txt",
+                5,
+                7,
+                std::time::Duration::from_millis(3),
+            )],
+            throughput_mbs: 1.5,
+        };
+
+        let output = ProcessorOutput {
+            metadata: Some(metadata.to_json().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            CompressionResultMetadata::from_output(&output).unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_compression_result_metadata_from_output_errors() {
+        let missing = ProcessorOutput::default();
+        assert!(CompressionResultMetadata::from_output(&missing).is_err());
+
+        let invalid = ProcessorOutput {
+            metadata: Some(serde_json::json!({"format": "zip"}).to_string()),
+            ..Default::default()
+        };
+        assert!(CompressionResultMetadata::from_output(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_records_entry_checksums() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, b"hello").unwrap();
+
+        for (format, output_name) in [("zip", "output.zip"), ("targz", "output.tar.gz")] {
+            let output_path = temp_dir.path().join(output_name);
+            let processor = CompressionProcessor::new();
+            let ctx = ProcessorContext::noop("test");
+            let input = ProcessorInput {
+                inputs: vec![input_path.to_string_lossy().to_string()],
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(serde_json::json!({"format": format}).to_string()),
+                ..Default::default()
+            };
+
+            let output = processor.process(&input, &ctx).await.unwrap();
+            let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+            assert_eq!(metadata.input_count, 1);
+            assert_eq!(metadata.skipped_count, 0);
+            assert_eq!(metadata.failed_count, 0);
+            assert_eq!(
+                metadata.entries,
+                vec![CompressionEntryMetadata {
+                    input_path: input_path.to_string_lossy().to_string(),
+                    archive_name: "input.txt".to_string(),
+                    size_bytes: 5,
+                    crc32: 0x3610_a686,
+                    is_virtual: false,
+                }]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_input_files() {
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![],
+            outputs: vec!["/output.zip".to_string()],
+            config: None,
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = processor.process(&input, &ctx).await;
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("No input files"));
+    }
+
+    #[tokio::test]
+    async fn test_input_file_not_found() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("output.zip");
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec!["/nonexistent/file.txt".to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: None,
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = processor.process(&input, &ctx).await;
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        // Create input and existing output
+        std::fs::write(&input_path, "test content").unwrap();
+        std::fs::write(&output_path, "existing archive").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"overwrite": false}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = processor.process(&input, &ctx).await;
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+
+    #[tokio::test]
+    async fn test_skip_if_exists_leaves_existing_archive_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "test content").unwrap();
+        std::fs::write(&output_path, "existing archive").unwrap();
+        let mtime_before = std::fs::metadata(&output_path).unwrap().modified().unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"overwrite": false, "skip_if_exists": true}).to_string(),
+            ),
+            ..Default::default()
+        };
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+
+        let report = processor.dry_run(&input, &ctx).await.unwrap();
+        assert!(report.config_errors.is_empty());
+
+        let output = processor.process(&input, &ctx).await.unwrap();
+
+        assert_eq!(
+            output.outputs,
+            vec![output_path.to_string_lossy().to_string()]
+        );
+        assert_eq!(
+            output.skipped_inputs,
+            vec![(
+                input_path.to_string_lossy().to_string(),
+                "output_already_exists".to_string()
+            )]
+        );
+        assert!(output.succeeded_inputs.is_empty());
+        let mtime_after = std::fs::metadata(&output_path).unwrap().modified().unwrap();
+        assert_eq!(mtime_before, mtime_after);
+        assert_eq!(
+            std::fs::read_to_string(&output_path).unwrap(),
+            "existing archive"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compression_level_zero() {
+        // Test no compression (stored)
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        std::fs::write(&input_path, "test content").unwrap();
+
+        let processor = CompressionProcessor::new();
+        let ctx = ProcessorContext::noop("test");
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"compression_level": 0}).to_string()),
+            streamer_id: "test".to_string(),
+            session_id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let _output = processor.process(&input, &ctx).await.unwrap();
+        assert!(output_path.exists());
+    }
+
+    fn zip_entry_contents(path: &Path) -> Vec<(String, String)> {
+        let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut entries = Vec::new();
+        for idx in 0..archive.len() {
+            let mut entry = archive.by_index(idx).unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((entry.name().to_string(), content));
+        }
+        entries.sort();
+        entries
+    }
+
+    #[tokio::test]
+    async fn test_zip_archive_with_virtual_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "file").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({
+                    "virtual_entries": [{"name": "danmu/video.xml", "bytes": [60, 105, 47, 62]}],
+                })
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process_with_virtual_entries(
+                &input,
+                &ProcessorContext::noop("test"),
+                vec![VirtualEntry::from_reader(
+                    "notes.txt",
+                    5,
+                    std::io::Cursor::new(b"notes".to_vec()),
+                )],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            zip_entry_contents(&output_path),
+            vec![
+                ("danmu/video.xml".to_string(), "<i/>".to_string()),
+                ("notes.txt".to_string(), "notes".to_string()),
+                ("video.txt".to_string(), "file".to_string()),
+            ]
+        );
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.total_input_size_bytes, 4 + 4 + 5);
+        let virtual_entries: Vec<&str> = metadata
+            .entries
+            .iter()
+            .filter(|entry| entry.is_virtual)
+            .map(|entry| entry.archive_name.as_str())
+            .collect();
+        assert_eq!(virtual_entries, vec!["danmu/video.xml", "notes.txt"]);
+        assert_eq!(output.succeeded_inputs, input.inputs);
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz_archive_with_only_virtual_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("output.tar.gz");
+
+        let input = ProcessorInput {
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"format": "targz"}).to_string()),
+            ..Default::default()
+        };
+        CompressionProcessor::new()
+            .process_with_virtual_entries(
+                &input,
+                &ProcessorContext::noop("test"),
+                vec![VirtualEntry::from_bytes("segment.xml", b"<i/>".to_vec())],
+            )
+            .await
+            .unwrap();
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+            File::open(&output_path).unwrap(),
+        ));
+        let mut entries = archive.entries().unwrap();
+        let mut entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap(), Path::new("segment.xml"));
+        assert!(entry.header().mtime().unwrap() > 0);
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "<i/>");
+        assert!(entries.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_virtual_entry_name_collision() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.xml");
+        std::fs::write(&input_path, "file").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![
+                temp_dir
+                    .path()
+                    .join("output.zip")
+                    .to_string_lossy()
+                    .to_string(),
+            ],
+            ..Default::default()
+        };
+        let error = CompressionProcessor::new()
+            .process_with_virtual_entries(
+                &input,
+                &ProcessorContext::noop("test"),
+                vec![VirtualEntry::from_bytes("video.xml", b"<i/>".to_vec())],
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("collides"));
+    }
+
+    #[tokio::test]
+    async fn test_append_zip_skips_existing_virtual_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&first, "first").unwrap();
+        append_to_zip(&[&first], &output_path, false).await.unwrap();
+
+        let input = ProcessorInput {
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"append": true, "overwrite": false}).to_string()),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process_with_virtual_entries(
+                &input,
+                &ProcessorContext::noop("test"),
+                vec![
+                    VirtualEntry::from_bytes("first.txt", b"virtual".to_vec()),
+                    VirtualEntry::from_bytes("second.txt", b"second".to_vec()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert!(output.skipped_inputs.is_empty());
+        assert_eq!(
+            zip_entry_contents(&output_path),
+            vec![
+                ("first.txt".to_string(), "first".to_string()),
+                ("second.txt".to_string(), "second".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zip_archive_and_entry_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&input_path, "video").unwrap();
+
+        let session_start = chrono::DateTime::parse_from_rfc3339("2024-03-05T10:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({
+                    "archive_comment": "{streamer} on {platform}, recorded %Y",
+                    "entry_comments": {
+                        "video.txt": "main recording",
+                        "danmu.xml": "x".repeat(ZIP_COMMENT_MAX_LEN + 10),
+                    },
+                })
+                .to_string(),
+            ),
+            streamer_id: "streamer-1".to_string(),
+            session_id: "session-1".to_string(),
+            ..Default::default()
+        }
+        .with_streamer_name("Alice")
+        .with_platform("twitch")
+        .with_session_start(session_start);
+
+        let output = CompressionProcessor::new()
+            .process_with_virtual_entries(
+                &input,
+                &ProcessorContext::noop("test"),
+                vec![VirtualEntry::from_bytes("danmu.xml", b"<i/>".to_vec())],
+            )
+            .await
+            .unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(archive.comment(), b"Alice on twitch, recorded 2024");
+        assert_eq!(
+            archive.by_name("video.txt").unwrap().comment(),
+            "main recording"
+        );
+        assert_eq!(
+            archive.by_name("danmu.xml").unwrap().comment().len(),
+            ZIP_COMMENT_MAX_LEN
+        );
+        assert!(
+            output
+                .logs
+                .iter()
+                .any(|entry| entry.message.contains("was truncated"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zip_stores_modification_times() {
+        use chrono::TimeZone;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.txt");
+        std::fs::write(&input_path, "video").unwrap();
+        let modified = chrono::Local
+            .with_ymd_and_hms(2024, 3, 5, 10, 20, 30)
+            .unwrap();
+        File::options()
+            .write(true)
+            .open(&input_path)
+            .unwrap()
+            .set_modified(modified.into())
+            .unwrap();
+
+        let run = |store_timestamps: bool, name: &str| {
+            let output_path = temp_dir.path().join(name);
+            let input = ProcessorInput {
+                inputs: vec![input_path.to_string_lossy().to_string()],
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(serde_json::json!({"store_timestamps": store_timestamps}).to_string()),
+                ..Default::default()
+            };
+            async move {
+                CompressionProcessor::new()
+                    .process(&input, &ProcessorContext::noop("test"))
+                    .await
+                    .unwrap();
+                let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+                archive
+                    .by_name("video.txt")
+                    .unwrap()
+                    .last_modified()
+                    .unwrap()
+            }
+        };
+
+        let stored = run(true, "stored.zip").await;
+        assert_eq!((stored.year(), stored.month(), stored.day()), (2024, 3, 5));
+        assert_eq!(
+            (stored.hour(), stored.minute(), stored.second()),
+            (10, 20, 30)
+        );
+
+        let unset = run(false, "unset.zip").await;
+        assert_eq!((unset.year(), unset.month(), unset.day()), (1980, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz_ignores_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&input_path, "video").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"format": "targz", "archive_comment": "ignored"}).to_string(),
+            ),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert!(output_path.exists());
+        assert!(output.logs.iter().any(|entry| {
+            entry.level == crate::pipeline::job_queue::LogLevel::Debug
+                && entry.message.contains("only apply to ZIP")
+                && entry.fields["output"] == output_path.to_string_lossy().as_ref()
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_per_file_method_selects_zip_entry_method() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.mp4");
+        let text_path = temp_dir.path().join("notes.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&video_path, "video ".repeat(100)).unwrap();
+        std::fs::write(&text_path, "notes ".repeat(100)).unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![
+                video_path.to_string_lossy().to_string(),
+                text_path.to_string_lossy().to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({
+                    "compression_level": 0,
+                    "per_file_method": [
+                        {"pattern": "*.{mp4,jpg}", "method": "stored"},
+                        {"pattern": "*.txt", "method": "deflated"},
+                        {"pattern": "*", "method": "stored"},
+                    ],
+                })
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
 
-        // Determine output path
-        let output_path_str = self.determine_output_path(&input.inputs, &config, input);
-        let output_path = PathBuf::from(&output_path_str);
+        let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(
+            archive.by_name("video.mp4").unwrap().compression(),
+            zip::CompressionMethod::Stored
+        );
+        assert_eq!(
+            archive.by_name("notes.txt").unwrap().compression(),
+            zip::CompressionMethod::Deflated
+        );
+    }
 
-        if let Some(parent) = output_path.parent()
-            && !parent.as_os_str().is_empty()
-        {
-            tokio::fs::create_dir_all(parent)
+    #[tokio::test]
+    async fn test_per_file_method_rejects_invalid_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.mp4");
+        std::fs::write(&input_path, "video").unwrap();
+
+        for rule in [
+            serde_json::json!({"pattern": "[", "method": "stored"}),
+            serde_json::json!({"pattern": "*.mp4", "method": "zstd"}),
+        ] {
+            let input = ProcessorInput {
+                inputs: vec![input_path.to_string_lossy().to_string()],
+                config: Some(serde_json::json!({"per_file_method": [rule]}).to_string()),
+                ..Default::default()
+            };
+            let report = CompressionProcessor::new()
+                .dry_run(&input, &ProcessorContext::noop("test"))
                 .await
-                .map_err(|e| crate::Error::io_path("create_dir_all", parent, e))?;
+                .unwrap();
+            assert!(!report.is_valid());
         }
+    }
 
-        // Check if output exists and handle overwrite
-        let output_exists = tokio::fs::try_exists(&output_path)
+    #[tokio::test]
+    async fn test_tar_gz_ignores_per_file_method() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("video.mp4");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&input_path, "video").unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![input_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({
+                    "format": "targz",
+                    "per_file_method": [{"pattern": "*.mp4", "method": "stored"}],
+                })
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
             .await
-            .map_err(|e| crate::Error::io_path("try_exists", &output_path, e))?;
-        if output_exists && !config.overwrite {
-            let msg = format!(
-                "Output archive already exists and overwrite is disabled: {}",
-                output_path.display()
-            );
-            error!("{}", msg);
-            logs.push(create_log_entry(
-                crate::pipeline::job_queue::LogLevel::Error,
-                msg.clone(),
-            ));
-            return Err(crate::Error::PipelineError(msg));
-        }
+            .unwrap();
 
-        let start_msg = format!(
-            "Creating {:?} archive with {} files -> {}",
-            config.format,
-            input.inputs.len(),
-            output_path_str
-        );
-        info!("{}", start_msg);
-        logs.push(create_log_entry(
-            crate::pipeline::job_queue::LogLevel::Info,
-            start_msg,
-        ));
+        assert!(output_path.exists());
+        assert!(output.logs.iter().any(|entry| {
+            entry.level == crate::pipeline::job_queue::LogLevel::Warn
+                && entry.message.contains("per_file_method")
+        }));
+    }
 
-        let tmp_path = tmp_output_path(&output_path);
+    #[tokio::test]
+    async fn test_empty_inputs_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let stub_path = temp_dir.path().join("stub.flv");
+        let video_path = temp_dir.path().join("video.flv");
+        std::fs::write(&stub_path, "").unwrap();
+        std::fs::write(&video_path, "video").unwrap();
+        let stub = stub_path.to_string_lossy().to_string();
+
+        for format in ["zip", "targz"] {
+            let output_path = temp_dir.path().join(format!("output.{format}"));
+            let input = ProcessorInput {
+                inputs: vec![stub.clone(), video_path.to_string_lossy().to_string()],
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(serde_json::json!({"format": format}).to_string()),
+                ..Default::default()
+            };
+            let output = CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
 
-        let inputs = input.inputs.clone();
-        let config_for_blocking = config.clone();
-        let cancel = ctx.cancellation_token.child_token();
-        let mut cancel_on_drop = CancelOnDrop::new(cancel.clone());
-        let progress = ctx.progress.clone();
+            assert_eq!(
+                output.skipped_inputs,
+                vec![(stub.clone(), "empty file".to_string())]
+            );
+            assert!(!output.succeeded_inputs.contains(&stub));
+            let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+            assert_eq!(metadata.input_count, 1, "{format}");
+            assert_eq!(metadata.entries.len(), 1, "{format}");
+            assert_eq!(metadata.entries[0].archive_name, "video.flv");
+        }
+    }
 
-        let result = tokio::task::spawn_blocking(move || {
-            struct TmpFileGuard {
-                path: Option<PathBuf>,
-            }
+    #[tokio::test]
+    async fn test_empty_inputs_are_kept_when_skipping_is_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let stub_path = temp_dir.path().join("stub.flv");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&stub_path, "").unwrap();
 
-            impl TmpFileGuard {
-                fn new(path: PathBuf) -> Self {
-                    Self { path: Some(path) }
-                }
+        let input = ProcessorInput {
+            inputs: vec![stub_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"skip_empty_files": false}).to_string()),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
 
-                fn commit(mut self) {
-                    self.path.take();
-                }
-            }
+        assert!(output.skipped_inputs.is_empty());
+        let archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+    }
 
-            impl Drop for TmpFileGuard {
-                fn drop(&mut self) {
-                    if let Some(path) = self.path.take()
-                        && let Err(error) = std::fs::remove_file(&path)
-                        && error.kind() != std::io::ErrorKind::NotFound
-                    {
-                        warn!(
-                            %error,
-                            path = %path.display(),
-                            "failed to remove partial compression output"
-                        );
-                    }
-                }
-            }
+    #[tokio::test]
+    async fn test_all_empty_inputs_fail() {
+        let temp_dir = TempDir::new().unwrap();
+        let stub_path = temp_dir.path().join("stub.flv");
+        std::fs::write(&stub_path, "").unwrap();
 
-            let guard = TmpFileGuard::new(tmp_path.clone());
+        let input = ProcessorInput {
+            inputs: vec![stub_path.to_string_lossy().to_string()],
+            outputs: vec![
+                temp_dir
+                    .path()
+                    .join("output.zip")
+                    .to_string_lossy()
+                    .to_string(),
+            ],
+            ..Default::default()
+        };
+        let err = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap_err();
 
-            if cancel.is_cancelled() {
-                return Err(crate::Error::PipelineError(
-                    "Compression cancelled".to_string(),
-                ));
-            }
+        assert!(err.to_string().contains("empty"));
+    }
 
-            let processor = CompressionProcessor;
-            let sizes = match config_for_blocking.format {
-                ArchiveFormat::Zip => processor.create_zip_archive(
-                    &inputs,
-                    &tmp_path,
-                    &config_for_blocking,
-                    progress,
-                    cancel.clone(),
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_are_preserved_in_tar_gz() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.flv");
+        let link_path = temp_dir.path().join("latest.flv");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&video_path, "video").unwrap();
+        std::os::unix::fs::symlink("video.flv", &link_path).unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![
+                video_path.to_string_lossy().to_string(),
+                link_path.to_string_lossy().to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"format": "targz", "symlink_handling": "preserve"}).to_string(),
+            ),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+        assert!(output.skipped_inputs.is_empty());
+
+        let decoder = flate2::read::GzDecoder::new(File::open(&output_path).unwrap());
+        let mut archive = tar::Archive::new(decoder);
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (
+                    entry.path().unwrap().to_string_lossy().to_string(),
+                    entry.header().entry_type(),
+                    entry
+                        .link_name()
+                        .unwrap()
+                        .map(|target| target.to_string_lossy().to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("video.flv".to_string(), tar::EntryType::Regular, None),
+                (
+                    "latest.flv".to_string(),
+                    tar::EntryType::Symlink,
+                    Some("video.flv".to_string())
                 ),
-                ArchiveFormat::TarGz => processor.create_tar_gz_archive(
-                    &inputs,
-                    &tmp_path,
-                    &config_for_blocking,
-                    progress,
-                    cancel.clone(),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.flv");
+        let link_path = temp_dir.path().join("latest.flv");
+        std::fs::write(&video_path, "video").unwrap();
+        std::os::unix::fs::symlink(&video_path, &link_path).unwrap();
+        let link = link_path.to_string_lossy().to_string();
+
+        for format in ["zip", "targz"] {
+            let output_path = temp_dir.path().join(format!("output.{format}"));
+            let input = ProcessorInput {
+                inputs: vec![video_path.to_string_lossy().to_string(), link.clone()],
+                outputs: vec![output_path.to_string_lossy().to_string()],
+                config: Some(
+                    serde_json::json!({"format": format, "symlink_handling": "skip"}).to_string(),
                 ),
-            }?;
+                ..Default::default()
+            };
+            let output = CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
 
-            if cancel.is_cancelled() {
-                return Err(crate::Error::PipelineError(
-                    "Compression cancelled".to_string(),
-                ));
-            }
+            assert_eq!(
+                output.skipped_inputs,
+                vec![(link.clone(), "symlink".to_string())]
+            );
+            let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+            assert_eq!(metadata.entries.len(), 1, "{format}");
+            assert_eq!(metadata.entries[0].archive_name, "video.flv");
+        }
+    }
 
-            match std::fs::rename(&tmp_path, &output_path) {
-                Ok(()) => {
-                    guard.commit();
-                }
-                Err(rename_err) => {
-                    if config_for_blocking.overwrite && output_path.exists() {
-                        std::fs::remove_file(&output_path)
-                            .map_err(|e| crate::Error::io_path("remove_file", &output_path, e))?;
-                        std::fs::rename(&tmp_path, &output_path)
-                            .map_err(|e| crate::Error::io_path("rename", &output_path, e))?;
-                        guard.commit();
-                    } else {
-                        return Err(crate::Error::io_path("rename", &output_path, rename_err));
-                    }
-                }
-            }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_are_followed_in_zip_when_preserving() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.flv");
+        let link_path = temp_dir.path().join("latest.flv");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&video_path, "video").unwrap();
+        std::os::unix::fs::symlink(&video_path, &link_path).unwrap();
 
-            Ok::<_, crate::Error>(sizes)
-        })
-        .await
-        .map_err(|e| crate::Error::Other(format!("Compression worker panicked: {}", e)))?;
+        let input = ProcessorInput {
+            inputs: vec![link_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(serde_json::json!({"symlink_handling": "preserve"}).to_string()),
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        assert!(output.logs.iter().any(|entry| {
+            entry.level == crate::pipeline::job_queue::LogLevel::Warn
+                && entry.message.contains("symlink_handling")
+        }));
+        let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("latest.flv")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "video");
+    }
 
-        cancel_on_drop.disarm();
+    /// Bytes that Deflate cannot shrink.
+    fn incompressible_bytes(len: usize) -> Vec<u8> {
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
 
-        // Add detailed logs for inputs
-        for input in &input.inputs {
-            logs.push(create_log_entry(
-                crate::pipeline::job_queue::LogLevel::Debug,
-                format!("Added file to archive: {}", input),
-            ));
-        }
+    #[tokio::test]
+    async fn test_min_compression_ratio_stores_incompressible_zip_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.mp4");
+        let text_path = temp_dir.path().join("notes.txt");
+        let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&video_path, incompressible_bytes(16 * 1024)).unwrap();
+        std::fs::write(&text_path, "notes ".repeat(1000)).unwrap();
 
-        let (total_input_size, output_size) = match result {
-            Ok(sizes) => sizes,
-            Err(e) => {
-                let msg = format!("Compression failed: {}", e);
-                error!("{}", msg);
-                logs.push(create_log_entry(
-                    crate::pipeline::job_queue::LogLevel::Error,
-                    msg,
-                ));
-                return Err(e);
-            }
+        let input = ProcessorInput {
+            inputs: vec![
+                video_path.to_string_lossy().to_string(),
+                text_path.to_string_lossy().to_string(),
+            ],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"min_compression_ratio": 0.05, "probe_bytes": 4096}).to_string(),
+            ),
+            ..Default::default()
         };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
 
-        let compression_ratio = Self::calculate_compression_ratio(total_input_size, output_size);
-        let duration = start.elapsed().as_secs_f64();
-
-        let complete_msg = format!(
-            "Compression completed in {:.2}s: {} files -> {} (ratio: {:.1}%)",
-            duration,
-            input.inputs.len(),
-            output_path_str,
-            compression_ratio
+        let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(
+            archive.by_name("video.mp4").unwrap().compression(),
+            zip::CompressionMethod::Stored
         );
-        info!("{}", complete_msg);
-        logs.push(create_log_entry(
-            crate::pipeline::job_queue::LogLevel::Info,
-            complete_msg,
-        ));
+        assert_eq!(
+            archive.by_name("notes.txt").unwrap().compression(),
+            zip::CompressionMethod::Deflated
+        );
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.skipped_compression_entries, 1);
+    }
 
-        Ok(ProcessorOutput {
-            outputs: vec![output_path_str.clone()],
-            duration_secs: duration,
-            metadata: Some(
-                serde_json::json!({
-                    "format": format!("{:?}", config.format),
-                    "compression_level": config.compression_level,
-                    "input_files": input.inputs,
-                    "input_count": input.inputs.len(),
-                    "total_input_size_bytes": total_input_size,
-                    "output_size_bytes": output_size,
-                    "compression_ratio_percent": compression_ratio,
-                })
-                .to_string(),
+    #[tokio::test]
+    async fn test_min_compression_ratio_applies_to_whole_tar_gz() {
+        let temp_dir = TempDir::new().unwrap();
+        let video_path = temp_dir.path().join("video.mp4");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&video_path, incompressible_bytes(16 * 1024)).unwrap();
+
+        let input = ProcessorInput {
+            inputs: vec![video_path.to_string_lossy().to_string()],
+            outputs: vec![output_path.to_string_lossy().to_string()],
+            config: Some(
+                serde_json::json!({"format": "targz", "min_compression_ratio": 0.05}).to_string(),
             ),
-            items_produced: vec![output_path_str],
-            input_size_bytes: Some(total_input_size),
-            output_size_bytes: Some(output_size),
-            // All inputs succeeded if we reach this point
-            failed_inputs: vec![],
-            succeeded_inputs: input.inputs.clone(),
-            skipped_inputs: vec![],
-            logs,
-        })
+            ..Default::default()
+        };
+        let output = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.skipped_compression_entries, 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+    #[tokio::test]
+    async fn test_min_compression_ratio_out_of_range_is_rejected() {
+        let input = ProcessorInput {
+            inputs: vec!["/tmp/input.txt".to_string()],
+            config: Some(serde_json::json!({"min_compression_ratio": 1.5}).to_string()),
+            ..Default::default()
+        };
+        let report = CompressionProcessor::new()
+            .dry_run(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
 
-    #[test]
-    fn test_compression_processor_type() {
-        let processor = CompressionProcessor::new();
-        assert_eq!(processor.processor_type(), ProcessorType::Cpu);
+        assert!(
+            report
+                .config_errors
+                .iter()
+                .any(|error| error.contains("min_compression_ratio"))
+        );
     }
 
-    #[test]
-    fn test_compression_processor_job_types() {
-        let processor = CompressionProcessor::new();
-        assert!(processor.can_process("compress"));
-        assert!(processor.can_process("archive"));
-        assert!(!processor.can_process("remux"));
+    #[tokio::test]
+    async fn test_buffer_sizes_below_minimum_are_rejected() {
+        let input = ProcessorInput {
+            inputs: vec!["/tmp/input.txt".to_string()],
+            config: Some(
+                serde_json::json!({"read_buffer_bytes": 1024, "write_buffer_bytes": 4096})
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let report = CompressionProcessor::new()
+            .dry_run(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap();
+
+        let buffer_errors: Vec<_> = report
+            .config_errors
+            .iter()
+            .filter(|error| error.contains("buffer_bytes"))
+            .collect();
+        assert_eq!(buffer_errors.len(), 1);
+        assert!(buffer_errors[0].contains("read_buffer_bytes 1024"));
+        let err = CompressionProcessor::new()
+            .process(&input, &ProcessorContext::noop("test"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("read_buffer_bytes"));
     }
 
-    #[test]
-    fn test_compression_processor_name() {
-        let processor = CompressionProcessor::new();
-        assert_eq!(processor.name(), "CompressionProcessor");
+    #[tokio::test]
+    async fn test_custom_buffer_sizes_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        let data = "abcdefgh".repeat(20_000);
+        std::fs::write(&input_path, &data).unwrap();
+
+        for format in ["zip", "targz"] {
+            let output_path = temp_dir.path().join(format!("output.{format}"));
+            let input = ProcessorInput {
+                inputs: vec![input_path.to_string_lossy().to_string()],
+                config: Some(
+                    serde_json::json!({
+                        "format": format,
+                        "output_path": output_path.to_string_lossy(),
+                        "read_buffer_bytes": 4096,
+                        "write_buffer_bytes": 1024 * 1024,
+                    })
+                    .to_string(),
+                ),
+                ..Default::default()
+            };
+            CompressionProcessor::new()
+                .process(&input, &ProcessorContext::noop("test"))
+                .await
+                .unwrap();
+
+            let mut contents = String::new();
+            if format == "zip" {
+                let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+                archive
+                    .by_index(0)
+                    .unwrap()
+                    .read_to_string(&mut contents)
+                    .unwrap();
+            } else {
+                let decoder = flate2::read::GzDecoder::new(File::open(&output_path).unwrap());
+                let mut archive = tar::Archive::new(decoder);
+                let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+                entry.read_to_string(&mut contents).unwrap();
+            }
+            assert_eq!(contents, data);
+        }
     }
 
-    #[test]
-    fn test_compression_processor_supports_batch() {
-        let processor = CompressionProcessor::new();
-        assert!(processor.supports_batch_input());
+    /// Yields `data`, then fails every read with EIO.
+    struct FailingReader {
+        data: std::io::Cursor<Vec<u8>>,
     }
 
-    #[test]
-    fn test_archive_format_extensions() {
-        assert_eq!(ArchiveFormat::Zip.extension(), "zip");
-        assert_eq!(ArchiveFormat::TarGz.extension(), "tar.gz");
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.data.read(buf)? {
+                0 => Err(std::io::Error::from_raw_os_error(EIO)),
+                n => Ok(n),
+            }
+        }
     }
 
-    #[test]
-    fn test_compression_config_default() {
-        let config = CompressionConfig::default();
-        assert_eq!(config.format, ArchiveFormat::Zip);
-        assert_eq!(config.compression_level, 6);
-        assert!(config.output_path.is_none());
-        assert!(config.overwrite);
-        assert!(!config.preserve_paths);
+    fn failing_reader(data: &[u8]) -> Box<dyn Read> {
+        Box::new(FailingReader {
+            data: std::io::Cursor::new(data.to_vec()),
+        })
+    }
+
+    fn read_retry(backoff_ms: u64, cancel: CancellationToken) -> ReadRetry {
+        ReadRetry::new(
+            RetryPolicy {
+                max_attempts: 3,
+                backoff_ms,
+            },
+            cancel,
+        )
     }
 
     #[test]
-    fn test_compression_config_parse() {
-        let json = r#"{
-            "format": "targz",
-            "compression_level": 9,
-            "output_path": "/output/archive.tar.gz",
-            "overwrite": false,
-            "preserve_paths": true
-        }"#;
-
-        let config: CompressionConfig = serde_json::from_str(json).unwrap();
-        assert_eq!(config.format, ArchiveFormat::TarGz);
-        assert_eq!(config.compression_level, 9);
-        assert_eq!(
-            config.output_path,
-            Some("/output/archive.tar.gz".to_string())
-        );
-        assert!(!config.overwrite);
-        assert!(config.preserve_paths);
+    fn test_retrying_reader_resumes_at_offset_after_transient_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("input.bin");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let retry = read_retry(0, CancellationToken::new());
+        let mut reader = RetryingFileReader::new(&path, failing_reader(b"0123"), retry.clone());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data, b"0123456789");
+        let events = retry.events();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains("at byte 4"));
     }
 
     #[test]
-    fn test_compression_config_parse_zip() {
-        let json = r#"{"format": "zip"}"#;
-        let config: CompressionConfig = serde_json::from_str(json).unwrap();
-        assert_eq!(config.format, ArchiveFormat::Zip);
+    fn test_retrying_reader_fails_when_input_cannot_be_resumed() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("input.bin");
+        std::fs::write(&path, b"01").unwrap();
+
+        let retry = read_retry(0, CancellationToken::new());
+        let mut reader = RetryingFileReader::new(&path, failing_reader(b"0123"), retry);
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
     }
 
     #[test]
-    fn test_calculate_compression_ratio() {
-        // 50% compression
-        assert!((CompressionProcessor::calculate_compression_ratio(1000, 500) - 50.0).abs() < 0.01);
-        // No compression
-        assert!((CompressionProcessor::calculate_compression_ratio(1000, 1000) - 0.0).abs() < 0.01);
-        // 90% compression
-        assert!((CompressionProcessor::calculate_compression_ratio(1000, 100) - 90.0).abs() < 0.01);
-        // Zero input size
-        assert!((CompressionProcessor::calculate_compression_ratio(0, 0) - 0.0).abs() < 0.01);
+    fn test_retrying_reader_stops_when_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("input.bin");
+        std::fs::write(&path, b"0123").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let mut reader =
+            RetryingFileReader::new(&path, failing_reader(b""), read_retry(60_000, cancel));
+        let started = std::time::Instant::now();
+
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
     }
 
     #[test]
-    fn test_get_archive_filename_no_preserve() {
-        let filename = archive_entry_name("/path/to/file.txt", false).unwrap();
-        assert_eq!(filename, "file.txt");
+    fn test_retrying_reader_passes_through_permanent_errors() {
+        let retry = read_retry(0, CancellationToken::new());
+        let mut reader = RetryingFileReader::new(
+            "/nonexistent/input.bin",
+            Box::new(std::io::Cursor::new(Vec::new())),
+            retry.clone(),
+        );
+        reader.reader = None;
+
+        let error = reader.read(&mut [0; 4]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(retry.events().is_empty());
     }
 
     #[test]
-    fn test_get_archive_filename_preserve() {
-        let filename = archive_entry_name("/path/to/file.txt", true).unwrap();
-        assert_eq!(filename, "path/to/file.txt");
+    fn test_truncate_zip_comment_respects_char_boundaries() {
+        let mut short = "short".to_string();
+        assert!(!truncate_zip_comment(&mut short));
+        assert_eq!(short, "short");
+
+        let mut long = format!("a{}", "é".repeat(ZIP_COMMENT_MAX_LEN));
+        assert!(truncate_zip_comment(&mut long));
+        assert!(long.len() <= ZIP_COMMENT_MAX_LEN);
+        assert_eq!(long.len(), ZIP_COMMENT_MAX_LEN);
     }
 
-    #[tokio::test]
-    async fn test_create_zip_archive_single_file() {
-        let temp_dir = TempDir::new().unwrap();
-        let input_path = temp_dir.path().join("input.txt");
-        let output_path = temp_dir.path().join("output.zip");
-
-        // Create input file
-        std::fs::write(&input_path, "test content for compression").unwrap();
-
+    async fn append_to_zip(
+        inputs: &[&Path],
+        output_path: &Path,
+        overwrite: bool,
+    ) -> Result<ProcessorOutput> {
         let processor = CompressionProcessor::new();
         let ctx = ProcessorContext::noop("test");
         let input = ProcessorInput {
-            inputs: vec![input_path.to_string_lossy().to_string()],
+            inputs: inputs
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
             outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"format": "zip"}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+            config: Some(
+                serde_json::json!({"format": "zip", "append": true, "overwrite": overwrite})
+                    .to_string(),
+            ),
             ..Default::default()
         };
+        processor.process(&input, &ctx).await
+    }
 
-        let output = processor.process(&input, &ctx).await.unwrap();
+    #[tokio::test]
+    async fn test_append_zip_skips_existing_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let output_path = temp_dir.path().join("output.zip");
+
+        std::fs::write(&first, "original").unwrap();
+        append_to_zip(&[&first], &output_path, false).await.unwrap();
+
+        std::fs::write(&first, "modified").unwrap();
+        std::fs::write(&second, "second").unwrap();
+        let output = append_to_zip(&[&first, &second], &output_path, false)
+            .await
+            .unwrap();
 
-        // Verify archive was created
-        assert!(output_path.exists());
         assert_eq!(
-            output.outputs,
-            vec![output_path.to_string_lossy().to_string()]
+            zip_entry_contents(&output_path),
+            vec![
+                ("first.txt".to_string(), "original".to_string()),
+                ("second.txt".to_string(), "second".to_string()),
+            ]
         );
-        assert!(output.input_size_bytes.is_some());
-        assert!(output.output_size_bytes.is_some());
+        assert_eq!(output.skipped_inputs.len(), 1);
+        assert_eq!(output.skipped_inputs[0].0, first.to_string_lossy());
+        assert_eq!(
+            output.succeeded_inputs,
+            vec![second.to_string_lossy().to_string()]
+        );
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.appended_to_existing);
     }
 
     #[tokio::test]
-    async fn test_create_tar_gz_archive_single_file() {
+    async fn test_append_zip_replaces_existing_entries_when_overwrite() {
         let temp_dir = TempDir::new().unwrap();
-        let input_path = temp_dir.path().join("input.txt");
-        let output_path = temp_dir.path().join("output.tar.gz");
-
-        // Create input file
-        std::fs::write(&input_path, "test content for compression").unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let output_path = temp_dir.path().join("output.zip");
 
-        let processor = CompressionProcessor::new();
-        let ctx = ProcessorContext::noop("test");
-        let input = ProcessorInput {
-            inputs: vec![input_path.to_string_lossy().to_string()],
-            outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"format": "targz"}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
-            ..Default::default()
-        };
+        std::fs::write(&first, "original").unwrap();
+        std::fs::write(&second, "second").unwrap();
+        append_to_zip(&[&first, &second], &output_path, true)
+            .await
+            .unwrap();
 
-        let output = processor.process(&input, &ctx).await.unwrap();
+        std::fs::write(&first, "modified").unwrap();
+        let output = append_to_zip(&[&first], &output_path, true).await.unwrap();
 
-        // Verify archive was created
-        assert!(output_path.exists());
         assert_eq!(
-            output.outputs,
-            vec![output_path.to_string_lossy().to_string()]
+            zip_entry_contents(&output_path),
+            vec![
+                ("first.txt".to_string(), "modified".to_string()),
+                ("second.txt".to_string(), "second".to_string()),
+            ]
         );
+        assert!(output.skipped_inputs.is_empty());
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.appended_to_existing);
+        assert_eq!(metadata.replaced_entries, vec!["first.txt".to_string()]);
     }
 
     #[tokio::test]
-    async fn test_create_zip_archive_multiple_files() {
+    async fn test_append_without_existing_archive_creates_it() {
         let temp_dir = TempDir::new().unwrap();
-        let input1 = temp_dir.path().join("file1.txt");
-        let input2 = temp_dir.path().join("file2.txt");
+        let first = temp_dir.path().join("first.txt");
         let output_path = temp_dir.path().join("output.zip");
+        std::fs::write(&first, "original").unwrap();
 
-        // Create input files
-        std::fs::write(&input1, "content of file 1").unwrap();
-        std::fs::write(&input2, "content of file 2").unwrap();
+        let output = append_to_zip(&[&first], &output_path, false).await.unwrap();
+
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(!metadata.appended_to_existing);
+        assert_eq!(zip_entry_contents(&output_path).len(), 1);
+    }
+
+    fn write_aged_file(path: &Path, contents: &[u8], age: std::time::Duration) {
+        std::fs::write(path, contents).unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - age)
+            .unwrap();
+    }
 
+    async fn compress_with_stability_check(
+        temp_dir: &TempDir,
+        inputs: &[&Path],
+        stability_check: serde_json::Value,
+    ) -> Result<ProcessorOutput> {
+        let output_path = temp_dir.path().join("output.zip");
         let processor = CompressionProcessor::new();
         let ctx = ProcessorContext::noop("test");
         let input = ProcessorInput {
-            inputs: vec![
-                input1.to_string_lossy().to_string(),
-                input2.to_string_lossy().to_string(),
-            ],
+            inputs: inputs
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
             outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"format": "zip"}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+            config: Some(
+                serde_json::json!({"format": "zip", "stability_check": stability_check})
+                    .to_string(),
+            ),
             ..Default::default()
         };
+        processor.process(&input, &ctx).await
+    }
 
-        let output = processor.process(&input, &ctx).await.unwrap();
-
-        // Verify archive was created
-        assert!(output_path.exists());
+    #[tokio::test]
+    async fn test_stability_check_stable_inputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("done.txt");
+        write_aged_file(&input_path, b"done", std::time::Duration::from_secs(600));
+
+        let output = compress_with_stability_check(
+            &temp_dir,
+            &[&input_path],
+            serde_json::json!({"interval_ms": 10, "min_mtime_age_secs": 60}),
+        )
+        .await
+        .unwrap();
 
-        // Verify metadata contains file count
-        let metadata: serde_json::Value =
-            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
-        assert_eq!(metadata["input_count"], 2);
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert!(metadata.unstable_inputs.is_empty());
+        assert_eq!(metadata.entries.len(), 1);
     }
 
     #[tokio::test]
-    async fn test_create_tar_gz_archive_multiple_files() {
+    async fn test_stability_check_skip_policy() {
         let temp_dir = TempDir::new().unwrap();
-        let input1 = temp_dir.path().join("file1.txt");
-        let input2 = temp_dir.path().join("file2.txt");
-        let output_path = temp_dir.path().join("output.tar.gz");
+        let done = temp_dir.path().join("done.txt");
+        let recent = temp_dir.path().join("recent.txt");
+        write_aged_file(&done, b"done", std::time::Duration::from_secs(600));
+        std::fs::write(&recent, b"still flushing").unwrap();
+
+        let output = compress_with_stability_check(
+            &temp_dir,
+            &[&done, &recent],
+            serde_json::json!({"interval_ms": 10, "min_mtime_age_secs": 60, "policy": "skip"}),
+        )
+        .await
+        .unwrap();
 
-        // Create input files
-        std::fs::write(&input1, "content of file 1").unwrap();
-        std::fs::write(&input2, "content of file 2").unwrap();
+        let recent = recent.to_string_lossy().to_string();
+        assert_eq!(output.skipped_inputs.len(), 1);
+        assert_eq!(output.skipped_inputs[0].0, recent);
+        assert_eq!(
+            output.succeeded_inputs,
+            vec![done.to_string_lossy().to_string()]
+        );
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.unstable_inputs, vec![recent]);
+        assert_eq!(metadata.skipped_count, 1);
+        assert_eq!(
+            zip_entry_contents(&temp_dir.path().join("output.zip")),
+            vec![("done.txt".to_string(), "done".to_string())]
+        );
+    }
 
-        let processor = CompressionProcessor::new();
-        let ctx = ProcessorContext::noop("test");
-        let input = ProcessorInput {
-            inputs: vec![
-                input1.to_string_lossy().to_string(),
-                input2.to_string_lossy().to_string(),
-            ],
-            outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"format": "targz"}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
-            ..Default::default()
-        };
+    #[tokio::test]
+    async fn test_stability_check_fail_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let recent = temp_dir.path().join("recent.txt");
+        std::fs::write(&recent, b"still flushing").unwrap();
+
+        let err = compress_with_stability_check(
+            &temp_dir,
+            &[&recent],
+            serde_json::json!({"interval_ms": 10, "min_mtime_age_secs": 60, "policy": "fail"}),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("still being written"));
+        assert!(!temp_dir.path().join("output.zip").exists());
+    }
 
-        let output = processor.process(&input, &ctx).await.unwrap();
+    #[tokio::test]
+    async fn test_stability_check_waits_for_growing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let growing = temp_dir.path().join("growing.txt");
+        std::fs::write(&growing, b"").unwrap();
+
+        let writer_path = growing.clone();
+        let writer = std::thread::spawn(move || {
+            let mut file = File::options().append(true).open(&writer_path).unwrap();
+            for _ in 0..20 {
+                file.write_all(b"x").unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        });
 
-        // Verify archive was created
-        assert!(output_path.exists());
+        let output = compress_with_stability_check(
+            &temp_dir,
+            &[&growing],
+            serde_json::json!({"interval_ms": 100, "policy": "wait", "wait_timeout_secs": 30}),
+        )
+        .await
+        .unwrap();
+        writer.join().unwrap();
 
-        // Verify metadata contains file count
-        let metadata: serde_json::Value =
-            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
-        assert_eq!(metadata["input_count"], 2);
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(
+            metadata.unstable_inputs,
+            vec![growing.to_string_lossy().to_string()]
+        );
+        assert_eq!(metadata.entries[0].size_bytes, 20);
     }
 
     #[tokio::test]
-    async fn test_compression_ratio_in_metadata() {
+    async fn test_stability_check_wait_timeout() {
         let temp_dir = TempDir::new().unwrap();
-        let input_path = temp_dir.path().join("input.txt");
-        let output_path = temp_dir.path().join("output.zip");
-
-        // Create a larger input file for better compression
-        let content = "a".repeat(10000);
-        std::fs::write(&input_path, &content).unwrap();
-
-        let processor = CompressionProcessor::new();
-        let ctx = ProcessorContext::noop("test");
-        let input = ProcessorInput {
-            inputs: vec![input_path.to_string_lossy().to_string()],
-            outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"format": "zip", "compression_level": 9}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
-            ..Default::default()
-        };
+        let recent = temp_dir.path().join("recent.txt");
+        std::fs::write(&recent, b"still flushing").unwrap();
+
+        let err = compress_with_stability_check(
+            &temp_dir,
+            &[&recent],
+            serde_json::json!({"interval_ms": 10, "min_mtime_age_secs": 3600, "wait_timeout_secs": 0}),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("did not stop changing"));
+    }
 
-        let output = processor.process(&input, &ctx).await.unwrap();
+    #[test]
+    fn test_stability_check_honors_cancellation() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, b"data").unwrap();
 
-        // Verify compression ratio is recorded
-        let metadata: serde_json::Value =
-            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
-        assert!(metadata["compression_ratio_percent"].as_f64().is_some());
-        assert!(metadata["total_input_size_bytes"].as_u64().is_some());
-        assert!(metadata["output_size_bytes"].as_u64().is_some());
-    }
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            canceller.cancel();
+        });
 
-    #[tokio::test]
-    async fn test_no_input_files() {
-        let processor = CompressionProcessor::new();
-        let ctx = ProcessorContext::noop("test");
-        let input = ProcessorInput {
-            inputs: vec![],
-            outputs: vec!["/output.zip".to_string()],
-            config: None,
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+        let check = StabilityCheck {
+            interval_ms: 60_000,
             ..Default::default()
         };
-
-        let result = processor.process(&input, &ctx).await;
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.to_string().contains("No input files"));
+        let started = std::time::Instant::now();
+        let result =
+            check_input_stability(&[input_path.to_string_lossy().to_string()], &check, &cancel);
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
     }
 
     #[tokio::test]
-    async fn test_input_file_not_found() {
+    async fn test_append_tar_gz_is_rejected() {
         let temp_dir = TempDir::new().unwrap();
-        let output_path = temp_dir.path().join("output.zip");
+        let input_path = temp_dir.path().join("input.txt");
+        let output_path = temp_dir.path().join("output.tar.gz");
+        std::fs::write(&input_path, "test content").unwrap();
+        std::fs::write(&output_path, "existing archive").unwrap();
 
         let processor = CompressionProcessor::new();
         let ctx = ProcessorContext::noop("test");
         let input = ProcessorInput {
-            inputs: vec!["/nonexistent/file.txt".to_string()],
+            inputs: vec![input_path.to_string_lossy().to_string()],
             outputs: vec![output_path.to_string_lossy().to_string()],
-            config: None,
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+            config: Some(serde_json::json!({"format": "targz", "append": true}).to_string()),
             ..Default::default()
         };
 
-        let result = processor.process(&input, &ctx).await;
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.to_string().contains("does not exist"));
+        let err = processor.process(&input, &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("only supported for ZIP"));
     }
 
     #[tokio::test]
-    async fn test_overwrite_disabled() {
+    async fn test_force_zip64_archive_is_readable() {
         let temp_dir = TempDir::new().unwrap();
         let input_path = temp_dir.path().join("input.txt");
         let output_path = temp_dir.path().join("output.zip");
-
-        // Create input and existing output
-        std::fs::write(&input_path, "test content").unwrap();
-        std::fs::write(&output_path, "existing archive").unwrap();
+        std::fs::write(&input_path, "small content").unwrap();
 
         let processor = CompressionProcessor::new();
         let ctx = ProcessorContext::noop("test");
         let input = ProcessorInput {
             inputs: vec![input_path.to_string_lossy().to_string()],
             outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"overwrite": false}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+            config: Some(serde_json::json!({"format": "zip", "force_zip64": true}).to_string()),
             ..Default::default()
         };
 
-        let result = processor.process(&input, &ctx).await;
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.to_string().contains("already exists"));
+        let output = processor.process(&input, &ctx).await.unwrap();
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.zip64_entries, 1);
+        assert_eq!(
+            zip_entry_contents(&output_path),
+            vec![("input.txt".to_string(), "small content".to_string())]
+        );
     }
 
     #[tokio::test]
-    async fn test_compression_level_zero() {
-        // Test no compression (stored)
+    #[ignore = "writes a sparse file larger than 4 GiB and compresses it; run explicitly"]
+    async fn test_zip64_archive_larger_than_4gib() {
         let temp_dir = TempDir::new().unwrap();
-        let input_path = temp_dir.path().join("input.txt");
-        let output_path = temp_dir.path().join("output.zip");
+        let input_path = temp_dir.path().join("large.bin");
+        let output_path = temp_dir.path().join("large.zip");
+        let size = u32::MAX as u64 + 1024 * 1024;
 
-        std::fs::write(&input_path, "test content").unwrap();
+        // Sparse file: reads back as zeros without allocating disk space.
+        File::create(&input_path).unwrap().set_len(size).unwrap();
 
         let processor = CompressionProcessor::new();
         let ctx = ProcessorContext::noop("test");
         let input = ProcessorInput {
             inputs: vec![input_path.to_string_lossy().to_string()],
             outputs: vec![output_path.to_string_lossy().to_string()],
-            config: Some(serde_json::json!({"compression_level": 0}).to_string()),
-            streamer_id: "test".to_string(),
-            session_id: "test".to_string(),
+            config: Some(serde_json::json!({"format": "zip", "compression_level": 1}).to_string()),
             ..Default::default()
         };
 
-        let _output = processor.process(&input, &ctx).await.unwrap();
-        assert!(output_path.exists());
+        let output = processor.process(&input, &ctx).await.unwrap();
+        let metadata = CompressionResultMetadata::from_output(&output).unwrap();
+        assert_eq!(metadata.zip64_entries, 1);
+
+        let mut archive = ZipArchive::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        let entry = archive.by_index(0).unwrap();
+        assert_eq!(entry.name(), "large.bin");
+        assert_eq!(entry.size(), size);
     }
 
     #[test]
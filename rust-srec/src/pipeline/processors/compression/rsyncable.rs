@@ -0,0 +1,229 @@
+//! Rsync-friendly gzip encoder.
+//!
+//! A regular gzip stream carries compression state across the whole input, so a
+//! single changed byte alters every compressed byte after it. This encoder
+//! resets the deflate state (a full flush) at content-defined boundaries, the
+//! same scheme as `gzip --rsyncable`: a rolling sum over the last
+//! [`RSYNC_WINDOW`] input bytes marks a boundary whenever it is a multiple of
+//! the window size. After a local edit the boundaries resynchronise, and the
+//! compressed output past that point is identical again.
+//!
+//! The output is a single standard gzip member readable by any gzip decoder.
+
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+use std::io::{self, Write};
+
+/// Size of the rolling window, matching `gzip --rsyncable`.
+const RSYNC_WINDOW: usize = 4096;
+
+/// Size of the compressed output buffer.
+const OUTPUT_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Gzip header: magic, deflate method, no flags, no mtime, no extra flags, unknown OS.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff];
+
+/// Rolling byte sum over the last [`RSYNC_WINDOW`] bytes.
+struct RollingSum {
+    window: Box<[u8; RSYNC_WINDOW]>,
+    pos: usize,
+    filled: usize,
+    sum: u32,
+}
+
+impl RollingSum {
+    fn new() -> Self {
+        Self {
+            window: Box::new([0; RSYNC_WINDOW]),
+            pos: 0,
+            filled: 0,
+            sum: 0,
+        }
+    }
+
+    /// Feed one byte and report whether it ends a chunk.
+    fn push(&mut self, byte: u8) -> bool {
+        if self.filled == RSYNC_WINDOW {
+            self.sum -= u32::from(self.window[self.pos]);
+        } else {
+            self.filled += 1;
+        }
+        self.window[self.pos] = byte;
+        self.sum += u32::from(byte);
+        self.pos = (self.pos + 1) % RSYNC_WINDOW;
+
+        self.filled == RSYNC_WINDOW && self.sum.is_multiple_of(RSYNC_WINDOW as u32)
+    }
+}
+
+/// Gzip encoder that flushes the deflate state at content-defined boundaries.
+pub(super) struct RsyncableGzEncoder<W: Write> {
+    inner: W,
+    compress: Compress,
+    crc: Crc,
+    rolling: RollingSum,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> RsyncableGzEncoder<W> {
+    /// Create an encoder writing a gzip stream to `inner`.
+    pub(super) fn new(mut inner: W, level: Compression) -> io::Result<Self> {
+        inner.write_all(&GZIP_HEADER)?;
+        Ok(Self {
+            inner,
+            compress: Compress::new(level, false),
+            crc: Crc::new(),
+            rolling: RollingSum::new(),
+            buffer: vec![0; OUTPUT_BUFFER_SIZE],
+        })
+    }
+
+    /// Run the compressor over `input` with the given flush mode, writing all output.
+    fn deflate(&mut self, mut input: &[u8], flush: FlushCompress) -> io::Result<()> {
+        loop {
+            let before_in = self.compress.total_in();
+            let before_out = self.compress.total_out();
+            let status = self
+                .compress
+                .compress(input, &mut self.buffer, flush)
+                .map_err(io::Error::other)?;
+            let consumed = (self.compress.total_in() - before_in) as usize;
+            let produced = (self.compress.total_out() - before_out) as usize;
+
+            self.inner.write_all(&self.buffer[..produced])?;
+            input = &input[consumed..];
+
+            let done = match flush {
+                FlushCompress::Finish => status == Status::StreamEnd,
+                FlushCompress::None => input.is_empty(),
+                // A flush is complete once the compressor stops filling the buffer.
+                _ => input.is_empty() && produced < self.buffer.len(),
+            };
+            if done {
+                return Ok(());
+            }
+            if consumed == 0 && produced == 0 && status == Status::BufError {
+                return Err(io::Error::other("deflate made no progress"));
+            }
+        }
+    }
+
+    /// Finish the gzip stream and return the underlying writer.
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        self.deflate(&[], FlushCompress::Finish)?;
+        self.inner.write_all(&self.crc.sum().to_le_bytes())?;
+        self.inner.write_all(&self.crc.amount().to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for RsyncableGzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc.update(buf);
+
+        let mut chunk_start = 0;
+        for (idx, &byte) in buf.iter().enumerate() {
+            if self.rolling.push(byte) {
+                self.deflate(&buf[chunk_start..=idx], FlushCompress::Full)?;
+                chunk_start = idx + 1;
+            }
+        }
+        if chunk_start < buf.len() {
+            self.deflate(&buf[chunk_start..], FlushCompress::None)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deflate(&[], FlushCompress::Sync)?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    /// Deterministic, moderately compressible test data.
+    fn sample_data(len: usize) -> Vec<u8> {
+        let words: [&[u8]; 6] = [
+            b"alpha ",
+            b"beta ",
+            b"gamma ",
+            b"delta ",
+            b"epsilon ",
+            b"\n",
+        ];
+        let mut state: u32 = 0x1234_5678;
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            data.extend_from_slice(words[(state >> 16) as usize % words.len()]);
+            data.extend_from_slice(&state.to_le_bytes()[..2]);
+        }
+        data.truncate(len);
+        data
+    }
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let mut encoder = RsyncableGzEncoder::new(Vec::new(), Compression::default()).unwrap();
+        // Write in uneven pieces to exercise boundaries that straddle writes.
+        for chunk in data.chunks(7919) {
+            encoder.write_all(chunk).unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    fn common_suffix_len(a: &[u8], b: &[u8]) -> usize {
+        a.iter()
+            .rev()
+            .zip(b.iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count()
+    }
+
+    #[test]
+    fn test_output_decodes_with_standard_decoder() {
+        let data = sample_data(300_000);
+        let encoded = encode(&data);
+
+        let mut decoded = Vec::new();
+        GzDecoder::new(encoded.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let encoded = encode(&[]);
+        let mut decoded = Vec::new();
+        GzDecoder::new(encoded.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn test_local_change_resynchronises() {
+        let original = sample_data(300_000);
+        let mut modified = original.clone();
+        modified[100] ^= 0xff;
+
+        let a = encode(&original);
+        let b = encode(&modified);
+
+        // Everything after the first boundary following the edit is identical,
+        // except the 8-byte trailer (CRC and length), which covers the whole input.
+        let shared = common_suffix_len(&a[..a.len() - 8], &b[..b.len() - 8]);
+        assert!(
+            shared > a.len() * 3 / 4,
+            "only {} of {} compressed bytes shared",
+            shared,
+            a.len()
+        );
+    }
+}
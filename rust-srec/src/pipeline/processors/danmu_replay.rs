@@ -0,0 +1,662 @@
+//! Danmu replay processor.
+//!
+//! This processor reads a danmu XML segment file written by the collection
+//! system and re-emits its messages as [`DanmuEvent::Message`] events on a
+//! broadcast channel, paced by the recorded offsets. It lets downstream
+//! consumers be exercised without a live stream.
+//!
+//! Both metadata layouts written by the XML writer are understood: inline
+//! attributes (`<d p="..." user="...">text</d>`) and child elements
+//! (`<d><p>...</p><user>...</user><text>text</text></d>`).
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use super::traits::{Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType};
+use super::utils::{create_log_entry, parse_config_or_default};
+use crate::Result;
+use crate::danmu::{DanmuEvent, DanmuMessage, DanmuType};
+
+fn default_speed_multiplier() -> f64 {
+    1.0
+}
+
+fn default_loop_count() -> u32 {
+    1
+}
+
+/// Configuration for danmu replay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmuReplayConfig {
+    /// Danmu XML file to replay. Falls back to the first job input when empty.
+    #[serde(default)]
+    pub input_path: String,
+
+    /// Playback speed relative to the recording (2.0 replays twice as fast).
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f64,
+
+    /// Skip messages recorded before this offset (seconds from segment start).
+    #[serde(default)]
+    pub start_offset_secs: Option<f64>,
+
+    /// Number of times to replay the file. 0 repeats until cancelled.
+    #[serde(default = "default_loop_count")]
+    pub loop_count: u32,
+}
+
+impl Default for DanmuReplayConfig {
+    fn default() -> Self {
+        Self {
+            input_path: String::new(),
+            speed_multiplier: default_speed_multiplier(),
+            start_offset_secs: None,
+            loop_count: default_loop_count(),
+        }
+    }
+}
+
+/// A message parsed from a danmu XML file, with its offset from segment start.
+#[derive(Debug, Clone)]
+struct ReplayItem {
+    offset_secs: f64,
+    message: DanmuMessage,
+}
+
+/// Message element being parsed: its fields come from attributes or child elements.
+struct PendingElement {
+    name: String,
+    fields: HashMap<String, String>,
+    text: String,
+    current_field: Option<String>,
+}
+
+impl PendingElement {
+    fn new(start: &BytesStart<'_>) -> Result<Self> {
+        let mut fields = HashMap::new();
+        for attr in start.attributes() {
+            let attr = attr.map_err(|e| xml_error(&format!("invalid attribute: {}", e)))?;
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_string();
+            let raw = String::from_utf8_lossy(&attr.value);
+            let value = quick_xml::escape::unescape(&raw)
+                .map_err(|e| xml_error(&format!("invalid attribute value: {}", e)))?;
+            fields.insert(key, value.to_string());
+        }
+        Ok(Self {
+            name: local_name(start),
+            fields,
+            text: String::new(),
+            current_field: None,
+        })
+    }
+
+    fn push_text(&mut self, text: &str) {
+        match &self.current_field {
+            Some(field) => self.fields.entry(field.clone()).or_default().push_str(text),
+            None => self.text.push_str(text),
+        }
+    }
+
+    fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    fn field_u64(&self, key: &str) -> u64 {
+        self.field(key)
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn timestamp(millis: Option<i64>) -> DateTime<Utc> {
+        millis
+            .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Convert the element into a replay item. Unknown elements yield `None`.
+    fn into_item(mut self) -> Option<ReplayItem> {
+        let text = self
+            .fields
+            .remove("text")
+            .unwrap_or_else(|| std::mem::take(&mut self.text));
+
+        match self.name.as_str() {
+            "d" => {
+                // p="{time},{type},{size},{color},{timestamp},{pool},{uid_crc32},{row_id}"
+                let p: Vec<&str> = self.field("p")?.split(',').collect();
+                let offset_secs = p.first()?.trim().parse().ok()?;
+                let message_type = match p.get(1).map(|s| s.trim()) {
+                    Some("4") => DanmuType::System,
+                    _ => DanmuType::Chat,
+                };
+                let timestamp = Self::timestamp(p.get(4).and_then(|s| s.trim().parse().ok()));
+                let user_id = p.get(6).map(|s| s.trim()).unwrap_or_default();
+                let id = p.get(7).map(|s| s.trim()).unwrap_or_default();
+
+                let mut message =
+                    DanmuMessage::chat(id, user_id, self.field("user").unwrap_or_default(), text)
+                        .with_timestamp(timestamp);
+                message.message_type = message_type;
+                if let Some(color) = p
+                    .get(3)
+                    .and_then(|s| s.trim().parse::<u32>().ok())
+                    .filter(|c| *c != 0xFFFFFF)
+                {
+                    message = message.with_color(format!("#{:06X}", color));
+                }
+                Some(ReplayItem {
+                    offset_secs,
+                    message,
+                })
+            }
+            "gift" => {
+                let offset_secs = self.field("ts")?.trim().parse().ok()?;
+                let timestamp =
+                    Self::timestamp(self.field("timestamp").and_then(|s| s.trim().parse().ok()));
+                let gift_count = u32::try_from(self.field_u64("giftcount")).unwrap_or(u32::MAX);
+                let message = DanmuMessage::gift(
+                    "",
+                    self.field("uid").unwrap_or_default(),
+                    self.field("user").unwrap_or_default(),
+                    self.field("giftname").unwrap_or_default(),
+                    gift_count,
+                )
+                .with_metadata("price", serde_json::json!(self.field_u64("price")))
+                .with_timestamp(timestamp);
+                Some(ReplayItem {
+                    offset_secs,
+                    message,
+                })
+            }
+            "sc" => {
+                let offset_secs = self.field("ts")?.trim().parse().ok()?;
+                let timestamp =
+                    Self::timestamp(self.field("timestamp").and_then(|s| s.trim().parse().ok()));
+                let message = DanmuMessage::super_chat(
+                    "",
+                    self.field("uid").unwrap_or_default(),
+                    self.field("user").unwrap_or_default(),
+                    text,
+                    self.field_u64("price"),
+                )
+                .with_super_chat_keep_time(self.field_u64("time"))
+                .with_timestamp(timestamp);
+                Some(ReplayItem {
+                    offset_secs,
+                    message,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn xml_error(msg: &str) -> crate::Error {
+    crate::Error::PipelineError(format!("Invalid danmu XML: {}", msg))
+}
+
+fn local_name(start: &BytesStart<'_>) -> String {
+    String::from_utf8_lossy(start.local_name().as_ref()).to_string()
+}
+
+/// Parse a danmu XML document into replay items ordered by offset.
+fn parse_danmu_xml(xml: &str) -> Result<Vec<ReplayItem>> {
+    let mut reader = Reader::from_str(xml);
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut pending: Option<PendingElement> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| xml_error(&format!("{} at byte {}", e, reader.error_position())))?;
+        match event {
+            Event::Start(start) => {
+                depth += 1;
+                match depth {
+                    2 => pending = Some(PendingElement::new(&start)?),
+                    3 => {
+                        if let Some(element) = pending.as_mut() {
+                            let field = local_name(&start);
+                            element.fields.insert(field.clone(), String::new());
+                            element.current_field = Some(field);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(start) if depth == 1 => {
+                items.extend(PendingElement::new(&start)?.into_item());
+            }
+            Event::End(_) => {
+                match depth {
+                    2 => items.extend(pending.take().and_then(PendingElement::into_item)),
+                    3 => {
+                        if let Some(element) = pending.as_mut() {
+                            element.current_field = None;
+                        }
+                    }
+                    _ => {}
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Event::Text(text) => {
+                if let Some(element) = pending.as_mut() {
+                    let text = text
+                        .decode()
+                        .map_err(|e| xml_error(&format!("invalid text: {}", e)))?;
+                    element.push_text(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = pending.as_mut() {
+                    element.push_text(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::GeneralRef(entity) => {
+                if let Some(element) = pending.as_mut() {
+                    let resolved = match entity
+                        .resolve_char_ref()
+                        .map_err(|e| xml_error(&format!("invalid character reference: {}", e)))?
+                    {
+                        Some(ch) => ch.to_string(),
+                        None => {
+                            let name = entity
+                                .decode()
+                                .map_err(|e| xml_error(&format!("invalid entity: {}", e)))?;
+                            quick_xml::escape::resolve_predefined_entity(&name)
+                                .ok_or_else(|| xml_error(&format!("unknown entity &{};", name)))?
+                                .to_string()
+                        }
+                    };
+                    element.push_text(&resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    items.sort_by(|a, b| a.offset_secs.total_cmp(&b.offset_secs));
+    Ok(items)
+}
+
+/// Processor that replays a recorded danmu XML file as live danmu events.
+///
+/// - Messages are emitted as [`DanmuEvent::Message`] on the sender given at construction
+/// - Pacing follows the recorded offsets, scaled by `speed_multiplier`
+/// - Stops after `loop_count` passes or when the job is cancelled
+///
+/// The processor is not part of the default pipeline processors because it
+/// needs the event sender of the consumer under test.
+pub struct DanmuReplayProcessor {
+    event_tx: broadcast::Sender<DanmuEvent>,
+}
+
+impl DanmuReplayProcessor {
+    /// Create a replay processor emitting events on `event_tx`.
+    pub fn new(event_tx: broadcast::Sender<DanmuEvent>) -> Self {
+        Self { event_tx }
+    }
+}
+
+#[async_trait]
+impl Processor for DanmuReplayProcessor {
+    fn processor_type(&self) -> ProcessorType {
+        ProcessorType::Io
+    }
+
+    fn job_types(&self) -> Vec<&'static str> {
+        vec!["danmu_replay"]
+    }
+
+    fn name(&self) -> &'static str {
+        "DanmuReplayProcessor"
+    }
+
+    async fn process(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<ProcessorOutput> {
+        let start = std::time::Instant::now();
+        let mut logs = Vec::new();
+
+        let config: DanmuReplayConfig = parse_config_or_default(
+            input.config.as_deref(),
+            ctx,
+            "danmu_replay",
+            Some(&mut logs),
+        );
+
+        if !config.speed_multiplier.is_finite() || config.speed_multiplier <= 0.0 {
+            return Err(crate::Error::PipelineError(format!(
+                "Invalid speed_multiplier {} (expected a positive number)",
+                config.speed_multiplier
+            )));
+        }
+
+        let input_path = if config.input_path.is_empty() {
+            input.inputs.first().cloned().ok_or_else(|| {
+                crate::Error::PipelineError(
+                    "No danmu XML file specified for replay (set input_path or provide an input)"
+                        .to_string(),
+                )
+            })?
+        } else {
+            config.input_path.clone()
+        };
+
+        let xml = tokio::fs::read_to_string(&input_path).await.map_err(|e| {
+            crate::Error::io_path("read_to_string", std::path::Path::new(&input_path), e)
+        })?;
+        let start_offset = config.start_offset_secs.unwrap_or(0.0).max(0.0);
+        let items: Vec<ReplayItem> = parse_danmu_xml(&xml)
+            .inspect_err(|e| {
+                let msg = format!("Failed to parse danmu file {}: {}", input_path, e);
+                error!("{}", msg);
+                logs.push(create_log_entry(
+                    crate::pipeline::job_queue::LogLevel::Error,
+                    msg,
+                ));
+            })?
+            .into_iter()
+            .filter(|item| item.offset_secs >= start_offset)
+            .collect();
+
+        let start_msg = format!(
+            "Replaying {} danmu messages from {} (speed x{}, loops: {})",
+            items.len(),
+            input_path,
+            config.speed_multiplier,
+            config.loop_count
+        );
+        info!("{}", start_msg);
+        logs.push(create_log_entry(
+            crate::pipeline::job_queue::LogLevel::Info,
+            start_msg,
+        ));
+
+        let cancel = &ctx.cancellation_token;
+        let mut messages_emitted: u64 = 0;
+        let mut loops_completed: u32 = 0;
+        let mut cancelled = false;
+
+        'replay: while config.loop_count == 0 || loops_completed < config.loop_count {
+            if items.is_empty() || cancel.is_cancelled() {
+                cancelled = cancel.is_cancelled();
+                break;
+            }
+
+            let loop_start = tokio::time::Instant::now();
+            for item in &items {
+                let delay = (item.offset_secs - start_offset) / config.speed_multiplier;
+                let deadline = loop_start + std::time::Duration::from_secs_f64(delay.max(0.0));
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        cancelled = true;
+                        break 'replay;
+                    }
+                    _ = tokio::time::sleep_until(deadline) => {}
+                }
+
+                // No subscribers is not an error: the replay keeps its pacing regardless.
+                let _ = self.event_tx.send(DanmuEvent::Message {
+                    session_id: input.session_id.clone(),
+                    streamer_id: input.streamer_id.clone(),
+                    message: item.message.clone(),
+                });
+                messages_emitted += 1;
+            }
+
+            loops_completed += 1;
+            debug!(
+                "Completed danmu replay loop {} for {}",
+                loops_completed, input_path
+            );
+        }
+
+        let complete_msg = format!(
+            "Danmu replay {}: {} messages emitted over {} loops",
+            if cancelled { "cancelled" } else { "completed" },
+            messages_emitted,
+            loops_completed
+        );
+        info!("{}", complete_msg);
+        logs.push(create_log_entry(
+            crate::pipeline::job_queue::LogLevel::Info,
+            complete_msg,
+        ));
+
+        Ok(ProcessorOutput {
+            outputs: input.inputs.clone(),
+            duration_secs: start.elapsed().as_secs_f64(),
+            metadata: Some(
+                serde_json::json!({
+                    "input_path": input_path,
+                    "message_count": items.len(),
+                    "messages_emitted": messages_emitted,
+                    "loops_completed": loops_completed,
+                    "speed_multiplier": config.speed_multiplier,
+                    "cancelled": cancelled,
+                })
+                .to_string(),
+            ),
+            items_produced: vec![],
+            input_size_bytes: Some(xml.len() as u64),
+            output_size_bytes: None,
+            failed_inputs: vec![],
+            succeeded_inputs: vec![input_path],
+            skipped_inputs: vec![],
+            logs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmu::{AttributeStyle, DanmuXmlFormat, XmlDanmuWriter};
+    use tempfile::TempDir;
+
+    fn segment_start() -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).single().unwrap()
+    }
+
+    async fn write_sample(path: &std::path::Path, style: AttributeStyle) {
+        let start = segment_start();
+        let format = DanmuXmlFormat {
+            attribute_style: style,
+            ..Default::default()
+        };
+        let mut writer = XmlDanmuWriter::with_format(path, start, vec![], format)
+            .await
+            .unwrap();
+        let messages = [
+            DanmuMessage::chat("1", "u1", "Alice", "hello & <welcome>")
+                .with_timestamp(start + chrono::Duration::milliseconds(1000)),
+            DanmuMessage::gift("2", "u2", "Bob", "Rocket", 3)
+                .with_timestamp(start + chrono::Duration::milliseconds(2000)),
+            DanmuMessage::super_chat("3", "u3", "Carol", "thanks", 30)
+                .with_timestamp(start + chrono::Duration::milliseconds(4000)),
+        ];
+        for message in &messages {
+            writer.write_message(message).await.unwrap();
+        }
+        writer.finalize().await.unwrap();
+    }
+
+    async fn replay(
+        path: &std::path::Path,
+        config: serde_json::Value,
+        ctx: &ProcessorContext,
+    ) -> (Result<ProcessorOutput>, Vec<DanmuEvent>) {
+        let (tx, mut rx) = broadcast::channel(64);
+        let processor = DanmuReplayProcessor::new(tx);
+        let input = ProcessorInput {
+            inputs: vec![path.to_string_lossy().to_string()],
+            config: Some(config.to_string()),
+            session_id: "session".to_string(),
+            streamer_id: "streamer".to_string(),
+            ..Default::default()
+        };
+        let result = processor.process(&input, ctx).await;
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        (result, events)
+    }
+
+    fn message_contents(events: &[DanmuEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                DanmuEvent::Message { message, .. } => message.content.clone(),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_parse_inline_and_element_styles() {
+        let temp_dir = TempDir::new().unwrap();
+        for style in [AttributeStyle::Inline, AttributeStyle::Elements] {
+            let path = temp_dir.path().join("danmu.xml");
+            write_sample(&path, style).await;
+            let xml = std::fs::read_to_string(&path).unwrap();
+
+            let items = parse_danmu_xml(&xml).unwrap();
+            assert_eq!(items.len(), 3);
+
+            assert_eq!(items[0].offset_secs, 1.0);
+            assert_eq!(items[0].message.message_type, DanmuType::Chat);
+            assert_eq!(items[0].message.username, "Alice");
+            assert_eq!(items[0].message.content, "hello & <welcome>");
+            assert_eq!(
+                items[0].message.timestamp,
+                segment_start() + chrono::Duration::milliseconds(1000)
+            );
+
+            assert_eq!(items[1].offset_secs, 2.0);
+            assert_eq!(items[1].message.message_type, DanmuType::Gift);
+            assert_eq!(items[1].message.user_id, "u2");
+            assert_eq!(items[1].message.content, "赠送 Rocket x3");
+
+            assert_eq!(items[2].offset_secs, 4.0);
+            assert_eq!(items[2].message.message_type, DanmuType::SuperChat);
+            assert_eq!(items[2].message.content, "thanks");
+            let metadata = items[2].message.metadata.as_ref().unwrap();
+            assert_eq!(metadata.get("price").unwrap(), 30);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_xml() {
+        let result = parse_danmu_xml("<i><d p=\"1.0,1\">unterminated</i>");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_honors_timestamps_and_speed() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("danmu.xml");
+        write_sample(&path, AttributeStyle::Inline).await;
+
+        let ctx = ProcessorContext::noop("test");
+        let started = tokio::time::Instant::now();
+        let (result, events) =
+            replay(&path, serde_json::json!({"speed_multiplier": 2.0}), &ctx).await;
+        let output = result.unwrap();
+
+        // Last message is at 4s; at 2x speed the replay takes 2s.
+        assert_eq!(started.elapsed(), std::time::Duration::from_secs(2));
+        assert_eq!(
+            message_contents(&events),
+            vec!["hello & <welcome>", "赠送 Rocket x3", "thanks"]
+        );
+        let DanmuEvent::Message {
+            session_id,
+            streamer_id,
+            ..
+        } = &events[0]
+        else {
+            panic!("expected message event");
+        };
+        assert_eq!(session_id, "session");
+        assert_eq!(streamer_id, "streamer");
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
+        assert_eq!(metadata["messages_emitted"], 3);
+        assert_eq!(metadata["cancelled"], false);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_loops_and_start_offset() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("danmu.xml");
+        write_sample(&path, AttributeStyle::Inline).await;
+
+        let ctx = ProcessorContext::noop("test");
+        let (result, events) = replay(
+            &path,
+            serde_json::json!({"start_offset_secs": 1.5, "loop_count": 2}),
+            &ctx,
+        )
+        .await;
+        let output = result.unwrap();
+
+        assert_eq!(
+            message_contents(&events),
+            vec!["赠送 Rocket x3", "thanks", "赠送 Rocket x3", "thanks"]
+        );
+        let metadata: serde_json::Value =
+            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
+        assert_eq!(metadata["loops_completed"], 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_stops_on_cancellation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("danmu.xml");
+        write_sample(&path, AttributeStyle::Inline).await;
+
+        let ctx = ProcessorContext::noop("test");
+        let cancel = ctx.cancellation_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(2500)).await;
+            cancel.cancel();
+        });
+
+        // Infinite loop count: only cancellation ends the replay.
+        let (result, events) = replay(&path, serde_json::json!({"loop_count": 0}), &ctx).await;
+        let output = result.unwrap();
+
+        assert_eq!(
+            message_contents(&events),
+            vec!["hello & <welcome>", "赠送 Rocket x3"]
+        );
+        let metadata: serde_json::Value =
+            serde_json::from_str(output.metadata.as_ref().unwrap()).unwrap();
+        assert_eq!(metadata["cancelled"], true);
+        assert_eq!(metadata["loops_completed"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_invalid_speed() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("danmu.xml");
+        write_sample(&path, AttributeStyle::Inline).await;
+
+        let ctx = ProcessorContext::noop("test");
+        let (result, _) = replay(&path, serde_json::json!({"speed_multiplier": 0.0}), &ctx).await;
+        assert!(result.unwrap_err().to_string().contains("speed_multiplier"));
+    }
+}
@@ -0,0 +1,513 @@
+//! Fan-out processor.
+//!
+//! This processor runs several processors on the same input within one job,
+//! e.g. compressing a recording and uploading the original, without building
+//! a separate pipeline chain for each. Children run one after another in the
+//! configured order, so a child that moves or deletes the input should come last.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::traits::{
+    DryRunReport, Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ProcessorType,
+};
+use super::utils::create_log_entry;
+use crate::Result;
+use crate::pipeline::job_queue::LogLevel;
+
+/// How the outputs of the child processors are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Run every child and merge the outputs of those that succeed; failed
+    /// children are reported in `failed_inputs`. Fails only if every child fails.
+    #[default]
+    Concatenate,
+    /// Run children in order until one succeeds and return its output.
+    FirstSuccessful,
+    /// Run every child and merge their outputs; fails if any child fails.
+    All,
+}
+
+/// A child processor of a fan-out job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessorJobConfig {
+    /// Job type of the child, e.g. `"compression"` or `"rclone"`.
+    pub processor: String,
+    /// Config passed to the child; strings are passed as-is, other values as JSON.
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+}
+
+/// Configuration for fan-out jobs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FanOutConfig {
+    /// Processors to run on the job's input, in order.
+    pub processors: Vec<ProcessorJobConfig>,
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+}
+
+/// Processor running several processors on the same input.
+pub struct FanOutProcessor {
+    processors: Vec<Arc<dyn Processor>>,
+}
+
+impl FanOutProcessor {
+    /// Create a fan-out processor whose children are looked up in `processors`.
+    pub fn new(processors: Vec<Arc<dyn Processor>>) -> Self {
+        Self { processors }
+    }
+
+    fn parse_config(input: &ProcessorInput) -> Result<FanOutConfig> {
+        let raw = input.config.as_deref().ok_or_else(|| {
+            crate::Error::Validation("No processors specified for fan-out".to_string())
+        })?;
+        let config: FanOutConfig = serde_json::from_str(raw)
+            .map_err(|e| crate::Error::Validation(format!("Invalid fan-out config JSON: {e}")))?;
+        if config.processors.is_empty() {
+            return Err(crate::Error::Validation(
+                "No processors specified for fan-out".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Resolve every child before any of them runs.
+    fn resolve<'a>(
+        &self,
+        config: &'a FanOutConfig,
+    ) -> Result<Vec<(&'a ProcessorJobConfig, Arc<dyn Processor>)>> {
+        config
+            .processors
+            .iter()
+            .map(|child| {
+                self.processors
+                    .iter()
+                    .find(|processor| processor.can_process(&child.processor))
+                    .map(|processor| (child, Arc::clone(processor)))
+                    .ok_or_else(|| {
+                        crate::Error::Validation(format!(
+                            "Unknown processor for fan-out: {}",
+                            child.processor
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Input of a child: the fan-out input with the child's config.
+fn child_input(input: &ProcessorInput, child: &ProcessorJobConfig) -> ProcessorInput {
+    let config = child.config.as_ref().map(|config| match config {
+        serde_json::Value::String(raw) => raw.clone(),
+        other => other.to_string(),
+    });
+    ProcessorInput {
+        config,
+        ..input.clone()
+    }
+}
+
+/// Append `values` to `target`, skipping those already present.
+fn extend_unique(target: &mut Vec<String>, values: Vec<String>) {
+    for value in values {
+        if !target.contains(&value) {
+            target.push(value);
+        }
+    }
+}
+
+/// Merge the output of a child into `merged`.
+fn merge_output(merged: &mut ProcessorOutput, child: ProcessorOutput) {
+    extend_unique(&mut merged.outputs, child.outputs);
+    merged.items_produced.extend(child.items_produced);
+    merged.input_size_bytes = merged.input_size_bytes.or(child.input_size_bytes);
+    merged.output_size_bytes = match (merged.output_size_bytes, child.output_size_bytes) {
+        (Some(total), Some(size)) => Some(total.saturating_add(size)),
+        (total, size) => total.or(size),
+    };
+    merged.failed_inputs.extend(child.failed_inputs);
+    extend_unique(&mut merged.succeeded_inputs, child.succeeded_inputs);
+    merged.skipped_inputs.extend(child.skipped_inputs);
+    merged.logs.extend(child.logs);
+}
+
+#[async_trait]
+impl Processor for FanOutProcessor {
+    fn processor_type(&self) -> ProcessorType {
+        ProcessorType::Io
+    }
+
+    fn job_types(&self) -> Vec<&'static str> {
+        vec!["fan_out"]
+    }
+
+    fn name(&self) -> &'static str {
+        "FanOutProcessor"
+    }
+
+    fn supports_fan_out(&self) -> bool {
+        true
+    }
+
+    async fn process(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<ProcessorOutput> {
+        let start = std::time::Instant::now();
+        let config = Self::parse_config(input)?;
+        let children = self.resolve(&config)?;
+
+        let mut merged = ProcessorOutput::default();
+        let mut child_metadata = Vec::with_capacity(children.len());
+        let mut failures = Vec::new();
+        for (child, processor) in children {
+            if ctx.cancellation_token.is_cancelled() {
+                return Err(crate::Error::PipelineError(
+                    "Fan-out job cancelled".to_string(),
+                ));
+            }
+
+            match processor.process(&child_input(input, child), ctx).await {
+                Ok(output) => {
+                    let msg = format!("Fan-out processor {} succeeded", child.processor);
+                    info!("{}", msg);
+                    merged.logs.push(create_log_entry(LogLevel::Info, msg));
+                    let metadata = output
+                        .metadata
+                        .as_deref()
+                        .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok());
+                    child_metadata.push(serde_json::json!({
+                        "processor": child.processor,
+                        "status": "succeeded",
+                        "metadata": metadata,
+                    }));
+                    merge_output(&mut merged, output);
+                    if config.merge_strategy == MergeStrategy::FirstSuccessful {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    let msg = format!("Fan-out processor {} failed: {}", child.processor, error);
+                    warn!("{}", msg);
+                    merged.logs.push(create_log_entry(LogLevel::Warn, msg));
+                    child_metadata.push(serde_json::json!({
+                        "processor": child.processor,
+                        "status": "failed",
+                        "error": error.to_string(),
+                    }));
+                    failures.push(format!("{}: {}", child.processor, error));
+                }
+            }
+        }
+
+        let succeeded = child_metadata.len() - failures.len();
+        if succeeded == 0 || (config.merge_strategy == MergeStrategy::All && !failures.is_empty()) {
+            return Err(crate::Error::PipelineError(format!(
+                "Fan-out processors failed: {}",
+                failures.join("; ")
+            )));
+        }
+        if config.merge_strategy == MergeStrategy::Concatenate {
+            for failure in &failures {
+                merged.failed_inputs.extend(
+                    input
+                        .inputs
+                        .iter()
+                        .map(|path| (path.clone(), failure.clone())),
+                );
+            }
+        }
+
+        merged.duration_secs = start.elapsed().as_secs_f64();
+        merged.metadata = Some(
+            serde_json::json!({
+                "merge_strategy": config.merge_strategy,
+                "children": child_metadata,
+            })
+            .to_string(),
+        );
+        Ok(merged)
+    }
+
+    async fn dry_run(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<DryRunReport> {
+        let config = match Self::parse_config(input) {
+            Ok(config) => config,
+            Err(error) => {
+                return Ok(DryRunReport {
+                    config_errors: vec![error.to_string()],
+                    ..Default::default()
+                });
+            }
+        };
+        let children = match self.resolve(&config) {
+            Ok(children) => children,
+            Err(error) => {
+                return Ok(DryRunReport {
+                    config_errors: vec![error.to_string()],
+                    ..Default::default()
+                });
+            }
+        };
+
+        let mut report = DryRunReport::default();
+        for (child, processor) in children {
+            let child_report = processor.dry_run(&child_input(input, child), ctx).await?;
+            extend_unique(
+                &mut report.estimated_output_paths,
+                child_report.estimated_output_paths,
+            );
+            report.estimated_output_size_bytes = match (
+                report.estimated_output_size_bytes,
+                child_report.estimated_output_size_bytes,
+            ) {
+                (Some(total), Some(size)) => Some(total.saturating_add(size)),
+                (total, size) => total.or(size),
+            };
+            report.config_warnings.extend(
+                child_report
+                    .config_warnings
+                    .into_iter()
+                    .map(|warning| format!("{}: {}", child.processor, warning)),
+            );
+            report.config_errors.extend(
+                child_report
+                    .config_errors
+                    .into_iter()
+                    .map(|error| format!("{}: {}", child.processor, error)),
+            );
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Child processor that records its calls and either succeeds with one
+    /// output or fails.
+    struct FakeProcessor {
+        job_type: &'static str,
+        fail: bool,
+        calls: AtomicUsize,
+        configs: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    impl FakeProcessor {
+        fn new(job_type: &'static str, fail: bool) -> Arc<Self> {
+            Arc::new(Self {
+                job_type,
+                fail,
+                calls: AtomicUsize::new(0),
+                configs: std::sync::Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Processor for FakeProcessor {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Io
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec![self.job_type]
+        }
+
+        fn name(&self) -> &'static str {
+            "FakeProcessor"
+        }
+
+        async fn process(
+            &self,
+            input: &ProcessorInput,
+            _ctx: &ProcessorContext,
+        ) -> Result<ProcessorOutput> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.configs
+                .lock()
+                .expect("configs lock")
+                .push(input.config.clone());
+            if self.fail {
+                return Err(crate::Error::PipelineError(format!(
+                    "{} broke",
+                    self.job_type
+                )));
+            }
+            Ok(ProcessorOutput {
+                outputs: vec![format!("/out/{}.bin", self.job_type)],
+                output_size_bytes: Some(10),
+                succeeded_inputs: input.inputs.clone(),
+                logs: vec![create_log_entry(LogLevel::Info, self.job_type)],
+                ..Default::default()
+            })
+        }
+    }
+
+    fn fan_out(children: &[Arc<FakeProcessor>]) -> FanOutProcessor {
+        FanOutProcessor::new(
+            children
+                .iter()
+                .map(|child| Arc::clone(child) as Arc<dyn Processor>)
+                .collect(),
+        )
+    }
+
+    fn fan_out_input(config: serde_json::Value) -> ProcessorInput {
+        ProcessorInput {
+            inputs: vec!["/rec/video.flv".to_string()],
+            config: Some(config.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fan_out_processor_type() {
+        let processor = FanOutProcessor::new(Vec::new());
+        assert_eq!(processor.processor_type(), ProcessorType::Io);
+        assert!(processor.can_process("fan_out"));
+        assert!(!processor.can_process("remux"));
+        assert_eq!(processor.name(), "FanOutProcessor");
+    }
+
+    #[test]
+    fn test_fan_out_config_parsing() {
+        let config: FanOutConfig = serde_json::from_str(
+            r#"{"processors":[{"processor":"remux","config":{"format":"mp4"}},{"processor":"rclone"}],"merge_strategy":"first_successful"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.processors.len(), 2);
+        assert_eq!(config.processors[1].config, None);
+        assert_eq!(config.merge_strategy, MergeStrategy::FirstSuccessful);
+
+        let config: FanOutConfig =
+            serde_json::from_str(r#"{"processors":[{"processor":"remux"}]}"#).unwrap();
+        assert_eq!(config.merge_strategy, MergeStrategy::Concatenate);
+    }
+
+    #[tokio::test]
+    async fn test_concatenate_merges_outputs_and_records_failures() {
+        let a = FakeProcessor::new("a", false);
+        let broken = FakeProcessor::new("broken", true);
+        let b = FakeProcessor::new("b", false);
+        let processor = fan_out(&[a.clone(), broken.clone(), b.clone()]);
+        let input = fan_out_input(serde_json::json!({
+            "processors": [
+                {"processor": "a", "config": {"level": 3}},
+                {"processor": "broken"},
+                {"processor": "b", "config": "raw"},
+            ],
+        }));
+
+        let output = processor
+            .process(&input, &ProcessorContext::noop("job"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs, vec!["/out/a.bin", "/out/b.bin"]);
+        assert_eq!(output.output_size_bytes, Some(20));
+        assert_eq!(output.succeeded_inputs, vec!["/rec/video.flv"]);
+        assert_eq!(output.failed_inputs.len(), 1);
+        assert_eq!(output.failed_inputs[0].0, "/rec/video.flv");
+        assert!(output.failed_inputs[0].1.contains("broken broke"));
+        assert!(output.logs.iter().any(|log| log.message == "a"));
+        assert!(output.logs.iter().any(|log| log.message == "b"));
+        assert_eq!(
+            a.configs.lock().unwrap().as_slice(),
+            [Some(r#"{"level":3}"#.to_string())]
+        );
+        assert_eq!(
+            b.configs.lock().unwrap().as_slice(),
+            [Some("raw".to_string())]
+        );
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(output.metadata.as_deref().unwrap()).unwrap();
+        assert_eq!(metadata["children"][1]["status"], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_concatenate_fails_when_every_child_fails() {
+        let broken = FakeProcessor::new("broken", true);
+        let processor = fan_out(&[broken]);
+        let input = fan_out_input(serde_json::json!({
+            "processors": [{"processor": "broken"}, {"processor": "broken"}],
+        }));
+
+        let result = processor
+            .process(&input, &ProcessorContext::noop("job"))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_first_successful_stops_after_first_success() {
+        let broken = FakeProcessor::new("broken", true);
+        let a = FakeProcessor::new("a", false);
+        let b = FakeProcessor::new("b", false);
+        let processor = fan_out(&[broken.clone(), a.clone(), b.clone()]);
+        let input = fan_out_input(serde_json::json!({
+            "processors": [{"processor": "broken"}, {"processor": "a"}, {"processor": "b"}],
+            "merge_strategy": "first_successful",
+        }));
+
+        let output = processor
+            .process(&input, &ProcessorContext::noop("job"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs, vec!["/out/a.bin"]);
+        assert!(output.failed_inputs.is_empty());
+        assert_eq!(broken.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(b.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_all_fails_when_any_child_fails() {
+        let a = FakeProcessor::new("a", false);
+        let broken = FakeProcessor::new("broken", true);
+        let processor = fan_out(&[a, broken]);
+        let input = fan_out_input(serde_json::json!({
+            "processors": [{"processor": "a"}, {"processor": "broken"}],
+            "merge_strategy": "all",
+        }));
+
+        let err = processor
+            .process(&input, &ProcessorContext::noop("job"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("broken broke"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_child_is_rejected_before_running() {
+        let a = FakeProcessor::new("a", false);
+        let processor = fan_out(std::slice::from_ref(&a));
+        let input = fan_out_input(serde_json::json!({
+            "processors": [{"processor": "a"}, {"processor": "missing"}],
+        }));
+
+        let err = processor
+            .process(&input, &ProcessorContext::noop("job"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Validation(_)));
+        assert_eq!(a.calls.load(Ordering::SeqCst), 0);
+
+        let report = processor
+            .dry_run(&input, &ProcessorContext::noop("job"))
+            .await
+            .unwrap();
+        assert!(report.config_errors[0].contains("missing"));
+    }
+}
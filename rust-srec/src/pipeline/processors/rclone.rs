@@ -1266,6 +1266,8 @@ mod tests {
             session_start: None,
             config: Some(r#"{"destination_root": "remote:/{streamer}/{title}/"}"#.to_string()),
             created_at: chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
 
         let config: RcloneConfig = serde_json::from_str(input.config.as_ref().unwrap()).unwrap();
@@ -1290,6 +1292,8 @@ mod tests {
             session_start: None,
             config: Some(r#"{"destination_root": "remote:/%Y/%m/%d/{streamer}/"}"#.to_string()),
             created_at: chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
 
         let config: RcloneConfig = serde_json::from_str(input.config.as_ref().unwrap()).unwrap();
@@ -1316,6 +1320,8 @@ mod tests {
                     .to_string(),
             ),
             created_at,
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
 
         let config: RcloneConfig = serde_json::from_str(input.config.as_ref().unwrap()).unwrap();
@@ -1341,6 +1347,8 @@ mod tests {
                     .to_string(),
             ),
             created_at,
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
 
         let config: RcloneConfig = serde_json::from_str(input.config.as_ref().unwrap()).unwrap();
@@ -1365,6 +1373,8 @@ mod tests {
             session_start: Some(session_start),
             config: None,
             created_at: first_created_at,
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
         let session_config: RcloneConfig = serde_json::from_str(
             r#"{"destination_root": "remote:/%Y/%m/%d/{streamer}/", "time_anchor": "session_start"}"#,
@@ -2,14 +2,26 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::{FutureExt, Stream, StreamExt, future, stream};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio_util::sync::CancellationToken;
 
 use crate::Result;
+use crate::pipeline::dedup::DedupStore;
+use crate::pipeline::job_dependencies::JobCompletionRegistry;
 use crate::pipeline::job_queue::JobLogEntry;
-use crate::pipeline::progress::ProgressReporter;
+use crate::pipeline::progress::{JobProgressSnapshot, ProgressReporter};
+
+/// Log entry a processor returns in [`ProcessorOutput::logs`].
+///
+/// Build with the level constructors and attach context with
+/// [`JobLogEntry::with_field`], e.g. `ProcessorLogEntry::warn(msg).with_field("input", path)`.
+pub type ProcessorLogEntry = JobLogEntry;
 
 /// Type of processor (determines which worker pool handles it).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -75,6 +87,10 @@ pub struct ProcessorInput {
     /// When the job was originally created.
     /// Used for time-based placeholder expansion to ensure consistency across retries.
     pub created_at: DateTime<Utc>,
+    /// Key identifying duplicate jobs; see [`ProcessorContext::dedup_store`].
+    pub dedup_key: Option<String>,
+    /// IDs of jobs that must complete successfully before this one starts.
+    pub depends_on: Vec<String>,
 }
 
 impl Default for ProcessorInput {
@@ -90,6 +106,8 @@ impl Default for ProcessorInput {
             platform: None,
             session_start: None,
             created_at: Utc::now(),
+            dedup_key: None,
+            depends_on: Vec::new(),
         }
     }
 }
@@ -113,6 +131,8 @@ impl ProcessorInput {
             platform: None,
             session_start: None,
             created_at: Utc::now(),
+            dedup_key: None,
+            depends_on: Vec::new(),
         }
     }
 
@@ -151,6 +171,39 @@ impl ProcessorInput {
         self.session_start = Some(session_start);
         self
     }
+
+    /// Set the dedup key.
+    pub fn with_dedup_key(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = Some(key.into());
+        self
+    }
+
+    /// Set the IDs of jobs this one depends on.
+    pub fn with_depends_on(mut self, job_ids: Vec<String>) -> Self {
+        self.depends_on = job_ids;
+        self
+    }
+}
+
+/// I/O scheduling priority requested for a job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoPriority {
+    #[default]
+    Normal,
+    Low,
+    Idle,
+}
+
+/// Resources a processor may use for a job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// Upper bound on the memory the job may use, in bytes.
+    pub max_memory_bytes: Option<u64>,
+    /// Upper bound on the worker threads the job may use.
+    pub max_cpu_threads: Option<u32>,
+    pub io_priority: IoPriority,
 }
 
 /// Processor context for emitting progress and other side-channel data.
@@ -160,6 +213,12 @@ pub struct ProcessorContext {
     pub progress: ProgressReporter,
     pub log_sink: JobLogSink,
     pub cancellation_token: CancellationToken,
+    /// Store consulted for [`ProcessorInput::dedup_key`] before the job runs.
+    pub dedup_store: Option<Arc<dyn DedupStore>>,
+    /// Terminal results of jobs, used to resolve [`ProcessorInput::depends_on`].
+    pub completion_registry: Option<JobCompletionRegistry>,
+    pub resource_limits: ResourceLimits,
+    dry_run: bool,
 }
 
 #[derive(Clone)]
@@ -186,14 +245,17 @@ impl JobLogSink {
 
 impl ProcessorContext {
     pub fn noop(job_id: impl Into<String>) -> Self {
-        let job_id = job_id.into();
         let (log_tx, _) = tokio::sync::mpsc::channel(100);
         let dropped = Arc::new(AtomicUsize::new(0));
         Self {
-            job_id: job_id.clone(),
-            progress: ProgressReporter::noop(job_id),
+            job_id: job_id.into(),
+            progress: ProgressReporter::noop(),
             log_sink: JobLogSink::new(log_tx, dropped),
             cancellation_token: CancellationToken::new(),
+            dedup_store: None,
+            completion_registry: None,
+            resource_limits: ResourceLimits::default(),
+            dry_run: false,
         }
     }
 
@@ -208,9 +270,50 @@ impl ProcessorContext {
             progress,
             log_sink,
             cancellation_token,
+            dedup_store: None,
+            completion_registry: None,
+            resource_limits: ResourceLimits::default(),
+            dry_run: false,
         }
     }
 
+    /// Create a no-op context with the given resource limits.
+    pub fn noop_with_limits(job_id: impl Into<String>, limits: ResourceLimits) -> Self {
+        Self::noop(job_id).with_resource_limits(limits)
+    }
+
+    /// Set the resource limits.
+    pub fn with_resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.resource_limits = limits;
+        self
+    }
+
+    /// Set the dedup store.
+    pub fn with_dedup_store(mut self, store: Arc<dyn DedupStore>) -> Self {
+        self.dedup_store = Some(store);
+        self
+    }
+
+    /// Set the job completion registry.
+    pub fn with_completion_registry(mut self, registry: JobCompletionRegistry) -> Self {
+        self.completion_registry = Some(registry);
+        self
+    }
+
+    /// Create a no-op context for validating a job of this context without side effects.
+    pub fn dry_run_context(&self) -> Self {
+        let mut ctx = Self::noop(self.job_id.clone());
+        ctx.cancellation_token = self.cancellation_token.child_token();
+        ctx.resource_limits = self.resource_limits;
+        ctx.dry_run = true;
+        ctx
+    }
+
+    /// Whether the processor must validate the job without side effects.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
     /// Emit a log entry.
     pub fn log(&self, entry: JobLogEntry) {
         self.log_sink.try_send(entry);
@@ -260,7 +363,83 @@ pub struct ProcessorOutput {
     /// Each tuple contains (input_path, reason).
     pub skipped_inputs: Vec<(String, String)>,
     /// Execution logs captured during processing.
-    pub logs: Vec<JobLogEntry>,
+    pub logs: Vec<ProcessorLogEntry>,
+}
+
+/// Result of validating a job with [`Processor::dry_run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunReport {
+    /// Paths the processor would write.
+    pub estimated_output_paths: Vec<String>,
+    /// Expected total output size, when the processor can tell in advance.
+    pub estimated_output_size_bytes: Option<u64>,
+    /// Problems that would not stop the job, e.g. ignored config options.
+    pub config_warnings: Vec<String>,
+    /// Problems that would make the job fail.
+    pub config_errors: Vec<String>,
+}
+
+impl DryRunReport {
+    /// Whether the job is expected to succeed.
+    pub fn is_valid(&self) -> bool {
+        self.config_errors.is_empty()
+    }
+}
+
+/// Event yielded by [`Processor::process_streaming`].
+#[derive(Debug, Clone)]
+pub enum StreamingOutputEvent {
+    /// An output file is complete and in its final location.
+    ItemProduced(String),
+    /// Progress of the running job.
+    ProgressUpdate(JobProgressSnapshot),
+    /// The job finished; always the last event of a successful stream.
+    Completed(ProcessorOutput),
+}
+
+/// Stream returned by [`Processor::process_streaming`].
+pub type ProcessorEventStream<'a> =
+    Pin<Box<dyn Stream<Item = Result<StreamingOutputEvent>> + Send + 'a>>;
+
+/// Run `process` and stream the events it sends while running, followed by
+/// [`StreamingOutputEvent::Completed`] with its output (or its error).
+pub fn streaming_output<'a, F>(
+    process: impl FnOnce(mpsc::UnboundedSender<StreamingOutputEvent>) -> F,
+) -> ProcessorEventStream<'a>
+where
+    F: Future<Output = Result<ProcessorOutput>> + Send + 'a,
+{
+    let (tx, rx) = mpsc::unbounded();
+    let result = Arc::new(parking_lot::Mutex::new(None));
+    let slot = Arc::clone(&result);
+    // Yields nothing; the sender is dropped with the future, which ends `rx`.
+    let run = stream::once(process(tx).map(move |output| *slot.lock() = Some(output)))
+        .filter_map(|()| future::ready(None));
+    let completed = stream::once(async move {
+        result
+            .lock()
+            .take()
+            .unwrap_or_else(|| {
+                Err(crate::Error::PipelineError(
+                    "processor stopped without output".to_string(),
+                ))
+            })
+            .map(StreamingOutputEvent::Completed)
+    });
+    Box::pin(stream::select(rx.map(Ok), run).chain(completed))
+}
+
+/// Static description of what a processor accepts, for UIs and pre-submit validation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProcessorCapabilities {
+    /// Maximum number of inputs per job (`None` = unlimited).
+    pub max_inputs: Option<usize>,
+    /// Input file extensions the processor handles (empty = any).
+    pub supported_input_extensions: Vec<&'static str>,
+    /// Extensions of the files the processor produces (empty = unspecified).
+    pub supported_output_extensions: Vec<&'static str>,
+    /// JSON Schema of the processor's config.
+    pub config_schema: Option<&'static str>,
 }
 
 /// Trait for pipeline processors.
@@ -290,6 +469,42 @@ pub trait Processor: Send + Sync {
         ctx: &ProcessorContext,
     ) -> Result<ProcessorOutput>;
 
+    /// Process the input, reporting outputs as soon as they are ready.
+    ///
+    /// The default implementation runs [`Processor::process`] and yields a single
+    /// [`StreamingOutputEvent::Completed`]. Progress is still reported through
+    /// [`ProcessorContext::progress`].
+    fn process_streaming<'a>(
+        &'a self,
+        input: &'a ProcessorInput,
+        ctx: &'a ProcessorContext,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamingOutputEvent>> + Send + 'a>> {
+        streaming_output(move |_| self.process(input, ctx))
+    }
+
+    /// Validate the input and config without side effects.
+    ///
+    /// The default implementation runs [`Processor::process`] on a no-op context whose
+    /// [`ProcessorContext::is_dry_run`] is set, and reports its error as a config error.
+    /// Processors that do not check the flag must override this method.
+    async fn dry_run(
+        &self,
+        input: &ProcessorInput,
+        ctx: &ProcessorContext,
+    ) -> Result<DryRunReport> {
+        match self.process(input, &ctx.dry_run_context()).await {
+            Ok(output) => Ok(DryRunReport {
+                estimated_output_paths: output.outputs,
+                estimated_output_size_bytes: output.output_size_bytes,
+                ..Default::default()
+            }),
+            Err(error) => Ok(DryRunReport {
+                config_errors: vec![error.to_string()],
+                ..Default::default()
+            }),
+        }
+    }
+
     /// Get the processor name.
     fn name(&self) -> &'static str;
 
@@ -310,6 +525,21 @@ pub trait Processor: Send + Sync {
     fn supports_fan_out(&self) -> bool {
         false
     }
+
+    /// Describe the inputs, outputs and config this processor accepts.
+    ///
+    /// The default implementation only derives `max_inputs` from
+    /// [`Processor::supports_batch_input`].
+    fn capabilities(&self) -> ProcessorCapabilities {
+        ProcessorCapabilities {
+            max_inputs: if self.supports_batch_input() {
+                None
+            } else {
+                Some(1)
+            },
+            ..Default::default()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -329,6 +559,8 @@ mod tests {
             platform: None,
             session_start: None,
             created_at: Utc::now(),
+            dedup_key: None,
+            depends_on: Vec::new(),
         };
 
         assert_eq!(input.inputs[0], "/input.flv");
@@ -377,4 +609,135 @@ mod tests {
         assert!(output.succeeded_inputs.is_empty());
         assert!(output.skipped_inputs.is_empty());
     }
+
+    struct DryRunProbe;
+
+    #[async_trait]
+    impl Processor for DryRunProbe {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Io
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec!["probe"]
+        }
+
+        async fn process(
+            &self,
+            input: &ProcessorInput,
+            ctx: &ProcessorContext,
+        ) -> Result<ProcessorOutput> {
+            if !ctx.is_dry_run() {
+                return Err(crate::Error::PipelineError("not a dry run".to_string()));
+            }
+            if input.inputs.is_empty() {
+                return Err(crate::Error::PipelineError("no inputs".to_string()));
+            }
+            Ok(ProcessorOutput {
+                outputs: input.outputs.clone(),
+                output_size_bytes: Some(42),
+                ..Default::default()
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "DryRunProbe"
+        }
+    }
+
+    #[test]
+    fn test_default_capabilities() {
+        let capabilities = DryRunProbe.capabilities();
+        assert_eq!(capabilities.max_inputs, Some(1));
+        assert!(capabilities.supported_input_extensions.is_empty());
+        assert!(capabilities.config_schema.is_none());
+    }
+
+    #[test]
+    fn test_dry_run_context() {
+        let ctx = ProcessorContext::noop("job-1");
+        assert!(!ctx.is_dry_run());
+
+        let dry = ctx.dry_run_context();
+        assert!(dry.is_dry_run());
+        assert_eq!(dry.job_id, "job-1");
+
+        ctx.cancellation_token.cancel();
+        assert!(dry.cancellation_token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_default_dry_run_reports_process_result() {
+        let ctx = ProcessorContext::noop("job-1");
+        let input = ProcessorInput {
+            inputs: vec!["/input.flv".to_string()],
+            outputs: vec!["/output.mp4".to_string()],
+            ..Default::default()
+        };
+
+        let report = DryRunProbe.dry_run(&input, &ctx).await.unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.estimated_output_paths, vec!["/output.mp4"]);
+        assert_eq!(report.estimated_output_size_bytes, Some(42));
+
+        let report = DryRunProbe
+            .dry_run(&ProcessorInput::default(), &ctx)
+            .await
+            .unwrap();
+        assert!(!report.is_valid());
+        assert!(report.config_errors[0].contains("no inputs"));
+    }
+
+    #[tokio::test]
+    async fn test_default_process_streaming_yields_completed_only() {
+        let ctx = ProcessorContext::noop("job-1").dry_run_context();
+        let input = ProcessorInput {
+            inputs: vec!["/input.flv".to_string()],
+            outputs: vec!["/output.mp4".to_string()],
+            ..Default::default()
+        };
+
+        let events: Vec<_> = DryRunProbe.process_streaming(&input, &ctx).collect().await;
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Ok(StreamingOutputEvent::Completed(output)) => {
+                assert_eq!(output.outputs, vec!["/output.mp4"]);
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+
+        let events: Vec<_> = DryRunProbe
+            .process_streaming(&ProcessorInput::default(), &ctx)
+            .collect()
+            .await;
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_output_yields_items_before_completed() {
+        let events: Vec<_> = streaming_output(|tx| async move {
+            tx.unbounded_send(StreamingOutputEvent::ItemProduced("/a".to_string()))
+                .unwrap();
+            tokio::task::yield_now().await;
+            tx.unbounded_send(StreamingOutputEvent::ItemProduced("/b".to_string()))
+                .unwrap();
+            Ok(ProcessorOutput {
+                outputs: vec!["/a".to_string(), "/b".to_string()],
+                ..Default::default()
+            })
+        })
+        .collect()
+        .await;
+
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|event| match event {
+                Ok(StreamingOutputEvent::ItemProduced(path)) => path.as_str(),
+                Ok(StreamingOutputEvent::Completed(_)) => "completed",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["/a", "/b", "completed"]);
+    }
 }
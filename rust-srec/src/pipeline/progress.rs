@@ -52,30 +52,81 @@ pub struct JobProgressUpdate {
     pub snapshot: JobProgressSnapshot,
 }
 
+/// Sends progress snapshots of a job to its subscribers.
+///
+/// Reporters made with [`ProgressReporter::noop`] discard every report without
+/// touching a channel, so hot loops can report unconditionally.
 #[derive(Clone)]
 pub struct ProgressReporter {
-    job_id: String,
-    tx: mpsc::Sender<JobProgressUpdate>,
+    inner: ProgressReporterInner,
+}
+
+#[derive(Clone)]
+enum ProgressReporterInner {
+    Noop,
+    Real {
+        job_id: String,
+        tx: mpsc::Sender<JobProgressUpdate>,
+    },
 }
 
 impl ProgressReporter {
     pub fn new(job_id: impl Into<String>, tx: mpsc::Sender<JobProgressUpdate>) -> Self {
         Self {
-            job_id: job_id.into(),
-            tx,
+            inner: ProgressReporterInner::Real {
+                job_id: job_id.into(),
+                tx,
+            },
         }
     }
 
-    pub fn noop(job_id: impl Into<String>) -> Self {
-        let (tx, _rx) = mpsc::channel::<JobProgressUpdate>(1);
-        Self::new(job_id, tx)
+    /// A reporter that discards all reports, for tests and benchmarks.
+    pub fn noop() -> Self {
+        Self {
+            inner: ProgressReporterInner::Noop,
+        }
+    }
+
+    /// Whether reports are discarded.
+    pub fn is_noop(&self) -> bool {
+        matches!(self.inner, ProgressReporterInner::Noop)
     }
 
     pub fn report(&self, mut snapshot: JobProgressSnapshot) {
+        let ProgressReporterInner::Real { job_id, tx } = &self.inner else {
+            return;
+        };
         snapshot.updated_at = Utc::now();
-        let _ = self.tx.try_send(JobProgressUpdate {
-            job_id: self.job_id.clone(),
+        let _ = tx.try_send(JobProgressUpdate {
+            job_id: job_id.clone(),
             snapshot,
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noop_discards_reports() {
+        let reporter = ProgressReporter::noop();
+
+        reporter.report(JobProgressSnapshot::new(ProgressKind::Compression));
+
+        assert!(reporter.is_noop());
+    }
+
+    #[test]
+    fn test_new_sends_reports() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let reporter = ProgressReporter::new("job-1", tx);
+
+        reporter.report(JobProgressSnapshot::new(ProgressKind::Compression));
+
+        assert!(!reporter.is_noop());
+        let update = rx.try_recv().unwrap();
+        assert_eq!(update.job_id, "job-1");
+        assert_eq!(update.snapshot.kind, ProgressKind::Compression);
+    }
+}
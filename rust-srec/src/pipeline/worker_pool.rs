@@ -12,8 +12,11 @@ use tracing::{debug, error, info, warn};
 use super::dag_scheduler::{
     DagCompletionInfo, DagJobCompletedUpdate, DagJobFailedUpdate, DagScheduler,
 };
+use super::dedup::{DedupStore, InMemoryDedupStore};
 use super::job_queue::{JobExecutionInfo, JobQueue, JobResult};
-use super::processors::{JobLogSink, Processor, ProcessorContext, ProcessorInput};
+use super::processors::{
+    JobLogSink, Processor, ProcessorContext, ProcessorInput, ProcessorOutput, ResourceLimits,
+};
 
 /// Type of worker.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -87,6 +90,9 @@ pub struct WorkerPoolConfig {
     /// Adaptive worker scaling configuration.
     #[serde(default)]
     pub adaptive: AdaptiveWorkerPoolConfig,
+    /// Resource limits passed to processors for every job of this pool.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
 }
 
 impl Default for WorkerPoolConfig {
@@ -96,6 +102,7 @@ impl Default for WorkerPoolConfig {
             job_timeout_secs: 3600, // 1 hour
             poll_interval_ms: 100,
             adaptive: AdaptiveWorkerPoolConfig::default(),
+            resource_limits: ResourceLimits::default(),
         }
     }
 }
@@ -120,6 +127,8 @@ pub struct WorkerPool {
     cancellation_token: CancellationToken,
     /// Task set for workers.
     tasks: parking_lot::Mutex<Option<JoinSet<()>>>,
+    /// Store of dedup keys already seen by this pool.
+    dedup_store: Arc<dyn DedupStore>,
 }
 
 fn set_desired_with_handles(
@@ -149,6 +158,53 @@ fn set_desired_with_handles(
     desired
 }
 
+/// Outcome of checking a job's dedup key before its processor runs.
+enum DedupCheck {
+    /// The job has no dedup key, or the context has no dedup store.
+    Unchecked,
+    /// The key was new and is now recorded for this run.
+    Claimed(String),
+    /// The key was already recorded; the job is skipped.
+    Duplicate(String),
+}
+
+/// Record the job's dedup key in the context's dedup store.
+async fn check_dedup_key(input: &ProcessorInput, ctx: &ProcessorContext) -> DedupCheck {
+    let (Some(key), Some(store)) = (input.dedup_key.as_deref(), &ctx.dedup_store) else {
+        return DedupCheck::Unchecked;
+    };
+    if store.check_and_insert(key).await {
+        DedupCheck::Claimed(key.to_owned())
+    } else {
+        DedupCheck::Duplicate(key.to_owned())
+    }
+}
+
+/// Run `processor`, or pass the inputs through untouched when the job's
+/// dedup key was already seen.
+async fn process_with_dedup(
+    processor: &dyn Processor,
+    input: &ProcessorInput,
+    ctx: &ProcessorContext,
+    dedup: &DedupCheck,
+) -> crate::Result<ProcessorOutput> {
+    if let DedupCheck::Duplicate(key) = dedup {
+        let reason = format!("duplicate dedup key '{key}'");
+        ctx.info(format!("Skipping job: {reason}"));
+        return Ok(ProcessorOutput {
+            outputs: input.inputs.clone(),
+            skipped_inputs: input
+                .inputs
+                .iter()
+                .map(|path| (path.clone(), reason.clone()))
+                .collect(),
+            ..Default::default()
+        });
+    }
+
+    processor.process(input, ctx).await
+}
+
 fn update_avg_runtime_ms(avg_runtime_ms: &AtomicU64, sample_ms: u64) {
     // EWMA with alpha=0.2 in integer space: new = old + (sample-old)/5
     let sample_ms = sample_ms.max(1);
@@ -181,9 +237,16 @@ impl WorkerPool {
             avg_runtime_ms: Arc::new(AtomicU64::new(0)),
             cancellation_token: CancellationToken::new(),
             tasks: parking_lot::Mutex::new(Some(JoinSet::new())),
+            dedup_store: Arc::new(InMemoryDedupStore::new()),
         }
     }
 
+    /// Use `store` to detect duplicate jobs instead of a pool-local in-memory set.
+    pub fn with_dedup_store(mut self, store: Arc<dyn DedupStore>) -> Self {
+        self.dedup_store = store;
+        self
+    }
+
     /// Get the desired effective concurrency for this pool.
     pub fn desired_max_workers(&self) -> usize {
         self.desired_workers.load(Ordering::SeqCst)
@@ -235,6 +298,8 @@ impl WorkerPool {
         let job_timeout = std::time::Duration::from_secs(self.config.job_timeout_secs);
         let active_workers = self.active_workers.clone();
         let avg_runtime_ms = self.avg_runtime_ms.clone();
+        let dedup_store = self.dedup_store.clone();
+        let resource_limits = self.config.resource_limits;
 
         info!(
             "Starting {} worker pool with {} max workers",
@@ -278,6 +343,7 @@ impl WorkerPool {
                 let avg_runtime_ms = avg_runtime_ms.clone();
                 let dag_scheduler = dag_scheduler.clone();
                 let dag_notify_tx = dag_notify_tx.clone();
+                let dedup_store = dedup_store.clone();
 
                 join_set.spawn(async move {
                     debug!("{} worker {} started", worker_type, i);
@@ -475,6 +541,8 @@ impl WorkerPool {
                                 platform: job.platform.take(),
                                 session_start: job.session_start.take(),
                                 created_at: job.created_at,
+                                dedup_key: job.dedup_key.take(),
+                                depends_on: std::mem::take(&mut job.depends_on),
                             };
 
                             let (log_tx, mut log_rx) = tokio::sync::mpsc::channel(1024);
@@ -627,12 +695,21 @@ impl WorkerPool {
                                 job_queue.progress_reporter(&job_id),
                                 log_sink,
                                 job_cancellation_token.clone(),
-                            );
+                            )
+                            .with_dedup_store(dedup_store.clone())
+                            .with_completion_registry(job_queue.completion_registry())
+                            .with_resource_limits(resource_limits);
 
+                            let dedup = check_dedup_key(&input, &ctx).await;
                             let result = {
                                 let timed = tokio::time::timeout(
                                     job_timeout,
-                                    processor.process(&input, &ctx),
+                                    process_with_dedup(
+                                        processor.as_ref(),
+                                        &input,
+                                        &ctx,
+                                        &dedup,
+                                    ),
                                 );
                                 tokio::pin!(timed);
 
@@ -643,6 +720,15 @@ impl WorkerPool {
                                 }
                             };
 
+                            // Forget the dedup key of a run that did not complete, so
+                            // a retry or a later job with the same key still runs.
+                            if let DedupCheck::Claimed(key) = &dedup
+                                && (!matches!(result, Some(Ok(Ok(_))))
+                                    || job_cancellation_token.is_cancelled())
+                            {
+                                dedup_store.remove(key).await;
+                            }
+
                             // Drop ctx to close the log channel
                             drop(ctx);
 
@@ -1187,6 +1273,330 @@ mod tests {
         assert!(pool.is_running());
     }
 
+    struct CountingProcessor {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Processor for CountingProcessor {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Cpu
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec!["count"]
+        }
+
+        async fn process(
+            &self,
+            input: &ProcessorInput,
+            _ctx: &ProcessorContext,
+        ) -> crate::Result<ProcessorOutput> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(ProcessorOutput {
+                outputs: input.inputs.clone(),
+                ..Default::default()
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "count"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_dedup_key_skips_second_job() {
+        let job_queue = Arc::new(JobQueue::new());
+        let pool = WorkerPool::with_config(
+            WorkerType::Cpu,
+            WorkerPoolConfig {
+                max_workers: 1,
+                job_timeout_secs: 3600,
+                poll_interval_ms: 10,
+                adaptive: AdaptiveWorkerPoolConfig::default(),
+                ..Default::default()
+            },
+        );
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        pool.start(
+            job_queue.clone(),
+            vec![Arc::new(CountingProcessor { runs: runs.clone() })],
+        );
+
+        let mut job_ids = Vec::new();
+        for _ in 0..2 {
+            let job = Job::new(
+                "count",
+                vec!["/input".to_string()],
+                vec![],
+                "streamer-1",
+                "session-1",
+            )
+            .with_dedup_key("count:/input");
+            job_ids.push(job_queue.enqueue(job).await.unwrap());
+        }
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let mut completed = 0;
+                for job_id in &job_ids {
+                    if let Some(job) = job_queue.get_job(job_id).await.unwrap()
+                        && job.status == JobStatus::Completed
+                    {
+                        completed += 1;
+                    }
+                }
+                if completed == job_ids.len() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("both jobs should complete");
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        pool.stop().await;
+    }
+
+    struct FailOnceProcessor {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Processor for FailOnceProcessor {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Cpu
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec!["fail-once"]
+        }
+
+        async fn process(
+            &self,
+            input: &ProcessorInput,
+            _ctx: &ProcessorContext,
+        ) -> crate::Result<ProcessorOutput> {
+            if self.runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(crate::Error::PipelineError("first run fails".to_string()));
+            }
+            Ok(ProcessorOutput {
+                outputs: input.inputs.clone(),
+                ..Default::default()
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "fail-once"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_does_not_keep_its_dedup_key() {
+        let job_queue = Arc::new(JobQueue::new());
+        let pool = WorkerPool::with_config(
+            WorkerType::Cpu,
+            WorkerPoolConfig {
+                max_workers: 1,
+                job_timeout_secs: 3600,
+                poll_interval_ms: 10,
+                adaptive: AdaptiveWorkerPoolConfig::default(),
+                ..Default::default()
+            },
+        );
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        pool.start(
+            job_queue.clone(),
+            vec![Arc::new(FailOnceProcessor { runs: runs.clone() })],
+        );
+
+        for expected in [JobStatus::Failed, JobStatus::Completed] {
+            let job = Job::new(
+                "fail-once",
+                vec!["/input".to_string()],
+                vec![],
+                "streamer-1",
+                "session-1",
+            )
+            .with_dedup_key("fail-once:/input");
+            let job_id = job_queue.enqueue(job).await.unwrap();
+
+            tokio::time::timeout(Duration::from_secs(2), async {
+                loop {
+                    if let Some(job) = job_queue.get_job(&job_id).await.unwrap()
+                        && job.status == expected
+                    {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("job should reach its expected status");
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        pool.stop().await;
+    }
+
+    struct LimitsRecordingProcessor {
+        seen: Arc<parking_lot::Mutex<Option<ResourceLimits>>>,
+    }
+
+    #[async_trait]
+    impl Processor for LimitsRecordingProcessor {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Cpu
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec!["limits"]
+        }
+
+        async fn process(
+            &self,
+            _input: &ProcessorInput,
+            ctx: &ProcessorContext,
+        ) -> crate::Result<ProcessorOutput> {
+            *self.seen.lock() = Some(ctx.resource_limits);
+            Ok(ProcessorOutput::default())
+        }
+
+        fn name(&self) -> &'static str {
+            "limits"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processors_receive_pool_resource_limits() {
+        let limits = ResourceLimits {
+            max_memory_bytes: Some(64 * 1024 * 1024),
+            max_cpu_threads: Some(2),
+            ..Default::default()
+        };
+        let job_queue = Arc::new(JobQueue::new());
+        let pool = WorkerPool::with_config(
+            WorkerType::Cpu,
+            WorkerPoolConfig {
+                max_workers: 1,
+                poll_interval_ms: 10,
+                resource_limits: limits,
+                ..Default::default()
+            },
+        );
+        let seen = Arc::new(parking_lot::Mutex::new(None));
+
+        pool.start(
+            job_queue.clone(),
+            vec![Arc::new(LimitsRecordingProcessor { seen: seen.clone() })],
+        );
+        let job = Job::new(
+            "limits",
+            vec!["/input".to_string()],
+            vec![],
+            "streamer-1",
+            "session-1",
+        );
+        job_queue.enqueue(job).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while seen.lock().is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("job should run");
+
+        assert_eq!(*seen.lock(), Some(limits));
+
+        pool.stop().await;
+    }
+
+    struct OrderRecordingProcessor {
+        started: Arc<parking_lot::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Processor for OrderRecordingProcessor {
+        fn processor_type(&self) -> ProcessorType {
+            ProcessorType::Cpu
+        }
+
+        fn job_types(&self) -> Vec<&'static str> {
+            vec!["ordered"]
+        }
+
+        async fn process(
+            &self,
+            input: &ProcessorInput,
+            _ctx: &ProcessorContext,
+        ) -> crate::Result<ProcessorOutput> {
+            self.started.lock().push(input.inputs[0].clone());
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(ProcessorOutput::default())
+        }
+
+        fn name(&self) -> &'static str {
+            "ordered"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dependency_chain_runs_in_order() {
+        let job_queue = Arc::new(JobQueue::new());
+        let pool = WorkerPool::with_config(
+            WorkerType::Cpu,
+            WorkerPoolConfig {
+                max_workers: 3,
+                job_timeout_secs: 3600,
+                poll_interval_ms: 10,
+                adaptive: AdaptiveWorkerPoolConfig::default(),
+                ..Default::default()
+            },
+        );
+        let started = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let a = Job::new("ordered", vec!["a".to_string()], vec![], "s", "s");
+        let b = Job::new("ordered", vec!["b".to_string()], vec![], "s", "s")
+            .with_depends_on(vec![a.id.clone()]);
+        let c = Job::new("ordered", vec!["c".to_string()], vec![], "s", "s")
+            .with_depends_on(vec![b.id.clone()]);
+        let c_id = c.id.clone();
+        // Enqueue in reverse so the dependents are offered to workers first.
+        job_queue.enqueue(c).await.unwrap();
+        job_queue.enqueue(b).await.unwrap();
+        job_queue.enqueue(a).await.unwrap();
+
+        pool.start(
+            job_queue.clone(),
+            vec![Arc::new(OrderRecordingProcessor {
+                started: started.clone(),
+            })],
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(job) = job_queue.get_job(&c_id).await.unwrap()
+                    && job.status == JobStatus::Completed
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the chain should complete");
+
+        assert_eq!(*started.lock(), vec!["a", "b", "c"]);
+
+        pool.stop().await;
+    }
+
     #[tokio::test]
     async fn test_cancel_processing_job_releases_worker() {
         let job_queue = Arc::new(JobQueue::new());
@@ -1197,6 +1607,7 @@ mod tests {
                 job_timeout_secs: 3600,
                 poll_interval_ms: 10,
                 adaptive: AdaptiveWorkerPoolConfig::default(),
+                ..Default::default()
             },
         );
 
@@ -1257,6 +1668,7 @@ mod tests {
                 job_timeout_secs: 1,
                 poll_interval_ms: 10,
                 adaptive: AdaptiveWorkerPoolConfig::default(),
+                ..Default::default()
             },
         );
 
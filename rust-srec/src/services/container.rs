@@ -609,7 +609,13 @@ impl ServiceContainer {
             self.notification_service.stop().await;
 
             info!("Stopping danmu service...");
-            self.danmu_service.shutdown().await;
+            let danmu = self.danmu_service.shutdown().await;
+            info!(
+                stopped = danmu.stopped,
+                timed_out = danmu.timed_out,
+                failed = danmu.failed,
+                "Stopped danmu collections"
+            );
 
             info!("Stopping download manager...");
             let stopped_downloads = self.download_manager.stop_all().await;
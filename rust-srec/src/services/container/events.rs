@@ -661,9 +661,9 @@ impl DownloadEventProcessor {
                     let segment_id = segment_index.to_string();
 
                     // Start danmu segment
-                    // We change extension to .xml for danmu file
+                    // Danmu file sits next to the segment, with the danmu format's extension
                     let mut danmu_path = path.to_path_buf();
-                    danmu_path.set_extension("xml");
+                    danmu_path.set_extension(self.danmu_service.output_format().extension());
 
                     if let Err(e) = handle
                         .start_segment(&segment_id, danmu_path, started_at.to_owned())
@@ -829,10 +829,15 @@ impl DanmuEventHandler {
             DanmuEvent::CollectionStarted {
                 session_id,
                 streamer_id,
+                room_id,
+                room_id_source,
             } => {
                 info!(
-                    "Danmu collection started for session {} (streamer: {})",
-                    session_id, streamer_id
+                    "Danmu collection started for session {} (streamer: {}, room: {} from {})",
+                    session_id,
+                    streamer_id,
+                    room_id,
+                    room_id_source.as_str()
                 );
                 self.pipeline_manager
                     .handle_danmu_event(event.clone())
@@ -841,6 +846,7 @@ impl DanmuEventHandler {
             DanmuEvent::CollectionStopped {
                 session_id,
                 statistics,
+                ..
             } => {
                 info!(
                     "Danmu collection stopped for session {}: {} messages",
@@ -862,11 +868,47 @@ impl DanmuEventHandler {
                     session_id, segment_id, output_path, start_time
                 );
             }
-            DanmuEvent::SegmentCompleted {
+            DanmuEvent::SegmentEnded {
                 session_id,
                 segment_id,
+                parts,
                 output_path,
                 message_count,
+                bytes_written,
+                ..
+            } => {
+                debug!(
+                    "Danmu segment ended: session={}, segment={}, messages={}, bytes={}, parts={}, path={:?}",
+                    session_id, segment_id, message_count, bytes_written, parts, output_path
+                );
+            }
+            DanmuEvent::SegmentRotated {
+                session_id,
+                segment_id,
+                part,
+                path,
+            } => {
+                debug!(
+                    "Danmu segment rotated: session={}, segment={}, part={}, path={:?}",
+                    session_id, segment_id, part, path
+                );
+            }
+            DanmuEvent::SegmentRolledOver {
+                session_id,
+                old_segment_id,
+                new_segment_id,
+                path,
+            } => {
+                debug!(
+                    "Danmu segment rolled over: session={}, {} -> {}, path={:?}",
+                    session_id, old_segment_id, new_segment_id, path
+                );
+            }
+            DanmuEvent::SegmentCompleted {
+                session_id,
+                segment_id,
+                output_paths,
+                message_count,
                 ..
             } => {
                 info!(
@@ -878,16 +920,18 @@ impl DanmuEventHandler {
                     .remove(&(session_id.clone(), segment_id.clone()))
                     .is_some()
                 {
-                    match tokio::fs::remove_file(output_path).await {
-                        Ok(()) => {
-                            debug!("Deleted discarded danmu segment: {}", output_path.display())
+                    for output_path in output_paths {
+                        match tokio::fs::remove_file(output_path).await {
+                            Ok(()) => {
+                                debug!("Deleted discarded danmu segment: {}", output_path.display())
+                            }
+                            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                            Err(e) => warn!(
+                                "Failed to delete discarded danmu segment {}: {}",
+                                output_path.display(),
+                                e
+                            ),
                         }
-                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
-                        Err(e) => warn!(
-                            "Failed to delete discarded danmu segment {}: {}",
-                            output_path.display(),
-                            e
-                        ),
                     }
                     debug!(
                         "Skipping danmu segment {} for session {} (paired video discarded)",
@@ -924,6 +968,9 @@ impl DanmuEventHandler {
                         .await;
                 }
             }
+            DanmuEvent::Message { .. }
+            | DanmuEvent::StatisticsSnapshot { .. }
+            | DanmuEvent::LiveFeedSnapshot { .. } => {}
             DanmuEvent::Reconnecting {
                 session_id,
                 attempt,
@@ -933,12 +980,66 @@ impl DanmuEventHandler {
                     session_id, attempt
                 );
             }
+            DanmuEvent::Reconnected {
+                session_id,
+                attempts,
+            } => {
+                info!(
+                    "Danmu reconnected for session {} after {} attempts",
+                    session_id, attempts
+                );
+            }
             DanmuEvent::ReconnectFailed { session_id, error } => {
                 warn!(
                     "Danmu reconnect failed for session {}: {}",
                     session_id, error
                 );
             }
+            DanmuEvent::ConnectionStale {
+                session_id,
+                silent_secs,
+            } => {
+                warn!(
+                    "Danmu connection for session {} received nothing for {}s",
+                    session_id, silent_secs
+                );
+            }
+            DanmuEvent::CollectionPaused { session_id, .. } => {
+                debug!("Danmu collection paused for session {}", session_id);
+            }
+            DanmuEvent::CollectionResumed {
+                session_id,
+                paused_secs,
+                dropped_count,
+                ..
+            } => {
+                debug!(
+                    "Danmu collection resumed for session {} after {}s ({} messages dropped)",
+                    session_id, paused_secs, dropped_count
+                );
+            }
+            DanmuEvent::SpamDetected {
+                session_id,
+                user_id,
+                suppressed_count,
+            } => {
+                debug!(
+                    "Danmu spam suppressed for session {} (user={}): {} messages",
+                    session_id, user_id, suppressed_count
+                );
+            }
+            DanmuEvent::KeywordMatched {
+                session_id,
+                rule,
+                user_id,
+                content,
+                ..
+            } => {
+                info!(
+                    "Danmu keyword {:?} matched for session {} (user={}): {}",
+                    rule.pattern, session_id, user_id, content
+                );
+            }
             DanmuEvent::Error { session_id, error } => {
                 warn!("Danmu error for session {}: {}", session_id, error);
             }
@@ -46,3 +46,26 @@ pub fn ensure_dir_all_sync(path: &Path) -> Result<()> {
 pub fn ensure_dir_all_sync_with_op(op: &'static str, path: &Path) -> Result<()> {
     std::fs::create_dir_all(path).map_err(|e| io_error(op, path, e))
 }
+
+/// Move a file, copying it and removing the original when `to` is on another
+/// filesystem.
+///
+/// On failure the original is left in place and any partial copy is removed.
+pub async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    let Err(rename_err) = tokio::fs::rename(from, to).await else {
+        return Ok(());
+    };
+    if rename_err.kind() != std::io::ErrorKind::CrossesDevices {
+        return Err(io_error("rename", from, rename_err));
+    }
+
+    if let Err(e) = tokio::fs::copy(from, to).await {
+        let _ = tokio::fs::remove_file(to).await;
+        return Err(io_error("copy", from, e));
+    }
+    if let Err(e) = tokio::fs::remove_file(from).await {
+        let _ = tokio::fs::remove_file(to).await;
+        return Err(io_error("remove", from, e));
+    }
+    Ok(())
+}
@@ -136,7 +136,7 @@ async fn sqlite_claim_stress_no_double_claims_or_lost_transitions() {
         let claimed_ids = claimed_ids.clone();
         workers.spawn(async move {
             loop {
-                match repo.claim_next_pending_job(None).await.unwrap() {
+                match repo.claim_next_pending_job(None, &[]).await.unwrap() {
                     Some(claimed) => {
                         let inserted = claimed_ids.insert(claimed.id.clone());
                         assert!(inserted, "double-claimed job {}", claimed.id);